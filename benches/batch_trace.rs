@@ -0,0 +1,86 @@
+//! Cost of tracing a transaction with a wide call tree
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. A root contract makes 200
+//! value-carrying subcalls, each logging and returning a word, so the output holds 200
+//! frames, logs and transfers.
+
+use alloy::primitives::{address, hex, Address, TxKind, U256};
+use criterion::{criterion_group, criterion_main, Criterion};
+use revm::{
+    database::{CacheDB, EmptyDB},
+    state::{AccountInfo, Bytecode},
+    Context, MainBuilder, MainContext,
+};
+use revm_trace::{
+    traits::TransactionTrace, types::SimulationBatch, SimulationTx, TraceEvm, TxInspector,
+};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const FANOUT: Address = address!("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+const EMITTER: Address = address!("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+
+/// Number of subcalls made by FANOUT
+const SUBCALLS: usize = 200;
+
+/// Logs the word 0x2a and returns it
+const EMITTER_CODE: &str = "602a60005260206000a060206000f3";
+
+/// `CALL(GAS, EMITTER, 1, 0, 0, 0, 0)`
+const CALL_EMITTER: &str = "6000600060006000600173bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb5af150";
+
+fn fanout_evm() -> TraceEvm<CacheDB<CacheDB<EmptyDB>>, TxInspector> {
+    let mut backend = CacheDB::new(EmptyDB::default());
+    backend.insert_account_info(
+        SENDER,
+        AccountInfo {
+            balance: U256::from(10).pow(U256::from(18)),
+            ..Default::default()
+        },
+    );
+    for (address, code) in [
+        (FANOUT, format!("{}00", CALL_EMITTER.repeat(SUBCALLS))),
+        (EMITTER, EMITTER_CODE.to_string()),
+    ] {
+        let code = Bytecode::new_raw(hex::decode(code).unwrap().into());
+        backend.insert_account_info(
+            address,
+            AccountInfo {
+                code_hash: code.hash_slow(),
+                code: Some(code),
+                ..Default::default()
+            },
+        );
+    }
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(backend));
+    ctx.cfg.disable_base_fee = true;
+    TraceEvm::new(ctx.build_mainnet_with_inspector(TxInspector::new()))
+}
+
+fn batch() -> SimulationBatch {
+    SimulationBatch {
+        transactions: vec![SimulationTx {
+            caller: SENDER,
+            transact_to: TxKind::Call(FANOUT),
+            value: U256::from(SUBCALLS),
+            data: Default::default(),
+            trace_config: None,
+            return_abi: None,
+        }],
+        is_stateful: false,
+        ..Default::default()
+    }
+}
+
+fn bench_wide_call_tree(c: &mut Criterion) {
+    let mut evm = fanout_evm();
+    c.bench_function("trace_200_subcalls", |b| {
+        b.iter(|| {
+            let results = evm.trace_transactions(batch());
+            assert!(results[0].is_ok());
+            results
+        })
+    });
+}
+
+criterion_group!(benches, bench_wide_call_tree);
+criterion_main!(benches);
@@ -4,8 +4,9 @@
 //! a RESTful API for Ethereum transaction simulation and tracing.
 //!
 //! ## Features
-//! - Transaction simulation with gas estimation
-//! - Optional transaction tracing with call traces and asset transfers
+//! - Batch simulation with gas estimation, using the `serde-api` request/response schema
+//! - Configurable tracing (call traces, asset transfers) via `trace_level`
+//! - Field-precise validation errors (`transactions[0].value: invalid hex quantity`)
 //! - Support for both HTTP and WebSocket RPC endpoints
 //! - Proper error handling and JSON responses
 //! - Multi-threading support using `web::block` and `tokio::task::spawn_blocking`
@@ -19,40 +20,36 @@
 //! ```json
 //! {
 //!     "rpc_url": "https://eth.llamarpc.com",
-//!     "from": "0xC255fC198eEdAC7AF8aF0f6e0ca781794B094A61",
-//!     "to": "0xd878229c9c3575F224784DE610911B5607a3ad15",
-//!     "value": "120000000000000000",
-//!     "data": "0x",
-//!     "with_trace": true
+//!     "block": "latest",
+//!     "transactions": [{
+//!         "from": "0xC255fC198eEdAC7AF8aF0f6e0ca781794B094A61",
+//!         "to": "0xd878229c9c3575F224784DE610911B5607a3ad15",
+//!         "value": "120000000000000000",
+//!         "data": "0x"
+//!     }],
+//!     "stateful": false,
+//!     "trace_level": "Full"
 //! }
 //! ```
 //!
-//! #### Response Example:
+//! Amounts may be decimal strings, 0x-hex strings or JSON integers; `block` may be
+//! `"latest"`, a block number or a block hash.
+//!
+//! #### Response Example (abridged):
 //! ```json
 //! {
-//!     "success": true,
-//!     "gas_used": 21000,
-//!     "error": null,
-//!     "traces": {
-//!         "asset_transfers": 1,
-//!         "call_traces": {
-//!             "from": "0xc255fc198eedac7af8af0f6e0ca781794b094a61",
-//!             "to": "0xd878229c9c3575f224784de610911b5607a3ad15",
-//!             "value": "0x1aa535d3d0c0000",
-//!             "input": "0x",
-//!             "call_scheme": "Call",
-//!             "create_scheme": null,
-//!             "gas_used": "0x0",
-//!             "output": "0x",
-//!             "status": "Success",
-//!             "error_origin": false,
-//!             "subtraces": [],
-//!             "trace_address": []
-//!         }
-//!     }
+//!     "block": { "number": 22000000, "timestamp": 1741000000, "chain_id": 1, ... },
+//!     "results": [{
+//!         "summary": { "success": true, "gas_used": 21000, "gas_refunded": 0, ... },
+//!         "error": null,
+//!         "trace": { "asset_transfers": [...], "call_trace": {...}, ... }
+//!     }]
 //! }
 //! ```
 //!
+//! Invalid requests are rejected with `400 Bad Request` and a body like
+//! `{"error": "transactions[0].from: invalid address"}`.
+//!
 //! ### POST /simulate_web_block
 //! Simulate a transaction using `web::block` approach (recommended for actix-web).
 //!
@@ -61,67 +58,58 @@
 //!
 //! ## Usage
 //! ```bash
-//! cargo run --example actix_web_integration
+//! cargo run --example actix_web_integration --features serde-api
 //! ```
 //!
 //! Then test with curl:
 //! ```bash
 //! curl -X POST http://127.0.0.1:8080/simulate \
 //!   -H "Content-Type: application/json" \
-//!   -d '{"rpc_url":"https://eth.llamarpc.com","from":"0xC255fC198eEdAC7AF8aF0f6e0ca781794B094A61","to":"0xd878229c9c3575F224784DE610911B5607a3ad15","value":"120000000000000000","with_trace":true}'
+//!   -d '{"transactions":[{"from":"0xC255fC198eEdAC7AF8aF0f6e0ca781794B094A61","to":"0xd878229c9c3575F224784DE610911B5607a3ad15","value":"0x1aa535d3d0c0000"}],"trace_level":"Full"}'
 //! ```
 
 use actix_web::{middleware::Logger, web, App, HttpResponse, HttpServer, Result};
-use alloy::primitives::{Address, TxKind, U256};
 use revm_trace::{
+    api::types::{SimulationPlan, SimulationRequest, SimulationResponse},
+    report::SimulationReport,
     traits::TransactionTrace,
-    types::{SimulationBatch, SimulationTx},
-    TxInspector,
+    EvmBuilder, TxInspector,
 };
-use serde::{Deserialize, Serialize};
-use std::str::FromStr;
+use serde::Serialize;
 
-#[cfg(not(feature = "foundry-fork"))]
-use revm_trace::{create_evm, create_evm_with_tracer};
+/// RPC endpoint used when the request does not name one
+const DEFAULT_RPC_URL: &str = "https://eth.llamarpc.com";
 
-#[cfg(feature = "foundry-fork")]
-use revm_trace::{create_shared_evm, create_shared_evm_with_tracer};
+/// Error body returned for failed requests
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
 
-/// Request structure for transaction simulation
-#[derive(Deserialize)]
-struct SimulateRequest {
-    /// RPC endpoint URL (HTTP or WebSocket)
-    rpc_url: String,
-    /// Transaction sender address (hex string with or without 0x prefix)
-    from: String,
-    /// Transaction recipient address (hex string with or without 0x prefix)
-    to: String,
-    /// Transaction value in wei (optional, defaults to 0)
-    value: Option<String>,
-    /// Transaction input data (optional, hex string with or without 0x prefix)
-    data: Option<String>,
-    /// Whether to include transaction traces (optional, defaults to false)
-    with_trace: Option<bool>,
+/// Why a simulation request failed
+enum SimulateError {
+    /// The request is invalid (400)
+    BadRequest(String),
+    /// The simulation could not be run (500)
+    Internal(String),
 }
 
-/// Response structure for transaction simulation
-#[derive(Serialize)]
-struct SimulateResponse {
-    /// Whether the simulation was successful
-    success: bool,
-    /// Gas used by the transaction (if successful)
-    gas_used: Option<u64>,
-    /// Error message (if failed)
-    error: Option<String>,
-    /// Transaction traces (if with_trace=true and successful)
-    traces: Option<serde_json::Value>,
+impl SimulateError {
+    fn into_response(self) -> HttpResponse {
+        match self {
+            Self::BadRequest(error) => HttpResponse::BadRequest().json(ErrorResponse { error }),
+            Self::Internal(error) => {
+                HttpResponse::InternalServerError().json(ErrorResponse { error })
+            }
+        }
+    }
 }
 
-/// Simulate transaction using tokio::task::spawn_blocking approach
+/// Simulate transactions using tokio::task::spawn_blocking approach
 ///
 /// This approach creates a new tokio runtime inside a blocking task to handle
 /// the EVM operations that may block on network I/O.
-async fn simulate_transaction(req: web::Json<SimulateRequest>) -> Result<HttpResponse> {
+async fn simulate_transaction(req: web::Json<SimulationRequest>) -> Result<HttpResponse> {
     let request = req.into_inner();
 
     // Use tokio::task::spawn_blocking to handle potentially blocking operations
@@ -133,22 +121,18 @@ async fn simulate_transaction(req: web::Json<SimulateRequest>) -> Result<HttpRes
     })
     .await;
 
-    match result {
-        Ok(response) => Ok(HttpResponse::Ok().json(response)),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(SimulateResponse {
-            success: false,
-            gas_used: None,
-            error: Some(format!("Runtime error: {}", e)),
-            traces: None,
-        })),
-    }
+    Ok(match result {
+        Ok(Ok(response)) => HttpResponse::Ok().json(response),
+        Ok(Err(e)) => e.into_response(),
+        Err(e) => SimulateError::Internal(format!("Runtime error: {}", e)).into_response(),
+    })
 }
 
-/// Simulate transaction using web::block approach (recommended for actix-web)
+/// Simulate transactions using web::block approach (recommended for actix-web)
 ///
 /// This approach uses actix-web's built-in blocking thread pool, which is
 /// more efficient and integrates better with the actix-web ecosystem.
-async fn simulate_transaction_web_block(req: web::Json<SimulateRequest>) -> Result<HttpResponse> {
+async fn simulate_transaction_web_block(req: web::Json<SimulationRequest>) -> Result<HttpResponse> {
     let request = req.into_inner();
 
     // Use actix-web's web::block for better integration
@@ -159,178 +143,47 @@ async fn simulate_transaction_web_block(req: web::Json<SimulateRequest>) -> Resu
     })
     .await;
 
-    match result {
-        Ok(response) => Ok(HttpResponse::Ok().json(response)),
-        Err(e) => Ok(HttpResponse::InternalServerError().json(SimulateResponse {
-            success: false,
-            gas_used: None,
-            error: Some(format!("Web block error: {}", e)),
-            traces: None,
-        })),
-    }
+    Ok(match result {
+        Ok(Ok(response)) => HttpResponse::Ok().json(response),
+        Ok(Err(e)) => e.into_response(),
+        Err(e) => SimulateError::Internal(format!("Web block error: {}", e)).into_response(),
+    })
 }
 
 /// Internal function to handle transaction simulation logic
 ///
 /// This function contains the core simulation logic that is shared between
 /// different endpoint implementations.
-async fn simulate_tx_internal(request: SimulateRequest) -> SimulateResponse {
-    // Parse addresses
-    let from_addr = match Address::from_str(&request.from) {
-        Ok(addr) => addr,
-        Err(e) => {
-            return SimulateResponse {
-                success: false,
-                gas_used: None,
-                error: Some(format!("Invalid from address: {}", e)),
-                traces: None,
-            }
-        }
-    };
+async fn simulate_tx_internal(
+    request: SimulationRequest,
+) -> Result<SimulationResponse, SimulateError> {
+    // Validate the request; errors name the offending field
+    let plan =
+        SimulationPlan::try_from(request).map_err(|e| SimulateError::BadRequest(e.to_string()))?;
+    let rpc_url = plan.rpc_url.as_deref().unwrap_or(DEFAULT_RPC_URL);
 
-    let to_addr = match Address::from_str(&request.to) {
-        Ok(addr) => addr,
-        Err(e) => {
-            return SimulateResponse {
-                success: false,
-                gas_used: None,
-                error: Some(format!("Invalid to address: {}", e)),
-                traces: None,
-            }
-        }
-    };
-
-    // Parse transaction value
-    let value = if let Some(val_str) = request.value {
-        match U256::from_str(&val_str) {
-            Ok(val) => val,
-            Err(e) => {
-                return SimulateResponse {
-                    success: false,
-                    gas_used: None,
-                    error: Some(format!("Invalid value: {}", e)),
-                    traces: None,
-                }
-            }
-        }
-    } else {
-        U256::ZERO
-    };
-
-    // Parse transaction data
-    let data = if let Some(data_str) = request.data {
-        match hex::decode(data_str.strip_prefix("0x").unwrap_or(&data_str)) {
-            Ok(bytes) => bytes.into(),
-            Err(e) => {
-                return SimulateResponse {
-                    success: false,
-                    gas_used: None,
-                    error: Some(format!("Invalid data: {}", e)),
-                    traces: None,
-                }
-            }
-        }
-    } else {
-        vec![].into()
-    };
-
-    // Create transaction object
-    let tx = SimulationTx {
-        caller: from_addr,
-        transact_to: TxKind::Call(to_addr),
-        value,
-        data,
-    };
-
-    let batch = SimulationBatch {
-        transactions: vec![tx],
-        is_stateful: false,
-        overrides: None,
-    };
-
-    // Choose EVM mode based on tracing requirement
-    if request.with_trace.unwrap_or(false) {
-        // Use tracing mode for detailed call traces and asset transfers
-        #[cfg(not(feature = "foundry-fork"))]
-        let create_evm_result = create_evm_with_tracer(&request.rpc_url, TxInspector::new()).await;
-
-        #[cfg(feature = "foundry-fork")]
-        let create_evm_result =
-            create_shared_evm_with_tracer(&request.rpc_url, TxInspector::new()).await;
-
-        match create_evm_result {
-            Ok(mut evm) => {
-                let results = evm.trace_transactions(batch);
-                match results.into_iter().next() {
-                    Some(Ok((execution_result, _, trace_output))) => SimulateResponse {
-                        success: true,
-                        gas_used: Some(execution_result.gas_used()),
-                        error: None,
-                        traces: Some(serde_json::json!({
-                            "asset_transfers": trace_output.asset_transfers.len(),
-                            "call_traces": trace_output.call_trace,
-                        })),
-                    },
-                    Some(Err(e)) => SimulateResponse {
-                        success: false,
-                        gas_used: None,
-                        error: Some(e.to_string()),
-                        traces: None,
-                    },
-                    None => SimulateResponse {
-                        success: false,
-                        gas_used: None,
-                        error: Some("No results returned".to_string()),
-                        traces: None,
-                    },
-                }
-            }
-            Err(e) => SimulateResponse {
-                success: false,
-                gas_used: None,
-                error: Some(format!("Failed to create tracing EVM: {}", e)),
-                traces: None,
-            },
-        }
-    } else {
-        // Use standard mode for high performance (no tracing overhead)
-        #[cfg(not(feature = "foundry-fork"))]
-        let create_evm_result = create_evm(&request.rpc_url).await;
-        #[cfg(feature = "foundry-fork")]
-        let create_evm_result = create_shared_evm(&request.rpc_url).await;
-
-        match create_evm_result {
-            Ok(mut evm) => {
-                let results = evm.execute_batch(batch);
-                match results.into_iter().next() {
-                    Some(Ok(execution_result)) => SimulateResponse {
-                        success: true,
-                        gas_used: Some(execution_result.gas_used()),
-                        error: None,
-                        traces: None,
-                    },
-                    Some(Err(e)) => SimulateResponse {
-                        success: false,
-                        gas_used: None,
-                        error: Some(e.to_string()),
-                        traces: None,
-                    },
-                    None => SimulateResponse {
-                        success: false,
-                        gas_used: None,
-                        error: Some("No results returned".to_string()),
-                        traces: None,
-                    },
-                }
-            }
-            Err(e) => SimulateResponse {
-                success: false,
-                gas_used: None,
-                error: Some(format!("Failed to create EVM: {}", e)),
-                traces: None,
-            },
-        }
-    }
+    #[cfg(not(feature = "foundry-fork"))]
+    let builder = EvmBuilder::new_alloy(rpc_url);
+    #[cfg(feature = "foundry-fork")]
+    let builder = EvmBuilder::new_shared(rpc_url);
+
+    // `trace_level: "Off"` skips the inspector, so one EVM type serves every request
+    let mut evm = plan
+        .block
+        .apply(builder)
+        .with_tracer(TxInspector::new())
+        .build()
+        .await
+        .map_err(|e| SimulateError::Internal(format!("Failed to create EVM: {}", e)))?;
+
+    let transactions = plan.batch.transactions.clone();
+    let results = evm.trace_transactions(plan.batch);
+    let reports = transactions.into_iter().zip(results).map(|(tx, result)| {
+        result.map(|(execution_result, storage_diff, trace)| {
+            SimulationReport::new(tx, &execution_result, storage_diff, trace)
+        })
+    });
+    Ok(SimulationResponse::new(evm.block_context(), reports))
 }
 
 /// Health check endpoint
@@ -73,13 +73,15 @@ async fn main() -> Result<()> {
         transact_to: TxKind::Call(usdc),
         value: U256::ZERO,
         data: transfer_data.into(),
+        trace_config: None,
+        return_abi: None,
     };
 
     let result = &evm
         .trace_transactions(SimulationBatch {
             is_stateful: false,
             transactions: vec![tx],
-            overrides: None,
+            ..Default::default()
         })
         .into_iter()
         .map(|v| v.unwrap())
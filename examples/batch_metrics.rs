@@ -0,0 +1,100 @@
+//! Batch execution metrics
+//!
+//! This example demonstrates:
+//! - Enabling per-batch metrics on a TraceEvm
+//! - Splitting transaction time into upstream RPC wait and EVM/inspector work
+//! - Observing the effect of the CacheDB overlay on repeated work
+//! - Serializing metrics for shipping to a dashboard
+
+use alloy::{
+    primitives::{address, Address, TxKind, U256},
+    sol,
+    sol_types::SolCall,
+};
+use anyhow::Result;
+use colored::*;
+use revm_trace::{
+    create_evm_with_tracer, BatchMetrics, SimulationBatch, SimulationTx, TransactionTrace,
+    TxInspector,
+};
+
+sol! {
+    function balanceOf(address owner) public returns (uint256);
+}
+
+const ETH_RPC_URL: &str = "https://eth.llamarpc.com";
+const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+const HOLDERS: [Address; 3] = [
+    address!("28C6c06298d514Db089934071355E5743bf21d60"),
+    address!("F977814e90dA44bFA03b6295A0616a897441aceC"),
+    address!("47ac0Fb4F2D84898e4D9E7b4DaB3C24507a6D503"),
+];
+
+fn batch() -> SimulationBatch {
+    SimulationBatch {
+        transactions: HOLDERS
+            .iter()
+            .map(|owner| SimulationTx {
+                caller: address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045"),
+                transact_to: TxKind::Call(USDC),
+                value: U256::ZERO,
+                data: balanceOfCall { owner: *owner }.abi_encode().into(),
+                trace_config: None,
+                return_abi: None,
+            })
+            .collect(),
+        is_stateful: false,
+        ..Default::default()
+    }
+}
+
+fn print_metrics(label: &str, metrics: &BatchMetrics) {
+    println!("{}", label.yellow().bold());
+    println!("  wall time:      {:?}", metrics.total_wall_time);
+    for (index, tx) in metrics.per_tx.iter().enumerate() {
+        let fetch = tx.db_fetch_time.unwrap_or_default();
+        println!(
+            "  tx {index}: {:?} total, {:?} waiting on RPC, {:?} EVM + inspector, {} gas, ~{} trace bytes",
+            tx.execution_time,
+            fetch,
+            tx.execution_time.saturating_sub(fetch),
+            tx.gas_used,
+            tx.trace_size_bytes,
+        );
+    }
+    let overhead = metrics
+        .total_wall_time
+        .saturating_sub(metrics.total_execution_time());
+    println!("  batch setup:    {overhead:?}");
+    if let Some(db) = &metrics.db_stats {
+        println!(
+            "  cache:          {} hits, {} misses",
+            db.cache_hits, db.cache_misses
+        );
+        println!(
+            "  upstream:       {} requests ({} storage), {:?}",
+            db.upstream_calls, db.storage_calls, db.fetch_time
+        );
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+    evm.enable_metrics(true);
+
+    // First batch: cold cache, time is dominated by RPC round trips
+    evm.trace_transactions(batch());
+    let cold = evm.take_last_batch_metrics().expect("metrics enabled");
+    print_metrics("Cold batch", &cold);
+
+    // Second batch: the CacheDB is reset between batches, so upstream requests repeat.
+    // A large gap between execution time and RPC wait points at inspector overhead.
+    evm.trace_transactions(batch());
+    let warm = evm.take_last_batch_metrics().expect("metrics enabled");
+    print_metrics("Second batch", &warm);
+
+    println!("\n{}", "Serialized metrics".green().bold());
+    println!("{}", serde_json::to_string_pretty(&warm)?);
+    Ok(())
+}
@@ -122,12 +122,14 @@ async fn worker_thread(
         transact_to: TxKind::Call(to_addr),
         value: value_wei,
         data: vec![].into(),
+        trace_config: None,
+        return_abi: None,
     };
 
     let batch = SimulationBatch {
         transactions: vec![tx],
         is_stateful: false,
-        overrides: None,
+        ..Default::default()
     };
 
     // Execute transaction with tracing
@@ -35,12 +35,14 @@ async fn main() -> Result<()> {
         transact_to: TxKind::Call(bayc),
         value: U256::ZERO,
         data: data.into(),
+        trace_config: None,
+        return_abi: None,
     };
     let result = evm
         .trace_transactions(SimulationBatch {
             transactions: vec![tx],
             is_stateful: true,
-            overrides: None,
+            ..Default::default()
         })
         .into_iter()
         .map(|v| v.unwrap())
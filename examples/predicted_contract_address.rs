@@ -64,13 +64,15 @@ async fn main() -> Result<()> {
         transact_to: TxKind::Create,
         value: U256::ZERO,
         data: hex::decode(BYTECODE).unwrap().into(),
+        trace_config: None,
+        return_abi: None,
     };
 
     // Execute deployment
     let results = evm.execute_batch(SimulationBatch {
         is_stateful: false,
         transactions: vec![deploy_tx],
-        overrides: None,
+        ..Default::default()
     });
 
     // Check the result
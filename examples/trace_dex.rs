@@ -124,6 +124,8 @@ async fn main() -> Result<()> {
         transact_to: TxKind::Call(router),
         value: swap_amount,
         data: data.into(),
+        trace_config: None,
+        return_abi: None,
     };
 
     // Process transaction and get results
@@ -131,7 +133,7 @@ async fn main() -> Result<()> {
         .trace_transactions(SimulationBatch {
             transactions: vec![tx],
             is_stateful: true,
-            overrides: None,
+            ..Default::default()
         })
         .into_iter()
         .map(|v| v.unwrap())
@@ -61,6 +61,8 @@ async fn main() -> Result<()> {
         transact_to: TxKind::Call(to),
         value: amount,
         data: vec![].into(),
+        trace_config: None,
+        return_abi: None,
     };
 
     // Create transaction batch
@@ -68,7 +70,7 @@ async fn main() -> Result<()> {
     let txs = SimulationBatch {
         transactions: vec![tx.clone()],
         is_stateful: true,
-        overrides: None,
+        ..Default::default()
     };
 
     // Process transaction
@@ -94,6 +96,7 @@ async fn main() -> Result<()> {
             storages: Default::default(), // No storage overrides needed
             balances: vec![(safe, amount)].into_iter().collect(), // Set sender balance to 0
         }),
+        ..Default::default()
     };
     let result = evm.trace_transactions(txs);
     assert!(
@@ -0,0 +1,42 @@
+//! Post-simulation analysis over [`SimulationReport`](crate::report::SimulationReport)s
+//!
+//! Analyses are pure functions over collected reports; they never execute
+//! transactions themselves unless stated otherwise.
+//!
+//! - **Risk assessment**: Wallet-draining heuristics for pending transactions
+//! - **Transfer consistency**: Event-implied vs actual balance changes (reads state)
+//! - **Trace diffs**: Structural comparison of two call trees
+//! - **Value flow**: Native value retained by each address, such as ETH stuck in routers
+//! - **Payers**: Addresses bearing the cost of a relayed or sponsored transaction
+//! - **Address activity**: Per-address rollup of calls, gas and token flows over a batch
+//! - **Gas profile**: Hot frames and storage slots of a transaction, with cold accesses
+//! - **Preconditions**: Token balances and approvals a transaction needs (executes it)
+
+pub mod activity;
+pub mod consistency;
+pub mod gas_profile;
+pub mod payer;
+pub mod preconditions;
+pub mod risk;
+pub mod trace_diff;
+pub mod value_flow;
+
+pub use activity::{address_activity, AddressActivity};
+pub use consistency::{verify_transfer_consistency, Inconsistency};
+pub use gas_profile::{
+    gas_profile, gas_profile_with_config, FrameGas, GasProfile, GasProfileConfig, SlotHeat,
+    WarmColdSummary,
+};
+pub use payer::{
+    payer_analysis, payer_analysis_with_config, EffectivePayer, PayerAnalysis, PayerConfig,
+    PayerRule,
+};
+pub use preconditions::{preconditions, ApprovalRequirement, Preconditions, TokenRequirement};
+pub use risk::{
+    risk_assessment, risk_assessment_with_config, FindingRef, RiskConfig, RiskFinding, RiskKind,
+    Severity,
+};
+pub use trace_diff::{
+    diff_trace_outputs, diff_traces, FrameChange, FrameDiff, TraceDiff, MAX_DIFF_FRAMES,
+};
+pub use value_flow::{stuck_funds, value_flow, ValueHolding};
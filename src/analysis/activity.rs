@@ -0,0 +1,246 @@
+//! Per-address activity across a batch
+//!
+//! [`address_activity`] rolls the reports of a batch up by address: how often each
+//! address was called and made calls, the gas its code burned, the tokens it sent and
+//! received and its net native value. Frames are attributed to the address whose
+//! storage they ran against, so the work of a DELEGATECALL shows up under the proxy
+//! rather than its implementation.
+
+use std::collections::HashMap;
+
+use super::{consistency::to_signed, value_flow::native_hops};
+use crate::{
+    report::SimulationReport,
+    types::{CallScheme, CallTrace, TokenType},
+};
+use alloy::primitives::{Address, I256, U256};
+use serde::Serialize;
+
+/// Activity of one address over a batch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AddressActivity {
+    /// Address whose storage the attributed frames ran against
+    pub address: Address,
+    /// Frames executed in the address's context, creations included
+    pub call_count: usize,
+    /// Frames started by code running in the address's context, plus the
+    /// transactions it sent
+    pub as_caller_count: usize,
+    /// Gas burned by the attributed frames themselves, excluding their subcalls
+    pub gas_consumed: u64,
+    /// Total amount received per token, ordered by token address
+    pub tokens_in: Vec<(Address, U256)>,
+    /// Total amount sent per token, ordered by token address
+    pub tokens_out: Vec<(Address, U256)>,
+    /// Native value received minus sent, counting frames whose effects persisted
+    pub native_net: I256,
+    /// Attributed frames that reverted or halted
+    pub failed_frames: usize,
+}
+
+/// Totals of one address while walking the batch
+#[derive(Default)]
+struct Totals {
+    call_count: usize,
+    as_caller_count: usize,
+    gas_consumed: u64,
+    tokens_in: HashMap<Address, U256>,
+    tokens_out: HashMap<Address, U256>,
+    native_net: I256,
+    failed_frames: usize,
+}
+
+/// Aggregates the activity of every address appearing in `reports`
+///
+/// Call trees and transfer lists are walked once, so the cost is linear in the
+/// number of frames and transfers. Token amounts come from the recorded ERC20,
+/// ERC721 and ERC1155 transfers; native value is taken from the call tree (see
+/// [`value_flow`](super::value_flow)). Reports without a call tree contribute their
+/// transfers only.
+///
+/// # Returns
+/// One entry per address, by descending gas consumed, ties ordered by address
+pub fn address_activity(reports: &[SimulationReport]) -> Vec<AddressActivity> {
+    let mut totals: HashMap<Address, Totals> = HashMap::new();
+
+    for report in reports {
+        let trace = &report.trace;
+        if let Some(root) = &trace.call_trace {
+            // Frames with the storage address of their caller
+            let mut stack: Vec<(&CallTrace, Address)> = vec![(root, report.tx.caller)];
+            while let Some((frame, caller)) = stack.pop() {
+                let context = match frame.call_scheme {
+                    Some(CallScheme::DelegateCall | CallScheme::CallCode) => caller,
+                    _ => frame.to,
+                };
+                let children_gas = frame.subtraces.iter().fold(U256::ZERO, |total, child| {
+                    total.saturating_add(child.gas_used)
+                });
+                let own_gas = frame.gas_used.saturating_sub(children_gas);
+
+                let entry = totals.entry(context).or_default();
+                entry.call_count += 1;
+                entry.gas_consumed = entry
+                    .gas_consumed
+                    .saturating_add(own_gas.saturating_to::<u64>());
+                if !frame.status.is_success() {
+                    entry.failed_frames += 1;
+                }
+                totals.entry(caller).or_default().as_caller_count += 1;
+
+                stack.extend(frame.subtraces.iter().map(|child| (child, context)));
+            }
+        }
+
+        for transfer in &trace.asset_transfers {
+            if transfer.token_type == TokenType::Native {
+                continue;
+            }
+            let sent = totals
+                .entry(transfer.from)
+                .or_default()
+                .tokens_out
+                .entry(transfer.token)
+                .or_default();
+            *sent = sent.saturating_add(transfer.value);
+            if let Some(to) = transfer.to {
+                let received = totals
+                    .entry(to)
+                    .or_default()
+                    .tokens_in
+                    .entry(transfer.token)
+                    .or_default();
+                *received = received.saturating_add(transfer.value);
+            }
+        }
+
+        for (from, to, value) in native_hops(report) {
+            let value = to_signed(value);
+            let sender = &mut totals.entry(from).or_default().native_net;
+            *sender = sender.saturating_sub(value);
+            let recipient = &mut totals.entry(to).or_default().native_net;
+            *recipient = recipient.saturating_add(value);
+        }
+    }
+
+    let sorted = |amounts: HashMap<Address, U256>| {
+        let mut amounts: Vec<_> = amounts.into_iter().collect();
+        amounts.sort_unstable_by_key(|(token, _)| *token);
+        amounts
+    };
+    let mut activity: Vec<_> = totals
+        .into_iter()
+        .map(|(address, totals)| AddressActivity {
+            address,
+            call_count: totals.call_count,
+            as_caller_count: totals.as_caller_count,
+            gas_consumed: totals.gas_consumed,
+            tokens_in: sorted(totals.tokens_in),
+            tokens_out: sorted(totals.tokens_out),
+            native_net: totals.native_net,
+            failed_frames: totals.failed_frames,
+        })
+        .collect();
+    activity.sort_unstable_by(|a, b| {
+        b.gas_consumed
+            .cmp(&a.gas_consumed)
+            .then(a.address.cmp(&b.address))
+    });
+    activity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{CallStatus, TokenTransfer};
+    use alloy::primitives::address;
+
+    const SENDER: Address = address!("1000000000000000000000000000000000000001");
+    const PROXY: Address = address!("2000000000000000000000000000000000000002");
+    const IMPL: Address = address!("3000000000000000000000000000000000000003");
+    const TOKEN: Address = address!("4000000000000000000000000000000000000004");
+    const BOB: Address = address!("5000000000000000000000000000000000000005");
+
+    fn frame(from: Address, to: Address, scheme: CallScheme, gas_used: u64) -> CallTrace {
+        CallTrace {
+            from,
+            to,
+            call_scheme: Some(scheme),
+            gas_used: U256::from(gas_used),
+            ..Default::default()
+        }
+    }
+
+    /// SENDER pays PROXY 10 wei; PROXY forwards to IMPL, whose code moves 5 TOKEN to
+    /// BOB and makes a failing call to TOKEN
+    fn report() -> SimulationReport {
+        let mut token_call = frame(PROXY, TOKEN, CallScheme::Call, 300);
+        token_call.trace_address = vec![0, 0];
+        token_call.status = CallStatus::Revert {
+            reason: None,
+            raw: Default::default(),
+        };
+        let mut forward = frame(PROXY, IMPL, CallScheme::DelegateCall, 1_000);
+        forward.trace_address = vec![0];
+        forward.subtraces = vec![token_call];
+        let mut root = frame(SENDER, PROXY, CallScheme::Call, 1_500);
+        root.value = U256::from(10);
+        root.subtraces = vec![forward];
+
+        let mut report = SimulationReport::default();
+        report.tx.caller = SENDER;
+        report.trace.call_trace = Some(root);
+        report.trace.asset_transfers = vec![TokenTransfer {
+            token: TOKEN,
+            from: PROXY,
+            to: Some(BOB),
+            value: U256::from(5),
+            token_type: TokenType::ERC20,
+            id: None,
+            role: Default::default(),
+            transfer_index: 0,
+            failed: false,
+        }];
+        report
+    }
+
+    fn find(activity: &[AddressActivity], address: Address) -> &AddressActivity {
+        activity.iter().find(|a| a.address == address).unwrap()
+    }
+
+    #[test]
+    fn test_delegatecall_is_attributed_to_storage_address() {
+        let activity = address_activity(&[report(), report()]);
+        assert!(activity.iter().all(|a| a.address != IMPL));
+        assert_eq!(
+            activity.iter().map(|a| a.address).collect::<Vec<_>>(),
+            vec![PROXY, TOKEN, SENDER, BOB]
+        );
+
+        let proxy = find(&activity, PROXY);
+        assert_eq!(proxy.call_count, 4);
+        assert_eq!(proxy.as_caller_count, 4);
+        // 500 of the root and 700 of the forward, per report
+        assert_eq!(proxy.gas_consumed, 2_400);
+        assert_eq!(proxy.tokens_out, vec![(TOKEN, U256::from(10))]);
+        assert_eq!(proxy.native_net, I256::try_from(20i64).unwrap());
+        assert_eq!(proxy.failed_frames, 0);
+
+        let token = find(&activity, TOKEN);
+        assert_eq!((token.call_count, token.failed_frames), (2, 2));
+        assert_eq!(
+            find(&activity, BOB).tokens_in,
+            vec![(TOKEN, U256::from(10))]
+        );
+
+        let sender = find(&activity, SENDER);
+        assert_eq!((sender.call_count, sender.as_caller_count), (0, 2));
+        assert_eq!(sender.native_net, I256::try_from(-20i64).unwrap());
+    }
+
+    #[test]
+    fn test_empty_input() {
+        assert!(address_activity(&[]).is_empty());
+        assert!(address_activity(&[SimulationReport::default()]).is_empty());
+    }
+}
@@ -0,0 +1,189 @@
+//! Event-derived vs state-derived balance consistency
+//!
+//! Fee-on-transfer and rebasing tokens emit `Transfer` events that do not match the
+//! balances they actually move. Comparing the balance change implied by a transaction's
+//! transfers with the real change in `balanceOf` exposes such tokens.
+//!
+//! The check works per transaction: the report's `balances_before` provide the starting
+//! balances and the EVM's current state provides the final ones. In a stateful batch,
+//! verify each transaction's report right after it executes and before the next one
+//! runs, or only the last transaction of the batch.
+
+use std::collections::HashMap;
+
+use crate::{
+    evm::TraceEvm,
+    report::SimulationReport,
+    types::{TokenType, NATIVE_TOKEN_ADDRESS},
+    utils::{balance_utils::query_balance, erc20_utils::query_erc20_balance},
+};
+use alloy::primitives::{Address, I256, U256};
+use revm::database::Database;
+use serde::Serialize;
+
+/// Mismatch between the transfers recorded for a holder and its real balance change
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Inconsistency {
+    /// Token address (`NATIVE_TOKEN_ADDRESS` for the native token)
+    pub token: Address,
+    /// Balance holder
+    pub address: Address,
+    /// Balance change implied by the recorded transfers
+    pub event_implied_delta: I256,
+    /// Balance change observed in state
+    pub state_delta: I256,
+}
+
+/// Compares transfer-implied balance changes with actual state changes
+///
+/// Every (token, holder) pair appearing in the report's native and ERC20 transfers is
+/// checked. Pairs without a recorded pre-execution balance, and pairs whose current
+/// balance cannot be read, are skipped. Differences of a single unit are tolerated to
+/// absorb share-based rounding.
+///
+/// # Arguments
+/// - `evm`: EVM whose current state is the state right after the reported transaction
+/// - `report`: Report of the transaction, with `balances_before` attached
+///
+/// # Returns
+/// Inconsistencies in order of first appearance in the transfer list
+pub fn verify_transfer_consistency<DB, INSP>(
+    evm: &mut TraceEvm<DB, INSP>,
+    report: &SimulationReport,
+) -> Vec<Inconsistency>
+where
+    DB: Database,
+{
+    compare_deltas(report, |token, holder| {
+        if token == NATIVE_TOKEN_ADDRESS {
+            query_balance(evm, holder).ok()
+        } else {
+            query_erc20_balance(evm, token, holder).ok()
+        }
+    })
+}
+
+/// Compares implied deltas against balances returned by `balance_after`
+fn compare_deltas(
+    report: &SimulationReport,
+    mut balance_after: impl FnMut(Address, Address) -> Option<U256>,
+) -> Vec<Inconsistency> {
+    let mut order = Vec::new();
+    let mut implied: HashMap<(Address, Address), I256> = HashMap::new();
+    for transfer in &report.trace.asset_transfers {
+        if !matches!(transfer.token_type, TokenType::Native | TokenType::ERC20) {
+            continue;
+        }
+        let value = to_signed(transfer.value);
+        let Some(to) = transfer.to else {
+            continue;
+        };
+        for (holder, delta) in [(transfer.from, -value), (to, value)] {
+            // Mints and burns: the zero address has no meaningful balance
+            if holder == Address::ZERO {
+                continue;
+            }
+            let key = (transfer.token, holder);
+            if !implied.contains_key(&key) {
+                order.push(key);
+            }
+            let entry = implied.entry(key).or_default();
+            *entry = entry.saturating_add(delta);
+        }
+    }
+
+    let mut inconsistencies = Vec::new();
+    for (token, address) in order {
+        let Some(before) = report.balance_before(token, address) else {
+            continue;
+        };
+        let Some(after) = balance_after(token, address) else {
+            continue;
+        };
+        let event_implied_delta = implied[&(token, address)];
+        let state_delta = to_signed(after).saturating_sub(to_signed(before));
+        if event_implied_delta
+            .saturating_sub(state_delta)
+            .unsigned_abs()
+            > U256::from(1)
+        {
+            inconsistencies.push(Inconsistency {
+                token,
+                address,
+                event_implied_delta,
+                state_delta,
+            });
+        }
+    }
+    inconsistencies
+}
+
+/// Converts an amount to a signed value, saturating at `I256::MAX`
+pub(super) fn to_signed(value: U256) -> I256 {
+    I256::try_from(value).unwrap_or(I256::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        report::BalanceSnapshot,
+        types::{TokenTransfer, TransferRole},
+    };
+    use alloy::primitives::address;
+
+    const TOKEN: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+    const ALICE: Address = address!("1000000000000000000000000000000000000001");
+    const BOB: Address = address!("2000000000000000000000000000000000000002");
+
+    fn report(value: u64) -> SimulationReport {
+        let mut report = SimulationReport::default();
+        report.trace.asset_transfers = vec![TokenTransfer {
+            token: TOKEN,
+            from: ALICE,
+            to: Some(BOB),
+            value: U256::from(value),
+            token_type: TokenType::ERC20,
+            id: None,
+            role: TransferRole::Normal,
+            transfer_index: 0,
+            failed: false,
+        }];
+        report.with_balances(vec![
+            BalanceSnapshot {
+                token: TOKEN,
+                holder: ALICE,
+                balance: U256::from(10_000),
+            },
+            BalanceSnapshot {
+                token: TOKEN,
+                holder: BOB,
+                balance: U256::ZERO,
+            },
+        ])
+    }
+
+    #[test]
+    fn test_fee_on_transfer_is_flagged() {
+        // 1% fee burned on transfer, not reflected in the event
+        let after = HashMap::from([(ALICE, U256::from(9_000)), (BOB, U256::from(990))]);
+        let found = compare_deltas(&report(1_000), |_, holder| after.get(&holder).copied());
+        assert_eq!(
+            found,
+            vec![Inconsistency {
+                token: TOKEN,
+                address: BOB,
+                event_implied_delta: I256::try_from(1_000i64).unwrap(),
+                state_delta: I256::try_from(990i64).unwrap(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_plain_transfer_and_missing_inputs() {
+        let after = HashMap::from([(ALICE, U256::from(9_000)), (BOB, U256::from(1_000))]);
+        assert!(compare_deltas(&report(1_000), |_, holder| after.get(&holder).copied()).is_empty());
+        // Unreadable balances are skipped rather than flagged
+        assert!(compare_deltas(&report(1_000), |_, _| None).is_empty());
+    }
+}
@@ -0,0 +1,310 @@
+//! Gas golf report of a single transaction
+//!
+//! [`gas_profile`] ranks the frames of a call tree by the gas their own code burned
+//! and the storage slots by how often they were accessed, and breaks storage accesses
+//! down into cold and warm ones (EIP-2929). Cold accesses are the first touch of a
+//! slot in the transaction, as classified by the inspector; slots touched in a
+//! reverted frame stay warm, and slots warmed by an access list are still reported
+//! cold on their first access.
+
+use std::collections::HashMap;
+
+use crate::{inspectors::tx_inspector::TxTraceOutput, types::CallTrace};
+use alloy::primitives::{Address, FixedBytes, U256};
+use serde::Serialize;
+
+/// Default number of frames kept by [`gas_profile`]
+pub const DEFAULT_MAX_FRAMES: usize = 10;
+
+/// Default number of storage slots kept by [`gas_profile`]
+pub const DEFAULT_MAX_SLOTS: usize = 10;
+
+/// How many entries of each ranking to keep
+#[derive(Debug, Clone)]
+pub struct GasProfileConfig {
+    /// Frames kept, starting with the most expensive
+    pub max_frames: usize,
+    /// Slots kept, starting with the most accessed
+    pub max_slots: usize,
+}
+
+impl Default for GasProfileConfig {
+    fn default() -> Self {
+        Self {
+            max_frames: DEFAULT_MAX_FRAMES,
+            max_slots: DEFAULT_MAX_SLOTS,
+        }
+    }
+}
+
+/// Gas burned by one frame of the call tree
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FrameGas {
+    /// Position of the frame in the call tree
+    pub trace_address: Vec<usize>,
+    /// Called or created address
+    pub to: Address,
+    /// Function selector of the call, `None` for creations and short calldata
+    pub selector: Option<FixedBytes<4>>,
+    /// Gas burned by the frame itself, excluding its subcalls
+    pub self_gas: u64,
+    /// Gas burned by the frame and its subcalls
+    pub total_gas: u64,
+}
+
+/// Accesses to one storage slot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SlotHeat {
+    /// Contract owning the slot
+    pub address: Address,
+    /// Slot index
+    pub slot: U256,
+    /// SLOADs of the slot
+    pub reads: usize,
+    /// SSTOREs of the slot
+    pub writes: usize,
+    /// Accesses charged as cold, at most one unless frames reverted
+    pub cold_accesses: usize,
+}
+
+impl SlotHeat {
+    /// Reads and writes combined
+    pub fn accesses(&self) -> usize {
+        self.reads + self.writes
+    }
+}
+
+/// Cold and warm storage accesses of the whole transaction
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct WarmColdSummary {
+    /// SLOADs of slots not accessed before
+    pub cold_reads: usize,
+    /// SLOADs of slots already accessed
+    pub warm_reads: usize,
+    /// SSTOREs to slots not accessed before
+    pub cold_writes: usize,
+    /// SSTOREs to slots already accessed
+    pub warm_writes: usize,
+}
+
+/// Hot frames and storage slots of a transaction
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct GasProfile {
+    /// Frames by descending self gas, ties ordered by trace address
+    pub frames: Vec<FrameGas>,
+    /// Slots by descending access count, ties ordered by address and slot
+    pub slots: Vec<SlotHeat>,
+    /// Cold and warm accesses over all slots, including those cut from `slots`
+    pub warm_cold_summary: WarmColdSummary,
+}
+
+/// Builds the gas profile of `output`, keeping the default number of entries
+pub fn gas_profile(output: &TxTraceOutput) -> GasProfile {
+    gas_profile_with_config(output, &GasProfileConfig::default())
+}
+
+/// Builds the gas profile of `output`, keeping the number of entries in `config`
+///
+/// Frame gas and slot accesses come from the call tree, so the profile is empty
+/// below `TraceLevel::Full`.
+pub fn gas_profile_with_config(output: &TxTraceOutput, config: &GasProfileConfig) -> GasProfile {
+    let Some(root) = &output.call_trace else {
+        return GasProfile::default();
+    };
+
+    let mut profile = GasProfile::default();
+    let mut slots: HashMap<(Address, U256), SlotHeat> = HashMap::new();
+    for frame in root.frames() {
+        profile.frames.push(frame_gas(frame));
+
+        for access in &frame.slot_accesses {
+            let heat = slots
+                .entry((access.address, access.slot))
+                .or_insert_with(|| SlotHeat {
+                    address: access.address,
+                    slot: access.slot,
+                    reads: 0,
+                    writes: 0,
+                    cold_accesses: 0,
+                });
+            let summary = &mut profile.warm_cold_summary;
+            match (access.is_write, access.is_cold) {
+                (false, true) => summary.cold_reads += 1,
+                (false, false) => summary.warm_reads += 1,
+                (true, true) => summary.cold_writes += 1,
+                (true, false) => summary.warm_writes += 1,
+            }
+            if access.is_write {
+                heat.writes += 1;
+            } else {
+                heat.reads += 1;
+            }
+            if access.is_cold {
+                heat.cold_accesses += 1;
+            }
+        }
+    }
+
+    profile.frames.sort_unstable_by(|a, b| {
+        b.self_gas
+            .cmp(&a.self_gas)
+            .then_with(|| a.trace_address.cmp(&b.trace_address))
+    });
+    profile.frames.truncate(config.max_frames);
+
+    profile.slots = slots.into_values().collect();
+    profile.slots.sort_unstable_by(|a, b| {
+        b.accesses()
+            .cmp(&a.accesses())
+            .then(a.address.cmp(&b.address))
+            .then(a.slot.cmp(&b.slot))
+    });
+    profile.slots.truncate(config.max_slots);
+
+    profile
+}
+
+fn frame_gas(frame: &CallTrace) -> FrameGas {
+    let children_gas = frame.subtraces.iter().fold(U256::ZERO, |total, child| {
+        total.saturating_add(child.gas_used)
+    });
+    let selector = match frame.create_scheme {
+        Some(_) => None,
+        None => frame.input.get(..4).map(FixedBytes::from_slice),
+    };
+    FrameGas {
+        trace_address: frame.trace_address.clone(),
+        to: frame.to,
+        selector,
+        self_gas: frame
+            .gas_used
+            .saturating_sub(children_gas)
+            .saturating_to::<u64>(),
+        total_gas: frame.gas_used.saturating_to::<u64>(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SlotAccess;
+    use alloy::primitives::{address, Bytes};
+
+    const ROUTER: Address = address!("1000000000000000000000000000000000000001");
+    const POOL: Address = address!("2000000000000000000000000000000000000002");
+    const TOKEN: Address = address!("3000000000000000000000000000000000000003");
+
+    fn frame(to: Address, trace_address: Vec<usize>, gas_used: u64) -> CallTrace {
+        CallTrace {
+            to,
+            input: Bytes::from(vec![trace_address.len() as u8; 4]),
+            gas_used: U256::from(gas_used),
+            trace_address,
+            ..Default::default()
+        }
+    }
+
+    fn access(address: Address, slot: u64, is_write: bool, is_cold: bool) -> SlotAccess {
+        SlotAccess {
+            address,
+            slot: U256::from(slot),
+            is_write,
+            is_cold,
+            ..Default::default()
+        }
+    }
+
+    /// ROUTER (10_000) calls POOL (7_000), which calls TOKEN (2_000), then calls
+    /// TOKEN (1_500) itself
+    fn output() -> TxTraceOutput {
+        let mut token = frame(TOKEN, vec![0, 0], 2_000);
+        token.slot_accesses = vec![access(TOKEN, 1, false, true), access(TOKEN, 1, true, false)];
+        let mut pool = frame(POOL, vec![0], 7_000);
+        pool.subtraces = vec![token];
+        pool.slot_accesses = vec![access(POOL, 0, false, true), access(POOL, 0, false, false)];
+        let mut second = frame(TOKEN, vec![1], 1_500);
+        second.slot_accesses = vec![access(TOKEN, 1, false, false)];
+        let mut root = frame(ROUTER, vec![], 10_000);
+        root.subtraces = vec![pool, second];
+
+        TxTraceOutput {
+            call_trace: Some(root),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_frames_ranked_by_self_gas() {
+        let profile = gas_profile(&output());
+        let ranked: Vec<_> = profile
+            .frames
+            .iter()
+            .map(|frame| (frame.trace_address.clone(), frame.self_gas, frame.total_gas))
+            .collect();
+        assert_eq!(
+            ranked,
+            vec![
+                (vec![0], 5_000, 7_000),
+                (vec![0, 0], 2_000, 2_000),
+                (vec![], 1_500, 10_000),
+                (vec![1], 1_500, 1_500),
+            ]
+        );
+        assert_eq!(profile.frames[0].selector, Some(FixedBytes([1; 4])));
+    }
+
+    #[test]
+    fn test_slots_ranked_by_accesses() {
+        let profile = gas_profile(&output());
+        assert_eq!(
+            profile.slots,
+            vec![
+                SlotHeat {
+                    address: TOKEN,
+                    slot: U256::from(1),
+                    reads: 2,
+                    writes: 1,
+                    cold_accesses: 1,
+                },
+                SlotHeat {
+                    address: POOL,
+                    slot: U256::ZERO,
+                    reads: 2,
+                    writes: 0,
+                    cold_accesses: 1,
+                },
+            ]
+        );
+        assert_eq!(
+            profile.warm_cold_summary,
+            WarmColdSummary {
+                cold_reads: 2,
+                warm_reads: 2,
+                cold_writes: 0,
+                warm_writes: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_truncation_keeps_summary() {
+        let config = GasProfileConfig {
+            max_frames: 1,
+            max_slots: 1,
+        };
+        let profile = gas_profile_with_config(&output(), &config);
+        assert_eq!(profile.frames.len(), 1);
+        assert_eq!(profile.frames[0].to, POOL);
+        assert_eq!(profile.slots.len(), 1);
+        assert_eq!(profile.slots[0].address, TOKEN);
+        assert_eq!(profile.warm_cold_summary.warm_reads, 2);
+    }
+
+    #[test]
+    fn test_no_call_trace() {
+        assert_eq!(
+            gas_profile(&TxTraceOutput::default()),
+            GasProfile::default()
+        );
+    }
+}
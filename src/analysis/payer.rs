@@ -0,0 +1,383 @@
+//! Who effectively pays for a transaction
+//!
+//! With relayers, meta-transactions and ERC-4337 paymasters, the account paying for
+//! gas (the nominal payer, `tx.caller`) is often not the one bearing the cost.
+//! [`payer_analysis`] looks for the patterns through which the cost is passed on and
+//! reports every effective payer with the rules that identified it:
+//!
+//! - [`PayerRule::NativeReimbursement`]: native value sent to the nominal payer by an
+//!   address it sent nothing to, capped at the transaction fee when known
+//! - [`PayerRule::PaymasterDeposit`]: `UserOperationEvent`s of an EntryPoint, whose
+//!   `actualGasCost` is taken from the deposit of the paymaster, or of the account
+//!   itself without one
+//! - [`PayerRule::RelayedFeeToken`]: ERC20 tokens pulled into the nominal payer or a
+//!   configured fee collector from an address it sent nothing to, as relayers charge
+//!   their fee with `transferFrom`
+//!
+//! Requiring that the nominal payer sent nothing to the counterparty keeps swaps and
+//! other exchanges out, at the price of missing sponsors that are also paid by the
+//! relayer in the same transaction.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use super::value_flow::native_hops;
+use crate::{
+    report::SimulationReport,
+    types::{TokenType, TransferRole},
+};
+use alloy::{
+    primitives::{address, Address, U256},
+    sol,
+    sol_types::SolEvent,
+};
+use serde::{Deserialize, Serialize};
+
+sol! {
+    /// Emitted by the ERC-4337 EntryPoint (v0.6 and v0.7) for every executed UserOp
+    event UserOperationEvent(
+        bytes32 indexed userOpHash,
+        address indexed sender,
+        address indexed paymaster,
+        uint256 nonce,
+        bool success,
+        uint256 actualGasCost,
+        uint256 actualGasUsed
+    );
+}
+
+/// ERC-4337 EntryPoint v0.6
+pub const ENTRY_POINT_V06: Address = address!("5FF137D4b0FDCD49DcA30c7CF57E578a026d2789");
+
+/// ERC-4337 EntryPoint v0.7
+pub const ENTRY_POINT_V07: Address = address!("0000000071727De22E5E9d8BAf0edAc6f37da032");
+
+/// Heuristic that identified an effective payer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum PayerRule {
+    /// Native value reimbursed to the nominal payer
+    NativeReimbursement,
+    /// Gas cost of a UserOp taken from an EntryPoint deposit
+    PaymasterDeposit,
+    /// Fee token pulled from the end user by a relayer
+    RelayedFeeToken,
+}
+
+/// Heuristics to run, and the addresses they rely on
+#[derive(Debug, Clone)]
+pub struct PayerConfig {
+    /// Detect native reimbursements of the nominal payer
+    pub native_reimbursement: bool,
+    /// Detect gas paid from EntryPoint deposits
+    pub paymaster_deposit: bool,
+    /// Detect fee tokens pulled by relayers
+    pub relayed_fee_token: bool,
+    /// EntryPoints whose `UserOperationEvent`s are trusted
+    pub entry_points: HashSet<Address>,
+    /// Addresses collecting relayer fees besides the nominal payer
+    pub fee_collectors: HashSet<Address>,
+}
+
+impl Default for PayerConfig {
+    fn default() -> Self {
+        Self {
+            native_reimbursement: true,
+            paymaster_deposit: true,
+            relayed_fee_token: true,
+            entry_points: HashSet::from([ENTRY_POINT_V06, ENTRY_POINT_V07]),
+            fee_collectors: HashSet::new(),
+        }
+    }
+}
+
+/// Address bearing (part of) the cost of a transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EffectivePayer {
+    /// Paying address
+    pub address: Address,
+    /// Native value paid
+    pub amount_native: U256,
+    /// Tokens paid, by token address
+    pub amount_tokens: Vec<(Address, U256)>,
+    /// Rules that identified the payer, in rule order
+    pub rules: Vec<PayerRule>,
+}
+
+/// Nominal and effective payers of a transaction
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayerAnalysis {
+    /// Account paying for gas, the transaction sender
+    pub nominal_payer: Address,
+    /// Addresses the cost was passed on to, by address
+    pub effective_payers: Vec<EffectivePayer>,
+}
+
+impl PayerAnalysis {
+    /// Whether someone other than the nominal payer bears part of the cost
+    pub fn is_sponsored(&self) -> bool {
+        !self.effective_payers.is_empty()
+    }
+}
+
+/// Runs all payer heuristics with the default configuration
+pub fn payer_analysis(report: &SimulationReport) -> PayerAnalysis {
+    payer_analysis_with_config(report, &PayerConfig::default())
+}
+
+/// Runs the payer heuristics enabled in `config`
+pub fn payer_analysis_with_config(
+    report: &SimulationReport,
+    config: &PayerConfig,
+) -> PayerAnalysis {
+    let nominal_payer = report
+        .executed_tx
+        .as_ref()
+        .map_or(report.tx.caller, |tx| tx.caller);
+    let hops = native_hops(report);
+    let tokens = report.trace.asset_transfers.iter().filter(|transfer| {
+        transfer.token_type == TokenType::ERC20 && transfer.role == TransferRole::Normal
+    });
+
+    // Counterparties the nominal payer gave something to are trading, not sponsoring
+    let paid_by_payer: HashSet<Address> = hops
+        .iter()
+        .filter(|(from, ..)| *from == nominal_payer)
+        .map(|(_, to, _)| *to)
+        .chain(
+            tokens
+                .clone()
+                .filter(|transfer| transfer.from == nominal_payer)
+                .filter_map(|transfer| transfer.to),
+        )
+        .collect();
+    let sponsor = |from: Address| from != nominal_payer && !paid_by_payer.contains(&from);
+
+    let mut payers: BTreeMap<Address, Payment> = BTreeMap::new();
+    if config.native_reimbursement {
+        let mut reimbursed: BTreeMap<Address, U256> = BTreeMap::new();
+        for (from, to, value) in &hops {
+            if *to == nominal_payer && sponsor(*from) {
+                let total = reimbursed.entry(*from).or_default();
+                *total = total.saturating_add(*value);
+            }
+        }
+        // Reimbursements beyond the fee pay for something else
+        let gas_used = U256::from(report.summary.gas_used);
+        let mut fee = report
+            .executed_tx
+            .as_ref()
+            .filter(|tx| tx.gas_price > 0)
+            .map(|tx| U256::from(tx.gas_price).saturating_mul(gas_used));
+        for (from, value) in reimbursed {
+            let amount = match fee.as_mut() {
+                Some(fee) => {
+                    let amount = value.min(*fee);
+                    *fee -= amount;
+                    amount
+                }
+                None => value,
+            };
+            if !amount.is_zero() {
+                payers
+                    .entry(from)
+                    .or_default()
+                    .add_native(amount, PayerRule::NativeReimbursement);
+            }
+        }
+    }
+    if config.paymaster_deposit {
+        for log in &report.trace.logs {
+            if !config.entry_points.contains(&log.address)
+                || log.topics().first() != Some(&UserOperationEvent::SIGNATURE_HASH)
+            {
+                continue;
+            }
+            let Ok(event) = UserOperationEvent::decode_log_data(&log.data) else {
+                continue;
+            };
+            let payer = if event.paymaster.is_zero() {
+                event.sender
+            } else {
+                event.paymaster
+            };
+            if payer != nominal_payer && !event.actualGasCost.is_zero() {
+                payers
+                    .entry(payer)
+                    .or_default()
+                    .add_native(event.actualGasCost, PayerRule::PaymasterDeposit);
+            }
+        }
+    }
+    if config.relayed_fee_token {
+        for transfer in tokens {
+            let Some(to) = transfer.to else {
+                continue;
+            };
+            if (to == nominal_payer || config.fee_collectors.contains(&to))
+                && sponsor(transfer.from)
+                && !transfer.value.is_zero()
+            {
+                payers.entry(transfer.from).or_default().add_token(
+                    transfer.token,
+                    transfer.value,
+                    PayerRule::RelayedFeeToken,
+                );
+            }
+        }
+    }
+
+    PayerAnalysis {
+        nominal_payer,
+        effective_payers: payers
+            .into_iter()
+            .map(|(address, payment)| EffectivePayer {
+                address,
+                amount_native: payment.native,
+                amount_tokens: payment.tokens.into_iter().collect(),
+                rules: payment.rules.into_iter().collect(),
+            })
+            .collect(),
+    }
+}
+
+/// Amounts attributed to one effective payer
+#[derive(Default)]
+struct Payment {
+    native: U256,
+    tokens: BTreeMap<Address, U256>,
+    rules: BTreeSet<PayerRule>,
+}
+
+impl Payment {
+    fn add_native(&mut self, amount: U256, rule: PayerRule) {
+        self.native = self.native.saturating_add(amount);
+        self.rules.insert(rule);
+    }
+
+    fn add_token(&mut self, token: Address, amount: U256, rule: PayerRule) {
+        let total = self.tokens.entry(token).or_default();
+        *total = total.saturating_add(amount);
+        self.rules.insert(rule);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ExecutedTxEnv, TokenTransfer, NATIVE_TOKEN_ADDRESS};
+    use alloy::primitives::{Log, TxKind, B256};
+
+    const RELAYER: Address = address!("1000000000000000000000000000000000000001");
+    const SPONSOR: Address = address!("2000000000000000000000000000000000000002");
+    const POOL: Address = address!("3000000000000000000000000000000000000003");
+    const PAYMASTER: Address = address!("4000000000000000000000000000000000000004");
+    const ACCOUNT: Address = address!("5000000000000000000000000000000000000005");
+
+    fn native(from: Address, to: Address, value: u64) -> TokenTransfer {
+        TokenTransfer {
+            token: NATIVE_TOKEN_ADDRESS,
+            from,
+            to: Some(to),
+            value: U256::from(value),
+            token_type: TokenType::Native,
+            id: None,
+            role: TransferRole::Normal,
+            transfer_index: 0,
+            failed: false,
+        }
+    }
+
+    fn report(transfers: Vec<TokenTransfer>, gas_price: u128) -> SimulationReport {
+        let mut report = SimulationReport::default();
+        report.tx.caller = RELAYER;
+        report.summary.gas_used = 100;
+        report.trace.asset_transfers = transfers;
+        report.executed_tx = Some(ExecutedTxEnv {
+            caller: RELAYER,
+            kind: TxKind::Call(POOL),
+            value: U256::ZERO,
+            data_len: 0,
+            data_hash: B256::ZERO,
+            nonce: 0,
+            chain_id: Some(1),
+            gas_limit: 30_000_000,
+            tx_type: 0,
+            gas_price,
+            gas_priority_fee: None,
+            access_list: Default::default(),
+            block_base_fee: 0,
+        });
+        report
+    }
+
+    #[test]
+    fn test_reimbursement_is_capped_at_the_fee() {
+        // SPONSOR refunds 5000 for a fee of 100 * 10; POOL pays RELAYER for a trade
+        let report = report(
+            vec![
+                native(SPONSOR, RELAYER, 5_000),
+                native(RELAYER, POOL, 1),
+                native(POOL, RELAYER, 700),
+            ],
+            10,
+        );
+        let analysis = payer_analysis(&report);
+        assert_eq!(analysis.nominal_payer, RELAYER);
+        assert_eq!(
+            analysis.effective_payers,
+            vec![EffectivePayer {
+                address: SPONSOR,
+                amount_native: U256::from(1_000),
+                amount_tokens: vec![],
+                rules: vec![PayerRule::NativeReimbursement],
+            }]
+        );
+
+        let disabled = PayerConfig {
+            native_reimbursement: false,
+            ..Default::default()
+        };
+        assert!(!payer_analysis_with_config(&report, &disabled).is_sponsored());
+    }
+
+    #[test]
+    fn test_paymaster_and_account_deposits() {
+        let event = |paymaster: Address, cost: u64| {
+            let event = UserOperationEvent {
+                userOpHash: B256::ZERO,
+                sender: ACCOUNT,
+                paymaster,
+                nonce: U256::ZERO,
+                success: true,
+                actualGasCost: U256::from(cost),
+                actualGasUsed: U256::from(50_000),
+            };
+            Log {
+                address: ENTRY_POINT_V07,
+                data: event.encode_log_data(),
+            }
+        };
+        let mut report = report(vec![], 0);
+        report.trace.logs = vec![event(PAYMASTER, 300), event(Address::ZERO, 200)];
+        // Events from other contracts are ignored
+        let mut forged = event(SPONSOR, 1);
+        forged.address = POOL;
+        report.trace.logs.push(forged);
+
+        let payers: Vec<_> = payer_analysis(&report)
+            .effective_payers
+            .into_iter()
+            .map(|payer| (payer.address, payer.amount_native, payer.rules))
+            .collect();
+        assert_eq!(
+            payers,
+            vec![
+                (
+                    PAYMASTER,
+                    U256::from(300),
+                    vec![PayerRule::PaymasterDeposit]
+                ),
+                (ACCOUNT, U256::from(200), vec![PayerRule::PaymasterDeposit]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,388 @@
+//! Balances and approvals a transaction needs to succeed
+//!
+//! Intent solvers checking a candidate transaction want its minimal preconditions:
+//! how much of each token the sender must hold and which allowances must exist.
+//! [`preconditions`] derives them by simulation rather than by reading the contracts:
+//!
+//! 1. The transaction runs with a generous native balance for the sender.
+//! 2. Every ERC20 `transfer` or `transferFrom` pulling the sender's tokens, and every
+//!    token transfer out of the sender, names a token balance and, for
+//!    `transferFrom`, an allowance of the calling spender. They are overridden with
+//!    generous values (located like [`deal`](crate::utils::cheat_utils::deal) does)
+//!    and the transaction runs again, until no new ones show up.
+//! 3. Overrides whose slot the final run never read are dropped. The others are
+//!    reported with the amount the run consumed: the largest shortfall of the
+//!    sender's running balance over the recorded transfers, and the larger of the
+//!    allowance decrement and the `transferFrom` amounts.
+//!
+//! This is a heuristic. Contracts whose token movements depend nonlinearly on the
+//! available balance or allowance (sweeps of the whole balance, fee tiers, different
+//! routes for large amounts) consume amounts under generous state that need not be
+//! the minimum under real state. Gas fees are not included in `native_required`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    errors::{EvmError, RuntimeError},
+    evm::TraceEvm,
+    inspectors::tx_inspector::{TxInspector, TxTraceOutput},
+    types::{
+        AccountOverride, SimulationTx, StorageDiff, TokenType, TraceLevel, NATIVE_TOKEN_ADDRESS,
+    },
+    utils::cheat_utils::{find_balance_slot, find_view_slot},
+};
+use alloy::{
+    primitives::{Address, U256},
+    sol,
+    sol_types::SolCall,
+};
+use revm::{
+    context_interface::result::ExecutionResult,
+    database::{CacheDB, DatabaseRef},
+};
+use serde::{Deserialize, Serialize};
+
+sol! {
+    function transfer(address to, uint256 amount) external returns (bool);
+    function transferFrom(address from, address to, uint256 amount) external returns (bool);
+    function allowance(address owner, address spender) external view returns (uint256);
+}
+
+/// Balance and allowance written into overridden slots, large enough for any
+/// realistic amount while leaving room for additions
+const GENEROUS_AMOUNT: U256 = U256::from_limbs([0, 0, 1, 0]);
+
+/// Maximum number of simulations discovering new requirements
+const MAX_ROUNDS: usize = 8;
+
+/// Token balance a holder needs before the transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenRequirement {
+    /// ERC20 token address
+    pub token: Address,
+    /// Account holding the tokens
+    pub holder: Address,
+    /// Smallest balance the transaction consumed
+    pub min_balance: U256,
+}
+
+/// ERC20 allowance needed before the transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApprovalRequirement {
+    /// ERC20 token address
+    pub token: Address,
+    /// Account whose tokens are spent
+    pub owner: Address,
+    /// Account spending them
+    pub spender: Address,
+    /// Smallest allowance the transaction consumed
+    pub min_allowance: U256,
+}
+
+/// Preconditions of a transaction, see the module docs
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Preconditions {
+    /// Native balance the sender needs, gas fees excluded
+    pub native_required: U256,
+    /// Token balances needed, by token
+    pub token_requirements: Vec<TokenRequirement>,
+    /// Allowances needed, by token and spender
+    pub approvals_required: Vec<ApprovalRequirement>,
+    /// Whether the transaction succeeded with every requirement met
+    ///
+    /// When false, the requirements found so far are reported, but the transaction
+    /// fails for another reason or needs state this analysis cannot provide.
+    pub succeeded: bool,
+}
+
+/// Requirement whose storage slot was overridden
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Requirement {
+    Balance { token: Address },
+    Allowance { token: Address, spender: Address },
+}
+
+/// Derives the balances and approvals the sender of `tx` needs (see the module docs)
+///
+/// The sender is `tx.caller`; requirements of other accounts are assumed to be met
+/// by the current state. Every simulation runs in ephemeral state, so the EVM's state
+/// is left as it was.
+///
+/// # Returns
+/// - `Ok(Preconditions)`: Requirements in token order, and whether the transaction
+///   succeeded with them met
+/// - `Err(EvmError::Runtime(_))`: The transaction could not be executed at all, or a
+///   database or RPC failure
+///
+/// # Example
+/// ```no_run
+/// # async fn example(tx: revm_trace::SimulationTx) -> Result<(), Box<dyn std::error::Error>> {
+/// use revm_trace::{analysis::preconditions, create_evm_with_tracer, TxInspector};
+///
+/// let mut evm = create_evm_with_tracer("https://eth.llamarpc.com", TxInspector::new()).await?;
+/// let needed = preconditions(&mut evm, tx)?;
+/// for requirement in &needed.approvals_required {
+///     println!("approve {} on {}", requirement.spender, requirement.token);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn preconditions<DB>(
+    evm: &mut TraceEvm<CacheDB<DB>, TxInspector>,
+    tx: SimulationTx,
+) -> Result<Preconditions, EvmError>
+where
+    DB: DatabaseRef,
+{
+    let sender = tx.caller;
+    let tx = SimulationTx {
+        trace_config: Some(TraceLevel::Full),
+        ..tx
+    };
+    let mut overrides = vec![AccountOverride {
+        address: sender,
+        balance: Some(GENEROUS_AMOUNT),
+        ..Default::default()
+    }];
+    let mut slots: BTreeMap<Requirement, (Address, U256)> = BTreeMap::new();
+    let mut tried: BTreeSet<Requirement> = BTreeSet::new();
+
+    let mut run = simulate(evm, &tx, &overrides)?;
+    for _ in 1..MAX_ROUNDS {
+        let new: Vec<_> = candidates(&run.2, sender)
+            .into_iter()
+            .filter(|requirement| tried.insert(*requirement))
+            .collect();
+        let mut found = false;
+        for requirement in new {
+            let Some((address, slot)) = locate(evm, requirement, sender)? else {
+                continue;
+            };
+            overrides.push(AccountOverride {
+                address,
+                storage: vec![(slot, GENEROUS_AMOUNT)],
+                ..Default::default()
+            });
+            slots.insert(requirement, (address, slot));
+            found = true;
+        }
+        if !found {
+            break;
+        }
+        run = simulate(evm, &tx, &overrides)?;
+    }
+
+    let (result, diff, output) = run;
+    let mut preconditions = Preconditions {
+        native_required: shortfall(&output, NATIVE_TOKEN_ADDRESS, sender),
+        succeeded: result.is_success(),
+        ..Default::default()
+    };
+    for (requirement, (address, slot)) in slots {
+        let read = output
+            .accessed_state
+            .get(&address)
+            .is_some_and(|slots| slots.contains(&slot));
+        if !read {
+            continue;
+        }
+        match requirement {
+            Requirement::Balance { token } => {
+                let min_balance = shortfall(&output, token, sender);
+                if !min_balance.is_zero() {
+                    preconditions.token_requirements.push(TokenRequirement {
+                        token,
+                        holder: sender,
+                        min_balance,
+                    });
+                }
+            }
+            Requirement::Allowance { token, spender } => {
+                let min_allowance =
+                    pulled(&output, token, sender, spender).max(decrement(&diff, address, slot));
+                if !min_allowance.is_zero() {
+                    preconditions.approvals_required.push(ApprovalRequirement {
+                        token,
+                        owner: sender,
+                        spender,
+                        min_allowance,
+                    });
+                }
+            }
+        }
+    }
+    Ok(preconditions)
+}
+
+/// Runs `tx` under `overrides` without committing it
+fn simulate<DB>(
+    evm: &mut TraceEvm<CacheDB<DB>, TxInspector>,
+    tx: &SimulationTx,
+    overrides: &[AccountOverride],
+) -> Result<(ExecutionResult, StorageDiff, TxTraceOutput), EvmError>
+where
+    DB: DatabaseRef,
+{
+    let result = evm.with_ephemeral_state(overrides.to_vec(), |evm| {
+        evm.trace_internal(tx.clone(), false)
+    })?;
+    Ok(result?)
+}
+
+/// Requirements of `sender` named by the token calls and transfers of a run
+fn candidates(output: &TxTraceOutput, sender: Address) -> BTreeSet<Requirement> {
+    let mut candidates = BTreeSet::new();
+    if let Some(root) = &output.call_trace {
+        for frame in root.frames() {
+            if let Ok(call) = transferFromCall::abi_decode(&frame.input) {
+                if call.from == sender {
+                    candidates.insert(Requirement::Balance { token: frame.to });
+                    candidates.insert(Requirement::Allowance {
+                        token: frame.to,
+                        spender: frame.from,
+                    });
+                }
+            } else if frame.from == sender && transferCall::abi_decode(&frame.input).is_ok() {
+                candidates.insert(Requirement::Balance { token: frame.to });
+            }
+        }
+    }
+    for transfer in &output.asset_transfers {
+        if transfer.token_type == TokenType::ERC20 && transfer.from == sender {
+            candidates.insert(Requirement::Balance {
+                token: transfer.token,
+            });
+        }
+    }
+    candidates
+}
+
+/// Finds the storage slot controlling a requirement, `None` if there is none
+fn locate<DB>(
+    evm: &mut TraceEvm<CacheDB<DB>, TxInspector>,
+    requirement: Requirement,
+    sender: Address,
+) -> Result<Option<(Address, U256)>, EvmError>
+where
+    DB: DatabaseRef,
+{
+    let found = match requirement {
+        Requirement::Balance { token } => find_balance_slot(evm, token, sender).map(Some),
+        Requirement::Allowance { token, spender } => {
+            let data = allowanceCall {
+                owner: sender,
+                spender,
+            }
+            .abi_encode()
+            .into();
+            find_view_slot(evm, token, sender, data)
+        }
+    };
+    match found {
+        Err(EvmError::Runtime(RuntimeError::BalanceSlotNotFound(_) | RuntimeError::Revert(_))) => {
+            Ok(None)
+        }
+        found => found,
+    }
+}
+
+/// Largest amount `holder`'s balance of `token` fell below its starting value while
+/// the recorded transfers executed
+fn shortfall(output: &TxTraceOutput, token: Address, holder: Address) -> U256 {
+    let native = token == NATIVE_TOKEN_ADDRESS;
+    let mut balance = U256::ZERO;
+    let mut deficit = U256::ZERO;
+    for transfer in &output.asset_transfers {
+        let kind_matches = if native {
+            transfer.token_type == TokenType::Native
+        } else {
+            transfer.token_type == TokenType::ERC20
+        };
+        if transfer.failed || !kind_matches || transfer.token != token {
+            continue;
+        }
+        // `balance` holds what inflows left available; outflows beyond it must come
+        // from the starting balance
+        if transfer.to == Some(holder) {
+            balance = balance.saturating_add(transfer.value);
+        }
+        if transfer.from == holder {
+            if balance < transfer.value {
+                deficit += transfer.value - balance;
+                balance = U256::ZERO;
+            } else {
+                balance -= transfer.value;
+            }
+        }
+    }
+    deficit
+}
+
+/// Sum of the successful `transferFrom`s by `spender` of `owner`'s `token`
+fn pulled(output: &TxTraceOutput, token: Address, owner: Address, spender: Address) -> U256 {
+    let Some(root) = &output.call_trace else {
+        return U256::ZERO;
+    };
+    root.frames()
+        .filter(|frame| frame.to == token && frame.from == spender && frame.status.is_success())
+        .filter_map(|frame| transferFromCall::abi_decode(&frame.input).ok())
+        .filter(|call| call.from == owner)
+        .fold(U256::ZERO, |total, call| total.saturating_add(call.amount))
+}
+
+/// Amount the value of a storage slot decreased by
+fn decrement(diff: &StorageDiff, address: Address, slot: U256) -> U256 {
+    diff.get(&address)
+        .and_then(|accesses| accesses.iter().find(|access| access.slot == slot))
+        .map_or(U256::ZERO, |access| {
+            access.old_value.saturating_sub(access.new_value)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{TokenTransfer, TransferRole};
+    use alloy::primitives::address;
+
+    const HOLDER: Address = address!("1000000000000000000000000000000000000001");
+    const POOL: Address = address!("3000000000000000000000000000000000000003");
+    const TOKEN: Address = address!("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+
+    fn erc20(from: Address, to: Address, value: u64) -> TokenTransfer {
+        TokenTransfer {
+            token: TOKEN,
+            from,
+            to: Some(to),
+            value: U256::from(value),
+            token_type: TokenType::ERC20,
+            id: None,
+            role: TransferRole::Normal,
+            transfer_index: 0,
+            failed: false,
+        }
+    }
+
+    #[test]
+    fn test_shortfall_follows_transfer_order() {
+        let mut output = TxTraceOutput::default();
+        // Pays 100, receives 70, pays 50: 100 up front covers everything
+        output.asset_transfers = vec![
+            erc20(HOLDER, POOL, 100),
+            erc20(POOL, HOLDER, 70),
+            erc20(HOLDER, POOL, 50),
+        ];
+        assert_eq!(shortfall(&output, TOKEN, HOLDER), U256::from(100));
+
+        // Receiving first covers part of the payment
+        output.asset_transfers = vec![erc20(POOL, HOLDER, 30), erc20(HOLDER, POOL, 100)];
+        assert_eq!(shortfall(&output, TOKEN, HOLDER), U256::from(70));
+
+        // Failed transfers and other holders do not count
+        let mut failed = erc20(HOLDER, POOL, 500);
+        failed.failed = true;
+        output.asset_transfers = vec![failed, erc20(POOL, HOLDER, 10)];
+        assert_eq!(shortfall(&output, TOKEN, HOLDER), U256::ZERO);
+        assert_eq!(shortfall(&output, TOKEN, POOL), U256::from(10));
+    }
+}
@@ -0,0 +1,673 @@
+//! Wallet-draining risk heuristics
+//!
+//! Each detector inspects a simulated transaction from the point of view of one user
+//! and emits [`RiskFinding`]s. Detectors depending on optional report inputs (balance
+//! snapshots, account facts) degrade gracefully: missing inputs suppress or downgrade
+//! findings but never cause a panic.
+
+use std::collections::HashSet;
+
+use crate::{
+    report::SimulationReport,
+    types::{CallScheme, CallTrace, TokenTransfer, TokenType, TransferRole, NATIVE_TOKEN_ADDRESS},
+};
+use alloy::primitives::{b256, Address, Log, B256, U256};
+use serde::Serialize;
+
+/// `Approval(address,address,uint256)` event signature
+pub(crate) const APPROVAL_EVENT_SIGNATURE: B256 =
+    b256!("8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925");
+
+/// `ApprovalForAll(address,address,bool)` event signature
+pub(crate) const APPROVAL_FOR_ALL_EVENT_SIGNATURE: B256 =
+    b256!("17307eab39ab6107e8899845ad3d59bd9653f200f220920489ca2b5937696c31");
+
+/// Severity of a risk finding, ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum Severity {
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+/// Detector that produced a finding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum RiskKind {
+    /// A large share of the user's balance leaves the wallet
+    LargeOutflow,
+    /// An unlimited allowance or operator approval was granted
+    UnlimitedApproval,
+    /// An NFT owned by the user moves to an unknown, code-less address
+    NftToUnknownRecipient,
+    /// The contract the user called executed a DELEGATECALL
+    DelegateCallFromEntryContract,
+    /// Native tokens were sent to a contract created in the same transaction
+    ValueToFreshContract,
+    /// A frame read `tx.origin` and then moved or approved the user's tokens
+    TxOriginAuthorization,
+}
+
+/// Location in the trace a finding refers to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum FindingRef {
+    /// Call frame position in the call tree
+    TraceAddress(Vec<usize>),
+    /// Index into the transaction's log list
+    LogIndex(usize),
+}
+
+/// Single risk finding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RiskFinding {
+    /// Detector that produced the finding
+    pub kind: RiskKind,
+    /// Finding severity
+    pub severity: Severity,
+    /// Human-readable description
+    pub message: String,
+    /// Trace locations supporting the finding
+    pub refs: Vec<FindingRef>,
+}
+
+/// Tunable thresholds for the risk detectors
+#[derive(Debug, Clone)]
+pub struct RiskConfig {
+    /// Outflow share of the pre-execution balance (in percent) that triggers a finding
+    pub outflow_threshold_pct: u8,
+    /// Allowances at or above this value are treated as unlimited
+    pub unlimited_approval_threshold: U256,
+    /// Spenders and operators that may receive unlimited approvals without a finding
+    pub spender_whitelist: HashSet<Address>,
+}
+
+impl Default for RiskConfig {
+    fn default() -> Self {
+        Self {
+            outflow_threshold_pct: 50,
+            unlimited_approval_threshold: U256::MAX >> 1,
+            spender_whitelist: HashSet::new(),
+        }
+    }
+}
+
+/// Runs all risk detectors with the default configuration
+///
+/// # Arguments
+/// - `report`: Simulated transaction to analyze
+/// - `user`: Wallet whose assets are at risk (usually the transaction sender)
+///
+/// # Returns
+/// Findings sorted by descending severity
+pub fn risk_assessment(report: &SimulationReport, user: Address) -> Vec<RiskFinding> {
+    risk_assessment_with_config(report, user, &RiskConfig::default())
+}
+
+/// Runs all risk detectors with a custom configuration
+pub fn risk_assessment_with_config(
+    report: &SimulationReport,
+    user: Address,
+    config: &RiskConfig,
+) -> Vec<RiskFinding> {
+    let mut findings = Vec::new();
+    findings.extend(detect_large_outflows(report, user, config));
+    findings.extend(detect_unlimited_approvals(report, user, config));
+    findings.extend(detect_nft_to_unknown_recipient(report, user));
+    findings.extend(detect_entry_delegatecall(report, user));
+    findings.extend(detect_value_to_fresh_contract(report, user));
+    findings.extend(detect_tx_origin_authorization(report, user));
+    findings.sort_by(|a, b| b.severity.cmp(&a.severity));
+    findings
+}
+
+/// Flags tokens of which more than the configured share of the user's balance leaves
+///
+/// Requires a balance snapshot for the token; without one the token is skipped.
+pub fn detect_large_outflows(
+    report: &SimulationReport,
+    user: Address,
+    config: &RiskConfig,
+) -> Vec<RiskFinding> {
+    let mut outflows: Vec<(Address, U256, Vec<FindingRef>)> = Vec::new();
+    for (transfer, location) in located_transfers(report) {
+        if transfer.from != user
+            || transfer.to == Some(user)
+            || !matches!(transfer.token_type, TokenType::Native | TokenType::ERC20)
+        {
+            continue;
+        }
+        match outflows
+            .iter_mut()
+            .find(|(token, ..)| *token == transfer.token)
+        {
+            Some((_, total, refs)) => {
+                *total = total.saturating_add(transfer.value);
+                refs.push(location);
+            }
+            None => outflows.push((transfer.token, transfer.value, vec![location])),
+        }
+    }
+
+    outflows
+        .into_iter()
+        .filter_map(|(token, total, refs)| {
+            let balance = report.balance_before(token, user)?;
+            let threshold =
+                balance.saturating_mul(U256::from(config.outflow_threshold_pct)) / U256::from(100);
+            if balance.is_zero() || total <= threshold {
+                return None;
+            }
+            let severity = if total >= balance {
+                Severity::Critical
+            } else {
+                Severity::High
+            };
+            Some(RiskFinding {
+                kind: RiskKind::LargeOutflow,
+                severity,
+                message: format!(
+                    "{} of {} leaves {user} (balance before: {balance})",
+                    total,
+                    token_label(token)
+                ),
+                refs,
+            })
+        })
+        .collect()
+}
+
+/// Flags unlimited ERC20 allowances and operator approvals to non-whitelisted spenders
+pub fn detect_unlimited_approvals(
+    report: &SimulationReport,
+    user: Address,
+    config: &RiskConfig,
+) -> Vec<RiskFinding> {
+    let mut findings = Vec::new();
+    for (index, log) in report.trace.logs.iter().enumerate() {
+        let topics = log.topics();
+        if topics.len() != 3 || topic_address(&topics[1]) != user {
+            continue;
+        }
+        let spender = topic_address(&topics[2]);
+        if config.spender_whitelist.contains(&spender) {
+            continue;
+        }
+        let data = &log.data.data;
+        let description = if topics[0] == APPROVAL_EVENT_SIGNATURE && data.len() >= 32 {
+            let amount = U256::from_be_slice(&data[..32]);
+            if amount < config.unlimited_approval_threshold {
+                continue;
+            }
+            format!("unlimited allowance on token {}", log.address)
+        } else if topics[0] == APPROVAL_FOR_ALL_EVENT_SIGNATURE && data.len() >= 32 {
+            if U256::from_be_slice(&data[..32]).is_zero() {
+                continue;
+            }
+            format!("operator approval for all assets of {}", log.address)
+        } else {
+            continue;
+        };
+        findings.push(RiskFinding {
+            kind: RiskKind::UnlimitedApproval,
+            severity: Severity::High,
+            message: format!("{user} grants {spender} an {description}"),
+            refs: vec![FindingRef::LogIndex(index)],
+        });
+    }
+    findings
+}
+
+/// Flags NFTs leaving the user towards addresses with no code and no trace history
+///
+/// Account facts in the report confirm the recipient is a fresh EOA; without them the
+/// finding is reported with reduced severity.
+pub fn detect_nft_to_unknown_recipient(
+    report: &SimulationReport,
+    user: Address,
+) -> Vec<RiskFinding> {
+    let participants = trace_participants(report.trace.call_trace.as_ref());
+    let mut findings = Vec::new();
+    for (transfer, location) in located_transfers(report) {
+        if transfer.from != user
+            || !matches!(transfer.token_type, TokenType::ERC721 | TokenType::ERC1155)
+        {
+            continue;
+        }
+        let Some(recipient) = transfer.to else {
+            continue;
+        };
+        if recipient == user || participants.contains(&recipient) {
+            continue;
+        }
+        let severity = match report.accounts.get(&recipient) {
+            Some(facts) if facts.has_code => continue,
+            Some(facts) if facts.nonce == 0 => Severity::High,
+            Some(_) => Severity::Medium,
+            None => Severity::Low,
+        };
+        findings.push(RiskFinding {
+            kind: RiskKind::NftToUnknownRecipient,
+            severity,
+            message: format!(
+                "NFT {} #{} is sent to {recipient}, which never appears in the call trace",
+                transfer.token,
+                transfer.id.unwrap_or_default()
+            ),
+            refs: vec![location],
+        });
+    }
+    findings
+}
+
+/// Flags DELEGATECALLs executed in the context of the contract the user called
+///
+/// Plain proxy forwarding (delegating the unchanged calldata) is not reported.
+pub fn detect_entry_delegatecall(report: &SimulationReport, user: Address) -> Vec<RiskFinding> {
+    let Some(root) = report.trace.call_trace.as_ref() else {
+        return Vec::new();
+    };
+    if root.from != user || root.create_scheme.is_some() {
+        return Vec::new();
+    }
+
+    let mut findings = Vec::new();
+    visit_frames(root, &mut |frame| {
+        if frame.call_scheme == Some(CallScheme::DelegateCall)
+            && frame.from == root.to
+            && frame.input != root.input
+        {
+            findings.push(RiskFinding {
+                kind: RiskKind::DelegateCallFromEntryContract,
+                severity: Severity::Medium,
+                message: format!(
+                    "{} executes code of {} via DELEGATECALL with its own storage",
+                    root.to, frame.to
+                ),
+                refs: vec![FindingRef::TraceAddress(frame.trace_address.clone())],
+            });
+        }
+    });
+    findings
+}
+
+/// Flags native tokens sent to contracts created in the same transaction
+pub fn detect_value_to_fresh_contract(
+    report: &SimulationReport,
+    user: Address,
+) -> Vec<RiskFinding> {
+    let Some(root) = report.trace.call_trace.as_ref() else {
+        return Vec::new();
+    };
+    let mut created = HashSet::new();
+    visit_frames(root, &mut |frame| {
+        if frame.create_scheme.is_some() && frame.status.is_success() {
+            created.insert(frame.to);
+        }
+    });
+
+    let mut findings = Vec::new();
+    visit_frames(root, &mut |frame| {
+        if frame.value.is_zero()
+            || !created.contains(&frame.to)
+            || frame.call_scheme == Some(CallScheme::DelegateCall)
+        {
+            return;
+        }
+        let severity = if frame.from == user {
+            Severity::High
+        } else {
+            Severity::Medium
+        };
+        findings.push(RiskFinding {
+            kind: RiskKind::ValueToFreshContract,
+            severity,
+            message: format!(
+                "{} wei sent from {} to freshly created contract {}",
+                frame.value, frame.from, frame.to
+            ),
+            refs: vec![FindingRef::TraceAddress(frame.trace_address.clone())],
+        });
+    });
+    findings
+}
+
+/// Flags frames reading `tx.origin` whose calls move or approve tokens of the user
+///
+/// A contract authorizing by `tx.origin` treats every contract the user calls as the
+/// user, so a phishing contract can spend the user's assets through it. `user` is the
+/// transaction origin. Only full traces record ORIGIN reads.
+pub fn detect_tx_origin_authorization(
+    report: &SimulationReport,
+    user: Address,
+) -> Vec<RiskFinding> {
+    let Some(root) = report.trace.call_trace.as_ref() else {
+        return Vec::new();
+    };
+    let mut findings = Vec::new();
+    visit_frames(root, &mut |frame| {
+        if !frame.used_tx_origin {
+            return;
+        }
+        let affected: Vec<_> = frame
+            .frames()
+            .flat_map(|nested| nested.log_indices.iter().copied())
+            .filter(|index| {
+                report
+                    .trace
+                    .logs
+                    .get(*index)
+                    .is_some_and(|log| affects_owner(log, user))
+            })
+            .map(FindingRef::LogIndex)
+            .collect();
+        if affected.is_empty() {
+            return;
+        }
+        // Called directly by the user, the frame cannot be impersonating anyone
+        let severity = if frame.from == user {
+            Severity::Medium
+        } else {
+            Severity::High
+        };
+        let mut refs = vec![FindingRef::TraceAddress(frame.trace_address.clone())];
+        refs.extend(affected);
+        findings.push(RiskFinding {
+            kind: RiskKind::TxOriginAuthorization,
+            severity,
+            message: format!(
+                "{} authorizes by tx.origin, then moves or approves tokens of {user}",
+                frame.to
+            ),
+            refs,
+        });
+    });
+    findings
+}
+
+/// Whether `log` is a token transfer from `owner` or an approval granted by `owner`
+fn affects_owner(log: &Log, owner: Address) -> bool {
+    let topics = log.topics();
+    let approves = topics.len() >= 2
+        && (topics[0] == APPROVAL_EVENT_SIGNATURE || topics[0] == APPROVAL_FOR_ALL_EVENT_SIGNATURE)
+        && topic_address(&topics[1]) == owner;
+    approves
+        || (!topics.is_empty()
+            && TokenTransfer::get_token_transfers(log)
+                .iter()
+                .any(|transfer| transfer.from == owner))
+}
+
+/// Collects transfers together with the trace location that produced them
+///
+/// Token transfers reference their log, native transfers the call frame moving value.
+pub(crate) fn located_transfers(report: &SimulationReport) -> Vec<(TokenTransfer, FindingRef)> {
+    let mut transfers = Vec::new();
+    if let Some(root) = report.trace.call_trace.as_ref() {
+        visit_frames(root, &mut |frame| {
+            let moves_value = match frame.call_scheme {
+                Some(scheme) => matches!(scheme, CallScheme::Call | CallScheme::CallCode),
+                None => frame.create_scheme.is_some(),
+            };
+            if moves_value && !frame.value.is_zero() {
+                transfers.push((
+                    TokenTransfer {
+                        token: NATIVE_TOKEN_ADDRESS,
+                        from: frame.from,
+                        to: Some(frame.to),
+                        value: frame.value,
+                        token_type: TokenType::Native,
+                        id: None,
+                        role: TransferRole::Normal,
+                        transfer_index: 0,
+                        failed: false,
+                    },
+                    FindingRef::TraceAddress(frame.trace_address.clone()),
+                ));
+            }
+        });
+    }
+    for (index, log) in report.trace.logs.iter().enumerate() {
+        if log.topics().is_empty() {
+            continue;
+        }
+        for transfer in TokenTransfer::get_token_transfers(log) {
+            transfers.push((transfer, FindingRef::LogIndex(index)));
+        }
+    }
+    transfers
+}
+
+/// Returns every address appearing as caller or target in the call tree
+fn trace_participants(root: Option<&CallTrace>) -> HashSet<Address> {
+    let mut participants = HashSet::new();
+    if let Some(root) = root {
+        visit_frames(root, &mut |frame| {
+            participants.insert(frame.from);
+            participants.insert(frame.to);
+        });
+    }
+    participants
+}
+
+fn visit_frames<'a, F: FnMut(&'a CallTrace)>(root: &'a CallTrace, f: &mut F) {
+    root.frames().for_each(f);
+}
+
+pub(crate) fn topic_address(topic: &B256) -> Address {
+    Address::from_slice(&topic.as_slice()[12..])
+}
+
+fn token_label(token: Address) -> String {
+    if token == NATIVE_TOKEN_ADDRESS {
+        "native token".to_string()
+    } else {
+        format!("token {token}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::{AccountFacts, BalanceSnapshot};
+    use crate::types::{CreateScheme, ERC20_TRANSFER_EVENT_SIGNATURE};
+    use alloy::primitives::{address, Bytes, LogData};
+
+    const USER: Address = address!("1000000000000000000000000000000000000001");
+    const DAPP: Address = address!("2000000000000000000000000000000000000002");
+    const TOKEN: Address = address!("3000000000000000000000000000000000000003");
+    const ATTACKER: Address = address!("4000000000000000000000000000000000000004");
+
+    fn topic(address: Address) -> B256 {
+        address.into_word()
+    }
+
+    fn log(emitter: Address, topics: Vec<B256>, data: Vec<u8>) -> Log {
+        Log {
+            address: emitter,
+            data: LogData::new_unchecked(topics, Bytes::from(data)),
+        }
+    }
+
+    fn root_call(children: Vec<CallTrace>) -> CallTrace {
+        CallTrace {
+            from: USER,
+            to: DAPP,
+            input: Bytes::from(vec![0x12, 0x34, 0x56, 0x78]),
+            call_scheme: Some(CallScheme::Call),
+            subtraces: children,
+            ..Default::default()
+        }
+    }
+
+    fn report(root: CallTrace, logs: Vec<Log>) -> SimulationReport {
+        let mut report = SimulationReport::default();
+        report.trace.call_trace = Some(root);
+        report.trace.logs = logs;
+        report
+    }
+
+    fn erc20_transfer(from: Address, to: Address, amount: u64) -> Log {
+        log(
+            TOKEN,
+            vec![ERC20_TRANSFER_EVENT_SIGNATURE, topic(from), topic(to)],
+            U256::from(amount).to_be_bytes_vec(),
+        )
+    }
+
+    #[test]
+    fn test_large_outflow_requires_snapshot() {
+        let logs = vec![erc20_transfer(USER, ATTACKER, 90)];
+        let without_snapshot = report(root_call(vec![]), logs);
+        assert!(detect_large_outflows(&without_snapshot, USER, &RiskConfig::default()).is_empty());
+
+        let with_snapshot = without_snapshot.with_balances(vec![BalanceSnapshot {
+            token: TOKEN,
+            holder: USER,
+            balance: U256::from(100),
+        }]);
+        let findings = detect_large_outflows(&with_snapshot, USER, &RiskConfig::default());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::High);
+        assert_eq!(findings[0].refs, vec![FindingRef::LogIndex(0)]);
+
+        let small = report(root_call(vec![]), vec![erc20_transfer(USER, ATTACKER, 10)])
+            .with_balances(with_snapshot.balances_before.clone());
+        assert!(detect_large_outflows(&small, USER, &RiskConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn test_unlimited_approval() {
+        let approval = |spender: Address| {
+            log(
+                TOKEN,
+                vec![APPROVAL_EVENT_SIGNATURE, topic(USER), topic(spender)],
+                U256::MAX.to_be_bytes_vec(),
+            )
+        };
+        let report = report(root_call(vec![]), vec![approval(ATTACKER), approval(DAPP)]);
+        let config = RiskConfig {
+            spender_whitelist: HashSet::from([DAPP]),
+            ..Default::default()
+        };
+        let findings = detect_unlimited_approvals(&report, USER, &config);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].kind, RiskKind::UnlimitedApproval);
+        assert_eq!(findings[0].refs, vec![FindingRef::LogIndex(0)]);
+    }
+
+    #[test]
+    fn test_nft_to_unknown_recipient() {
+        let nft_transfer = log(
+            TOKEN,
+            vec![
+                ERC20_TRANSFER_EVENT_SIGNATURE,
+                topic(USER),
+                topic(ATTACKER),
+                B256::with_last_byte(7),
+            ],
+            vec![],
+        );
+        let mut report = report(root_call(vec![]), vec![nft_transfer]);
+        let findings = detect_nft_to_unknown_recipient(&report, USER);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Low);
+
+        report.accounts.insert(ATTACKER, AccountFacts::default());
+        assert_eq!(
+            detect_nft_to_unknown_recipient(&report, USER)[0].severity,
+            Severity::High
+        );
+
+        report.accounts.insert(
+            ATTACKER,
+            AccountFacts {
+                has_code: true,
+                nonce: 1,
+            },
+        );
+        assert!(detect_nft_to_unknown_recipient(&report, USER).is_empty());
+    }
+
+    #[test]
+    fn test_entry_delegatecall_ignores_proxy_forwarding() {
+        let delegate = |input: Vec<u8>| CallTrace {
+            from: DAPP,
+            to: ATTACKER,
+            input: Bytes::from(input),
+            call_scheme: Some(CallScheme::DelegateCall),
+            trace_address: vec![0],
+            ..Default::default()
+        };
+        let forwarding = report(
+            root_call(vec![delegate(vec![0x12, 0x34, 0x56, 0x78])]),
+            vec![],
+        );
+        assert!(detect_entry_delegatecall(&forwarding, USER).is_empty());
+
+        let injected = report(root_call(vec![delegate(vec![0xde, 0xad])]), vec![]);
+        let findings = detect_entry_delegatecall(&injected, USER);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].refs, vec![FindingRef::TraceAddress(vec![0])]);
+    }
+
+    #[test]
+    fn test_value_to_fresh_contract() {
+        let fresh = address!("5000000000000000000000000000000000000005");
+        let create = CallTrace {
+            from: DAPP,
+            to: fresh,
+            create_scheme: Some(CreateScheme::Create),
+            trace_address: vec![0],
+            ..Default::default()
+        };
+        let fund = CallTrace {
+            from: DAPP,
+            to: fresh,
+            value: U256::from(1),
+            call_scheme: Some(CallScheme::Call),
+            trace_address: vec![1],
+            ..Default::default()
+        };
+        let report = report(root_call(vec![create, fund]), vec![]);
+        let findings = detect_value_to_fresh_contract(&report, USER);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Medium);
+        assert_eq!(findings[0].refs, vec![FindingRef::TraceAddress(vec![1])]);
+    }
+
+    #[test]
+    fn test_tx_origin_authorization() {
+        let guard = |log_indices: Vec<usize>| CallTrace {
+            from: DAPP,
+            to: TOKEN,
+            call_scheme: Some(CallScheme::Call),
+            trace_address: vec![0],
+            used_tx_origin: true,
+            tx_origin_reads: 1,
+            log_indices,
+            ..Default::default()
+        };
+        let logs = vec![
+            erc20_transfer(ATTACKER, DAPP, 5),
+            erc20_transfer(USER, ATTACKER, 5),
+        ];
+        let unrelated = report(root_call(vec![guard(vec![0])]), logs.clone());
+        assert!(detect_tx_origin_authorization(&unrelated, USER).is_empty());
+
+        let drained = report(root_call(vec![guard(vec![0, 1])]), logs);
+        let findings = detect_tx_origin_authorization(&drained, USER);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::High);
+        assert_eq!(
+            findings[0].refs,
+            vec![FindingRef::TraceAddress(vec![0]), FindingRef::LogIndex(1)]
+        );
+    }
+
+    #[test]
+    fn test_empty_report_does_not_panic() {
+        let findings = risk_assessment(&SimulationReport::default(), USER);
+        assert!(findings.is_empty());
+    }
+}
@@ -0,0 +1,273 @@
+//! Structural diff of two call trees
+//!
+//! Compares the call trees of two related transactions, such as a failed attempt and
+//! its successful retry in the same batch. Frames are aligned greedily and in order:
+//! among the subcalls of two matched frames, a frame of A is matched with the next
+//! frame of B calling the same target with the same selector; a frame without such a
+//! counterpart is matched with the next frame of B by position, as long as that one
+//! has no counterpart of its own further down A. The alignment is deterministic, but
+//! not guaranteed to be minimal.
+
+use crate::{
+    inspectors::tx_inspector::TxTraceOutput,
+    types::{CallStatus, CallTrace},
+};
+use alloy::primitives::{Address, Bytes, U256};
+use serde::Serialize;
+
+/// Number of matched frame pairs after which `diff_traces` stops aligning
+pub const MAX_DIFF_FRAMES: usize = 10_000;
+
+/// Differences between two call trees
+///
+/// Paths are positions in each tree, in the format of `CallTrace::trace_address`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TraceDiff {
+    /// Frames of A without a counterpart in B; their subcalls are not listed
+    pub only_in_a: Vec<Vec<usize>>,
+    /// Frames of B without a counterpart in A; their subcalls are not listed
+    pub only_in_b: Vec<Vec<usize>>,
+    /// Matched frames that differ, in pre-order of A
+    pub changed: Vec<FrameDiff>,
+    /// Whether alignment stopped after `MAX_DIFF_FRAMES` frames, leaving the rest of
+    /// both trees uncompared
+    pub truncated: bool,
+}
+
+impl TraceDiff {
+    /// Whether the two trees are structurally identical in every compared field
+    pub fn is_empty(&self) -> bool {
+        self.only_in_a.is_empty() && self.only_in_b.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Pair of matched frames that differ
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FrameDiff {
+    /// Position of the frame in A
+    pub path_a: Vec<usize>,
+    /// Position of the frame in B
+    pub path_b: Vec<usize>,
+    /// Fields that differ
+    pub changes: Vec<FrameChange>,
+}
+
+/// Field of a call frame differing between A and B
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum FrameChange {
+    Status { a: CallStatus, b: CallStatus },
+    GasUsed { a: U256, b: U256 },
+    Output { a: Bytes, b: Bytes },
+    Value { a: U256, b: U256 },
+}
+
+/// Diffs two call trees
+///
+/// The top-level frames are always matched with each other. Matched frames are
+/// compared by status, gas used, output and value.
+///
+/// # Arguments
+/// - `a`: Call tree of the first transaction
+/// - `b`: Call tree of the second transaction
+///
+/// # Returns
+/// Unmatched and differing frames, with paths into both trees
+pub fn diff_traces(a: &CallTrace, b: &CallTrace) -> TraceDiff {
+    let mut diff = TraceDiff::default();
+    let mut budget = MAX_DIFF_FRAMES;
+    diff_frames(
+        a,
+        b,
+        &mut Vec::new(),
+        &mut Vec::new(),
+        &mut budget,
+        &mut diff,
+    );
+    diff
+}
+
+/// Diffs the call trees of two trace outputs
+///
+/// Returns `None` when either output has no call tree, as happens below
+/// `TraceLevel::Full`.
+pub fn diff_trace_outputs(a: &TxTraceOutput, b: &TxTraceOutput) -> Option<TraceDiff> {
+    Some(diff_traces(a.call_trace.as_ref()?, b.call_trace.as_ref()?))
+}
+
+/// Alignment key of a frame among its siblings: target and selector
+fn alignment_key(frame: &CallTrace) -> (Address, Option<&[u8]>) {
+    (frame.to, frame.input.get(..4))
+}
+
+fn diff_frames(
+    a: &CallTrace,
+    b: &CallTrace,
+    path_a: &mut Vec<usize>,
+    path_b: &mut Vec<usize>,
+    budget: &mut usize,
+    diff: &mut TraceDiff,
+) {
+    if *budget == 0 {
+        diff.truncated = true;
+        return;
+    }
+    *budget -= 1;
+
+    let mut changes = Vec::new();
+    if a.status != b.status {
+        changes.push(FrameChange::Status {
+            a: a.status.clone(),
+            b: b.status.clone(),
+        });
+    }
+    if a.gas_used != b.gas_used {
+        changes.push(FrameChange::GasUsed {
+            a: a.gas_used,
+            b: b.gas_used,
+        });
+    }
+    if a.output != b.output {
+        changes.push(FrameChange::Output {
+            a: a.output.clone(),
+            b: b.output.clone(),
+        });
+    }
+    if a.value != b.value {
+        changes.push(FrameChange::Value {
+            a: a.value,
+            b: b.value,
+        });
+    }
+    if !changes.is_empty() {
+        diff.changed.push(FrameDiff {
+            path_a: path_a.clone(),
+            path_b: path_b.clone(),
+            changes,
+        });
+    }
+
+    let (children_a, children_b) = (&a.subtraces, &b.subtraces);
+    let mut next_b = 0;
+    for (i, child_a) in children_a.iter().enumerate() {
+        if *budget == 0 {
+            diff.truncated = true;
+            return;
+        }
+        let key = alignment_key(child_a);
+        let by_key = children_b[next_b..]
+            .iter()
+            .position(|child_b| alignment_key(child_b) == key)
+            .map(|offset| next_b + offset);
+        let by_position = || {
+            let child_b = children_b.get(next_b)?;
+            let key_b = alignment_key(child_b);
+            let claimed = children_a[i + 1..]
+                .iter()
+                .any(|later| alignment_key(later) == key_b);
+            (!claimed).then_some(next_b)
+        };
+        let Some(j) = by_key.or_else(by_position) else {
+            diff.only_in_a.push(child_path(path_a, i));
+            continue;
+        };
+        for skipped in next_b..j {
+            diff.only_in_b.push(child_path(path_b, skipped));
+        }
+        path_a.push(i);
+        path_b.push(j);
+        diff_frames(child_a, &children_b[j], path_a, path_b, budget, diff);
+        path_a.pop();
+        path_b.pop();
+        next_b = j + 1;
+    }
+    for skipped in next_b..children_b.len() {
+        diff.only_in_b.push(child_path(path_b, skipped));
+    }
+}
+
+fn child_path(parent: &[usize], index: usize) -> Vec<usize> {
+    let mut path = parent.to_vec();
+    path.push(index);
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::DecodedRevert;
+
+    fn frame(to: u8, selector: u8, children: Vec<CallTrace>) -> CallTrace {
+        CallTrace {
+            to: Address::with_last_byte(to),
+            input: Bytes::from(vec![selector; 4]),
+            subtraces: children,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_identical_trees() {
+        let tree = frame(1, 1, vec![frame(2, 2, vec![]), frame(3, 3, vec![])]);
+        assert!(diff_traces(&tree, &tree).is_empty());
+    }
+
+    #[test]
+    fn test_inserted_and_removed_frames() {
+        let a = frame(1, 1, vec![frame(2, 2, vec![]), frame(3, 3, vec![])]);
+        let b = frame(
+            1,
+            1,
+            vec![frame(9, 9, vec![]), frame(3, 3, vec![frame(4, 4, vec![])])],
+        );
+        let diff = diff_traces(&a, &b);
+        // 0x02 is matched with 0x09 by position, since 0x09 has no counterpart in A
+        assert!(diff.only_in_a.is_empty());
+        assert_eq!(diff.only_in_b, vec![vec![1, 0]]);
+        assert!(diff.changed.is_empty());
+
+        let b = frame(1, 1, vec![frame(3, 3, vec![frame(4, 4, vec![])])]);
+        let diff = diff_traces(&a, &b);
+        // 0x02 cannot take 0x03 by position, as the next frame of A claims it
+        assert_eq!(diff.only_in_a, vec![vec![0]]);
+        assert_eq!(diff.only_in_b, vec![vec![0, 0]]);
+        assert!(diff.changed.is_empty());
+    }
+
+    #[test]
+    fn test_changed_fields_are_localized() {
+        let a = frame(1, 1, vec![frame(2, 2, vec![])]);
+        let mut b = a.clone();
+        let nope = CallStatus::Revert {
+            reason: Some(DecodedRevert::Error("nope".to_string())),
+            raw: Default::default(),
+        };
+        b.subtraces[0].status = nope.clone();
+        b.subtraces[0].gas_used = U256::from(100);
+        let diff = diff_traces(&a, &b);
+        assert_eq!(
+            diff.changed,
+            vec![FrameDiff {
+                path_a: vec![0],
+                path_b: vec![0],
+                changes: vec![
+                    FrameChange::Status {
+                        a: CallStatus::Success,
+                        b: nope,
+                    },
+                    FrameChange::GasUsed {
+                        a: U256::ZERO,
+                        b: U256::from(100),
+                    },
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_frame_cap() {
+        let wide = frame(1, 1, vec![frame(2, 2, vec![]); MAX_DIFF_FRAMES + 5]);
+        let diff = diff_traces(&wide, &wide);
+        assert!(diff.truncated);
+        assert!(diff.is_empty());
+    }
+}
@@ -0,0 +1,274 @@
+//! Native value held by each address after a transaction
+//!
+//! Routers and other intermediate contracts are expected to pass on all the ETH they
+//! receive; a contract ending up with ETH it did not start with usually points at
+//! funds stuck by a rounding or accounting bug. [`value_flow`] totals the native value
+//! every address received and sent, and [`stuck_funds`] keeps the contracts that
+//! retained some of it.
+//!
+//! Totals are derived from the call tree: value-bearing frames whose effects persisted
+//! plus selfdestruct credits. When the report carries native balances from before and
+//! after the transaction, the state-derived change is reported next to them, so that
+//! value moved outside of the traced frames shows up as a discrepancy instead of being
+//! trusted silently.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::consistency::to_signed;
+use crate::{
+    report::SimulationReport,
+    types::{TargetKind, TokenType, NATIVE_TOKEN_ADDRESS},
+};
+use alloy::primitives::{Address, I256, U256};
+use serde::Serialize;
+
+/// Native value received and sent by one address during a transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValueHolding {
+    /// Address holding the value
+    pub address: Address,
+    /// Received minus sent value
+    pub net_gain: I256,
+    /// Total value received
+    pub incoming: U256,
+    /// Total value sent
+    pub outgoing: U256,
+    /// Change of the native balance between the report's snapshots, when both are
+    /// recorded
+    pub state_net_gain: Option<I256>,
+    /// Whether the address had code when it was called, or was created in the
+    /// transaction
+    pub is_contract: bool,
+}
+
+impl ValueHolding {
+    /// Whether the state-derived balance change contradicts the transfers
+    pub fn has_discrepancy(&self) -> bool {
+        self.state_net_gain
+            .is_some_and(|state_net_gain| state_net_gain != self.net_gain)
+    }
+}
+
+/// Totals the native value received and sent by every address
+///
+/// The transaction sender, whose balance also pays for gas, and the block's coinbase
+/// are left out. The coinbase is identified by the direct payments it received, if
+/// any. Without a call tree (below `TraceLevel::Full`), totals fall back to the raw
+/// native transfers, which include value moved by reverted frames, and no address is
+/// known to be a contract.
+///
+/// # Returns
+/// One entry per address that received or sent value, by descending net gain, ties
+/// ordered by address
+pub fn value_flow(report: &SimulationReport) -> Vec<ValueHolding> {
+    let trace = &report.trace;
+    let hops = native_hops(report);
+
+    let mut contracts = BTreeSet::new();
+    let mut excluded = BTreeSet::from([report.tx.caller]);
+    if let Some(root) = &trace.call_trace {
+        contracts.extend(
+            root.frames()
+                .filter(|frame| {
+                    matches!(
+                        frame.target_kind,
+                        TargetKind::Contract | TargetKind::Created
+                    )
+                })
+                .map(|frame| frame.to),
+        );
+        excluded.extend(
+            trace
+                .coinbase_payments
+                .iter()
+                .filter_map(|payment| root.frame_at(&payment.trace_address))
+                .map(|frame| frame.to),
+        );
+    }
+
+    let mut totals: BTreeMap<Address, (U256, U256)> = BTreeMap::new();
+    for (from, to, value) in hops {
+        let sent = &mut totals.entry(from).or_default().1;
+        *sent = sent.saturating_add(value);
+        let received = &mut totals.entry(to).or_default().0;
+        *received = received.saturating_add(value);
+    }
+
+    let mut holdings: Vec<_> = totals
+        .into_iter()
+        .filter(|(address, _)| !excluded.contains(address))
+        .map(|(address, (incoming, outgoing))| {
+            let state_net_gain = report
+                .balance_before(NATIVE_TOKEN_ADDRESS, address)
+                .zip(report.balance_after(NATIVE_TOKEN_ADDRESS, address))
+                .map(|(before, after)| to_signed(after).saturating_sub(to_signed(before)));
+            ValueHolding {
+                address,
+                net_gain: to_signed(incoming).saturating_sub(to_signed(outgoing)),
+                incoming,
+                outgoing,
+                state_net_gain,
+                is_contract: contracts.contains(&address),
+            }
+        })
+        .collect();
+    // Stable sort keeps the address order among equal gains
+    holdings.sort_by(|a, b| b.net_gain.cmp(&a.net_gain));
+    holdings
+}
+
+/// Native value moves `(from, to, value)` of a transaction
+///
+/// Taken from the call tree when there is one, so that moves of reverted frames are
+/// left out, and from the raw native transfers otherwise.
+pub(super) fn native_hops(report: &SimulationReport) -> Vec<(Address, Address, U256)> {
+    let trace = &report.trace;
+    trace.native_hops().unwrap_or_else(|| {
+        trace
+            .asset_transfers
+            .iter()
+            .filter(|transfer| transfer.token_type == TokenType::Native)
+            .filter_map(|transfer| Some((transfer.from, transfer.to?, transfer.value)))
+            .filter(|(from, to, value)| from != to && !value.is_zero())
+            .collect()
+    })
+}
+
+/// Contracts that retained at least `threshold` of the native value they received
+///
+/// Entries with a discrepancy are kept and can be told apart with
+/// [`ValueHolding::has_discrepancy`].
+pub fn stuck_funds(report: &SimulationReport, threshold: U256) -> Vec<ValueHolding> {
+    let threshold = to_signed(threshold);
+    value_flow(report)
+        .into_iter()
+        .filter(|holding| {
+            holding.is_contract && holding.net_gain > I256::ZERO && holding.net_gain >= threshold
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        report::BalanceSnapshot,
+        types::{CallScheme, CallStatus, CallTrace, CoinbasePayment, ContractChange},
+    };
+    use alloy::primitives::address;
+
+    const SENDER: Address = address!("1000000000000000000000000000000000000001");
+    const ROUTER: Address = address!("2000000000000000000000000000000000000002");
+    const POOL: Address = address!("3000000000000000000000000000000000000003");
+    const BOB: Address = address!("4000000000000000000000000000000000000004");
+    const COINBASE: Address = address!("5000000000000000000000000000000000000005");
+
+    fn call(from: Address, to: Address, value: u64, trace_address: Vec<usize>) -> CallTrace {
+        CallTrace {
+            from,
+            to,
+            value: U256::from(value),
+            call_scheme: Some(CallScheme::Call),
+            trace_address,
+            ..Default::default()
+        }
+    }
+
+    /// SENDER pays ROUTER 100, which passes 60 to POOL (which passes 60 to BOB) and
+    /// tips the coinbase 10
+    fn report() -> SimulationReport {
+        let mut pool = call(ROUTER, POOL, 60, vec![0]);
+        let mut to_bob = call(POOL, BOB, 60, vec![0, 0]);
+        to_bob.target_kind = TargetKind::Eoa;
+        pool.subtraces = vec![to_bob];
+        let mut tip = call(ROUTER, COINBASE, 10, vec![1]);
+        tip.target_kind = TargetKind::Eoa;
+        let mut root = call(SENDER, ROUTER, 100, vec![]);
+        root.subtraces = vec![pool, tip];
+
+        let mut report = SimulationReport::default();
+        report.tx.caller = SENDER;
+        report.trace.call_trace = Some(root);
+        report.trace.coinbase_payments = vec![CoinbasePayment {
+            from: ROUTER,
+            value: U256::from(10),
+            trace_address: vec![1],
+        }];
+        report
+    }
+
+    #[test]
+    fn test_holdings_exclude_sender_and_coinbase() {
+        let holdings = value_flow(&report());
+        let summary: Vec<_> = holdings
+            .iter()
+            .map(|holding| (holding.address, holding.net_gain, holding.is_contract))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (BOB, I256::try_from(60i64).unwrap(), false),
+                (ROUTER, I256::try_from(30i64).unwrap(), true),
+                (POOL, I256::ZERO, true),
+            ]
+        );
+        assert_eq!(holdings[1].incoming, U256::from(100));
+        assert_eq!(holdings[1].outgoing, U256::from(70));
+
+        let stuck = stuck_funds(&report(), U256::from(1));
+        assert_eq!(stuck.len(), 1);
+        assert_eq!(stuck[0].address, ROUTER);
+        assert!(stuck_funds(&report(), U256::from(31)).is_empty());
+    }
+
+    #[test]
+    fn test_reverted_frames_and_selfdestructs() {
+        let mut report = report();
+        let root = report.trace.call_trace.as_mut().unwrap();
+        // The pool call reverts, so ROUTER keeps the 60 it sent
+        root.subtraces[0].status = CallStatus::Revert {
+            reason: None,
+            raw: Default::default(),
+        };
+        // ROUTER then self-destructs in favor of BOB
+        report
+            .trace
+            .contract_changes
+            .push(ContractChange::SelfDestructed {
+                contract: ROUTER,
+                beneficiary: BOB,
+                balance_moved: U256::from(90),
+                same_tx_creation: false,
+                trace_address: vec![],
+            });
+
+        let holdings = value_flow(&report);
+        let router = holdings.iter().find(|h| h.address == ROUTER).unwrap();
+        assert_eq!(router.net_gain, I256::ZERO);
+        let bob = holdings.iter().find(|h| h.address == BOB).unwrap();
+        assert_eq!(bob.incoming, U256::from(90));
+        assert!(holdings.iter().all(|h| h.address != POOL));
+    }
+
+    #[test]
+    fn test_state_discrepancy_is_flagged() {
+        let snapshot = |holder, balance: u64| BalanceSnapshot {
+            token: NATIVE_TOKEN_ADDRESS,
+            holder,
+            balance: U256::from(balance),
+        };
+        let report = report()
+            .with_balances(vec![snapshot(ROUTER, 5), snapshot(BOB, 0)])
+            .with_balances_after(vec![snapshot(ROUTER, 35), snapshot(BOB, 50)]);
+
+        let holdings = value_flow(&report);
+        let router = holdings.iter().find(|h| h.address == ROUTER).unwrap();
+        assert_eq!(router.state_net_gain, Some(I256::try_from(30i64).unwrap()));
+        assert!(!router.has_discrepancy());
+        let bob = holdings.iter().find(|h| h.address == BOB).unwrap();
+        assert_eq!(bob.state_net_gain, Some(I256::try_from(50i64).unwrap()));
+        assert!(bob.has_discrepancy());
+        let pool = holdings.iter().find(|h| h.address == POOL).unwrap();
+        assert_eq!(pool.state_net_gain, None);
+    }
+}
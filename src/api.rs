@@ -0,0 +1,29 @@
+//! JSON schema for simulation services (feature = "serde-api")
+//!
+//! Services exposing simulations over HTTP tend to re-declare the same request and
+//! response structs with ad-hoc parsing of addresses, hex data and amounts. The
+//! [`types`] module provides a canonical schema instead:
+//!
+//! - [`SimulationRequest`](types::SimulationRequest) deserializes leniently: amounts may
+//!   be 0x-hex strings, decimal strings or JSON integers
+//! - Converting it into a [`SimulationPlan`](types::SimulationPlan) validates every
+//!   field and reports the first invalid one by path (`transactions[2].value: invalid
+//!   hex quantity`)
+//! - [`SimulationResponse`](types::SimulationResponse) serializes simulation reports
+//!
+//! ```
+//! use revm_trace::api::types::{SimulationPlan, SimulationRequest};
+//!
+//! let request: SimulationRequest = serde_json::from_str(r#"{
+//!     "transactions": [{
+//!         "from": "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045",
+//!         "to": "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2",
+//!         "value": "1000000000000000000",
+//!         "data": "0xd0e30db0"
+//!     }]
+//! }"#).unwrap();
+//! let plan = SimulationPlan::try_from(request).unwrap();
+//! assert_eq!(plan.batch.transactions.len(), 1);
+//! ```
+
+pub mod types;
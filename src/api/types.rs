@@ -0,0 +1,504 @@
+//! Request and response types of the simulation API
+//!
+//! Wire types keep clients' scalars as sent ([`RawScalar`], strings for addresses and
+//! data) so that validation can name the exact field that is wrong. Conversion into a
+//! [`SimulationPlan`] performs all parsing.
+//!
+//! Transaction addresses may also be ENS names, resolved by
+//! [`SimulationPlan::resolve`] (feature = "ens"); plain conversion rejects them.
+
+use std::{collections::HashMap, str::FromStr};
+
+use alloy::primitives::{hex, Address, Bytes, TxKind, B256, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::{ApiFieldError, EvmError},
+    inspectors::tx_inspector::TxTraceOutput,
+    report::{ExecutionSummary, SimulationReport},
+    types::{BlockContext, SimulationBatch, SimulationTx, StateOverride, TraceLevel},
+};
+
+/// Scalar as sent by a client: a JSON integer or a string
+///
+/// Strings starting with `0x` are read as hex, other strings as decimal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RawScalar {
+    Number(u64),
+    Text(String),
+}
+
+/// Simulation request as received from a client
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SimulationRequest {
+    /// RPC endpoint to fork from; services usually fall back to their own
+    #[serde(default)]
+    pub rpc_url: Option<String>,
+    /// Block to fork at: `"latest"`, a block number or a block hash (default: latest)
+    #[serde(default)]
+    pub block: Option<RawScalar>,
+    /// Balance and storage overrides applied before the batch
+    #[serde(default)]
+    pub state_overrides: Option<StateOverrideRequest>,
+    /// Transactions, executed in order
+    pub transactions: Vec<TxRequest>,
+    /// Whether each transaction sees the effects of the previous ones
+    #[serde(default)]
+    pub stateful: bool,
+    /// Amount of tracing for every transaction
+    #[serde(default)]
+    pub trace_level: TraceLevel,
+}
+
+/// Transaction of a [`SimulationRequest`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TxRequest {
+    /// Sender address or ENS name
+    pub from: String,
+    /// Target address or ENS name; absent for contract creation
+    #[serde(default)]
+    pub to: Option<String>,
+    /// Native value in wei (default: 0)
+    #[serde(default)]
+    pub value: Option<RawScalar>,
+    /// Hex calldata, or init code for creations (default: empty)
+    #[serde(default, alias = "input")]
+    pub data: Option<String>,
+}
+
+/// State overrides of a [`SimulationRequest`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StateOverrideRequest {
+    /// Balance per address
+    #[serde(default)]
+    pub balances: HashMap<String, RawScalar>,
+    /// Slot values per address
+    #[serde(default)]
+    pub storage: HashMap<String, HashMap<String, RawScalar>>,
+}
+
+/// Block a simulation forks at
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockSelector {
+    #[default]
+    Latest,
+    Number(u64),
+    Hash(B256),
+}
+
+#[cfg(any(feature = "default", feature = "rustls-tls"))]
+impl BlockSelector {
+    /// Pins `builder` to the selected block
+    pub fn apply<DB, INSP>(
+        self,
+        builder: crate::EvmBuilder<DB, INSP>,
+    ) -> crate::EvmBuilder<DB, INSP>
+    where
+        DB: revm::database::DatabaseRef,
+    {
+        match self {
+            Self::Latest => builder,
+            Self::Number(number) => builder.with_block_number(number),
+            Self::Hash(hash) => builder.with_block_hash(hash),
+        }
+    }
+}
+
+/// Validated simulation request
+#[derive(Debug, Clone)]
+pub struct SimulationPlan {
+    /// RPC endpoint requested by the client
+    pub rpc_url: Option<String>,
+    /// Block to build the EVM at
+    pub block: BlockSelector,
+    /// Batch to pass to `trace_transactions`
+    pub batch: SimulationBatch,
+}
+
+/// Resolves an ENS name found in a transaction address field
+type NameResolver<'a> = dyn FnMut(&str) -> Result<Address, String> + 'a;
+
+impl TryFrom<SimulationRequest> for SimulationPlan {
+    type Error = ApiFieldError;
+
+    /// Parses and validates every field, failing on the first invalid one
+    ///
+    /// ENS names are rejected; use [`SimulationPlan::resolve`] to accept them.
+    fn try_from(request: SimulationRequest) -> Result<Self, Self::Error> {
+        Self::parse(request, &mut |_| {
+            Err("ENS names require resolution".to_string())
+        })
+    }
+}
+
+impl SimulationPlan {
+    /// Converts `request` like `try_from`, resolving ENS names in transaction
+    /// addresses through `evm` (feature = "ens")
+    ///
+    /// `evm` should be built at the plan's block, so names resolve as of the fork.
+    #[cfg(feature = "ens")]
+    pub fn resolve<DB, INSP>(
+        request: SimulationRequest,
+        evm: &mut crate::TraceEvm<DB, INSP>,
+    ) -> Result<Self, ApiFieldError>
+    where
+        DB: revm::Database,
+    {
+        Self::parse(request, &mut |name| {
+            crate::utils::ens_utils::resolve(evm, name).map_err(|e| e.to_string())
+        })
+    }
+
+    fn parse(
+        request: SimulationRequest,
+        resolve_name: &mut NameResolver<'_>,
+    ) -> Result<Self, ApiFieldError> {
+        let block = match &request.block {
+            Some(block) => parse_block("block", block)?,
+            None => BlockSelector::Latest,
+        };
+        let transactions = request
+            .transactions
+            .iter()
+            .enumerate()
+            .map(|(index, tx)| {
+                parse_tx(
+                    &format!("transactions[{index}]"),
+                    tx,
+                    request.trace_level,
+                    resolve_name,
+                )
+            })
+            .collect::<Result<_, _>>()?;
+        let overrides = request
+            .state_overrides
+            .as_ref()
+            .map(|overrides| parse_overrides("state_overrides", overrides))
+            .transpose()?;
+        Ok(Self {
+            rpc_url: request.rpc_url,
+            block,
+            batch: SimulationBatch {
+                transactions,
+                is_stateful: request.stateful,
+                overrides,
+                ..Default::default()
+            },
+        })
+    }
+}
+
+/// Outcome of one transaction of a simulation
+#[derive(Debug, Clone, Serialize)]
+pub struct TxResponse {
+    /// Execution outcome; absent if the transaction could not be executed
+    pub summary: Option<ExecutionSummary>,
+    /// Why the transaction could not be executed (e.g. a database failure)
+    pub error: Option<String>,
+    /// Collected trace, per the requested trace level
+    pub trace: Option<TxTraceOutput>,
+}
+
+impl From<SimulationReport> for TxResponse {
+    fn from(report: SimulationReport) -> Self {
+        Self {
+            summary: Some(report.summary),
+            error: None,
+            trace: Some(report.trace),
+        }
+    }
+}
+
+impl From<Result<SimulationReport, EvmError>> for TxResponse {
+    fn from(result: Result<SimulationReport, EvmError>) -> Self {
+        match result {
+            Ok(report) => report.into(),
+            Err(e) => Self {
+                summary: None,
+                error: Some(e.to_string()),
+                trace: None,
+            },
+        }
+    }
+}
+
+/// Response to a [`SimulationRequest`]
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulationResponse {
+    /// Block the simulation forked at
+    pub block: BlockContext,
+    /// One entry per requested transaction, in order
+    pub results: Vec<TxResponse>,
+}
+
+impl SimulationResponse {
+    /// Builds a response from the reports of a batch
+    pub fn new<R>(block: BlockContext, results: impl IntoIterator<Item = R>) -> Self
+    where
+        R: Into<TxResponse>,
+    {
+        Self {
+            block,
+            results: results.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+fn field_error(field: &str, reason: impl Into<String>) -> ApiFieldError {
+    ApiFieldError {
+        field: field.to_string(),
+        reason: reason.into(),
+    }
+}
+
+fn parse_tx(
+    field: &str,
+    tx: &TxRequest,
+    trace_level: TraceLevel,
+    resolve_name: &mut NameResolver<'_>,
+) -> Result<SimulationTx, ApiFieldError> {
+    let caller = parse_party(&format!("{field}.from"), &tx.from, resolve_name)?;
+    let transact_to = match &tx.to {
+        Some(to) => TxKind::Call(parse_party(&format!("{field}.to"), to, resolve_name)?),
+        None => TxKind::Create,
+    };
+    let value = match &tx.value {
+        Some(value) => parse_quantity(&format!("{field}.value"), value)?,
+        None => U256::ZERO,
+    };
+    let data = match &tx.data {
+        Some(data) => parse_bytes(&format!("{field}.data"), data)?,
+        None => Bytes::new(),
+    };
+    Ok(SimulationTx {
+        caller,
+        value,
+        data,
+        transact_to,
+        trace_config: Some(trace_level),
+        return_abi: None,
+    })
+}
+
+fn parse_overrides(
+    field: &str,
+    overrides: &StateOverrideRequest,
+) -> Result<StateOverride, ApiFieldError> {
+    let mut parsed = StateOverride::default();
+    for (address, balance) in &overrides.balances {
+        let field = format!("{field}.balances[{address}]");
+        parsed.balances.insert(
+            parse_address(&field, address)?,
+            parse_quantity(&field, balance)?,
+        );
+    }
+    for (address, slots) in &overrides.storage {
+        let account = parse_address(&format!("{field}.storage[{address}]"), address)?;
+        let entries = parsed.storages.entry(account).or_default();
+        for (slot, value) in slots {
+            let field = format!("{field}.storage[{address}][{slot}]");
+            entries.push((
+                parse_quantity(&field, &RawScalar::Text(slot.clone()))?,
+                parse_quantity(&field, value)?,
+            ));
+        }
+        // Map iteration order is arbitrary; keep overrides deterministic
+        entries.sort();
+    }
+    Ok(parsed)
+}
+
+fn parse_address(field: &str, value: &str) -> Result<Address, ApiFieldError> {
+    Address::from_str(value.trim()).map_err(|_| field_error(field, "invalid address"))
+}
+
+/// Parses a hex address, or resolves an ENS name (anything else containing a dot)
+fn parse_party(
+    field: &str,
+    value: &str,
+    resolve_name: &mut NameResolver<'_>,
+) -> Result<Address, ApiFieldError> {
+    let name = value.trim();
+    if name.contains('.') && !name.starts_with("0x") && !name.starts_with("0X") {
+        return resolve_name(name).map_err(|reason| field_error(field, reason));
+    }
+    parse_address(field, value)
+}
+
+fn parse_bytes(field: &str, value: &str) -> Result<Bytes, ApiFieldError> {
+    let value = value.trim();
+    let digits = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value);
+    hex::decode(digits)
+        .map(Into::into)
+        .map_err(|_| field_error(field, "invalid hex"))
+}
+
+fn parse_quantity(field: &str, value: &RawScalar) -> Result<U256, ApiFieldError> {
+    let text = match value {
+        RawScalar::Number(number) => return Ok(U256::from(*number)),
+        RawScalar::Text(text) => text.trim(),
+    };
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(digits) if !digits.is_empty() => {
+            U256::from_str_radix(digits, 16).map_err(|_| field_error(field, "invalid hex quantity"))
+        }
+        Some(_) => Err(field_error(field, "invalid hex quantity")),
+        None if !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit()) => {
+            U256::from_str_radix(text, 10)
+                .map_err(|_| field_error(field, "quantity overflows 256 bits"))
+        }
+        None => Err(field_error(field, "invalid decimal quantity")),
+    }
+}
+
+fn parse_block(field: &str, value: &RawScalar) -> Result<BlockSelector, ApiFieldError> {
+    if let RawScalar::Text(text) = value {
+        let text = text.trim();
+        if text.eq_ignore_ascii_case("latest") {
+            return Ok(BlockSelector::Latest);
+        }
+        // 32-byte hex strings are hashes, shorter ones numbers
+        if text.len() == 66 {
+            return B256::from_str(text)
+                .map(BlockSelector::Hash)
+                .map_err(|_| field_error(field, "invalid block hash"));
+        }
+    }
+    let number = parse_quantity(field, value)?;
+    u64::try_from(number)
+        .map(BlockSelector::Number)
+        .map_err(|_| field_error(field, "block number overflows 64 bits"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::address;
+
+    fn plan(json: &str) -> Result<SimulationPlan, ApiFieldError> {
+        let request: SimulationRequest = serde_json::from_str(json).unwrap();
+        SimulationPlan::try_from(request)
+    }
+
+    #[test]
+    fn test_mixed_hex_and_decimal_inputs() {
+        let plan = plan(
+            r#"{
+                "block": "0x112a880",
+                "state_overrides": {
+                    "balances": { "0x1000000000000000000000000000000000000001": 1000 },
+                    "storage": {
+                        "0x2000000000000000000000000000000000000002": { "0x1": "0xff", "2": "10" }
+                    }
+                },
+                "transactions": [
+                    { "from": "0x1000000000000000000000000000000000000001",
+                      "to": "0x2000000000000000000000000000000000000002",
+                      "value": "0xde0b6b3a7640000", "data": "0xd0e30db0" },
+                    { "from": "1000000000000000000000000000000000000001",
+                      "value": "1000000000000000000", "input": "6000" },
+                    { "from": "0x1000000000000000000000000000000000000001",
+                      "to": "0x2000000000000000000000000000000000000002", "value": 7 }
+                ],
+                "stateful": true,
+                "trace_level": "TransfersOnly"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(plan.block, BlockSelector::Number(18_000_000));
+        let batch = plan.batch;
+        assert!(batch.is_stateful);
+        let ether = U256::from(10u64.pow(18));
+        assert_eq!(batch.transactions[0].value, ether);
+        assert_eq!(batch.transactions[1].value, ether);
+        assert_eq!(batch.transactions[2].value, U256::from(7));
+        assert_eq!(batch.transactions[1].transact_to, TxKind::Create);
+        assert_eq!(batch.transactions[1].data, Bytes::from(vec![0x60, 0x00]));
+        assert_eq!(
+            batch.transactions[0].trace_config,
+            Some(TraceLevel::TransfersOnly)
+        );
+
+        let overrides = batch.overrides.unwrap();
+        let user = address!("1000000000000000000000000000000000000001");
+        let contract = address!("2000000000000000000000000000000000000002");
+        assert_eq!(overrides.balances[&user], U256::from(1000));
+        assert_eq!(
+            overrides.storages[&contract],
+            vec![
+                (U256::from(1), U256::from(255)),
+                (U256::from(2), U256::from(10))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_block_selectors() {
+        let block = |value: &str| plan(&format!(r#"{{"block": {value}, "transactions": []}}"#));
+        assert_eq!(block(r#""latest""#).unwrap().block, BlockSelector::Latest);
+        assert_eq!(
+            block("18000000").unwrap().block,
+            BlockSelector::Number(18_000_000)
+        );
+        let hash = format!("0x{}", "ab".repeat(32));
+        assert_eq!(
+            block(&format!(r#""{hash}""#)).unwrap().block,
+            BlockSelector::Hash(B256::from_str(&hash).unwrap())
+        );
+        assert_eq!(
+            block(r#""0x1ffffffffffffffff""#).unwrap_err().to_string(),
+            "block: block number overflows 64 bits"
+        );
+    }
+
+    #[test]
+    fn test_field_precise_errors() {
+        let tx = |fields: &str| {
+            format!(
+                r#"{{"from": "0x1000000000000000000000000000000000000001", "to": "0x2000000000000000000000000000000000000002"{fields}}}"#
+            )
+        };
+        let error = |transactions: Vec<String>| {
+            plan(&format!(
+                r#"{{"transactions": [{}]}}"#,
+                transactions.join(",")
+            ))
+            .unwrap_err()
+            .to_string()
+        };
+
+        assert_eq!(
+            error(vec![tx(""), tx(""), tx(r#", "value": "0xzz""#)]),
+            "transactions[2].value: invalid hex quantity"
+        );
+        assert_eq!(
+            error(vec![tx(r#", "value": "1e18""#)]),
+            "transactions[0].value: invalid decimal quantity"
+        );
+        assert_eq!(
+            error(vec![tx(""), tx(r#", "data": "0xabc""#)]),
+            "transactions[1].data: invalid hex"
+        );
+        assert_eq!(
+            error(vec![r#"{"from": "0x1234"}"#.to_string()]),
+            "transactions[0].from: invalid address"
+        );
+        assert_eq!(
+            error(vec![r#"{"from": "vitalik.eth"}"#.to_string()]),
+            "transactions[0].from: ENS names require resolution"
+        );
+        assert_eq!(
+            plan(r#"{"transactions": [], "state_overrides": {"balances": {"0x1": 1}}}"#)
+                .unwrap_err(),
+            ApiFieldError {
+                field: "state_overrides.balances[0x1]".to_string(),
+                reason: "invalid address".to_string(),
+            }
+        );
+    }
+}
@@ -0,0 +1,480 @@
+//! Per-chain presets for transfer accounting
+//!
+//! Chains differ in how their native token shows up in traces: most have a WETH-style
+//! wrapper, while on Celo the native token is itself an ERC20 and gas fees can be paid
+//! in several fee currencies whose debits appear as token transfers. A [`ChainPreset`]
+//! captures these addresses so post-processing can tell such transfers apart (see
+//! `TxTraceOutput::classify_transfers`). The inspector itself stays chain-agnostic.
+//!
+//! The module also holds the registry of protocol-level system contracts used to label
+//! call targets (see [`system_contract_name`]), and the policy for system transactions
+//! that cannot be simulated (see [`SystemTxHandling`]).
+//!
+//! ```
+//! use revm_trace::chains::ChainPreset;
+//!
+//! let celo = ChainPreset::from_chain_id(42220).unwrap();
+//! assert_eq!(celo.native_symbol, "CELO");
+//! assert!(!celo.fee_currency_addresses.is_empty());
+//! ```
+
+use crate::errors::RuntimeError;
+use alloy::primitives::{address, Address, U256};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// EIP-2718 type of OP Stack deposit transactions
+pub const DEPOSIT_TX_TYPE: u8 = 0x7e;
+
+/// Sender of the L1 attributes deposit opening every OP Stack block
+pub const L1_ATTRIBUTES_DEPOSITOR: Address = address!("deaddeaddeaddeaddeaddeaddeaddeaddead0001");
+
+/// `L1Block` predeploy the L1 attributes deposit writes to
+pub const L1_BLOCK_PREDEPLOY: Address = address!("4200000000000000000000000000000000000015");
+
+/// Selector of `setL1BlockValues(uint64,uint64,uint256,bytes32,uint64,bytes32,uint256,uint256)`
+const SET_L1_BLOCK_VALUES: [u8; 4] = [0x01, 0x5d, 0x8e, 0xb9];
+/// Selector of `setL1BlockValuesEcotone()`
+const SET_L1_BLOCK_VALUES_ECOTONE: [u8; 4] = [0x44, 0x0a, 0x5e, 0x20];
+/// Selector of `setL1BlockValuesIsthmus()`
+const SET_L1_BLOCK_VALUES_ISTHMUS: [u8; 4] = [0x09, 0x89, 0x99, 0xbe];
+
+/// Whether a transaction type is a system transaction a `SimulationTx` cannot represent
+///
+/// OP Stack deposits (type `0x7e`) are minted by the protocol: they have no signature,
+/// nonce or gas payment, and may create value out of thin air. Executing one as a
+/// regular call from its `from` address fails with confusing nonce or balance errors.
+pub fn is_system_tx_type(tx_type: u8) -> bool {
+    tx_type == DEPOSIT_TX_TYPE
+}
+
+/// How transactions that cannot be simulated are treated when building batches from
+/// chain data, e.g. by `TraceEvm::trace_transaction_by_hash`
+///
+/// Full deposit semantics (minting, gas accounting exemptions, the depositor's nonce)
+/// are not simulated. Skipping deposits is safe for replaying user transactions,
+/// which only observe their effects through state already present at the fork block.
+/// Transactions reading the L1 attributes of their own block, such as L1 fee oracles,
+/// need `ApplyAsStateOverride`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SystemTxHandling {
+    /// Leave system transactions out of the batch
+    #[default]
+    Skip,
+    /// Write the `L1Block` storage of L1 attributes deposits as state overrides (see
+    /// [`l1_attributes_storage`]) and leave other deposits out, as with `Skip`
+    ApplyAsStateOverride,
+    /// Fail on the first system transaction
+    Error,
+}
+
+impl SystemTxHandling {
+    /// Decides whether a transaction of type `tx_type` is simulated
+    ///
+    /// # Returns
+    /// - `Ok(true)`: A regular transaction, to be simulated
+    /// - `Ok(false)`: A system transaction not executed (`Skip`, and
+    ///   `ApplyAsStateOverride`, whose caller applies the effects)
+    /// - `Err(RuntimeError::UnsupportedTransaction(_))`: A system transaction under `Error`
+    pub fn admit(self, tx_type: u8) -> Result<bool, RuntimeError> {
+        if !is_system_tx_type(tx_type) {
+            return Ok(true);
+        }
+        match self {
+            Self::Skip | Self::ApplyAsStateOverride => Ok(false),
+            Self::Error => Err(RuntimeError::UnsupportedTransaction(format!(
+                "system transaction of type {tx_type:#04x} cannot be simulated"
+            ))),
+        }
+    }
+}
+
+/// Storage an L1 attributes deposit with calldata `input` writes to the `L1Block`
+/// predeploy, as `(slot, value)` pairs
+///
+/// Decodes the Bedrock `setL1BlockValues` call and the packed calldata of
+/// `setL1BlockValuesEcotone` and `setL1BlockValuesIsthmus`, following the storage
+/// layout of the predeploy in each upgrade. Returns `None` for any other calldata,
+/// including truncated calls.
+///
+/// ```
+/// use alloy::primitives::U256;
+/// use revm_trace::chains::l1_attributes_storage;
+///
+/// let mut input = vec![0x44, 0x0a, 0x5e, 0x20];
+/// input.extend([0u8; 160]);
+/// input[35] = 7; // L1 block number
+/// let slots = l1_attributes_storage(&input).unwrap();
+/// assert!(slots.contains(&(U256::ZERO, U256::from(7))));
+/// assert!(l1_attributes_storage(&input[..100]).is_none());
+/// ```
+pub fn l1_attributes_storage(input: &[u8]) -> Option<Vec<(U256, U256)>> {
+    let (selector, data) = input.split_first_chunk::<4>()?;
+    let word = |range: Range<usize>| data.get(range).map(U256::from_be_slice);
+    match *selector {
+        SET_L1_BLOCK_VALUES => {
+            let arg = |index: usize| word(index * 32..(index + 1) * 32);
+            let low_u64 = |value: U256| value & U256::from(u64::MAX);
+            // number and timestamp share slot 0
+            let packed = (low_u64(arg(1)?) << 64) | low_u64(arg(0)?);
+            Some(vec![
+                (U256::ZERO, packed),
+                (U256::from(1), arg(2)?),
+                (U256::from(2), arg(3)?),
+                (U256::from(3), low_u64(arg(4)?)),
+                (U256::from(4), arg(5)?),
+                (U256::from(5), arg(6)?),
+                (U256::from(6), arg(7)?),
+            ])
+        }
+        SET_L1_BLOCK_VALUES_ECOTONE | SET_L1_BLOCK_VALUES_ISTHMUS => {
+            // Packed fields are stored as the contract copies them from calldata:
+            // scalars and sequence number in slot 3, timestamp and number in slot 0
+            let mut slots = vec![
+                (U256::from(3), word(0..16)?),
+                (U256::ZERO, word(16..32)?),
+                (U256::from(1), word(32..64)?),
+                (U256::from(7), word(64..96)?),
+                (U256::from(2), word(96..128)?),
+                (U256::from(4), word(128..160)?),
+            ];
+            if *selector == SET_L1_BLOCK_VALUES_ISTHMUS {
+                // Operator fee scalar and constant
+                slots.push((U256::from(8), word(160..172)?));
+            }
+            Some(slots)
+        }
+        _ => None,
+    }
+}
+
+/// Accounting configuration of one chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainPreset {
+    /// EIP-155 chain id
+    pub chain_id: u64,
+    /// Human-readable chain name
+    pub name: String,
+    /// Symbol of the native token
+    pub native_symbol: String,
+    /// Decimals of the native token
+    pub native_decimals: u8,
+    /// Wrapped (or ERC20) representation of the native token
+    pub native_wrapped: Address,
+    /// ERC20 tokens accepted for paying gas fees
+    pub fee_currency_addresses: Vec<Address>,
+}
+
+impl ChainPreset {
+    /// Creates a preset for a chain with an 18-decimal native token and no fee currencies
+    pub fn new(chain_id: u64, name: &str, native_symbol: &str, native_wrapped: Address) -> Self {
+        Self {
+            chain_id,
+            name: name.to_string(),
+            native_symbol: native_symbol.to_string(),
+            native_decimals: 18,
+            native_wrapped,
+            fee_currency_addresses: Vec::new(),
+        }
+    }
+
+    /// Sets the tokens accepted for paying gas fees
+    pub fn with_fee_currencies(mut self, fee_currencies: Vec<Address>) -> Self {
+        self.fee_currency_addresses = fee_currencies;
+        self
+    }
+
+    /// Sets the decimals of the native token
+    pub fn with_native_decimals(mut self, decimals: u8) -> Self {
+        self.native_decimals = decimals;
+        self
+    }
+
+    /// Returns the built-in preset for a chain id
+    ///
+    /// Returns `None` for chains without a preset; construct one with
+    /// [`ChainPreset::new`] to classify transfers on other chains.
+    pub fn from_chain_id(chain_id: u64) -> Option<Self> {
+        let preset = match chain_id {
+            1 => Self::new(
+                1,
+                "Ethereum",
+                "ETH",
+                address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+            ),
+            10 => Self::new(
+                10,
+                "Optimism",
+                "ETH",
+                address!("4200000000000000000000000000000000000006"),
+            ),
+            56 => Self::new(
+                56,
+                "BNB Smart Chain",
+                "BNB",
+                address!("bb4CdB9CBd36B01bD1cBaEBF2De08d9173bc095c"),
+            ),
+            137 => Self::new(
+                137,
+                "Polygon",
+                "POL",
+                address!("0d500B1d8E8eF31E21C99d1Db9A6444d3ADf1270"),
+            ),
+            8453 => Self::new(
+                8453,
+                "Base",
+                "ETH",
+                address!("4200000000000000000000000000000000000006"),
+            ),
+            42161 => Self::new(
+                42161,
+                "Arbitrum One",
+                "ETH",
+                address!("82aF49447D8a07e3bd95BD0d56f35241523fBab1"),
+            ),
+            // CELO is both the native token and an ERC20; fees may also be paid in
+            // the Mento stablecoins
+            42220 => Self::new(
+                42220,
+                "Celo",
+                "CELO",
+                address!("471EcE3750Da237f93B8E339c536989b8978a438"),
+            )
+            .with_fee_currencies(vec![
+                address!("471EcE3750Da237f93B8E339c536989b8978a438"),
+                address!("765DE816845861e75A25fCA122bb6898B8B1282a"),
+                address!("D8763CBa276a3738E6DE85b4b3bF5FDed6D6cA73"),
+                address!("e8537a3d056DA446677B9E9d6c5dB704EaAb4787"),
+            ]),
+            _ => return None,
+        };
+        Some(preset)
+    }
+
+    /// Whether `token` is accepted for paying gas fees
+    pub fn is_fee_currency(&self, token: Address) -> bool {
+        self.fee_currency_addresses.contains(&token)
+    }
+
+    /// Name of the system contract at `address` on this chain, if any
+    pub fn system_contract(&self, address: Address) -> Option<&'static str> {
+        system_contract_name(self.chain_id, address)
+    }
+}
+
+/// System contracts at the same address on every chain that activated them
+const COMMON_SYSTEM_CONTRACTS: &[(Address, &str)] = &[
+    (
+        address!("000F3df6D732807Ef1319fB7B8bB8522d0Beac02"),
+        "BeaconRoots",
+    ),
+    (
+        address!("0000F90827F1C53a10cb7A02335B175320002935"),
+        "HistoryStorage",
+    ),
+    (
+        address!("00000961Ef480Eb55e80D19ad83579A64c007002"),
+        "WithdrawalRequests",
+    ),
+    (
+        address!("0000BBdDc7CE488642fb579F8B00f3a590007251"),
+        "ConsolidationRequests",
+    ),
+];
+
+/// Ethereum mainnet system contracts
+const MAINNET_SYSTEM_CONTRACTS: &[(Address, &str)] = &[(
+    address!("00000000219ab540356cBB839Cbe05303d7705Fa"),
+    "DepositContract",
+)];
+
+/// OP Stack predeploys
+const OP_STACK_SYSTEM_CONTRACTS: &[(Address, &str)] = &[
+    (
+        address!("4200000000000000000000000000000000000007"),
+        "L2CrossDomainMessenger",
+    ),
+    (
+        address!("420000000000000000000000000000000000000F"),
+        "GasPriceOracle",
+    ),
+    (
+        address!("4200000000000000000000000000000000000010"),
+        "L2StandardBridge",
+    ),
+    (
+        address!("4200000000000000000000000000000000000015"),
+        "L1Block",
+    ),
+    (
+        address!("4200000000000000000000000000000000000016"),
+        "L2ToL1MessagePasser",
+    ),
+];
+
+/// Arbitrum precompiles, implemented natively by the node rather than as EVM code
+const ARBITRUM_SYSTEM_CONTRACTS: &[(Address, &str)] = &[
+    (
+        address!("0000000000000000000000000000000000000064"),
+        "ArbSys",
+    ),
+    (
+        address!("000000000000000000000000000000000000006C"),
+        "ArbGasInfo",
+    ),
+    (
+        address!("000000000000000000000000000000000000006E"),
+        "ArbRetryableTx",
+    ),
+    (
+        address!("00000000000000000000000000000000000000C8"),
+        "NodeInterface",
+    ),
+];
+
+/// System contract tables of a chain, chain-specific ones last
+fn system_contracts(chain_id: u64) -> impl Iterator<Item = &'static (Address, &'static str)> {
+    let specific: &[(Address, &str)] = match chain_id {
+        1 => MAINNET_SYSTEM_CONTRACTS,
+        10 | 8453 => OP_STACK_SYSTEM_CONTRACTS,
+        42161 => ARBITRUM_SYSTEM_CONTRACTS,
+        _ => &[],
+    };
+    COMMON_SYSTEM_CONTRACTS.iter().chain(specific)
+}
+
+/// Returns the name of the system contract at `address` on `chain_id`, if any
+///
+/// Covers the EIP-4788, EIP-2935, EIP-7002 and EIP-7251 contracts on every chain, plus
+/// the mainnet deposit contract, OP Stack predeploys (Optimism, Base) and Arbitrum
+/// precompiles.
+///
+/// ```
+/// use alloy::primitives::address;
+/// use revm_trace::chains::system_contract_name;
+///
+/// let arb_sys = address!("0000000000000000000000000000000000000064");
+/// assert_eq!(system_contract_name(42161, arb_sys), Some("ArbSys"));
+/// assert_eq!(system_contract_name(1, arb_sys), None);
+/// ```
+pub fn system_contract_name(chain_id: u64, address: Address) -> Option<&'static str> {
+    system_contracts(chain_id)
+        .find(|(known, _)| *known == address)
+        .map(|(_, name)| *name)
+}
+
+/// Resolves a system contract name to its registry entry
+pub(crate) fn known_system_contract_name(name: &str) -> Option<&'static str> {
+    [
+        COMMON_SYSTEM_CONTRACTS,
+        MAINNET_SYSTEM_CONTRACTS,
+        OP_STACK_SYSTEM_CONTRACTS,
+        ARBITRUM_SYSTEM_CONTRACTS,
+    ]
+    .into_iter()
+    .flatten()
+    .map(|(_, known)| *known)
+    .find(|known| *known == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_tx_handling() {
+        assert!(SystemTxHandling::Skip.admit(2).unwrap());
+        assert!(SystemTxHandling::Error.admit(0).unwrap());
+        assert!(!SystemTxHandling::Skip.admit(DEPOSIT_TX_TYPE).unwrap());
+        assert!(SystemTxHandling::ApplyAsStateOverride.admit(2).unwrap());
+        assert!(!SystemTxHandling::ApplyAsStateOverride
+            .admit(DEPOSIT_TX_TYPE)
+            .unwrap());
+        assert!(matches!(
+            SystemTxHandling::Error.admit(DEPOSIT_TX_TYPE),
+            Err(RuntimeError::UnsupportedTransaction(_))
+        ));
+    }
+
+    /// Packed Ecotone calldata with every field set to a distinct value
+    fn ecotone_input(selector: [u8; 4]) -> Vec<u8> {
+        let mut input = selector.to_vec();
+        input.extend(0x1111_2222u32.to_be_bytes()); // baseFeeScalar
+        input.extend(0x3333_4444u32.to_be_bytes()); // blobBaseFeeScalar
+        input.extend(5u64.to_be_bytes()); // sequenceNumber
+        input.extend(1_700_000_000u64.to_be_bytes()); // timestamp
+        input.extend(19_000_000u64.to_be_bytes()); // number
+        input.extend(U256::from(30).to_be_bytes::<32>()); // basefee
+        input.extend(U256::from(1).to_be_bytes::<32>()); // blobBaseFee
+        input.extend([0xaa; 32]); // hash
+        input.extend([0xbb; 32]); // batcherHash
+        input
+    }
+
+    #[test]
+    fn test_l1_attributes_storage_ecotone() {
+        let slots = l1_attributes_storage(&ecotone_input(SET_L1_BLOCK_VALUES_ECOTONE)).unwrap();
+        let packed_scalars =
+            (U256::from(0x1111_2222u32) << 96) | (U256::from(0x3333_4444u32) << 64) | U256::from(5);
+        assert_eq!(
+            slots,
+            vec![
+                (U256::from(3), packed_scalars),
+                (
+                    U256::ZERO,
+                    (U256::from(1_700_000_000u64) << 64) | U256::from(19_000_000u64)
+                ),
+                (U256::from(1), U256::from(30)),
+                (U256::from(7), U256::from(1)),
+                (U256::from(2), U256::from_be_bytes([0xaa; 32])),
+                (U256::from(4), U256::from_be_bytes([0xbb; 32])),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_l1_attributes_storage_isthmus() {
+        let mut input = ecotone_input(SET_L1_BLOCK_VALUES_ISTHMUS);
+        assert!(
+            l1_attributes_storage(&input).is_none(),
+            "operator fee missing"
+        );
+        input.extend(7u32.to_be_bytes()); // operatorFeeScalar
+        input.extend(9u64.to_be_bytes()); // operatorFeeConstant
+        let slots = l1_attributes_storage(&input).unwrap();
+        assert_eq!(slots.len(), 7);
+        assert_eq!(
+            slots[6],
+            (U256::from(8), (U256::from(7) << 64) | U256::from(9))
+        );
+    }
+
+    #[test]
+    fn test_l1_attributes_storage_bedrock() {
+        let mut input = SET_L1_BLOCK_VALUES.to_vec();
+        for arg in [
+            19_000_000u64,
+            1_700_000_000,
+            30,
+            0xaa,
+            5,
+            0xbb,
+            188,
+            684_000,
+        ] {
+            input.extend(U256::from(arg).to_be_bytes::<32>());
+        }
+        let slots = l1_attributes_storage(&input).unwrap();
+        assert_eq!(
+            slots[0],
+            (
+                U256::ZERO,
+                (U256::from(1_700_000_000u64) << 64) | U256::from(19_000_000u64)
+            )
+        );
+        assert_eq!(slots[3], (U256::from(3), U256::from(5)));
+        assert_eq!(slots[6], (U256::from(6), U256::from(684_000)));
+        assert!(l1_attributes_storage(&input[..100]).is_none());
+        assert!(l1_attributes_storage(&[0xde, 0xad, 0xbe, 0xef]).is_none());
+    }
+}
@@ -0,0 +1,463 @@
+//! Differential simulation of one batch under two scenarios
+//!
+//! Evaluating a parameter change (a governance proposal, an upgrade) comes down to
+//! running the same transactions against the current and the proposed state and
+//! looking at what differs. A [`Scenario`] is a block height plus optional state
+//! overrides; [`compare`] runs a batch under two scenarios on separate EVMs and
+//! reports per transaction:
+//!
+//! - status changes (success, failure, not executed)
+//! - gas used deltas
+//! - transfers present in one run only, or with different amounts (matched by token,
+//!   sender, recipient and token ID)
+//! - storage slots whose final values differ
+//! - logs added or removed (matched by emitter and topics)
+//!
+//! Values that legitimately differ between runs, like stored timestamps, are excluded
+//! with [`IgnoreRule`]s.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! use alloy::primitives::{address, U256};
+//! use revm_trace::{
+//!     differential::{compare, Scenario},
+//!     types::StateOverride,
+//!     SimulationBatch,
+//! };
+//!
+//! # let batch: SimulationBatch = unimplemented!();
+//! let pool = address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640");
+//! let proposed = StateOverride {
+//!     storages: [(pool, vec![(U256::from(4), U256::from(500))])].into(),
+//!     balances: Default::default(),
+//! };
+//! let report = compare(
+//!     "https://eth.llamarpc.com",
+//!     &batch,
+//!     &Scenario::at_block(18_000_000),
+//!     &Scenario::at_block(18_000_000).with_overrides(proposed),
+//!     &[],
+//! )
+//! .await?;
+//! for tx in &report.txs {
+//!     println!("tx {}: {} transfer deltas", tx.tx_index, tx.transfers.len());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+
+use crate::{
+    errors::EvmError,
+    evm::TraceEvm,
+    inspectors::tx_inspector::TxInspector,
+    report::SimulationReport,
+    traits::TransactionTrace,
+    types::{SimulationBatch, StateOverride},
+};
+use alloy::primitives::{Address, Log, B256, U256};
+use revm::database::{CacheDB, DatabaseRef};
+use serde::Serialize;
+
+/// State a batch runs against
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Scenario {
+    /// Block to fork at, latest if `None`
+    ///
+    /// Only used by [`compare`] to build the EVM; [`compare_evms`] takes the EVMs as
+    /// they are.
+    pub block_number: Option<u64>,
+    /// Overrides applied on top of the batch's own
+    #[serde(skip)]
+    pub overrides: Option<StateOverride>,
+}
+
+impl Scenario {
+    /// Scenario at `block_number` without overrides
+    pub fn at_block(block_number: u64) -> Self {
+        Self {
+            block_number: Some(block_number),
+            overrides: None,
+        }
+    }
+
+    /// Adds state overrides to the scenario
+    pub fn with_overrides(mut self, overrides: StateOverride) -> Self {
+        self.overrides = Some(overrides);
+        self
+    }
+}
+
+/// Difference the comparison disregards
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IgnoreRule {
+    /// Final values of a storage slot, or of all slots of the contract if `slot` is
+    /// `None`
+    Slot {
+        address: Address,
+        slot: Option<U256>,
+    },
+    /// Logs matching the emitter and first topic, when given
+    Log {
+        address: Option<Address>,
+        topic0: Option<B256>,
+    },
+    /// Gas used deltas
+    GasUsed,
+}
+
+impl IgnoreRule {
+    fn ignores_slot(&self, address: Address, slot: U256) -> bool {
+        matches!(self, Self::Slot { address: ignored, slot: ignored_slot }
+            if *ignored == address && ignored_slot.is_none_or(|ignored| ignored == slot))
+    }
+
+    fn ignores_log(&self, log: &Log) -> bool {
+        matches!(self, Self::Log { address, topic0 }
+            if address.is_none_or(|address| log.address == address)
+                && topic0.is_none_or(|topic0| log.topics().first() == Some(&topic0)))
+    }
+}
+
+/// Outcome of one transaction in one run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum TxStatus {
+    Success,
+    /// Reverted or halted, with the decoded reason
+    Failed(Option<String>),
+    /// Could not be executed, with the error
+    NotExecuted(String),
+}
+
+/// Transfers between the same parties of the same token in both runs
+///
+/// Amounts are summed per run; `None` means the run has no such transfer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TransferDelta {
+    pub token: Address,
+    pub from: Address,
+    pub to: Option<Address>,
+    /// Token ID for NFTs
+    pub id: Option<U256>,
+    pub a: Option<U256>,
+    pub b: Option<U256>,
+}
+
+/// Final value of a storage slot in both runs; `None` if the run did not change it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SlotDelta {
+    pub address: Address,
+    pub slot: U256,
+    pub a: Option<U256>,
+    pub b: Option<U256>,
+}
+
+/// Differences of one transaction between the two runs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TxDiff {
+    /// Index of the transaction in the batch
+    pub tx_index: usize,
+    /// Outcome in run A and run B, if they differ
+    pub status: Option<(TxStatus, TxStatus)>,
+    /// Gas used in run A and run B, if both executed and the amounts differ
+    pub gas_used: Option<(u64, u64)>,
+    /// Transfers whose summed amounts differ
+    pub transfers: Vec<TransferDelta>,
+    /// Slots whose final values differ
+    pub storage: Vec<SlotDelta>,
+    /// Logs of run B without a counterpart in run A
+    pub logs_added: Vec<Log>,
+    /// Logs of run A without a counterpart in run B
+    pub logs_removed: Vec<Log>,
+}
+
+impl TxDiff {
+    /// Whether the transaction behaved the same in both runs
+    pub fn is_empty(&self) -> bool {
+        self.status.is_none()
+            && self.gas_used.is_none()
+            && self.transfers.is_empty()
+            && self.storage.is_empty()
+            && self.logs_added.is_empty()
+            && self.logs_removed.is_empty()
+    }
+}
+
+/// Result of a differential simulation
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffReport {
+    pub scenario_a: Scenario,
+    pub scenario_b: Scenario,
+    /// Transactions that behaved differently, in batch order
+    pub txs: Vec<TxDiff>,
+}
+
+impl DiffReport {
+    /// Whether every transaction behaved the same under both scenarios
+    pub fn is_identical(&self) -> bool {
+        self.txs.is_empty()
+    }
+}
+
+/// Runs `batch` under both scenarios on fresh EVMs forked from `rpc_url` and diffs
+/// the outcomes
+#[cfg(any(feature = "default", feature = "rustls-tls"))]
+pub async fn compare(
+    rpc_url: &str,
+    batch: &SimulationBatch,
+    scenario_a: &Scenario,
+    scenario_b: &Scenario,
+    ignore: &[IgnoreRule],
+) -> Result<DiffReport, EvmError> {
+    let build = |scenario: &Scenario| {
+        let mut builder = crate::EvmBuilder::new_alloy(rpc_url).with_tracer(TxInspector::new());
+        if let Some(block_number) = scenario.block_number {
+            builder = builder.with_block_number(block_number);
+        }
+        builder.build()
+    };
+    let mut evm_a = build(scenario_a).await?;
+    let mut evm_b = build(scenario_b).await?;
+    Ok(compare_evms(
+        &mut evm_a, scenario_a, &mut evm_b, scenario_b, batch, ignore,
+    ))
+}
+
+/// Runs `batch` with the overrides of each scenario on the given EVMs and diffs the
+/// outcomes
+///
+/// The EVMs are used at their current block, so they can differ in block environment
+/// (number, timestamp, base fee) as well as in state.
+pub fn compare_evms<DB: DatabaseRef>(
+    evm_a: &mut TraceEvm<CacheDB<DB>, TxInspector>,
+    scenario_a: &Scenario,
+    evm_b: &mut TraceEvm<CacheDB<DB>, TxInspector>,
+    scenario_b: &Scenario,
+    batch: &SimulationBatch,
+    ignore: &[IgnoreRule],
+) -> DiffReport {
+    let run_a = run(evm_a, batch, scenario_a);
+    let run_b = run(evm_b, batch, scenario_b);
+    DiffReport {
+        scenario_a: scenario_a.clone(),
+        scenario_b: scenario_b.clone(),
+        txs: diff_runs(&run_a, &run_b, ignore),
+    }
+}
+
+/// Diffs two runs of the same batch, one entry per transaction
+///
+/// Only transactions that behaved differently are returned.
+pub fn diff_runs(
+    run_a: &[Result<SimulationReport, EvmError>],
+    run_b: &[Result<SimulationReport, EvmError>],
+    ignore: &[IgnoreRule],
+) -> Vec<TxDiff> {
+    run_a
+        .iter()
+        .zip(run_b)
+        .enumerate()
+        .map(|(tx_index, (a, b))| diff_tx(tx_index, a, b, ignore))
+        .filter(|diff| !diff.is_empty())
+        .collect()
+}
+
+/// Traces `batch` with the scenario's overrides applied on top of the batch's own
+fn run<DB: DatabaseRef>(
+    evm: &mut TraceEvm<CacheDB<DB>, TxInspector>,
+    batch: &SimulationBatch,
+    scenario: &Scenario,
+) -> Vec<Result<SimulationReport, EvmError>> {
+    let mut batch = batch.clone();
+    if let Some(overrides) = scenario.overrides.clone() {
+        let merged = batch.overrides.get_or_insert_with(Default::default);
+        for (address, slots) in overrides.storages {
+            merged.storages.entry(address).or_default().extend(slots);
+        }
+        merged.balances.extend(overrides.balances);
+    }
+    let transactions = batch.transactions.clone();
+    transactions
+        .into_iter()
+        .zip(evm.trace_transactions(batch))
+        .map(|(tx, result)| {
+            result.map(|(result, storage_diff, trace)| {
+                SimulationReport::new(tx, &result, storage_diff, trace)
+            })
+        })
+        .collect()
+}
+
+fn diff_tx(
+    tx_index: usize,
+    a: &Result<SimulationReport, EvmError>,
+    b: &Result<SimulationReport, EvmError>,
+    ignore: &[IgnoreRule],
+) -> TxDiff {
+    let (status_a, status_b) = (status(a), status(b));
+    let mut diff = TxDiff {
+        tx_index,
+        status: (status_a != status_b).then_some((status_a, status_b)),
+        gas_used: None,
+        transfers: Vec::new(),
+        storage: Vec::new(),
+        logs_added: Vec::new(),
+        logs_removed: Vec::new(),
+    };
+    let empty = SimulationReport::default();
+    let (report_a, report_b) = (a.as_ref().unwrap_or(&empty), b.as_ref().unwrap_or(&empty));
+    if a.is_ok()
+        && b.is_ok()
+        && report_a.summary.gas_used != report_b.summary.gas_used
+        && !ignore.contains(&IgnoreRule::GasUsed)
+    {
+        diff.gas_used = Some((report_a.summary.gas_used, report_b.summary.gas_used));
+    }
+
+    diff.transfers = join(transfer_totals(report_a), transfer_totals(report_b))
+        .map(|((token, from, to, id), a, b)| TransferDelta {
+            token,
+            from,
+            to,
+            id,
+            a,
+            b,
+        })
+        .collect();
+    diff.storage = join(final_slots(report_a), final_slots(report_b))
+        .filter(|((address, slot), ..)| {
+            !ignore.iter().any(|rule| rule.ignores_slot(*address, *slot))
+        })
+        .map(|((address, slot), a, b)| SlotDelta {
+            address,
+            slot,
+            a,
+            b,
+        })
+        .collect();
+
+    // Match logs by emitter and topics, as a multiset
+    let key = |log: &Log| (log.address, log.topics().to_vec());
+    let kept = |log: &&Log| !ignore.iter().any(|rule| rule.ignores_log(log));
+    let mut unmatched: Vec<&Log> = report_a.trace.logs.iter().filter(kept).collect();
+    for log in report_b.trace.logs.iter().filter(kept) {
+        match unmatched.iter().position(|other| key(other) == key(log)) {
+            Some(position) => {
+                unmatched.remove(position);
+            }
+            None => diff.logs_added.push(log.clone()),
+        }
+    }
+    diff.logs_removed = unmatched.into_iter().cloned().collect();
+    diff
+}
+
+fn status(result: &Result<SimulationReport, EvmError>) -> TxStatus {
+    match result {
+        Ok(report) if report.summary.success => TxStatus::Success,
+        Ok(report) => TxStatus::Failed(report.summary.error.clone()),
+        Err(e) => TxStatus::NotExecuted(e.to_string()),
+    }
+}
+
+type TransferKey = (Address, Address, Option<Address>, Option<U256>);
+
+fn transfer_totals(report: &SimulationReport) -> BTreeMap<TransferKey, U256> {
+    let mut totals = BTreeMap::new();
+    for transfer in &report.trace.asset_transfers {
+        let total: &mut U256 = totals
+            .entry((transfer.token, transfer.from, transfer.to, transfer.id))
+            .or_default();
+        *total = total.saturating_add(transfer.value);
+    }
+    totals
+}
+
+fn final_slots(report: &SimulationReport) -> BTreeMap<(Address, U256), U256> {
+    report
+        .storage_diff
+        .iter()
+        .flat_map(|(address, accesses)| {
+            accesses
+                .iter()
+                .map(|access| ((*address, access.slot), access.new_value))
+        })
+        .collect()
+}
+
+/// Pairs the values of both maps by key, keeping the keys whose values differ
+fn join<K: Ord + Clone, V: PartialEq + Copy>(
+    a: BTreeMap<K, V>,
+    b: BTreeMap<K, V>,
+) -> impl Iterator<Item = (K, Option<V>, Option<V>)> {
+    let mut keys: Vec<K> = a.keys().chain(b.keys()).cloned().collect();
+    keys.sort();
+    keys.dedup();
+    keys.into_iter().filter_map(move |key| {
+        let (value_a, value_b) = (a.get(&key).copied(), b.get(&key).copied());
+        (value_a != value_b).then_some((key, value_a, value_b))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{address, LogData};
+
+    const CONTRACT: Address = address!("1000000000000000000000000000000000000001");
+
+    fn log(topic: u8) -> Log {
+        Log {
+            address: CONTRACT,
+            data: LogData::new_unchecked(vec![B256::with_last_byte(topic)], Default::default()),
+        }
+    }
+
+    #[test]
+    fn test_logs_match_as_multiset() {
+        let report = |logs: Vec<Log>| {
+            let mut report = SimulationReport::default();
+            report.summary.success = true;
+            report.trace.logs = logs;
+            Ok(report)
+        };
+        let diffs = diff_runs(
+            &[report(vec![log(1), log(1), log(2)])],
+            &[report(vec![log(1), log(3)])],
+            &[],
+        );
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].logs_added, vec![log(3)]);
+        assert_eq!(diffs[0].logs_removed, vec![log(1), log(2)]);
+
+        let ignore_all = [IgnoreRule::Log {
+            address: Some(CONTRACT),
+            topic0: None,
+        }];
+        assert!(diff_runs(
+            &[report(vec![log(1)])],
+            &[report(vec![log(2)])],
+            &ignore_all
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_status_change() {
+        let success = {
+            let mut report = SimulationReport::default();
+            report.summary.success = true;
+            Ok(report)
+        };
+        let diffs = diff_runs(
+            &[success],
+            &[Err(EvmError::BatchAborted { failed_index: 0 })],
+            &[IgnoreRule::GasUsed],
+        );
+        assert!(matches!(
+            &diffs[0].status,
+            Some((TxStatus::Success, TxStatus::NotExecuted(_)))
+        ));
+    }
+}
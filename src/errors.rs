@@ -6,6 +6,8 @@
 //! - Token-related errors
 //! - Error conversion and propagation
 
+use alloy::primitives::Address;
+use revm::context_interface::result::InvalidTransaction;
 use thiserror::Error;
 
 /// Top-level error type for the EVM tracing system
@@ -29,6 +31,19 @@ pub enum EvmError {
     /// Errors related to override operations
     #[error("Override error: {0}")]
     OverrideError(String),
+
+    /// Errors related to ENS name resolution
+    #[cfg(feature = "ens")]
+    #[error("ENS error: {0}")]
+    Ens(#[from] EnsError),
+
+    /// Errors replaying simulations on a local development node
+    #[error("Local node error: {0}")]
+    LocalNode(#[from] LocalNodeError),
+
+    /// Transaction skipped because an earlier one failed under `FailurePolicy::Abort`
+    #[error("Batch aborted after transaction {failed_index} failed")]
+    BatchAborted { failed_index: usize },
 }
 
 /// Initialization-specific errors
@@ -64,6 +79,14 @@ pub enum InitError {
     /// Errors related to block not found
     #[error("Block not found: {0}")]
     BlockNotFound(String),
+
+    /// The node cannot resolve a block tag, e.g. `finalized` on a chain without finality
+    #[error("Unsupported block tag: {0}")]
+    UnsupportedBlockTag(String),
+
+    /// The endpoint is not a supported development node (Anvil, Hardhat)
+    #[error("Not a development node: {0}")]
+    NotDevNode(String),
 }
 
 /// Runtime execution errors
@@ -99,6 +122,75 @@ pub enum RuntimeError {
     /// Errors decoding data from the EVM
     #[error("Failed to decode data: {0}")]
     DecodeError(String),
+
+    /// Invalid user-supplied input (bytecode, arguments, parameters)
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    /// The storage slot holding a token balance could not be located
+    #[error("Balance slot not found: {0}")]
+    BalanceSlotNotFound(String),
+
+    /// A permit could not be synthesized or signed for the token
+    #[error("Permit failed: {0}")]
+    PermitFailed(String),
+
+    /// The transaction type cannot be represented as a `SimulationTx`
+    #[error("Unsupported transaction: {0}")]
+    UnsupportedTransaction(String),
+
+    /// The node does not serve state for the block (typically a non-archive node)
+    #[error("Historical state unavailable at block {block}")]
+    HistoricalStateUnavailable { block: u64 },
+
+    /// Calldata sent to an account without code under `EmptyCodeCallPolicy::Error`
+    #[error("Call with data to {address}, which has no code")]
+    CallToEmptyAccount { address: Address },
+
+    /// An inspector hook panicked while tracing the transaction
+    #[error("Inspector panicked: {message}")]
+    InspectorPanic { message: String },
+
+    /// The transaction failed pre-execution validation (nonce, fees, gas limit)
+    #[error("Invalid transaction: {0}")]
+    InvalidTransaction(InvalidTransaction),
+
+    /// The batch's `CancellationToken` was cancelled before the transaction finished
+    #[error("Simulation cancelled")]
+    Cancelled,
+
+    /// A `TargetRef::CreatedBy` target could not be resolved, so the transaction did
+    /// not execute
+    #[error("No contract created by transaction {tx_index}: {reason}")]
+    UnresolvedTarget {
+        tx_index: usize,
+        reason: UnresolvedTargetReason,
+    },
+
+    /// A frame of a static call attempted to modify state
+    #[error("State mutation by {opcode} in static call at trace address {trace_address:?}")]
+    StateMutationInStaticCall {
+        opcode: String,
+        trace_address: Vec<usize>,
+    },
+
+    /// The block of the EVM changed since the `BatchSession` executed its prefix
+    #[error("Batch session invalidated: the block changed since the prefix ran")]
+    SessionInvalidated,
+}
+
+/// Why a `TargetRef::CreatedBy` target could not be resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum UnresolvedTargetReason {
+    /// The referenced transaction does not come before the referencing one
+    #[error("not executed earlier in the batch")]
+    NotExecutedBefore,
+    /// The referenced transaction is not a contract creation
+    #[error("not a contract creation")]
+    NotACreation,
+    /// The referenced creation reverted, halted or could not be executed
+    #[error("the creation failed")]
+    Failed,
 }
 
 #[derive(Debug, Error)]
@@ -198,3 +290,224 @@ pub enum TokenError {
     #[error("Token call reverted for {address}")]
     CallReverted { address: String },
 }
+
+/// Pool state query errors
+///
+/// Reported per pool by `utils::defi_utils`, so one broken entry does not fail the
+/// whole snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PoolError {
+    /// Pool call reverted
+    ///
+    /// # Fields
+    /// * `address` - Pool contract address
+    /// * `call` - Name of the reverted function
+    #[error("Pool call {call} reverted for {address}")]
+    CallReverted { address: String, call: String },
+
+    /// Failed to decode a pool call result
+    ///
+    /// # Fields
+    /// * `address` - Pool contract address
+    /// * `call` - Name of the called function
+    /// * `reason` - Detailed error message
+    #[error("Failed to decode {call} for pool {address}: {reason}")]
+    Decode {
+        address: String,
+        call: String,
+        reason: String,
+    },
+}
+
+/// Multi-chain scenario errors
+///
+/// These errors abort a `multichain::ScenarioRunner` run; steps are numbered from 0.
+#[derive(Debug, Clone, Error)]
+pub enum ScenarioError {
+    /// A step names a chain the runner does not have
+    #[error("Step {step}: unknown chain {chain}")]
+    UnknownChain { step: usize, chain: String },
+
+    /// A step uses a placeholder no earlier step extracted
+    #[error("Step {step}: no value extracted for placeholder {name}")]
+    UnresolvedPlaceholder { step: usize, name: String },
+
+    /// A step's extractor found nothing to extract
+    #[error("Step {step}: failed to extract {name}: {reason}")]
+    Extraction {
+        step: usize,
+        name: String,
+        reason: String,
+    },
+
+    /// A transaction of a step could not be executed
+    #[error("Step {step}, transaction {tx_index}: {source}")]
+    Execution {
+        step: usize,
+        tx_index: usize,
+        source: EvmError,
+    },
+}
+
+/// Transaction conversion errors
+///
+/// These errors occur while turning alloy transaction requests or signed
+/// transactions into a `SimulationTx`.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ConversionError {
+    /// The request does not name a sender (`from`)
+    #[error("Transaction request has no sender")]
+    MissingSender,
+
+    /// The transaction type cannot be represented by a `SimulationTx`
+    #[error("Unsupported transaction type {0:#04x}")]
+    UnsupportedType(u8),
+
+    /// The signer could not be recovered from the signature
+    #[error("Failed to recover transaction signer: {0}")]
+    InvalidSignature(String),
+
+    /// The raw transaction could not be decoded
+    #[error("Failed to decode transaction: {0}")]
+    Decode(String),
+}
+
+/// Errors turning a simulation report into a transaction request
+///
+/// These errors occur in `SimulationReport::to_transaction_request`.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TransactionRequestError {
+    /// The simulated transaction failed and failed reports were not allowed
+    #[error("Simulated transaction failed: {}", reason.as_deref().unwrap_or("no reason"))]
+    FailedSimulation {
+        /// Decoded revert or halt reason of the simulation
+        reason: Option<String>,
+    },
+
+    /// The report has no executed transaction environment to take nonce and chain
+    /// ID from
+    #[error("Report has no executed transaction environment")]
+    MissingExecutedTx,
+
+    /// The calldata of the report differs from the executed one, e.g. because the tx
+    /// hook replaced it
+    #[error("Calldata of the report does not match the executed calldata")]
+    CalldataMismatch,
+}
+
+/// ENS resolution errors
+///
+/// These errors occur while resolving names through `utils::ens_utils`.
+#[cfg(feature = "ens")]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum EnsError {
+    /// ENS is only deployed on Ethereum mainnet
+    #[error("ENS is not supported on chain {chain_id}")]
+    EnsUnsupported { chain_id: u64 },
+
+    /// The name is empty or has an empty label
+    #[error("Invalid ENS name: {0}")]
+    InvalidName(String),
+
+    /// The name has no resolver or its resolver has no address for it
+    #[error("ENS name not resolved: {0}")]
+    NotResolved(String),
+
+    /// A registry or resolver call could not be executed
+    #[error("ENS call failed: {0}")]
+    Call(String),
+}
+
+/// Local development node errors
+///
+/// These errors occur while replaying a simulated batch on a node through
+/// `utils::local_node_utils::LocalNodeBridge`.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum LocalNodeError {
+    /// The number of simulated results does not match the batch
+    #[error("Expected {expected} simulated results, got {actual}")]
+    ResultCount { expected: usize, actual: usize },
+
+    /// State overrides cannot be replayed on the node
+    #[error("Batches with state overrides cannot be applied to a node")]
+    OverridesUnsupported,
+
+    /// A node RPC call failed
+    #[error("{method} failed: {reason}")]
+    Rpc { method: String, reason: String },
+}
+
+/// Storage layout errors
+///
+/// These errors occur while parsing layouts with `layout::StorageLayout`.
+#[cfg(feature = "storage-layout")]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum LayoutError {
+    /// The layout is not valid storage layout JSON
+    #[error("Invalid storage layout: {0}")]
+    Parse(String),
+
+    /// A variable or type refers to a type the layout does not define
+    #[error("Unknown type {type_id} of {label}")]
+    UnknownType { label: String, type_id: String },
+}
+
+/// Simulation API request validation error
+///
+/// Names the offending field by its path in the request, e.g.
+/// `transactions[2].value: invalid hex quantity`.
+#[cfg(feature = "serde-api")]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{field}: {reason}")]
+pub struct ApiFieldError {
+    /// Path of the field in the request
+    pub field: String,
+    /// What is wrong with its value
+    pub reason: String,
+}
+
+/// Signature database errors
+///
+/// These errors occur while loading a selector/topic signature database.
+#[cfg(feature = "signatures")]
+#[derive(Debug, Clone, Error)]
+pub enum SignatureError {
+    /// The database file could not be read
+    #[error("Failed to read signature database {path}: {reason}")]
+    Io { path: String, reason: String },
+
+    /// An entry could not be parsed
+    #[error("Invalid signature entry {entry}: {reason}")]
+    Parse { entry: String, reason: String },
+}
+
+/// Label book errors
+///
+/// These errors occur while loading a known-contract label book.
+#[cfg(feature = "labels")]
+#[derive(Debug, Clone, Error)]
+pub enum LabelError {
+    /// The label file could not be read
+    #[error("Failed to read label book {path}: {reason}")]
+    Io { path: String, reason: String },
+
+    /// An entry could not be parsed
+    #[error("Invalid label entry {entry}: {reason}")]
+    Parse { entry: String, reason: String },
+}
+
+/// State import errors
+///
+/// These errors occur while loading an Anvil state dump or a genesis alloc. Entries
+/// that cannot be read are skipped and reported in the import summary instead.
+#[cfg(feature = "state-import")]
+#[derive(Debug, Clone, Error)]
+pub enum StateImportError {
+    /// The document is not valid JSON of the expected format
+    #[error("Invalid state document: {0}")]
+    Parse(String),
+
+    /// An account could not be written to the `CacheDB` overlay
+    #[error("Failed to insert account {address}: {reason}")]
+    Insert { address: Address, reason: String },
+}
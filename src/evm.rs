@@ -8,6 +8,11 @@
 //! - **`TraceEvm`**: Main wrapper struct that adds tracing capabilities to revm's EVM
 //! - **Database Reset**: Utilities for clearing cache state between executions
 //! - **Inspector Integration**: Support for transaction tracing and analysis
+//! - **Block Advance**: Moving the executing block forward in long-running sessions
+//! - **Transaction Hooks**: Last-mile edits of the transaction environment before execution
+//! - **Configuration**: Introspection of the network, checks and block fields, and setters
+//! - **Static Calls**: View-safety checks running arbitrary calldata under STATICCALL rules
+//! - **Simulation Cache**: Memoized reports of identical batches (feature = "sim-cache")
 //!
 //! ## Usage Examples
 //!
@@ -29,6 +34,11 @@
 //! # }
 //! ```
 
+use crate::{
+    types::{BlockContext, CancellationToken, EmptyCodeCallPolicy, ExecutedTxEnv},
+    DbStats, RemoteFetch,
+};
+use alloy::primitives::Address;
 pub use revm::{
     context_interface::ContextTr,
     database::Database,
@@ -36,14 +46,36 @@ pub use revm::{
     inspector::{Inspector, NoOpInspector},
     MainnetEvm,
 };
-use std::ops::{Deref, DerefMut};
+use revm::{
+    database::{CacheDB, DatabaseRef},
+    Context, MainBuilder, MainContext,
+};
+use std::{
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicU64, Ordering},
+};
 
 // Sub-modules for EVM functionality
+pub mod access_list;
+pub mod blocks;
 #[cfg(any(feature = "default", feature = "rustls-tls"))]
 pub mod builder;
+#[cfg(feature = "sim-cache")]
+pub mod cache;
+pub mod chunking;
+pub mod config;
+pub mod fees;
+pub mod hooks;
 pub mod inspector;
+pub mod metrics;
 pub mod processor;
+#[cfg(any(feature = "default", feature = "rustls-tls"))]
+pub mod replay;
 pub mod reset;
+pub mod session;
+pub mod static_call;
+pub mod timeline;
 
 /// Enhanced EVM wrapper with tracing capabilities
 ///
@@ -73,6 +105,7 @@ pub mod reset;
 /// let batch = SimulationBatch {
 ///     transactions: vec![],
 ///     is_stateful: false,
+///     ..Default::default()
 /// };
 ///
 /// // High-level batch processing with automatic state management
@@ -136,7 +169,47 @@ pub mod reset;
 /// # Ok(())
 /// # }
 /// ```
-pub struct TraceEvm<DB: Database, INSP>(MainnetEvm<MainnetContext<DB>, INSP>);
+pub struct TraceEvm<DB: Database, INSP> {
+    /// Underlying revm EVM
+    evm: MainnetEvm<MainnetContext<DB>, INSP>,
+    /// Block the EVM was built against
+    block_context: BlockContext,
+    /// Whether `trace_transactions` records batch metrics
+    metrics_enabled: bool,
+    /// Metrics of the most recent batch, if recorded
+    last_batch_metrics: Option<metrics::BatchMetrics>,
+    /// Fee accounting of the most recent batch, if base fees were enforced
+    last_fee_accounting: Option<fees::FeeAccounting>,
+    /// Environment of the transaction being executed, taken once it finished
+    executed_tx: Option<ExecutedTxEnv>,
+    /// Environments of the transactions of the most recent batch
+    last_executed_txs: Vec<Option<ExecutedTxEnv>>,
+    /// Mutation timeline of the most recent batch, if requested
+    last_timeline: Option<timeline::BatchTimeline>,
+    /// Number of transactions per chunk of a batch, `None` to run batches in one go
+    chunk_size: Option<usize>,
+    /// Cancellation token of the batch being executed
+    cancellation: Option<CancellationToken>,
+    /// Account nonces read or committed during the batch being executed, by address
+    batch_nonces: Option<HashMap<Address, u64>>,
+    /// Reads lookup statistics from the database, when it is instrumented
+    db_stats_source: Option<fn(&DB) -> DbStats>,
+    /// Account and storage lookups of executed transactions the `CacheDB` answered,
+    /// counted while the database is instrumented
+    cache_hits: AtomicU64,
+    /// Takes the upstream lookups logged by the database, when it records them
+    remote_fetch_source: Option<fn(&DB) -> Option<Vec<RemoteFetch>>>,
+    /// Upstream lookups of each transaction of the most recent batch, if recorded
+    last_remote_dependencies: Option<Vec<Vec<RemoteFetch>>>,
+    /// Gas used by committed transactions in the current simulated block
+    block_gas_used: u64,
+    /// Callback editing each transaction environment before execution
+    tx_hook: Option<hooks::TxHook>,
+    /// Handling of calls with data to accounts without code in the current batch
+    empty_code_call_policy: EmptyCodeCallPolicy,
+    /// Database backend the EVM was built with
+    backend: config::BackendKind,
+}
 
 impl<DB, INSP> TraceEvm<DB, INSP>
 where
@@ -175,7 +248,119 @@ where
     /// # }
     /// ```
     pub fn new(evm: MainnetEvm<MainnetContext<DB>, INSP>) -> Self {
-        Self(evm)
+        let block_context = BlockContext {
+            number: evm.ctx.block.number,
+            timestamp: evm.ctx.block.timestamp,
+            chain_id: evm.ctx.cfg.chain_id,
+            ..Default::default()
+        };
+        Self {
+            evm,
+            block_context,
+            metrics_enabled: false,
+            last_batch_metrics: None,
+            last_fee_accounting: None,
+            executed_tx: None,
+            last_executed_txs: Vec::new(),
+            last_timeline: None,
+            chunk_size: None,
+            cancellation: None,
+            batch_nonces: None,
+            db_stats_source: None,
+            cache_hits: AtomicU64::new(0),
+            remote_fetch_source: None,
+            last_remote_dependencies: None,
+            block_gas_used: 0,
+            tx_hook: None,
+            empty_code_call_policy: EmptyCodeCallPolicy::Allow,
+            backend: config::BackendKind::Custom,
+        }
+    }
+
+    /// Attaches the block context resolved at build time
+    pub(crate) fn with_block_context(mut self, block_context: BlockContext) -> Self {
+        self.block_context = block_context;
+        self
+    }
+
+    /// Attaches a reader for the database's lookup statistics, used by batch metrics
+    pub(crate) fn with_db_stats_source(mut self, source: fn(&DB) -> DbStats) -> Self {
+        self.db_stats_source = Some(source);
+        self
+    }
+
+    /// Attaches a reader for the upstream lookups logged by the database
+    pub(crate) fn with_remote_fetch_source(
+        mut self,
+        source: fn(&DB) -> Option<Vec<RemoteFetch>>,
+    ) -> Self {
+        self.remote_fetch_source = Some(source);
+        self
+    }
+
+    /// Takes the upstream lookups logged since the last call, `None` if the database
+    /// does not record them
+    pub(crate) fn take_remote_fetches(&self) -> Option<Vec<RemoteFetch>> {
+        self.remote_fetch_source
+            .and_then(|source| source(&self.evm.ctx.journaled_state.database))
+    }
+
+    /// Returns the block this EVM was built against
+    ///
+    /// Record this alongside simulation results to know exactly which state they
+    /// were computed on. The hash is resolved by the builder for both the by-number
+    /// and by-hash paths; after [`set_db_block`](Self::set_db_block) it is reset to
+    /// `B256::ZERO` since the new block is only known by number.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use revm_trace::create_evm;
+    ///
+    /// let evm = create_evm("https://eth.llamarpc.com").await?;
+    /// let block = evm.block_context();
+    /// println!("simulating on block {} ({})", block.number, block.hash);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn block_context(&self) -> BlockContext {
+        self.block_context
+    }
+
+    /// Updates the recorded block context
+    pub(crate) fn block_context_mut(&mut self) -> &mut BlockContext {
+        &mut self.block_context
+    }
+
+    /// Takes the transaction environments of the most recent batch
+    ///
+    /// One entry per transaction of the batch passed to `trace_transactions`, `None`
+    /// for transactions that failed before execution or were skipped. Attach an entry
+    /// to its report with
+    /// [`SimulationReport::with_executed_tx`](crate::report::SimulationReport::with_executed_tx)
+    /// to make it self-contained.
+    pub fn take_last_executed_txs(&mut self) -> Vec<Option<ExecutedTxEnv>> {
+        std::mem::take(&mut self.last_executed_txs)
+    }
+
+    /// Takes the upstream lookups of each transaction of the most recent batch
+    ///
+    /// One entry per transaction, listing in order the state it fetched from the
+    /// remote node; empty for transactions that fetched nothing or never ran. Returns
+    /// `None` unless recording was enabled (see
+    /// [`record_remote_dependencies`](TraceEvm::record_remote_dependencies)) while the
+    /// batch ran. Lookups made while applying state overrides are not attributed to
+    /// any transaction. Attach an entry to its report with
+    /// [`SimulationReport::with_remote_dependencies`](crate::report::SimulationReport::with_remote_dependencies).
+    pub fn take_last_remote_dependencies(&mut self) -> Option<Vec<Vec<RemoteFetch>>> {
+        self.last_remote_dependencies.take()
+    }
+
+    /// Whether the cancellation token of the batch being executed was cancelled
+    pub(crate) fn cancellation_requested(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
     }
 
     /// Get direct access to the inspector instance
@@ -206,7 +391,8 @@ where
     /// let traces = inspector.get_traces();
     /// let logs = inspector.get_logs();
     ///
-    /// // Advanced error tracing methods
+    /// // Advanced error tracing methods, also available on the output as
+    /// // `error_trace_address` and `TxTraceOutput::error_trace`
     /// let error_addr = inspector.get_error_trace_address();
     /// let error_trace = inspector.find_error_trace();
     ///
@@ -249,7 +435,7 @@ where
     type Target = MainnetEvm<MainnetContext<DB>, INSP>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.evm
     }
 }
 
@@ -262,6 +448,55 @@ where
     DB: Database,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.evm
+    }
+}
+
+/// Independent copy of a `CacheDB`-backed EVM, for branching scenarios in one thread
+///
+/// The copy is rebuilt from the environment (cfg, block and pending transaction), a
+/// deep copy of the `CacheDB` overlay and a clone of the inspector, so its cost grows
+/// with the cached state, like [`snapshot`](TraceEvm::snapshot). Transactions
+/// committed on either side are not seen by the other.
+///
+/// The backend below the overlay is cloned with its own `Clone` implementation, which
+/// for RPC backends such as foundry-fork-db's `SharedBackend` shares the provider
+/// handle: uncached state keeps being fetched from the same source. The transaction
+/// hook is not copied, since boxed closures cannot be cloned; set it again on the copy
+/// if needed.
+impl<DB, INSP> Clone for TraceEvm<CacheDB<DB>, INSP>
+where
+    DB: DatabaseRef + Clone,
+    INSP: Clone,
+{
+    fn clone(&self) -> Self {
+        let ctx = &self.evm.ctx;
+        let evm = Context::mainnet()
+            .with_db(ctx.journaled_state.database.clone())
+            .with_cfg(ctx.cfg.clone())
+            .with_block(ctx.block.clone())
+            .with_tx(ctx.tx.clone())
+            .build_mainnet_with_inspector(self.evm.inspector.clone());
+        Self {
+            evm,
+            block_context: self.block_context,
+            metrics_enabled: self.metrics_enabled,
+            last_batch_metrics: self.last_batch_metrics.clone(),
+            last_fee_accounting: self.last_fee_accounting.clone(),
+            executed_tx: self.executed_tx.clone(),
+            last_executed_txs: self.last_executed_txs.clone(),
+            last_timeline: self.last_timeline.clone(),
+            chunk_size: self.chunk_size,
+            cancellation: self.cancellation.clone(),
+            batch_nonces: self.batch_nonces.clone(),
+            db_stats_source: self.db_stats_source,
+            cache_hits: AtomicU64::new(self.cache_hits.load(Ordering::Relaxed)),
+            remote_fetch_source: self.remote_fetch_source,
+            last_remote_dependencies: self.last_remote_dependencies.clone(),
+            block_gas_used: self.block_gas_used,
+            tx_hook: None,
+            empty_code_call_policy: self.empty_code_call_policy,
+            backend: self.backend,
+        }
     }
 }
@@ -0,0 +1,216 @@
+//! Measuring what an EIP-2930 access list saves
+//!
+//! An access list prepays account and storage accesses at a discount (2400 gas per
+//! address, 1900 per storage key) and makes them warm from the start, so their first
+//! access costs 100 gas instead of 2600 or 2100. Whether a list pays off depends on
+//! what the transaction actually touches. [`TraceEvm::evaluate_access_list`] runs a
+//! transaction with and without a list and reports the difference, attributing it to
+//! the individual entries.
+
+use crate::{
+    errors::EvmError,
+    evm::{
+        processor::{execution_error, transact_error},
+        TraceEvm,
+    },
+    types::{KnownPrecompile, SimulationTx},
+};
+use alloy::{
+    eips::eip2930::AccessList,
+    primitives::{Address, TxKind, B256, U256},
+};
+use revm::{
+    context::{ContextTr, TxEnv},
+    database::{CacheDB, DatabaseRef},
+    primitives::hardfork::SpecId,
+    state::EvmState,
+    ExecuteEvm,
+};
+use serde::Serialize;
+
+/// Gas saved by listing an address that is accessed cold: 2600 - (2400 + 100)
+const ADDRESS_SAVING: i64 = 100;
+/// Gas saved by listing a storage key that is accessed cold: 2100 - (1900 + 100)
+const STORAGE_KEY_SAVING: i64 = 100;
+/// Cost of listing an address
+const ADDRESS_COST: i64 = 2400;
+/// Cost of listing a storage key
+const STORAGE_KEY_COST: i64 = 1900;
+
+/// Outcome of running a transaction with and without an access list
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccessListEvaluation {
+    /// Gas used without the access list
+    pub gas_without: u64,
+    /// Gas used with the access list, including its intrinsic cost
+    pub gas_with: u64,
+    /// `gas_without - gas_with`; negative when the list costs more than it saves
+    pub savings: i64,
+    /// Whether the list lowers the gas used
+    pub list_is_beneficial: bool,
+    /// Contribution of every address and storage key of the list, in list order
+    pub entries: Vec<AccessListEntryUse>,
+}
+
+/// Contribution of one access list entry, see [`AccessListEvaluation`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccessListEntryUse {
+    /// Listed address
+    pub address: Address,
+    /// Listed storage key, `None` for the address itself
+    pub storage_key: Option<B256>,
+    /// Whether the transaction accessed the entry while it would otherwise have been
+    /// cold
+    pub useful: bool,
+    /// Estimated gas saved by the entry: 100 when useful, minus its listing cost
+    /// (2400 for an address, 1900 for a storage key) otherwise
+    pub estimated_savings: i64,
+}
+
+impl<DB, INSP> TraceEvm<CacheDB<DB>, INSP>
+where
+    DB: DatabaseRef,
+{
+    /// Runs `tx` without and with `access_list` and compares the gas used
+    ///
+    /// Both runs execute against the current state (including committed batches)
+    /// and nothing is committed, so they see identical state. The inspector and the
+    /// transaction hook are not used. A legacy transaction is sent as an EIP-2930
+    /// transaction in both runs, so only the list differs.
+    ///
+    /// Entries are attributed by what the run without the list accessed: an address
+    /// or storage key it loaded is useful, unless the address is warm anyway (sender,
+    /// target, precompiles and, from Shanghai on, the coinbase). Entries listed twice
+    /// are charged twice but save once; the estimates do not account for this, nor for
+    /// refund caps or code that branches on remaining gas, so their sum can differ
+    /// from `savings`.
+    ///
+    /// # Gas Configuration
+    ///
+    /// Warm and cold access costs follow the EVM's hardfork (see
+    /// [`config`](Self::config)): lists are rejected before Berlin. The relaxations of
+    /// builder EVMs (base fee, block gas limit, code size, EIP-3607) do not change gas
+    /// accounting, but with the base fee disabled the gas price stays zero, so the
+    /// savings are reported in gas rather than in fees. Build with
+    /// `with_strict_validation(true)` to also check the sender can pay for the list.
+    ///
+    /// # Errors
+    /// - `RuntimeError::InvalidTransaction` if either run fails validation
+    /// - `RuntimeError::ExecutionFailed` if either run cannot be executed
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use alloy::eips::eip2930::{AccessList, AccessListItem};
+    /// use alloy::primitives::{address, Address, B256, TxKind, U256};
+    /// use revm_trace::{create_evm, SimulationTx};
+    ///
+    /// let mut evm = create_evm("https://eth.llamarpc.com").await?;
+    /// let usdc = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+    /// let tx = SimulationTx {
+    ///     caller: Address::repeat_byte(1),
+    ///     transact_to: TxKind::Call(usdc),
+    ///     value: U256::ZERO,
+    ///     data: Default::default(),
+    ///     trace_config: None,
+    ///     return_abi: None,
+    /// };
+    /// let list = AccessList(vec![AccessListItem {
+    ///     address: address!("43506849D7C04F9138D1A2050bbF3A0c054402dd"),
+    ///     storage_keys: vec![B256::ZERO],
+    /// }]);
+    /// let evaluation = evm.evaluate_access_list(tx, list)?;
+    /// println!("saves {} gas", evaluation.savings);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn evaluate_access_list(
+        &mut self,
+        tx: SimulationTx,
+        access_list: AccessList,
+    ) -> Result<AccessListEvaluation, EvmError> {
+        let block = self.block.number;
+        let sender = tx.caller;
+        let kind = tx.transact_to;
+        let nonce = self
+            .db()
+            .basic(sender)
+            .map_err(|e| execution_error(block, "Failed to get account info", e))?
+            .map(|acc| acc.nonce)
+            .unwrap_or_default();
+        let mut env = TxEnv::builder()
+            .caller(sender)
+            .value(tx.value)
+            .data(tx.data)
+            .kind(kind)
+            .nonce(nonce)
+            .chain_id(Some(self.cfg.chain_id))
+            .build_fill();
+        // Legacy transactions cannot carry a list
+        env.tx_type = env.tx_type.max(1);
+
+        let without = self
+            .transact(env.clone())
+            .map_err(|e| transact_error(block, "Execution without access list failed", e))?;
+        env.access_list = access_list.clone();
+        let with = self
+            .transact(env)
+            .map_err(|e| transact_error(block, "Execution with access list failed", e))?;
+
+        let gas_without = without.result.gas_used();
+        let gas_with = with.result.gas_used();
+        let savings = gas_without as i64 - gas_with as i64;
+        let prewarmed = |address: Address| {
+            address == sender
+                || kind == TxKind::Call(address)
+                || KnownPrecompile::from_address(address).is_some()
+                || (address == self.block.beneficiary && self.cfg.spec >= SpecId::SHANGHAI)
+        };
+        let entries = attribute_entries(&access_list, &without.state, prewarmed);
+        Ok(AccessListEvaluation {
+            gas_without,
+            gas_with,
+            savings,
+            list_is_beneficial: savings > 0,
+            entries,
+        })
+    }
+}
+
+/// Rates every entry of `access_list` by the accesses recorded in `state`
+fn attribute_entries(
+    access_list: &AccessList,
+    state: &EvmState,
+    prewarmed: impl Fn(Address) -> bool,
+) -> Vec<AccessListEntryUse> {
+    let mut entries = Vec::new();
+    for item in &access_list.0 {
+        let account = state.get(&item.address);
+        let useful = account.is_some() && !prewarmed(item.address);
+        entries.push(AccessListEntryUse {
+            address: item.address,
+            storage_key: None,
+            useful,
+            estimated_savings: if useful {
+                ADDRESS_SAVING
+            } else {
+                -ADDRESS_COST
+            },
+        });
+        for key in &item.storage_keys {
+            let useful = account
+                .is_some_and(|account| account.storage.contains_key(&U256::from_be_bytes(key.0)));
+            entries.push(AccessListEntryUse {
+                address: item.address,
+                storage_key: Some(*key),
+                useful,
+                estimated_savings: if useful {
+                    STORAGE_KEY_SAVING
+                } else {
+                    -STORAGE_KEY_COST
+                },
+            });
+        }
+    }
+    entries
+}
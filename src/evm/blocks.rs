@@ -0,0 +1,113 @@
+//! Simulated passage of blocks
+//!
+//! Long-running stateful sessions (vesting, interest accrual, auction endings) need to
+//! move the executing block forward between batches. [`TraceEvm::advance_block`] only
+//! moves the block environment transactions observe (`NUMBER`, `TIMESTAMP`, `BASEFEE`);
+//! the database stays pinned to the block the EVM was built against, so state that was
+//! not written by the simulation still resolves at that block.
+
+use crate::evm::TraceEvm;
+use revm::database::Database;
+
+/// EIP-1559 base fee max change denominator
+const BASE_FEE_MAX_CHANGE_DENOMINATOR: u128 = 8;
+/// EIP-1559 elasticity multiplier
+const ELASTICITY_MULTIPLIER: u64 = 2;
+
+/// Computes the base fee of the block following a parent block, per EIP-1559
+///
+/// # Arguments
+/// - `base_fee`: Base fee of the parent block
+/// - `gas_used`: Gas used in the parent block
+/// - `gas_limit`: Gas limit of the parent block
+pub fn next_base_fee(base_fee: u64, gas_used: u64, gas_limit: u64) -> u64 {
+    let gas_target = gas_limit / ELASTICITY_MULTIPLIER;
+    if gas_target == 0 || gas_used == gas_target {
+        return base_fee;
+    }
+    let delta = |gas_delta: u64| {
+        base_fee as u128 * gas_delta as u128 / gas_target as u128 / BASE_FEE_MAX_CHANGE_DENOMINATOR
+    };
+    if gas_used > gas_target {
+        let increase = delta(gas_used - gas_target).max(1);
+        base_fee.saturating_add(increase.min(u64::MAX as u128) as u64)
+    } else {
+        base_fee - delta(gas_target - gas_used) as u64
+    }
+}
+
+impl<DB, INSP> TraceEvm<DB, INSP>
+where
+    DB: Database,
+{
+    /// Advances the executing block by `blocks` blocks of `block_time_secs` seconds each
+    ///
+    /// Increments the block number and timestamp seen by subsequent transactions. When
+    /// base fee enforcement is enabled (`cfg.disable_base_fee == false`), the base fee
+    /// is recomputed per advanced block with the EIP-1559 formula: the first block uses
+    /// the gas of the transactions committed since the previous advance, the following
+    /// ones are treated as empty.
+    ///
+    /// Only the execution context moves. Storage, balances and code that the simulation
+    /// did not write are still read at the pinned fork block, and
+    /// [`block_context`](Self::block_context) keeps reporting that block. Use
+    /// [`set_db_block`](TraceEvm::set_db_block) to re-pin the fork instead.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use revm_trace::{create_evm_with_tracer, TxInspector};
+    ///
+    /// let mut evm = create_evm_with_tracer("https://eth.llamarpc.com", TxInspector::new()).await?;
+    /// // ... evm.trace_transactions(batch) ...
+    /// evm.advance_block(7200, 12); // one day of mainnet blocks
+    /// // ... evm.trace_transactions(next_batch) ...
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn advance_block(&mut self, blocks: u64, block_time_secs: u64) {
+        if blocks == 0 {
+            return;
+        }
+        let block = &mut self.evm.ctx.block;
+        block.number = block.number.saturating_add(blocks);
+        block.timestamp = block
+            .timestamp
+            .saturating_add(blocks.saturating_mul(block_time_secs));
+
+        if !self.evm.ctx.cfg.disable_base_fee {
+            let mut gas_used = self.block_gas_used;
+            for _ in 0..blocks {
+                let next = next_base_fee(block.basefee, gas_used, block.gas_limit);
+                // Once an empty block leaves the fee unchanged, so do all later ones
+                if next == block.basefee && gas_used == 0 {
+                    break;
+                }
+                block.basefee = next;
+                gas_used = 0;
+            }
+        }
+        self.block_gas_used = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_base_fee() {
+        let limit = 30_000_000;
+        // At target the fee is unchanged
+        assert_eq!(
+            next_base_fee(1_000_000_000, 15_000_000, limit),
+            1_000_000_000
+        );
+        // Full and empty blocks move it by 1/8
+        assert_eq!(next_base_fee(1_000_000_000, limit, limit), 1_125_000_000);
+        assert_eq!(next_base_fee(1_000_000_000, 0, limit), 875_000_000);
+        // Any excess raises the fee by at least 1 wei
+        assert_eq!(next_base_fee(1, 15_000_001, limit), 2);
+        assert_eq!(next_base_fee(0, 0, limit), 0);
+    }
+}
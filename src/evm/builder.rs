@@ -85,19 +85,23 @@
 //! - Upstream changes to REVM's Context structure to be Send + Sync
 //! - Alternative error handling that doesn't break thread safety
 //! - Wrapper types that can safely cross thread boundaries
+use self::failover::connect_endpoints;
 use crate::{
-    errors::{EvmError, InitError},
-    types::{AllDBType, AnyNetworkProvider},
-    MyWrapDatabaseAsync, TraceEvm, TraceInspector,
+    errors::{EvmError, InitError, RuntimeError},
+    types::{AllDBType, AnyNetworkProvider, BlockContext, BlockTag},
+    utils::local_node_utils::DevNode,
+    wrap_db::is_missing_state_error,
+    BackendKind, MyWrapDatabaseAsync, RpcRateLimiter, TraceEvm, TraceInspector, WarmCache,
 };
 use alloy::{
     eips::{BlockId, BlockNumberOrTag},
     network::{AnyNetwork, BlockResponse},
+    primitives::{Address, B256},
     providers::{Provider, ProviderBuilder, WsConnect},
 };
 use revm::{
-    context::Context,
-    database::{AlloyDB, CacheDB, DatabaseRef},
+    context::{BlockEnv, Context},
+    database::{AlloyDB, CacheDB, Database, DatabaseRef},
     handler::{MainBuilder, MainContext, MainnetContext},
     inspector::NoOpInspector,
 };
@@ -169,6 +173,14 @@ pub async fn get_provider(rpc_url: &str) -> Result<AnyNetworkProvider, EvmError>
     Ok(provider)
 }
 
+/// Scheme of `rpc_url` for diagnostics; the rest of the URL may hold an API key
+#[cfg(feature = "tracing-logs")]
+fn rpc_scheme(rpc_url: &str) -> &str {
+    rpc_url
+        .split_once("://")
+        .map_or("unknown", |(scheme, _)| scheme)
+}
+
 /// Internal function to fetch block information from the blockchain
 ///
 /// Retrieves essential block data needed for EVM initialization:
@@ -192,31 +204,215 @@ pub async fn get_block<P: Provider<AnyNetwork>>(
     provider: &P,
     block_number: Option<u64>,
 ) -> Result<(u64, u64, u64), InitError> {
+    let block = get_block_context(provider, block_number, None).await?;
+    Ok((block.chain_id, block.number, block.timestamp))
+}
+
+/// Internal function to resolve the full block context from the blockchain
+///
+/// Like [`get_block`], but also returns the block hash and accepts a block hash
+/// as selector. When `block_hash` is given it takes precedence over `block_number`.
+///
+/// # Arguments
+/// - `provider`: Blockchain provider for RPC calls
+/// - `block_number`: Optional specific block number (uses latest if None)
+/// - `block_hash`: Optional specific block hash
+///
+/// # Returns
+/// - `Ok(BlockContext)`: Number, hash, timestamp and chain ID of the block
+/// - `Err(InitError::BlockNotFound)`: The requested block is unknown to the node
+/// - `Err(InitError::BlockFetchError)`: Failed to fetch required blockchain data
+pub async fn get_block_context<P: Provider<AnyNetwork>>(
+    provider: &P,
+    block_number: Option<u64>,
+    block_hash: Option<B256>,
+) -> Result<BlockContext, InitError> {
     // Fetch chain ID for network identification
     let chain_id = provider
         .get_chain_id()
         .await
         .map_err(|_| InitError::BlockFetchError("Failed to fetch chain ID".to_string()))?;
+    get_block_header(provider, chain_id, block_number, block_hash).await
+}
 
-    // Determine block number (use latest if not specified)
-    let block_number = if let Some(number) = block_number {
-        number
+/// Resolves the block context of a chain whose ID is already known
+///
+/// Like [`get_block_context`] without the chain ID lookup.
+pub(crate) async fn get_block_header<P: Provider<AnyNetwork>>(
+    provider: &P,
+    chain_id: u64,
+    block_number: Option<u64>,
+    block_hash: Option<B256>,
+) -> Result<BlockContext, InitError> {
+    let block_info = if let Some(hash) = block_hash {
+        // Resolve the header by hash
+        provider
+            .get_block_by_hash(hash)
+            .await
+            .map_err(|_| InitError::BlockFetchError("Failed to fetch block".to_string()))?
+            .ok_or_else(|| InitError::BlockNotFound(format!("Block {hash} not found")))?
     } else {
-        let number = provider.get_block_number().await.map_err(|_| {
-            InitError::BlockFetchError("Failed to fetch latest block number".to_string())
-        })?;
-        number
+        // Determine block number (use latest if not specified)
+        let block_number = if let Some(number) = block_number {
+            number
+        } else {
+            provider.get_block_number().await.map_err(|_| {
+                InitError::BlockFetchError("Failed to fetch latest block number".to_string())
+            })?
+        };
+
+        provider
+            .get_block_by_number(BlockNumberOrTag::Number(block_number))
+            .await
+            .map_err(|_| InitError::BlockFetchError("Failed to fetch block".to_string()))?
+            .ok_or_else(|| InitError::BlockNotFound(format!("Block {block_number} not found")))?
     };
+    let header = block_info.header();
+
+    Ok(BlockContext {
+        number: header.number,
+        hash: header.hash,
+        timestamp: header.timestamp,
+        chain_id,
+        tag: None,
+    })
+}
+
+/// Resolves the block a tag currently points to
+///
+/// # Arguments
+/// - `provider`: Blockchain provider for RPC calls
+/// - `tag`: Block tag to resolve
+///
+/// # Returns
+/// - `Ok(BlockContext)`: Number, hash, timestamp and chain ID of the block, with `tag`
+///   recorded
+/// - `Err(InitError::UnsupportedBlockTag)`: The node rejects the tag or knows no block
+///   for it, as pre-merge chains do for `safe` and `finalized`
+/// - `Err(InitError::BlockFetchError)`: Failed to fetch required blockchain data
+pub async fn get_block_context_by_tag<P: Provider<AnyNetwork>>(
+    provider: &P,
+    tag: BlockTag,
+) -> Result<BlockContext, InitError> {
+    let chain_id = provider
+        .get_chain_id()
+        .await
+        .map_err(|_| InitError::BlockFetchError("Failed to fetch chain ID".to_string()))?;
+    get_block_header_by_tag(provider, chain_id, tag).await
+}
 
-    // Fetch block information for timestamp
+/// Resolves a block tag on a chain whose ID is already known
+///
+/// Like [`get_block_context_by_tag`] without the chain ID lookup.
+pub(crate) async fn get_block_header_by_tag<P: Provider<AnyNetwork>>(
+    provider: &P,
+    chain_id: u64,
+    tag: BlockTag,
+) -> Result<BlockContext, InitError> {
     let block_info = provider
-        .get_block_by_number(BlockNumberOrTag::Number(block_number))
+        .get_block_by_number(tag.into())
         .await
-        .map_err(|_| InitError::BlockFetchError("Failed to fetch block".to_string()))?
-        .ok_or_else(|| InitError::BlockNotFound("Block not found".to_string()))?;
-    let timestamp = block_info.header().timestamp;
+        .map_err(|e| match e.as_error_resp() {
+            // The node answered, but does not know the tag
+            Some(response) => {
+                InitError::UnsupportedBlockTag(format!("{tag}: {}", response.message))
+            }
+            None => InitError::BlockFetchError("Failed to fetch block".to_string()),
+        })?
+        .ok_or_else(|| InitError::UnsupportedBlockTag(format!("no {tag} block")))?;
+    let header = block_info.header();
 
-    Ok((chain_id, block_number, timestamp))
+    Ok(BlockContext {
+        number: header.number,
+        hash: header.hash,
+        timestamp: header.timestamp,
+        chain_id,
+        tag: Some(tag),
+    })
+}
+
+/// Policy for blocks whose state the node does not serve
+///
+/// Non-archive nodes only keep the state of recent blocks; simulating at an older block
+/// fails on the first state lookup. See [`EvmBuilder::with_historical_fallback`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fallback {
+    /// Fail the build with `RuntimeError::HistoricalStateUnavailable`
+    Error,
+    /// Build at the latest block instead
+    UseLatest,
+    /// Build at the first later block with available state, probing every
+    /// `probe_step` blocks up to the latest one
+    NearestAvailable { probe_step: u64 },
+}
+
+/// Whether the node serves state at `block_number`, probed with a balance lookup
+async fn state_available<P: Provider<AnyNetwork>>(
+    provider: &P,
+    block_number: u64,
+) -> Result<bool, InitError> {
+    match provider
+        .get_balance(Address::ZERO)
+        .number(block_number)
+        .await
+    {
+        Ok(_) => Ok(true),
+        Err(e) if is_missing_state_error(&e.to_string()) => Ok(false),
+        Err(e) => Err(InitError::DatabaseError(format!(
+            "Failed to probe state at block {block_number}: {e}"
+        ))),
+    }
+}
+
+/// Checks that the node serves the state of `block` and applies `fallback` if not
+///
+/// # Arguments
+/// - `provider`: Blockchain provider for RPC calls
+/// - `block`: Block resolved from the builder configuration
+/// - `fallback`: Policy to apply when the state of `block` is unavailable
+///
+/// # Returns
+/// - `Ok(BlockContext)`: `block` itself, or the block chosen by the policy
+/// - `Err(EvmError::Runtime(RuntimeError::HistoricalStateUnavailable { .. }))`: The
+///   state is unavailable under `Fallback::Error`
+/// - `Err(EvmError::Init(_))`: The probe or a block lookup failed for another reason
+pub async fn apply_historical_fallback<P: Provider<AnyNetwork>>(
+    provider: &P,
+    block: BlockContext,
+    fallback: Fallback,
+) -> Result<BlockContext, EvmError> {
+    if state_available(provider, block.number).await? {
+        return Ok(block);
+    }
+    #[cfg(feature = "tracing-logs")]
+    {
+        if fallback != Fallback::Error {
+            tracing::warn!(
+                block = block.number,
+                ?fallback,
+                "State unavailable at the requested block, applying the historical fallback"
+            );
+        }
+    }
+    match fallback {
+        Fallback::Error => Err(RuntimeError::HistoricalStateUnavailable {
+            block: block.number,
+        }
+        .into()),
+        Fallback::UseLatest => Ok(get_block_context(provider, None, None).await?),
+        Fallback::NearestAvailable { probe_step } => {
+            let latest = provider.get_block_number().await.map_err(|_| {
+                InitError::BlockFetchError("Failed to fetch latest block number".to_string())
+            })?;
+            let mut number = block.number;
+            loop {
+                number = number.saturating_add(probe_step.max(1)).min(latest);
+                if number >= latest || state_available(provider, number).await? {
+                    return Ok(get_block_context(provider, Some(number), None).await?);
+                }
+            }
+        }
+    }
 }
 
 // ========================= Core Builder Structure =========================
@@ -247,8 +443,26 @@ pub async fn get_block<P: Provider<AnyNetwork>>(
 pub struct EvmBuilder<DB: DatabaseRef, INSP = NoOpInspector> {
     /// RPC endpoint URL for blockchain connectivity
     rpc_url: String,
+    /// Endpoints to fail over to, in order, when `rpc_url` fails
+    fallback_urls: Vec<String>,
     /// Optional specific block number (uses latest if None)
     block_number: Option<u64>,
+    /// Optional specific block hash (takes precedence over `block_number`)
+    block_hash: Option<B256>,
+    /// Optional block tag, resolved at build time (exclusive with number and hash)
+    block_tag: Option<BlockTag>,
+    /// Optional block coinbase (keeps the default if None)
+    coinbase: Option<Address>,
+    /// Optional policy for blocks without available state (no probe if None)
+    historical_fallback: Option<Fallback>,
+    /// Whether the endpoint is a development node followed at its latest block
+    local_node: bool,
+    /// Optional cache of upstream lookups shared with other EVMs
+    warm_cache: Option<WarmCache>,
+    /// Optional limit on upstream lookups, possibly shared with other EVMs
+    rate_limiter: Option<RpcRateLimiter>,
+    /// Whether consensus restrictions stay enabled and the block env follows the header
+    strict_validation: bool,
     /// Inspector instance for transaction tracing
     inspector: INSP,
     /// Phantom data to track database type at compile time
@@ -284,11 +498,73 @@ impl EvmBuilder<AllDBType, NoOpInspector> {
     pub fn new_alloy(url: &str) -> Self {
         Self {
             rpc_url: url.to_string(),
+            fallback_urls: Vec::new(),
             block_number: None,
+            block_hash: None,
+            block_tag: None,
+            coinbase: None,
+            historical_fallback: None,
+            local_node: false,
+            warm_cache: None,
+            rate_limiter: None,
+            strict_validation: false,
             inspector: NoOpInspector,
             _marker: std::marker::PhantomData,
         }
     }
+
+    /// Creates a new EVM builder reading state through several RPC endpoints
+    ///
+    /// Endpoints are tried in order: `build()` connects to every one of them and
+    /// leaves out those that cannot be reached, failing with `InitError::ChainId`
+    /// when the reachable ones serve different chains. Requests then go to the first
+    /// reachable endpoint, and move to the next one for good when a request fails at
+    /// the transport level. [`TraceEvm::db_stats`] reports the endpoint in use and
+    /// the number of failovers.
+    ///
+    /// # Arguments
+    /// - `urls`: RPC endpoint URLs of one chain (HTTP/HTTPS or WS/WSS), by preference
+    ///
+    /// # Example
+    /// ```rust
+    /// use revm_trace::EvmBuilder;
+    /// let builder = EvmBuilder::new_alloy_multi(vec![
+    ///     "https://eth.llamarpc.com".to_string(),
+    ///     "https://rpc.ankr.com/eth".to_string(),
+    /// ]);
+    /// ```
+    pub fn new_alloy_multi(urls: Vec<String>) -> Self {
+        let mut urls = urls.into_iter();
+        let rpc_url = urls.next().unwrap_or_default();
+        Self {
+            fallback_urls: urls.collect(),
+            ..Self::new_alloy(&rpc_url)
+        }
+    }
+
+    /// Creates a new EVM builder for a local development node (Anvil, Hardhat)
+    ///
+    /// `build()` checks `web3_clientVersion` and fails with `InitError::NotDevNode`
+    /// for any other client. Dev nodes mine on demand, so the EVM is always built at
+    /// the node's latest block: block number, hash, tag and historical fallback settings
+    /// are ignored. Call [`TraceEvm::refresh_to_latest`] to follow blocks mined later, and
+    /// see [`LocalNodeBridge`](crate::utils::local_node_utils::LocalNodeBridge) to
+    /// replay simulated batches on the node.
+    ///
+    /// # Arguments
+    /// - `url`: RPC endpoint URL of the node (HTTP/HTTPS or WS/WSS)
+    ///
+    /// # Example
+    /// ```rust
+    /// use revm_trace::EvmBuilder;
+    /// let builder = EvmBuilder::new_local("http://127.0.0.1:8545");
+    /// ```
+    pub fn new_local(url: &str) -> Self {
+        Self {
+            local_node: true,
+            ..Self::new_alloy(url)
+        }
+    }
 }
 
 // ========================= Generic Configuration Methods =========================
@@ -321,12 +597,201 @@ impl<DB: DatabaseRef, INSP> EvmBuilder<DB, INSP> {
     pub fn with_block_number(self, block_number: u64) -> Self {
         EvmBuilder {
             rpc_url: self.rpc_url,
+            fallback_urls: self.fallback_urls,
             block_number: Some(block_number),
+            block_hash: None,
+            block_tag: None,
+            coinbase: self.coinbase,
+            historical_fallback: self.historical_fallback,
+            local_node: self.local_node,
+            warm_cache: self.warm_cache,
+            rate_limiter: self.rate_limiter,
+            strict_validation: self.strict_validation,
+            inspector: self.inspector,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Pins the EVM to the block with the given hash
+    ///
+    /// Unlike [`with_block_number`](Self::with_block_number), a hash identifies exactly
+    /// one block, so simulations near the chain head cannot silently run against a
+    /// different block after a reorg. The header is resolved at build time and the
+    /// database is pinned with `BlockId::Hash`; building fails with
+    /// `InitError::BlockNotFound` if the node does not know the hash.
+    ///
+    /// Overrides any previously set block number.
+    ///
+    /// # Arguments
+    /// - `block_hash`: Hash of the block to use
+    ///
+    /// # Returns
+    /// Updated builder instance with block hash set
+    ///
+    /// # Example
+    /// ```rust
+    /// use revm_trace::EvmBuilder;
+    /// use alloy::primitives::B256;
+    /// let builder = EvmBuilder::new_alloy("https://eth.llamarpc.com")
+    ///     .with_block_hash(B256::ZERO);
+    /// ```
+    pub fn with_block_hash(self, block_hash: B256) -> Self {
+        EvmBuilder {
+            rpc_url: self.rpc_url,
+            fallback_urls: self.fallback_urls,
+            block_number: None,
+            block_hash: Some(block_hash),
+            block_tag: None,
+            coinbase: self.coinbase,
+            historical_fallback: self.historical_fallback,
+            local_node: self.local_node,
+            warm_cache: self.warm_cache,
+            rate_limiter: self.rate_limiter,
+            strict_validation: self.strict_validation,
+            inspector: self.inspector,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Builds the EVM at the block a tag points to
+    ///
+    /// Services that must not see reorged state build at [`BlockTag::Finalized`] (or
+    /// `Safe`) instead of a number near the head. The tag is resolved once, at build
+    /// time, and the database is pinned to the hash it resolved to, so the EVM stays on
+    /// that block as the chain advances; [`TraceEvm::block_context`] records the tag
+    /// with the resolved number and hash. Call [`TraceEvm::refresh_tag`] to move a
+    /// long-lived EVM to the block the tag points to later. Building fails with
+    /// `InitError::UnsupportedBlockTag` when the node cannot resolve the tag.
+    ///
+    /// Overrides any previously set block number or hash.
+    ///
+    /// # Arguments
+    /// - `tag`: Block tag to resolve
+    ///
+    /// # Returns
+    /// Updated builder instance with block tag set
+    ///
+    /// # Example
+    /// ```rust
+    /// use revm_trace::{types::BlockTag, EvmBuilder};
+    /// let builder = EvmBuilder::new_alloy("https://eth.llamarpc.com")
+    ///     .with_block_tag(BlockTag::Finalized);
+    /// ```
+    pub fn with_block_tag(self, tag: BlockTag) -> Self {
+        EvmBuilder {
+            block_number: None,
+            block_hash: None,
+            block_tag: Some(tag),
+            ..self
+        }
+    }
+
+    /// Sets the block coinbase (beneficiary) used during execution
+    ///
+    /// Useful for MEV bundle evaluation, where `block.coinbase` must be the builder
+    /// address for the `COINBASE` opcode and direct coinbase payments to behave as
+    /// on-chain. `TxInspector` reports such payments in `coinbase_payments`.
+    ///
+    /// # Arguments
+    /// - `coinbase`: Address returned by the `COINBASE` opcode
+    ///
+    /// # Returns
+    /// Updated builder instance with coinbase set
+    ///
+    /// # Example
+    /// ```rust
+    /// use revm_trace::EvmBuilder;
+    /// use alloy::primitives::address;
+    /// let builder = EvmBuilder::new_alloy("https://eth.llamarpc.com")
+    ///     .with_coinbase(address!("95222290DD7278Aa3Ddd389Cc1E1d165CC4BAfe5"));
+    /// ```
+    pub fn with_coinbase(self, coinbase: Address) -> Self {
+        EvmBuilder {
+            rpc_url: self.rpc_url,
+            fallback_urls: self.fallback_urls,
+            block_number: self.block_number,
+            block_hash: self.block_hash,
+            block_tag: self.block_tag,
+            coinbase: Some(coinbase),
+            historical_fallback: self.historical_fallback,
+            local_node: self.local_node,
+            warm_cache: self.warm_cache,
+            rate_limiter: self.rate_limiter,
+            strict_validation: self.strict_validation,
+            inspector: self.inspector,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Probes at build time whether the node serves the block's state
+    ///
+    /// Without a policy, simulations at a block the node has pruned fail on the first
+    /// state lookup with `RuntimeError::HistoricalStateUnavailable`. With one, the
+    /// builder checks the state with a balance lookup at the block and applies the
+    /// policy before constructing the EVM. A block selected by hash is only kept when
+    /// its state is available.
+    ///
+    /// # Arguments
+    /// - `fallback`: What to do when the state is unavailable
+    ///
+    /// # Returns
+    /// Updated builder instance with the policy set
+    ///
+    /// # Example
+    /// ```rust
+    /// use revm_trace::{EvmBuilder, Fallback};
+    /// let builder = EvmBuilder::new_alloy("https://eth.llamarpc.com")
+    ///     .with_block_number(15_000_000)
+    ///     .with_historical_fallback(Fallback::NearestAvailable { probe_step: 1_000 });
+    /// ```
+    pub fn with_historical_fallback(self, fallback: Fallback) -> Self {
+        EvmBuilder {
+            rpc_url: self.rpc_url,
+            fallback_urls: self.fallback_urls,
+            block_number: self.block_number,
+            block_hash: self.block_hash,
+            block_tag: self.block_tag,
+            coinbase: self.coinbase,
+            historical_fallback: Some(fallback),
+            local_node: self.local_node,
+            warm_cache: self.warm_cache,
+            rate_limiter: self.rate_limiter,
+            strict_validation: self.strict_validation,
             inspector: self.inspector,
             _marker: std::marker::PhantomData,
         }
     }
 
+    /// Keeps consensus restrictions enabled for validation-grade simulations
+    ///
+    /// By default the built EVM lifts EIP-3607 (senders with code), the block gas
+    /// limit, the base fee and the contract code size limit, and only takes number and
+    /// timestamp from the header, which suits what-if simulation. In strict mode all
+    /// four restrictions stay enabled and the block environment is populated from the
+    /// header (beneficiary, gas limit, base fee, difficulty and prevrandao), so
+    /// transactions that could not be included fail with
+    /// `RuntimeError::InvalidTransaction` before executing. Building costs one extra
+    /// header request.
+    ///
+    /// Simulated transactions carry no gas price, so set one with
+    /// [`TraceEvm::set_tx_hook`] to pass the base fee check.
+    ///
+    /// # Arguments
+    /// - `strict`: Whether to enforce the restrictions (`false` by default)
+    ///
+    /// # Example
+    /// ```rust
+    /// use revm_trace::EvmBuilder;
+    /// let builder = EvmBuilder::new_alloy("https://eth.llamarpc.com")
+    ///     .with_strict_validation(true);
+    /// ```
+    pub fn with_strict_validation(self, strict: bool) -> Self {
+        EvmBuilder {
+            strict_validation: strict,
+            ..self
+        }
+    }
+
     /// Replaces the inspector with a custom implementation
     ///
     /// This method enables the builder to switch from the default `NoOpInspector`
@@ -359,7 +824,16 @@ impl<DB: DatabaseRef, INSP> EvmBuilder<DB, INSP> {
     {
         EvmBuilder {
             rpc_url: self.rpc_url,
+            fallback_urls: self.fallback_urls,
             block_number: self.block_number,
+            block_hash: self.block_hash,
+            block_tag: self.block_tag,
+            coinbase: self.coinbase,
+            historical_fallback: self.historical_fallback,
+            local_node: self.local_node,
+            warm_cache: self.warm_cache,
+            rate_limiter: self.rate_limiter,
+            strict_validation: self.strict_validation,
             inspector,
             _marker: std::marker::PhantomData,
         }
@@ -373,11 +847,70 @@ impl<DB: DatabaseRef, INSP> EvmBuilder<DB, INSP> {
 /// This specialized implementation handles the unique requirements of AlloyDB,
 /// including async wrapper creation and stateless database configuration.
 impl<INSP> EvmBuilder<AllDBType, INSP> {
+    /// Shares upstream lookups with other EVMs built at the same block
+    ///
+    /// Accounts, code and storage slots fetched by one EVM are served to every other
+    /// EVM holding a clone of `cache`, so services building a fresh EVM per request
+    /// stop refetching hot contracts. Only upstream answers are cached, never
+    /// simulated state changes. If the built block differs from the block the cache is
+    /// pinned to, the cache is emptied and pinned to the built block; see
+    /// [`TraceEvm::with_warm_cache`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use revm_trace::{EvmBuilder, WarmCache};
+    /// let cache = WarmCache::new(18_000_000);
+    /// let builder = EvmBuilder::new_alloy("https://eth.llamarpc.com")
+    ///     .with_block_number(18_000_000)
+    ///     .with_warm_cache(cache.clone());
+    /// ```
+    pub fn with_warm_cache(self, cache: WarmCache) -> Self {
+        EvmBuilder {
+            warm_cache: Some(cache),
+            ..self
+        }
+    }
+
+    /// Limits upstream lookups to `requests_per_second` on average, `burst` at once
+    ///
+    /// Lookups the EVM sends to the node (account, code, storage and block hash
+    /// lookups missing from the `CacheDB` overlay) take a token from a token bucket
+    /// and wait asynchronously when it is empty, so bursts of cold-cache lookups stay
+    /// under the quota of free-tier endpoints. The requests made by `build()` itself
+    /// are not limited. [`TraceEvm::db_stats`] reports the time spent waiting as
+    /// `rate_limit_wait`.
+    ///
+    /// The limit applies to the built EVM alone; use
+    /// [`with_rate_limiter`](Self::with_rate_limiter) to share one limit between EVMs.
+    ///
+    /// # Example
+    /// ```rust
+    /// use revm_trace::EvmBuilder;
+    /// let builder = EvmBuilder::new_alloy("https://eth.llamarpc.com")
+    ///     .with_rpc_rate_limit(10, 5);
+    /// ```
+    pub fn with_rpc_rate_limit(self, requests_per_second: u32, burst: u32) -> Self {
+        self.with_rate_limiter(RpcRateLimiter::new(requests_per_second, burst))
+    }
+
+    /// Limits upstream lookups with a limiter shared with other EVMs
+    ///
+    /// Clones of `limiter` share one budget, so every EVM built with a clone counts
+    /// against the same limit; see [`with_rpc_rate_limit`](Self::with_rpc_rate_limit).
+    pub fn with_rate_limiter(self, limiter: RpcRateLimiter) -> Self {
+        EvmBuilder {
+            rate_limiter: Some(limiter),
+            ..self
+        }
+    }
+
     /// Builds an EVM instance using AlloyDB backend
     ///
     /// This async method performs the complete EVM initialization process:
-    /// 1. **Provider Setup**: Creates RPC provider with protocol detection
-    /// 2. **Blockchain Data**: Fetches chain ID, block number, and timestamp
+    /// 1. **Provider Setup**: Creates RPC provider with protocol detection, failing over
+    ///    between endpoints when several were given
+    /// 2. **Blockchain Data**: Fetches chain ID, block number, hash and timestamp,
+    ///    resolving the block tag if one was set
     /// 3. **Database Creation**: Initializes AlloyDB with async wrapper
     /// 4. **EVM Context**: Configures mainnet context with proper settings
     /// 5. **Inspector Integration**: Builds EVM with the specified inspector
@@ -395,7 +928,8 @@ impl<INSP> EvmBuilder<AllDBType, INSP> {
     ///
     /// # EVM Configuration
     ///
-    /// The following mainnet-compatible settings are applied:
+    /// Unless [strict validation](Self::with_strict_validation) is enabled, the
+    /// following mainnet-compatible settings are applied:
     /// - `disable_eip3607`: Allows transactions from zero-address
     /// - `limit_contract_code_size`: Removes contract size limits
     /// - `disable_block_gas_limit`: Removes gas limit restrictions
@@ -408,6 +942,18 @@ impl<INSP> EvmBuilder<AllDBType, INSP> {
     /// - Invalid block numbers
     /// - Runtime availability problems
     /// - Provider creation failures
+    #[cfg_attr(
+        feature = "tracing-logs",
+        tracing::instrument(
+            name = "build",
+            skip_all,
+            fields(
+                rpc_scheme = rpc_scheme(&self.rpc_url),
+                block_number = tracing::field::Empty,
+                chain_id = tracing::field::Empty,
+            )
+        )
+    )]
     pub async fn build(self) -> Result<TraceEvm<CacheDB<AllDBType>, INSP>, EvmError>
     where
         INSP: TraceInspector<MainnetContext<CacheDB<AllDBType>>>,
@@ -415,55 +961,184 @@ impl<INSP> EvmBuilder<AllDBType, INSP> {
         // Destructure builder to extract configuration
         let EvmBuilder {
             rpc_url,
+            fallback_urls,
             block_number,
+            block_hash,
+            block_tag,
+            coinbase,
+            historical_fallback,
+            local_node,
+            warm_cache,
+            rate_limiter,
+            strict_validation,
             inspector,
             _marker,
         } = self;
 
         // Step 1: Create provider with automatic protocol detection
-        let provider = get_provider(&rpc_url).await?;
-
-        // Step 2: Fetch essential blockchain data
-        let (chain_id, block_number, timestamp) = get_block(&provider, block_number).await?;
-
-        // Step 3: Create AlloyDB instance
-        let block_id = BlockId::Number(BlockNumberOrTag::Number(block_number));
-        let alloy_db = AlloyDB::new(provider, block_id);
-
-        // Step 4: Wrap AlloyDB for sync compatibility
-        // Note: This requires a suitable tokio runtime to be available
-        let wrap_db = MyWrapDatabaseAsync::new(alloy_db).ok_or_else(|| {
-            EvmError::Init(InitError::DatabaseError(
-                "Failed to create wrapped database: no suitable tokio runtime available"
-                    .to_string(),
-            ))
-        })?;
-
-        // Step 5: Create cache layer on top of wrapped database
-        let cache_db = CacheDB::new(wrap_db);
-
-        // Step 6: Create and configure EVM context
-        let mut ctx = Context::mainnet().with_db(cache_db);
-
-        // Network configuration
-        ctx.cfg.chain_id = chain_id;
-
-        // Disable restrictions for simulation environment
-        ctx.cfg.disable_eip3607 = true; // Allow zero-address transactions
-        ctx.cfg.limit_contract_code_size = None; // Remove contract size limits
-        ctx.cfg.disable_block_gas_limit = true; // Remove gas limit restrictions
-        ctx.cfg.disable_base_fee = true; // Disable EIP-1559 base fee
-
-        // Block environment configuration
-        ctx.block.number = block_number;
-        ctx.block.timestamp = timestamp;
-
-        // Step 7: Build final EVM instance with inspector
-        let evm = ctx.build_mainnet_with_inspector(inspector);
-        Ok(TraceEvm::new(evm))
+        let (provider, endpoints) = connect_endpoints(&rpc_url, &fallback_urls).await?;
+
+        // Step 2: Fetch essential blockchain data; dev nodes are followed at their
+        // latest block regardless of the requested one
+        let (block, pinned_hash) = if local_node {
+            DevNode::detect(&provider).await?;
+            (get_block_context(&provider, None, None).await?, None)
+        } else {
+            let mut block = match block_tag {
+                Some(tag) => get_block_context_by_tag(&provider, tag).await?,
+                None => get_block_context(&provider, block_number, block_hash).await?,
+            };
+            // Blocks selected by tag are pinned to the hash they resolved to
+            let pinned_hash = block_hash.or(block_tag.map(|_| block.hash));
+            if let Some(fallback) = historical_fallback {
+                block = apply_historical_fallback(&provider, block, fallback).await?;
+            }
+            (block, pinned_hash)
+        };
+        #[cfg(feature = "tracing-logs")]
+        {
+            let span = tracing::Span::current();
+            span.record("block_number", block.number);
+            span.record("chain_id", block.chain_id);
+        }
+
+        // Step 3: Create AlloyDB instance, pinned by hash when one was requested
+        let block_id = match pinned_hash {
+            Some(hash) if hash == block.hash => BlockId::hash(hash),
+            _ => BlockId::Number(BlockNumberOrTag::Number(block.number)),
+        };
+        let block_env = if strict_validation {
+            Some(get_block_env(&provider, &block).await?)
+        } else {
+            None
+        };
+        let mut evm = evm_from_provider(provider, block, block_id, block_env, coinbase, inspector)?;
+        if let Some(endpoints) = endpoints {
+            evm.evm
+                .ctx
+                .journaled_state
+                .database
+                .db
+                .attach_endpoints(endpoints);
+        }
+        if let Some(limiter) = rate_limiter {
+            evm = evm.with_rate_limiter(limiter);
+        }
+        Ok(match warm_cache {
+            Some(cache) => evm.with_warm_cache(cache),
+            None => evm,
+        })
+    }
+}
+
+/// Builds an EVM reading state through `provider` at `block_id`
+///
+/// Performs steps 3 to 7 of [`EvmBuilder::build`] for an already resolved block, so
+/// callers holding a provider (see [`EvmFactory`](factory::EvmFactory)) skip the
+/// connection setup and block lookups. A `block_env` selects strict validation, see
+/// [`configure_context`].
+pub(crate) fn evm_from_provider<INSP>(
+    provider: AnyNetworkProvider,
+    block: BlockContext,
+    block_id: BlockId,
+    block_env: Option<BlockEnv>,
+    coinbase: Option<Address>,
+    inspector: INSP,
+) -> Result<InspectorEvm<INSP>, EvmError>
+where
+    INSP: TraceInspector<MainnetContext<CacheDB<AllDBType>>>,
+{
+    let alloy_db = AlloyDB::new(provider, block_id);
+
+    // Step 4: Wrap AlloyDB for sync compatibility
+    // Note: This requires a suitable tokio runtime to be available
+    let wrap_db = MyWrapDatabaseAsync::new(alloy_db).ok_or_else(|| {
+        EvmError::Init(InitError::DatabaseError(
+            "Failed to create wrapped database: no suitable tokio runtime available".to_string(),
+        ))
+    })?;
+
+    // Step 5: Create cache layer on top of wrapped database
+    let cache_db = CacheDB::new(wrap_db);
+
+    // Step 6: Create and configure EVM context
+    let mut ctx = Context::mainnet().with_db(cache_db);
+    configure_context(&mut ctx, &block, block_env, coinbase);
+
+    // Step 7: Build final EVM instance with inspector
+    let evm = ctx.build_mainnet_with_inspector(inspector);
+    Ok(TraceEvm::new(evm)
+        .with_block_context(block)
+        .with_backend(BackendKind::AlloyDb)
+        .with_db_stats_source(|db| db.db.stats())
+        .with_remote_fetch_source(|db| db.db.take_fetches()))
+}
+
+/// Applies the network, restriction and block settings shared by all build paths
+///
+/// Without a `block_env`, the simulation relaxations are applied and the block only
+/// takes number and timestamp from `block`. With one (strict validation), the
+/// restrictions keep their consensus defaults and the block environment is replaced
+/// by `block_env`. A `coinbase` overrides the beneficiary in both cases.
+pub(crate) fn configure_context<DB: Database>(
+    ctx: &mut MainnetContext<DB>,
+    block: &BlockContext,
+    block_env: Option<BlockEnv>,
+    coinbase: Option<Address>,
+) {
+    // Network configuration
+    ctx.cfg.chain_id = block.chain_id;
+
+    match block_env {
+        Some(block_env) => ctx.block = block_env,
+        None => {
+            relax_restrictions(ctx);
+
+            // Block environment configuration
+            ctx.block.number = block.number;
+            ctx.block.timestamp = block.timestamp;
+        }
+    }
+    if let Some(coinbase) = coinbase {
+        ctx.block.beneficiary = coinbase;
     }
 }
 
+/// Disables the consensus restrictions that get in the way of simulations
+pub(crate) fn relax_restrictions<DB: Database>(ctx: &mut MainnetContext<DB>) {
+    ctx.cfg.disable_eip3607 = true; // Allow zero-address transactions
+    ctx.cfg.limit_contract_code_size = None; // Remove contract size limits
+    ctx.cfg.disable_block_gas_limit = true; // Remove gas limit restrictions
+    ctx.cfg.disable_base_fee = true; // Disable EIP-1559 base fee
+}
+
+/// Fetches the full block environment of `block` for strict validation
+///
+/// Looks the header up by hash and takes beneficiary, gas limit, base fee, difficulty
+/// and prevrandao from it. Blob gas pricing keeps revm's defaults.
+pub(crate) async fn get_block_env<P: Provider<AnyNetwork>>(
+    provider: &P,
+    block: &BlockContext,
+) -> Result<BlockEnv, InitError> {
+    let block_info = provider
+        .get_block_by_hash(block.hash)
+        .await
+        .map_err(|_| InitError::BlockFetchError("Failed to fetch block".to_string()))?
+        .ok_or_else(|| InitError::BlockNotFound(format!("Block {} not found", block.hash)))?;
+    let header = block_info.header();
+
+    Ok(BlockEnv {
+        number: header.number,
+        beneficiary: header.beneficiary,
+        timestamp: header.timestamp,
+        gas_limit: header.gas_limit,
+        basefee: header.base_fee_per_gas.unwrap_or_default(),
+        difficulty: header.difficulty,
+        prevrandao: Some(header.mix_hash.unwrap_or_default()),
+        ..BlockEnv::default()
+    })
+}
+
 // ========================= Convenience Functions =========================
 
 /// Creates a basic EVM instance using AlloyDB backend with no tracing
@@ -545,5 +1220,8 @@ where
     evm_builder.build().await
 }
 
+pub mod custom_db;
+pub mod factory;
+mod failover;
 #[cfg(feature = "foundry-fork")]
 pub mod fork_db;
@@ -0,0 +1,125 @@
+//! EVMs over caller-provided databases
+//!
+//! [`EvmBuilder::new_custom_db`] wraps any revm `Database` (a persistent state
+//! snapshot, an in-memory test fixture, ...) without touching a provider: the chain ID
+//! and block environment are taken as given. Batches run on such an EVM through
+//! [`TransactionTrace`](crate::traits::TransactionTrace) once the database implements
+//! `DatabaseCommit` and [`ResetDB`](crate::traits::ResetDB).
+//!
+//! Helpers that rely on the `CacheDB` overlay (snapshots, ephemeral state, storage
+//! and balance cheats, cloning) stay available only for `CacheDB`-backed EVMs; wrap
+//! the database in a `CacheDB` to use them.
+
+use super::{relax_restrictions, EvmBuilder};
+use crate::{types::AllDBType, TraceEvm, TraceInspector};
+use revm::{
+    context::{BlockEnv, Context},
+    database::Database,
+    handler::{MainBuilder, MainContext, MainnetContext},
+    inspector::NoOpInspector,
+};
+
+/// Builder of an EVM over a caller-provided database, see [`EvmBuilder::new_custom_db`]
+pub struct CustomDbBuilder<DB: Database, INSP = NoOpInspector> {
+    /// Database state is read from and committed to
+    db: DB,
+    /// Chain ID of the executed transactions
+    chain_id: u64,
+    /// Block environment the transactions execute in
+    block_env: BlockEnv,
+    /// Whether consensus restrictions stay enabled
+    strict_validation: bool,
+    /// Inspector instance for transaction tracing
+    inspector: INSP,
+}
+
+impl EvmBuilder<AllDBType, NoOpInspector> {
+    /// Creates a builder for an EVM over `db`, skipping all provider logic
+    ///
+    /// No RPC endpoint is contacted: `chain_id` and `block_env` are used as given, and
+    /// the EVM's [`block_context`](TraceEvm::block_context) takes number and timestamp
+    /// from `block_env`, with an unknown (zero) hash.
+    ///
+    /// # Arguments
+    /// - `db`: Database to read state from and commit state to
+    /// - `chain_id`: Chain ID of the executed transactions
+    /// - `block_env`: Block environment the transactions execute in
+    ///
+    /// # Example
+    /// ```rust
+    /// use revm::{context::BlockEnv, database::InMemoryDB};
+    /// use revm_trace::{EvmBuilder, TxInspector};
+    ///
+    /// let block_env = BlockEnv {
+    ///     number: 18_000_000,
+    ///     ..Default::default()
+    /// };
+    /// let evm = EvmBuilder::new_custom_db(InMemoryDB::default(), 1, block_env)
+    ///     .with_tracer(TxInspector::new())
+    ///     .build();
+    /// ```
+    pub fn new_custom_db<DB: Database>(
+        db: DB,
+        chain_id: u64,
+        block_env: BlockEnv,
+    ) -> CustomDbBuilder<DB, NoOpInspector> {
+        CustomDbBuilder {
+            db,
+            chain_id,
+            block_env,
+            strict_validation: false,
+            inspector: NoOpInspector,
+        }
+    }
+}
+
+impl<DB: Database, INSP> CustomDbBuilder<DB, INSP> {
+    /// Keeps consensus restrictions enabled
+    ///
+    /// By default EIP-3607 (senders with code), the block gas limit, the base fee and
+    /// the contract code size limit are lifted, as for
+    /// [`EvmBuilder::with_strict_validation`]. The block environment is used as given
+    /// either way.
+    pub fn with_strict_validation(self, strict: bool) -> Self {
+        Self {
+            strict_validation: strict,
+            ..self
+        }
+    }
+
+    /// Sets a custom inspector for transaction tracing
+    pub fn with_tracer<NewInsp>(self, inspector: NewInsp) -> CustomDbBuilder<DB, NewInsp>
+    where
+        NewInsp: TraceInspector<MainnetContext<DB>>,
+    {
+        CustomDbBuilder {
+            db: self.db,
+            chain_id: self.chain_id,
+            block_env: self.block_env,
+            strict_validation: self.strict_validation,
+            inspector,
+        }
+    }
+
+    /// Builds the EVM
+    ///
+    /// Nothing is fetched, so building cannot fail.
+    pub fn build(self) -> TraceEvm<DB, INSP>
+    where
+        INSP: TraceInspector<MainnetContext<DB>>,
+    {
+        let CustomDbBuilder {
+            db,
+            chain_id,
+            block_env,
+            strict_validation,
+            inspector,
+        } = self;
+        let mut ctx = Context::mainnet().with_db(db).with_block(block_env);
+        ctx.cfg.chain_id = chain_id;
+        if !strict_validation {
+            relax_restrictions(&mut ctx);
+        }
+        TraceEvm::new(ctx.build_mainnet_with_inspector(inspector))
+    }
+}
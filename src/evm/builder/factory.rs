@@ -0,0 +1,157 @@
+//! Building many EVMs over one provider
+//!
+//! Every [`EvmBuilder::build`](super::EvmBuilder::build) opens its own connection and
+//! looks up the chain ID; scanning thousands of blocks that way exhausts the
+//! connection limits of WebSocket endpoints. An [`EvmFactory`] connects once, looks up
+//! the chain ID once and remembers the headers it resolved, and every EVM it builds
+//! reads state through a clone of its provider. Provider clones share the underlying
+//! client and its connection pool.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use alloy::{
+    eips::{BlockId, BlockNumberOrTag},
+    providers::Provider,
+};
+use revm::{database::CacheDB, handler::MainnetContext};
+
+use super::{evm_from_provider, get_block_header, get_provider, InspectorEvm};
+use crate::{
+    errors::{EvmError, InitError},
+    types::{AllDBType, AnyNetworkProvider, BlockContext},
+    RpcRateLimiter, TraceInspector,
+};
+
+/// Builds EVMs at arbitrary blocks over one shared provider
+///
+/// The factory is `Send + Sync` and can be shared behind an `Arc`, while the EVMs it
+/// builds are confined to their thread like any other (see the
+/// [builder docs](super)).
+///
+/// # Example
+/// ```no_run
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// use revm_trace::{EvmFactory, TxInspector};
+///
+/// let factory = EvmFactory::new("wss://eth.llamarpc.com").await?;
+/// for block in 18_000_000..18_000_010 {
+///     let evm = factory.evm_at_block(block, TxInspector::new()).await?;
+///     // ... trace transactions at `block` ...
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct EvmFactory {
+    /// Provider shared by all built EVMs
+    provider: AnyNetworkProvider,
+    /// Chain ID, looked up once
+    chain_id: u64,
+    /// Resolved block contexts by number
+    blocks: Mutex<HashMap<u64, BlockContext>>,
+    /// Limit on upstream lookups shared by all built EVMs
+    rate_limiter: Option<RpcRateLimiter>,
+}
+
+impl EvmFactory {
+    /// Connects to `rpc_url` (HTTP/HTTPS or WS/WSS) and looks up the chain ID
+    pub async fn new(rpc_url: &str) -> Result<Self, EvmError> {
+        Self::from_provider(get_provider(rpc_url).await?).await
+    }
+
+    /// Wraps an existing provider and looks up the chain ID
+    pub async fn from_provider(provider: AnyNetworkProvider) -> Result<Self, EvmError> {
+        let chain_id = provider
+            .get_chain_id()
+            .await
+            .map_err(|_| InitError::BlockFetchError("Failed to fetch chain ID".to_string()))?;
+        Ok(Self {
+            provider,
+            chain_id,
+            blocks: Mutex::new(HashMap::new()),
+            rate_limiter: None,
+        })
+    }
+
+    /// Limits the upstream lookups of all built EVMs together to `requests_per_second`
+    /// on average, `burst` at once
+    ///
+    /// The EVMs share one token bucket, so the limit holds for the endpoint however
+    /// many EVMs run concurrently, see
+    /// [`EvmBuilder::with_rpc_rate_limit`](super::EvmBuilder::with_rpc_rate_limit).
+    /// Header lookups of the factory are not limited.
+    pub fn with_rpc_rate_limit(self, requests_per_second: u32, burst: u32) -> Self {
+        Self {
+            rate_limiter: Some(RpcRateLimiter::new(requests_per_second, burst)),
+            ..self
+        }
+    }
+
+    /// Returns the limiter shared by the built EVMs, if a rate limit was set
+    ///
+    /// Attach clones of it to EVMs built elsewhere against the same endpoint to have
+    /// them count against the same limit.
+    pub fn rate_limiter(&self) -> Option<&RpcRateLimiter> {
+        self.rate_limiter.as_ref()
+    }
+
+    /// Returns the shared provider
+    pub fn provider(&self) -> &AnyNetworkProvider {
+        &self.provider
+    }
+
+    /// Returns the chain ID looked up at construction
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    /// Resolves the context of block `block_number`, fetching each header only once
+    pub async fn block_context(&self, block_number: u64) -> Result<BlockContext, EvmError> {
+        if let Some(block) = self.cached_block(block_number) {
+            return Ok(block);
+        }
+        let block =
+            get_block_header(&self.provider, self.chain_id, Some(block_number), None).await?;
+        self.blocks
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(block_number, block);
+        Ok(block)
+    }
+
+    /// Builds an EVM at block `block_number` with `inspector`
+    ///
+    /// Configured like [`EvmBuilder::build`](super::EvmBuilder::build) without a
+    /// coinbase or historical fallback; the only RPC request is the header lookup, and
+    /// only for blocks this factory has not resolved before. The EVM shares the
+    /// factory's rate limit, if one was set.
+    pub async fn evm_at_block<INSP>(
+        &self,
+        block_number: u64,
+        inspector: INSP,
+    ) -> Result<InspectorEvm<INSP>, EvmError>
+    where
+        INSP: TraceInspector<MainnetContext<CacheDB<AllDBType>>>,
+    {
+        let block = self.block_context(block_number).await?;
+        let evm = evm_from_provider(
+            self.provider.clone(),
+            block,
+            BlockId::Number(BlockNumberOrTag::Number(block.number)),
+            None,
+            None,
+            inspector,
+        )?;
+        Ok(match &self.rate_limiter {
+            Some(limiter) => evm.with_rate_limiter(limiter.clone()),
+            None => evm,
+        })
+    }
+
+    fn cached_block(&self, block_number: u64) -> Option<BlockContext> {
+        self.blocks
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(&block_number)
+            .copied()
+    }
+}
@@ -0,0 +1,183 @@
+//! Failover across several RPC endpoints
+//!
+//! [`EvmBuilder::new_alloy_multi`](super::EvmBuilder::new_alloy_multi) connects to a
+//! list of endpoints serving the same chain. Requests go to one endpoint at a time.
+//! When it fails at the transport level (refused or dropped connections, timeouts,
+//! HTTP errors), the request is sent to the next endpoint in the list, which then
+//! serves all following requests. Each request tries every endpoint at most once.
+//! JSON-RPC error responses are answers of the node and are returned as they are.
+
+use std::{
+    future::poll_fn,
+    sync::{atomic::Ordering, Arc},
+    task::{Context, Poll},
+};
+
+use alloy::{
+    network::AnyNetwork,
+    providers::{Provider, ProviderBuilder, RootProvider},
+    rpc::{
+        client::{BuiltInConnectionString, RpcClient},
+        json_rpc::{RequestPacket, ResponsePacket, RpcError},
+    },
+    transports::{BoxTransport, TransportError, TransportErrorKind, TransportFut},
+};
+use tower::Service;
+
+use super::get_provider;
+use crate::{
+    errors::{EvmError, InitError},
+    types::AnyNetworkProvider,
+    wrap_db::EndpointState,
+};
+
+/// Endpoint of a [`FailoverTransport`]
+struct Endpoint {
+    /// Position in the configured list
+    index: usize,
+    transport: BoxTransport,
+}
+
+/// Transport sending each request to the active endpoint, failing over to the next
+#[derive(Clone)]
+pub(crate) struct FailoverTransport {
+    /// Endpoints in failover order
+    endpoints: Arc<[Endpoint]>,
+    state: Arc<EndpointState>,
+}
+
+impl FailoverTransport {
+    async fn dispatch(self, packet: RequestPacket) -> Result<ResponsePacket, TransportError> {
+        let active = self.state.active.load(Ordering::Relaxed);
+        let start = self
+            .endpoints
+            .iter()
+            .position(|endpoint| endpoint.index == active)
+            .unwrap_or(0);
+        let mut failure: Option<(usize, TransportError)> = None;
+        for offset in 0..self.endpoints.len() {
+            let endpoint = &self.endpoints[(start + offset) % self.endpoints.len()];
+            if let Some((failed, _)) = &failure {
+                self.switch(*failed, endpoint.index);
+            }
+            let mut transport = endpoint.transport.clone();
+            let result = match poll_fn(|cx| transport.poll_ready(cx)).await {
+                Ok(()) => transport.call(packet.clone()).await,
+                Err(error) => Err(error),
+            };
+            match result {
+                Err(error @ RpcError::Transport(_)) => failure = Some((endpoint.index, error)),
+                result => return result,
+            }
+        }
+        Err(failure.map_or_else(
+            || TransportErrorKind::custom_str("No RPC endpoint"),
+            |(_, error)| error,
+        ))
+    }
+
+    /// Moves the active endpoint from `from` to `to`
+    ///
+    /// Requests failing concurrently on the same endpoint switch and count once.
+    fn switch(&self, from: usize, to: usize) {
+        let switched = self
+            .state
+            .active
+            .compare_exchange(from, to, Ordering::AcqRel, Ordering::Relaxed)
+            .is_ok();
+        if switched {
+            self.state.failovers.fetch_add(1, Ordering::Relaxed);
+            #[cfg(feature = "tracing-logs")]
+            tracing::warn!(from, to, "RPC endpoint failed, failing over");
+        }
+    }
+}
+
+impl Service<RequestPacket> for FailoverTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, packet: RequestPacket) -> Self::Future {
+        Box::pin(self.clone().dispatch(packet))
+    }
+}
+
+/// Connects to `rpc_url`, failing over to `fallback_urls` when any are given
+///
+/// Returns the endpoint selection to report in the database statistics, `None` for a
+/// single endpoint.
+pub(crate) async fn connect_endpoints(
+    rpc_url: &str,
+    fallback_urls: &[String],
+) -> Result<(AnyNetworkProvider, Option<Arc<EndpointState>>), EvmError> {
+    if fallback_urls.is_empty() {
+        return Ok((get_provider(rpc_url).await?, None));
+    }
+    let urls = std::iter::once(rpc_url).chain(fallback_urls.iter().map(String::as_str));
+    let (provider, state) = failover_provider(urls).await?;
+    Ok((provider, Some(state)))
+}
+
+/// Connects to `urls` in order and builds a provider failing over between them
+///
+/// Endpoints that cannot be reached now are left out; all others must serve the same
+/// chain. Errors name endpoints by position, as URLs often hold API keys.
+async fn failover_provider<'a>(
+    urls: impl IntoIterator<Item = &'a str>,
+) -> Result<(AnyNetworkProvider, Arc<EndpointState>), EvmError> {
+    let mut endpoints = Vec::new();
+    // First reachable endpoint and its chain ID
+    let mut chain: Option<(usize, u64)> = None;
+    for (index, url) in urls.into_iter().enumerate() {
+        let (transport, chain_id) = match probe(url).await {
+            Ok(probed) => probed,
+            Err(_error) => {
+                #[cfg(feature = "tracing-logs")]
+                tracing::warn!(endpoint = index, error = %_error, "RPC endpoint unreachable");
+                continue;
+            }
+        };
+        match chain {
+            Some((first, expected)) if chain_id != expected => {
+                return Err(InitError::ChainId(format!(
+                    "RPC endpoint {index} serves chain {chain_id}, endpoint {first} serves chain {expected}"
+                ))
+                .into());
+            }
+            Some(_) => {}
+            None => chain = Some((index, chain_id)),
+        }
+        endpoints.push(Endpoint { index, transport });
+    }
+
+    let Some(first) = endpoints.first() else {
+        return Err(InitError::InvalidRpcUrl("No reachable RPC endpoint".to_string()).into());
+    };
+    let state = Arc::new(EndpointState::default());
+    state.active.store(first.index, Ordering::Relaxed);
+    let transport = FailoverTransport {
+        endpoints: endpoints.into(),
+        state: state.clone(),
+    };
+    let provider = ProviderBuilder::new()
+        .network::<AnyNetwork>()
+        .connect_client(RpcClient::new(transport, false));
+    Ok((provider, state))
+}
+
+/// Connects to `url` and looks up the chain it serves
+async fn probe(url: &str) -> Result<(BoxTransport, u64), TransportError> {
+    let transport = url
+        .parse::<BuiltInConnectionString>()?
+        .connect_boxed()
+        .await?;
+    let chain_id = RootProvider::<AnyNetwork>::new(RpcClient::new(transport.clone(), false))
+        .get_chain_id()
+        .await?;
+    Ok((transport, chain_id))
+}
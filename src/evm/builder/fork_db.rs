@@ -13,8 +13,11 @@ pub use foundry_fork_db::SharedBackend;
 use foundry_fork_db::{cache::BlockchainDbMeta, BlockchainDb};
 use std::sync::Arc;
 
-use super::{get_block, get_provider, EvmBuilder};
-use crate::{errors::EvmError, TraceEvm, TraceInspector};
+use super::{
+    apply_historical_fallback, configure_context, failover::connect_endpoints, get_block,
+    get_block_context, get_block_context_by_tag, get_block_env, get_provider, EvmBuilder,
+};
+use crate::{errors::EvmError, BackendKind, TraceEvm, TraceInspector};
 use alloy::{
     eips::{BlockId, BlockNumberOrTag},
     network::AnyNetwork,
@@ -64,11 +67,42 @@ impl EvmBuilder<SharedBackend, NoOpInspector> {
     pub fn new_shared(url: &str) -> Self {
         Self {
             rpc_url: url.to_string(),
+            fallback_urls: Vec::new(),
             block_number: None,
+            block_hash: None,
+            block_tag: None,
+            coinbase: None,
+            historical_fallback: None,
+            local_node: false,
+            warm_cache: None,
+            strict_validation: false,
             inspector: NoOpInspector,
             _marker: std::marker::PhantomData,
         }
     }
+
+    /// Creates a new EVM builder for SharedBackend reading through several RPC endpoints
+    ///
+    /// Endpoints are connected, checked and failed over as described for
+    /// [`EvmBuilder::new_alloy_multi`]. The backend thread reads through the failover
+    /// provider, but SharedBackend EVMs have no database statistics to report the
+    /// endpoint in use.
+    ///
+    /// # Example
+    /// ```rust
+    /// let builder = EvmBuilder::new_shared_multi(vec![
+    ///     "https://eth.llamarpc.com".to_string(),
+    ///     "https://rpc.ankr.com/eth".to_string(),
+    /// ]);
+    /// ```
+    pub fn new_shared_multi(urls: Vec<String>) -> Self {
+        let mut urls = urls.into_iter();
+        let rpc_url = urls.next().unwrap_or_default();
+        Self {
+            fallback_urls: urls.collect(),
+            ..Self::new_shared(&rpc_url)
+        }
+    }
 }
 
 /// SharedBackend-specific build implementation
@@ -119,24 +153,50 @@ impl<INSP> EvmBuilder<SharedBackend, INSP> {
         // Destructure builder to extract configuration
         let EvmBuilder {
             rpc_url,
+            fallback_urls,
             block_number,
+            block_hash,
+            block_tag,
+            coinbase,
+            historical_fallback,
+            local_node: _,
+            warm_cache: _,
+            rate_limiter: _,
+            strict_validation,
             inspector,
             _marker,
         } = self;
 
         // Step 1: Create provider with automatic protocol detection
-        let provider = get_provider(&rpc_url).await?;
+        let (provider, _) = connect_endpoints(&rpc_url, &fallback_urls).await?;
 
         // Step 2: Fetch essential blockchain data
-        let (chain_id, block_number, timestamp) = get_block(&provider, block_number).await?;
-        let block_id = BlockId::Number(BlockNumberOrTag::Number(block_number));
+        let mut block = match block_tag {
+            Some(tag) => get_block_context_by_tag(&provider, tag).await?,
+            None => get_block_context(&provider, block_number, block_hash).await?,
+        };
+        // Blocks selected by tag are pinned to the hash they resolved to, so the
+        // backend thread never follows the tag
+        let pinned_hash = block_hash.or(block_tag.map(|_| block.hash));
+        if let Some(fallback) = historical_fallback {
+            block = apply_historical_fallback(&provider, block, fallback).await?;
+        }
+        let block_id = match pinned_hash {
+            Some(hash) if hash == block.hash => BlockId::hash(hash),
+            _ => BlockId::Number(BlockNumberOrTag::Number(block.number)),
+        };
 
         // Step 3: Create block environment for metadata
-        let block_env = BlockEnv {
-            number: block_number,
-            timestamp,
-            ..BlockEnv::default()
+        let strict_env = if strict_validation {
+            Some(get_block_env(&provider, &block).await?)
+        } else {
+            None
         };
+        let block_env = strict_env.clone().unwrap_or_else(|| BlockEnv {
+            number: block.number,
+            timestamp: block.timestamp,
+            ..BlockEnv::default()
+        });
 
         // Step 4: Initialize blockchain database with metadata
         let meta = BlockchainDbMeta::new(block_env, rpc_url);
@@ -156,20 +216,13 @@ impl<INSP> EvmBuilder<SharedBackend, INSP> {
 
         // Step 7: Create and configure EVM context
         let mut ctx = Context::mainnet().with_db(cache_db);
-        let cfg = &mut ctx.cfg;
-
-        // Network configuration
-        cfg.chain_id = chain_id;
-
-        // Disable restrictions for simulation environment
-        cfg.disable_eip3607 = true; // Allow zero-address transactions
-        cfg.limit_contract_code_size = None; // Remove contract size limits
-        cfg.disable_block_gas_limit = true; // Remove gas limit restrictions
-        cfg.disable_base_fee = true; // Disable EIP-1559 base fee
+        configure_context(&mut ctx, &block, strict_env, coinbase);
 
         // Step 8: Build final EVM instance with inspector
         let evm = ctx.build_mainnet_with_inspector(inspector);
-        Ok(TraceEvm::new(evm))
+        Ok(TraceEvm::new(evm)
+            .with_block_context(block)
+            .with_backend(BackendKind::FoundryFork))
     }
 }
 
@@ -426,35 +479,59 @@ pub async fn create_evm_from_shared_backend<INSP, P>(
     provider: &P,
     inspector: INSP,
 ) -> Result<TraceEvm<CacheDB<SharedBackend>, INSP>, EvmError>
+where
+    P: Provider<AnyNetwork>,
+    INSP: TraceInspector<MainnetContext<CacheDB<SharedBackend>>>,
+{
+    evm_from_shared_backend(shared_backend, provider, inspector, false).await
+}
+
+/// Creates an EVM instance from an existing SharedBackend with strict validation
+///
+/// Same as [`create_evm_from_shared_backend`], but keeps consensus restrictions
+/// enabled and populates the block environment from the header, see
+/// [`EvmBuilder::with_strict_validation`].
+pub async fn create_evm_from_shared_backend_strict<INSP, P>(
+    shared_backend: SharedBackend,
+    provider: &P,
+    inspector: INSP,
+) -> Result<TraceEvm<CacheDB<SharedBackend>, INSP>, EvmError>
+where
+    P: Provider<AnyNetwork>,
+    INSP: TraceInspector<MainnetContext<CacheDB<SharedBackend>>>,
+{
+    evm_from_shared_backend(shared_backend, provider, inspector, true).await
+}
+
+async fn evm_from_shared_backend<INSP, P>(
+    shared_backend: SharedBackend,
+    provider: &P,
+    inspector: INSP,
+    strict_validation: bool,
+) -> Result<TraceEvm<CacheDB<SharedBackend>, INSP>, EvmError>
 where
     P: Provider<AnyNetwork>,
     INSP: TraceInspector<MainnetContext<CacheDB<SharedBackend>>>,
 {
     // Extract chain ID and block information from the SharedBackend
-    let (chain_id, block_number, timestamp) = get_block(&provider, None).await?;
+    let block = get_block_context(&provider, None, None).await?;
+    let block_env = if strict_validation {
+        Some(get_block_env(provider, &block).await?)
+    } else {
+        None
+    };
     // Create cache layer on top of SharedBackend
     let cache_db: CacheDB<SharedBackend> = CacheDB::new(shared_backend);
 
     // Create and configure EVM context
     let mut ctx = Context::mainnet().with_db(cache_db);
-    let cfg = &mut ctx.cfg;
-
-    // Network configuration
-    cfg.chain_id = chain_id;
-
-    // Disable restrictions for simulation environment
-    cfg.disable_eip3607 = true; // Allow zero-address transactions
-    cfg.limit_contract_code_size = None; // Remove contract size limits
-    cfg.disable_block_gas_limit = true; // Remove gas limit restrictions
-    cfg.disable_base_fee = true; // Disable EIP-1559 base fee
-
-    // Set block environment from SharedBackend metadata
-    ctx.block.number = block_number;
-    ctx.block.timestamp = timestamp;
+    configure_context(&mut ctx, &block, block_env, None);
 
     // Build final EVM instance with inspector
     let evm = ctx.build_mainnet_with_inspector(inspector);
-    Ok(TraceEvm::new(evm))
+    Ok(TraceEvm::new(evm)
+        .with_block_context(block)
+        .with_backend(BackendKind::FoundryFork))
 }
 
 /// Creates an EVM instance with no inspector from an existing SharedBackend
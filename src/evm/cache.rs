@@ -0,0 +1,300 @@
+//! Memoization of identical simulations
+//!
+//! Services often receive bursts of the same request: the same transactions against
+//! the same block. [`TraceEvm::trace_transactions_cached`] looks such a batch up in a
+//! [`SimulationCache`] before executing it and stores the reports of fresh runs.
+//!
+//! Batches are keyed by a keccak256 hash of everything that determines their outcome:
+//! the EVM configuration (chain, hardfork, relaxed checks, executing and pinned block,
+//! see [`TraceEvm::config`]), the chunk size, the inspector settings shaping the trace
+//! (depth limit, capture limits, mapping resolution and log filter), the state
+//! overrides, the batch options and the content and trace level of every transaction,
+//! in order. Execution is deterministic for a given key, so a cached report serializes
+//! exactly like a fresh one.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    errors::EvmError, evm::TraceEvm, inspectors::tx_inspector::TxInspector,
+    report::SimulationReport, types::SimulationBatch,
+};
+use alloy::primitives::{keccak256, B256};
+use revm::database::{CacheDB, DatabaseRef};
+
+/// Bounded cache of simulation reports, keyed by batch
+///
+/// Shared by reference and safe to use from several threads, e.g. one cache for all
+/// EVMs of an `EvmFactory`. Reports are stored serialized; when full, the least
+/// recently used batch is evicted.
+#[derive(Debug)]
+pub struct SimulationCache {
+    capacity: usize,
+    ttl: Option<Duration>,
+    entries: Mutex<HashMap<B256, CacheEntry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+/// Reports of one batch
+#[derive(Debug)]
+struct CacheEntry {
+    /// JSON array of the batch's reports
+    reports: Vec<u8>,
+    /// Block the EVM was pinned to
+    block_number: u64,
+    inserted: Instant,
+    last_used: Instant,
+}
+
+impl SimulationCache {
+    /// Creates a cache holding the reports of up to `capacity` batches
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ttl: None,
+            entries: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Expires cached batches `ttl` after they were stored
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Number of batches answered from the cache
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of batches that had to be executed
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Number of cached batches, including expired ones not yet evicted
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    /// Whether no batch is cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops the batches simulated on EVMs pinned to `block_number`, returning how many
+    ///
+    /// Use this when a block is reorganized away, or state below the EVMs changed.
+    pub fn invalidate_block(&self, block_number: u64) -> usize {
+        let mut entries = self.entries();
+        let before = entries.len();
+        entries.retain(|_, entry| entry.block_number != block_number);
+        before - entries.len()
+    }
+
+    /// Drops all cached batches; the counters are kept
+    pub fn clear(&self) {
+        self.entries().clear();
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, HashMap<B256, CacheEntry>> {
+        // A panic while holding the lock cannot leave an entry half-written
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Returns the reports cached under `key`, counting a hit or a miss
+    fn get(&self, key: &B256) -> Option<Vec<SimulationReport>> {
+        let reports = {
+            let mut entries = self.entries();
+            let expired = match (entries.get(key), self.ttl) {
+                (Some(entry), Some(ttl)) => entry.inserted.elapsed() >= ttl,
+                _ => false,
+            };
+            if expired {
+                entries.remove(key);
+            }
+            entries.get_mut(key).and_then(|entry| {
+                entry.last_used = Instant::now();
+                serde_json::from_slice(&entry.reports).ok()
+            })
+        };
+        let counter = if reports.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        reports
+    }
+
+    /// Stores the reports of a batch, evicting the least recently used one if full
+    fn insert(&self, key: B256, block_number: u64, reports: &[SimulationReport]) {
+        if self.capacity == 0 {
+            return;
+        }
+        let Ok(reports) = serde_json::to_vec(reports) else {
+            return;
+        };
+        let mut entries = self.entries();
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        let now = Instant::now();
+        entries.insert(
+            key,
+            CacheEntry {
+                reports,
+                block_number,
+                inserted: now,
+                last_used: now,
+            },
+        );
+    }
+}
+
+impl<DB> TraceEvm<CacheDB<DB>, TxInspector>
+where
+    DB: DatabaseRef,
+{
+    /// Traces `batch` unless `cache` already holds its reports
+    ///
+    /// Returns one report per transaction, in batch order. A batch is only stored when
+    /// every transaction could be traced, as failures to trace are usually transient
+    /// (e.g. RPC errors). The key cannot capture a transaction hook, so while one is
+    /// set the cache is bypassed and its counters left unchanged. Batches requesting a
+    /// timeline bypass it too, as the timeline is not cached.
+    ///
+    /// Only the reports are cached. A hit executes nothing, so afterwards the
+    /// `take_last_*` accessors (batch metrics, fee accounting, timeline, remote
+    /// dependencies) return `None` and `take_last_executed_txs` one `None` per
+    /// transaction, rather than the outputs of an earlier batch.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use revm_trace::{
+    ///     create_evm_with_tracer, types::SimulationBatch, SimulationCache, TxInspector,
+    /// };
+    /// use std::time::Duration;
+    ///
+    /// let cache = SimulationCache::new(1_000).with_ttl(Duration::from_secs(12));
+    /// let mut evm = create_evm_with_tracer("https://eth.llamarpc.com", TxInspector::new()).await?;
+    /// let batch = SimulationBatch {
+    ///     transactions: vec![/* transactions */],
+    ///     is_stateful: false,
+    ///     ..Default::default()
+    /// };
+    /// let reports = evm.trace_transactions_cached(batch.clone(), &cache);
+    /// // Answered from the cache
+    /// let reports = evm.trace_transactions_cached(batch, &cache);
+    /// assert_eq!(cache.hits(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn trace_transactions_cached(
+        &mut self,
+        batch: SimulationBatch,
+        cache: &SimulationCache,
+    ) -> Vec<Result<SimulationReport, EvmError>> {
+        if self.has_tx_hook() || batch.build_timeline {
+            return self.trace_reports(batch);
+        }
+        let Some(key) = self.simulation_key(&batch) else {
+            return self.trace_reports(batch);
+        };
+        if let Some(reports) = cache.get(&key) {
+            self.clear_batch_outputs(reports.len());
+            return reports.into_iter().map(Ok).collect();
+        }
+
+        let results = self.trace_reports(batch);
+        if let Ok(reports) = results.iter().cloned().collect::<Result<Vec<_>, _>>() {
+            cache.insert(key, self.block_context.number, &reports);
+        }
+        results
+    }
+
+    /// Traces `batch` and builds the report of every transaction
+    fn trace_reports(&mut self, batch: SimulationBatch) -> Vec<Result<SimulationReport, EvmError>> {
+        let mut reports = Vec::with_capacity(batch.transactions.len());
+        self.trace_transactions_with(batch, |_, tx, _, result| {
+            reports.push(result.map(|(result, storage_diff, trace)| {
+                SimulationReport::new(tx, &result, storage_diff, trace)
+            }));
+        });
+        reports
+    }
+
+    /// Drops the side outputs of the previous batch, which a cache hit does not replace
+    fn clear_batch_outputs(&mut self, len: usize) {
+        self.last_batch_metrics = None;
+        self.set_last_fee_accounting(None);
+        self.set_last_timeline(None);
+        self.last_executed_txs = vec![None; len];
+        self.last_remote_dependencies = None;
+    }
+
+    /// Hashes everything that determines the outcome of `batch` on this EVM
+    ///
+    /// `None` if the key cannot be encoded, in which case the cache is bypassed.
+    fn simulation_key(&self, batch: &SimulationBatch) -> Option<B256> {
+        let overrides = batch.overrides.as_ref().map(|state| {
+            let mut storages: Vec<_> = state.storages.iter().collect();
+            storages.sort_by_key(|(address, _)| **address);
+            let mut balances: Vec<_> = state.balances.iter().collect();
+            balances.sort_by_key(|(address, _)| **address);
+            (storages, balances)
+        });
+        fn sorted<T: Ord>(set: &HashSet<T>) -> Vec<&T> {
+            let mut items: Vec<_> = set.iter().collect();
+            items.sort();
+            items
+        }
+        let inspector = &self.inspector;
+        let log_filter = inspector.log_filter().map(|filter| {
+            (
+                filter.addresses.as_ref().map(sorted),
+                filter.topics0.as_ref().map(sorted),
+            )
+        });
+        let inspector_config = (
+            inspector.max_trace_depth(),
+            inspector.max_input_capture(),
+            inspector.max_output_capture(),
+            inspector.mapping_resolution(),
+            inspector.max_preimages(),
+            log_filter,
+        );
+        let key = serde_json::to_vec(&(
+            self.config(),
+            self.chunk_size,
+            inspector_config,
+            overrides,
+            batch.is_stateful,
+            batch.coinbase,
+            batch.failure_policy,
+            batch.empty_code_call_policy,
+            &batch.transactions,
+            &batch.targets,
+        ))
+        .ok()?;
+        Some(keccak256(key))
+    }
+}
@@ -0,0 +1,130 @@
+//! Chunked execution of large batches
+//!
+//! Batches of thousands of transactions keep growing the inspector's transient caches
+//! until they return. With a chunk size set, `trace_transactions` runs a batch in
+//! chunks of that many transactions and does its housekeeping in between; results are
+//! the same as for an unchunked run. [`TraceEvm::trace_transactions_chunked`] also
+//! hands results over a chunk at a time, so callers can process or persist them
+//! before the next chunk runs.
+//!
+//! Chunk boundaries are invisible to the transactions: a stateful batch carries its
+//! state over, and a stateless batch still executes every transaction in isolation.
+
+use crate::{
+    evm::TraceEvm,
+    traits::{ResetDB, TraceInspector, TraceResult},
+    types::SimulationBatch,
+};
+use revm::{
+    database::{Database, DatabaseCommit},
+    handler::MainnetContext,
+};
+
+impl<DB, INSP> TraceEvm<DB, INSP>
+where
+    DB: Database,
+{
+    /// Runs batches in chunks of `chunk_size` transactions
+    ///
+    /// See [`set_chunk_size`](Self::set_chunk_size).
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use revm_trace::{create_evm_with_tracer, TxInspector};
+    ///
+    /// let evm = create_evm_with_tracer("https://eth.llamarpc.com", TxInspector::new())
+    ///     .await?
+    ///     .with_chunk_size(1_000);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.set_chunk_size(Some(chunk_size));
+        self
+    }
+
+    /// Sets the number of transactions per chunk, `None` (or 0) to run batches in one go
+    ///
+    /// Between two chunks of a batch, the inspector's slot cache is cleared; it is
+    /// refilled from the database, which holds the committed state. With the
+    /// `tracing-logs` feature, every finished chunk is logged at debug level with the
+    /// number of transactions executed so far. A `WarmCache` needs no flushing, as
+    /// upstream answers are added to it as they arrive.
+    pub fn set_chunk_size(&mut self, chunk_size: Option<usize>) {
+        self.chunk_size = chunk_size.filter(|&size| size > 0);
+    }
+
+    /// Number of transactions per chunk, `None` if batches run in one go
+    pub fn chunk_size(&self) -> Option<usize> {
+        self.chunk_size
+    }
+
+    /// Whether a chunk ends after `executed` of a batch's `len` transactions, with
+    /// more to follow
+    pub(crate) fn chunk_ends_at(&self, executed: usize, len: usize) -> bool {
+        self.chunk_size
+            .is_some_and(|size| executed < len && executed % size == 0)
+    }
+}
+
+impl<DB, INSP> TraceEvm<DB, INSP>
+where
+    DB: Database + DatabaseCommit + ResetDB,
+    INSP: TraceInspector<MainnetContext<DB>>,
+{
+    /// Traces a batch, handing over the results one chunk at a time
+    ///
+    /// `on_chunk` receives the batch index of the chunk's first transaction and the
+    /// chunk's results, in order, right after the chunk ran and before the next one
+    /// starts. Without a chunk size the whole batch is a single chunk. Everything else
+    /// behaves as for
+    /// [`trace_transactions`](crate::traits::TransactionTrace::trace_transactions).
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use revm_trace::{create_evm_with_tracer, SimulationBatch, TxInspector};
+    ///
+    /// let mut evm = create_evm_with_tracer("https://eth.llamarpc.com", TxInspector::new())
+    ///     .await?
+    ///     .with_chunk_size(1_000);
+    /// let batch = SimulationBatch {
+    ///     transactions: vec![/* transactions */],
+    ///     is_stateful: true,
+    ///     ..Default::default()
+    /// };
+    /// evm.trace_transactions_chunked(batch, |first, results| {
+    ///     println!("transactions {first}..{}", first + results.len());
+    /// });
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn trace_transactions_chunked<F>(&mut self, batch: SimulationBatch, mut on_chunk: F)
+    where
+        F: FnMut(usize, Vec<TraceResult<INSP::Output>>),
+    {
+        let len = batch.transactions.len();
+        let capacity = self.chunk_size.unwrap_or(len).min(len);
+        let mut first = 0;
+        let mut chunk = Vec::with_capacity(capacity);
+        self.trace_transactions_with(batch, |index, _, _, result| {
+            chunk.push(result);
+            let executed = index + 1;
+            if executed == len || executed % capacity == 0 {
+                on_chunk(
+                    first,
+                    std::mem::replace(&mut chunk, Vec::with_capacity(capacity)),
+                );
+                first = executed;
+            }
+        });
+    }
+
+    /// Housekeeping between two chunks of a batch, after `executed` transactions
+    pub(crate) fn finish_chunk(&mut self, _executed: usize) {
+        self.inspector.reset_slot_cache();
+        #[cfg(feature = "tracing-logs")]
+        tracing::debug!(executed = _executed, "finished batch chunk");
+    }
+}
@@ -0,0 +1,150 @@
+//! Configuration introspection and adjustment
+//!
+//! [`TraceEvm::config`] reports how an EVM is configured (network, hardfork, relaxed
+//! checks, block fields and backend) without reaching into revm's context types, and
+//! the setters below change the block fields and chain ID between batches.
+
+use crate::{
+    errors::{EvmError, RuntimeError},
+    evm::TraceEvm,
+    types::BlockContext,
+};
+use alloy::primitives::Address;
+use revm::{database::Database, primitives::hardfork::SpecId};
+use serde::Serialize;
+
+/// Database backend an EVM reads state from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub enum BackendKind {
+    /// Alloy provider wrapped in `MyWrapDatabaseAsync` (the builder default)
+    AlloyDb,
+    /// Foundry-fork-db `SharedBackend`
+    FoundryFork,
+    /// Any database passed to `TraceEvm::new` directly
+    #[default]
+    Custom,
+}
+
+/// Validation checks that are relaxed, `true` meaning the check is skipped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ConfigFlags {
+    /// Transaction nonces are not checked against the sender's
+    pub disable_nonce_check: bool,
+    /// Senders need not cover value and gas
+    pub disable_balance_check: bool,
+    /// The base fee is neither required nor charged
+    pub disable_base_fee: bool,
+    /// Transactions may exceed the block gas limit
+    pub disable_block_gas_limit: bool,
+    /// Accounts with code may send transactions (EIP-3607)
+    pub disable_eip3607: bool,
+    /// Maximum contract code size, `None` for the hardfork default
+    pub limit_contract_code_size: Option<usize>,
+}
+
+/// Snapshot of an EVM's configuration, see [`TraceEvm::config`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EvmConfigView {
+    /// Chain ID transactions are built with
+    pub chain_id: u64,
+    /// Active hardfork
+    pub spec: SpecId,
+    /// Relaxed validation checks
+    pub flags: ConfigFlags,
+    /// Block number seen by `NUMBER`
+    pub block_number: u64,
+    /// Block timestamp seen by `TIMESTAMP`
+    pub timestamp: u64,
+    /// Base fee seen by `BASEFEE`
+    pub basefee: u64,
+    /// Block gas limit
+    pub gas_limit: u64,
+    /// Block beneficiary seen by `COINBASE`
+    pub coinbase: Address,
+    /// Database backend
+    pub backend: BackendKind,
+    /// Block the database is pinned to
+    pub pinned_block: BlockContext,
+}
+
+impl<DB, INSP> TraceEvm<DB, INSP>
+where
+    DB: Database,
+{
+    /// Returns the current configuration
+    ///
+    /// Block fields reflect the executing block, which moves with
+    /// [`advance_block`](Self::advance_block) and the setters below, while
+    /// `pinned_block` is the block state is read at. Serialize the view to log
+    /// exactly how an EVM was set up.
+    pub fn config(&self) -> EvmConfigView {
+        let cfg = &self.evm.ctx.cfg;
+        let block = &self.evm.ctx.block;
+        EvmConfigView {
+            chain_id: cfg.chain_id,
+            spec: cfg.spec,
+            flags: ConfigFlags {
+                disable_nonce_check: cfg.disable_nonce_check,
+                disable_balance_check: cfg.disable_balance_check,
+                disable_base_fee: cfg.disable_base_fee,
+                disable_block_gas_limit: cfg.disable_block_gas_limit,
+                disable_eip3607: cfg.disable_eip3607,
+                limit_contract_code_size: cfg.limit_contract_code_size,
+            },
+            block_number: block.number,
+            timestamp: block.timestamp,
+            basefee: block.basefee,
+            gas_limit: block.gas_limit,
+            coinbase: block.beneficiary,
+            backend: self.backend,
+            pinned_block: self.block_context,
+        }
+    }
+
+    /// Sets the chain ID used for `CHAINID` and for building transactions
+    ///
+    /// The recorded block context follows, so chain presets and system contract
+    /// lookups match the new network.
+    pub fn set_chain_id(&mut self, chain_id: u64) {
+        self.evm.ctx.cfg.chain_id = chain_id;
+        self.block_context.chain_id = chain_id;
+    }
+
+    /// Sets the timestamp seen by subsequent transactions
+    ///
+    /// Like [`advance_block`](Self::advance_block), this only moves the execution
+    /// context; state is still read at the pinned block.
+    pub fn set_timestamp(&mut self, timestamp: u64) {
+        self.evm.ctx.block.timestamp = timestamp;
+    }
+
+    /// Sets the base fee charged to subsequent transactions
+    ///
+    /// # Errors
+    /// `RuntimeError::InvalidInput` if base fee enforcement is disabled, since the
+    /// value would not be charged; the base fee is left unchanged.
+    pub fn set_basefee(&mut self, basefee: u64) -> Result<(), EvmError> {
+        if self.evm.ctx.cfg.disable_base_fee {
+            return Err(RuntimeError::InvalidInput(
+                "Cannot set the base fee while base fee enforcement is disabled".to_string(),
+            )
+            .into());
+        }
+        self.evm.ctx.block.basefee = basefee;
+        Ok(())
+    }
+
+    /// Sets the block beneficiary seen by `COINBASE`
+    ///
+    /// A batch's own `SimulationBatch::coinbase` still takes precedence while that
+    /// batch runs.
+    pub fn set_coinbase(&mut self, coinbase: Address) {
+        self.evm.ctx.block.beneficiary = coinbase;
+    }
+
+    /// Records the backend the EVM was built with
+    pub(crate) fn with_backend(mut self, backend: BackendKind) -> Self {
+        self.backend = backend;
+        self
+    }
+}
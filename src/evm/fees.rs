@@ -0,0 +1,252 @@
+//! EIP-1559 fee and burn accounting
+//!
+//! With base fee enforcement enabled (`cfg.disable_base_fee == false`),
+//! `trace_transactions` accounts for the fees of each batch as if it were one block:
+//! the amount burned (`base_fee * gas_used`), the priority fees credited to the
+//! coinbase and the base fee of the following block. The coinbase balance change of
+//! every transaction is reconciled with its tip plus the direct payments the inspector
+//! observed, and differences are reported as [`CoinbaseDiscrepancy`].
+//!
+//! The builders disable base fee enforcement, so transactions run at any gas price
+//! (0 unless a [tx hook](TraceEvm::set_tx_hook) sets one) and nothing is burned; no
+//! accounting is produced then. Enable it with `evm.cfg.disable_base_fee = false`.
+
+use crate::evm::{blocks::next_base_fee, TraceEvm};
+use alloy::primitives::{Address, I256, U256};
+use revm::{context::BlockEnv, database::Database};
+use serde::Serialize;
+
+/// Fees of one executed transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TxFees {
+    /// Gas used after refunds
+    pub gas_used: u64,
+    /// Price per gas paid by the sender
+    pub effective_gas_price: u128,
+    /// Amount burned, `base_fee * gas_used`
+    pub burned: U256,
+    /// Priority fee credited to the coinbase, `(effective_gas_price - base_fee) * gas_used`
+    pub tip: U256,
+    /// Value sent to the coinbase by calls, if the inspector tracks it
+    pub direct_payments: Option<U256>,
+    /// Change of the coinbase balance caused by the transaction
+    pub coinbase_delta: I256,
+}
+
+/// Coinbase balance change not explained by a transaction's tip and direct payments
+///
+/// Expected when value reaches the coinbase in ways the inspector does not track
+/// (e.g. SELFDESTRUCT), when the coinbase sends the transaction itself, or when the
+/// paying call was reverted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CoinbaseDiscrepancy {
+    /// Index of the transaction in the batch
+    pub tx_index: usize,
+    /// Tip plus direct payments
+    pub expected: U256,
+    /// Observed balance change
+    pub actual: I256,
+}
+
+/// Fee and burn accounting of one `trace_transactions` batch, treated as one block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FeeAccounting {
+    /// Base fee the batch executed with
+    pub base_fee: u64,
+    /// Coinbase the batch executed with
+    pub coinbase: Address,
+    /// Fees of each transaction, in batch order; `None` if it could not be executed
+    pub per_tx: Vec<Option<TxFees>>,
+    /// Total gas used
+    pub gas_used: u64,
+    /// Total amount burned
+    pub total_burned: U256,
+    /// Total priority fees credited to the coinbase
+    pub total_tips: U256,
+    /// Total value sent to the coinbase by calls, if the inspector tracks it
+    pub total_direct_payments: Option<U256>,
+    /// Total change of the coinbase balance
+    pub coinbase_delta: I256,
+    /// Base fee of the following block, per EIP-1559 with the batch as the parent block
+    pub next_base_fee: u64,
+    /// Transactions whose coinbase balance change differs from tip plus direct payments
+    ///
+    /// Only transactions with tracked direct payments are checked.
+    pub discrepancies: Vec<CoinbaseDiscrepancy>,
+}
+
+impl FeeAccounting {
+    /// Whether every checked coinbase balance change matched tip plus direct payments
+    pub fn is_consistent(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+}
+
+/// Accumulates the fees of a batch as its transactions execute
+pub(crate) struct FeeRecorder {
+    base_fee: u64,
+    gas_limit: u64,
+    coinbase: Address,
+    per_tx: Vec<Option<TxFees>>,
+}
+
+impl FeeRecorder {
+    /// Starts accounting for a batch executing in `block`
+    pub(crate) fn new(block: &BlockEnv) -> Self {
+        Self {
+            base_fee: block.basefee,
+            gas_limit: block.gas_limit,
+            coinbase: block.beneficiary,
+            per_tx: Vec::new(),
+        }
+    }
+
+    /// Records an executed transaction
+    pub(crate) fn record_tx(
+        &mut self,
+        effective_gas_price: u128,
+        gas_used: u64,
+        coinbase_before: U256,
+        coinbase_after: U256,
+        direct_payments: Option<U256>,
+    ) {
+        let gas = U256::from(gas_used);
+        let priority_fee = effective_gas_price.saturating_sub(self.base_fee as u128);
+        let coinbase_delta = if coinbase_after >= coinbase_before {
+            I256::from_raw(coinbase_after - coinbase_before)
+        } else {
+            -I256::from_raw(coinbase_before - coinbase_after)
+        };
+        self.per_tx.push(Some(TxFees {
+            gas_used,
+            effective_gas_price,
+            burned: U256::from(self.base_fee) * gas,
+            tip: U256::from(priority_fee) * gas,
+            direct_payments,
+            coinbase_delta,
+        }));
+    }
+
+    /// Records a transaction that could not be executed
+    pub(crate) fn skip_tx(&mut self) {
+        self.per_tx.push(None);
+    }
+
+    /// Completes the accounting of the batch
+    pub(crate) fn finish(self) -> FeeAccounting {
+        let executed = || self.per_tx.iter().flatten();
+        let gas_used = executed().map(|fees| fees.gas_used).sum();
+        let discrepancies = self
+            .per_tx
+            .iter()
+            .enumerate()
+            .filter_map(|(tx_index, fees)| {
+                let fees = fees.as_ref()?;
+                let expected = fees.tip + fees.direct_payments?;
+                (fees.coinbase_delta != I256::from_raw(expected)).then_some(CoinbaseDiscrepancy {
+                    tx_index,
+                    expected,
+                    actual: fees.coinbase_delta,
+                })
+            })
+            .collect();
+        FeeAccounting {
+            base_fee: self.base_fee,
+            coinbase: self.coinbase,
+            gas_used,
+            total_burned: executed().map(|fees| fees.burned).sum(),
+            total_tips: executed().map(|fees| fees.tip).sum(),
+            total_direct_payments: executed()
+                .map(|fees| fees.direct_payments)
+                .sum::<Option<U256>>(),
+            coinbase_delta: executed().map(|fees| fees.coinbase_delta).sum(),
+            next_base_fee: next_base_fee(self.base_fee, gas_used, self.gas_limit),
+            discrepancies,
+            per_tx: self.per_tx,
+        }
+    }
+}
+
+impl<DB, INSP> TraceEvm<DB, INSP>
+where
+    DB: Database,
+{
+    /// Takes the fee accounting of the most recent batch
+    ///
+    /// Returns `None` if base fee enforcement was disabled for that batch (see the
+    /// [module docs](crate::evm::fees)), its state overrides could not be applied, or the
+    /// accounting was already taken.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use revm_trace::{create_evm_with_tracer, TxInspector};
+    ///
+    /// let mut evm = create_evm_with_tracer("https://eth.llamarpc.com", TxInspector::new()).await?;
+    /// evm.cfg.disable_base_fee = false;
+    /// // ... evm.trace_transactions(batch) with fee-paying transactions ...
+    /// if let Some(fees) = evm.take_last_fee_accounting() {
+    ///     println!("burned {} wei, next base fee {}", fees.total_burned, fees.next_base_fee);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn take_last_fee_accounting(&mut self) -> Option<FeeAccounting> {
+        self.last_fee_accounting.take()
+    }
+
+    /// Stores the fee accounting of a completed batch
+    pub(crate) fn set_last_fee_accounting(&mut self, fees: Option<FeeAccounting>) {
+        self.last_fee_accounting = fees;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discrepancies() {
+        let block = BlockEnv {
+            basefee: 10,
+            gas_limit: 30_000_000,
+            ..Default::default()
+        };
+        let mut recorder = FeeRecorder::new(&block);
+        // Tip of 2 * 100 plus a direct payment of 50
+        recorder.record_tx(
+            12,
+            100,
+            U256::from(1_000),
+            U256::from(1_250),
+            Some(U256::from(50)),
+        );
+        recorder.skip_tx();
+        // Untracked direct payments are not checked
+        recorder.record_tx(10, 100, U256::from(1_250), U256::from(1_300), None);
+        // The coinbase paid for its own transaction
+        recorder.record_tx(
+            11,
+            100,
+            U256::from(1_300),
+            U256::from(1_000),
+            Some(U256::ZERO),
+        );
+
+        let fees = recorder.finish();
+        assert_eq!(fees.gas_used, 300);
+        assert_eq!(fees.total_burned, U256::from(3_000));
+        assert_eq!(fees.total_tips, U256::from(300));
+        assert_eq!(fees.total_direct_payments, None);
+        assert_eq!(fees.coinbase_delta, I256::ZERO);
+        assert_eq!(
+            fees.discrepancies,
+            vec![CoinbaseDiscrepancy {
+                tx_index: 3,
+                expected: U256::from(100),
+                actual: I256::try_from(-300i64).unwrap(),
+            }]
+        );
+        assert!(!fees.is_consistent());
+    }
+}
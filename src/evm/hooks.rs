@@ -0,0 +1,59 @@
+//! Transaction environment hooks
+//!
+//! `SimulationTx` only models the common transaction fields. A hook set with
+//! [`TraceEvm::set_tx_hook`] receives every `TxEnv` built by `trace_transactions` right
+//! before execution, so callers can set anything else revm supports: access lists,
+//! gas limits and prices, transaction types or authorization lists.
+
+use crate::{evm::TraceEvm, types::SimulationTx};
+use revm::{context::TxEnv, database::Database};
+
+/// Callback editing a transaction environment before execution
+///
+/// Receives the environment built from the simulated transaction and the transaction
+/// itself. Like the EVM, the hook stays on one thread and need not be `Send`.
+///
+/// `tx.origin` cannot be set apart from the sender: revm reads `ORIGIN` from
+/// `TxEnv::caller`, so changing `caller` changes both. Testing `tx.origin` based
+/// authorization takes a transaction from the authorized account that reaches the
+/// checked contract through an intermediate one.
+pub type TxHook = Box<dyn FnMut(&mut TxEnv, &SimulationTx)>;
+
+impl<DB, INSP> TraceEvm<DB, INSP>
+where
+    DB: Database,
+{
+    /// Sets a hook invoked for every simulated transaction before it executes
+    ///
+    /// The hook runs after the `TxEnv` is built from the [`SimulationTx`] (sender,
+    /// nonce, value, data, target and chain id) and may change any field. It replaces a
+    /// previously set hook and stays active until [`clear_tx_hook`](Self::clear_tx_hook).
+    /// Fields the hook sets are not validated beyond revm's own checks, so e.g. a
+    /// changed nonce can make the transaction fail.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use revm_trace::{create_evm_with_tracer, TxInspector};
+    ///
+    /// let mut evm = create_evm_with_tracer("https://eth.llamarpc.com", TxInspector::new()).await?;
+    /// evm.set_tx_hook(Box::new(|tx, _| tx.gas_limit = 1_000_000));
+    /// // ... evm.trace_transactions(batch) ...
+    /// evm.clear_tx_hook();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_tx_hook(&mut self, hook: TxHook) {
+        self.tx_hook = Some(hook);
+    }
+
+    /// Removes the hook set by [`set_tx_hook`](Self::set_tx_hook)
+    pub fn clear_tx_hook(&mut self) {
+        self.tx_hook = None;
+    }
+
+    /// Whether a transaction hook is set
+    pub fn has_tx_hook(&self) -> bool {
+        self.tx_hook.is_some()
+    }
+}
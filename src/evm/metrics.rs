@@ -0,0 +1,203 @@
+//! Per-batch execution metrics
+//!
+//! When enabled with [`TraceEvm::enable_metrics`], `trace_transactions` records where
+//! the time of each batch went: wall time, per-transaction execution time and gas,
+//! time blocked on upstream database lookups and the size of the collected traces.
+//! Disabled metrics cost a single branch per batch and transaction.
+//!
+//! Database figures are only available when the EVM was created by the builder on top
+//! of the instrumented `MyWrapDatabaseAsync`; other backends report `None`.
+
+use std::{
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use crate::{evm::TraceEvm, DbStats};
+use revm::{database::Database, state::EvmState};
+use serde::Serialize;
+
+/// Execution metrics of one transaction
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TxMetrics {
+    /// Time spent executing the transaction, including database lookups
+    pub execution_time: Duration,
+    /// Gas used (0 if the transaction could not be executed)
+    pub gas_used: u64,
+    /// Time spent waiting for upstream database lookups, if instrumented
+    pub db_fetch_time: Option<Duration>,
+    /// Estimated in-memory size of the inspector output
+    pub trace_size_bytes: usize,
+}
+
+/// Execution metrics of one `trace_transactions` batch
+///
+/// `execution_time - db_fetch_time` approximates pure EVM and inspector time, and
+/// `total_wall_time` minus the sum of execution times is the batch setup overhead
+/// (cache reset and state overrides).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BatchMetrics {
+    /// Wall time of the whole batch
+    pub total_wall_time: Duration,
+    /// Metrics of each transaction, in batch order
+    pub per_tx: Vec<TxMetrics>,
+    /// Database lookups made during the batch, if instrumented
+    pub db_stats: Option<DbStats>,
+}
+
+impl BatchMetrics {
+    /// Total gas used by all transactions of the batch
+    pub fn total_gas_used(&self) -> u64 {
+        self.per_tx.iter().map(|tx| tx.gas_used).sum()
+    }
+
+    /// Total execution time of all transactions of the batch
+    pub fn total_execution_time(&self) -> Duration {
+        self.per_tx.iter().map(|tx| tx.execution_time).sum()
+    }
+}
+
+/// Collects metrics while a batch executes
+pub(crate) struct BatchRecorder {
+    start: Instant,
+    db_before: Option<DbStats>,
+    per_tx: Vec<TxMetrics>,
+}
+
+/// Starting point of one transaction's measurement
+pub(crate) struct TxStart {
+    start: Instant,
+    db_before: Option<DbStats>,
+}
+
+impl BatchRecorder {
+    /// Starts measuring a batch
+    pub(crate) fn new(db_before: Option<DbStats>) -> Self {
+        Self {
+            start: Instant::now(),
+            db_before,
+            per_tx: Vec::new(),
+        }
+    }
+
+    /// Starts measuring a transaction
+    pub(crate) fn start_tx(db_before: Option<DbStats>) -> TxStart {
+        TxStart {
+            start: Instant::now(),
+            db_before,
+        }
+    }
+
+    /// Records a finished transaction
+    pub(crate) fn record_tx(
+        &mut self,
+        tx: TxStart,
+        db_after: Option<DbStats>,
+        gas_used: u64,
+        trace_size_bytes: usize,
+    ) {
+        self.per_tx.push(TxMetrics {
+            execution_time: tx.start.elapsed(),
+            gas_used,
+            db_fetch_time: db_after
+                .zip(tx.db_before)
+                .map(|(after, before)| after.since(&before).fetch_time),
+            trace_size_bytes,
+        });
+    }
+
+    /// Completes the batch measurement
+    pub(crate) fn finish(self, db_after: Option<DbStats>) -> BatchMetrics {
+        BatchMetrics {
+            total_wall_time: self.start.elapsed(),
+            per_tx: self.per_tx,
+            db_stats: db_after
+                .zip(self.db_before)
+                .map(|(after, before)| after.since(&before)),
+        }
+    }
+}
+
+impl<DB, INSP> TraceEvm<DB, INSP>
+where
+    DB: Database,
+{
+    /// Enables or disables batch metrics collection
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use revm_trace::{create_evm_with_tracer, TransactionTrace, TxInspector};
+    ///
+    /// let mut evm = create_evm_with_tracer("https://eth.llamarpc.com", TxInspector::new()).await?;
+    /// evm.enable_metrics(true);
+    /// // ... evm.trace_transactions(batch) ...
+    /// if let Some(metrics) = evm.take_last_batch_metrics() {
+    ///     println!("batch took {:?}", metrics.total_wall_time);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn enable_metrics(&mut self, enabled: bool) {
+        self.metrics_enabled = enabled;
+        if !enabled {
+            self.last_batch_metrics = None;
+        }
+    }
+
+    /// Whether batch metrics are being collected
+    pub fn metrics_enabled(&self) -> bool {
+        self.metrics_enabled
+    }
+
+    /// Takes the metrics of the most recent batch
+    ///
+    /// Returns `None` if metrics are disabled, no batch ran since enabling them, or
+    /// the metrics were already taken.
+    pub fn take_last_batch_metrics(&mut self) -> Option<BatchMetrics> {
+        self.last_batch_metrics.take()
+    }
+
+    /// Stores the metrics of a completed batch
+    pub(crate) fn set_last_batch_metrics(&mut self, metrics: BatchMetrics) {
+        self.last_batch_metrics = Some(metrics);
+    }
+
+    /// Reads the current database lookup statistics, if the database is instrumented
+    pub(crate) fn db_stats_snapshot(&self) -> Option<DbStats> {
+        self.db_stats_source.map(|source| DbStats {
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            ..source(&self.evm.ctx.journaled_state.database)
+        })
+    }
+
+    /// Counts the `CacheDB` hits of an executed transaction, if the database is
+    /// instrumented
+    ///
+    /// Every account in `state` was looked up once, and so was every storage slot of
+    /// an account that existed before the transaction; slots of created accounts are
+    /// never read from the database. The lookups not forwarded to the wrapper since
+    /// `before` were answered by the overlay.
+    pub(crate) fn record_cache_hits(&self, state: &EvmState, before: Option<DbStats>) {
+        let Some(before) = before else {
+            return;
+        };
+        let Some(after) = self.db_stats_snapshot() else {
+            return;
+        };
+        let lookups: u64 = state
+            .values()
+            .map(|account| {
+                let slots = if account.is_created() {
+                    0
+                } else {
+                    account.storage.len()
+                };
+                1 + slots as u64
+            })
+            .sum();
+        let misses = after.cache_misses.saturating_sub(before.cache_misses);
+        self.cache_hits
+            .fetch_add(lookups.saturating_sub(misses), Ordering::Relaxed);
+    }
+}
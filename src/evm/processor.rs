@@ -4,23 +4,37 @@
 //! It supports both stateful and stateless execution modes and provides detailed
 //! inspector output for each transaction.
 
-use std::collections::HashMap;
-
 use crate::{
-    evm::TraceEvm,
-    traits::{ResetDB, TraceOutput, TransactionTrace},
-    types::{SimulationBatch, SimulationTx, SlotAccess, StateOverride, StorageDiff},
+    chains::system_contract_name,
+    evm::{fees::FeeRecorder, metrics::BatchRecorder, timeline::BatchTimeline, TraceEvm},
+    traits::{ResetDB, TraceOutput, TraceResult, TransactionTrace},
+    types::{
+        EmptyCodeCallPolicy, ExecutedTxEnv, FailurePolicy, KnownPrecompile, SimulationBatch,
+        SimulationTx, SimulationWarning, SlotAccess, StateOverride, StorageDiff, TargetRef,
+        TraceLevel,
+    },
+    wrap_db::is_missing_state_error,
 };
 
-use crate::errors::{EvmError, RuntimeError};
+use crate::errors::{EvmError, RuntimeError, UnresolvedTargetReason};
 use crate::traits::TraceInspector;
+use alloy::primitives::{Address, TxKind};
 use revm::{
     context::{ContextTr, TxEnv},
-    context_interface::result::ExecutionResult,
-    database::{CacheDB, Database, DatabaseCommit, DatabaseRef},
+    context_interface::{
+        result::{EVMError, ExecutionResult, Output},
+        Transaction,
+    },
+    database::{Database, DatabaseCommit},
     handler::MainnetContext,
+    state::{Account, EvmState, EvmStorageSlot},
     ExecuteEvm, InspectEvm,
 };
+use std::{
+    any::Any,
+    collections::HashMap,
+    panic::{self, AssertUnwindSafe},
+};
 
 impl<DB, INSP> TraceEvm<DB, INSP>
 where
@@ -40,49 +54,338 @@ where
     /// * `Err(RuntimeError)` - If transaction execution fails
     ///
     /// # Implementation Details
-    /// 1. Resets inspector state before execution
-    /// 2. Fetches current nonce from account state
-    /// 3. Builds transaction environment from input parameters
-    /// 4. Executes transaction with inspector and commits changes
+    /// 1. Resets per-transaction inspector state (`Reset::reset_tx`) before execution
+    /// 2. Fetches current nonce from account state, once per account within a batch
+    /// 3. Builds transaction environment from input parameters and applies the tx hook,
+    ///    then checks calls to accounts without code against the batch policy
+    /// 4. Executes transaction with inspector (plainly for `TraceLevel::Off`) and commits changes
     /// 5. Collects and returns inspector output
     ///
     /// # Note
     /// This method is internal and should not be called directly.
     /// Use `trace_transactions` or `execute_batch` instead.
-    fn trace_internal(
+    pub(crate) fn trace_internal(
         &mut self,
         input: SimulationTx,
         is_stateful: bool,
     ) -> Result<(ExecutionResult, StorageDiff, INSP::Output), RuntimeError> {
-        // Reset inspector state before processing
-        self.reset_inspector();
+        let (result, state) = self.execute_internal(input)?;
+        Ok(self.finish_internal(result, state, is_stateful))
+    }
 
-        // Fetch current nonce for the transaction sender
-        let nonce = self
+    /// Executes one transaction of a `trace_transactions` batch
+    ///
+    /// Like `trace_internal`, except that a failed transaction is only committed if
+    /// `failure_policy` allows it, and that the fees of an executed transaction are
+    /// recorded into `fees` when given. The coinbase balance before the transaction is
+    /// read from the database and the one after from the transaction's state, so fee
+    /// recording works in both execution modes.
+    fn trace_batch_tx(
+        &mut self,
+        input: SimulationTx,
+        is_stateful: bool,
+        failure_policy: FailurePolicy,
+        fees: Option<&mut FeeRecorder>,
+    ) -> Result<(ExecutionResult, StorageDiff, INSP::Output), RuntimeError> {
+        let Some(fees) = fees else {
+            let (result, state) = self.execute_internal(input)?;
+            self.check_cancellation()?;
+            let commit = is_stateful && failure_policy.commits(&result);
+            return Ok(self.finish_internal(result, state, commit));
+        };
+        let block = self.block.number;
+        let coinbase = self.block.beneficiary;
+        let before = self
             .db()
-            .basic(input.caller)
-            .map_err(|e| RuntimeError::ExecutionFailed(format!("Failed to get account info: {e}")))?
-            .map(|acc| acc.nonce)
+            .basic(coinbase)
+            .map_err(|e| execution_error(block, "Failed to get account info", e))?
+            .map(|acc| acc.balance)
             .unwrap_or_default();
+        let (result, state) = self.execute_internal(input)?;
+        self.check_cancellation()?;
+        let effective_gas_price = self.tx.effective_gas_price(self.block.basefee as u128);
+        let after = state
+            .get(&coinbase)
+            .map_or(before, |account| account.info.balance);
+        let commit = is_stateful && failure_policy.commits(&result);
+        let (result, diff, output) = self.finish_internal(result, state, commit);
+        fees.record_tx(
+            effective_gas_price,
+            result.gas_used(),
+            before,
+            after,
+            INSP::coinbase_payments(&output),
+        );
+        Ok((result, diff, output))
+    }
+
+    /// Fails with `RuntimeError::Cancelled` if the batch's cancellation token was
+    /// cancelled
+    ///
+    /// Called after a transaction executed and before it is committed, so a
+    /// transaction the token fired during is discarded whether or not the inspector
+    /// halted it.
+    fn check_cancellation(&self) -> Result<(), RuntimeError> {
+        if self.cancellation_requested() {
+            return Err(RuntimeError::Cancelled);
+        }
+        Ok(())
+    }
+
+    /// Executes a transaction without committing it (steps 1-4 of `trace_internal`)
+    ///
+    /// The returned state holds every account and slot the transaction touched, with
+    /// the values from before the transaction still available as originals.
+    #[cfg_attr(
+        feature = "tracing-logs",
+        tracing::instrument(
+            name = "tx",
+            level = "debug",
+            skip_all,
+            fields(
+                caller = %input.caller,
+                kind = if input.transact_to.is_create() { "create" } else { "call" },
+                gas_used = tracing::field::Empty,
+                status = tracing::field::Empty,
+            )
+        )
+    )]
+    pub(crate) fn execute_internal(
+        &mut self,
+        input: SimulationTx,
+    ) -> Result<(ExecutionResult, EvmState), RuntimeError> {
+        // Reset inspector state before processing
+        self.inspector.reset_tx();
+        self.executed_tx = None;
+        let trace_level = input.trace_config.unwrap_or_default();
+        self.inspector.set_trace_level(trace_level);
+
+        // Fetch current nonce for the transaction sender
+        let block = self.block.number;
+        let sender = input.caller;
+        let nonce = self.account_nonce(sender)?;
         let chain_id = self.cfg.chain_id;
         // Build transaction environment
-        let tx = TxEnv::builder()
-            .caller(input.caller)
+        let mut tx = TxEnv::builder()
+            .caller(sender)
             .value(input.value)
-            .data(input.data)
+            .data(input.data.clone())
             .kind(input.transact_to)
             .nonce(nonce)
             .chain_id(Some(chain_id))
             .build_fill();
+        if let Some(hook) = self.tx_hook.as_mut() {
+            hook(&mut tx, &input);
+        }
+        if let Some(warning) = self.empty_code_call_warning(&tx)? {
+            self.inspector.record_warning(warning);
+        }
+        self.executed_tx = Some(ExecutedTxEnv {
+            block_base_fee: self.block.basefee,
+            ..ExecutedTxEnv::from(&tx)
+        });
+        if tx.kind.is_create() {
+            for warning in self.creation_warnings(&tx)? {
+                self.inspector.record_warning(warning);
+            }
+        }
 
-        // Set transaction and execute with current inspector, committing changes
-        self.set_tx(tx);
-        let result = self.inspect_replay().map_err(|e| {
-            RuntimeError::ExecutionFailed(format!("Inspector execution failed: {e}"))
-        })?;
-        let state = result.state;
-        let result = result.result;
-        let mut diffs = HashMap::new();
+        // Execute with the inspector unless tracing is disabled for this transaction
+        let db_before = self.db_stats_snapshot();
+        let result = if trace_level == TraceLevel::Off {
+            self.transact(tx)
+                .map_err(|e| transact_error(block, "Transaction execution failed", e))?
+        } else {
+            self.set_tx(tx);
+            // The inspector is not unwind safe in general; it is reset after a panic
+            match panic::catch_unwind(AssertUnwindSafe(|| self.inspect_replay())) {
+                Ok(result) => {
+                    result.map_err(|e| transact_error(block, "Inspector execution failed", e))?
+                }
+                Err(payload) => {
+                    self.recover_from_panic();
+                    return Err(RuntimeError::InspectorPanic {
+                        message: panic_message(payload.as_ref()),
+                    });
+                }
+            }
+        };
+        self.record_cache_hits(&result.state, db_before);
+        #[cfg(feature = "tracing-logs")]
+        {
+            let span = tracing::Span::current();
+            span.record("gas_used", result.result.gas_used());
+            span.record(
+                "status",
+                match &result.result {
+                    ExecutionResult::Success { .. } => "success",
+                    ExecutionResult::Revert { .. } => "revert",
+                    ExecutionResult::Halt { .. } => "halt",
+                },
+            );
+        }
+        Ok((result.result, result.state))
+    }
+
+    /// Current nonce of `address`
+    ///
+    /// Within a batch, nonces are read from the database once per account and then
+    /// taken from the batch's nonce map, which `finish_internal` keeps up to date with
+    /// every committed state. Outside batches the database is read every time.
+    fn account_nonce(&mut self, address: Address) -> Result<u64, RuntimeError> {
+        if let Some(nonce) = self
+            .batch_nonces
+            .as_ref()
+            .and_then(|nonces| nonces.get(&address))
+        {
+            return Ok(*nonce);
+        }
+        let block = self.block.number;
+        let nonce = self
+            .db()
+            .basic(address)
+            .map_err(|e| {
+                #[cfg(feature = "tracing-logs")]
+                tracing::warn!(%address, error = %e, "Failed to fetch the sender nonce");
+                execution_error(block, "Failed to get account info", e)
+            })?
+            .map(|acc| acc.nonce)
+            .unwrap_or_default();
+        if let Some(nonces) = self.batch_nonces.as_mut() {
+            nonces.insert(address, nonce);
+        }
+        Ok(nonce)
+    }
+
+    /// Restores a usable EVM after a panic unwound through `inspect_replay`
+    ///
+    /// The unfinished transaction's journal is discarded, so none of its changes
+    /// reach the database; the `CacheDB` overlay is only written on commit and keeps
+    /// the earlier transactions of a stateful batch. The inspector may have been left
+    /// mid-update and is reset entirely, batch-wide state included.
+    fn recover_from_panic(&mut self) {
+        self.journal().clear();
+        self.inspector.reset_batch();
+        self.inspector.reset_slot_cache();
+    }
+
+    /// Diagnoses a deployment before it runs
+    ///
+    /// Top-level creations always use CREATE, so the new address follows from the
+    /// sender and its account nonce. Reports a nonce differing from the account's and
+    /// an existing account with code or a nonce at the predicted address, which makes
+    /// the creation halt with a collision.
+    fn creation_warnings(&mut self, tx: &TxEnv) -> Result<Vec<SimulationWarning>, RuntimeError> {
+        let block = self.block.number;
+        let mut warnings = Vec::new();
+        let nonce = self.account_nonce(tx.caller)?;
+        if tx.nonce != nonce {
+            warnings.push(SimulationWarning::NonceMismatchForCreate {
+                expected: nonce,
+                actual: tx.nonce,
+            });
+        }
+        let address = tx.caller.create(nonce);
+        let target = self
+            .db()
+            .basic(address)
+            .map_err(|e| execution_error(block, "Failed to get account info", e))?;
+        if target.is_some_and(|acc| acc.nonce != 0 || !acc.is_empty_code_hash()) {
+            warnings.push(SimulationWarning::AddressCollision { address });
+        }
+        Ok(warnings)
+    }
+
+    /// Applies the batch's `EmptyCodeCallPolicy` to a transaction before it runs
+    ///
+    /// Returns a warning under `Warn` and fails under `Error` when the transaction
+    /// sends calldata to an account without code. Precompiles and system contracts
+    /// are exempt, as their code lives in the client.
+    fn empty_code_call_warning(
+        &mut self,
+        tx: &TxEnv,
+    ) -> Result<Option<SimulationWarning>, RuntimeError> {
+        let TxKind::Call(address) = tx.kind else {
+            return Ok(None);
+        };
+        if self.empty_code_call_policy == EmptyCodeCallPolicy::Allow
+            || tx.data.is_empty()
+            || KnownPrecompile::from_address(address).is_some()
+            || system_contract_name(self.cfg.chain_id, address).is_some()
+        {
+            return Ok(None);
+        }
+        let block = self.block.number;
+        let has_code = self
+            .db()
+            .basic(address)
+            .map_err(|e| execution_error(block, "Failed to get account info", e))?
+            .is_some_and(|acc| !acc.is_empty_code_hash());
+        if has_code {
+            return Ok(None);
+        }
+        match self.empty_code_call_policy {
+            EmptyCodeCallPolicy::Allow => Ok(None),
+            EmptyCodeCallPolicy::Warn => {
+                Ok(Some(SimulationWarning::CallToEmptyAccount { address }))
+            }
+            EmptyCodeCallPolicy::Error => Err(RuntimeError::CallToEmptyAccount { address }),
+        }
+    }
+
+    /// Writes the storage and balance overrides of a batch to the database
+    ///
+    /// Overrides are committed like transaction state, so they work with any database
+    /// and last until the database is reset.
+    fn apply_overrides(&mut self, overrides: StateOverride) -> Result<(), EvmError> {
+        let StateOverride { storages, balances } = overrides;
+        let mut state = EvmState::default();
+        for (address, slots) in storages {
+            let account = self.override_account(&mut state, address, "storage")?;
+            for (slot, value) in slots {
+                account.storage.insert(slot, EvmStorageSlot::new(value));
+            }
+        }
+        for (address, balance) in balances {
+            let account = self.override_account(&mut state, address, "balance")?;
+            account.info.balance = balance;
+        }
+        self.db().commit(state);
+        Ok(())
+    }
+
+    /// Entry of `address` in the override `state`, loaded from the database first
+    fn override_account<'a>(
+        &mut self,
+        state: &'a mut EvmState,
+        address: Address,
+        kind: &str,
+    ) -> Result<&'a mut Account, EvmError> {
+        let account = match state.remove(&address) {
+            Some(account) => account,
+            None => {
+                let info = self.db().basic(address).map_err(|e| {
+                    EvmError::OverrideError(format!(
+                        "Failed to load account {address} for {kind} override: {e}"
+                    ))
+                })?;
+                let mut account = Account::from(info.unwrap_or_default());
+                account.mark_touch();
+                account
+            }
+        };
+        Ok(state.entry(address).or_insert(account))
+    }
+
+    /// Collects the storage diff and inspector output of an executed transaction and
+    /// commits its state if `is_stateful`
+    pub(crate) fn finish_internal(
+        &mut self,
+        result: ExecutionResult,
+        state: EvmState,
+        is_stateful: bool,
+    ) -> (ExecutionResult, StorageDiff, INSP::Output) {
+        let mut diffs = StorageDiff::new();
         for (address, account) in state.iter() {
             for (slot, value) in account.storage.iter() {
                 if value.original_value != value.present_value {
@@ -96,26 +399,46 @@ where
                             old_value: value.original_value,
                             new_value: value.present_value,
                             is_write: true,
+                            is_cold: false,
                         });
                 }
             }
         }
+        // Slot order of the state is arbitrary
+        for accesses in diffs.values_mut() {
+            accesses.sort_by_key(|access| access.slot);
+        }
+        self.inspector.apply_post_state(&state);
         if is_stateful {
-            self.db().commit(state)
+            // Senders pay their nonce for reverted and halted transactions too, so the
+            // committed state is the source of truth rather than the transaction
+            if let Some(nonces) = self.batch_nonces.as_mut() {
+                nonces.retain(|address, nonce| match state.get(address) {
+                    // Committing a destroyed account clears it
+                    Some(account) if account.is_selfdestructed() => false,
+                    Some(account) => {
+                        *nonce = account.info.nonce;
+                        true
+                    }
+                    None => true,
+                });
+            }
+            self.db().commit(state);
+            self.block_gas_used += result.gas_used();
         } else {
             self.inspector.reset_slot_cache();
         }
         // Collect inspector output
-        let output = self.get_inspector_output();
-        Ok((result, diffs, output))
+        let output = self.inspector.take_output();
+        (result, diffs, output)
     }
 }
 
 /// Implementation of TransactionTrace trait for batch processing
-impl<DB, INSP> TransactionTrace<MainnetContext<CacheDB<DB>>> for TraceEvm<CacheDB<DB>, INSP>
+impl<DB, INSP> TransactionTrace<MainnetContext<DB>> for TraceEvm<DB, INSP>
 where
-    DB: DatabaseRef,
-    INSP: TraceInspector<MainnetContext<CacheDB<DB>>>,
+    DB: Database + DatabaseCommit + ResetDB,
+    INSP: TraceInspector<MainnetContext<DB>>,
 {
     type Inspector = INSP;
 
@@ -136,12 +459,66 @@ where
     /// - **Stateful** (`is_stateful = true`): State persists between transactions
     /// - **Stateless** (`is_stateful = false`): Database resets between transactions
     ///
+    /// # Failed Transactions
+    /// In stateful mode, the batch's [`FailurePolicy`] decides whether a reverted or
+    /// halted transaction is committed (consuming the sender's nonce and gas) and whether
+    /// the remaining transactions run. Transactions skipped by
+    /// [`FailurePolicy::Abort`] yield [`EvmError::BatchAborted`].
+    ///
+    /// # Databases
+    /// Any database committing state works. The batch starts with
+    /// [`ResetDB::reset_db`], which for `CacheDB` clears the overlay so that every batch
+    /// reads the upstream state; state overrides and stateful transactions are then
+    /// written with `DatabaseCommit::commit`. Custom databases decide themselves what a
+    /// reset restores, and keep whatever is committed to them until then.
+    ///
+    /// # Inspector Panics
+    /// A panic in an inspector hook fails only its transaction, with
+    /// `RuntimeError::InspectorPanic`. The transaction's changes are discarded and the
+    /// inspector is reset entirely (`Reset::reset_batch`), since the panic may have
+    /// left it mid-update; the failure policy then decides whether the batch goes on.
+    /// Inspectors sharing state outside themselves (`Rc<RefCell<_>>`, mutexes) must
+    /// cope with that state being inconsistent. The panic hook still runs, so the
+    /// message is printed as usual.
+    ///
+    /// # Chunking
+    /// With a chunk size set (`TraceEvm::set_chunk_size`), the batch runs in chunks
+    /// with housekeeping in between. The results equal those of an unchunked run.
+    ///
+    /// # Cancellation
+    /// With a [`CancellationToken`](crate::types::CancellationToken) set, the token is
+    /// checked before every transaction and after it executed. Once it is cancelled,
+    /// the transaction that was running is discarded and it and all remaining ones
+    /// yield `RuntimeError::Cancelled`; earlier results are kept. Inspectors can also
+    /// halt the running transaction early (`TraceOutput::set_cancellation`), which
+    /// `TxInspector` does; transactions traced at `TraceLevel::Off` run without the
+    /// inspector and always run to completion.
+    ///
+    /// # Created Contract Targets
+    /// A transaction given a [`TargetRef::CreatedBy`] target (see
+    /// [`SimulationBatch::with_target`]) calls the contract created by the referenced
+    /// earlier transaction. The target is resolved right before the transaction runs,
+    /// and `on_tx`, the tx hook and reports see the resolved call.
+    /// A reference to a later, failed or non-creating transaction fails with
+    /// `RuntimeError::UnresolvedTarget`, which the failure policy treats like any
+    /// other failure.
+    ///
+    /// # Sender Nonces
+    /// Each sender's nonce is read from the database once per batch and then taken
+    /// from the committed state of the batch's transactions, failed ones included when
+    /// the failure policy commits them. Nonces set by the tx hook are used as given.
+    ///
+    /// # Timeline
+    /// With `build_timeline` set, the mutations the inspector records for every
+    /// transaction are merged into one [`BatchTimeline`] in execution order, read with
+    /// `TraceEvm::take_last_timeline`.
+    ///
     /// # Implementation Details
     /// 1. Sets block environment if provided in batch parameters
-    /// 2. Resets database to clean state before processing
+    /// 2. Resets database and batch-wide inspector state (`Reset::reset_batch`) before processing
     /// 3. Processes each transaction in sequence using `trace_internal`
-    /// 4. Manages state persistence based on `is_stateful` flag
-    /// 5. Resets inspector state after batch completion
+    /// 4. Manages state persistence based on `is_stateful` and `failure_policy`
+    /// 5. Resets per-transaction inspector state (`Reset::reset_tx`) after batch completion
     ///
     /// # Example
     /// ```no_run
@@ -152,11 +529,20 @@ where
     /// let batch = SimulationBatch {
     ///     transactions: vec![/* transactions */],
     ///     is_stateful: true,
+    ///     ..Default::default()
     /// };
     /// let results = evm.trace_transactions(batch);
     /// # Ok(())
     /// # }
     /// ```
+    #[cfg_attr(
+        feature = "tracing-logs",
+        tracing::instrument(
+            name = "trace_transactions",
+            skip_all,
+            fields(batch_size = batch.transactions.len(), is_stateful = batch.is_stateful)
+        )
+    )]
     fn trace_transactions(
         &mut self,
         batch: SimulationBatch,
@@ -170,80 +556,184 @@ where
             EvmError,
         >,
     > {
+        let mut results = Vec::with_capacity(batch.transactions.len());
+        self.trace_transactions_with(batch, |_, _, _, result| results.push(result));
+        results
+    }
+}
+
+impl<DB, INSP> TraceEvm<DB, INSP>
+where
+    DB: Database + DatabaseCommit + ResetDB,
+    INSP: TraceInspector<MainnetContext<DB>>,
+{
+    /// Traces a batch like [`trace_transactions`](TransactionTrace::trace_transactions),
+    /// handing each transaction's result to `on_tx` as soon as it finishes
+    ///
+    /// Trace outputs are not buffered, so memory use does not grow with them. `on_tx`
+    /// receives the batch index, the simulated transaction, the environment it was
+    /// executed with (`None` if it never ran) and its result. Batch metrics, fee
+    /// accounting, the timeline and executed environments are recorded as for
+    /// `trace_transactions`. With a chunk size set (see `TraceEvm::set_chunk_size`),
+    /// the batch runs in chunks as described in [`chunking`](crate::evm::chunking).
+    pub fn trace_transactions_with<F>(&mut self, batch: SimulationBatch, on_tx: F)
+    where
+        F: FnMut(usize, SimulationTx, Option<&ExecutedTxEnv>, TraceResult<INSP::Output>),
+    {
+        self.run_batch(batch, None, on_tx);
+    }
+
+    /// Runs a batch as described for
+    /// [`trace_transactions_with`](Self::trace_transactions_with), returning the
+    /// sender nonces read or committed during it
+    ///
+    /// With `resume_nonces`, the batch continues from the current state instead: the
+    /// database is not reset, the inspector keeps its batch-wide state, and sender
+    /// nonces start from the given map. Used by [`BatchSession`](super::session::BatchSession)
+    /// to run tails on top of a restored prefix.
+    pub(crate) fn run_batch<F>(
+        &mut self,
+        batch: SimulationBatch,
+        resume_nonces: Option<HashMap<Address, u64>>,
+        mut on_tx: F,
+    ) -> HashMap<Address, u64>
+    where
+        F: FnMut(usize, SimulationTx, Option<&ExecutedTxEnv>, TraceResult<INSP::Output>),
+    {
         let SimulationBatch {
             transactions,
             is_stateful,
             overrides,
+            coinbase,
+            failure_policy,
+            empty_code_call_policy,
+            build_timeline,
+            cancellation,
+            targets,
         } = batch;
         let len = transactions.len();
+        let mut recorder = self
+            .metrics_enabled()
+            .then(|| BatchRecorder::new(self.db_stats_snapshot()));
 
         // 2. Reset database to clean state
-        self.reset_db();
-        // reset inspector slot cache
+        let resumed = resume_nonces.is_some();
+        if !resumed {
+            self.reset_db();
+        }
+        // reset inspector slot cache and batch-wide state
         self.inspector.reset_slot_cache();
-        let mut override_error: Option<EvmError> = None;
+        if !resumed {
+            self.inspector.reset_batch();
+        }
         // if has overrides, set them in db
-        if let Some(overrides) = overrides {
-            let StateOverride { storages, balances } = overrides;
-            for (address, slots) in storages {
-                for (slot, value) in slots {
-                    if let Err(e) = self.db().insert_account_storage(address, slot, value) {
-                        override_error = Some(EvmError::OverrideError(format!(
-                            "Failed to set storage override for {address}:{slot} = {value}: {e}"
-                        )));
-                        break;
-                    }
-                }
+        let override_error = overrides.and_then(|overrides| self.apply_overrides(overrides).err());
+        if let Some(e) = override_error {
+            if let Some(recorder) = recorder {
+                let metrics = recorder.finish(self.db_stats_snapshot());
+                self.set_last_batch_metrics(metrics);
             }
-            if override_error.is_none() {
-                for (address, balance) in balances {
-                    let account = self.db().load_account(address);
-                    if let Err(e) = account {
-                        override_error = Some(EvmError::OverrideError(format!(
-                            "Failed to load account {address} for balance override: {e}"
-                        )));
-                        break;
-                    } else {
-                        let account = account.unwrap();
-                        account.info.balance = balance;
-                    }
-                }
+            self.set_last_fee_accounting(None);
+            self.set_last_timeline(build_timeline.then(BatchTimeline::default));
+            self.last_executed_txs = vec![None; len];
+            self.last_remote_dependencies =
+                self.take_remote_fetches().map(|_| vec![Vec::new(); len]);
+            for (index, tx) in transactions.into_iter().enumerate() {
+                on_tx(index, tx, None, Err(e.clone()));
             }
+            return resume_nonces.unwrap_or_default();
         }
 
-        if let Some(e) = override_error {
-            return std::iter::repeat_with(|| Err(e.clone()))
-                .take(len)
-                .collect();
-        }
+        let mut executed_txs = Vec::with_capacity(len);
+        // Lookups made while resetting and applying overrides belong to no transaction
+        let mut remote_dependencies = self.take_remote_fetches().map(|_| Vec::with_capacity(len));
 
-        let mut results = Vec::with_capacity(len);
+        // Apply the batch coinbase before execution so COINBASE observes it
+        let previous_coinbase = self.block.beneficiary;
+        if let Some(coinbase) = coinbase {
+            self.block.beneficiary = coinbase;
+        }
+        let mut fees = (!self.cfg.disable_base_fee).then(|| FeeRecorder::new(&self.block));
+        self.empty_code_call_policy = empty_code_call_policy;
+        let mut timeline = build_timeline.then(BatchTimeline::default);
+        self.inspector.set_timeline(build_timeline);
+        self.cancellation = cancellation.clone();
+        self.inspector.set_cancellation(cancellation);
+        // Overrides are in place, so nonces only change through commits from here on
+        self.batch_nonces = Some(resume_nonces.unwrap_or_default());
 
-        // 3. Process each transaction in the batch
-        for input in transactions.into_iter() {
-            let result = self
-                .trace_internal(input, is_stateful)
-                .map_err(EvmError::Runtime);
-            results.push(result);
+        // 3. Process each transaction in the batch, skipping those after an aborting failure
+        let mut failed_index = None;
+        let mut created = Vec::with_capacity(len);
+        for (index, mut input) in transactions.into_iter().enumerate() {
+            let tx_start = recorder
+                .is_some()
+                .then(|| BatchRecorder::start_tx(self.db_stats_snapshot()));
+            let resolved = resolve_target(&mut input, targets.get(&index), &created);
+            let tx = input.clone();
+            let result = match failed_index {
+                _ if self.cancellation_requested() => {
+                    Err(EvmError::Runtime(RuntimeError::Cancelled))
+                }
+                Some(failed_index) => Err(EvmError::BatchAborted { failed_index }),
+                None => resolved
+                    .and_then(|()| {
+                        self.trace_batch_tx(input, is_stateful, failure_policy, fees.as_mut())
+                    })
+                    .map_err(EvmError::Runtime),
+            };
+            created.push(created_address(&tx, &result));
+            executed_txs.push(self.executed_tx.take());
+            if let Some(remote_dependencies) = remote_dependencies.as_mut() {
+                remote_dependencies.push(self.take_remote_fetches().unwrap_or_default());
+            }
+            if let Some(timeline) = timeline.as_mut() {
+                timeline.push_tx(index, self.inspector.take_timeline());
+            }
+            if let (Err(_), Some(fees)) = (&result, fees.as_mut()) {
+                fees.skip_tx();
+            }
+            let succeeded = matches!(&result, Ok((execution, _, _)) if execution.is_success());
+            if failure_policy == FailurePolicy::Abort && failed_index.is_none() && !succeeded {
+                failed_index = Some(index);
+            }
+            if let (Some(recorder), Some(tx_start)) = (recorder.as_mut(), tx_start) {
+                let (gas_used, trace_size) = match &result {
+                    Ok((execution, _, output)) => (execution.gas_used(), INSP::output_size(output)),
+                    Err(_) => (0, 0),
+                };
+                recorder.record_tx(tx_start, self.db_stats_snapshot(), gas_used, trace_size);
+            }
+            on_tx(index, tx, executed_txs[index].as_ref(), result);
+            if self.chunk_ends_at(index + 1, len) {
+                self.finish_chunk(index + 1);
+            }
         }
+        self.block.beneficiary = previous_coinbase;
+        self.empty_code_call_policy = EmptyCodeCallPolicy::Allow;
+        self.inspector.set_timeline(false);
+        self.cancellation = None;
+        self.inspector.set_cancellation(None);
+        let nonces = self.batch_nonces.take().unwrap_or_default();
+        if let Some(recorder) = recorder {
+            let metrics = recorder.finish(self.db_stats_snapshot());
+            self.set_last_batch_metrics(metrics);
+        }
+        self.set_last_fee_accounting(fees.map(FeeRecorder::finish));
+        self.set_last_timeline(timeline);
+        self.last_executed_txs = executed_txs;
+        self.last_remote_dependencies = remote_dependencies;
 
-        // 4. Clean up inspector state after batch completion
-        self.reset_inspector();
+        // 4. Clean up per-transaction inspector state, keeping batch-wide state readable
+        self.inspector.reset_tx();
 
         // 5. Reset transaction environment to prevent interference with other uses
         self.set_tx(Default::default());
         // Note: We don't reset_db here because EVM state can be preserved for other scenarios,
         // such as querying ERC20 token balances
-
-        results
+        nonces
     }
-}
 
-impl<DB, INSP> TraceEvm<CacheDB<DB>, INSP>
-where
-    DB: DatabaseRef,
-    INSP: TraceInspector<MainnetContext<CacheDB<DB>>>,
-{
     /// Execute a batch of transactions and return only execution results
     ///
     /// This is a convenience method for users who only need transaction execution
@@ -265,6 +755,7 @@ where
     /// let batch = SimulationBatch {
     ///     transactions: vec![/* transactions */],
     ///     is_stateful: false,
+    ///     ..Default::default()
     /// };
     /// let results = evm.execute_batch(batch);
     /// # Ok(())
@@ -280,3 +771,91 @@ where
             .collect()
     }
 }
+
+/// Maps an execution or database failure at `block` to a runtime error
+///
+/// Database errors reporting missing historical state become
+/// `RuntimeError::HistoricalStateUnavailable`; everything else is an execution failure.
+pub(crate) fn execution_error(
+    block: u64,
+    context: &str,
+    error: impl std::fmt::Display,
+) -> RuntimeError {
+    let message = error.to_string();
+    if is_missing_state_error(&message) {
+        RuntimeError::HistoricalStateUnavailable { block }
+    } else {
+        RuntimeError::ExecutionFailed(format!("{context}: {message}"))
+    }
+}
+
+/// Points `input` at the contract created by the transaction `target` refers to,
+/// given the creation outcome of every earlier one
+fn resolve_target(
+    input: &mut SimulationTx,
+    target: Option<&TargetRef>,
+    created: &[Result<Address, UnresolvedTargetReason>],
+) -> Result<(), RuntimeError> {
+    let Some(&TargetRef::CreatedBy { tx_index }) = target else {
+        return Ok(());
+    };
+    match created.get(tx_index) {
+        Some(Ok(address)) => {
+            input.transact_to = TxKind::Call(*address);
+            Ok(())
+        }
+        Some(Err(reason)) => Err(RuntimeError::UnresolvedTarget {
+            tx_index,
+            reason: *reason,
+        }),
+        None => Err(RuntimeError::UnresolvedTarget {
+            tx_index,
+            reason: UnresolvedTargetReason::NotExecutedBefore,
+        }),
+    }
+}
+
+/// Address of the contract a batch transaction created, or why there is none
+fn created_address<O>(
+    tx: &SimulationTx,
+    result: &TraceResult<O>,
+) -> Result<Address, UnresolvedTargetReason> {
+    if !tx.transact_to.is_create() {
+        return Err(UnresolvedTargetReason::NotACreation);
+    }
+    match result {
+        Ok((execution, _, _)) if execution.is_success() => execution
+            .output()
+            .and_then(Output::address)
+            .copied()
+            .ok_or(UnresolvedTargetReason::Failed),
+        _ => Err(UnresolvedTargetReason::Failed),
+    }
+}
+
+/// Extracts the message of a panic payload, as passed to `panic!`
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
+
+/// Maps a failed transact call at `block` to a runtime error
+///
+/// Transactions rejected by pre-execution validation (only possible under strict
+/// validation or an explicit gas price) keep their typed reason as
+/// `RuntimeError::InvalidTransaction`; other failures go through [`execution_error`].
+pub(crate) fn transact_error<E: std::fmt::Display>(
+    block: u64,
+    context: &str,
+    error: EVMError<E>,
+) -> RuntimeError {
+    match error {
+        EVMError::Transaction(invalid) => RuntimeError::InvalidTransaction(invalid),
+        error => execution_error(block, context, error),
+    }
+}
@@ -0,0 +1,273 @@
+//! Replay of mined transactions by hash
+//!
+//! [`TraceEvm::trace_transaction_by_hash`] fetches a transaction and its block, pins the
+//! EVM to the parent block and re-executes the block's transactions up to the
+//! requested one, so the transaction sees the state it was mined on.
+//!
+//! Transactions a `SimulationTx` cannot represent, such as the OP Stack deposits
+//! opening every Optimism and Base block, are detected from their type in the RPC
+//! response and routed through a [`SystemTxHandling`] instead of failing with
+//! confusing nonce or balance errors.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::{
+    chains::{
+        l1_attributes_storage, SystemTxHandling, L1_ATTRIBUTES_DEPOSITOR, L1_BLOCK_PREDEPLOY,
+    },
+    errors::{EvmError, InitError, RuntimeError},
+    inspectors::tx_inspector::TxInspector,
+    report::SimulationReport,
+    traits::ResetBlock,
+    types::{SimulationBatch, SimulationTx, StateOverride},
+    TraceEvm,
+};
+use alloy::{
+    consensus::{BlockHeader, Transaction as _},
+    eips::{eip2930::AccessList, Typed2718},
+    network::{AnyNetwork, AnyRpcTransaction, TransactionResponse},
+    primitives::{Address, B256, U256},
+    providers::Provider,
+};
+use revm::{
+    context::TxEnv,
+    database::{CacheDB, DatabaseRef},
+    ExecuteEvm,
+};
+
+impl<DB> TraceEvm<CacheDB<DB>, TxInspector>
+where
+    DB: DatabaseRef + ResetBlock,
+    <DB as ResetBlock>::Error: Into<EvmError>,
+{
+    /// Replays the mined transaction `hash` on the state it was executed on
+    ///
+    /// The EVM is re-pinned to the parent of the transaction's block, which clears the
+    /// cache like [`set_db_block`](Self::set_db_block), and executes with the block's
+    /// number, timestamp, coinbase, base fee, gas limit and randomness. The
+    /// transactions preceding it in the block run first, in one stateful batch, as the
+    /// transaction may depend on their effects. Each transaction is replayed with its
+    /// mined envelope: type, nonce, gas limit, fee caps, access list and blob hashes.
+    /// EIP-7702 authorization lists are not carried, so such transactions run as
+    /// EIP-1559 transactions without their delegations. A transaction hook set on the
+    /// EVM is not applied during the replay and is kept for later batches.
+    ///
+    /// Every transaction of the block is admitted with `system_txs` (see
+    /// [`SystemTxHandling::admit`]). With `Skip`, OP Stack deposits are left out, so
+    /// their effects within the block (the L1 attributes update, minted deposits) are
+    /// missing from the replay; user transactions rarely observe them. With
+    /// `ApplyAsStateOverride`, the `L1Block` storage written by the L1 attributes
+    /// deposit is applied as a state override before the first transaction, and other
+    /// deposits are left out. With `Error`, the replay fails on the first deposit up to
+    /// the requested transaction.
+    ///
+    /// # Returns
+    /// - `Ok(Some(report))`: Report of the replayed transaction
+    /// - `Ok(None)`: The transaction is itself a system transaction, not executed
+    ///   under `Skip` or `ApplyAsStateOverride`
+    /// - `Err(EvmError::Runtime(RuntimeError::UnsupportedTransaction(_)))`: A system
+    ///   transaction was met under `SystemTxHandling::Error`
+    /// - `Err(EvmError::Runtime(RuntimeError::InvalidInput(_)))`: The transaction is
+    ///   unknown to the node or still pending
+    /// - `Err(EvmError::Init(_))`: The transaction or its block could not be fetched
+    /// - `Err(_)`: The transaction could not be traced
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use alloy::primitives::b256;
+    /// use revm_trace::{
+    ///     chains::SystemTxHandling, create_evm_with_tracer, evm::builder::get_provider,
+    ///     TxInspector,
+    /// };
+    ///
+    /// let rpc_url = "https://mainnet.base.org";
+    /// let provider = get_provider(rpc_url).await?;
+    /// let mut evm = create_evm_with_tracer(rpc_url, TxInspector::new()).await?;
+    /// let hash = b256!("0000000000000000000000000000000000000000000000000000000000000001");
+    /// if let Some(report) = evm
+    ///     .trace_transaction_by_hash(&provider, hash, SystemTxHandling::Skip)
+    ///     .await?
+    /// {
+    ///     println!("success: {}", report.summary.success);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn trace_transaction_by_hash<P>(
+        &mut self,
+        provider: &P,
+        hash: B256,
+        system_txs: SystemTxHandling,
+    ) -> Result<Option<SimulationReport>, EvmError>
+    where
+        P: Provider<AnyNetwork>,
+    {
+        let tx = provider
+            .get_transaction_by_hash(hash)
+            .await
+            .map_err(|e| {
+                InitError::BlockFetchError(format!("Failed to fetch transaction {hash}: {e}"))
+            })?
+            .ok_or_else(|| RuntimeError::InvalidInput(format!("transaction {hash} not found")))?;
+        if !system_txs.admit(tx.ty())? {
+            return Ok(None);
+        }
+        let (Some(block_number), Some(index)) = (tx.block_number, tx.transaction_index) else {
+            return Err(
+                RuntimeError::InvalidInput(format!("transaction {hash} is pending")).into(),
+            );
+        };
+
+        let block = provider
+            .get_block_by_number(block_number.into())
+            .full()
+            .await
+            .map_err(|e| {
+                InitError::BlockFetchError(format!("Failed to fetch block {block_number}: {e}"))
+            })?
+            .ok_or_else(|| InitError::BlockNotFound(format!("Block {block_number} not found")))?;
+        let preceding = block
+            .transactions
+            .as_transactions()
+            .and_then(|mined| mined.get(..index as usize))
+            .ok_or_else(|| {
+                InitError::BlockFetchError(format!(
+                    "Block {block_number} lacks the transactions before {hash}"
+                ))
+            })?;
+        let mut transactions = Vec::with_capacity(preceding.len() + 1);
+        let mut envelopes: HashMap<Address, VecDeque<MinedEnvelope>> = HashMap::new();
+        let mut overrides = StateOverride::default();
+        for mined in preceding {
+            if system_txs.admit(mined.ty())? {
+                transactions.push(simulation_tx(mined));
+                envelopes
+                    .entry(mined.from())
+                    .or_default()
+                    .push_back(MinedEnvelope::new(mined));
+            } else if system_txs == SystemTxHandling::ApplyAsStateOverride {
+                if let Some(slots) = l1_attributes_deposit_storage(mined) {
+                    overrides
+                        .storages
+                        .entry(L1_BLOCK_PREDEPLOY)
+                        .or_default()
+                        .extend(slots);
+                }
+            }
+        }
+        transactions.push(simulation_tx(&tx));
+        envelopes
+            .entry(tx.from())
+            .or_default()
+            .push_back(MinedEnvelope::new(&tx));
+
+        // State at the end of the parent block, environment of the mined block
+        let header = block.header();
+        let mut block_env = self.evm.ctx.block.clone();
+        block_env.number = block_number.saturating_sub(1);
+        self.set_db_block(block_env.clone())?;
+        block_env.number = block_number;
+        block_env.timestamp = header.timestamp();
+        block_env.beneficiary = header.beneficiary();
+        block_env.basefee = header.base_fee_per_gas().unwrap_or_default();
+        block_env.gas_limit = header.gas_limit();
+        block_env.prevrandao = header.mix_hash();
+        self.set_block(block_env);
+
+        let target = transactions.len() - 1;
+        let batch = SimulationBatch {
+            transactions,
+            is_stateful: true,
+            overrides: (!overrides.storages.is_empty()).then_some(overrides),
+            ..Default::default()
+        };
+        // Each sender's transactions execute in block order, taking its envelopes in turn
+        let user_hook = self.tx_hook.replace(Box::new(move |env, input| {
+            if let Some(envelope) = envelopes
+                .get_mut(&input.caller)
+                .and_then(VecDeque::pop_front)
+            {
+                envelope.apply(env);
+            }
+        }));
+        let mut report = None;
+        self.trace_transactions_with(batch, |index, tx, _, result| {
+            if index == target {
+                report = Some(result.map(|(result, storage_diff, trace)| {
+                    SimulationReport::new(tx, &result, storage_diff, trace)
+                }));
+            }
+        });
+        self.tx_hook = user_hook;
+        report.transpose()
+    }
+}
+
+/// Envelope fields of a mined transaction a `SimulationTx` does not carry
+#[derive(Debug, Clone)]
+struct MinedEnvelope {
+    tx_type: u8,
+    nonce: u64,
+    gas_limit: u64,
+    gas_price: u128,
+    gas_priority_fee: Option<u128>,
+    access_list: AccessList,
+    blob_hashes: Vec<B256>,
+    max_fee_per_blob_gas: u128,
+}
+
+impl MinedEnvelope {
+    fn new(tx: &AnyRpcTransaction) -> Self {
+        // Without their authorization list, EIP-7702 transactions run as EIP-1559 ones
+        let tx_type = match tx.ty() {
+            0x04 => 0x02,
+            ty => ty,
+        };
+        Self {
+            tx_type,
+            nonce: tx.nonce(),
+            gas_limit: tx.gas_limit(),
+            // The fee cap for dynamic fee transactions, as in revm's `TxEnv`
+            gas_price: tx.max_fee_per_gas(),
+            gas_priority_fee: tx.max_priority_fee_per_gas(),
+            access_list: tx.access_list().cloned().unwrap_or_default(),
+            blob_hashes: tx
+                .blob_versioned_hashes()
+                .map(<[B256]>::to_vec)
+                .unwrap_or_default(),
+            max_fee_per_blob_gas: tx.max_fee_per_blob_gas().unwrap_or_default(),
+        }
+    }
+
+    fn apply(self, env: &mut TxEnv) {
+        env.tx_type = self.tx_type;
+        env.nonce = self.nonce;
+        env.gas_limit = self.gas_limit;
+        env.gas_price = self.gas_price;
+        env.gas_priority_fee = self.gas_priority_fee;
+        env.access_list = self.access_list;
+        env.blob_hashes = self.blob_hashes;
+        env.max_fee_per_blob_gas = self.max_fee_per_blob_gas;
+    }
+}
+
+/// `L1Block` storage written by `tx`, if it is an L1 attributes deposit
+fn l1_attributes_deposit_storage(tx: &AnyRpcTransaction) -> Option<Vec<(U256, U256)>> {
+    if tx.from() != L1_ATTRIBUTES_DEPOSITOR || tx.to() != Some(L1_BLOCK_PREDEPLOY) {
+        return None;
+    }
+    l1_attributes_storage(tx.input())
+}
+
+/// Converts a mined transaction into the call it made
+fn simulation_tx(tx: &AnyRpcTransaction) -> SimulationTx {
+    SimulationTx {
+        caller: tx.from(),
+        value: tx.value(),
+        data: tx.input().clone(),
+        transact_to: tx.kind(),
+        trace_config: None,
+        return_abi: None,
+    }
+}
@@ -1,29 +1,47 @@
 use crate::{
-    errors::EvmError,
+    errors::{EvmError, RuntimeError},
     traits::{ResetBlock, ResetDB},
-    types::AllDBType,
-    TraceEvm,
+    types::{AccountOverride, AllDBType},
+    DbStats, MyWrapDatabaseAsync, RpcRateLimiter, TraceEvm, WarmCache,
+};
+use alloy::{
+    eips::{BlockId, BlockNumberOrTag},
+    primitives::{Address, B256, U256},
 };
-use alloy::eips::{BlockId, BlockNumberOrTag};
 use revm::{
     context::BlockEnv,
     context_interface::ContextTr,
-    database::{CacheDB, DatabaseRef},
+    database::{
+        in_memory_db::{AccountState, Cache, DbAccount},
+        CacheDB, Database, DatabaseRef,
+    },
+    state::Bytecode,
     ExecuteEvm,
 };
+use std::sync::atomic::Ordering;
 // ========================= Database Management =========================
 
-/// Implementation for TraceEvm instances with CacheDB
-///
-/// Provides database cache management utilities specifically for EVM instances
-/// that use `CacheDB` as their database layer.
-impl<DB, INSP> ResetDB for TraceEvm<CacheDB<DB>, INSP>
+/// Clears the `CacheDB` overlay, so that reads go to the wrapped database again
+impl<DB> ResetDB for CacheDB<DB>
 where
     DB: DatabaseRef,
+{
+    fn reset_db(&mut self) {
+        self.cache.accounts.clear();
+        self.cache.contracts.clear();
+        self.cache.logs = Vec::new();
+        self.cache.block_hashes.clear();
+    }
+}
+
+/// Resets the database of any TraceEvm whose database supports it
+impl<DB, INSP> ResetDB for TraceEvm<DB, INSP>
+where
+    DB: Database + ResetDB,
 {
     /// Reset the database cache to clear all cached state
     ///
-    /// This method clears all cached data from the `CacheDB` layer, including:
+    /// For `CacheDB`, this clears all cached data, including:
     /// - Account states and balances
     /// - Contract bytecode and storage
     /// - Event logs
@@ -53,11 +71,328 @@ where
     /// # }
     /// ```
     fn reset_db(&mut self) {
-        let cached_db = &mut self.0.ctx.db().cache;
-        cached_db.accounts.clear();
-        cached_db.contracts.clear();
-        cached_db.logs = Vec::new();
-        cached_db.block_hashes.clear();
+        self.evm.ctx.db().reset_db();
+    }
+}
+
+// ========================= State Modification =========================
+
+impl<DB, INSP> TraceEvm<CacheDB<DB>, INSP>
+where
+    DB: DatabaseRef,
+{
+    /// Writes a storage slot into the `CacheDB` overlay
+    ///
+    /// The value shadows the upstream state until the cache is cleared. Note that
+    /// `trace_transactions` clears the cache at the start of every batch; use
+    /// `StateOverride` to apply storage changes to a batch.
+    pub fn insert_storage(
+        &mut self,
+        address: Address,
+        slot: U256,
+        value: U256,
+    ) -> Result<(), EvmError> {
+        self.evm
+            .ctx
+            .db()
+            .insert_account_storage(address, slot, value)
+            .map_err(|e| {
+                RuntimeError::SlotAccess(format!(
+                    "Failed to set storage {address}:{slot} = {value}: {e}"
+                ))
+                .into()
+            })
+    }
+
+    /// Sets the native balance of an account in the `CacheDB` overlay
+    ///
+    /// The same cache lifetime caveats as for [`insert_storage`](Self::insert_storage) apply.
+    pub fn set_balance(&mut self, address: Address, balance: U256) -> Result<(), EvmError> {
+        let account = self.evm.ctx.db().load_account(address).map_err(|e| {
+            RuntimeError::AccountAccess(format!("Failed to load account {address}: {e}"))
+        })?;
+        account.info.balance = balance;
+        Ok(())
+    }
+}
+
+// ========================= State Snapshots =========================
+
+/// Saved contents of the `CacheDB` overlay, created by [`TraceEvm::snapshot`]
+#[derive(Debug, Clone)]
+pub struct DbSnapshot {
+    cache: Cache,
+}
+
+impl<DB, INSP> TraceEvm<CacheDB<DB>, INSP>
+where
+    DB: DatabaseRef,
+{
+    /// Saves the current simulated state
+    ///
+    /// Captures everything committed to the `CacheDB` overlay (executed stateful
+    /// transactions, inserted storage and balances). Restore it with
+    /// [`revert_to`](Self::revert_to) to explore alternatives from the same state.
+    /// A snapshot copies the whole overlay, so its cost grows with the cached state.
+    pub fn snapshot(&mut self) -> DbSnapshot {
+        DbSnapshot {
+            cache: self.evm.ctx.db().cache.clone(),
+        }
+    }
+
+    /// Restores the simulated state saved by [`snapshot`](Self::snapshot)
+    ///
+    /// Inspectors that cache storage values (such as `TxInspector`) should have
+    /// their slot cache reset afterwards.
+    pub fn revert_to(&mut self, snapshot: DbSnapshot) {
+        self.evm.ctx.db().cache = snapshot.cache;
+    }
+}
+
+// ========================= Ephemeral State =========================
+
+/// Overlay entries replaced by `with_ephemeral_state`, restored when dropped
+///
+/// Restoring on drop covers a closure that panics as well as one that returns.
+struct EphemeralGuard<'a, DB: DatabaseRef, INSP> {
+    evm: &'a mut TraceEvm<CacheDB<DB>, INSP>,
+    /// Previous entry of every overridden account, `None` if it was not cached
+    accounts: Vec<(Address, Option<DbAccount>)>,
+    /// Code hashes that were added to the overlay by the overrides
+    contracts: Vec<B256>,
+}
+
+impl<DB: DatabaseRef, INSP> Drop for EphemeralGuard<'_, DB, INSP> {
+    fn drop(&mut self) {
+        let cache = &mut self.evm.evm.ctx.db().cache;
+        for (address, previous) in self.accounts.drain(..).rev() {
+            match previous {
+                Some(account) => cache.accounts.insert(address, account),
+                None => cache.accounts.remove(&address),
+            };
+        }
+        // Code the closure itself committed to an account stays
+        for hash in self.contracts.drain(..) {
+            let referenced = cache
+                .accounts
+                .values()
+                .any(|account| account.info.code_hash == hash);
+            if !referenced {
+                cache.contracts.remove(&hash);
+            }
+        }
+    }
+}
+
+impl<DB, INSP> TraceEvm<CacheDB<DB>, INSP>
+where
+    DB: DatabaseRef,
+{
+    /// Runs `f` with `overrides` applied to the simulated state, then undoes them
+    ///
+    /// The `CacheDB` entries of the overridden accounts are saved first and put back
+    /// exactly as they were once `f` returns or panics; accounts that were not cached
+    /// before are removed again. Changes `f` makes to other accounts (e.g. by committing
+    /// a transaction) are kept. Overrides are applied in order, so a later override of
+    /// the same account wins.
+    ///
+    /// # Returns
+    /// - `Ok(R)`: The result of `f`
+    /// - `Err(EvmError::Runtime(_))`: An overridden account could not be loaded; no
+    ///   override is left in place and `f` did not run
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use revm_trace::{create_evm, types::AccountOverride};
+    /// use alloy::primitives::{address, U256};
+    ///
+    /// let mut evm = create_evm("https://eth.llamarpc.com").await?;
+    /// let whale = address!("1000000000000000000000000000000000000001");
+    /// let balance = evm.with_ephemeral_state(
+    ///     vec![AccountOverride {
+    ///         address: whale,
+    ///         balance: Some(U256::MAX),
+    ///         ..Default::default()
+    ///     }],
+    ///     |evm| revm_trace::utils::balance_utils::query_balance(evm, whale),
+    /// )??;
+    /// assert_eq!(balance, U256::MAX);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_ephemeral_state<R>(
+        &mut self,
+        overrides: Vec<AccountOverride>,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> Result<R, EvmError> {
+        let mut guard = EphemeralGuard {
+            evm: self,
+            accounts: Vec::new(),
+            contracts: Vec::new(),
+        };
+        for account_override in overrides {
+            guard.apply(account_override)?;
+        }
+        Ok(f(&mut *guard.evm))
+    }
+}
+
+impl<DB: DatabaseRef, INSP> EphemeralGuard<'_, DB, INSP> {
+    /// Saves the entries `account_override` touches, then applies it
+    fn apply(&mut self, account_override: AccountOverride) -> Result<(), EvmError> {
+        let AccountOverride {
+            address,
+            balance,
+            nonce,
+            code,
+            storage,
+        } = account_override;
+        let db = self.evm.evm.ctx.db();
+        if !self.accounts.iter().any(|(saved, _)| *saved == address) {
+            self.accounts
+                .push((address, db.cache.accounts.get(&address).cloned()));
+        }
+        let account = db.load_account(address).map_err(|e| {
+            RuntimeError::AccountAccess(format!("Failed to load account {address}: {e}"))
+        })?;
+        // Accounts missing upstream only read as existing once changed
+        if account.account_state == AccountState::NotExisting
+            && (balance.is_some() || nonce.is_some() || code.is_some())
+        {
+            account.account_state = AccountState::Touched;
+        }
+        if let Some(balance) = balance {
+            account.info.balance = balance;
+        }
+        if let Some(nonce) = nonce {
+            account.info.nonce = nonce;
+        }
+        for (slot, value) in storage {
+            account.storage.insert(slot, value);
+        }
+        if let Some(code) = code {
+            let mut info = account.info.clone();
+            let bytecode = Bytecode::new_raw(code);
+            info.code_hash = bytecode.hash_slow();
+            info.code = Some(bytecode);
+            if !db.cache.contracts.contains_key(&info.code_hash) {
+                self.contracts.push(info.code_hash);
+            }
+            db.insert_contract(&mut info);
+            if let Some(account) = db.cache.accounts.get_mut(&address) {
+                account.info = info;
+            }
+        }
+        Ok(())
+    }
+}
+
+// ========================= Database Statistics =========================
+
+impl<T, INSP> TraceEvm<CacheDB<MyWrapDatabaseAsync<T>>, INSP> {
+    /// Returns lookup statistics of the `CacheDB` overlay and the wrapped upstream
+    /// database
+    ///
+    /// `cache_hits` and `cache_misses` tell how often the overlay answered the state
+    /// executed transactions loaded, the remaining counts how often the lookups it
+    /// forwarded reached the node. `cache_hits` is only counted for EVMs created by
+    /// the builder.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use revm_trace::create_evm;
+    ///
+    /// let mut evm = create_evm("https://eth.llamarpc.com").await?;
+    /// evm.reset_db_stats();
+    /// // ... run simulations ...
+    /// let stats = evm.db_stats();
+    /// println!("cache hits: {}, misses: {}", stats.cache_hits, stats.cache_misses);
+    /// println!("upstream requests: {}", stats.upstream_calls);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn db_stats(&self) -> DbStats {
+        DbStats {
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            ..self.evm.ctx.journaled_state.database.db.stats()
+        }
+    }
+
+    /// Resets the database lookup statistics, `CacheDB` hits included
+    pub fn reset_db_stats(&self) {
+        self.cache_hits.store(0, Ordering::Relaxed);
+        self.evm.ctx.journaled_state.database.db.reset_stats();
+    }
+
+    /// Enables or disables recording which state each transaction fetched upstream
+    ///
+    /// While enabled, batches record the lookups that reached the remote node during
+    /// each transaction, available afterwards from
+    /// [`take_last_remote_dependencies`](TraceEvm::take_last_remote_dependencies).
+    /// State answered by the `CacheDB` overlay, overrides or a warm cache is not
+    /// listed, so the entries are exactly what the results assume from the node.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use revm_trace::{create_evm_with_tracer, dependencies_digest, TxInspector};
+    ///
+    /// let mut evm = create_evm_with_tracer("https://eth.llamarpc.com", TxInspector::new()).await?;
+    /// evm.record_remote_dependencies(true);
+    /// // ... evm.trace_transactions(batch) ...
+    /// for fetches in evm.take_last_remote_dependencies().unwrap_or_default() {
+    ///     println!("{} fetches, digest {}", fetches.len(), dependencies_digest(&fetches));
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn record_remote_dependencies(&self, enabled: bool) {
+        self.evm
+            .ctx
+            .journaled_state
+            .database
+            .db
+            .record_fetches(enabled);
+    }
+
+    /// Shares upstream lookups with other EVMs at the same block through `cache`
+    ///
+    /// The cache is pinned to the block of the EVM first, which drops its entries if
+    /// they were fetched at another block. Accounts, code and storage slots missing
+    /// from the `CacheDB` overlay are then answered from the cache when possible, and
+    /// the answers of upstream requests are added to it. Simulated state changes stay
+    /// in the overlay and never reach the cache. Moving the EVM to another block with
+    /// [`set_db_block`](Self::set_db_block) stops it from using the cache.
+    ///
+    /// Lookups answered by the cache are reported as `warm_cache_hits` by
+    /// [`db_stats`](Self::db_stats).
+    pub fn with_warm_cache(mut self, cache: WarmCache) -> Self {
+        cache.pin(self.evm.ctx.block.number);
+        self.evm
+            .ctx
+            .journaled_state
+            .database
+            .db
+            .attach_warm_cache(cache);
+        self
+    }
+
+    /// Limits the rate of upstream lookups with `limiter`
+    ///
+    /// Lookups not answered by the `CacheDB` overlay or a warm cache wait for a token
+    /// of `limiter` before reaching the node; the time spent waiting is reported as
+    /// `rate_limit_wait` by [`db_stats`](Self::db_stats). Clones of one limiter share
+    /// their budget, so attach clones to every EVM reading from the same endpoint.
+    pub fn with_rate_limiter(mut self, limiter: RpcRateLimiter) -> Self {
+        self.evm
+            .ctx
+            .journaled_state
+            .database
+            .db
+            .attach_rate_limiter(limiter);
+        self
     }
 }
 
@@ -65,8 +400,16 @@ impl ResetBlock for AllDBType {
     type Error = EvmError;
     fn reset_block(&mut self, block_number: u64) -> Result<(), EvmError> {
         // Reset the block number in the EVM context
-        let db = self.get_db_mut();
-        db.set_block_number(BlockId::Number(BlockNumberOrTag::Number(block_number)));
+        self.get_db_mut()
+            .set_block_number(BlockId::Number(BlockNumberOrTag::Number(block_number)));
+        self.set_warm_block(block_number);
+        Ok(())
+    }
+
+    fn reset_block_hash(&mut self, block_number: u64, block_hash: B256) -> Result<(), EvmError> {
+        self.get_db_mut()
+            .set_block_number(BlockId::hash(block_hash));
+        self.set_warm_block(block_number);
         Ok(())
     }
 }
@@ -86,17 +429,97 @@ where
     pub fn set_db_block(&mut self, block_env: BlockEnv) -> Result<(), EvmError> {
         // Step 1: Reset the underlying database's block state
         {
-            let cache_db = &mut self.0.ctx.db().db;
+            let cache_db = &mut self.evm.ctx.db().db;
             cache_db.reset_block(block_env.number).map_err(Into::into)?;
         }
         // Step 2: Clear the outer CacheDB cache
         self.reset_db();
 
         // Step 3: Update the EVM's block context
+        let block_context = self.block_context_mut();
+        block_context.number = block_env.number;
+        block_context.timestamp = block_env.timestamp;
+        block_context.hash = B256::ZERO;
+        block_context.tag = None;
         self.set_block(block_env);
+        self.block_gas_used = 0;
 
         Ok(())
     }
+
+    /// Re-resolves the block tag the EVM was built at and pins the EVM to its block
+    ///
+    /// For long-lived EVMs built with `EvmBuilder::with_block_tag`: the tag is resolved
+    /// again through `provider` and the database is pinned to the hash of the block it
+    /// points to now. Like [`set_db_block`](Self::set_db_block) this clears the cache
+    /// and keeps the rest of the block environment (coinbase, basefee, ...).
+    ///
+    /// # Returns
+    /// - `Ok(BlockContext)`: The block the EVM now runs on
+    /// - `Err(EvmError::Runtime(RuntimeError::InvalidInput))`: The EVM was not built at
+    ///   a block tag
+    /// - `Err(EvmError::Init(_))`: The tag could not be resolved; the EVM stays on its
+    ///   current block
+    #[cfg(any(feature = "default", feature = "rustls-tls"))]
+    pub async fn refresh_tag<P>(
+        &mut self,
+        provider: &P,
+    ) -> Result<crate::types::BlockContext, EvmError>
+    where
+        P: alloy::providers::Provider<alloy::network::AnyNetwork>,
+    {
+        let current = self.block_context();
+        let tag = current.tag.ok_or_else(|| {
+            RuntimeError::InvalidInput("the EVM was not built at a block tag".to_string())
+        })?;
+        let block =
+            crate::evm::builder::get_block_header_by_tag(provider, current.chain_id, tag).await?;
+
+        {
+            let cache_db = &mut self.evm.ctx.db().db;
+            cache_db
+                .reset_block_hash(block.number, block.hash)
+                .map_err(Into::into)?;
+        }
+        self.reset_db();
+
+        let mut block_env = self.evm.ctx.block.clone();
+        block_env.number = block.number;
+        block_env.timestamp = block.timestamp;
+        *self.block_context_mut() = block;
+        self.set_block(block_env);
+        self.block_gas_used = 0;
+
+        Ok(block)
+    }
+}
+
+#[cfg(any(feature = "default", feature = "rustls-tls"))]
+impl<INSP> TraceEvm<CacheDB<AllDBType>, INSP> {
+    /// Re-pins the EVM to the latest block of `provider`, typically a dev node
+    ///
+    /// Costs one header lookup; like [`set_db_block`](Self::set_db_block) it clears
+    /// the cache, so state mined since is fetched on demand. Unlike it, the block hash
+    /// is recorded and the rest of the block environment (coinbase, basefee, ...) is
+    /// kept.
+    ///
+    /// # Returns
+    /// The block the EVM now follows
+    pub async fn refresh_to_latest<P>(
+        &mut self,
+        provider: &P,
+    ) -> Result<crate::types::BlockContext, EvmError>
+    where
+        P: alloy::providers::Provider<alloy::network::AnyNetwork>,
+    {
+        let block = crate::evm::builder::get_block_context(provider, None, None).await?;
+        let mut block_env = self.evm.ctx.block.clone();
+        block_env.number = block.number;
+        block_env.timestamp = block.timestamp;
+        self.set_db_block(block_env)?;
+        self.block_context_mut().hash = block.hash;
+        Ok(self.block_context())
+    }
 }
 
 #[cfg(feature = "foundry-fork")]
@@ -117,4 +540,12 @@ impl ResetBlock for SharedBackend {
         data.clear();
         Ok(())
     }
+
+    fn reset_block_hash(&mut self, _block_number: u64, block_hash: B256) -> Result<(), EvmError> {
+        self.set_pinned_block(BlockId::hash(block_hash))
+            .map_err(|e| EvmError::Init(InitError::DatabaseError(e.to_string())))?;
+        let data = self.data();
+        data.clear();
+        Ok(())
+    }
 }
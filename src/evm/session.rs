@@ -0,0 +1,174 @@
+//! Re-simulating the tail of a stateful batch
+//!
+//! Tweaking the last transactions of a long stateful batch normally means executing
+//! the whole batch again. A [`BatchSession`] executes and commits the prefix once,
+//! snapshots the resulting state, and then runs any number of tails on top of it with
+//! [`BatchSession::run_tail`]. Every tail starts from the same snapshot, so tails do
+//! not see each other and the same tail always gives the same results.
+
+use crate::{
+    errors::{EvmError, RuntimeError},
+    evm::{reset::DbSnapshot, TraceEvm},
+    traits::{TraceInspector, TraceResult},
+    types::{BlockContext, SimulationBatch, SimulationTx},
+};
+use alloy::primitives::Address;
+use revm::{
+    context::BlockEnv,
+    database::{CacheDB, DatabaseRef},
+    handler::MainnetContext,
+};
+use std::collections::HashMap;
+
+/// State after the prefix of a batch, from which tails are run
+///
+/// Created by [`TraceEvm::begin_session`]. Besides the `CacheDB` overlay, the session
+/// keeps the sender nonces of the prefix, the inspector with its batch-wide state and
+/// the gas used in the simulated block, and puts all of them back before every tail.
+///
+/// The session is bound to the block the prefix ran at: once the EVM's block context
+/// or block environment changes (e.g. through [`evm`](Self::evm) and
+/// `TraceEvm::set_db_block`), the session is invalidated and tails fail with
+/// `RuntimeError::SessionInvalidated`.
+pub struct BatchSession<'a, DB: DatabaseRef, INSP: TraceInspector<MainnetContext<CacheDB<DB>>>> {
+    evm: &'a mut TraceEvm<CacheDB<DB>, INSP>,
+    /// Overlay after the prefix
+    snapshot: DbSnapshot,
+    /// Inspector after the prefix
+    inspector: INSP,
+    /// Sender nonces read or committed by the prefix
+    nonces: HashMap<Address, u64>,
+    /// Gas used in the simulated block after the prefix
+    block_gas_used: u64,
+    /// Block context the prefix ran at
+    block_context: BlockContext,
+    /// Block environment the prefix ran in
+    block_env: BlockEnv,
+    /// Settings of the prefix batch, used for every tail
+    settings: SimulationBatch,
+    /// Results of the prefix transactions
+    prefix_results: Vec<TraceResult<INSP::Output>>,
+}
+
+impl<DB, INSP> TraceEvm<CacheDB<DB>, INSP>
+where
+    DB: DatabaseRef,
+    INSP: TraceInspector<MainnetContext<CacheDB<DB>>> + Clone,
+{
+    /// Executes and commits `prefix`, then keeps the resulting state for running tails
+    ///
+    /// The prefix always runs statefully, whatever its `is_stateful`; its overrides
+    /// are applied first as for a regular batch. Every tail passed to
+    /// [`BatchSession::run_tail`] then runs with the remaining settings of `prefix`
+    /// (coinbase, failure policy, empty code call policy, timeline and cancellation).
+    /// With `FailurePolicy::Abort`, a failure in the prefix does not carry over to the
+    /// tails.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use revm_trace::{create_evm_with_tracer, SimulationBatch, SimulationTx, TxInspector};
+    ///
+    /// let mut evm = create_evm_with_tracer("https://eth.llamarpc.com", TxInspector::new()).await?;
+    /// let prefix = SimulationBatch {
+    ///     transactions: vec![/* the first 47 transactions */],
+    ///     is_stateful: true,
+    ///     ..Default::default()
+    /// };
+    /// let variants: Vec<Vec<SimulationTx>> = vec![/* alternatives for the last transactions */];
+    /// let mut session = evm.begin_session(prefix);
+    /// for tail in variants {
+    ///     let results = session.run_tail(tail);
+    ///     // ... compare results ...
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn begin_session(&mut self, prefix: SimulationBatch) -> BatchSession<'_, DB, INSP> {
+        let mut settings = SimulationBatch {
+            is_stateful: true,
+            ..prefix
+        };
+        let transactions = std::mem::take(&mut settings.transactions);
+        let mut prefix_results = Vec::with_capacity(transactions.len());
+        let nonces = self.run_batch(
+            SimulationBatch {
+                transactions,
+                ..settings.clone()
+            },
+            None,
+            |_, _, _, result| prefix_results.push(result),
+        );
+        // Tails start from the committed prefix, overrides included, and number their
+        // transactions from zero
+        settings.overrides = None;
+        settings.targets.clear();
+        BatchSession {
+            snapshot: self.snapshot(),
+            inspector: self.inspector.clone(),
+            nonces,
+            block_gas_used: self.block_gas_used,
+            block_context: self.block_context(),
+            block_env: self.block.clone(),
+            settings,
+            prefix_results,
+            evm: self,
+        }
+    }
+}
+
+impl<DB, INSP> BatchSession<'_, DB, INSP>
+where
+    DB: DatabaseRef,
+    INSP: TraceInspector<MainnetContext<CacheDB<DB>>> + Clone,
+{
+    /// Runs `tail` on top of the state left by the prefix
+    ///
+    /// The state, sender nonces, inspector and block gas of the prefix are restored
+    /// first, so results do not depend on earlier tails. The tail runs statefully, so
+    /// its transactions see each other's changes; the prefix's
+    /// [`targets`](SimulationBatch::targets) do not apply to it. Batch metrics, fee accounting, the timeline and
+    /// executed environments of the EVM describe the tail afterwards.
+    ///
+    /// Once the session is invalidated, every transaction fails with
+    /// `RuntimeError::SessionInvalidated` and nothing is executed.
+    pub fn run_tail(&mut self, tail: Vec<SimulationTx>) -> Vec<TraceResult<INSP::Output>> {
+        if !self.is_valid() {
+            return tail
+                .iter()
+                .map(|_| Err(EvmError::Runtime(RuntimeError::SessionInvalidated)))
+                .collect();
+        }
+        self.evm.revert_to(self.snapshot.clone());
+        self.evm.inspector = self.inspector.clone();
+        self.evm.block_gas_used = self.block_gas_used;
+        let mut results = Vec::with_capacity(tail.len());
+        self.evm.run_batch(
+            SimulationBatch {
+                transactions: tail,
+                ..self.settings.clone()
+            },
+            Some(self.nonces.clone()),
+            |_, _, _, result| results.push(result),
+        );
+        results
+    }
+
+    /// Whether the EVM still simulates the block the prefix ran at
+    pub fn is_valid(&self) -> bool {
+        self.evm.block_context() == self.block_context && self.evm.block == self.block_env
+    }
+
+    /// Returns the results of the prefix transactions
+    pub fn prefix_results(&self) -> &[TraceResult<INSP::Output>] {
+        &self.prefix_results
+    }
+
+    /// Returns the EVM the session runs on
+    ///
+    /// Tails restore the state they need, so the EVM can be used in between. Changing
+    /// its block invalidates the session.
+    pub fn evm(&mut self) -> &mut TraceEvm<CacheDB<DB>, INSP> {
+        self.evm
+    }
+}
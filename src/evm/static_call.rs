@@ -0,0 +1,165 @@
+//! View-safety checks for arbitrary calls
+//!
+//! [`TraceEvm::static_call`] runs a call with STATICCALL semantics from the outermost
+//! frame on, so any attempt to modify state fails the way it would inside a regular
+//! STATICCALL. A guard inspector records the first such attempt with its opcode and
+//! the trace address of the frame that made it.
+
+use crate::{
+    errors::{EvmError, RuntimeError},
+    utils::error_utils::parse_custom_error,
+    TraceEvm,
+};
+use alloy::primitives::{hex, Address, Bytes, TxKind};
+use revm::{
+    bytecode::OpCode,
+    context::{ContextTr, TxEnv},
+    context_interface::result::ExecutionResult,
+    database::{CacheDB, DatabaseRef},
+    interpreter::{
+        interpreter_types::{InterpreterTypes, Jumps, LoopControl},
+        CallInputs, CallOutcome, InstructionResult, Interpreter,
+    },
+    Context, ExecuteEvm, InspectEvm, Inspector, MainBuilder, MainContext,
+};
+
+/// Forces static semantics on the outermost frame and records the first violation
+#[derive(Debug, Default)]
+struct StaticCallGuard {
+    /// Trace address and started children of every open frame, innermost last
+    frames: Vec<(Vec<usize>, usize)>,
+    /// Opcode being executed
+    opcode: u8,
+    /// Opcode and trace address of the first attempted state modification
+    violation: Option<(u8, Vec<usize>)>,
+}
+
+impl<CTX, INTR> Inspector<CTX, INTR> for StaticCallGuard
+where
+    CTX: ContextTr,
+    INTR: InterpreterTypes,
+{
+    fn call(&mut self, _context: &mut CTX, inputs: &mut CallInputs) -> Option<CallOutcome> {
+        let trace_address = match self.frames.last_mut() {
+            // Nested frames inherit static semantics from the outermost one
+            None => {
+                inputs.is_static = true;
+                Vec::new()
+            }
+            Some((parent, children)) => {
+                let mut trace_address = parent.clone();
+                trace_address.push(*children);
+                *children += 1;
+                trace_address
+            }
+        };
+        self.frames.push((trace_address, 0));
+        None
+    }
+
+    fn call_end(&mut self, _context: &mut CTX, _inputs: &CallInputs, _outcome: &mut CallOutcome) {
+        self.frames.pop();
+    }
+
+    fn step(&mut self, interp: &mut Interpreter<INTR>, _context: &mut CTX) {
+        self.opcode = interp.bytecode.opcode();
+    }
+
+    fn step_end(&mut self, interp: &mut Interpreter<INTR>, _context: &mut CTX) {
+        if self.violation.is_some()
+            || interp.control.instruction_result() != InstructionResult::StateChangeDuringStaticCall
+        {
+            return;
+        }
+        let trace_address = self
+            .frames
+            .last()
+            .map(|(trace_address, _)| trace_address.clone())
+            .unwrap_or_default();
+        self.violation = Some((self.opcode, trace_address));
+    }
+}
+
+impl<DB, INSP> TraceEvm<CacheDB<DB>, INSP>
+where
+    DB: DatabaseRef,
+{
+    /// Calls `to` with `data` from `from` under STATICCALL semantics
+    ///
+    /// Use this to guarantee that user-supplied calldata cannot mutate state, e.g.
+    /// before serving it from a read endpoint. The call runs on the current state
+    /// (including committed batches) with nonce, balance and base fee checks disabled,
+    /// and nothing it does is committed. The EVM's own inspector is not invoked.
+    ///
+    /// # Errors
+    /// - [`RuntimeError::StateMutationInStaticCall`] if any frame attempted SSTORE,
+    ///   TSTORE, LOG, CREATE, CREATE2, SELFDESTRUCT or a CALL with value, even when a
+    ///   parent frame caught the failure; reports the first attempt
+    /// - [`RuntimeError::Revert`] if the call reverted
+    /// - [`RuntimeError::ExecutionFailed`] if it halted or could not be executed
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use alloy::primitives::{address, Address, Bytes};
+    /// use revm_trace::create_evm;
+    ///
+    /// let mut evm = create_evm("https://eth.llamarpc.com").await?;
+    /// let token = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+    /// let calldata = Bytes::from_static(&[0x18, 0x16, 0x0d, 0xdd]); // totalSupply()
+    /// let output = evm.static_call(token, calldata, Address::ZERO)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn static_call(
+        &mut self,
+        to: Address,
+        data: Bytes,
+        from: Address,
+    ) -> Result<Bytes, EvmError> {
+        let ctx = &self.evm.ctx;
+        let mut cfg = ctx.cfg.clone();
+        cfg.disable_nonce_check = true;
+        cfg.disable_balance_check = true;
+        cfg.disable_base_fee = true;
+        // Writes land in a throwaway overlay on top of the current state
+        let mut evm = Context::mainnet()
+            .with_db(CacheDB::new(&ctx.journaled_state.database))
+            .with_cfg(cfg)
+            .with_block(ctx.block.clone())
+            .build_mainnet_with_inspector(StaticCallGuard::default());
+
+        let tx = TxEnv::builder()
+            .caller(from)
+            .kind(TxKind::Call(to))
+            .chain_id(Some(evm.ctx.cfg.chain_id))
+            .data(data)
+            .build_fill();
+        evm.set_tx(tx);
+        let result = evm
+            .inspect_replay()
+            .map_err(|e| RuntimeError::ExecutionFailed(format!("Static call failed: {e}")))?;
+
+        if let Some((opcode, trace_address)) = evm.inspector.violation.take() {
+            let opcode = OpCode::new(opcode)
+                .map(|op| op.as_str().to_string())
+                .unwrap_or_else(|| format!("0x{opcode:02x}"));
+            return Err(RuntimeError::StateMutationInStaticCall {
+                opcode,
+                trace_address,
+            }
+            .into());
+        }
+        match result.result {
+            ExecutionResult::Success { output, .. } => Ok(output.into_data()),
+            ExecutionResult::Revert { output, .. } => Err(RuntimeError::Revert(
+                parse_custom_error(&output)
+                    .unwrap_or_else(|| format!("0x{}", hex::encode(&output))),
+            )
+            .into()),
+            ExecutionResult::Halt { reason, .. } => {
+                Err(RuntimeError::ExecutionFailed(format!("Static call halted: {reason:?}")).into())
+            }
+        }
+    }
+}
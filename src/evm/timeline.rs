@@ -0,0 +1,251 @@
+//! Ordered mutation log across a batch
+//!
+//! With [`SimulationBatch::build_timeline`](crate::types::SimulationBatch::build_timeline)
+//! set, `trace_transactions` merges the mutations the inspector observed in every
+//! transaction into one [`BatchTimeline`]: storage writes, transfers, contract
+//! creations and self-destructs, numbered in the order they executed across
+//! transaction boundaries. Stepping through it replays the batch one state change at
+//! a time, which is what reconstructing an attack spanning several transactions needs.
+//!
+//! Events of frames that reverted stay in the timeline, flagged `reverted`, so that
+//! attempts rolled back by the EVM are visible too.
+
+use crate::{evm::TraceEvm, types::TokenTransfer};
+use alloy::primitives::{Address, U256};
+use revm::database::Database;
+use serde::{Deserialize, Serialize};
+
+/// State change observed during a transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimelineEventKind {
+    /// SSTORE to a storage slot; only recorded at `TraceLevel::Full`
+    StorageWrite {
+        /// Account whose storage was written
+        address: Address,
+        slot: U256,
+        /// Value before the write
+        old_value: U256,
+        /// Value written
+        new_value: U256,
+    },
+    /// Native value or tokens moved, as recorded in the transaction's transfers
+    Transfer(TokenTransfer),
+    /// A contract creation started
+    Creation {
+        /// Account executing the creation
+        creator: Address,
+        /// Address of the new contract, `None` if the creation failed
+        created: Option<Address>,
+        /// Native value sent with the creation
+        value: U256,
+    },
+    /// A contract executed SELFDESTRUCT
+    SelfDestruct {
+        contract: Address,
+        /// Recipient of the remaining balance
+        beneficiary: Address,
+        /// Native balance moved to the beneficiary
+        value: U256,
+    },
+}
+
+/// One entry of a [`BatchTimeline`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimelineEvent {
+    /// Position in the batch's execution order, starting at 0
+    pub seq: u64,
+    /// Index of the transaction in the batch
+    pub tx_index: usize,
+    /// Position of the frame that executed the mutating operation
+    ///
+    /// Value sent with a call or creation belongs to the calling frame. Frames beyond
+    /// the inspector's depth limit report the deepest recorded frame.
+    pub trace_address: Vec<usize>,
+    /// Whether the frame, or one of its ancestors, failed, rolling the change back
+    pub reverted: bool,
+    pub kind: TimelineEventKind,
+}
+
+impl TimelineEvent {
+    /// Whether the event changes or moves value of `address`
+    ///
+    /// Covers the written account, both sides of a transfer and the token contract,
+    /// the creator and created contract, and both sides of a self-destruct.
+    pub fn involves(&self, address: Address) -> bool {
+        match &self.kind {
+            TimelineEventKind::StorageWrite {
+                address: account, ..
+            } => *account == address,
+            TimelineEventKind::Transfer(transfer) => {
+                transfer.from == address
+                    || transfer.to == Some(address)
+                    || (!transfer.is_native_token() && transfer.token == address)
+            }
+            TimelineEventKind::Creation {
+                creator, created, ..
+            } => *creator == address || *created == Some(address),
+            TimelineEventKind::SelfDestruct {
+                contract,
+                beneficiary,
+                ..
+            } => *contract == address || *beneficiary == address,
+        }
+    }
+}
+
+/// Mutations of a batch in execution order
+///
+/// Serializes as a flat list of [`TimelineEvent`]s ordered by `seq`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct BatchTimeline {
+    events: Vec<TimelineEvent>,
+}
+
+impl BatchTimeline {
+    /// All events, ordered by `seq`
+    pub fn events(&self) -> &[TimelineEvent] {
+        &self.events
+    }
+
+    /// Moves the events out of the timeline
+    pub fn into_events(self) -> Vec<TimelineEvent> {
+        self.events
+    }
+
+    /// Events involving `address` (see [`TimelineEvent::involves`]), in order
+    pub fn by_address(&self, address: Address) -> impl Iterator<Item = &TimelineEvent> + '_ {
+        self.events
+            .iter()
+            .filter(move |event| event.involves(address))
+    }
+
+    /// Writes to one storage slot of `address`, in order
+    ///
+    /// In a stateful batch this is the slot's full history: skipping reverted events,
+    /// each `old_value` equals the previous `new_value`.
+    pub fn by_slot(
+        &self,
+        address: Address,
+        slot: U256,
+    ) -> impl Iterator<Item = &TimelineEvent> + '_ {
+        self.events.iter().filter(move |event| {
+            matches!(
+                event.kind,
+                TimelineEventKind::StorageWrite { address: account, slot: written, .. }
+                    if account == address && written == slot
+            )
+        })
+    }
+
+    /// Events with `seq_a <= seq <= seq_b`
+    pub fn between(&self, seq_a: u64, seq_b: u64) -> &[TimelineEvent] {
+        let start = self.events.partition_point(|event| event.seq < seq_a);
+        let end = self.events.partition_point(|event| event.seq <= seq_b);
+        &self.events[start..end.max(start)]
+    }
+
+    /// Appends the events of transaction `tx_index`, given in capture order
+    pub(crate) fn push_tx(&mut self, tx_index: usize, events: Vec<TimelineEvent>) {
+        for mut event in events {
+            event.seq = self.events.len() as u64;
+            event.tx_index = tx_index;
+            self.events.push(event);
+        }
+    }
+}
+
+impl<DB, INSP> TraceEvm<DB, INSP>
+where
+    DB: Database,
+{
+    /// Takes the timeline of the most recent batch
+    ///
+    /// Returns `None` if that batch did not set `build_timeline`, or the timeline was
+    /// already taken. Only inspectors implementing
+    /// [`TraceOutput::take_timeline`](crate::traits::TraceOutput::take_timeline)
+    /// contribute events; `TxInspector` does.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use revm_trace::{create_evm_with_tracer, TxInspector};
+    ///
+    /// let mut evm = create_evm_with_tracer("https://eth.llamarpc.com", TxInspector::new()).await?;
+    /// // ... evm.trace_transactions(batch) with `build_timeline: true` ...
+    /// if let Some(timeline) = evm.take_last_timeline() {
+    ///     println!("{}", serde_json::to_string(&timeline)?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn take_last_timeline(&mut self) -> Option<BatchTimeline> {
+        self.last_timeline.take()
+    }
+
+    /// Stores the timeline of a completed batch
+    pub(crate) fn set_last_timeline(&mut self, timeline: Option<BatchTimeline>) {
+        self.last_timeline = timeline;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::address;
+
+    const CONTRACT: Address = address!("1000000000000000000000000000000000000001");
+    const OTHER: Address = address!("2000000000000000000000000000000000000002");
+
+    fn write(address: Address, slot: u64, new_value: u64) -> TimelineEvent {
+        TimelineEvent {
+            seq: 0,
+            tx_index: 0,
+            trace_address: Vec::new(),
+            reverted: false,
+            kind: TimelineEventKind::StorageWrite {
+                address,
+                slot: U256::from(slot),
+                old_value: U256::ZERO,
+                new_value: U256::from(new_value),
+            },
+        }
+    }
+
+    #[test]
+    fn test_sequence_spans_transactions() {
+        let mut timeline = BatchTimeline::default();
+        timeline.push_tx(0, vec![write(CONTRACT, 0, 1), write(OTHER, 0, 2)]);
+        timeline.push_tx(2, vec![write(CONTRACT, 1, 3), write(CONTRACT, 0, 4)]);
+
+        let order: Vec<_> = timeline
+            .events()
+            .iter()
+            .map(|event| (event.seq, event.tx_index))
+            .collect();
+        assert_eq!(order, vec![(0, 0), (1, 0), (2, 2), (3, 2)]);
+
+        let slot: Vec<_> = timeline
+            .by_slot(CONTRACT, U256::ZERO)
+            .map(|event| event.seq)
+            .collect();
+        assert_eq!(slot, vec![0, 3]);
+        assert_eq!(timeline.by_address(OTHER).count(), 1);
+
+        let seqs = |events: &[TimelineEvent]| events.iter().map(|e| e.seq).collect::<Vec<_>>();
+        assert_eq!(seqs(timeline.between(1, 2)), vec![1, 2]);
+        assert_eq!(seqs(timeline.between(3, 10)), vec![3]);
+        assert!(timeline.between(2, 1).is_empty());
+    }
+
+    #[test]
+    fn test_serializes_as_flat_list() {
+        let mut timeline = BatchTimeline::default();
+        timeline.push_tx(1, vec![write(CONTRACT, 0, 1)]);
+        let json = serde_json::to_value(&timeline).unwrap();
+        assert!(json.is_array());
+        assert_eq!(json[0]["tx_index"], 1);
+        let decoded: BatchTimeline = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, timeline);
+    }
+}
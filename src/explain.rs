@@ -0,0 +1,465 @@
+//! Plain-language summaries of simulation reports (feature = "labels")
+//!
+//! [`explain`] turns a [`SimulationReport`] into short sentences for wallet users,
+//! such as "Sends 1.2 ETH to 0x… (Uniswap V2: Router)" or "Grants unlimited USDC
+//! approval to 0x…". Sentences are composed from data the report already
+//! holds: asset transfers, `Approval` and `ApprovalForAll` logs, created contracts,
+//! the decoded revert reason and the findings of
+//! [`risk_assessment`](crate::analysis::risk_assessment). Nothing is generated: every
+//! sentence comes from a fixed template of the requested [`Lang`], so the output is
+//! deterministic and can be localized by adding a template table.
+//!
+//! Sentences are ordered by topic: the failure of a reverted transaction (which is
+//! then the only sentence), created contracts, transfers, approvals and risk
+//! findings. Within a topic they follow the order of the underlying data.
+//!
+//! Amounts are formatted with the decimals and symbols of
+//! [`SimulationReport::token_infos`]; tokens without metadata are shown as raw units.
+//! Addresses are named after the report's labels and address hints first, then after
+//! the label book.
+
+use crate::{
+    analysis::risk::{
+        located_transfers, risk_assessment, topic_address, FindingRef, RiskConfig, RiskKind,
+        Severity, APPROVAL_EVENT_SIGNATURE, APPROVAL_FOR_ALL_EVENT_SIGNATURE,
+    },
+    chains::ChainPreset,
+    labels::LabelBook,
+    report::SimulationReport,
+    types::{TokenTransfer, TokenType, NATIVE_TOKEN_ADDRESS},
+};
+use alloy::primitives::{Address, U256};
+use serde::Serialize;
+
+/// Chain whose labels and native token are used when the report does not record one
+const DEFAULT_CHAIN_ID: u64 = 1;
+
+/// Language of the generated sentences
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize)]
+#[non_exhaustive]
+pub enum Lang {
+    #[default]
+    English,
+}
+
+/// One sentence about a simulated transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Explanation {
+    /// How much the user should care
+    pub severity: Severity,
+    /// The sentence itself
+    pub text: String,
+    /// Trace locations the sentence is based on
+    pub refs: Vec<FindingRef>,
+}
+
+/// Sentence templates of one language
+///
+/// Placeholders in braces are replaced by [`fill`]; every template of a table must use
+/// the same placeholders as its English counterpart.
+struct Templates {
+    reverts: &'static str,
+    reverts_silently: &'static str,
+    deploys: &'static str,
+    sends: &'static str,
+    receives: &'static str,
+    mints: &'static str,
+    sends_nft: &'static str,
+    receives_nft: &'static str,
+    mints_nft: &'static str,
+    sends_items: &'static str,
+    receives_items: &'static str,
+    mints_items: &'static str,
+    amount: &'static str,
+    raw_amount: &'static str,
+    unlimited_approval: &'static str,
+    approval: &'static str,
+    revokes_approval: &'static str,
+    nft_approval: &'static str,
+    operator_approval: &'static str,
+    revokes_operator_approval: &'static str,
+    large_outflow: &'static str,
+    nft_to_unknown_recipient: &'static str,
+    entry_delegatecall: &'static str,
+    value_to_fresh_contract: &'static str,
+    tx_origin_authorization: &'static str,
+}
+
+const ENGLISH: Templates = Templates {
+    reverts: "Transaction reverts with: {reason}",
+    reverts_silently: "Transaction reverts without a reason",
+    deploys: "Deploys a contract at {address}",
+    sends: "Sends {amount} to {recipient}",
+    receives: "Receives {amount} from {sender}",
+    mints: "Mints {amount}",
+    sends_nft: "Sends NFT #{id} of {collection} to {recipient}",
+    receives_nft: "Receives NFT #{id} of {collection} from {sender}",
+    mints_nft: "Mints NFT #{id} of {collection}",
+    sends_items: "Sends {count} of item #{id} of {collection} to {recipient}",
+    receives_items: "Receives {count} of item #{id} of {collection} from {sender}",
+    mints_items: "Mints {count} of item #{id} of {collection}",
+    amount: "{value} {symbol}",
+    raw_amount: "{value} units of {token}",
+    unlimited_approval: "Grants unlimited {token} approval to {spender}",
+    approval: "Approves {spender} to spend {amount}",
+    revokes_approval: "Revokes the {token} approval of {spender}",
+    nft_approval: "Approves {spender} to transfer NFT #{id} of {collection}",
+    operator_approval: "Grants {spender} control of all {collection} NFTs",
+    revokes_operator_approval: "Revokes the control of {spender} over all {collection} NFTs",
+    large_outflow: "Moves out most of the sender's balance of a token",
+    nft_to_unknown_recipient: "Sends an NFT to an address that does not appear in the call trace",
+    entry_delegatecall: "The called contract delegates execution to other code",
+    value_to_fresh_contract: "Sends native tokens to a contract created in this transaction",
+    tx_origin_authorization: "A contract authorizes asset movements through tx.origin",
+};
+
+impl Lang {
+    fn templates(self) -> &'static Templates {
+        match self {
+            Self::English => &ENGLISH,
+        }
+    }
+}
+
+/// Explains `report` from the point of view of its sender
+///
+/// # Arguments
+/// - `report`: Simulated transaction; attach token metadata and address hints first
+///   for richer sentences
+/// - `label_book`: Labels for addresses the report does not name itself, looked up on
+///   the chain of the executed transaction (mainnet when unknown)
+/// - `lang`: Language of the sentences
+///
+/// # Returns
+/// Sentences in topic order, see the [module documentation](self)
+pub fn explain(
+    report: &SimulationReport,
+    label_book: Option<&LabelBook>,
+    lang: Lang,
+) -> Vec<Explanation> {
+    let explainer = Explainer {
+        report,
+        label_book,
+        templates: lang.templates(),
+        chain_id: report
+            .executed_tx
+            .as_ref()
+            .and_then(|tx| tx.chain_id)
+            .unwrap_or(DEFAULT_CHAIN_ID),
+        sender: report.tx.caller,
+    };
+    if !report.summary.success {
+        return vec![explainer.failure()];
+    }
+    let mut explanations = explainer.creations();
+    explanations.extend(explainer.transfers());
+    explanations.extend(explainer.approvals());
+    explanations.extend(explainer.risks());
+    explanations
+}
+
+/// Shared context of the sentence builders
+struct Explainer<'a> {
+    report: &'a SimulationReport,
+    label_book: Option<&'a LabelBook>,
+    templates: &'static Templates,
+    chain_id: u64,
+    sender: Address,
+}
+
+impl Explainer<'_> {
+    fn failure(&self) -> Explanation {
+        let text = match self.report.summary.error.as_deref() {
+            Some(reason) if !reason.is_empty() && reason != "0x" => {
+                fill(self.templates.reverts, &[("reason", reason)])
+            }
+            _ => self.templates.reverts_silently.to_string(),
+        };
+        Explanation {
+            severity: Severity::High,
+            text,
+            refs: self
+                .report
+                .trace
+                .error_trace_address
+                .clone()
+                .map(FindingRef::TraceAddress)
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    fn creations(&self) -> Vec<Explanation> {
+        let Some(root) = &self.report.trace.call_trace else {
+            return Vec::new();
+        };
+        root.frames()
+            .filter(|frame| frame.create_scheme.is_some() && frame.status.is_success())
+            .map(|frame| Explanation {
+                severity: Severity::Info,
+                text: fill(self.templates.deploys, &[("address", &self.name(frame.to))]),
+                refs: vec![FindingRef::TraceAddress(frame.trace_address.clone())],
+            })
+            .collect()
+    }
+
+    /// Transfers into and out of the sender's wallet
+    fn transfers(&self) -> Vec<Explanation> {
+        located_transfers(self.report)
+            .into_iter()
+            .filter_map(|(transfer, location)| {
+                let text = self.transfer_text(&transfer)?;
+                Some(Explanation {
+                    severity: Severity::Info,
+                    text,
+                    refs: vec![location],
+                })
+            })
+            .collect()
+    }
+
+    fn transfer_text(&self, transfer: &TokenTransfer) -> Option<String> {
+        let t = self.templates;
+        let outgoing = transfer.from == self.sender;
+        let incoming = transfer.to == Some(self.sender) && !outgoing;
+        if !outgoing && !incoming {
+            return None;
+        }
+        let minted = incoming && transfer.from.is_zero() && !transfer.is_native_token();
+        let recipient = self.name(transfer.to.unwrap_or_default());
+        let sender = self.name(transfer.from);
+        let id = transfer.id.unwrap_or_default().to_string();
+        let collection = self.token_name(transfer.token);
+
+        let text = match transfer.token_type {
+            TokenType::Native | TokenType::ERC20 => {
+                let amount = self.amount(transfer.token, transfer.value);
+                match (outgoing, minted) {
+                    (true, _) => fill(t.sends, &[("amount", &amount), ("recipient", &recipient)]),
+                    (false, true) => fill(t.mints, &[("amount", &amount)]),
+                    (false, false) => fill(t.receives, &[("amount", &amount), ("sender", &sender)]),
+                }
+            }
+            TokenType::ERC721 => {
+                let args: &[(&str, &str)] = &[
+                    ("id", id.as_str()),
+                    ("collection", &collection),
+                    ("recipient", &recipient),
+                    ("sender", &sender),
+                ];
+                match (outgoing, minted) {
+                    (true, _) => fill(t.sends_nft, args),
+                    (false, true) => fill(t.mints_nft, args),
+                    (false, false) => fill(t.receives_nft, args),
+                }
+            }
+            TokenType::ERC1155 => {
+                let count = transfer.value.to_string();
+                let args: &[(&str, &str)] = &[
+                    ("count", count.as_str()),
+                    ("id", &id),
+                    ("collection", &collection),
+                    ("recipient", &recipient),
+                    ("sender", &sender),
+                ];
+                match (outgoing, minted) {
+                    (true, _) => fill(t.sends_items, args),
+                    (false, true) => fill(t.mints_items, args),
+                    (false, false) => fill(t.receives_items, args),
+                }
+            }
+        };
+        Some(text)
+    }
+
+    /// Approvals granted or revoked by the sender
+    fn approvals(&self) -> Vec<Explanation> {
+        let t = self.templates;
+        let threshold = RiskConfig::default().unlimited_approval_threshold;
+        let mut explanations = Vec::new();
+        for (index, log) in self.report.trace.logs.iter().enumerate() {
+            let topics = log.topics();
+            if topics.len() < 3 || topic_address(&topics[1]) != self.sender {
+                continue;
+            }
+            let spender = self.name(topic_address(&topics[2]));
+            let data = &log.data.data;
+            let word = (data.len() >= 32).then(|| U256::from_be_slice(&data[..32]));
+            let (severity, text) = match (topics[0], topics.len(), word) {
+                // ERC20: the amount is the data
+                (signature, 3, Some(amount)) if signature == APPROVAL_EVENT_SIGNATURE => {
+                    let token = self.token_name(log.address);
+                    if amount >= threshold {
+                        let args: &[(&str, &str)] =
+                            &[("token", token.as_str()), ("spender", &spender)];
+                        (Severity::High, fill(t.unlimited_approval, args))
+                    } else if amount.is_zero() {
+                        let args: &[(&str, &str)] =
+                            &[("token", token.as_str()), ("spender", &spender)];
+                        (Severity::Info, fill(t.revokes_approval, args))
+                    } else {
+                        let amount = self.amount(log.address, amount);
+                        let args: &[(&str, &str)] =
+                            &[("spender", spender.as_str()), ("amount", &amount)];
+                        (Severity::Info, fill(t.approval, args))
+                    }
+                }
+                // ERC721: the token ID is the third topic
+                (signature, 4, _) if signature == APPROVAL_EVENT_SIGNATURE => {
+                    let id = U256::from_be_bytes(topics[3].0).to_string();
+                    let collection = self.token_name(log.address);
+                    let args: &[(&str, &str)] = &[
+                        ("spender", spender.as_str()),
+                        ("id", &id),
+                        ("collection", &collection),
+                    ];
+                    (Severity::Info, fill(t.nft_approval, args))
+                }
+                (signature, 3, Some(approved)) if signature == APPROVAL_FOR_ALL_EVENT_SIGNATURE => {
+                    let collection = self.token_name(log.address);
+                    let args: &[(&str, &str)] =
+                        &[("spender", spender.as_str()), ("collection", &collection)];
+                    if approved.is_zero() {
+                        (Severity::Info, fill(t.revokes_operator_approval, args))
+                    } else {
+                        (Severity::High, fill(t.operator_approval, args))
+                    }
+                }
+                _ => continue,
+            };
+            explanations.push(Explanation {
+                severity,
+                text,
+                refs: vec![FindingRef::LogIndex(index)],
+            });
+        }
+        explanations
+    }
+
+    /// Risk findings not already told by another sentence
+    fn risks(&self) -> Vec<Explanation> {
+        let t = self.templates;
+        risk_assessment(self.report, self.sender)
+            .into_iter()
+            .filter_map(|finding| {
+                let text = match finding.kind {
+                    // Told by the approval sentences
+                    RiskKind::UnlimitedApproval => return None,
+                    RiskKind::LargeOutflow => t.large_outflow,
+                    RiskKind::NftToUnknownRecipient => t.nft_to_unknown_recipient,
+                    RiskKind::DelegateCallFromEntryContract => t.entry_delegatecall,
+                    RiskKind::ValueToFreshContract => t.value_to_fresh_contract,
+                    RiskKind::TxOriginAuthorization => t.tx_origin_authorization,
+                };
+                Some(Explanation {
+                    severity: finding.severity,
+                    text: text.to_string(),
+                    refs: finding.refs,
+                })
+            })
+            .collect()
+    }
+
+    /// Address followed by its label, if known
+    fn name(&self, address: Address) -> String {
+        match self.label(address) {
+            Some(label) => format!("{address} ({label})"),
+            None => address.to_string(),
+        }
+    }
+
+    fn label(&self, address: Address) -> Option<&str> {
+        self.report
+            .labels
+            .get(&address)
+            .map(String::as_str)
+            .or_else(|| {
+                let hint = self.report.address_hints.get(&address)?;
+                hint.label.as_deref()
+            })
+            .or_else(|| {
+                let label = self.label_book?.get(self.chain_id, address)?;
+                Some(label.name.as_str())
+            })
+    }
+
+    /// Symbol of a token, falling back to its label and then its address
+    fn token_name(&self, token: Address) -> String {
+        match self.report.token_infos.get(&token) {
+            Some(info) => info.symbol.clone(),
+            None => self
+                .label(token)
+                .map_or_else(|| token.to_string(), str::to_string),
+        }
+    }
+
+    /// Amount of a token with its symbol, in raw units without metadata
+    fn amount(&self, token: Address, value: U256) -> String {
+        let t = self.templates;
+        let (symbol, decimals) = if token == NATIVE_TOKEN_ADDRESS {
+            let preset = ChainPreset::from_chain_id(self.chain_id);
+            preset.map_or(("ETH".to_string(), 18), |preset| {
+                (preset.native_symbol, preset.native_decimals)
+            })
+        } else if let Some(info) = self.report.token_infos.get(&token) {
+            (info.symbol.clone(), info.decimals)
+        } else {
+            let value = value.to_string();
+            let token = self.name(token);
+            return fill(t.raw_amount, &[("value", &value), ("token", &token)]);
+        };
+        let value = format_units(value, decimals);
+        fill(t.amount, &[("value", &value), ("symbol", &symbol)])
+    }
+}
+
+/// Replaces every `{key}` of `template` with its value
+fn fill(template: &str, args: &[(&str, &str)]) -> String {
+    args.iter()
+        .fold(template.to_string(), |text, (key, value)| {
+            text.replace(&format!("{{{key}}}"), value)
+        })
+}
+
+/// Formats `value` with `decimals` decimal places, dropping trailing zeros
+fn format_units(value: U256, decimals: u8) -> String {
+    let Some(unit) = U256::from(10).checked_pow(U256::from(decimals)) else {
+        return value.to_string();
+    };
+    let (whole, fraction) = value.div_rem(unit);
+    if fraction.is_zero() {
+        return whole.to_string();
+    }
+    let fraction = format!(
+        "{:0>width$}",
+        fraction.to_string(),
+        width = decimals as usize
+    );
+    format!("{whole}.{}", fraction.trim_end_matches('0'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_units() {
+        let eth = U256::from(10).pow(U256::from(18));
+        assert_eq!(
+            format_units(eth * U256::from(12) / U256::from(10), 18),
+            "1.2"
+        );
+        assert_eq!(format_units(U256::from(1_500_000), 6), "1.5");
+        assert_eq!(format_units(U256::from(42), 0), "42");
+        assert_eq!(format_units(U256::from(5), 6), "0.000005");
+        assert_eq!(format_units(U256::ZERO, 18), "0");
+    }
+
+    #[test]
+    fn test_fill() {
+        assert_eq!(
+            fill(ENGLISH.sends, &[("amount", "1 ETH"), ("recipient", "0x1")]),
+            "Sends 1 ETH to 0x1"
+        );
+    }
+}
@@ -0,0 +1,13 @@
+//! Trace export for observability pipelines
+//!
+//! - [`parity`] converts call trees into the flat trace format used by
+//!   `trace_transaction` on parity-compatible nodes.
+//! - `otel` (feature = "otel") converts them into SDK-agnostic, OpenTelemetry-style
+//!   spans; its `to_otel_spans` and `visit_spans` are re-exported here.
+
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod parity;
+
+#[cfg(feature = "otel")]
+pub use otel::{to_otel_spans, visit_spans, SpanData, SpanEvent, SpanStatus, TxSpanContext};
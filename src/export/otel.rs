@@ -0,0 +1,365 @@
+//! OpenTelemetry-style spans (feature = "otel")
+//!
+//! Call trees map naturally onto distributed-tracing spans: every `CallTrace` frame
+//! becomes a span, subtraces become child spans and emitted logs become span events.
+//!
+//! This module deliberately does not depend on a specific OpenTelemetry SDK version.
+//! It produces plain [`SpanData`] values that follow OpenTelemetry naming conventions,
+//! which can be handed to any exporter:
+//!
+//! - [`to_otel_spans`] collects all spans of a transaction into a `Vec`
+//! - [`visit_spans`] hands the spans to a callback for adapting to any tracing backend
+//!
+//! # Timing
+//!
+//! The EVM has no wall clock, so span timestamps are synthesized from execution order:
+//! entering and leaving a frame each advance a logical tick, and every tick is mapped
+//! to `tick_nanos` nanoseconds after `start_time_unix_nano`. Parents therefore always
+//! enclose their children and siblings never overlap.
+
+use crate::inspectors::tx_inspector::TxTraceOutput;
+use crate::types::{CallStatus, CallTrace, CreateScheme};
+use alloy::primitives::{hex, Log, B256};
+use serde::Serialize;
+
+/// Transaction-level context shared by all spans of one trace
+#[derive(Debug, Clone, Default)]
+pub struct TxSpanContext {
+    /// Trace ID assigned to all spans (OpenTelemetry uses 16 bytes)
+    pub trace_id: [u8; 16],
+    /// Optional span the transaction root should be attached to
+    pub parent_span_id: Option<u64>,
+    /// Hash of the simulated transaction, if known
+    pub tx_hash: Option<B256>,
+    /// Position of the transaction within its batch
+    pub tx_index: Option<usize>,
+    /// Wall-clock anchor for synthesized timestamps
+    pub start_time_unix_nano: u64,
+    /// Duration of one logical execution tick (0 is treated as 1)
+    pub tick_nanos: u64,
+}
+
+/// Status of an exported span
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum SpanStatus {
+    Ok,
+    Error(String),
+}
+
+/// Span event derived from an emitted log
+#[derive(Debug, Clone, Serialize)]
+pub struct SpanEvent {
+    /// Event name (`log`)
+    pub name: String,
+    /// Synthesized event timestamp
+    pub time_unix_nano: u64,
+    /// Event attributes (emitter address, topics, data)
+    pub attributes: Vec<(String, String)>,
+}
+
+/// SDK-agnostic representation of a single span
+#[derive(Debug, Clone, Serialize)]
+pub struct SpanData {
+    /// Trace ID shared by all spans of the transaction
+    pub trace_id: [u8; 16],
+    /// Span ID, unique within the trace (pre-order frame position, starting at 1)
+    pub span_id: u64,
+    /// Parent span ID (`None` for the root frame unless the context provides one)
+    pub parent_span_id: Option<u64>,
+    /// Span name: function selector, creation scheme or `fallback`
+    pub name: String,
+    /// Synthesized start time
+    pub start_time_unix_nano: u64,
+    /// Synthesized end time
+    pub end_time_unix_nano: u64,
+    /// Frame attributes (from/to/value/gas/status/revert reason)
+    pub attributes: Vec<(String, String)>,
+    /// Logs emitted directly by this frame
+    pub events: Vec<SpanEvent>,
+    /// Span status
+    pub status: SpanStatus,
+}
+
+/// Converts a transaction trace into spans, one per call frame
+///
+/// Spans are returned in pre-order (parents before children), so the root span is
+/// always first. Returns an empty vector if the output has no call trace.
+pub fn to_otel_spans(output: &TxTraceOutput, tx_context: &TxSpanContext) -> Vec<SpanData> {
+    let mut spans = Vec::new();
+    visit_spans(output, tx_context, |span| spans.push(span));
+    spans
+}
+
+/// Passes the spans of a transaction trace to a callback
+///
+/// Spans are delivered in pre-order, so the callback sees a parent before any of its
+/// children. As a span's end time is only known once its children are done, all spans
+/// are built first and handed over afterwards; the callback saves the caller from
+/// collecting them into a `Vec` of its own, not from holding them in memory.
+pub fn visit_spans<F>(output: &TxTraceOutput, tx_context: &TxSpanContext, mut callback: F)
+where
+    F: FnMut(SpanData),
+{
+    let Some(root) = &output.call_trace else {
+        return;
+    };
+
+    let mut spans = Vec::new();
+    let mut builder = SpanBuilder {
+        ctx: tx_context,
+        logs: &output.logs,
+        tick: 0,
+        next_id: 1,
+    };
+    builder.build(root, tx_context.parent_span_id, &mut spans);
+
+    for span in spans {
+        callback(span);
+    }
+}
+
+/// Internal state used while walking the call tree
+struct SpanBuilder<'a> {
+    ctx: &'a TxSpanContext,
+    logs: &'a [Log],
+    tick: u64,
+    next_id: u64,
+}
+
+/// Pending work while walking the call tree
+enum Visit<'a> {
+    /// Open the span of a frame and schedule its children
+    Enter(&'a CallTrace, Option<u64>),
+    /// Close the span at `position` once all children are done
+    Exit {
+        position: usize,
+        events: Vec<SpanEvent>,
+    },
+}
+
+impl SpanBuilder<'_> {
+    fn time(&self) -> u64 {
+        let tick_nanos = self.ctx.tick_nanos.max(1);
+        self.ctx.start_time_unix_nano + self.tick * tick_nanos
+    }
+
+    fn build(&mut self, root: &CallTrace, parent: Option<u64>, out: &mut Vec<SpanData>) {
+        // Explicit stack instead of recursion, so deep call trees cannot overflow the stack
+        let mut stack = vec![Visit::Enter(root, parent)];
+        while let Some(visit) = stack.pop() {
+            match visit {
+                Visit::Enter(trace, parent) => {
+                    let span_id = self.next_id;
+                    self.next_id += 1;
+                    let start = self.time();
+                    self.tick += 1;
+
+                    // Reserve the slot so the parent precedes its children in the output
+                    let position = out.len();
+                    out.push(SpanData {
+                        trace_id: self.ctx.trace_id,
+                        span_id,
+                        parent_span_id: parent,
+                        name: span_name(trace),
+                        start_time_unix_nano: start,
+                        end_time_unix_nano: start,
+                        attributes: self.attributes(trace),
+                        events: Vec::new(),
+                        status: match &trace.status {
+                            CallStatus::Success | CallStatus::InProgress => SpanStatus::Ok,
+                            status => SpanStatus::Error(status_message(status)),
+                        },
+                    });
+
+                    let mut events = Vec::with_capacity(trace.log_indices.len());
+                    for &index in &trace.log_indices {
+                        if let Some(log) = self.logs.get(index) {
+                            events.push(log_event(log, index, self.time()));
+                        }
+                    }
+
+                    stack.push(Visit::Exit { position, events });
+                    stack.extend(
+                        trace
+                            .subtraces
+                            .iter()
+                            .rev()
+                            .map(|subtrace| Visit::Enter(subtrace, Some(span_id))),
+                    );
+                }
+                Visit::Exit { position, events } => {
+                    self.tick += 1;
+                    let span = &mut out[position];
+                    span.end_time_unix_nano = self.time();
+                    span.events = events;
+                }
+            }
+        }
+    }
+
+    fn attributes(&self, trace: &CallTrace) -> Vec<(String, String)> {
+        let mut attributes = vec![
+            ("evm.from".to_string(), trace.from.to_string()),
+            ("evm.to".to_string(), trace.to.to_string()),
+            ("evm.value".to_string(), trace.value.to_string()),
+            ("evm.gas_used".to_string(), trace.gas_used.to_string()),
+            (
+                "evm.status".to_string(),
+                status_label(&trace.status).to_string(),
+            ),
+            (
+                "evm.trace_address".to_string(),
+                format!("{:?}", trace.trace_address),
+            ),
+        ];
+        if let Some(scheme) = &trace.call_scheme {
+            attributes.push(("evm.call_scheme".to_string(), format!("{scheme:?}")));
+        }
+        if let Some(scheme) = &trace.create_scheme {
+            attributes.push(("evm.create_scheme".to_string(), format!("{scheme:?}")));
+        }
+        match &trace.status {
+            status @ CallStatus::Revert { .. } => attributes.push((
+                "evm.revert_reason".to_string(),
+                status.message().unwrap_or_default(),
+            )),
+            CallStatus::Halt { raw, .. } => {
+                attributes.push(("evm.halt_reason".to_string(), raw.clone()))
+            }
+            _ => {}
+        }
+        if let Some(tx_hash) = self.ctx.tx_hash {
+            attributes.push(("evm.tx_hash".to_string(), tx_hash.to_string()));
+        }
+        if let Some(tx_index) = self.ctx.tx_index {
+            attributes.push(("evm.tx_index".to_string(), tx_index.to_string()));
+        }
+        attributes
+    }
+}
+
+/// Derives a span name from the frame: creation scheme, 4-byte selector or `fallback`
+fn span_name(trace: &CallTrace) -> String {
+    match trace.create_scheme {
+        Some(CreateScheme::Create2 { .. }) => "CREATE2".to_string(),
+        Some(_) => "CREATE".to_string(),
+        None if trace.input.len() >= 4 => format!("0x{}", hex::encode(&trace.input[..4])),
+        None => "fallback".to_string(),
+    }
+}
+
+fn status_label(status: &CallStatus) -> &'static str {
+    match status {
+        CallStatus::Success => "success",
+        CallStatus::Revert { .. } => "revert",
+        CallStatus::Halt { .. } => "halt",
+        CallStatus::FatalError => "fatal_error",
+        CallStatus::InProgress => "in_progress",
+    }
+}
+
+fn status_message(status: &CallStatus) -> String {
+    status
+        .message()
+        .unwrap_or_else(|| status_label(status).to_string())
+}
+
+fn log_event(log: &Log, index: usize, time_unix_nano: u64) -> SpanEvent {
+    let mut attributes = vec![
+        ("evm.log_index".to_string(), index.to_string()),
+        ("evm.log_address".to_string(), log.address.to_string()),
+    ];
+    for (i, topic) in log.topics().iter().enumerate() {
+        attributes.push((format!("evm.topic{i}"), topic.to_string()));
+    }
+    attributes.push((
+        "evm.data".to_string(),
+        format!("0x{}", hex::encode(&log.data.data)),
+    ));
+    SpanEvent {
+        name: "log".to_string(),
+        time_unix_nano,
+        attributes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::DecodedRevert;
+    use alloy::primitives::{address, Bytes, LogData};
+
+    fn frame(trace_address: Vec<usize>, subtraces: Vec<CallTrace>) -> CallTrace {
+        CallTrace {
+            from: address!("1000000000000000000000000000000000000001"),
+            to: address!("2000000000000000000000000000000000000002"),
+            input: Bytes::from(vec![0xa9, 0x05, 0x9c, 0xbb, 0x00]),
+            subtraces,
+            trace_address,
+            ..Default::default()
+        }
+    }
+
+    fn nested_output() -> TxTraceOutput {
+        // root -> [a -> [a0], b]
+        let a0 = frame(vec![0, 0], vec![]);
+        let a = frame(vec![0], vec![a0]);
+        let mut b = frame(vec![1], vec![]);
+        b.status = CallStatus::Revert {
+            reason: Some(DecodedRevert::Error("nope".to_string())),
+            raw: Bytes::new(),
+        };
+        b.log_indices = vec![0];
+        let root = frame(vec![], vec![a, b]);
+        let log = Log {
+            address: address!("3000000000000000000000000000000000000003"),
+            data: LogData::new_unchecked(vec![B256::ZERO], Bytes::new()),
+        };
+        TxTraceOutput {
+            asset_transfers: vec![],
+            call_trace: Some(root),
+            logs: vec![log],
+            error_trace_address: None,
+            contract_changes: vec![],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_span_tree_matches_call_tree() {
+        let ctx = TxSpanContext {
+            start_time_unix_nano: 1_000,
+            tick_nanos: 10,
+            ..Default::default()
+        };
+        let spans = to_otel_spans(&nested_output(), &ctx);
+        assert_eq!(spans.len(), 4);
+
+        let parents: Vec<_> = spans.iter().map(|s| s.parent_span_id).collect();
+        assert_eq!(parents, vec![None, Some(1), Some(2), Some(1)]);
+        assert_eq!(spans[0].name, "0xa9059cbb");
+
+        // Children are enclosed by their parents
+        for span in &spans[1..] {
+            let parent = &spans[span.parent_span_id.unwrap() as usize - 1];
+            assert!(parent.start_time_unix_nano < span.start_time_unix_nano);
+            assert!(parent.end_time_unix_nano > span.end_time_unix_nano);
+        }
+
+        // Log and revert reason land on the emitting frame
+        assert_eq!(spans[3].events.len(), 1);
+        assert_eq!(spans[3].status, SpanStatus::Error("nope".to_string()));
+        assert!(spans[3]
+            .attributes
+            .contains(&("evm.revert_reason".to_string(), "nope".to_string())));
+    }
+
+    #[test]
+    fn test_visit_spans_without_call_trace() {
+        let mut output = nested_output();
+        output.call_trace = None;
+        let mut count = 0;
+        visit_spans(&output, &TxSpanContext::default(), |_| count += 1);
+        assert_eq!(count, 0);
+    }
+}
@@ -0,0 +1,438 @@
+//! Parity/OpenEthereum flat trace format
+//!
+//! Converts the hierarchical call tree of a [`TxTraceOutput`] into the flat trace list
+//! returned by `trace_transaction` / `trace_filter` on parity-compatible nodes.
+//! Traces are emitted in depth-first order; self-destructs become `suicide` entries
+//! placed as the last child of the frame that executed SELFDESTRUCT.
+
+use crate::inspectors::tx_inspector::TxTraceOutput;
+use crate::types::{CallScheme, CallStatus, CallTrace, ContractChange, HaltReasonKind};
+use alloy::primitives::{Address, Bytes, B256, U256};
+use serde::{Deserialize, Serialize};
+
+/// Block and transaction metadata attached to every flat trace
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TxMeta {
+    /// Hash of the containing block
+    pub block_hash: Option<B256>,
+    /// Number of the containing block
+    pub block_number: Option<u64>,
+    /// Transaction hash
+    pub transaction_hash: Option<B256>,
+    /// Position of the transaction within the block
+    pub transaction_position: Option<u64>,
+}
+
+/// Flat trace entry in parity format
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParityTrace {
+    /// Frame action
+    pub action: ParityAction,
+    /// Block hash
+    pub block_hash: Option<B256>,
+    /// Block number
+    pub block_number: Option<u64>,
+    /// Error message for failed frames
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Frame result (`None` for failed frames and self-destructs)
+    pub result: Option<ParityResult>,
+    /// Number of direct children
+    pub subtraces: usize,
+    /// Position in the call tree
+    pub trace_address: Vec<usize>,
+    /// Transaction hash
+    pub transaction_hash: Option<B256>,
+    /// Transaction position in block
+    pub transaction_position: Option<u64>,
+    /// Trace type
+    #[serde(rename = "type")]
+    pub trace_type: ParityTraceType,
+}
+
+/// Parity trace type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParityTraceType {
+    Call,
+    Create,
+    Suicide,
+}
+
+/// Action of a flat trace entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ParityAction {
+    Call(ParityCallAction),
+    Create(ParityCreateAction),
+    Suicide(ParitySuicideAction),
+}
+
+/// Message call action
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParityCallAction {
+    /// Effective caller
+    pub from: Address,
+    /// `call`, `callcode`, `delegatecall` or `staticcall`
+    pub call_type: String,
+    /// Gas provided to the call
+    pub gas: U256,
+    /// Call data
+    pub input: Bytes,
+    /// Callee (code address for delegate calls)
+    pub to: Address,
+    /// Value transferred
+    pub value: U256,
+}
+
+/// Contract creation action
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParityCreateAction {
+    /// Creator
+    pub from: Address,
+    /// Gas provided to the creation
+    pub gas: U256,
+    /// Init code
+    pub init: Bytes,
+    /// Endowment
+    pub value: U256,
+}
+
+/// Self-destruct action
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParitySuicideAction {
+    /// Destroyed contract
+    pub address: Address,
+    /// Beneficiary of the remaining balance
+    pub refund_address: Address,
+    /// Balance moved to the beneficiary
+    pub balance: U256,
+}
+
+/// Result of a successful frame
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ParityResult {
+    /// Creation result (listed first so untagged deserialization prefers it)
+    Create {
+        /// Created contract address
+        address: Address,
+        /// Deployed runtime code
+        code: Bytes,
+        /// Gas used
+        #[serde(rename = "gasUsed")]
+        gas_used: U256,
+    },
+    /// Call result
+    Call {
+        /// Gas used
+        #[serde(rename = "gasUsed")]
+        gas_used: U256,
+        /// Return data
+        output: Bytes,
+    },
+}
+
+impl TxTraceOutput {
+    /// Flattens the call tree into parity-style traces
+    ///
+    /// Returns an empty vector if the output has no call trace.
+    pub fn to_parity_traces(&self, tx_meta: &TxMeta) -> Vec<ParityTrace> {
+        let mut traces = Vec::new();
+        if let Some(root) = &self.call_trace {
+            flatten(root, &self.contract_changes, tx_meta, &mut traces);
+        }
+        traces
+    }
+}
+
+/// Pending work while flattening the call tree
+enum Step<'a> {
+    /// Emit a frame, then its subtraces and self-destructs
+    Frame(&'a CallTrace),
+    /// Emit the self-destructs of a frame, after all of its subtraces
+    Suicides(&'a CallTrace, Vec<ParitySuicideAction>),
+}
+
+fn flatten(
+    root: &CallTrace,
+    changes: &[ContractChange],
+    meta: &TxMeta,
+    out: &mut Vec<ParityTrace>,
+) {
+    // Explicit stack instead of recursion, so deep call trees cannot overflow the stack
+    let mut stack = vec![Step::Frame(root)];
+    while let Some(step) = stack.pop() {
+        match step {
+            Step::Frame(frame) => {
+                let suicides: Vec<_> = changes
+                    .iter()
+                    .filter_map(|change| match change {
+                        ContractChange::SelfDestructed {
+                            contract,
+                            beneficiary,
+                            balance_moved,
+                            trace_address,
+                            ..
+                        } if *trace_address == frame.trace_address => Some(ParitySuicideAction {
+                            address: *contract,
+                            refund_address: *beneficiary,
+                            balance: *balance_moved,
+                        }),
+                        _ => None,
+                    })
+                    .collect();
+
+                let error = parity_error(&frame.status);
+                let (action, result, trace_type) = match frame.create_scheme {
+                    Some(_) => (
+                        ParityAction::Create(ParityCreateAction {
+                            from: frame.from,
+                            gas: frame.gas_limit,
+                            init: frame.input.clone(),
+                            value: frame.value,
+                        }),
+                        ParityResult::Create {
+                            address: frame.to,
+                            code: frame.output.clone(),
+                            gas_used: frame.gas_used,
+                        },
+                        ParityTraceType::Create,
+                    ),
+                    None => (
+                        ParityAction::Call(ParityCallAction {
+                            from: frame.from,
+                            call_type: call_type(frame.call_scheme).to_string(),
+                            gas: frame.gas_limit,
+                            input: frame.input.clone(),
+                            to: frame.to,
+                            value: frame.value,
+                        }),
+                        ParityResult::Call {
+                            gas_used: frame.gas_used,
+                            output: frame.output.clone(),
+                        },
+                        ParityTraceType::Call,
+                    ),
+                };
+
+                out.push(ParityTrace {
+                    action,
+                    block_hash: meta.block_hash,
+                    block_number: meta.block_number,
+                    result: error.is_none().then_some(result),
+                    error,
+                    subtraces: frame.subtraces.len() + suicides.len(),
+                    trace_address: frame.trace_address.clone(),
+                    transaction_hash: meta.transaction_hash,
+                    transaction_position: meta.transaction_position,
+                    trace_type,
+                });
+
+                stack.push(Step::Suicides(frame, suicides));
+                stack.extend(frame.subtraces.iter().rev().map(Step::Frame));
+            }
+            Step::Suicides(frame, suicides) => {
+                for (i, suicide) in suicides.into_iter().enumerate() {
+                    let mut trace_address = frame.trace_address.clone();
+                    trace_address.push(frame.subtraces.len() + i);
+                    out.push(ParityTrace {
+                        action: ParityAction::Suicide(suicide),
+                        block_hash: meta.block_hash,
+                        block_number: meta.block_number,
+                        error: None,
+                        result: None,
+                        subtraces: 0,
+                        trace_address,
+                        transaction_hash: meta.transaction_hash,
+                        transaction_position: meta.transaction_position,
+                        trace_type: ParityTraceType::Suicide,
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn call_type(scheme: Option<CallScheme>) -> &'static str {
+    match scheme {
+        Some(CallScheme::CallCode) => "callcode",
+        Some(CallScheme::DelegateCall) => "delegatecall",
+        Some(CallScheme::StaticCall) => "staticcall",
+        _ => "call",
+    }
+}
+
+/// Maps a frame status to the error strings used by parity-compatible nodes
+fn parity_error(status: &CallStatus) -> Option<String> {
+    let message = match status {
+        CallStatus::Success | CallStatus::InProgress => return None,
+        CallStatus::Revert { .. } => "Reverted",
+        CallStatus::FatalError => "Fatal error",
+        CallStatus::Halt { reason, raw } => match reason {
+            HaltReasonKind::OutOfGas => "Out of gas",
+            HaltReasonKind::InvalidOpcode => "Bad instruction",
+            HaltReasonKind::InvalidJump => "Bad jump destination",
+            HaltReasonKind::StackUnderflow => "Stack underflow",
+            HaltReasonKind::CallTooDeep => "Call stack limit reached",
+            _ => return Some(raw.clone()),
+        },
+    };
+    Some(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::CreateScheme;
+    use alloy::primitives::{address, b256};
+
+    /// Hand-built contract-to-contract call with a reverted inner call, shaped after
+    /// the `trace_transaction` response of a parity-compatible node
+    ///
+    /// Pins the serialized layout; `tests/parity_export_tests.rs` compares the export
+    /// with a node's recorded response for a mined transaction.
+    const FIXTURE: &str = r#"[
+      {
+        "action": {
+          "from": "0x1000000000000000000000000000000000000001",
+          "callType": "call",
+          "gas": "0x5208",
+          "input": "0xa9059cbb",
+          "to": "0x2000000000000000000000000000000000000002",
+          "value": "0x0"
+        },
+        "blockHash": "0x00000000000000000000000000000000000000000000000000000000000000aa",
+        "blockNumber": 100,
+        "result": { "gasUsed": "0x100", "output": "0x" },
+        "subtraces": 2,
+        "traceAddress": [],
+        "transactionHash": "0x00000000000000000000000000000000000000000000000000000000000000bb",
+        "transactionPosition": 3,
+        "type": "call"
+      },
+      {
+        "action": {
+          "from": "0x2000000000000000000000000000000000000002",
+          "callType": "staticcall",
+          "gas": "0x1000",
+          "input": "0x",
+          "to": "0x3000000000000000000000000000000000000003",
+          "value": "0x0"
+        },
+        "blockHash": "0x00000000000000000000000000000000000000000000000000000000000000aa",
+        "blockNumber": 100,
+        "error": "Reverted",
+        "result": null,
+        "subtraces": 0,
+        "traceAddress": [0],
+        "transactionHash": "0x00000000000000000000000000000000000000000000000000000000000000bb",
+        "transactionPosition": 3,
+        "type": "call"
+      },
+      {
+        "action": {
+          "address": "0x2000000000000000000000000000000000000002",
+          "refundAddress": "0x1000000000000000000000000000000000000001",
+          "balance": "0x0"
+        },
+        "blockHash": "0x00000000000000000000000000000000000000000000000000000000000000aa",
+        "blockNumber": 100,
+        "result": null,
+        "subtraces": 0,
+        "traceAddress": [1],
+        "transactionHash": "0x00000000000000000000000000000000000000000000000000000000000000bb",
+        "transactionPosition": 3,
+        "type": "suicide"
+      }
+    ]"#;
+
+    fn output() -> TxTraceOutput {
+        let user = address!("1000000000000000000000000000000000000001");
+        let contract = address!("2000000000000000000000000000000000000002");
+        let inner = CallTrace {
+            from: contract,
+            to: address!("3000000000000000000000000000000000000003"),
+            call_scheme: Some(CallScheme::StaticCall),
+            gas_limit: U256::from(0x1000),
+            status: CallStatus::Revert {
+                reason: None,
+                raw: Bytes::new(),
+            },
+            trace_address: vec![0],
+            ..Default::default()
+        };
+        let root = CallTrace {
+            from: user,
+            to: contract,
+            input: Bytes::from(vec![0xa9, 0x05, 0x9c, 0xbb]),
+            call_scheme: Some(CallScheme::Call),
+            gas_limit: U256::from(21_000),
+            gas_used: U256::from(0x100),
+            subtraces: vec![inner],
+            ..Default::default()
+        };
+        TxTraceOutput {
+            call_trace: Some(root),
+            contract_changes: vec![ContractChange::SelfDestructed {
+                contract,
+                beneficiary: user,
+                balance_moved: U256::ZERO,
+                same_tx_creation: false,
+                trace_address: vec![],
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parity_traces_match_fixture() {
+        let meta = TxMeta {
+            block_hash: Some(b256!(
+                "00000000000000000000000000000000000000000000000000000000000000aa"
+            )),
+            block_number: Some(100),
+            transaction_hash: Some(b256!(
+                "00000000000000000000000000000000000000000000000000000000000000bb"
+            )),
+            transaction_position: Some(3),
+        };
+        let traces = output().to_parity_traces(&meta);
+
+        let expected: serde_json::Value = serde_json::from_str(FIXTURE).unwrap();
+        assert_eq!(serde_json::to_value(&traces).unwrap(), expected);
+
+        // Round-trip through the fixture
+        let parsed: Vec<ParityTrace> = serde_json::from_str(FIXTURE).unwrap();
+        assert_eq!(parsed, traces);
+    }
+
+    #[test]
+    fn test_create_frame() {
+        let created = address!("4000000000000000000000000000000000000004");
+        let output = TxTraceOutput {
+            call_trace: Some(CallTrace {
+                to: created,
+                create_scheme: Some(CreateScheme::Create),
+                output: Bytes::from(vec![0x60, 0x00]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let traces = output.to_parity_traces(&TxMeta::default());
+        assert_eq!(traces.len(), 1);
+        assert_eq!(traces[0].trace_type, ParityTraceType::Create);
+        assert_eq!(
+            traces[0].result,
+            Some(ParityResult::Create {
+                address: created,
+                code: Bytes::from(vec![0x60, 0x00]),
+                gas_used: U256::ZERO,
+            })
+        );
+    }
+}
@@ -0,0 +1,369 @@
+//! Lightweight input sweeps over a single contract function
+//!
+//! [`run_sweep`] executes one call template many times with generated arguments and
+//! callers, restoring the simulated state after every iteration so each run starts
+//! from the same point. Inputs come from a seeded generator, so a sweep with the same
+//! seed against the same state is fully reproducible.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! use alloy::primitives::{address, fixed_bytes, U256};
+//! use revm_trace::{
+//!     create_evm_with_tracer,
+//!     fuzz::{run_sweep, ArgGen, TxTemplate},
+//!     TxInspector,
+//! };
+//!
+//! let mut evm = create_evm_with_tracer("https://eth.llamarpc.com", TxInspector::new()).await?;
+//! let template = TxTemplate {
+//!     callers: vec![address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045")],
+//!     to: address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+//!     selector: fixed_bytes!("2e1a7d4d"), // withdraw(uint256)
+//!     arg_generators: vec![ArgGen::Uint { min: U256::ZERO, max: U256::from(10u64.pow(18)) }],
+//!     value: U256::ZERO,
+//!     watched_slots: vec![],
+//! };
+//! let report = run_sweep(&mut evm, template, 100, 42);
+//! println!("{} of {} succeeded", report.success_count, report.iterations);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    evm::TraceEvm,
+    inspectors::tx_inspector::TxInspector,
+    report::ExecutionSummary,
+    traits::Reset,
+    types::{SimulationTx, TraceLevel},
+};
+use alloy::{
+    dyn_abi::DynSolValue,
+    primitives::{Address, Bytes, FixedBytes, TxKind, U256},
+};
+use revm::database::{CacheDB, DatabaseRef};
+use serde::Serialize;
+
+/// Generator for one function argument
+#[derive(Debug, Clone)]
+pub enum ArgGen {
+    /// `uint256` uniformly drawn from `min..=max`
+    Uint { min: U256, max: U256 },
+    /// `address` drawn from a pool
+    AddressPool(Vec<Address>),
+    /// `bytes` of random content with a length in `min_len..=max_len`
+    Bytes { min_len: usize, max_len: usize },
+}
+
+/// Call template swept by [`run_sweep`]
+#[derive(Debug, Clone)]
+pub struct TxTemplate {
+    /// Callers, one drawn per iteration (must not be empty)
+    pub callers: Vec<Address>,
+    /// Called contract
+    pub to: Address,
+    /// Function selector
+    pub selector: FixedBytes<4>,
+    /// Argument generators, in parameter order
+    pub arg_generators: Vec<ArgGen>,
+    /// Native value sent with every call
+    pub value: U256,
+    /// Storage slots whose changes are reported
+    pub watched_slots: Vec<(Address, U256)>,
+}
+
+/// Concrete input of one iteration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SweepInput {
+    /// Iteration index
+    pub iteration: usize,
+    /// Caller of the iteration
+    pub caller: Address,
+    /// Encoded calldata, selector included
+    pub calldata: Bytes,
+}
+
+/// Iterations that failed with the same reason
+#[derive(Debug, Clone, Serialize)]
+pub struct RevertGroup {
+    /// Decoded revert reason, or the halt reason
+    pub reason: String,
+    /// Number of iterations failing with this reason
+    pub count: usize,
+    /// First input that produced this reason
+    pub example: SweepInput,
+}
+
+/// Change of a watched storage slot by one iteration
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchedSlotChange {
+    /// Input that changed the slot
+    pub input: SweepInput,
+    /// Contract owning the slot
+    pub address: Address,
+    /// Storage slot
+    pub slot: U256,
+    /// Value before the iteration
+    pub old_value: U256,
+    /// Value after the iteration
+    pub new_value: U256,
+}
+
+/// Gas used across iterations, as nearest-rank percentiles
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct GasDistribution {
+    /// Lowest gas used
+    pub min: u64,
+    /// Median
+    pub p50: u64,
+    /// 90th percentile
+    pub p90: u64,
+    /// 99th percentile
+    pub p99: u64,
+    /// Highest gas used
+    pub max: u64,
+}
+
+/// Aggregated results of a sweep
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SweepReport {
+    /// Number of executed iterations
+    pub iterations: usize,
+    /// Number of successful iterations
+    pub success_count: usize,
+    /// First successful input
+    pub success_example: Option<SweepInput>,
+    /// Distinct failure reasons, most frequent first
+    pub reverts: Vec<RevertGroup>,
+    /// Iterations that could not be executed at all (e.g. RPC failures)
+    pub execution_errors: usize,
+    /// Gas used by executed iterations
+    pub gas: GasDistribution,
+    /// Changes of watched storage slots, in iteration order
+    pub watched_slot_changes: Vec<WatchedSlotChange>,
+}
+
+/// Executes `template` for `iterations` generated inputs
+///
+/// Each iteration draws a caller and arguments, ABI-encodes the calldata, snapshots
+/// the simulated state, executes the call with full tracing, records the outcome and
+/// reverts to the snapshot. The state is therefore identical before every iteration
+/// and after the sweep.
+///
+/// # Arguments
+/// - `evm`: EVM instance backed by a `CacheDB`
+/// - `template`: Call to sweep
+/// - `iterations`: Number of executions
+/// - `seed`: Generator seed; equal seeds produce equal inputs
+///
+/// # Panics
+/// If `template.callers` or an `ArgGen::AddressPool` is empty.
+pub fn run_sweep<DB>(
+    evm: &mut TraceEvm<CacheDB<DB>, TxInspector>,
+    template: TxTemplate,
+    iterations: usize,
+    seed: u64,
+) -> SweepReport
+where
+    DB: DatabaseRef,
+{
+    let mut rng = SplitMix64(seed);
+    let mut report = SweepReport {
+        iterations,
+        ..Default::default()
+    };
+    let mut gas_used = Vec::with_capacity(iterations);
+
+    for iteration in 0..iterations {
+        let caller = *rng.pick(&template.callers);
+        let args: Vec<DynSolValue> = template
+            .arg_generators
+            .iter()
+            .map(|generator| rng.arg(generator))
+            .collect();
+        let mut calldata = template.selector.to_vec();
+        calldata.extend(DynSolValue::Tuple(args).abi_encode_params());
+        let input = SweepInput {
+            iteration,
+            caller,
+            calldata: calldata.into(),
+        };
+
+        let snapshot = evm.snapshot();
+        let tx = SimulationTx {
+            caller,
+            value: template.value,
+            data: input.calldata.clone(),
+            transact_to: TxKind::Call(template.to),
+            trace_config: Some(TraceLevel::Full),
+            return_abi: None,
+        };
+        let outcome = evm.trace_internal(tx, true);
+        evm.revert_to(snapshot);
+        evm.inspector.reset_slot_cache();
+
+        let (result, diff, _) = match outcome {
+            Ok(outcome) => outcome,
+            Err(_) => {
+                report.execution_errors += 1;
+                continue;
+            }
+        };
+        let summary = ExecutionSummary::from_result(&result);
+        gas_used.push(summary.gas_used);
+        match summary.error {
+            None => {
+                report.success_count += 1;
+                report.success_example.get_or_insert_with(|| input.clone());
+            }
+            Some(reason) => match report.reverts.iter_mut().find(|g| g.reason == reason) {
+                Some(group) => group.count += 1,
+                None => report.reverts.push(RevertGroup {
+                    reason,
+                    count: 1,
+                    example: input.clone(),
+                }),
+            },
+        }
+        for (address, slot) in &template.watched_slots {
+            let changes = diff.get(address).into_iter().flatten();
+            for access in changes.filter(|access| access.slot == *slot) {
+                report.watched_slot_changes.push(WatchedSlotChange {
+                    input: input.clone(),
+                    address: *address,
+                    slot: *slot,
+                    old_value: access.old_value,
+                    new_value: access.new_value,
+                });
+            }
+        }
+    }
+
+    report.reverts.sort_by(|a, b| b.count.cmp(&a.count));
+    report.gas = gas_distribution(gas_used);
+    report
+}
+
+/// Computes nearest-rank percentiles
+fn gas_distribution(mut samples: Vec<u64>) -> GasDistribution {
+    if samples.is_empty() {
+        return GasDistribution::default();
+    }
+    samples.sort_unstable();
+    let rank = |percentile: usize| {
+        let index = (percentile * samples.len()).div_ceil(100).max(1) - 1;
+        samples[index]
+    };
+    GasDistribution {
+        min: samples[0],
+        p50: rank(50),
+        p90: rank(90),
+        p99: rank(99),
+        max: samples[samples.len() - 1],
+    }
+}
+
+/// Deterministic SplitMix64 generator
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    fn pick<'a, T>(&mut self, pool: &'a [T]) -> &'a T {
+        assert!(!pool.is_empty(), "cannot draw from an empty pool");
+        &pool[self.below(pool.len())]
+    }
+
+    fn u256(&mut self) -> U256 {
+        U256::from_limbs([
+            self.next_u64(),
+            self.next_u64(),
+            self.next_u64(),
+            self.next_u64(),
+        ])
+    }
+
+    fn arg(&mut self, generator: &ArgGen) -> DynSolValue {
+        match generator {
+            ArgGen::Uint { min, max } => {
+                let (low, high) = if min <= max {
+                    (*min, *max)
+                } else {
+                    (*max, *min)
+                };
+                let value = match (high - low).checked_add(U256::from(1)) {
+                    Some(span) => low + self.u256() % span,
+                    // Full range
+                    None => self.u256(),
+                };
+                DynSolValue::Uint(value, 256)
+            }
+            ArgGen::AddressPool(pool) => DynSolValue::Address(*self.pick(pool)),
+            ArgGen::Bytes { min_len, max_len } => {
+                let (low, high) = ((*min_len).min(*max_len), (*min_len).max(*max_len));
+                let len = low + self.below(high - low + 1);
+                DynSolValue::Bytes((0..len).map(|_| self.next_u64() as u8).collect())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generators_are_deterministic_and_in_range() {
+        let generators = [
+            ArgGen::Uint {
+                min: U256::from(10),
+                max: U256::from(20),
+            },
+            ArgGen::Bytes {
+                min_len: 1,
+                max_len: 4,
+            },
+        ];
+        let draw = |seed| {
+            let mut rng = SplitMix64(seed);
+            (0..50)
+                .flat_map(|_| generators.iter().map(|g| rng.arg(g)).collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        };
+        let values = draw(7);
+        assert_eq!(values, draw(7));
+        assert_ne!(values, draw(8));
+        for value in values {
+            match value {
+                DynSolValue::Uint(v, _) => assert!(v >= U256::from(10) && v <= U256::from(20)),
+                DynSolValue::Bytes(b) => assert!((1..=4).contains(&b.len())),
+                other => panic!("unexpected value {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_gas_percentiles() {
+        let gas = gas_distribution((1..=100).collect());
+        assert_eq!(
+            gas,
+            GasDistribution {
+                min: 1,
+                p50: 50,
+                p90: 90,
+                p99: 99,
+                max: 100
+            }
+        );
+        assert_eq!(gas_distribution(vec![]), GasDistribution::default());
+    }
+}
@@ -0,0 +1,441 @@
+//! Expectation checks for regression suites
+//!
+//! A [`CheckedBatch`] pairs every transaction with the [`Expectation`]s it should
+//! meet. [`TraceEvm::run_checked`] executes the batch with full tracing and evaluates
+//! each expectation against the execution result and the trace, returning a
+//! [`CheckedReport`] with a pass/fail verdict and an explanation per expectation
+//! instead of panicking on the first mismatch.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! use alloy::primitives::{address, TxKind, U256};
+//! use revm_trace::{
+//!     create_evm_with_tracer,
+//!     harness::{CheckedBatch, Expectation},
+//!     SimulationTx, TxInspector,
+//! };
+//!
+//! let mut evm = create_evm_with_tracer("https://eth.llamarpc.com", TxInspector::new()).await?;
+//! let deposit = SimulationTx {
+//!     caller: address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045"),
+//!     transact_to: TxKind::Call(address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2")),
+//!     value: U256::from(10u64.pow(18)),
+//!     data: Default::default(),
+//!     trace_config: None,
+//!     return_abi: None,
+//! };
+//! let report = evm.run_checked(CheckedBatch {
+//!     items: vec![(deposit, vec![Expectation::Succeeds])],
+//!     is_stateful: false,
+//! });
+//! for failure in report.failures() {
+//!     println!("{failure}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+
+use crate::{
+    evm::TraceEvm,
+    inspectors::tx_inspector::{TxInspector, TxTraceOutput},
+    traits::{ResetDB, TransactionTrace},
+    types::{SimulationBatch, SimulationTx},
+    utils::error_utils::parse_custom_error,
+};
+use alloy::{
+    dyn_abi::DynSolValue,
+    hex,
+    primitives::{Address, B256, U256},
+};
+use revm::{
+    context_interface::result::ExecutionResult,
+    database::{Database, DatabaseCommit},
+};
+use serde::Serialize;
+
+/// Outcome a transaction is expected to have
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expectation {
+    /// The transaction succeeds
+    Succeeds,
+    /// The transaction reverts with this `Error(string)` reason or panic description
+    /// (see [`parse_custom_error`])
+    RevertsWith(String),
+    /// The transaction reverts with data starting with this selector, typically a
+    /// custom error
+    RevertsWithSelector([u8; 4]),
+    /// A log with this first topic is emitted by `address`
+    EmitsEvent { address: Address, topic0: B256 },
+    /// Transfers of `token` from `from` to `to` add up to at least `min`
+    ///
+    /// Native transfers use `NATIVE_TOKEN_ADDRESS` as the token.
+    TransfersAtLeast {
+        token: Address,
+        from: Address,
+        to: Address,
+        min: U256,
+    },
+    /// The transaction succeeds and returns this ABI-encoded value
+    ///
+    /// A `DynSolValue::Tuple` is matched against all return values; any other value
+    /// against a single one.
+    ReturnsAbi(DynSolValue),
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Succeeds => write!(f, "succeeds"),
+            Self::RevertsWith(reason) => write!(f, "reverts with {reason:?}"),
+            Self::RevertsWithSelector(selector) => {
+                write!(f, "reverts with selector 0x{}", hex::encode(selector))
+            }
+            Self::EmitsEvent { address, topic0 } => {
+                write!(f, "emits event {topic0} from {address}")
+            }
+            Self::TransfersAtLeast {
+                token,
+                from,
+                to,
+                min,
+            } => write!(f, "transfers at least {min} of {token} from {from} to {to}"),
+            Self::ReturnsAbi(value) => write!(f, "returns {value:?}"),
+        }
+    }
+}
+
+/// Verdict on one expectation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExpectationResult {
+    /// The expectation, as text
+    pub expectation: String,
+    /// Whether the transaction met it
+    pub passed: bool,
+    /// What actually happened
+    pub message: String,
+}
+
+impl fmt::Display for ExpectationResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = if self.passed { "passed" } else { "failed" };
+        write!(f, "{verdict}: {}: {}", self.expectation, self.message)
+    }
+}
+
+impl Expectation {
+    /// Evaluates the expectation against an executed transaction
+    ///
+    /// # Arguments
+    /// - `result`: Execution result of the transaction
+    /// - `output`: Trace of the transaction; events and transfers are only found in
+    ///   traces collected at a level covering them
+    pub fn check(&self, result: &ExecutionResult, output: &TxTraceOutput) -> ExpectationResult {
+        let (passed, message) = match self {
+            Self::Succeeds => (result.is_success(), describe_outcome(result)),
+            Self::RevertsWith(reason) => match result {
+                ExecutionResult::Revert { output, .. } => (
+                    parse_custom_error(output).as_ref() == Some(reason),
+                    describe_outcome(result),
+                ),
+                _ => (false, describe_outcome(result)),
+            },
+            Self::RevertsWithSelector(selector) => match result {
+                ExecutionResult::Revert { output, .. } => (
+                    output.starts_with(selector),
+                    format!("reverted with data 0x{}", hex::encode(output)),
+                ),
+                _ => (false, describe_outcome(result)),
+            },
+            Self::EmitsEvent { address, topic0 } => {
+                let from_address = output.logs.iter().filter(|log| log.address == *address);
+                let topics: Vec<_> = from_address
+                    .filter_map(|log| log.topics().first())
+                    .collect();
+                let passed = topics.contains(&topic0);
+                let message = if topics.is_empty() {
+                    format!("no event from {address} among {} logs", output.logs.len())
+                } else {
+                    let topics: Vec<_> = topics.iter().map(ToString::to_string).collect();
+                    format!("events from {address}: {}", topics.join(", "))
+                };
+                (passed, message)
+            }
+            Self::TransfersAtLeast {
+                token,
+                from,
+                to,
+                min,
+            } => {
+                let total = output
+                    .asset_transfers
+                    .iter()
+                    .filter(|transfer| {
+                        transfer.token == *token
+                            && transfer.from == *from
+                            && transfer.to == Some(*to)
+                    })
+                    .fold(U256::ZERO, |total, transfer| {
+                        total.saturating_add(transfer.value)
+                    });
+                (total >= *min, format!("transferred {total}"))
+            }
+            Self::ReturnsAbi(expected) => check_return(expected, result),
+        };
+        ExpectationResult {
+            expectation: self.to_string(),
+            passed,
+            message,
+        }
+    }
+}
+
+/// Decodes the return data of a successful call and compares it with `expected`
+fn check_return(expected: &DynSolValue, result: &ExecutionResult) -> (bool, String) {
+    let ExecutionResult::Success { output, .. } = result else {
+        return (false, describe_outcome(result));
+    };
+    let data = output.data();
+    let Some(ty) = expected.as_type() else {
+        return (false, "type of the expected value is unknown".to_string());
+    };
+    let decoded = if matches!(expected, DynSolValue::Tuple(_)) {
+        ty.abi_decode_params(data)
+    } else {
+        ty.abi_decode(data)
+    };
+    match decoded {
+        Ok(actual) => (&actual == expected, format!("returned {actual:?}")),
+        Err(_) => (
+            false,
+            format!(
+                "returned 0x{}, which does not decode as {}",
+                hex::encode(data),
+                ty.sol_type_name()
+            ),
+        ),
+    }
+}
+
+/// Describes how a transaction ended, with its revert reason if decodable
+fn describe_outcome(result: &ExecutionResult) -> String {
+    match result {
+        ExecutionResult::Success { .. } => "succeeded".to_string(),
+        ExecutionResult::Revert { output, .. } => match parse_custom_error(output) {
+            Some(reason) => format!("reverted with {reason:?}"),
+            None if output.is_empty() => "reverted without data".to_string(),
+            None => format!("reverted with data 0x{}", hex::encode(output)),
+        },
+        ExecutionResult::Halt { reason, .. } => format!("halted with {reason:?}"),
+    }
+}
+
+/// Transactions paired with the expectations they should meet
+#[derive(Debug, Clone)]
+pub struct CheckedBatch {
+    /// Transactions in execution order, each with its expectations
+    pub items: Vec<(SimulationTx, Vec<Expectation>)>,
+    /// Whether state persists between transactions (see `SimulationBatch::is_stateful`)
+    pub is_stateful: bool,
+}
+
+/// Verdicts on the expectations of one transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CheckedItem {
+    /// Position of the transaction in the batch
+    pub index: usize,
+    /// One verdict per expectation, in the order they were given
+    pub results: Vec<ExpectationResult>,
+}
+
+impl CheckedItem {
+    /// Whether every expectation of the transaction was met
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|result| result.passed)
+    }
+}
+
+/// Outcome of [`TraceEvm::run_checked`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CheckedReport {
+    /// One entry per transaction, in batch order
+    pub items: Vec<CheckedItem>,
+}
+
+impl CheckedReport {
+    /// Whether every expectation of every transaction was met
+    pub fn passed(&self) -> bool {
+        self.items.iter().all(CheckedItem::passed)
+    }
+
+    /// Verdicts of the failed expectations, in batch order
+    pub fn failures(&self) -> impl Iterator<Item = &ExpectationResult> {
+        self.items
+            .iter()
+            .flat_map(|item| &item.results)
+            .filter(|result| !result.passed)
+    }
+}
+
+impl<DB> TraceEvm<DB, TxInspector>
+where
+    DB: Database + DatabaseCommit + ResetDB,
+{
+    /// Executes a batch and checks every transaction against its expectations
+    ///
+    /// The transactions run as one `trace_transactions` batch at their own trace
+    /// levels, without overrides and with the default failure policy. Expectations of
+    /// a transaction that could not be executed all fail, with the error as message.
+    pub fn run_checked(&mut self, batch: CheckedBatch) -> CheckedReport {
+        let (transactions, expectations): (Vec<_>, Vec<_>) = batch.items.into_iter().unzip();
+        let results = self.trace_transactions(SimulationBatch {
+            transactions,
+            is_stateful: batch.is_stateful,
+            ..Default::default()
+        });
+
+        let items = results
+            .into_iter()
+            .zip(expectations)
+            .enumerate()
+            .map(|(index, (result, expectations))| {
+                let results = expectations
+                    .iter()
+                    .map(|expectation| match &result {
+                        Ok((result, _, output)) => expectation.check(result, output),
+                        Err(e) => ExpectationResult {
+                            expectation: expectation.to_string(),
+                            passed: false,
+                            message: format!("transaction was not executed: {e}"),
+                        },
+                    })
+                    .collect();
+                CheckedItem { index, results }
+            })
+            .collect();
+        CheckedReport { items }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{TokenTransfer, TokenType, NATIVE_TOKEN_ADDRESS};
+    use alloy::primitives::{address, Bytes, Log};
+    use revm::context_interface::result::{HaltReason, Output, SuccessReason};
+
+    const ALICE: Address = address!("1000000000000000000000000000000000000001");
+    const BOB: Address = address!("2000000000000000000000000000000000000002");
+
+    fn success(data: Vec<u8>) -> ExecutionResult {
+        ExecutionResult::Success {
+            reason: SuccessReason::Return,
+            gas_used: 21_000,
+            gas_refunded: 0,
+            logs: vec![],
+            output: Output::Call(Bytes::from(data)),
+        }
+    }
+
+    fn revert(data: &str) -> ExecutionResult {
+        ExecutionResult::Revert {
+            gas_used: 21_000,
+            output: hex::decode(data).unwrap().into(),
+        }
+    }
+
+    /// `Error("nope")`
+    const NOPE: &str = "08c379a0\
+        0000000000000000000000000000000000000000000000000000000000000020\
+        0000000000000000000000000000000000000000000000000000000000000004\
+        6e6f706500000000000000000000000000000000000000000000000000000000";
+
+    fn check(expectation: Expectation, result: &ExecutionResult) -> ExpectationResult {
+        expectation.check(result, &TxTraceOutput::default())
+    }
+
+    #[test]
+    fn test_status_expectations() {
+        let reverted = revert(NOPE);
+        let failed = check(Expectation::Succeeds, &reverted);
+        assert!(!failed.passed);
+        assert_eq!(failed.message, "reverted with \"nope\"");
+
+        assert!(check(Expectation::RevertsWith("nope".to_string()), &reverted).passed);
+        assert!(!check(Expectation::RevertsWith("other".to_string()), &reverted).passed);
+        assert!(
+            check(
+                Expectation::RevertsWithSelector([0x08, 0xc3, 0x79, 0xa0]),
+                &reverted
+            )
+            .passed
+        );
+
+        let halted = ExecutionResult::Halt {
+            reason: HaltReason::OpcodeNotFound,
+            gas_used: 21_000,
+        };
+        let failed = check(Expectation::RevertsWithSelector([0; 4]), &halted);
+        assert!(!failed.passed);
+        assert_eq!(failed.message, "halted with OpcodeNotFound");
+    }
+
+    #[test]
+    fn test_returns_abi() {
+        let result = success(U256::from(7).to_be_bytes_vec());
+        assert!(check(Expectation::ReturnsAbi(U256::from(7).into()), &result).passed);
+
+        let failed = check(Expectation::ReturnsAbi(U256::from(8).into()), &result);
+        assert!(!failed.passed);
+        assert_eq!(failed.message, "returned Uint(7, 256)");
+
+        let pair = DynSolValue::Tuple(vec![U256::from(7).into(), DynSolValue::Bool(true)]);
+        let failed = check(Expectation::ReturnsAbi(pair), &result);
+        assert!(!failed.passed);
+        assert!(failed.message.contains("does not decode as (uint256,bool)"));
+    }
+
+    #[test]
+    fn test_trace_expectations() {
+        let topic0 = B256::repeat_byte(0xee);
+        let output = TxTraceOutput {
+            logs: vec![Log::new_unchecked(BOB, vec![topic0], Bytes::new())],
+            asset_transfers: [3, 4]
+                .map(|value| TokenTransfer {
+                    token: NATIVE_TOKEN_ADDRESS,
+                    from: ALICE,
+                    to: Some(BOB),
+                    value: U256::from(value),
+                    token_type: TokenType::Native,
+                    id: None,
+                    role: Default::default(),
+                    transfer_index: 0,
+                    failed: false,
+                })
+                .to_vec(),
+            ..Default::default()
+        };
+        let result = success(vec![]);
+
+        let emits = |address| Expectation::EmitsEvent { address, topic0 };
+        assert!(emits(BOB).check(&result, &output).passed);
+        let failed = emits(ALICE).check(&result, &output);
+        assert!(!failed.passed);
+        assert_eq!(
+            failed.message,
+            format!("no event from {ALICE} among 1 logs")
+        );
+
+        let transfers = |min: u64| Expectation::TransfersAtLeast {
+            token: NATIVE_TOKEN_ADDRESS,
+            from: ALICE,
+            to: BOB,
+            min: U256::from(min),
+        };
+        assert!(transfers(7).check(&result, &output).passed);
+        let failed = transfers(8).check(&result, &output);
+        assert!(!failed.passed);
+        assert_eq!(failed.message, "transferred 7");
+    }
+}
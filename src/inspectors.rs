@@ -5,5 +5,7 @@
 //!
 //! # Available Inspectors
 //! - `tx_inspector`: Custom transaction inspector with comprehensive tracing
+//! - `streaming`: Inspector delivering execution events to a callback as they happen
 
+pub mod streaming;
 pub mod tx_inspector;
@@ -0,0 +1,295 @@
+//! Callback-based event streaming
+//!
+//! [`StreamingInspector`] hands every call, creation, transfer and log to a callback
+//! while the transaction executes, instead of collecting a [`TxTraceOutput`]. Memory
+//! use stays flat however large the transaction, and consumers can feed their own
+//! sinks (metrics, queues, databases) directly.
+//!
+//! Events are delivered synchronously and in execution order. The callback only sees
+//! borrowed, read-only events, so it cannot alter what the inspector tracks.
+//!
+//! # Panicking Callbacks
+//!
+//! A panic in the callback is caught and halts collection: no further events are
+//! delivered until the inspector is reset for the next transaction. Execution itself
+//! is unaffected. The panic is still reported by the process panic hook (by default
+//! printed to stderr); [`StreamingInspector::is_halted`] tells whether it happened.
+//!
+//! ```
+//! use revm_trace::inspectors::streaming::{StreamingInspector, TraceEvent};
+//!
+//! let mut calls = 0;
+//! let inspector = StreamingInspector::new(|event| {
+//!     if let TraceEvent::CallStart { .. } = event {
+//!         calls += 1;
+//!     }
+//! });
+//! assert!(!inspector.is_halted());
+//! ```
+//!
+//! [`TxTraceOutput`]: crate::inspectors::tx_inspector::TxTraceOutput
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::{
+    traits::{Reset, TraceOutput},
+    types::{TokenTransfer, TokenType, TransferRole, NATIVE_TOKEN_ADDRESS},
+};
+use alloy::primitives::{Address, FixedBytes, Log, B256, U256};
+use revm::{
+    context::ContextTr,
+    interpreter::{
+        interpreter_types::InterpreterTypes, CallInputs, CallOutcome, CallScheme, CreateInputs,
+        CreateOutcome, InstructionResult, Interpreter,
+    },
+    Inspector,
+};
+
+/// Execution event delivered by [`StreamingInspector`]
+///
+/// `depth` is 0 for the top-level frame of the transaction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TraceEvent<'a> {
+    /// A call or creation frame started
+    CallStart {
+        depth: usize,
+        /// Account executing the frame's caller code
+        from: Address,
+        /// Called address (the implementation for DELEGATECALL); `None` for creations,
+        /// whose address is reported by the matching `Create` event
+        to: Option<Address>,
+        value: U256,
+        /// First four bytes of the call input, if it has that many
+        selector: Option<FixedBytes<4>>,
+    },
+    /// A frame ended
+    CallEnd {
+        depth: usize,
+        status: InstructionResult,
+        gas_used: u64,
+    },
+    /// Native or token transfer, as `TxInspector` would record it
+    Transfer(&'a TokenTransfer),
+    /// A log was emitted
+    Log {
+        address: Address,
+        topic0: Option<B256>,
+    },
+    /// A contract was deployed successfully; precedes the `CallEnd` of its frame
+    Create { address: Address },
+}
+
+/// Inspector streaming [`TraceEvent`]s to a callback instead of collecting a trace
+///
+/// Works standalone with `TraceEvm` (its output is `()`) or next to other inspectors.
+pub struct StreamingInspector<F> {
+    /// Receiver of the events
+    callback: F,
+    /// Number of currently open frames
+    depth: usize,
+    /// Whether the callback panicked during the current transaction
+    halted: bool,
+    /// Number of transfers delivered during the current transaction
+    transfers: usize,
+}
+
+impl<F> StreamingInspector<F>
+where
+    F: FnMut(TraceEvent<'_>),
+{
+    /// Creates an inspector delivering events to `callback`
+    pub fn new(callback: F) -> Self {
+        Self {
+            callback,
+            depth: 0,
+            halted: false,
+            transfers: 0,
+        }
+    }
+
+    /// Whether a panicking callback halted collection for the current transaction
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Consumes the inspector, returning the callback
+    pub fn into_callback(self) -> F {
+        self.callback
+    }
+
+    /// Delivers an event unless collection is halted
+    fn emit(&mut self, event: TraceEvent<'_>) {
+        if self.halted {
+            return;
+        }
+        let callback = &mut self.callback;
+        if catch_unwind(AssertUnwindSafe(|| callback(event))).is_err() {
+            self.halted = true;
+        }
+    }
+
+    /// Delivers a native transfer
+    fn emit_native(&mut self, from: Address, to: Address, value: U256) {
+        let transfer = TokenTransfer {
+            token: NATIVE_TOKEN_ADDRESS,
+            from,
+            to: Some(to),
+            value,
+            token_type: TokenType::Native,
+            id: None,
+            role: TransferRole::Normal,
+            transfer_index: 0,
+            failed: false,
+        };
+        self.emit_transfer(transfer);
+    }
+
+    /// Delivers a transfer, numbering it by its position in the transaction
+    fn emit_transfer(&mut self, mut transfer: TokenTransfer) {
+        transfer.transfer_index = self.transfers;
+        self.transfers += 1;
+        self.emit(TraceEvent::Transfer(&transfer));
+    }
+
+    /// Closes the innermost frame
+    fn end_frame(&mut self, status: InstructionResult, gas_used: u64) {
+        self.depth = self.depth.saturating_sub(1);
+        self.emit(TraceEvent::CallEnd {
+            depth: self.depth,
+            status,
+            gas_used,
+        });
+    }
+}
+
+impl<CTX, INTR, F> Inspector<CTX, INTR> for StreamingInspector<F>
+where
+    CTX: ContextTr,
+    INTR: InterpreterTypes,
+    F: FnMut(TraceEvent<'_>),
+{
+    fn call(&mut self, context: &mut CTX, inputs: &mut CallInputs) -> Option<CallOutcome> {
+        let to = match inputs.scheme {
+            CallScheme::DelegateCall => inputs.bytecode_address,
+            _ => inputs.target_address,
+        };
+        let input = inputs.input.bytes(context);
+        let depth = self.depth;
+        self.depth += 1;
+        self.emit(TraceEvent::CallStart {
+            depth,
+            from: inputs.caller,
+            to: Some(to),
+            value: inputs.call_value(),
+            selector: input.get(..4).map(FixedBytes::from_slice),
+        });
+        if let Some(value) = inputs.transfer_value() {
+            if value > U256::ZERO
+                && matches!(inputs.scheme, CallScheme::Call | CallScheme::CallCode)
+            {
+                self.emit_native(inputs.transfer_from(), inputs.transfer_to(), value);
+            }
+        }
+        None
+    }
+
+    fn create(&mut self, _context: &mut CTX, inputs: &mut CreateInputs) -> Option<CreateOutcome> {
+        let depth = self.depth;
+        self.depth += 1;
+        self.emit(TraceEvent::CallStart {
+            depth,
+            from: inputs.caller,
+            to: None,
+            value: inputs.value,
+            selector: None,
+        });
+        None
+    }
+
+    fn call_end(&mut self, _context: &mut CTX, _inputs: &CallInputs, outcome: &mut CallOutcome) {
+        self.end_frame(outcome.result.result, outcome.result.gas.spent());
+    }
+
+    /// Reports the deployment, with its endowment now that the address is known
+    fn create_end(
+        &mut self,
+        _context: &mut CTX,
+        inputs: &CreateInputs,
+        outcome: &mut CreateOutcome,
+    ) {
+        let deployed = outcome.result.result.is_ok();
+        if let Some(address) = outcome.address.filter(|_| deployed) {
+            if inputs.value > U256::ZERO {
+                self.emit_native(inputs.caller, address, inputs.value);
+            }
+            self.emit(TraceEvent::Create { address });
+        }
+        self.end_frame(outcome.result.result, outcome.result.gas.spent());
+    }
+
+    fn log(&mut self, _interp: &mut Interpreter<INTR>, _context: &mut CTX, log: Log) {
+        self.emit(TraceEvent::Log {
+            address: log.address,
+            topic0: log.topics().first().copied(),
+        });
+        if log.topics().is_empty() {
+            return;
+        }
+        for transfer in TokenTransfer::get_token_transfers(&log) {
+            self.emit_transfer(transfer);
+        }
+    }
+
+    fn selfdestruct(&mut self, contract: Address, target: Address, value: U256) {
+        if value > U256::ZERO {
+            self.emit_native(contract, target, value);
+        }
+    }
+}
+
+impl<F> Reset for StreamingInspector<F> {
+    /// Prepares for the next transaction, resuming collection after a halt
+    fn reset(&mut self) {
+        self.depth = 0;
+        self.halted = false;
+        self.transfers = 0;
+    }
+
+    fn reset_slot_cache(&mut self) {}
+}
+
+impl<F> TraceOutput for StreamingInspector<F> {
+    type Output = ();
+
+    fn get_output(&self) -> Self::Output {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panicking_callback_halts_collection() {
+        let mut delivered = 0;
+        let mut inspector = StreamingInspector::new(|_: TraceEvent<'_>| {
+            delivered += 1;
+            if delivered == 2 {
+                panic!("sink failed");
+            }
+        });
+        for _ in 0..3 {
+            inspector.emit(TraceEvent::Create {
+                address: Address::ZERO,
+            });
+        }
+        assert!(inspector.is_halted());
+
+        inspector.reset();
+        assert!(!inspector.is_halted());
+        inspector.emit(TraceEvent::Create {
+            address: Address::ZERO,
+        });
+        drop(inspector);
+        assert_eq!(delivered, 3);
+    }
+}
@@ -0,0 +1,233 @@
+//! Net native token flows
+//!
+//! Value-bearing calls that forward ETH through intermediate contracts (router → pair →
+//! recipient) produce one raw transfer per hop. This module collapses such pass-through
+//! hops into net sender → receiver flows, using the call tree to ignore value moved by
+//! frames that were later reverted. The raw per-hop `asset_transfers` are left untouched.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::inspectors::tx_inspector::TxTraceOutput;
+use crate::types::{CallScheme, CallTrace, ContractChange, NativeFlow};
+use alloy::primitives::{Address, U256};
+
+impl TxTraceOutput {
+    /// Returns native token movements netted across pass-through hops
+    ///
+    /// Addresses that forward exactly what they receive disappear from the result;
+    /// every remaining flow goes from an address with a net outflow to one with a net
+    /// inflow, following the actual transfer paths. Flows are ordered by the first
+    /// appearance of their sender.
+    pub fn net_native_flows(&self) -> Vec<NativeFlow> {
+        self.native_hops()
+            .map(|hops| decompose(&hops))
+            .unwrap_or_default()
+    }
+
+    /// Returns the native token hops of frames whose effects persisted, in execution
+    /// order, followed by selfdestruct credits
+    ///
+    /// Hops moving nothing or moving value to the sender itself are left out. `None`
+    /// without a call tree.
+    pub(crate) fn native_hops(&self) -> Option<Vec<(Address, Address, U256)>> {
+        let root = self.call_trace.as_ref()?;
+
+        let mut hops = Vec::new();
+        let mut succeeded = HashSet::new();
+        collect_hops(root, &mut hops, &mut succeeded);
+        for change in &self.contract_changes {
+            if let ContractChange::SelfDestructed {
+                contract,
+                beneficiary,
+                balance_moved,
+                trace_address,
+                ..
+            } = change
+            {
+                if succeeded.contains(trace_address) && contract != beneficiary {
+                    hops.push((*contract, *beneficiary, *balance_moved));
+                }
+            }
+        }
+        hops.retain(|(from, to, value)| from != to && !value.is_zero());
+        Some(hops)
+    }
+}
+
+/// Collects value-bearing hops of successful frames in execution order
+fn collect_hops(
+    root: &CallTrace,
+    hops: &mut Vec<(Address, Address, U256)>,
+    succeeded: &mut HashSet<Vec<usize>>,
+) {
+    // (frame, whether all ancestors succeeded)
+    let mut stack = vec![(root, true)];
+    while let Some((frame, parent_ok)) = stack.pop() {
+        let ok = parent_ok && frame.status.is_success();
+        if ok {
+            succeeded.insert(frame.trace_address.clone());
+            let moves_value = match frame.call_scheme {
+                Some(scheme) => scheme == CallScheme::Call,
+                None => frame.create_scheme.is_some(),
+            };
+            if moves_value {
+                hops.push((frame.from, frame.to, frame.value));
+            }
+        }
+        stack.extend(frame.subtraces.iter().rev().map(|sub| (sub, ok)));
+    }
+}
+
+/// Decomposes the hop graph into paths from net senders to net receivers
+fn decompose(hops: &[(Address, Address, U256)]) -> Vec<NativeFlow> {
+    let mut order = Vec::new();
+    let mut incoming: HashMap<Address, U256> = HashMap::new();
+    let mut outgoing: HashMap<Address, U256> = HashMap::new();
+    let mut capacity: Vec<(Address, Address, U256)> = Vec::new();
+    for &(from, to, value) in hops {
+        for address in [from, to] {
+            if !order.contains(&address) {
+                order.push(address);
+            }
+        }
+        *outgoing.entry(from).or_default() += value;
+        *incoming.entry(to).or_default() += value;
+        match capacity.iter_mut().find(|(f, t, _)| *f == from && *t == to) {
+            Some((_, _, cap)) => *cap += value,
+            None => capacity.push((from, to, value)),
+        }
+    }
+
+    let net = |address: &Address, map: &HashMap<Address, U256>, other: &HashMap<Address, U256>| {
+        let a = map.get(address).copied().unwrap_or_default();
+        let b = other.get(address).copied().unwrap_or_default();
+        a.saturating_sub(b)
+    };
+    let mut surplus: HashMap<Address, U256> = order
+        .iter()
+        .map(|address| (*address, net(address, &incoming, &outgoing)))
+        .collect();
+
+    let mut flows: Vec<NativeFlow> = Vec::new();
+    for sender in &order {
+        let mut excess = net(sender, &outgoing, &incoming);
+        while !excess.is_zero() {
+            let Some(path) = find_path(*sender, &capacity, &surplus) else {
+                break;
+            };
+            let receiver = capacity[*path.last().unwrap()].1;
+            let amount = path
+                .iter()
+                .map(|&edge| capacity[edge].2)
+                .fold(excess.min(surplus[&receiver]), U256::min);
+            for &edge in &path {
+                capacity[edge].2 -= amount;
+            }
+            excess -= amount;
+            *surplus.get_mut(&receiver).unwrap() -= amount;
+            match flows
+                .iter_mut()
+                .find(|flow| flow.from == *sender && flow.to == receiver)
+            {
+                Some(flow) => flow.value += amount,
+                None => flows.push(NativeFlow {
+                    from: *sender,
+                    to: receiver,
+                    value: amount,
+                }),
+            }
+        }
+    }
+    flows
+}
+
+/// Finds a path (as edge indices) from `source` to any address with remaining surplus
+fn find_path(
+    source: Address,
+    capacity: &[(Address, Address, U256)],
+    surplus: &HashMap<Address, U256>,
+) -> Option<Vec<usize>> {
+    let mut visited = HashSet::from([source]);
+    let mut stack: Vec<(Address, Vec<usize>)> = vec![(source, Vec::new())];
+    while let Some((node, path)) = stack.pop() {
+        for (index, (from, to, cap)) in capacity.iter().enumerate().rev() {
+            if *from != node || cap.is_zero() || !visited.insert(*to) {
+                continue;
+            }
+            let mut next = path.clone();
+            next.push(index);
+            if surplus.get(to).is_some_and(|s| !s.is_zero()) {
+                return Some(next);
+            }
+            stack.push((*to, next));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::CallStatus;
+    use alloy::primitives::address;
+
+    const USER: Address = address!("1000000000000000000000000000000000000001");
+    const ROUTER: Address = address!("7a250d5630B4cF539739dF2C5dAcb4c659F2488D");
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+
+    fn call(from: Address, to: Address, value: u64, trace_address: Vec<usize>) -> CallTrace {
+        CallTrace {
+            from,
+            to,
+            value: U256::from(value),
+            call_scheme: Some(CallScheme::Call),
+            trace_address,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_swap_collapses_to_single_outflow() {
+        // user -> router (10), router -> WETH deposit (8), router -> user refund (2)
+        let mut root = call(USER, ROUTER, 10, vec![]);
+        root.subtraces = vec![
+            call(ROUTER, WETH, 8, vec![0]),
+            call(ROUTER, USER, 2, vec![1]),
+        ];
+        let output = TxTraceOutput {
+            call_trace: Some(root),
+            ..Default::default()
+        };
+        assert_eq!(
+            output.net_native_flows(),
+            vec![NativeFlow {
+                from: USER,
+                to: WETH,
+                value: U256::from(8)
+            }]
+        );
+    }
+
+    #[test]
+    fn test_reverted_hops_are_ignored() {
+        let mut reverted = call(ROUTER, WETH, 10, vec![0]);
+        reverted.status = CallStatus::Revert {
+            reason: None,
+            raw: Default::default(),
+        };
+        let mut root = call(USER, ROUTER, 10, vec![]);
+        root.subtraces = vec![reverted];
+        let output = TxTraceOutput {
+            call_trace: Some(root),
+            ..Default::default()
+        };
+        assert_eq!(
+            output.net_native_flows(),
+            vec![NativeFlow {
+                from: USER,
+                to: ROUTER,
+                value: U256::from(10)
+            }]
+        );
+    }
+}
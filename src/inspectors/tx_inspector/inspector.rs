@@ -12,18 +12,23 @@
 //! - Self-destructs and balance transfers
 //! - ERC20 transfer event parsing
 
+use super::{trace::capture, MAX_PREIMAGE_LEN};
 use crate::TxInspector;
 use revm::{
     context::ContextTr,
+    context_interface::{Block, Cfg},
     interpreter::{
-        interpreter_types::{InputsTr, InterpreterTypes, Jumps, StackTr},
-        CallInputs, CallOutcome, CallScheme, CreateInputs, CreateOutcome, Interpreter,
+        interpreter_types::{InputsTr, InterpreterTypes, Jumps, LoopControl, MemoryTr, StackTr},
+        CallInputs, CallOutcome, CallScheme, CreateInputs, CreateOutcome, InstructionResult,
+        Interpreter,
     },
     Database, Inspector,
 };
 
+use crate::chains::system_contract_name;
+use crate::evm::timeline::TimelineEventKind;
 use crate::types::*;
-use alloy::primitives::{Address, Bytes, Log, U256};
+use alloy::primitives::{Address, Bytes, Log, B256, U256};
 
 impl<CTX, INTR> Inspector<CTX, INTR> for TxInspector
 where
@@ -53,19 +58,35 @@ where
             _ => inputs.target_address,
         };
 
+        // Create call trace entry; frames beyond the depth limit report the
+        // location of the frame they are collapsed into
+        let collapse = self.enter_truncated_frame();
+        let trace_address = self.next_trace_address(collapse);
+
         // Track ETH transfers
         if let Some(value) = inputs.transfer_value() {
             if value > U256::ZERO
                 && (inputs.scheme == CallScheme::Call || inputs.scheme == CallScheme::CallCode)
             {
-                self.transfers.push(TokenTransfer {
+                self.record_transfer(TokenTransfer {
                     token: NATIVE_TOKEN_ADDRESS,
                     from: inputs.transfer_from(),
                     to: Some(inputs.transfer_to()),
                     token_type: TokenType::Native,
                     id: None,
+                    role: TransferRole::Normal,
                     value,
+                    transfer_index: 0,
+                    failed: false,
                 });
+                // Direct payments to the block producer
+                if inputs.transfer_to() == context.block().beneficiary() {
+                    self.coinbase_payments.push(CoinbasePayment {
+                        from: inputs.transfer_from(),
+                        value,
+                        trace_address: trace_address.clone(),
+                    });
+                }
             }
         }
 
@@ -75,28 +96,42 @@ where
             _ => to,
         };
         self.address_stack.push(next_caller);
-
-        // Create call trace entry
-        let mut trace_address = Vec::new();
-        if let Some(&parent_index) = self.call_stack.last() {
-            trace_address = self.call_traces[parent_index].trace_address.clone();
-            trace_address.push(self.call_traces[parent_index].subtraces.len());
+        if collapse {
+            return None;
         }
+        let target_kind = self.target_kind(context, to);
+        let (input, input_truncated) = capture(inputs.input.bytes(context), self.max_input_capture);
 
         let trace = CallTrace {
             from,
             to,
             value: inputs.call_value(),
-            input: inputs.input.bytes(context),
+            input,
+            input_truncated,
             call_scheme: Some(inputs.scheme),
             create_scheme: None,
+            gas_limit: U256::from(inputs.gas_limit),
             gas_used: U256::ZERO,
             output: Bytes::new(),
+            output_truncated: None,
             status: CallStatus::InProgress,
+            failure_kind: None,
             error_origin: false,
             subtraces: Vec::new(),
+            frame_id: CallTrace::frame_id_for(&trace_address),
             trace_address,
             slot_accesses: Vec::new(), // Initialize empty slot accesses
+            refund_counter_delta: 0,
+            log_indices: Vec::new(),
+            depth: self.call_stack.len(),
+            truncated: false,
+            target_kind,
+            used_tx_origin: false,
+            tx_origin_reads: 0,
+            facet_route: None,
+            code_address: (inputs.bytecode_address != to).then_some(inputs.bytecode_address),
+            opcodes_executed: (self.trace_level == TraceLevel::Full).then_some(0),
+            resource_stats: (self.trace_level == TraceLevel::Full).then(FrameResources::default),
         };
 
         self.call_traces.push(trace);
@@ -107,8 +142,8 @@ where
     /// Processes contract creation transactions
     ///
     /// # Processing Steps
-    /// 1. Records initial ETH transfer (if any)
-    /// 2. Creates pending transfer record
+    /// 1. Records initial ETH transfer and creation funding (if any)
+    /// 2. Creates pending transfer record and timeline events
     /// 3. Initializes creation trace entry
     /// 4. Updates call stack
     ///
@@ -122,43 +157,78 @@ where
         }
         let to = Address::ZERO; // Will be updated in create_end
         self.address_stack.push(to);
+        // Opened first so that the timeline flags the transfer of a failed creation
+        // as reverted
+        let collapse = self.enter_truncated_frame();
 
-        // Track initial ETH transfer
-        if inputs.value > U256::ZERO {
-            let transfer = TokenTransfer {
+        // Track initial ETH transfer; every creation pushes an entry so that
+        // create_end pops its own
+        let pending = (inputs.value > U256::ZERO).then(|| {
+            let transfer_index = self.transfers.len();
+            self.transfers.push(TokenTransfer {
                 token: NATIVE_TOKEN_ADDRESS,
                 from,
                 to: None, // Updated in create_end
                 token_type: TokenType::Native,
                 id: None,
+                role: TransferRole::Normal,
                 value: inputs.value,
-            };
-            self.transfers.push(transfer.clone());
-            self.pending_create_transfers
-                .push((self.transfers.len() - 1, transfer));
+                transfer_index,
+                failed: false,
+            });
+            self.creation_funding.push(CreationFunding {
+                created: None, // Updated in create_end
+                funder: from,
+                value: inputs.value,
+                success: false,
+            });
+            (transfer_index, self.creation_funding.len() - 1)
+        });
+        self.pending_create_transfers.push(pending);
+        if self.timeline.is_enabled() {
+            let funding = pending.map(|(transfer_index, _)| self.transfers[transfer_index].clone());
+            let trace_address = self.current_trace_address();
+            self.timeline
+                .start_creation(trace_address, from, inputs.value, funding);
         }
 
         // Create trace entry
-        let mut trace_address = Vec::new();
-        if let Some(&parent_index) = self.call_stack.last() {
-            trace_address = self.call_traces[parent_index].trace_address.clone();
-            trace_address.push(self.call_traces[parent_index].subtraces.len());
+        if collapse {
+            return None;
         }
+        let trace_address = self.next_trace_address(false);
+        let (input, input_truncated) = capture(inputs.init_code.clone(), self.max_input_capture);
 
         let trace = CallTrace {
             from,
             to, // Updated in create_end
             value: inputs.value,
-            input: inputs.init_code.clone(),
+            input,
+            input_truncated,
             call_scheme: None,
             create_scheme: Some(inputs.scheme),
+            gas_limit: U256::from(inputs.gas_limit),
             gas_used: U256::ZERO,
             output: Bytes::new(),
+            output_truncated: None,
             status: CallStatus::InProgress,
+            failure_kind: None,
             error_origin: false,
             subtraces: Vec::new(),
+            frame_id: CallTrace::frame_id_for(&trace_address),
             trace_address,
             slot_accesses: Vec::new(), // Initialize empty slot accesses
+            refund_counter_delta: 0,
+            log_indices: Vec::new(),
+            depth: self.call_stack.len(),
+            truncated: false,
+            target_kind: TargetKind::Created,
+            used_tx_origin: false,
+            tx_origin_reads: 0,
+            facet_route: None,
+            code_address: None,
+            opcodes_executed: (self.trace_level == TraceLevel::Full).then_some(0),
+            resource_stats: (self.trace_level == TraceLevel::Full).then(FrameResources::default),
         };
 
         self.call_traces.push(trace);
@@ -180,7 +250,7 @@ where
         self.handle_end(
             outcome.result.result,
             outcome.result.gas.spent(),
-            outcome.result.output.clone(),
+            &outcome.result.output,
         );
         self.address_stack.pop();
     }
@@ -189,7 +259,7 @@ where
     ///
     /// # Processing Steps
     /// 1. Updates trace with actual contract address
-    /// 2. Resolves pending transfer recipient
+    /// 2. Resolves pending transfer recipient, or marks the transfer failed
     /// 3. Updates execution status
     ///
     /// # Important
@@ -201,25 +271,44 @@ where
         _inputs: &CreateInputs,
         outcome: &mut CreateOutcome,
     ) {
+        let succeeded = outcome.result.result.is_ok();
+        // The value only arrives when the creation succeeds
+        let created = outcome.address.filter(|_| succeeded);
+
+        // Remove and process the corresponding pending transfer
+        // We pop here because this transfer is now complete
+        if let Some((transfer_index, funding_index)) = self.pending_create_transfers.pop().flatten()
+        {
+            let transfer = &mut self.transfers[transfer_index];
+            transfer.to = created;
+            transfer.failed = created.is_none();
+            let funding = &mut self.creation_funding[funding_index];
+            funding.created = created;
+            funding.success = succeeded;
+        }
+        self.timeline.end_creation(created);
+
         if let Some(address) = outcome.address {
             // Get current trace index without removing it
-            // This will be popped in handle_end
-            if let Some(trace_index) = self.call_stack.last() {
-                self.call_traces[*trace_index].to = address;
+            // This will be popped in handle_end (collapsed frames have no entry)
+            if self.truncated_calls == 0 {
+                if let Some(trace_index) = self.call_stack.last() {
+                    self.call_traces[*trace_index].to = address;
+                }
             }
 
-            // Remove and process the corresponding pending transfer
-            // We pop here because this transfer is now complete
-            if let Some((transfer_index, mut transfer)) = self.pending_create_transfers.pop() {
-                transfer.to = Some(address);
-                self.transfers[transfer_index] = transfer;
+            if succeeded {
+                self.contract_changes.push(ContractChange::Created {
+                    address,
+                    code_len: outcome.result.output.len(),
+                });
             }
         }
         // handle_end will pop the call_stack
         self.handle_end(
             outcome.result.result,
             outcome.result.gas.spent(),
-            outcome.result.output.clone(),
+            &outcome.result.output,
         );
         self.address_stack.pop();
     }
@@ -227,17 +316,36 @@ where
     /// Processes emitted event logs
     ///
     /// # Processing Steps
-    /// 1. Records all logs for complete history
-    /// 2. Parses ERC20/ERC721/ERC1155 Transfer events
-    /// 3. Records token transfers if detected
+    /// 1. Parses ERC20/ERC721/ERC1155 Transfer events
+    /// 2. Records token transfers if detected, or a parse warning if a transfer
+    ///    event has an unrecognized layout
+    /// 3. Records the log unless the log filter rejects it
     ///
     /// # Note
     /// Special attention to ERC20/ERC721/ERC1155 Transfer events for
     /// accurate token transfer tracking
     fn log(&mut self, _interp: &mut Interpreter<INTR>, _context: &mut CTX, log: Log) {
-        self.logs.push(log.clone());
-        let mut transfers = TokenTransfer::get_token_transfers(&log);
-        self.transfers.append(&mut transfers);
+        // Transfer tracking sees every log, the filter only limits what is stored
+        match TokenTransfer::try_get_token_transfers(&log) {
+            Ok(transfers) => {
+                for transfer in transfers {
+                    self.record_transfer(transfer);
+                }
+            }
+            Err(warning) => self.parse_warnings.push(warning),
+        }
+        if self
+            .log_filter
+            .as_ref()
+            .is_some_and(|filter| !filter.matches(&log))
+        {
+            return;
+        }
+        // Attribute the log to the frame that emitted it
+        if let Some(&index) = self.call_stack.last() {
+            self.call_traces[index].log_indices.push(self.logs.len());
+        }
+        self.logs.push(log);
     }
 
     /// Handles contract self-destruction
@@ -250,14 +358,33 @@ where
     /// This is the final transfer of a contract's remaining balance
     /// before it is destroyed
     fn selfdestruct(&mut self, contract: Address, target: Address, value: U256) {
+        let same_tx_creation = self.contract_changes.iter().any(
+            |change| matches!(change, ContractChange::Created { address, .. } if *address == contract),
+        );
+        let trace_address = self.current_trace_address();
+        self.log_mutation(TimelineEventKind::SelfDestruct {
+            contract,
+            beneficiary: target,
+            value,
+        });
+        self.contract_changes.push(ContractChange::SelfDestructed {
+            contract,
+            beneficiary: target,
+            balance_moved: value,
+            same_tx_creation,
+            trace_address,
+        });
         if value > U256::ZERO {
-            self.transfers.push(TokenTransfer {
+            self.record_transfer(TokenTransfer {
                 token: NATIVE_TOKEN_ADDRESS,
                 from: contract,
                 to: Some(target),
                 value,
                 token_type: TokenType::Native,
                 id: None,
+                role: TransferRole::Normal,
+                transfer_index: 0,
+                failed: false,
             });
         }
     }
@@ -267,8 +394,33 @@ where
     /// Setting `interp.instruction_result` to anything other than [`interpreter::InstructionResult::Continue`]
     /// alters the execution of the interpreter.
     fn step(&mut self, interp: &mut Interpreter<INTR>, context: &mut CTX) {
+        if self.cancellation_requested() {
+            // Reverts the current frame before the opcode runs; its callers revert
+            // in turn when they execute their next opcode
+            interp
+                .control
+                .set_instruction_result(InstructionResult::Revert);
+            return;
+        }
+        // Slot and opcode tracking is only part of full traces
+        if self.trace_level != TraceLevel::Full {
+            return;
+        }
+        if let Some(&index) = self.call_stack.last() {
+            if let Some(count) = self.call_traces[index].opcodes_executed.as_mut() {
+                *count += 1;
+            }
+        }
         let opcode = interp.bytecode.opcode();
-        if opcode == 0x55 && self.call_stack.last().is_some() {
+        if opcode == 0x32 {
+            // ORIGIN: attribute the read to the current frame
+            if let Some(index) = self.call_stack.last() {
+                let call_trace = &mut self.call_traces[*index];
+                call_trace.used_tx_origin = true;
+                call_trace.tx_origin_reads += 1;
+            }
+        } else if opcode == 0x55 && self.call_stack.last().is_some() {
+            self.refund_before_sstore = Some(interp.control.gas().refunded());
             let slot = interp.stack.pop();
             let value = interp.stack.pop();
             if let Some(value) = value {
@@ -287,6 +439,7 @@ where
                 };
 
                 // Store the slot change in the current call trace
+                let is_cold = self.warm_slots.insert((target, slot));
                 let index = self.call_stack.last().unwrap();
                 let call_trace = &mut self.call_traces[*index];
                 call_trace.slot_accesses.push(SlotAccess {
@@ -295,9 +448,17 @@ where
                     old_value: old,
                     new_value: value,
                     is_write: true, // This is a write operation
+                    is_cold,
+                });
+                self.log_mutation(TimelineEventKind::StorageWrite {
+                    address: target,
+                    slot,
+                    old_value: old,
+                    new_value: value,
                 });
-                // Update the slot cache
-                self.slot_cache.insert((target, slot), value);
+                // Update the slot cache, remembering the entry in case the frame reverts
+                let previous = self.slot_cache.insert((target, slot), value);
+                self.slot_journal.push(((target, slot), previous));
             }
         } else if opcode == 0x54 && self.call_stack.last().is_some() {
             let slot = interp.stack.pop();
@@ -310,6 +471,7 @@ where
                 } else {
                     context.db().storage(target, slot).unwrap_or_default()
                 };
+                let is_cold = self.warm_slots.insert((target, slot));
                 let index = self.call_stack.last().unwrap();
                 let call_trace = &mut self.call_traces[*index];
                 call_trace.slot_accesses.push(SlotAccess {
@@ -318,8 +480,163 @@ where
                     old_value: value,
                     new_value: value,
                     is_write: false, // This is a read operation
+                    is_cold,
                 });
             }
+        } else if opcode == 0x20 && self.mapping_resolution {
+            // KECCAK256: remember the input until the hash is on the stack
+            let offset = interp.stack.pop();
+            let size = interp.stack.pop();
+            if let Some(size) = size {
+                let _ = interp.stack.push(size);
+            }
+            if let Some(offset) = offset {
+                let _ = interp.stack.push(offset);
+            }
+            let (Some(Ok(offset)), Some(Ok(size))) =
+                (offset.map(usize::try_from), size.map(usize::try_from))
+            else {
+                return;
+            };
+            // Memory is only expanded by the operation itself; skip inputs beyond it
+            if (32..=MAX_PREIMAGE_LEN).contains(&size)
+                && offset.saturating_add(size) <= interp.memory.size()
+            {
+                let input = interp.memory.slice_len(offset, size);
+                self.pending_preimage = Some(Bytes::copy_from_slice(&input));
+            }
+        }
+    }
+
+    /// Called after an instruction has executed
+    ///
+    /// Updates the resource peaks of the current frame, attributes the refund counter
+    /// change of an SSTORE to it and records the hash of a KECCAK256 whose input was
+    /// captured.
+    fn step_end(&mut self, interp: &mut Interpreter<INTR>, _context: &mut CTX) {
+        if self.trace_level == TraceLevel::Full && self.truncated_calls == 0 {
+            self.track_frame_resources(interp.memory.size(), interp.stack.len());
+        }
+        if let Some(preimage) = self.pending_preimage.take() {
+            // A failed KECCAK256 leaves no hash on the stack
+            if interp.control.instruction_result() == InstructionResult::Continue {
+                if let Some(hash) = interp.stack.pop() {
+                    let _ = interp.stack.push(hash);
+                    self.keccak_preimages
+                        .insert(B256::from(hash), preimage, self.max_preimages);
+                }
+            }
+        }
+        let Some(before) = self.refund_before_sstore.take() else {
+            return;
+        };
+        if let Some(index) = self.call_stack.last() {
+            self.call_traces[*index].refund_counter_delta +=
+                interp.control.gas().refunded() - before;
         }
     }
 }
+
+impl TxInspector {
+    /// Whether the batch was cancelled, checking the token every
+    /// `cancellation_interval` opcodes
+    ///
+    /// Stays true for the rest of the transaction once the token was found cancelled.
+    fn cancellation_requested(&mut self) -> bool {
+        if self.cancelled {
+            return true;
+        }
+        let Some(token) = &self.cancellation else {
+            return false;
+        };
+        if self.steps_to_cancellation_check > 0 {
+            self.steps_to_cancellation_check -= 1;
+            return false;
+        }
+        self.steps_to_cancellation_check = self.cancellation_interval - 1;
+        self.cancelled = token.is_cancelled();
+        self.cancelled
+    }
+
+    /// Raises the resource peaks of the current frame to the sizes after an
+    /// instruction
+    ///
+    /// Memory never shrinks within a frame, so its expansion gas only needs
+    /// recomputing when the peak grows.
+    fn track_frame_resources(&mut self, memory_len: usize, stack_len: usize) {
+        let Some(&index) = self.call_stack.last() else {
+            return;
+        };
+        let Some(stats) = self.call_traces[index].resource_stats.as_mut() else {
+            return;
+        };
+        stats.peak_stack_depth = stats.peak_stack_depth.max(stack_len as u64);
+        if memory_len as u64 > stats.peak_memory_bytes {
+            stats.peak_memory_bytes = memory_len as u64;
+            stats.mem_expansion_gas = memory_gas(memory_len as u64 / 32);
+        }
+    }
+
+    /// Appends a transfer, numbering it by its position in the transaction
+    fn record_transfer(&mut self, mut transfer: TokenTransfer) {
+        transfer.transfer_index = self.transfers.len();
+        if self.timeline.is_enabled() {
+            self.log_mutation(TimelineEventKind::Transfer(transfer.clone()));
+        }
+        self.transfers.push(transfer);
+    }
+
+    /// Appends a timeline event executed by the current frame, if the timeline is
+    /// recorded
+    fn log_mutation(&mut self, kind: TimelineEventKind) {
+        if self.timeline.is_enabled() {
+            let trace_address = self.current_trace_address();
+            self.timeline.record(trace_address, kind);
+        }
+    }
+
+    /// Trace address of the innermost recorded frame, empty before the first one
+    fn current_trace_address(&self) -> Vec<usize> {
+        self.call_stack
+            .last()
+            .map(|&index| self.call_traces[index].trace_address.clone())
+            .unwrap_or_default()
+    }
+
+    /// Classifies the account targeted by a new call frame
+    ///
+    /// Code is looked up in the database, so contracts deployed by committed earlier
+    /// transactions count as `Contract`; code deployed within this transaction is only
+    /// in the journal, which `contract_changes` covers. Only full traces pay for the
+    /// lookup.
+    fn target_kind<CTX: ContextTr>(&self, context: &mut CTX, target: Address) -> TargetKind {
+        if self.trace_level != TraceLevel::Full {
+            return TargetKind::default();
+        }
+        if let Some(precompile) = KnownPrecompile::from_address(target) {
+            return TargetKind::Precompile(precompile);
+        }
+        if let Some(name) = system_contract_name(context.cfg().chain_id(), target) {
+            return TargetKind::SystemContract(name);
+        }
+        let created = self.contract_changes.iter().any(
+            |change| matches!(change, ContractChange::Created { address, .. } if *address == target),
+        );
+        if created {
+            return TargetKind::Created;
+        }
+        match context.db().basic(target) {
+            Ok(Some(account)) if !account.is_empty_code_hash() => TargetKind::Contract,
+            Ok(_) => TargetKind::Eoa,
+            // Unknown, keep the neutral default
+            Err(_) => TargetKind::default(),
+        }
+    }
+}
+
+/// Total gas charged for `words` 32-byte words of memory: `3 * words + words² / 512`
+fn memory_gas(words: u64) -> u64 {
+    words
+        .saturating_mul(3)
+        .saturating_add(words.saturating_mul(words) / 512)
+}
@@ -0,0 +1,155 @@
+//! Logical call tree view
+//!
+//! Calling a proxy produces two frames: the call into the proxy and the proxy's
+//! DELEGATECALL into its implementation, both carrying the same input. UIs reading the
+//! raw tree show the selector twice and tend to attribute the activity to the
+//! implementation address. [`TxTraceOutput::logical_view`] merges such forwards into
+//! a single [`LogicalCall`] naming both the proxy and the implementation; the raw trace
+//! is left untouched.
+
+use crate::inspectors::tx_inspector::TxTraceOutput;
+use crate::types::{CallScheme, CallTrace, LogicalCall};
+use alloy::primitives::{Address, FixedBytes};
+
+impl TxTraceOutput {
+    /// Returns the call tree with proxy forwards merged, if a call tree was recorded
+    ///
+    /// A frame is merged with its child when the child is its only subcall, is a
+    /// DELEGATECALL and has exactly the same input (a classic proxy forward). Merging
+    /// repeats down chains of proxies; all other frames map one to one.
+    pub fn logical_view(&self) -> Option<LogicalCall> {
+        self.call_trace
+            .as_ref()
+            .map(|root| logical_call(root, root.from))
+    }
+}
+
+/// Builds the logical node of `frame`; `context` is the storage address of its parent
+fn logical_call(frame: &CallTrace, context: Address) -> LogicalCall {
+    let storage_address = match frame.call_scheme {
+        Some(CallScheme::DelegateCall | CallScheme::CallCode) => context,
+        _ => frame.to,
+    };
+    let mut handler = frame;
+    let mut merged_frames = Vec::new();
+    while let [child] = handler.subtraces.as_slice() {
+        if child.call_scheme != Some(CallScheme::DelegateCall) || child.input != frame.input {
+            break;
+        }
+        merged_frames.push(child.trace_address.clone());
+        handler = child;
+    }
+
+    LogicalCall {
+        from: frame.from,
+        to: frame.to,
+        proxy: (!merged_frames.is_empty()).then_some(frame.to),
+        implementation: handler.to,
+        active_implementation: None,
+        storage_address,
+        function_selector: frame
+            .input
+            .get(..4)
+            .map(|selector| FixedBytes::from_slice(selector)),
+        value: frame.value,
+        input: frame.input.clone(),
+        output: frame.output.clone(),
+        call_scheme: frame.call_scheme,
+        create_scheme: frame.create_scheme,
+        gas_used: frame.gas_used,
+        status: frame.status.clone(),
+        trace_address: frame.trace_address.clone(),
+        merged_frames,
+        subtraces: handler
+            .subtraces
+            .iter()
+            .map(|child| logical_call(child, storage_address))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{address, Bytes};
+
+    const USER: Address = address!("1000000000000000000000000000000000000001");
+    const PROXY: Address = address!("2000000000000000000000000000000000000002");
+    const IMPL: Address = address!("3000000000000000000000000000000000000003");
+    const TOKEN: Address = address!("4000000000000000000000000000000000000004");
+
+    fn frame(from: Address, to: Address, scheme: CallScheme, input: &[u8]) -> CallTrace {
+        CallTrace {
+            from,
+            to,
+            input: Bytes::copy_from_slice(input),
+            call_scheme: Some(scheme),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_proxy_forward_is_merged() {
+        // user -> proxy, proxy delegatecalls impl with the same input, impl calls a token
+        let mut forward = frame(
+            USER,
+            IMPL,
+            CallScheme::DelegateCall,
+            &[0xa9, 0x05, 0x9c, 0xbb],
+        );
+        forward.trace_address = vec![0];
+        forward.subtraces = vec![frame(PROXY, TOKEN, CallScheme::Call, &[1, 2, 3, 4, 5])];
+        let mut root = frame(USER, PROXY, CallScheme::Call, &[0xa9, 0x05, 0x9c, 0xbb]);
+        root.subtraces = vec![forward];
+        let output = TxTraceOutput {
+            call_trace: Some(root),
+            ..Default::default()
+        };
+
+        let view = output.logical_view().unwrap();
+        assert_eq!(view.to, PROXY);
+        assert_eq!(view.proxy, Some(PROXY));
+        assert_eq!(view.implementation, IMPL);
+        assert_eq!(view.storage_address, PROXY);
+        assert_eq!(
+            view.function_selector,
+            Some(FixedBytes::new([0xa9, 0x05, 0x9c, 0xbb]))
+        );
+        assert_eq!(view.merged_frames, vec![vec![0]]);
+        // The implementation's own calls move up one level
+        assert_eq!(view.subtraces.len(), 1);
+        assert_eq!(view.subtraces[0].to, TOKEN);
+        assert_eq!(view.subtraces[0].proxy, None);
+        // The raw trace is unchanged
+        assert_eq!(output.call_trace.unwrap().subtraces.len(), 1);
+    }
+
+    #[test]
+    fn test_other_delegatecalls_are_kept() {
+        // Library call: delegatecall with different input, next to another call
+        let mut root = frame(USER, PROXY, CallScheme::Call, &[1, 1, 1, 1]);
+        root.subtraces = vec![frame(USER, IMPL, CallScheme::DelegateCall, &[2, 2, 2, 2])];
+        let output = TxTraceOutput {
+            call_trace: Some(root.clone()),
+            ..Default::default()
+        };
+        let view = output.logical_view().unwrap();
+        assert_eq!(view.proxy, None);
+        assert_eq!(view.implementation, PROXY);
+        assert_eq!(view.subtraces[0].storage_address, PROXY);
+        assert_eq!(view.subtraces[0].implementation, IMPL);
+
+        // Same input, but not the only subcall
+        root.subtraces[0].input = root.input.clone();
+        root.subtraces
+            .push(frame(PROXY, TOKEN, CallScheme::StaticCall, &[]));
+        let output = TxTraceOutput {
+            call_trace: Some(root),
+            ..Default::default()
+        };
+        let view = output.logical_view().unwrap();
+        assert_eq!(view.proxy, None);
+        assert_eq!(view.subtraces.len(), 2);
+        assert_eq!(view.subtraces[1].function_selector, None);
+    }
+}
@@ -10,6 +10,12 @@
 //! - `types`: Output and internal data structures
 //! - `trace`: Call tracing and error tracking
 //! - `inspector`: REVM Inspector trait implementation
+//! - `flows`: Netting of native token transfers across pass-through hops
+//! - `logical`: Call tree view merging proxy forwards with their implementation call
+//! - `preimages`: Bounded record of KECCAK256 inputs for mapping resolution
+//! - `prune`: Size-bounded copies of traces for API responses
+//! - `roles`: Chain-aware classification of transfers (fees, wraps) and token netting
+//! - `timeline`: Ordered log of the mutations feeding batch timelines
 //!
 //! # Features
 //!
@@ -20,12 +26,37 @@
 //! - Event log collection
 
 use crate::types::*;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+mod flows;
 mod inspector;
+mod logical;
+mod preimages;
+mod proofs;
+mod prune;
+mod roles;
+mod timeline;
 mod trace;
 mod traits;
-use alloy::primitives::{Address, Log, U256};
-use std::collections::HashMap;
+use alloy::primitives::{Address, Bytes, Log, B256, U256};
+use preimages::PreimageLru;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use timeline::TimelineLog;
+
+/// Default maximum depth of the recorded call tree, the EVM's own call depth limit
+pub const DEFAULT_MAX_TRACE_DEPTH: usize = 1024;
+
+/// Default number of bytes of call input, init code and call output stored per frame
+pub const DEFAULT_MAX_CAPTURE: usize = 128 * 1024;
+
+/// Longest KECCAK256 input recorded as preimage: a 32-byte slot after a key of up to
+/// 96 bytes
+pub const MAX_PREIMAGE_LEN: usize = 128;
+
+/// Default number of KECCAK256 preimages kept per transaction
+pub const DEFAULT_MAX_PREIMAGES: usize = 4096;
+
+/// Default number of opcodes executed between two checks of the cancellation token
+pub const DEFAULT_CANCELLATION_INTERVAL: u64 = 1024;
 
 /// Core transaction tracing inspector
 ///
@@ -46,12 +77,43 @@ use std::collections::HashMap;
 /// - Call stack: Current execution path
 /// - Address stack: Caller context for delegate calls
 ///
+/// # Call Depth
+///
+/// Frames nested deeper than [`TxInspector::max_trace_depth`] are not recorded
+/// individually. Their storage accesses and logs are attributed to the deepest
+/// recorded frame, which is marked `truncated`; asset transfers are still tracked.
+///
+/// # Captured Data
+///
+/// Call inputs, init code and call outputs longer than [`TxInspector::max_input_capture`]
+/// or [`TxInspector::max_output_capture`] are stored cut to that length, with their full
+/// length and hash in `input_truncated` / `output_truncated` of the frame. Otherwise a
+/// contract returning megabytes from thousands of subcalls would make the trace grow to
+/// gigabytes. The output of the transaction in its `ExecutionResult` is not affected.
+///
+/// # Hash Preimages
+///
+/// With [mapping resolution](TxInspector::with_mapping_resolution) enabled, full traces
+/// record the inputs of KECCAK256 operations of 32 to [`MAX_PREIMAGE_LEN`] bytes by
+/// their hash. Solidity derives the slots of mapping entries and dynamic array
+/// elements this way, so the preimages map such slots back to their key and base slot
+/// (see `layout::StorageLayout`, feature = "storage-layout"). At most
+/// [`TxInspector::max_preimages`] are kept per transaction, the least recently hashed
+/// being dropped first.
+///
+/// # Cancellation
+///
+/// During a batch with a [`CancellationToken`], the token is checked every
+/// [`TxInspector::cancellation_interval`] opcodes. Once it is cancelled, the running
+/// transaction reverts at its next opcode, so a long loop stops within that many
+/// steps instead of running out of gas.
+///
 /// # Thread Safety
 ///
 /// This inspector is designed to be Send + Sync safe for multi-threaded usage.
 /// All internal state uses thread-safe types or types that can be safely
 /// moved between threads.
-#[derive(Default, Clone)]
+#[derive(Clone)]
 pub struct TxInspector {
     /// Chronological record of all asset transfers during execution
     transfers: Vec<TokenTransfer>,
@@ -65,12 +127,103 @@ pub struct TxInspector {
     address_stack: Vec<Address>,
     /// Stack of pending contract creation transfers
     ///
-    /// Tracks (transfer_index, transfer) pairs for each level of contract creation
-    /// to properly handle nested contract creations. Operates in parallel with
-    /// call_stack to maintain proper creation context.
-    pending_create_transfers: Vec<(usize, TokenTransfer)>,
+    /// Holds one entry per open contract creation, with the indices of its value
+    /// transfer in `transfers` and of its record in `creation_funding` (None for
+    /// creations without value), so each creation resolves its own transfer however
+    /// creations nest.
+    pending_create_transfers: Vec<Option<(usize, usize)>>,
     /// Cache for storage slot values to avoid redundant database queries
     slot_cache: HashMap<(Address, U256), U256>,
+    /// Contract creations and self-destructs in execution order
+    contract_changes: Vec<ContractChange>,
+    /// Value-bearing calls to the block coinbase in execution order
+    coinbase_payments: Vec<CoinbasePayment>,
+    /// Value-bearing contract creations in the order they started
+    creation_funding: Vec<CreationFunding>,
+    /// Amount of data collected for the current transaction
+    trace_level: TraceLevel,
+    /// Maximum number of nested frames recorded in the call tree
+    max_trace_depth: usize,
+    /// Number of currently open frames beyond `max_trace_depth`
+    truncated_calls: usize,
+    /// Previous `slot_cache` entries overwritten by SSTOREs of the current transaction
+    slot_journal: Vec<((Address, U256), Option<U256>)>,
+    /// `slot_journal` length at the start of each open frame, collapsed ones included
+    slot_checkpoints: Vec<usize>,
+    /// Selection of the logs to store, if not all
+    log_filter: Option<LogFilter>,
+    /// Refund counter before the SSTORE being executed
+    refund_before_sstore: Option<i64>,
+    /// Diagnoses of the current transaction found before execution
+    warnings: Vec<SimulationWarning>,
+    /// Logs of the current transaction that could not be decoded
+    parse_warnings: Vec<ParseWarning>,
+    /// Accounts and storage slots loaded by the last transaction
+    accessed_state: BTreeMap<Address, BTreeSet<U256>>,
+    /// Maximum number of call input and init code bytes stored per frame
+    max_input_capture: usize,
+    /// Maximum number of call output bytes stored per frame
+    max_output_capture: usize,
+    /// Whether KECCAK256 inputs are recorded
+    mapping_resolution: bool,
+    /// Maximum number of KECCAK256 inputs kept per transaction
+    max_preimages: usize,
+    /// KECCAK256 inputs of the current transaction by hash
+    keccak_preimages: PreimageLru,
+    /// Input of the KECCAK256 being executed, if recorded
+    pending_preimage: Option<Bytes>,
+    /// Mutations of the current transaction for the batch timeline
+    timeline: TimelineLog,
+    /// Storage slots the current transaction accessed so far, for EIP-2929 cold access
+    /// classification
+    warm_slots: HashSet<(Address, U256)>,
+    /// Cancellation token of the batch being executed
+    cancellation: Option<CancellationToken>,
+    /// Number of opcodes executed between two checks of `cancellation`
+    cancellation_interval: u64,
+    /// Opcodes left until `cancellation` is checked again
+    steps_to_cancellation_check: u64,
+    /// Whether the current transaction was halted by `cancellation`
+    cancelled: bool,
+}
+
+impl Default for TxInspector {
+    fn default() -> Self {
+        Self {
+            transfers: Vec::new(),
+            call_traces: Vec::new(),
+            logs: Vec::new(),
+            call_stack: Vec::new(),
+            address_stack: Vec::new(),
+            pending_create_transfers: Vec::new(),
+            slot_cache: HashMap::new(),
+            contract_changes: Vec::new(),
+            coinbase_payments: Vec::new(),
+            creation_funding: Vec::new(),
+            trace_level: TraceLevel::default(),
+            max_trace_depth: DEFAULT_MAX_TRACE_DEPTH,
+            truncated_calls: 0,
+            slot_journal: Vec::new(),
+            slot_checkpoints: Vec::new(),
+            log_filter: None,
+            refund_before_sstore: None,
+            warnings: Vec::new(),
+            parse_warnings: Vec::new(),
+            accessed_state: BTreeMap::new(),
+            max_input_capture: DEFAULT_MAX_CAPTURE,
+            max_output_capture: DEFAULT_MAX_CAPTURE,
+            mapping_resolution: false,
+            max_preimages: DEFAULT_MAX_PREIMAGES,
+            keccak_preimages: PreimageLru::default(),
+            pending_preimage: None,
+            timeline: TimelineLog::default(),
+            warm_slots: HashSet::new(),
+            cancellation: None,
+            cancellation_interval: DEFAULT_CANCELLATION_INTERVAL,
+            steps_to_cancellation_check: 0,
+            cancelled: false,
+        }
+    }
 }
 
 // The explicit implementation of Send and Sync ensures thread safety.
@@ -84,16 +237,116 @@ unsafe impl Sync for TxInspector {}
 /// - Complete call hierarchy
 /// - Event logs
 /// - Error location if execution failed
-#[derive(Debug, Clone, Serialize)]
+///
+/// Implements `Deserialize` so stored traces can be loaded back for re-analysis;
+/// fields missing from older JSON take their default values.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TxTraceOutput {
     /// All asset transfers (ETH and tokens) during execution
     pub asset_transfers: Vec<TokenTransfer>,
     /// Complete hierarchical call tree
     pub call_trace: Option<CallTrace>,
-    /// All emitted event logs
+    /// Emitted event logs, only those passing the log filter if `logs_filtered`
+    ///
+    /// Each log serializes with its `log_index` in this list.
+    #[serde(serialize_with = "indexed_logs::serialize")]
     pub logs: Vec<Log>,
+    /// Whether a log filter was active, so `logs` may omit emitted events
+    pub logs_filtered: bool,
     /// Location of the first error in the call tree
     pub error_trace_address: Option<Vec<usize>>,
+    /// Contract creations, self-destructs and code removals
+    pub contract_changes: Vec<ContractChange>,
+    /// Direct payments to the block coinbase
+    pub coinbase_payments: Vec<CoinbasePayment>,
+    /// Native value sent with contract creations, and where it ended up
+    pub creation_funding: Vec<CreationFunding>,
+    /// Net storage writes of each frame whose effects persisted, keyed by trace address
+    #[serde(with = "frame_diffs_serde")]
+    pub frame_diffs: FrameDiffs,
+    /// Net storage writes of frames that reverted themselves or inside a reverting
+    /// ancestor; rolled back, but kept for forensics
+    #[serde(with = "frame_diffs_serde")]
+    pub reverted_writes: FrameDiffs,
+    /// Amount of data that was collected
+    pub trace_level: TraceLevel,
+    /// Diagnoses found before execution, reported at every trace level
+    pub warnings: Vec<SimulationWarning>,
+    /// Inputs of 32 to `MAX_PREIMAGE_LEN` bytes hashed by KECCAK256, by hash; only
+    /// recorded with mapping resolution enabled
+    #[serde(
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "sorted_map::serialize"
+    )]
+    pub keccak_preimages: HashMap<B256, Bytes>,
+    /// Trace addresses of the frames that read `tx.origin`, in call order
+    ///
+    /// Gating logic on `tx.origin` lets a contract the user calls act on the user's
+    /// behalf elsewhere, a common phishing vector.
+    pub tx_origin_usages: Vec<Vec<usize>>,
+    /// Failed frames that would have transferred native value, in call order
+    ///
+    /// A failed `send()` or low-level value call does not revert its caller, so the
+    /// transaction can succeed although the value never arrived.
+    pub failed_value_transfers: Vec<FailedValueTransfer>,
+    /// Logs that looked like token transfers but could not be decoded, so no
+    /// transfer was recorded for them
+    pub parse_warnings: Vec<ParseWarning>,
+    /// Accounts the transaction loaded, with the storage slots it read or wrote
+    ///
+    /// Taken from the final execution state, so it is reported at every trace level
+    /// and includes accounts only read for their balance, nonce or code (with no
+    /// slots). See [`TxTraceOutput::proof_requests`].
+    pub accessed_state: BTreeMap<Address, BTreeSet<U256>>,
+    /// Accounts whose code ran, including delegatecalled libraries and proxy
+    /// implementations, see [`CallTrace::executed_code`]
+    ///
+    /// Calls to accounts without code, precompiles and plain value transfers are left
+    /// out. Taken from the call tree, so only reported with `TraceLevel::Full`.
+    #[serde(
+        skip_serializing_if = "HashSet::is_empty",
+        serialize_with = "sorted_set::serialize"
+    )]
+    pub executed_code_addresses: HashSet<Address>,
+}
+
+/// Size-bounded copy of a [`TxTraceOutput`], built by [`TxTraceOutput::pruned`]
+///
+/// Serializes with the field names of `TxTraceOutput`; call frames and logs carry
+/// markers describing what was cut.
+#[derive(Debug, Clone, Serialize)]
+pub struct PrunedTraceOutput {
+    pub asset_transfers: Vec<TokenTransfer>,
+    pub call_trace: Option<PrunedCallTrace>,
+    pub logs: Vec<PrunedLog>,
+    pub logs_filtered: bool,
+    pub error_trace_address: Option<Vec<usize>>,
+    pub contract_changes: Vec<ContractChange>,
+    pub coinbase_payments: Vec<CoinbasePayment>,
+    pub creation_funding: Vec<CreationFunding>,
+    #[serde(with = "frame_diffs_serde")]
+    pub frame_diffs: FrameDiffs,
+    #[serde(with = "frame_diffs_serde")]
+    pub reverted_writes: FrameDiffs,
+    pub trace_level: TraceLevel,
+    pub warnings: Vec<SimulationWarning>,
+    #[serde(
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "sorted_map::serialize"
+    )]
+    pub keccak_preimages: HashMap<B256, Bytes>,
+    pub tx_origin_usages: Vec<Vec<usize>>,
+    pub failed_value_transfers: Vec<FailedValueTransfer>,
+    pub parse_warnings: Vec<ParseWarning>,
+    pub accessed_state: BTreeMap<Address, BTreeSet<U256>>,
+    #[serde(
+        skip_serializing_if = "HashSet::is_empty",
+        serialize_with = "sorted_set::serialize"
+    )]
+    pub executed_code_addresses: HashSet<Address>,
+    /// Whether anything was cut or left out
+    pub truncated: bool,
 }
 
 impl TxInspector {
@@ -102,6 +355,113 @@ impl TxInspector {
         Default::default()
     }
 
+    /// Limits the number of nested frames recorded in the call tree
+    ///
+    /// Deeper frames are collapsed into the deepest recorded one (see the type-level
+    /// docs). Bounds memory and keeps consumers of the call tree cheap when tracing
+    /// adversarial, deeply recursive contracts. A depth of 0 is treated as 1.
+    ///
+    /// Defaults to [`DEFAULT_MAX_TRACE_DEPTH`].
+    pub fn with_max_trace_depth(mut self, max_trace_depth: usize) -> Self {
+        self.max_trace_depth = max_trace_depth.max(1);
+        self
+    }
+
+    /// Returns the maximum number of nested frames recorded in the call tree
+    pub fn max_trace_depth(&self) -> usize {
+        self.max_trace_depth
+    }
+
+    /// Limits the number of call input and init code bytes stored per frame
+    ///
+    /// Longer data is cut and described by the frame's `input_truncated` (see the
+    /// type-level docs). Defaults to [`DEFAULT_MAX_CAPTURE`].
+    pub fn with_max_input_capture(mut self, max_input_capture: usize) -> Self {
+        self.max_input_capture = max_input_capture;
+        self
+    }
+
+    /// Returns the maximum number of call input and init code bytes stored per frame
+    pub fn max_input_capture(&self) -> usize {
+        self.max_input_capture
+    }
+
+    /// Limits the number of call output bytes stored per frame
+    ///
+    /// Longer output is cut and described by the frame's `output_truncated`; revert
+    /// reasons are decoded from the full output first. Defaults to
+    /// [`DEFAULT_MAX_CAPTURE`].
+    pub fn with_max_output_capture(mut self, max_output_capture: usize) -> Self {
+        self.max_output_capture = max_output_capture;
+        self
+    }
+
+    /// Returns the maximum number of call output bytes stored per frame
+    pub fn max_output_capture(&self) -> usize {
+        self.max_output_capture
+    }
+
+    /// Records KECCAK256 inputs to resolve mapping keys in storage diffs
+    ///
+    /// Reads the hashed memory region on every KECCAK256, so it is off by default.
+    /// The recorded preimages are reported in `TxTraceOutput::keccak_preimages` and
+    /// label slots like `balances[0x…]` (see the type-level docs).
+    pub fn with_mapping_resolution(mut self, enabled: bool) -> Self {
+        self.mapping_resolution = enabled;
+        self
+    }
+
+    /// Whether KECCAK256 inputs are recorded
+    pub fn mapping_resolution(&self) -> bool {
+        self.mapping_resolution
+    }
+
+    /// Limits the number of KECCAK256 preimages kept per transaction
+    ///
+    /// The least recently hashed input is dropped first. Defaults to
+    /// [`DEFAULT_MAX_PREIMAGES`].
+    pub fn with_max_preimages(mut self, max_preimages: usize) -> Self {
+        self.max_preimages = max_preimages;
+        self
+    }
+
+    /// Returns the maximum number of KECCAK256 preimages kept per transaction
+    pub fn max_preimages(&self) -> usize {
+        self.max_preimages
+    }
+
+    /// Checks the cancellation token every `interval` opcodes
+    ///
+    /// Smaller intervals stop cancelled transactions sooner at the cost of more atomic
+    /// loads; 0 is treated as 1. Defaults to [`DEFAULT_CANCELLATION_INTERVAL`].
+    pub fn with_cancellation_interval(mut self, interval: u64) -> Self {
+        self.cancellation_interval = interval.max(1);
+        self
+    }
+
+    /// Returns the number of opcodes executed between two checks of the cancellation
+    /// token
+    pub fn cancellation_interval(&self) -> u64 {
+        self.cancellation_interval
+    }
+
+    /// Stores only the event logs passing `filter`
+    ///
+    /// Other logs are neither cloned nor stored, which saves memory when only a few
+    /// contracts or events matter. Transfer tracking still sees every log, so token
+    /// transfers are complete. `log_indices` of call frames index into the filtered
+    /// list, and the output reports `logs_filtered` so that missing events are not
+    /// mistaken for events that never happened.
+    pub fn with_log_filter(mut self, filter: LogFilter) -> Self {
+        self.log_filter = Some(filter);
+        self
+    }
+
+    /// Returns the active log filter, if any
+    pub fn log_filter(&self) -> Option<&LogFilter> {
+        self.log_filter.as_ref()
+    }
+
     /// Returns all recorded asset transfers in chronological order
     ///
     /// Includes both ETH transfers and ERC20 token transfers
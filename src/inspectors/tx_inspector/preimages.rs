@@ -0,0 +1,70 @@
+//! Bounded record of KECCAK256 preimages
+//!
+//! Contracts hashing in a loop would otherwise make the record grow without limit, so
+//! entries beyond the capacity evict the one hashed least recently.
+
+use alloy::primitives::{Bytes, B256};
+use std::collections::{BTreeMap, HashMap};
+
+/// KECCAK256 inputs by hash, evicting the least recently hashed entry when full
+#[derive(Debug, Clone, Default)]
+pub(super) struct PreimageLru {
+    /// Input and last use of each hash
+    entries: HashMap<B256, (Bytes, u64)>,
+    /// Hashes by last use
+    order: BTreeMap<u64, B256>,
+    /// Counter ordering the uses
+    tick: u64,
+}
+
+impl PreimageLru {
+    /// Records `preimage` as the input of `hash`, keeping at most `capacity` entries
+    pub(super) fn insert(&mut self, hash: B256, preimage: Bytes, capacity: usize) {
+        if capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+        if let Some((_, used)) = self.entries.insert(hash, (preimage, self.tick)) {
+            self.order.remove(&used);
+        }
+        self.order.insert(self.tick, hash);
+        while self.entries.len() > capacity {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+
+    /// Returns the recorded preimages by hash
+    pub(super) fn to_map(&self) -> HashMap<B256, Bytes> {
+        self.entries
+            .iter()
+            .map(|(hash, (preimage, _))| (*hash, preimage.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_least_recently_hashed_is_evicted() {
+        let hash = |byte| B256::repeat_byte(byte);
+        let mut lru = PreimageLru::default();
+        lru.insert(hash(1), Bytes::from_static(b"a"), 2);
+        lru.insert(hash(2), Bytes::from_static(b"b"), 2);
+        // Hashing the first input again makes the second one the oldest
+        lru.insert(hash(1), Bytes::from_static(b"a"), 2);
+        lru.insert(hash(3), Bytes::from_static(b"c"), 2);
+
+        let map = lru.to_map();
+        assert_eq!(map.len(), 2);
+        assert!(map.contains_key(&hash(1)));
+        assert!(map.contains_key(&hash(3)));
+
+        lru.insert(hash(4), Bytes::from_static(b"d"), 0);
+        assert_eq!(lru.to_map().len(), 2);
+    }
+}
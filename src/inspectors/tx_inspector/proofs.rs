@@ -0,0 +1,27 @@
+//! State proof requests
+//!
+//! Witness data for offline verification proves every piece of state a transaction
+//! read against the block's state root. [`TxTraceOutput::proof_requests`] turns the
+//! accounts and slots the transaction accessed into `eth_getProof` requests, which
+//! [`fetch_proofs`](crate::verify::fetch_proofs) answers.
+
+use crate::inspectors::tx_inspector::TxTraceOutput;
+use crate::types::ProofRequest;
+use alloy::primitives::B256;
+
+impl TxTraceOutput {
+    /// Returns one proof request per accessed account, ordered by address
+    ///
+    /// Storage keys are the slots the transaction read or wrote, sorted; accounts only
+    /// read for their balance, nonce or code get no keys. Accounts the protocol loads,
+    /// like the sender and the block beneficiary, are included as well.
+    pub fn proof_requests(&self) -> Vec<ProofRequest> {
+        self.accessed_state
+            .iter()
+            .map(|(address, slots)| ProofRequest {
+                address: *address,
+                storage_keys: slots.iter().map(|slot| B256::from(*slot)).collect(),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,271 @@
+//! Size-bounded trace copies
+//!
+//! Traces of heavy transactions can reach tens of megabytes through large calldata
+//! blobs and deep or wide call trees. [`TxTraceOutput::pruned`] produces a copy within a
+//! [`PruneBudget`] for serving over an API. Nothing is cut silently: truncated byte
+//! fields keep the length and hash of the full data, and left out frames are counted on
+//! their parent.
+
+use crate::inspectors::tx_inspector::{PrunedTraceOutput, TxTraceOutput};
+use crate::types::{
+    CallScheme, CallTrace, OmittedFrames, PruneBudget, PrunedCallTrace, PrunedLog, TruncatedBytes,
+};
+use alloy::primitives::{keccak256, Bytes};
+
+impl TxTraceOutput {
+    /// Returns a copy of the trace cut down to `budget`
+    ///
+    /// - Call inputs, call outputs and log data longer than `max_bytes_per_field` keep
+    ///   their first bytes plus a [`TruncatedBytes`] marker
+    /// - Subcalls of frames at `max_depth` are replaced by an [`OmittedFrames`] summary
+    /// - Frames keep at most `max_children_per_frame` subcalls, the first ones in
+    ///   execution order; the rest are counted in the summary
+    /// - With `drop_static_calls`, STATICCALL subtrees are counted instead of kept
+    ///
+    /// Transfers, contract changes and frame diffs are kept as they are. The result only
+    /// depends on the trace and the budget.
+    pub fn pruned(&self, budget: PruneBudget) -> PrunedTraceOutput {
+        let mut truncated = false;
+        let call_trace = self
+            .call_trace
+            .as_ref()
+            .map(|root| prune_frame(root, &budget, 0, &mut truncated));
+        let logs = self
+            .logs
+            .iter()
+            .enumerate()
+            .map(|(log_index, log)| {
+                let (data, data_truncated) =
+                    prune_bytes(&log.data.data, budget.max_bytes_per_field);
+                truncated |= data_truncated.is_some();
+                PrunedLog {
+                    log_index,
+                    address: log.address,
+                    topics: log.topics().to_vec(),
+                    data,
+                    data_truncated,
+                }
+            })
+            .collect();
+
+        PrunedTraceOutput {
+            asset_transfers: self.asset_transfers.clone(),
+            call_trace,
+            logs,
+            logs_filtered: self.logs_filtered,
+            error_trace_address: self.error_trace_address.clone(),
+            contract_changes: self.contract_changes.clone(),
+            coinbase_payments: self.coinbase_payments.clone(),
+            creation_funding: self.creation_funding.clone(),
+            frame_diffs: self.frame_diffs.clone(),
+            reverted_writes: self.reverted_writes.clone(),
+            trace_level: self.trace_level,
+            warnings: self.warnings.clone(),
+            keccak_preimages: self.keccak_preimages.clone(),
+            tx_origin_usages: self.tx_origin_usages.clone(),
+            failed_value_transfers: self.failed_value_transfers.clone(),
+            parse_warnings: self.parse_warnings.clone(),
+            accessed_state: self.accessed_state.clone(),
+            executed_code_addresses: self.executed_code_addresses.clone(),
+            truncated,
+        }
+    }
+}
+
+/// Prunes one frame; recursion is bounded by `budget.max_depth`
+fn prune_frame(
+    frame: &CallTrace,
+    budget: &PruneBudget,
+    depth: usize,
+    truncated: &mut bool,
+) -> PrunedCallTrace {
+    // Markers set while tracing describe the full data, not the captured prefix
+    let (input, input_truncated) = prune_bytes(&frame.input, budget.max_bytes_per_field);
+    let input_truncated = frame.input_truncated.or(input_truncated);
+    let (output, output_truncated) = prune_bytes(&frame.output, budget.max_bytes_per_field);
+    let output_truncated = frame.output_truncated.or(output_truncated);
+    *truncated |= input_truncated.is_some() || output_truncated.is_some();
+
+    let mut subtraces = Vec::new();
+    let mut omitted = OmittedFrames::default();
+    for child in &frame.subtraces {
+        let dropped = budget.drop_static_calls && child.call_scheme == Some(CallScheme::StaticCall);
+        if dropped || depth >= budget.max_depth || subtraces.len() >= budget.max_children_per_frame
+        {
+            omitted.subtraces += 1;
+            for nested in child.frames() {
+                omitted.frames += 1;
+                omitted.failed += usize::from(!nested.status.is_success());
+            }
+            continue;
+        }
+        subtraces.push(prune_frame(child, budget, depth + 1, truncated));
+    }
+    let omitted_subtraces = (omitted.subtraces > 0).then_some(omitted);
+    *truncated |= omitted_subtraces.is_some();
+
+    PrunedCallTrace {
+        from: frame.from,
+        to: frame.to,
+        value: frame.value,
+        input,
+        input_truncated,
+        call_scheme: frame.call_scheme,
+        create_scheme: frame.create_scheme,
+        gas_limit: frame.gas_limit,
+        gas_used: frame.gas_used,
+        output,
+        output_truncated,
+        status: frame.status.clone(),
+        failure_kind: frame.failure_kind,
+        error_origin: frame.error_origin,
+        subtraces,
+        omitted_subtraces,
+        trace_address: frame.trace_address.clone(),
+        frame_id: frame.frame_id.clone(),
+        slot_access_count: frame.slot_accesses.len(),
+        refund_counter_delta: frame.refund_counter_delta,
+        log_indices: frame.log_indices.clone(),
+        depth: frame.depth,
+        truncated: frame.truncated,
+        target_kind: frame.target_kind,
+        used_tx_origin: frame.used_tx_origin,
+        tx_origin_reads: frame.tx_origin_reads,
+        facet_route: frame.facet_route,
+        code_address: frame.code_address,
+        opcodes_executed: frame.opcodes_executed,
+        resource_stats: frame.resource_stats,
+    }
+}
+
+/// Cuts `data` to `max_len` bytes, describing the full data if anything was cut
+fn prune_bytes(data: &Bytes, max_len: usize) -> (Bytes, Option<TruncatedBytes>) {
+    if data.len() <= max_len {
+        return (data.clone(), None);
+    }
+    (
+        data.slice(..max_len),
+        Some(TruncatedBytes {
+            len: data.len(),
+            keccak256: keccak256(data),
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::CallStatus;
+    use alloy::primitives::{Address, Log, LogData, B256};
+
+    /// Trace with a 1 MB calldata blob, 2000 direct subcalls and a 300-deep chain
+    fn oversized_trace() -> TxTraceOutput {
+        let frame = |trace_address: Vec<usize>, scheme: CallScheme| CallTrace {
+            from: Address::repeat_byte(1),
+            to: Address::repeat_byte(2),
+            input: vec![0xab; 256].into(),
+            output: vec![0xcd; 64].into(),
+            call_scheme: Some(scheme),
+            depth: trace_address.len(),
+            trace_address,
+            status: CallStatus::Success,
+            ..Default::default()
+        };
+        let mut root = frame(vec![], CallScheme::Call);
+        root.input = vec![0x11; 1 << 20].into();
+        for i in 0..2000 {
+            let scheme = if i % 2 == 0 {
+                CallScheme::StaticCall
+            } else {
+                CallScheme::Call
+            };
+            root.subtraces.push(frame(vec![i], scheme));
+        }
+        let mut chain = frame(vec![2000; 300], CallScheme::Call);
+        for depth in (1..300).rev() {
+            let mut parent = frame(vec![2000; depth], CallScheme::Call);
+            parent.subtraces.push(chain);
+            chain = parent;
+        }
+        root.subtraces.push(chain);
+
+        TxTraceOutput {
+            call_trace: Some(root),
+            logs: vec![Log {
+                address: Address::repeat_byte(2),
+                data: LogData::new_unchecked(vec![B256::ZERO], vec![0xee; 100_000].into()),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_pruned_trace_fits_budget() {
+        let trace = oversized_trace();
+        let full_size = serde_json::to_vec(&trace).unwrap().len();
+        let budget = PruneBudget {
+            max_bytes_per_field: 32,
+            max_depth: 8,
+            max_children_per_frame: 16,
+            drop_static_calls: true,
+        };
+
+        let pruned = trace.pruned(budget);
+        let json = serde_json::to_vec(&pruned).unwrap();
+        assert!(full_size > 2_000_000);
+        assert!(json.len() < 64 * 1024, "pruned size {}", json.len());
+        assert_eq!(json, serde_json::to_vec(&trace.pruned(budget)).unwrap());
+        assert!(pruned.truncated);
+
+        let root = pruned.call_trace.unwrap();
+        let input = root.input_truncated.unwrap();
+        assert_eq!(root.input.len(), 32);
+        assert_eq!(input.len, 1 << 20);
+        assert_eq!(
+            input.keccak256,
+            keccak256(&trace.call_trace.as_ref().unwrap().input)
+        );
+        assert_eq!(pruned.logs[0].data_truncated.unwrap().len, 100_000);
+
+        // 1000 static calls dropped, 16 of the remaining 1001 frames kept
+        let omitted = root.omitted_subtraces.unwrap();
+        assert_eq!(root.subtraces.len(), 16);
+        assert_eq!(omitted.subtraces, 2001 - 16);
+        assert!(root
+            .subtraces
+            .iter()
+            .all(|child| child.call_scheme == Some(CallScheme::Call)));
+        // The deep chain is not among the kept children
+        assert_eq!(omitted.frames, 2001 - 16 + 299);
+    }
+
+    #[test]
+    fn test_pruned_depth_summary() {
+        let trace = oversized_trace();
+        let budget = PruneBudget {
+            max_bytes_per_field: 1024,
+            max_depth: 5,
+            max_children_per_frame: usize::MAX,
+            drop_static_calls: false,
+        };
+        let root = trace.pruned(budget).call_trace.unwrap();
+        assert!(root.omitted_subtraces.is_none());
+        assert!(root.input_truncated.is_some());
+
+        let mut frame = root.subtraces.last().unwrap();
+        while let Some(child) = frame.subtraces.first() {
+            frame = child;
+        }
+        assert_eq!(frame.depth, 5);
+        let omitted = frame.omitted_subtraces.unwrap();
+        assert_eq!((omitted.subtraces, omitted.frames), (1, 300 - 5));
+        assert!(frame.input_truncated.is_none());
+
+        // Within budget nothing is marked
+        let small = TxTraceOutput {
+            call_trace: Some(CallTrace::default()),
+            ..Default::default()
+        };
+        assert!(!small.pruned(PruneBudget::default()).truncated);
+    }
+}
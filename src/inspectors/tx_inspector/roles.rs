@@ -0,0 +1,334 @@
+//! Chain-aware transfer classification
+//!
+//! Tags recorded transfers with their accounting role using a [`ChainPreset`]: gas fee
+//! debits and credits in fee currencies, and conversions between the native token and
+//! its wrapped representation. Classification is pure post-processing, so the same
+//! trace can be classified for any chain after execution.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::chains::ChainPreset;
+use crate::inspectors::tx_inspector::TxTraceOutput;
+use crate::types::{TokenDelta, TokenTransfer, TokenType, TransferRole};
+use alloy::primitives::{Address, I256, U256};
+
+impl TxTraceOutput {
+    /// Tags every asset transfer of a transaction sent by `sender` with its role on the
+    /// given chain
+    ///
+    /// - `FeePayment`: the gas fee debit and credits of a fee currency. The fee is
+    ///   burned from `sender` before execution, as the first transfer of its token,
+    ///   and minted to the fee recipients (and the refund to `sender`) after it, as the
+    ///   last transfers of the token. Only trailing mints adding up to at most the
+    ///   debited amount count as credits; other mints and burns of fee currencies,
+    ///   such as stablecoins minted by an exchange, stay `Normal`
+    /// - `Wrap`: native value sent to the wrapper, or the wrapper minting tokens
+    /// - `Unwrap`: native value sent by the wrapper, or the wrapper burning tokens
+    /// - `Normal`: everything else
+    ///
+    /// # Example
+    /// ```
+    /// use alloy::primitives::Address;
+    /// use revm_trace::{chains::ChainPreset, inspectors::tx_inspector::TxTraceOutput};
+    ///
+    /// let mut output = TxTraceOutput::default();
+    /// if let Some(preset) = ChainPreset::from_chain_id(42220) {
+    ///     output.classify_transfers(&preset, Address::ZERO);
+    /// }
+    /// ```
+    pub fn classify_transfers(&mut self, preset: &ChainPreset, sender: Address) {
+        for transfer in &mut self.asset_transfers {
+            transfer.role = transfer_role(transfer, preset);
+        }
+        for index in fee_payments(&self.asset_transfers, preset, sender) {
+            self.asset_transfers[index].role = TransferRole::FeePayment;
+        }
+    }
+
+    /// Returns the net balance change per holder implied by native and ERC20 transfers
+    ///
+    /// With `exclude_fee_payments`, transfers classified as `FeePayment` are left out,
+    /// so gas fees paid in a fee currency do not count as value moved by the
+    /// transaction. Classify the transfers first; unclassified transfers are all
+    /// `Normal`. Holders whose transfers cancel out and the zero address (mints and
+    /// burns) are omitted. Deltas are ordered by first appearance.
+    pub fn net_token_deltas(&self, exclude_fee_payments: bool) -> Vec<TokenDelta> {
+        let mut order = Vec::new();
+        let mut deltas: HashMap<(Address, Address), I256> = HashMap::new();
+        for transfer in &self.asset_transfers {
+            if !matches!(transfer.token_type, TokenType::Native | TokenType::ERC20)
+                || (exclude_fee_payments && transfer.role == TransferRole::FeePayment)
+            {
+                continue;
+            }
+            let Some(to) = transfer.to else {
+                continue;
+            };
+            let value = I256::try_from(transfer.value).unwrap_or(I256::MAX);
+            for (holder, delta) in [(transfer.from, -value), (to, value)] {
+                if holder == Address::ZERO {
+                    continue;
+                }
+                let key = (transfer.token, holder);
+                let entry = deltas.entry(key).or_insert_with(|| {
+                    order.push(key);
+                    I256::ZERO
+                });
+                *entry = entry.saturating_add(delta);
+            }
+        }
+        order
+            .into_iter()
+            .filter_map(|(token, holder)| {
+                let delta = deltas[&(token, holder)];
+                (!delta.is_zero()).then_some(TokenDelta {
+                    token,
+                    holder,
+                    delta,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Determines the role of a single transfer, gas fee payments aside
+fn transfer_role(transfer: &TokenTransfer, preset: &ChainPreset) -> TransferRole {
+    match transfer.token_type {
+        TokenType::Native => {
+            if transfer.to == Some(preset.native_wrapped) {
+                TransferRole::Wrap
+            } else if transfer.from == preset.native_wrapped {
+                TransferRole::Unwrap
+            } else {
+                TransferRole::Normal
+            }
+        }
+        TokenType::ERC20 => {
+            let minted = transfer.from == Address::ZERO;
+            let burned = transfer.to == Some(Address::ZERO);
+            if transfer.token == preset.native_wrapped && minted {
+                TransferRole::Wrap
+            } else if transfer.token == preset.native_wrapped && burned {
+                TransferRole::Unwrap
+            } else {
+                TransferRole::Normal
+            }
+        }
+        _ => TransferRole::Normal,
+    }
+}
+
+/// Returns the indices of the transfers debiting and crediting gas fees in fee
+/// currencies (see [`TxTraceOutput::classify_transfers`])
+fn fee_payments(transfers: &[TokenTransfer], preset: &ChainPreset, sender: Address) -> Vec<usize> {
+    let mut seen = HashSet::new();
+    let mut indices = Vec::new();
+    for (debit_index, debit) in transfers.iter().enumerate() {
+        if debit.token_type != TokenType::ERC20 || !seen.insert(debit.token) {
+            continue;
+        }
+        if debit.from != sender
+            || debit.to != Some(Address::ZERO)
+            || !preset.is_fee_currency(debit.token)
+        {
+            continue;
+        }
+        let mut credited = U256::ZERO;
+        let mut credits = Vec::new();
+        for (index, credit) in transfers.iter().enumerate().skip(debit_index + 1).rev() {
+            if credit.token_type != TokenType::ERC20 || credit.token != debit.token {
+                continue;
+            }
+            if credit.from != Address::ZERO {
+                break;
+            }
+            credited = credited.saturating_add(credit.value);
+            if credited > debit.value {
+                break;
+            }
+            credits.push(index);
+        }
+        // A burn without credits is a plain burn, not a fee debit
+        if !credits.is_empty() {
+            indices.push(debit_index);
+            indices.extend(credits);
+        }
+    }
+    indices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ERC20_TRANSFER_EVENT_SIGNATURE, NATIVE_TOKEN_ADDRESS};
+    use alloy::primitives::{address, Log, LogData, B256, U256};
+
+    const CUSD: Address = address!("765DE816845861e75A25fCA122bb6898B8B1282a");
+    const CELO: Address = address!("471EcE3750Da237f93B8E339c536989b8978a438");
+    const USER: Address = address!("1000000000000000000000000000000000000001");
+    const SHOP: Address = address!("2000000000000000000000000000000000000002");
+    const FEE_RECIPIENT: Address = address!("3000000000000000000000000000000000000003");
+    const RESERVE: Address = address!("4000000000000000000000000000000000000004");
+
+    fn transfer_log(token: Address, from: Address, to: Address, value: u64) -> Log {
+        Log {
+            address: token,
+            data: LogData::new_unchecked(
+                vec![
+                    ERC20_TRANSFER_EVENT_SIGNATURE,
+                    B256::left_padding_from(from.as_slice()),
+                    B256::left_padding_from(to.as_slice()),
+                ],
+                U256::from(value).to_be_bytes::<32>().to_vec().into(),
+            ),
+        }
+    }
+
+    fn output(logs: Vec<Log>) -> TxTraceOutput {
+        TxTraceOutput {
+            asset_transfers: logs
+                .iter()
+                .flat_map(TokenTransfer::get_token_transfers)
+                .collect(),
+            logs,
+            ..Default::default()
+        }
+    }
+
+    /// A cUSD payment whose gas fee is paid in cUSD, as emitted on Celo
+    fn celo_output() -> TxTraceOutput {
+        output(vec![
+            // Fee debit before execution
+            transfer_log(CUSD, USER, Address::ZERO, 30),
+            // The payment itself
+            transfer_log(CUSD, USER, SHOP, 1_000),
+            // Fee credit to the recipient after execution
+            transfer_log(CUSD, Address::ZERO, FEE_RECIPIENT, 30),
+        ])
+    }
+
+    fn delta(token: Address, holder: Address, value: i64) -> TokenDelta {
+        TokenDelta {
+            token,
+            holder,
+            delta: I256::try_from(value).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_celo_fee_payment_is_tagged() {
+        let mut output = celo_output();
+        assert_eq!(output.asset_transfers.len(), 3);
+        output.classify_transfers(&ChainPreset::from_chain_id(42220).unwrap(), USER);
+        let roles: Vec<_> = output.asset_transfers.iter().map(|t| t.role).collect();
+        assert_eq!(
+            roles,
+            vec![
+                TransferRole::FeePayment,
+                TransferRole::Normal,
+                TransferRole::FeePayment
+            ]
+        );
+
+        assert_eq!(
+            output.net_token_deltas(true),
+            vec![delta(CUSD, USER, -1_000), delta(CUSD, SHOP, 1_000)]
+        );
+        assert_eq!(
+            output.net_token_deltas(false),
+            vec![
+                delta(CUSD, USER, -1_030),
+                delta(CUSD, SHOP, 1_000),
+                delta(CUSD, FEE_RECIPIENT, 30)
+            ]
+        );
+    }
+
+    fn roles(output: &TxTraceOutput) -> Vec<TransferRole> {
+        output.asset_transfers.iter().map(|t| t.role).collect()
+    }
+
+    #[test]
+    fn test_fee_currency_mints_and_burns_are_not_fees() {
+        let preset = ChainPreset::from_chain_id(42220).unwrap();
+        // A Mento swap paid with native CELO: the broker mints cUSD to the user
+        let mut mint = output(vec![
+            transfer_log(CELO, USER, RESERVE, 2_000),
+            transfer_log(CUSD, Address::ZERO, USER, 1_000),
+        ]);
+        mint.classify_transfers(&preset, USER);
+        assert_eq!(roles(&mint), vec![TransferRole::Normal; 2]);
+
+        // The reverse swap burns the user's cUSD, and nothing is minted back
+        let mut burn = output(vec![
+            transfer_log(CUSD, USER, Address::ZERO, 1_000),
+            transfer_log(CELO, RESERVE, USER, 2_000),
+        ]);
+        burn.classify_transfers(&preset, USER);
+        assert_eq!(roles(&burn), vec![TransferRole::Normal; 2]);
+
+        // A burn from another account is not the sender's fee debit
+        let mut other = celo_output();
+        other.classify_transfers(&preset, SHOP);
+        assert_eq!(roles(&other), vec![TransferRole::Normal; 3]);
+    }
+
+    #[test]
+    fn test_mint_during_execution_is_not_a_fee_credit() {
+        // Gas paid in cUSD by a transaction that also mints cUSD through Mento
+        let mut output = output(vec![
+            transfer_log(CUSD, USER, Address::ZERO, 30),
+            transfer_log(CUSD, Address::ZERO, USER, 1_000),
+            transfer_log(CUSD, Address::ZERO, USER, 10),
+            transfer_log(CUSD, Address::ZERO, FEE_RECIPIENT, 20),
+        ]);
+        output.classify_transfers(&ChainPreset::from_chain_id(42220).unwrap(), USER);
+        assert_eq!(
+            roles(&output),
+            vec![
+                TransferRole::FeePayment,
+                TransferRole::Normal,
+                TransferRole::FeePayment,
+                TransferRole::FeePayment
+            ]
+        );
+    }
+
+    #[test]
+    fn test_same_trace_on_chain_without_fee_currencies() {
+        let mut output = celo_output();
+        output.classify_transfers(&ChainPreset::from_chain_id(1).unwrap(), USER);
+        assert!(output
+            .asset_transfers
+            .iter()
+            .all(|t| t.role == TransferRole::Normal));
+    }
+
+    #[test]
+    fn test_wrap_and_unwrap() {
+        let preset = ChainPreset::from_chain_id(42220).unwrap();
+        let native = |from: Address, to: Address| TokenTransfer {
+            token: NATIVE_TOKEN_ADDRESS,
+            from,
+            to: Some(to),
+            value: U256::from(1),
+            token_type: TokenType::Native,
+            id: None,
+            role: TransferRole::Normal,
+            transfer_index: 0,
+            failed: false,
+        };
+        assert_eq!(
+            transfer_role(&native(USER, CELO), &preset),
+            TransferRole::Wrap
+        );
+        assert_eq!(
+            transfer_role(&native(CELO, USER), &preset),
+            TransferRole::Unwrap
+        );
+        assert_eq!(
+            transfer_role(&native(USER, SHOP), &preset),
+            TransferRole::Normal
+        );
+    }
+}
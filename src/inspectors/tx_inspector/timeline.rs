@@ -0,0 +1,111 @@
+//! Per-transaction mutation log feeding batch timelines
+//!
+//! Events are numbered in capture order; `trace_transactions` renumbers them into the
+//! batch-wide [`BatchTimeline`](crate::evm::timeline::BatchTimeline).
+
+use crate::evm::timeline::{TimelineEvent, TimelineEventKind};
+use crate::types::TokenTransfer;
+use alloy::primitives::{Address, U256};
+
+/// Mutations of the current transaction, recorded only when enabled
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TimelineLog {
+    /// Whether mutations are recorded; kept across transactions
+    enabled: bool,
+    events: Vec<TimelineEvent>,
+    /// `events` length at the start of each open frame, collapsed ones included
+    checkpoints: Vec<usize>,
+    /// Positions of the creation event and funding transfer event of each open
+    /// contract creation
+    creations: Vec<(usize, Option<usize>)>,
+}
+
+impl TimelineLog {
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Drops the recorded events and open frames, keeping the setting
+    pub(crate) fn clear(&mut self) {
+        self.events = Vec::new();
+        self.checkpoints = Vec::new();
+        self.creations = Vec::new();
+    }
+
+    /// Moves the recorded events out
+    pub(crate) fn take(&mut self) -> Vec<TimelineEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Appends a mutation executed by the frame at `trace_address`
+    pub(crate) fn record(&mut self, trace_address: Vec<usize>, kind: TimelineEventKind) -> usize {
+        self.events.push(TimelineEvent {
+            seq: self.events.len() as u64,
+            tx_index: 0,
+            trace_address,
+            reverted: false,
+            kind,
+        });
+        self.events.len() - 1
+    }
+
+    /// Records the start of a contract creation and its funding transfer, if any
+    ///
+    /// The new address is filled in by [`end_creation`](Self::end_creation).
+    pub(crate) fn start_creation(
+        &mut self,
+        trace_address: Vec<usize>,
+        creator: Address,
+        value: U256,
+        funding: Option<TokenTransfer>,
+    ) {
+        let creation = self.record(
+            trace_address.clone(),
+            TimelineEventKind::Creation {
+                creator,
+                created: None,
+                value,
+            },
+        );
+        let transfer = funding
+            .map(|transfer| self.record(trace_address, TimelineEventKind::Transfer(transfer)));
+        self.creations.push((creation, transfer));
+    }
+
+    /// Resolves the innermost open creation with its address, `None` if it failed
+    pub(crate) fn end_creation(&mut self, address: Option<Address>) {
+        let Some((creation, transfer)) = self.creations.pop() else {
+            return;
+        };
+        if let TimelineEventKind::Creation { created, .. } = &mut self.events[creation].kind {
+            *created = address;
+        }
+        if let Some(TimelineEventKind::Transfer(transfer)) =
+            transfer.map(|index| &mut self.events[index].kind)
+        {
+            transfer.to = address;
+            transfer.failed = address.is_none();
+        }
+    }
+
+    /// Opens a frame
+    pub(crate) fn enter_frame(&mut self) {
+        self.checkpoints.push(self.events.len());
+    }
+
+    /// Closes the current frame, flagging its events as reverted if it failed
+    pub(crate) fn exit_frame(&mut self, succeeded: bool) {
+        let Some(checkpoint) = self.checkpoints.pop() else {
+            return;
+        };
+        if !succeeded {
+            for event in &mut self.events[checkpoint..] {
+                event.reverted = true;
+            }
+        }
+    }
+}
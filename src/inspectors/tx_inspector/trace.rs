@@ -10,19 +10,69 @@
 //! call hierarchy, with special handling for error cases to identify
 //! the exact point of failure in complex transactions.
 
-use crate::inspectors::tx_inspector::TxInspector;
+use crate::inspectors::tx_inspector::{TxInspector, TxTraceOutput};
 use revm::context_interface::result::HaltReason;
-use revm::interpreter::{InstructionResult, SuccessOrHalt};
+use revm::interpreter::{gas::CALL_STIPEND, InstructionResult, SuccessOrHalt};
 
 use crate::types::*;
-use crate::utils::error_utils::parse_custom_error;
-use alloy::primitives::{hex, Bytes, U256};
+use alloy::primitives::{keccak256, Bytes, U256};
+
+/// Keeps at most `max_len` bytes of `data`, describing the full data if it is longer
+///
+/// The kept prefix is copied so that the full buffer is not held alive by it.
+pub(super) fn capture(data: Bytes, max_len: usize) -> (Bytes, Option<TruncatedBytes>) {
+    if data.len() <= max_len {
+        return (data, None);
+    }
+    let truncated = TruncatedBytes {
+        len: data.len(),
+        keccak256: keccak256(&data),
+    };
+    (Bytes::copy_from_slice(&data[..max_len]), Some(truncated))
+}
+
+/// Identifies why a frame failed, from its result and the call's value and gas
+///
+/// Out-of-gas failures only count as `StipendOutOfGas` for value-bearing calls that
+/// were given no more than the stipend; other out-of-gas failures and all reverts stay
+/// unclassified.
+fn failure_kind(result: InstructionResult, trace: &CallTrace) -> Option<FrameFailureKind> {
+    match result {
+        InstructionResult::CallTooDeep => Some(FrameFailureKind::CallDepthExceeded),
+        InstructionResult::OutOfFunds => Some(FrameFailureKind::InsufficientBalanceForTransfer),
+        InstructionResult::StateChangeDuringStaticCall
+        | InstructionResult::CallNotAllowedInsideStatic => {
+            Some(FrameFailureKind::StaticCallViolation)
+        }
+        _ => {
+            let out_of_gas = trace.status.is_out_of_gas();
+            let stipend_only = trace.call_scheme.is_some()
+                && trace.transfers_value()
+                && trace.gas_limit <= U256::from(CALL_STIPEND);
+            (out_of_gas && stipend_only).then_some(FrameFailureKind::StipendOutOfGas)
+        }
+    }
+}
+
+impl TxTraceOutput {
+    /// Returns the frame where the error originated, if the transaction failed
+    ///
+    /// Resolves [`error_trace_address`](Self::error_trace_address) against the call
+    /// tree. The frame has `error_origin` set: it failed while all of its own subcalls
+    /// succeeded. A failing top-level call without failing subcalls is its own origin,
+    /// at the empty address.
+    pub fn error_trace(&self) -> Option<&CallTrace> {
+        let trace_address = self.error_trace_address.as_ref()?;
+        self.call_trace.as_ref()?.frame_at(trace_address)
+    }
+}
 
 impl TxInspector {
     /// Locates the trace address of the first error in the call tree
     ///
     /// Returns the position in the call tree where the first error occurred,
-    /// represented as a sequence of indices into the call tree.
+    /// represented as a sequence of indices into the call tree. Reported as
+    /// `error_trace_address` of the output; see [`TxTraceOutput::error_trace`].
     ///
     /// # Returns
     /// * `Some(vec![0,1,2])` - Error occurred in the third child of the second child of the first call
@@ -46,32 +96,52 @@ impl TxInspector {
     /// * `Some(&CallTrace)` - Reference to the trace where the error originated
     /// * `None` - No errors found in the call tree
     pub fn find_error_trace(&self) -> Option<&CallTrace> {
-        fn find_error_recursive(trace: &CallTrace) -> Option<&CallTrace> {
-            let mut last_error = None;
-            for subtrace in &trace.subtraces {
-                if !subtrace.status.is_success() {
-                    if let Some(error) = find_error_recursive(subtrace) {
-                        last_error = Some(error);
-                    }
-                }
-            }
+        fn failed(traces: &[CallTrace]) -> impl Iterator<Item = &CallTrace> {
+            traces.iter().filter(|trace| !trace.status.is_success())
+        }
 
+        // Depth-first through failed frames, latest sibling first, so the first error
+        // origin found lies on the latest failing path. An explicit stack keeps deep
+        // call trees from exhausting the thread stack.
+        let mut stack: Vec<_> = failed(&self.call_traces).collect();
+        while let Some(trace) = stack.pop() {
             if trace.error_origin {
-                Some(trace)
-            } else {
-                last_error
+                return Some(trace);
             }
+            stack.extend(failed(&trace.subtraces));
         }
+        None
+    }
 
-        let mut last_error = None;
-        for trace in &self.call_traces {
-            if !trace.status.is_success() {
-                if let Some(error) = find_error_recursive(trace) {
-                    last_error = Some(error);
-                }
-            }
+    /// Registers a new frame, returning whether it is beyond `max_trace_depth`
+    ///
+    /// A collapsed frame marks the deepest recorded frame as `truncated` and must not
+    /// push a call trace; its matching `handle_end` then only rolls back the slot cache.
+    pub(crate) fn enter_truncated_frame(&mut self) -> bool {
+        self.slot_checkpoints.push(self.slot_journal.len());
+        self.timeline.enter_frame();
+        if self.call_stack.len() < self.max_trace_depth {
+            return false;
         }
-        last_error
+        if let Some(&index) = self.call_stack.last() {
+            self.call_traces[index].truncated = true;
+        }
+        self.truncated_calls += 1;
+        true
+    }
+
+    /// Trace address of a new child of the current frame, or of the current frame
+    /// itself when the new frame is collapsed into it
+    pub(crate) fn next_trace_address(&self, collapsed: bool) -> Vec<usize> {
+        let Some(&parent_index) = self.call_stack.last() else {
+            return Vec::new();
+        };
+        let parent = &self.call_traces[parent_index];
+        let mut trace_address = parent.trace_address.clone();
+        if !collapsed {
+            trace_address.push(parent.subtraces.len());
+        }
+        trace_address
     }
 
     /// Updates call trace with execution results and maintains call hierarchy
@@ -85,36 +155,44 @@ impl TxInspector {
     /// # Arguments
     /// * `result` - Final execution status from the EVM
     /// * `gas_used` - Total gas consumed by the call
-    /// * `output` - Return data or error message
+    /// * `output` - Return data or error message, cloned only into the stored trace
     ///
     /// # Call Tree Management
     /// - Pops the current call from the stack
     /// - Updates its execution details
     /// - Moves it to parent's subtraces if not root
     /// - Marks error origins for failed calls
-    pub fn handle_end(&mut self, result: InstructionResult, gas_used: u64, output: Bytes) {
+    pub fn handle_end(&mut self, result: InstructionResult, gas_used: u64, output: &Bytes) {
+        self.close_slot_checkpoint(result.is_ok());
+        self.timeline.exit_frame(result.is_ok());
+        // Collapsed frames were never pushed
+        if self.truncated_calls > 0 {
+            self.truncated_calls -= 1;
+            return;
+        }
         if let Some(trace_index) = self.call_stack.pop() {
+            let (captured, output_truncated) = capture(output.clone(), self.max_output_capture);
             let trace = &mut self.call_traces[trace_index];
             trace.gas_used = U256::from(gas_used);
-            trace.output = output.clone();
 
             // Convert execution result to call status
             let status = match SuccessOrHalt::<HaltReason>::from(result) {
                 SuccessOrHalt::Success(_) => CallStatus::Success,
-                SuccessOrHalt::Revert => {
-                    if let Some(error_msg) = parse_custom_error(&output) {
-                        CallStatus::Revert(error_msg)
-                    } else {
-                        CallStatus::Revert(format!("0x{}", hex::encode(output)))
-                    }
-                }
-                SuccessOrHalt::Halt(reason) => CallStatus::Halt(format!("{reason:?}")),
+                // Decoded from the full data; the raw data is stored cut like the output
+                SuccessOrHalt::Revert => CallStatus::Revert {
+                    reason: DecodedRevert::decode(output),
+                    raw: captured.clone(),
+                },
+                SuccessOrHalt::Halt(reason) => CallStatus::halt(&reason),
                 SuccessOrHalt::FatalExternalError => CallStatus::FatalError,
                 // Internal state is impossible here as call_end is only called after execution completion
                 SuccessOrHalt::Internal(_) => CallStatus::Success,
             };
 
             trace.status = status;
+            trace.failure_kind = failure_kind(result, trace);
+            trace.output = captured;
+            trace.output_truncated = output_truncated;
 
             // Mark as error origin if this call failed but all subtraces succeeded
             trace.error_origin = !trace.status.is_success()
@@ -130,4 +208,67 @@ impl TxInspector {
             }
         }
     }
+
+    /// Ends the slot cache checkpoint of the current frame
+    ///
+    /// A failed frame's storage writes are rolled back by the EVM, so the cache entries
+    /// they overwrote are restored; later writes then report correct old values.
+    fn close_slot_checkpoint(&mut self, succeeded: bool) {
+        let Some(checkpoint) = self.slot_checkpoints.pop() else {
+            return;
+        };
+        if succeeded {
+            return;
+        }
+        for (key, previous) in self.slot_journal.drain(checkpoint..).rev() {
+            match previous {
+                Some(value) => self.slot_cache.insert(key, value),
+                None => self.slot_cache.remove(&key),
+            };
+        }
+    }
+
+    /// Collects the net storage writes of every frame, split by whether they persisted
+    ///
+    /// Repeated writes to a slot within one frame collapse into the value before the
+    /// first and the value after the last. Writes of frames that failed, or that ran
+    /// inside a failing ancestor, were rolled back and are returned in the second map.
+    pub(crate) fn frame_diffs(&self) -> (FrameDiffs, FrameDiffs) {
+        let mut applied = FrameDiffs::new();
+        let mut reverted = FrameDiffs::new();
+        let mut stack: Vec<(&CallTrace, bool)> = self
+            .call_traces
+            .first()
+            .map(|root| (root, false))
+            .into_iter()
+            .collect();
+        while let Some((frame, ancestor_reverted)) = stack.pop() {
+            let rolled_back = ancestor_reverted || !frame.status.is_success();
+            let mut changes: Vec<SlotChange> = Vec::new();
+            for access in frame.slot_accesses.iter().filter(|access| access.is_write) {
+                let existing = changes
+                    .iter_mut()
+                    .find(|change| change.address == access.address && change.slot == access.slot);
+                match existing {
+                    Some(change) => change.new_value = access.new_value,
+                    None => changes.push(SlotChange {
+                        address: access.address,
+                        slot: access.slot,
+                        old_value: access.old_value,
+                        new_value: access.new_value,
+                    }),
+                }
+            }
+            if !changes.is_empty() {
+                let diffs = if rolled_back {
+                    &mut reverted
+                } else {
+                    &mut applied
+                };
+                diffs.insert(frame.trace_address.clone(), changes);
+            }
+            stack.extend(frame.subtraces.iter().map(|sub| (sub, rolled_back)));
+        }
+        (applied, reverted)
+    }
 }
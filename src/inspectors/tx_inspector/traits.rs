@@ -11,14 +11,27 @@
 //! - `TraceOutput`: Converts collected data into final output format
 //!   - Aggregates all execution data
 //!   - Formats results for external consumption
+//!   - Estimates output size for batch metrics
 //!
 //! These implementations enable the inspector to:
 //! - Maintain clean state between transactions
 //! - Provide standardized output format
 //! - Integrate with the broader tracing system
+use crate::evm::timeline::TimelineEvent;
 use crate::inspectors::tx_inspector::TxInspector;
 use crate::inspectors::tx_inspector::TxTraceOutput;
 use crate::traits::{Reset, TraceOutput};
+use crate::types::{
+    CallTrace, CancellationToken, CoinbasePayment, ContractChange, CreationFunding,
+    FailedValueTransfer, ParseWarning, SimulationWarning, SlotAccess, SlotChange, TokenTransfer,
+    TraceLevel,
+};
+use alloy::primitives::{Address, Bytes, Log, B256, U256};
+use revm::state::EvmState;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    mem::{self, size_of},
+};
 
 impl Reset for TxInspector {
     /// Resets all internal state for processing a new transaction
@@ -29,6 +42,19 @@ impl Reset for TxInspector {
     /// - Event logs
     /// - Call and address stacks
     /// - Pending creation transfers
+    /// - Contract lifecycle changes
+    /// - Coinbase payments
+    /// - Value sent with contract creations
+    /// - Collapsed frame count
+    /// - Pre-execution warnings
+    /// - Hash preimages
+    /// - Accessed state
+    /// - Timeline events
+    /// - Slots accessed so far
+    /// - Cancellation state of the last transaction
+    ///
+    /// The trace level returns to `TraceLevel::Full`; whether the timeline is recorded
+    /// and the cancellation token are kept.
     fn reset(&mut self) {
         self.call_traces = Vec::new();
         self.call_stack = Vec::new();
@@ -36,6 +62,23 @@ impl Reset for TxInspector {
         self.logs = Vec::new();
         self.address_stack = Vec::new();
         self.pending_create_transfers = Vec::new();
+        self.contract_changes = Vec::new();
+        self.coinbase_payments = Vec::new();
+        self.creation_funding = Vec::new();
+        self.truncated_calls = 0;
+        self.slot_journal = Vec::new();
+        self.slot_checkpoints = Vec::new();
+        self.refund_before_sstore = None;
+        self.warnings = Vec::new();
+        self.parse_warnings = Vec::new();
+        self.accessed_state = BTreeMap::new();
+        self.keccak_preimages = Default::default();
+        self.pending_preimage = None;
+        self.timeline.clear();
+        self.warm_slots.clear();
+        self.steps_to_cancellation_check = 0;
+        self.cancelled = false;
+        self.trace_level = TraceLevel::Full;
     }
 
     /// reset the slot cache
@@ -54,12 +97,278 @@ impl TraceOutput for TxInspector {
     /// - Complete call tree
     /// - All event logs
     /// - Error location if any
+    /// - Contract lifecycle changes
+    /// - Coinbase payments
+    /// - Value sent with contract creations
+    /// - Pre-execution warnings
+    /// - Hash preimages
+    /// - Frames reading `tx.origin`
+    /// - Logs that could not be decoded
+    /// - Accounts and slots the transaction accessed
+    /// - Accounts whose code ran
+    ///
+    /// Reduced trace levels only report the collections they cover, plus warnings
+    /// and accessed state.
     fn get_output(&self) -> Self::Output {
-        TxTraceOutput {
-            asset_transfers: self.transfers.clone(),
+        let collected = Collected {
+            transfers: self.transfers.clone(),
             call_trace: self.call_traces.first().cloned(),
             logs: self.logs.clone(),
+            contract_changes: self.contract_changes.clone(),
+            coinbase_payments: self.coinbase_payments.clone(),
+            creation_funding: self.creation_funding.clone(),
+            warnings: self.warnings.clone(),
+            parse_warnings: self.parse_warnings.clone(),
+            accessed_state: self.accessed_state.clone(),
+        };
+        collected.into_output(self.derived_output())
+    }
+
+    /// Moves the collected data into the output instead of cloning it
+    ///
+    /// Yields the same output as [`get_output`](TraceOutput::get_output) and leaves
+    /// the per-transaction collections empty; the slot cache is kept.
+    fn take_output(&mut self) -> Self::Output {
+        // Derived fields read the call tree, so they are computed before it is moved
+        let output = self.derived_output();
+        let collected = Collected {
+            transfers: mem::take(&mut self.transfers),
+            call_trace: mem::take(&mut self.call_traces).into_iter().next(),
+            logs: mem::take(&mut self.logs),
+            contract_changes: mem::take(&mut self.contract_changes),
+            coinbase_payments: mem::take(&mut self.coinbase_payments),
+            creation_funding: mem::take(&mut self.creation_funding),
+            warnings: mem::take(&mut self.warnings),
+            parse_warnings: mem::take(&mut self.parse_warnings),
+            accessed_state: mem::take(&mut self.accessed_state),
+        };
+        collected.into_output(output)
+    }
+
+    /// Reconciles contract lifecycle changes with the final state
+    ///
+    /// - Drops creations that were rolled back by a reverting parent frame
+    /// - Marks self-destructs of contracts created in this transaction
+    /// - Records `CodeCleared` for every account removed by SELFDESTRUCT
+    /// - Records the accounts and slots the transaction accessed
+    fn apply_post_state(&mut self, state: &EvmState) {
+        self.accessed_state = state
+            .iter()
+            .map(|(address, acc)| (*address, acc.storage.keys().copied().collect()))
+            .collect();
+
+        let created = |address: &Address| state.get(address).is_some_and(|acc| acc.is_created());
+        self.contract_changes.retain(|change| match change {
+            ContractChange::Created { address, .. } => created(address),
+            _ => true,
+        });
+        for change in self.contract_changes.iter_mut() {
+            if let ContractChange::SelfDestructed {
+                contract,
+                same_tx_creation,
+                ..
+            } = change
+            {
+                *same_tx_creation |= created(contract);
+            }
+        }
+        let mut cleared: Vec<_> = state
+            .iter()
+            .filter(|(_, acc)| acc.is_selfdestructed())
+            .map(|(address, _)| *address)
+            .collect();
+        cleared.sort();
+        self.contract_changes.extend(
+            cleared
+                .into_iter()
+                .map(|address| ContractChange::CodeCleared { address }),
+        );
+    }
+
+    fn set_trace_level(&mut self, level: TraceLevel) {
+        self.trace_level = level;
+    }
+
+    fn record_warning(&mut self, warning: SimulationWarning) {
+        self.warnings.push(warning);
+    }
+
+    /// Records storage writes (at `TraceLevel::Full`), transfers, creations and
+    /// self-destructs
+    fn set_timeline(&mut self, enabled: bool) {
+        self.timeline.set_enabled(enabled);
+    }
+
+    fn take_timeline(&mut self) -> Vec<TimelineEvent> {
+        self.timeline.take()
+    }
+
+    fn set_cancellation(&mut self, token: Option<CancellationToken>) {
+        self.cancellation = token;
+        self.steps_to_cancellation_check = 0;
+        self.cancelled = false;
+    }
+
+    /// Sums the recorded coinbase payments, which every trace level but `Off` collects
+    fn coinbase_payments(output: &TxTraceOutput) -> Option<U256> {
+        (output.trace_level != TraceLevel::Off).then(|| {
+            output
+                .coinbase_payments
+                .iter()
+                .map(|payment| payment.value)
+                .sum()
+        })
+    }
+
+    /// Sums the inline sizes of all collected records plus their byte payloads
+    fn output_size(output: &TxTraceOutput) -> usize {
+        size_of::<TxTraceOutput>()
+            + output.asset_transfers.len() * size_of::<TokenTransfer>()
+            + output
+                .logs
+                .iter()
+                .map(|log| {
+                    size_of::<Log>() + log.topics().len() * size_of::<B256>() + log.data.data.len()
+                })
+                .sum::<usize>()
+            + output.call_trace.as_ref().map_or(0, call_trace_size)
+            + output
+                .error_trace_address
+                .as_ref()
+                .map_or(0, |address| address.len() * size_of::<usize>())
+            + output.contract_changes.len() * size_of::<ContractChange>()
+            + output.coinbase_payments.len() * size_of::<CoinbasePayment>()
+            + output.creation_funding.len() * size_of::<CreationFunding>()
+            + [&output.frame_diffs, &output.reverted_writes]
+                .into_iter()
+                .flatten()
+                .map(|(trace_address, changes)| {
+                    trace_address.len() * size_of::<usize>()
+                        + changes.len() * size_of::<SlotChange>()
+                })
+                .sum::<usize>()
+            + output
+                .keccak_preimages
+                .values()
+                .map(|preimage| size_of::<B256>() + size_of::<Bytes>() + preimage.len())
+                .sum::<usize>()
+            + output
+                .tx_origin_usages
+                .iter()
+                .map(|trace_address| {
+                    size_of::<Vec<usize>>() + trace_address.len() * size_of::<usize>()
+                })
+                .sum::<usize>()
+            + output
+                .failed_value_transfers
+                .iter()
+                .map(|failed| {
+                    size_of::<FailedValueTransfer>()
+                        + failed.trace_address.len() * size_of::<usize>()
+                })
+                .sum::<usize>()
+    }
+}
+
+/// Estimated size of a call frame and all of its subtraces
+fn call_trace_size(root: &CallTrace) -> usize {
+    root.frames()
+        .map(|trace| {
+            size_of::<CallTrace>()
+                + trace.input.len()
+                + trace.output.len()
+                + trace.trace_address.len() * size_of::<usize>()
+                + trace.slot_accesses.len() * size_of::<SlotAccess>()
+                + trace.log_indices.len() * size_of::<usize>()
+        })
+        .sum()
+}
+
+impl TxInspector {
+    /// Starts an output holding the fields derived from the call tree
+    ///
+    /// Only `TraceLevel::Full` derives anything; the other levels get an empty output
+    /// tagged with their level.
+    fn derived_output(&self) -> TxTraceOutput {
+        if self.trace_level != TraceLevel::Full {
+            return TxTraceOutput {
+                trace_level: self.trace_level,
+                ..Default::default()
+            };
+        }
+        let (frame_diffs, reverted_writes) = self.frame_diffs();
+        TxTraceOutput {
+            logs_filtered: self.log_filter.is_some(),
             error_trace_address: self.get_error_trace_address(),
+            frame_diffs,
+            reverted_writes,
+            trace_level: TraceLevel::Full,
+            keccak_preimages: self.keccak_preimages.to_map(),
+            tx_origin_usages: self
+                .call_traces
+                .iter()
+                .filter(|trace| trace.used_tx_origin)
+                .map(|trace| trace.trace_address.clone())
+                .collect(),
+            failed_value_transfers: self
+                .call_traces
+                .first()
+                .into_iter()
+                .flat_map(CallTrace::frames)
+                .filter(|trace| !trace.status.is_success() && trace.transfers_value())
+                .map(|trace| FailedValueTransfer {
+                    trace_address: trace.trace_address.clone(),
+                    from: trace.from,
+                    to: trace.to,
+                    value: trace.value,
+                    failure_kind: trace.failure_kind,
+                })
+                .collect(),
+            executed_code_addresses: self
+                .call_traces
+                .iter()
+                .filter(|trace| trace.executed_code())
+                .map(|trace| trace.code_address.unwrap_or(trace.to))
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Collections of a transaction, cloned or moved out of the inspector
+struct Collected {
+    transfers: Vec<TokenTransfer>,
+    call_trace: Option<CallTrace>,
+    logs: Vec<Log>,
+    contract_changes: Vec<ContractChange>,
+    coinbase_payments: Vec<CoinbasePayment>,
+    creation_funding: Vec<CreationFunding>,
+    warnings: Vec<SimulationWarning>,
+    parse_warnings: Vec<ParseWarning>,
+    accessed_state: BTreeMap<Address, BTreeSet<U256>>,
+}
+
+impl Collected {
+    /// Fills in the collections the output's trace level reports
+    ///
+    /// Every level reports warnings and accessed state; `TransfersOnly` adds transfers,
+    /// coinbase payments, creation funding and parse warnings, and `Full` reports
+    /// everything.
+    fn into_output(self, mut output: TxTraceOutput) -> TxTraceOutput {
+        output.warnings = self.warnings;
+        output.accessed_state = self.accessed_state;
+        if output.trace_level == TraceLevel::Off {
+            return output;
+        }
+        output.asset_transfers = self.transfers;
+        output.coinbase_payments = self.coinbase_payments;
+        output.creation_funding = self.creation_funding;
+        output.parse_warnings = self.parse_warnings;
+        if output.trace_level == TraceLevel::Full {
+            output.call_trace = self.call_trace;
+            output.logs = self.logs;
+            output.contract_changes = self.contract_changes;
         }
+        output
     }
 }
@@ -0,0 +1,472 @@
+//! Known-contract labels and code fingerprints (feature = "labels")
+//!
+//! Reports name parties by raw address. A [`LabelBook`] maps well-known contracts
+//! (routers, tokens, bridges) to a label and category per chain, and
+//! [`codehash_fingerprint`] identifies any other contract by the hash of its code, so
+//! the same unknown contract can be recognised across simulations and deployments.
+//! [`enrich_report`] attaches both to the addresses of a `SimulationReport`.
+//!
+//! Label books are JSON objects mapping chain IDs to objects mapping addresses to
+//! entries:
+//!
+//! ```json
+//! { "1": { "0x7a250d5630b4cf539739df2c5dacb4c659f2488d": { "label": "Uniswap V2: Router", "category": "router" } } }
+//! ```
+//!
+//! Lookups are plain hash map accesses. A loaded book is immutable, so one instance
+//! can be wrapped in an `Arc` and shared by any number of EVMs and threads.
+//!
+//! ```no_run
+//! # fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! use std::sync::Arc;
+//! use alloy::primitives::address;
+//! use revm_trace::labels::LabelBook;
+//!
+//! let mut book = LabelBook::builtin();
+//! book.merge(LabelBook::from_file("labels.json")?);
+//! let book = Arc::new(book);
+//! let router = book.get(1, address!("7a250d5630b4cf539739df2c5dacb4c659f2488d"));
+//! assert_eq!(router.unwrap().name, "Uniswap V2: Router");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::Path,
+};
+
+use crate::{
+    errors::{EvmError, LabelError, RuntimeError},
+    evm::TraceEvm,
+    report::{AddressHint, SimulationReport},
+    types::NATIVE_TOKEN_ADDRESS,
+};
+use alloy::primitives::{address, Address, B256, KECCAK256_EMPTY};
+use revm::{context_interface::ContextTr, database::Database};
+use serde::{Deserialize, Serialize};
+
+/// Label of a known contract
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Label {
+    /// Human-readable name, e.g. "Uniswap V2: Router"
+    #[serde(rename = "label")]
+    pub name: String,
+    /// Kind of contract, e.g. "router", "token" or "bridge"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+}
+
+impl Label {
+    /// Creates a label with a category
+    pub fn new(name: &str, category: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            category: Some(category.to_string()),
+        }
+    }
+}
+
+/// Contracts deployed at the same address on every chain of the built-in set
+const SHARED: &[(Address, &str, &str)] = &[
+    (
+        address!("000000000022d473030f116ddee9f6b43ac78ba3"),
+        "Uniswap: Permit2",
+        "approval",
+    ),
+    (
+        address!("ca11bde05977b3631167028862be2a173976ca11"),
+        "Multicall3",
+        "utility",
+    ),
+];
+
+/// Chains of the built-in set with their chain-specific contracts
+const BUILTIN: &[(u64, &[(Address, &str, &str)])] = &[
+    (
+        1,
+        &[
+            (
+                address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+                "WETH",
+                "token",
+            ),
+            (
+                address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+                "USDC",
+                "token",
+            ),
+            (
+                address!("dac17f958d2ee523a2206206994597c13d831ec7"),
+                "USDT",
+                "token",
+            ),
+            (
+                address!("6b175474e89094c44da98b954eedeac495271d0f"),
+                "DAI",
+                "token",
+            ),
+            (
+                address!("2260fac5e5542a773aa44fbcfedf7c193bc2c599"),
+                "WBTC",
+                "token",
+            ),
+            (
+                address!("7a250d5630b4cf539739df2c5dacb4c659f2488d"),
+                "Uniswap V2: Router",
+                "router",
+            ),
+            (
+                address!("5c69bee701ef814a2b6a3edd4b1652cb9cc5aa6f"),
+                "Uniswap V2: Factory",
+                "factory",
+            ),
+            (
+                address!("e592427a0aece92de3edee1f18e0157c05861564"),
+                "Uniswap V3: Router",
+                "router",
+            ),
+            (
+                address!("68b3465833fb72a70ecdf485e0e4c7bd8665fc45"),
+                "Uniswap V3: Router 2",
+                "router",
+            ),
+            (
+                address!("3fc91a3afd70395cd496c647d5a6cc9d4b2b7fad"),
+                "Uniswap: Universal Router",
+                "router",
+            ),
+            (
+                address!("d9e1ce17f2641f24ae83637ab66a2cca9c378b9f"),
+                "SushiSwap: Router",
+                "router",
+            ),
+            (
+                address!("1111111254eeb25477b68fb85ed929f73a960582"),
+                "1inch: Aggregation Router V5",
+                "router",
+            ),
+            (
+                address!("99c9fc46f92e8a1c0dec1b1747d010903e884be1"),
+                "Optimism: L1 Standard Bridge",
+                "bridge",
+            ),
+            (
+                address!("4dbd4fc535ac27206064b68ffcf827b0a60bab3f"),
+                "Arbitrum: Delayed Inbox",
+                "bridge",
+            ),
+        ],
+    ),
+    (
+        10,
+        &[
+            (
+                address!("4200000000000000000000000000000000000006"),
+                "WETH",
+                "token",
+            ),
+            (
+                address!("0b2c639c533813f4aa9d7837caf62653d097ff85"),
+                "USDC",
+                "token",
+            ),
+            (
+                address!("4200000000000000000000000000000000000010"),
+                "Optimism: L2 Standard Bridge",
+                "bridge",
+            ),
+        ],
+    ),
+    (
+        137,
+        &[
+            (
+                address!("0d500b1d8e8ef31e21c99d1db9a6444d3adf1270"),
+                "WPOL",
+                "token",
+            ),
+            (
+                address!("3c499c542cef5e3811e1192ce70d8cc03d5c3359"),
+                "USDC",
+                "token",
+            ),
+        ],
+    ),
+    (
+        8453,
+        &[
+            (
+                address!("4200000000000000000000000000000000000006"),
+                "WETH",
+                "token",
+            ),
+            (
+                address!("833589fcd6edb6e08f4c7c32d4f71b54bda02913"),
+                "USDC",
+                "token",
+            ),
+            (
+                address!("4200000000000000000000000000000000000010"),
+                "Base: L2 Standard Bridge",
+                "bridge",
+            ),
+        ],
+    ),
+    (
+        42161,
+        &[
+            (
+                address!("82af49447d8a07e3bd95bd0d56f35241523fbab1"),
+                "WETH",
+                "token",
+            ),
+            (
+                address!("af88d065e77c8cc2239327c5edb3a432268e5831"),
+                "USDC",
+                "token",
+            ),
+        ],
+    ),
+];
+
+/// Per-chain labels of known contracts
+#[derive(Debug, Clone, Default)]
+pub struct LabelBook {
+    labels: HashMap<(u64, Address), Label>,
+}
+
+impl LabelBook {
+    /// Creates an empty book
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Creates a book with a minimal set of major contracts
+    ///
+    /// Covers the main tokens, DEX routers and canonical bridges of Ethereum mainnet,
+    /// and the wrapped native token, USDC and bridge of Optimism, Polygon, Base and
+    /// Arbitrum One, plus Permit2 and Multicall3 on all of them.
+    pub fn builtin() -> Self {
+        let mut book = Self::new();
+        for (chain_id, contracts) in BUILTIN {
+            for (address, name, category) in SHARED.iter().chain(contracts.iter()) {
+                book.insert(*chain_id, *address, Label::new(name, category));
+            }
+        }
+        book
+    }
+
+    /// Loads a book from a JSON file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, LabelError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| LabelError::Io {
+            path: path.display().to_string(),
+            reason: e.to_string(),
+        })?;
+        Self::from_json(&content)
+    }
+
+    /// Parses a JSON object mapping chain IDs to objects mapping addresses to labels
+    pub fn from_json(content: &str) -> Result<Self, LabelError> {
+        let chains: HashMap<String, HashMap<String, Label>> = serde_json::from_str(content)
+            .map_err(|e| LabelError::Parse {
+                entry: "<json>".to_string(),
+                reason: e.to_string(),
+            })?;
+        let mut book = Self::new();
+        for (chain, labels) in chains {
+            let chain_id = chain.parse().map_err(|_| LabelError::Parse {
+                entry: chain.clone(),
+                reason: "chain ID must be a decimal number".to_string(),
+            })?;
+            for (address, label) in labels {
+                let address = address.parse().map_err(|e| LabelError::Parse {
+                    entry: format!("{chain}:{address}"),
+                    reason: format!("{e}"),
+                })?;
+                book.insert(chain_id, address, label);
+            }
+        }
+        Ok(book)
+    }
+
+    /// Inserts or replaces the label of `address` on `chain_id`
+    pub fn insert(&mut self, chain_id: u64, address: Address, label: Label) {
+        self.labels.insert((chain_id, address), label);
+    }
+
+    /// Adds the labels of `other`, which replace those of the same address
+    pub fn merge(&mut self, other: LabelBook) {
+        self.labels.extend(other.labels);
+    }
+
+    /// Label of `address` on `chain_id`, if known
+    pub fn get(&self, chain_id: u64, address: Address) -> Option<&Label> {
+        self.labels.get(&(chain_id, address))
+    }
+
+    /// Number of labeled addresses, over all chains
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Whether the book is empty
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+}
+
+/// Fingerprint of the code deployed at `address`: the keccak256 hash of its code
+///
+/// The code hash is read through the EVM's database, so contracts deployed by
+/// committed simulations are fingerprinted like on-chain ones. Identical runtime code
+/// yields the same fingerprint at any address and on any chain.
+///
+/// # Returns
+/// - `Ok(Some(hash))`: The code hash of a contract
+/// - `Ok(None)`: The account does not exist or has no code
+/// - `Err(EvmError::Runtime(_))`: The account could not be loaded
+pub fn codehash_fingerprint<DB, INSP>(
+    evm: &mut TraceEvm<DB, INSP>,
+    address: Address,
+) -> Result<Option<B256>, EvmError>
+where
+    DB: Database,
+{
+    let account = evm.db().basic(address).map_err(|e| {
+        RuntimeError::AccountAccess(format!("Failed to load account {address}: {e}"))
+    })?;
+    Ok(account
+        .map(|account| account.code_hash)
+        .filter(|hash| *hash != KECCAK256_EMPTY && !hash.is_zero()))
+}
+
+/// Attaches labels and code fingerprints of the report's addresses
+///
+/// Addresses are the sender and target, the targets of all call frames, both sides
+/// of every asset transfer and the transferred tokens. Labels are looked up for the
+/// EVM's chain ID and fingerprints read from its current state, so enrich the reports
+/// of a stateful batch before the state is reset to fingerprint the contracts it
+/// deployed. Addresses without label and code get no entry.
+///
+/// # Example
+/// ```no_run
+/// use revm_trace::labels::{enrich_report, LabelBook};
+/// # use revm_trace::{report::SimulationReport, TraceEvm};
+/// # fn example<DB: revm::Database, INSP>(evm: &mut TraceEvm<DB, INSP>, report: SimulationReport) {
+/// let report = enrich_report(evm, report, &LabelBook::builtin()).unwrap();
+/// for (address, hint) in &report.address_hints {
+///     println!("{address}: {:?} {:?}", hint.label, hint.code_fingerprint);
+/// }
+/// # }
+/// ```
+pub fn enrich_report<DB, INSP>(
+    evm: &mut TraceEvm<DB, INSP>,
+    report: SimulationReport,
+    book: &LabelBook,
+) -> Result<SimulationReport, EvmError>
+where
+    DB: Database,
+{
+    let mut addresses = BTreeSet::from([report.tx.caller]);
+    addresses.extend(report.tx.transact_to.to().copied());
+    if let Some(root) = &report.trace.call_trace {
+        addresses.extend(root.frames().map(|frame| frame.to));
+    }
+    for transfer in &report.trace.asset_transfers {
+        addresses.insert(transfer.from);
+        addresses.extend(transfer.to);
+        if !transfer.is_native_token() {
+            addresses.insert(transfer.token);
+        }
+    }
+    addresses.remove(&NATIVE_TOKEN_ADDRESS);
+
+    let chain_id = evm.cfg.chain_id;
+    let mut hints = HashMap::new();
+    for address in addresses {
+        let label = book.get(chain_id, address);
+        let code_fingerprint = codehash_fingerprint(evm, address)?;
+        if label.is_none() && code_fingerprint.is_none() {
+            continue;
+        }
+        hints.insert(
+            address,
+            AddressHint {
+                label: label.map(|label| label.name.clone()),
+                category: label.and_then(|label| label.category.clone()),
+                code_fingerprint,
+            },
+        );
+    }
+    Ok(report.with_address_hints(hints))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROUTER: Address = address!("7a250d5630b4cf539739df2c5dacb4c659f2488d");
+
+    #[test]
+    fn test_builtin_is_per_chain() {
+        let book = LabelBook::builtin();
+        assert_eq!(
+            book.get(1, ROUTER),
+            Some(&Label::new("Uniswap V2: Router", "router"))
+        );
+        assert_eq!(book.get(10, ROUTER), None);
+
+        let weth = address!("4200000000000000000000000000000000000006");
+        assert_eq!(book.get(10, weth).unwrap().name, "WETH");
+        assert_eq!(book.get(1, weth), None);
+
+        let permit2 = address!("000000000022d473030f116ddee9f6b43ac78ba3");
+        for chain_id in [1, 10, 137, 8453, 42161] {
+            assert_eq!(
+                book.get(chain_id, permit2).unwrap().name,
+                "Uniswap: Permit2"
+            );
+        }
+    }
+
+    #[test]
+    fn test_json_overrides_builtin() {
+        let json = r#"{
+            "1": {
+                "0x7a250d5630b4cf539739df2c5dacb4c659f2488d": { "label": "V2 Router" },
+                "0x1000000000000000000000000000000000000001": { "label": "Treasury", "category": "multisig" }
+            }
+        }"#;
+        let mut book = LabelBook::builtin();
+        let builtin = book.len();
+        book.merge(LabelBook::from_json(json).unwrap());
+        assert_eq!(book.len(), builtin + 1);
+        assert_eq!(
+            book.get(1, ROUTER),
+            Some(&Label {
+                name: "V2 Router".to_string(),
+                category: None,
+            })
+        );
+        let treasury = address!("1000000000000000000000000000000000000001");
+        assert_eq!(
+            book.get(1, treasury),
+            Some(&Label::new("Treasury", "multisig"))
+        );
+    }
+
+    #[test]
+    fn test_invalid_entries() {
+        assert!(matches!(
+            LabelBook::from_json(r#"{ "mainnet": {} }"#),
+            Err(LabelError::Parse { entry, .. }) if entry == "mainnet"
+        ));
+        assert!(matches!(
+            LabelBook::from_json(r#"{ "1": { "0x12": { "label": "Short" } } }"#),
+            Err(LabelError::Parse { entry, .. }) if entry == "1:0x12"
+        ));
+        assert!(LabelBook::from_json(r#"{ "1": { "0x12": "Short" } }"#).is_err());
+    }
+}
@@ -0,0 +1,738 @@
+//! Storage layouts for readable storage diffs (feature = "storage-layout")
+//!
+//! Storage diffs report raw slot numbers. The storage layout emitted by solc
+//! (`storageLayout` output selection) or `forge inspect <Contract> storage-layout --json`
+//! maps them back to state variables. [`StorageLayout`] parses that JSON and
+//! [`LabelStorage`] labels a [`StorageDiff`] with the layouts of the touched contracts:
+//!
+//! ```text
+//! owner (slot 0): 0x3ee18B2214AFF97000D974cf647E7C347E8fa585 → 0xdeAD00000000000000000000000000000000dEAd
+//! balances[0x1f9840a85d5aF5bf1D1762F925BDADdC4201F984] (slot 0x4f6c…): 0 → 100
+//! ```
+//!
+//! Slots of mapping entries and dynamic array elements are hashes of the key and the
+//! slot of the mapping or array. They are resolved from the KECCAK256 preimages
+//! recorded by `TxInspector` with `with_mapping_resolution(true)`
+//! (`TxTraceOutput::keccak_preimages`), so only keys the traced transaction hashed
+//! itself are found. Slots that cannot be resolved keep their raw form.
+//!
+//! ```no_run
+//! # fn example(
+//! #     diff: revm_trace::types::StorageDiff,
+//! #     output: revm_trace::inspectors::tx_inspector::TxTraceOutput,
+//! #     token: alloy::primitives::Address,
+//! # ) -> Result<(), Box<dyn std::error::Error>> {
+//! use std::collections::HashMap;
+//! use revm_trace::layout::{LabelStorage, StorageLayout};
+//!
+//! let layout = StorageLayout::from_solc_json(&std::fs::read_to_string("Token.layout.json")?)?;
+//! let layouts = HashMap::from([(token, layout)]);
+//! for slot in &diff.label_with_preimages(&layouts, &output.keccak_preimages)[&token] {
+//!     println!("{slot}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    str::FromStr,
+};
+
+use crate::{
+    errors::LayoutError,
+    types::{SlotAccess, StorageDiff},
+};
+use alloy::primitives::{hex, Address, Bytes, B256, I256, U256};
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Maximum number of nested mappings and arrays followed to resolve a slot
+const MAX_NESTING: usize = 8;
+
+/// Largest distance of a derived slot from the hash it is derived from
+const MAX_DERIVED_OFFSET: u64 = 1 << 32;
+
+/// State variable or struct member in a storage layout
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct StorageVariable {
+    /// Variable or member name
+    pub label: String,
+    /// First slot, relative to the struct for members
+    #[serde(deserialize_with = "decimal")]
+    pub slot: U256,
+    /// Byte offset within the slot, counted from the lowest-order byte
+    pub offset: usize,
+    /// Identifier of the type in [`StorageLayout::types`]
+    #[serde(rename = "type")]
+    pub type_id: String,
+}
+
+/// How values of a type are laid out in storage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    /// Stored in consecutive slots from the variable's slot, packed when small
+    Inplace,
+    /// Entries at `keccak256(key . slot)`
+    Mapping,
+    /// Length at the slot, elements from `keccak256(slot)`
+    DynamicArray,
+    /// `bytes` and `string`: inline when short, else data from `keccak256(slot)`
+    Bytes,
+}
+
+/// Type entry of a storage layout
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageType {
+    /// Storage encoding
+    pub encoding: Encoding,
+    /// Solidity type name, e.g. `mapping(address => uint256)`
+    pub label: String,
+    /// Size in bytes; a multiple of 32 for types spanning several slots
+    #[serde(deserialize_with = "decimal")]
+    pub number_of_bytes: usize,
+    /// Key type of mappings
+    #[serde(default)]
+    pub key: Option<String>,
+    /// Value type of mappings
+    #[serde(default)]
+    pub value: Option<String>,
+    /// Element type of arrays
+    #[serde(default)]
+    pub base: Option<String>,
+    /// Members of structs
+    #[serde(default)]
+    pub members: Vec<StorageVariable>,
+}
+
+impl StorageType {
+    /// Number of slots a value of the type occupies
+    fn slots(&self) -> usize {
+        self.number_of_bytes.div_ceil(32).max(1)
+    }
+}
+
+/// Storage layout of one contract, as emitted by solc
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StorageLayout {
+    /// State variables in declaration order
+    pub storage: Vec<StorageVariable>,
+    /// Types by identifier
+    pub types: HashMap<String, StorageType>,
+}
+
+/// Layout JSON; solc emits `"types": null` for contracts without state variables
+#[derive(Deserialize)]
+struct RawLayout {
+    storage: Vec<StorageVariable>,
+    types: Option<HashMap<String, StorageType>>,
+}
+
+/// State variable stored in a slot, with its part of the slot's values decoded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SlotVariable {
+    /// Access path, e.g. `owner`, `balances[0x…]`, `pools[3].reserve0`
+    pub label: String,
+    /// Solidity type name
+    pub type_label: String,
+    /// Decoded value before the access
+    pub old_value: String,
+    /// Decoded value after the access
+    pub new_value: String,
+}
+
+/// Slot access labeled with the variables stored in the slot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LabeledSlot {
+    pub slot: U256,
+    pub old_value: U256,
+    pub new_value: U256,
+    pub is_write: bool,
+    /// Variables stored in the slot, only the changed ones for writes changing any;
+    /// empty if the slot could not be resolved
+    pub variables: Vec<SlotVariable>,
+}
+
+impl fmt::Display for LabeledSlot {
+    /// Writes `label (slot n): old → new` per variable, or the raw values
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let slot = format_slot(self.slot);
+        if self.variables.is_empty() {
+            return write!(
+                f,
+                "slot {slot}: {:#x} → {:#x}",
+                self.old_value, self.new_value
+            );
+        }
+        for (index, variable) in self.variables.iter().enumerate() {
+            if index > 0 {
+                f.write_str("; ")?;
+            }
+            write!(
+                f,
+                "{} (slot {slot}): {} → {}",
+                variable.label, variable.old_value, variable.new_value
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Labeled storage accesses by contract, in the order of the [`StorageDiff`]
+pub type LabeledDiff = BTreeMap<Address, Vec<LabeledSlot>>;
+
+/// Variable found in a slot
+struct Located<'a> {
+    label: String,
+    type_id: &'a str,
+    offset: usize,
+}
+
+impl StorageLayout {
+    /// Parses the storage layout JSON of a contract
+    ///
+    /// Accepts the `storageLayout` object of solc's standard JSON output, which is
+    /// also what `forge inspect <Contract> storage-layout --json` prints.
+    pub fn from_solc_json(json: &str) -> Result<Self, LayoutError> {
+        let raw: RawLayout =
+            serde_json::from_str(json).map_err(|e| LayoutError::Parse(e.to_string()))?;
+        let layout = Self {
+            storage: raw.storage,
+            types: raw.types.unwrap_or_default(),
+        };
+        layout.validate()?;
+        Ok(layout)
+    }
+
+    /// Checks that every referenced type is defined
+    fn validate(&self) -> Result<(), LayoutError> {
+        let unknown = |label: &str, type_id: &str| LayoutError::UnknownType {
+            label: label.to_string(),
+            type_id: type_id.to_string(),
+        };
+        let members = self.types.values().flat_map(|ty| &ty.members);
+        for variable in self.storage.iter().chain(members) {
+            if !self.types.contains_key(&variable.type_id) {
+                return Err(unknown(&variable.label, &variable.type_id));
+            }
+        }
+        for ty in self.types.values() {
+            for type_id in [&ty.key, &ty.value, &ty.base].into_iter().flatten() {
+                if !self.types.contains_key(type_id) {
+                    return Err(unknown(&ty.label, type_id));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the variables stored in `slot` with their decoded values
+    ///
+    /// `preimages` are the KECCAK256 inputs of the transaction by hash, used to
+    /// resolve mapping entries and dynamic array elements. Empty if the slot cannot be
+    /// resolved.
+    pub fn label_slot(
+        &self,
+        slot: U256,
+        old_value: U256,
+        new_value: U256,
+        preimages: &HashMap<B256, Bytes>,
+    ) -> Vec<SlotVariable> {
+        self.locate(slot, preimages, 0)
+            .into_iter()
+            .map(|located| {
+                let ty = &self.types[located.type_id];
+                let label = match ty.encoding {
+                    Encoding::DynamicArray => format!("{}.length", located.label),
+                    _ => located.label,
+                };
+                SlotVariable {
+                    label,
+                    type_label: ty.label.clone(),
+                    old_value: format_value(ty, old_value, located.offset),
+                    new_value: format_value(ty, new_value, located.offset),
+                }
+            })
+            .collect()
+    }
+
+    /// Finds the variables stored in `slot`, declared or derived from a hash
+    fn locate(
+        &self,
+        slot: U256,
+        preimages: &HashMap<B256, Bytes>,
+        depth: usize,
+    ) -> Vec<Located<'_>> {
+        let mut found = Vec::new();
+        for variable in &self.storage {
+            self.within(
+                variable.label.clone(),
+                &variable.type_id,
+                variable.slot,
+                variable.offset,
+                slot,
+                &mut found,
+            );
+        }
+        if found.is_empty() && depth < MAX_NESTING {
+            self.derived(slot, preimages, depth, &mut found);
+        }
+        found
+    }
+
+    /// Collects the variables of a value of type `type_id` stored from `start` that
+    /// occupy `slot`
+    fn within<'a>(
+        &'a self,
+        label: String,
+        type_id: &'a str,
+        start: U256,
+        offset: usize,
+        slot: U256,
+        found: &mut Vec<Located<'a>>,
+    ) {
+        let Some(ty) = self.types.get(type_id) else {
+            return;
+        };
+        let Some(index) = slot
+            .checked_sub(start)
+            .filter(|index| *index < U256::from(ty.slots()))
+        else {
+            return;
+        };
+        let index = index.to::<usize>();
+        match (&ty.base, ty.members.is_empty()) {
+            _ if ty.encoding != Encoding::Inplace => found.push(Located {
+                label,
+                type_id,
+                offset,
+            }),
+            (_, false) => {
+                for member in &ty.members {
+                    self.within(
+                        format!("{label}.{}", member.label),
+                        &member.type_id,
+                        start + member.slot,
+                        member.offset,
+                        slot,
+                        found,
+                    );
+                }
+            }
+            (Some(base), true) => {
+                let len = static_len(&ty.label);
+                self.element(&label, base, start, index, len, slot, found);
+            }
+            (None, true) => found.push(Located {
+                label,
+                type_id,
+                offset,
+            }),
+        }
+    }
+
+    /// Collects the elements of an array of `base` stored from `start` that occupy
+    /// `slot`, `index` slots after `start`
+    #[allow(clippy::too_many_arguments)]
+    fn element<'a>(
+        &'a self,
+        label: &str,
+        base: &'a str,
+        start: U256,
+        index: usize,
+        len: Option<usize>,
+        slot: U256,
+        found: &mut Vec<Located<'a>>,
+    ) {
+        let Some(element) = self.types.get(base) else {
+            return;
+        };
+        // Elements of up to 16 bytes share slots, larger ones start a new slot
+        if element.number_of_bytes > 16 {
+            let position = index / element.slots();
+            if len.is_none_or(|len| position < len) {
+                let first = start + U256::from(position * element.slots());
+                self.within(format!("{label}[{position}]"), base, first, 0, slot, found);
+            }
+            return;
+        }
+        let size = element.number_of_bytes.max(1);
+        for position in index * (32 / size)..(index + 1) * (32 / size) {
+            if len.is_some_and(|len| position >= len) {
+                break;
+            }
+            found.push(Located {
+                label: format!("{label}[{position}]"),
+                type_id: base,
+                offset: (position % (32 / size)) * size,
+            });
+        }
+    }
+
+    /// Resolves `slot` as a mapping entry or array element through the hash it is
+    /// derived from, trying the closest recorded hashes first
+    fn derived<'a>(
+        &'a self,
+        slot: U256,
+        preimages: &HashMap<B256, Bytes>,
+        depth: usize,
+        found: &mut Vec<Located<'a>>,
+    ) {
+        let mut candidates: Vec<(U256, U256, &Bytes)> = preimages
+            .iter()
+            .filter_map(|(hash, input)| {
+                let hash = U256::from_be_bytes(hash.0);
+                let distance = slot.checked_sub(hash)?;
+                (distance < U256::from(MAX_DERIVED_OFFSET)).then_some((distance, hash, input))
+            })
+            .collect();
+        candidates.sort_by_key(|(distance, ..)| *distance);
+
+        for (distance, hash, input) in candidates {
+            // The base slot is the last word of the input, preceded by the key
+            let Some(split) = input.len().checked_sub(32) else {
+                continue;
+            };
+            let (key, base_slot) = input.split_at(split);
+            let base_slot = U256::from_be_slice(base_slot);
+            for container in self.locate(base_slot, preimages, depth + 1) {
+                let ty = &self.types[container.type_id];
+                match (ty.encoding, &ty.key, &ty.value, &ty.base) {
+                    (Encoding::Mapping, Some(key_type), Some(value_type), _) => {
+                        let key_type = &self.types[key_type];
+                        // Only `bytes` and `string` keys can be empty
+                        if key.is_empty() && key_type.encoding != Encoding::Bytes {
+                            continue;
+                        }
+                        self.within(
+                            format!("{}[{}]", container.label, format_key(key_type, key)),
+                            value_type,
+                            hash,
+                            0,
+                            slot,
+                            found,
+                        );
+                    }
+                    (Encoding::DynamicArray, _, _, Some(base)) if key.is_empty() => {
+                        let index = distance.to::<usize>();
+                        self.element(&container.label, base, hash, index, None, slot, found);
+                    }
+                    (Encoding::Bytes, ..) if key.is_empty() => found.push(Located {
+                        label: format!("{} (data)", container.label),
+                        type_id: container.type_id,
+                        offset: 0,
+                    }),
+                    _ => {}
+                }
+            }
+            if !found.is_empty() {
+                return;
+            }
+        }
+    }
+}
+
+/// Labels storage accesses with the variables they touched
+pub trait LabelStorage {
+    /// Labels the accesses of contracts with a layout, without resolving mapping
+    /// entries and dynamic array elements
+    fn label_with(&self, layouts: &HashMap<Address, StorageLayout>) -> LabeledDiff {
+        self.label_with_preimages(layouts, &HashMap::new())
+    }
+
+    /// Labels the accesses of contracts with a layout, resolving mapping entries and
+    /// dynamic array elements through the transaction's KECCAK256 preimages
+    fn label_with_preimages(
+        &self,
+        layouts: &HashMap<Address, StorageLayout>,
+        preimages: &HashMap<B256, Bytes>,
+    ) -> LabeledDiff;
+}
+
+impl LabelStorage for StorageDiff {
+    fn label_with_preimages(
+        &self,
+        layouts: &HashMap<Address, StorageLayout>,
+        preimages: &HashMap<B256, Bytes>,
+    ) -> LabeledDiff {
+        self.iter()
+            .map(|(address, accesses)| {
+                let layout = layouts.get(address);
+                let labeled = accesses
+                    .iter()
+                    .map(|access| label_access(layout, access, preimages))
+                    .collect();
+                (*address, labeled)
+            })
+            .collect()
+    }
+}
+
+fn label_access(
+    layout: Option<&StorageLayout>,
+    access: &SlotAccess,
+    preimages: &HashMap<B256, Bytes>,
+) -> LabeledSlot {
+    let mut variables = layout.map_or_else(Vec::new, |layout| {
+        layout.label_slot(access.slot, access.old_value, access.new_value, preimages)
+    });
+    // A write to a packed slot usually changes one of its variables
+    if access.is_write && variables.iter().any(|v| v.old_value != v.new_value) {
+        variables.retain(|v| v.old_value != v.new_value);
+    }
+    LabeledSlot {
+        slot: access.slot,
+        old_value: access.old_value,
+        new_value: access.new_value,
+        is_write: access.is_write,
+        variables,
+    }
+}
+
+/// Small slots in decimal, hash-derived ones in hex
+fn format_slot(slot: U256) -> String {
+    if slot <= U256::from(u32::MAX) {
+        slot.to_string()
+    } else {
+        format!("{slot:#x}")
+    }
+}
+
+/// Decodes the part of `word` holding a value of type `ty` stored at `offset`
+fn format_value(ty: &StorageType, word: U256, offset: usize) -> String {
+    match ty.encoding {
+        // The slot of a dynamic array holds its length
+        Encoding::DynamicArray => word.to_string(),
+        Encoding::Inplace if ty.members.is_empty() && ty.base.is_none() => {
+            let size = ty.number_of_bytes.clamp(1, 32);
+            let mut value = word >> (offset * 8);
+            if size < 32 {
+                value &= (U256::from(1) << (size * 8)) - U256::from(1);
+            }
+            format_word(&ty.label, size, value)
+        }
+        _ => format!("{word:#x}"),
+    }
+}
+
+/// Formats a right-aligned value of `size` bytes by its Solidity type name
+fn format_word(type_label: &str, size: usize, value: U256) -> String {
+    if type_label == "bool" {
+        (!value.is_zero()).to_string()
+    } else if type_label.starts_with("address") || type_label.starts_with("contract ") {
+        Address::from_word(B256::from(value)).to_string()
+    } else if type_label.starts_with("uint") || type_label.starts_with("enum ") {
+        value.to_string()
+    } else if type_label.starts_with("int") {
+        // Sign-extend to 256 bits
+        let negative = size < 32 && value.bit(size * 8 - 1);
+        let value = if negative {
+            value | (U256::MAX << (size * 8))
+        } else {
+            value
+        };
+        I256::from_raw(value).to_string()
+    } else if type_label.starts_with("bytes") && size < 32 {
+        hex::encode_prefixed(&value.to_be_bytes::<32>()[32 - size..])
+    } else {
+        format!("{value:#x}")
+    }
+}
+
+/// Formats a mapping key as hashed: value types padded to 32 bytes, `bytes` and
+/// `string` keys unpadded
+fn format_key(key_type: &StorageType, key: &[u8]) -> String {
+    match key_type.encoding {
+        Encoding::Bytes if key_type.label == "string" => {
+            format!("{:?}", String::from_utf8_lossy(key))
+        }
+        Encoding::Inplace if key.len() == 32 => {
+            let size = key_type.number_of_bytes.clamp(1, 32);
+            // Fixed-size byte arrays are left-aligned, other value types right-aligned
+            if key_type.label.starts_with("bytes") {
+                hex::encode_prefixed(&key[..size])
+            } else {
+                format_word(&key_type.label, size, U256::from_be_slice(key))
+            }
+        }
+        _ => hex::encode_prefixed(key),
+    }
+}
+
+/// Length of a static array from its type name, e.g. 3 for `uint8[3]`
+fn static_len(type_label: &str) -> Option<usize> {
+    let (_, len) = type_label.strip_suffix(']')?.rsplit_once('[')?;
+    len.parse().ok()
+}
+
+/// Deserializes a decimal number given as string, as solc does for slots and sizes
+fn decimal<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Number {
+        Text(String),
+        Integer(u64),
+    }
+    let text = match Number::deserialize(deserializer)? {
+        Number::Text(text) => text,
+        Number::Integer(value) => value.to_string(),
+    };
+    text.parse().map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{address, keccak256};
+
+    const LAYOUT: &str = r#"{
+        "storage": [
+            {"astId": 1, "contract": "T.sol:T", "label": "owner", "offset": 0, "slot": "0", "type": "t_address"},
+            {"astId": 2, "contract": "T.sol:T", "label": "paused", "offset": 20, "slot": "0", "type": "t_bool"},
+            {"astId": 3, "contract": "T.sol:T", "label": "balances", "offset": 0, "slot": "1", "type": "t_mapping(t_address,t_uint256)"},
+            {"astId": 4, "contract": "T.sol:T", "label": "points", "offset": 0, "slot": "2", "type": "t_array(t_struct(Point)5_storage)dyn_storage"},
+            {"astId": 5, "contract": "T.sol:T", "label": "small", "offset": 0, "slot": "3", "type": "t_array(t_int8)3_storage"}
+        ],
+        "types": {
+            "t_address": {"encoding": "inplace", "label": "address", "numberOfBytes": "20"},
+            "t_bool": {"encoding": "inplace", "label": "bool", "numberOfBytes": "1"},
+            "t_int8": {"encoding": "inplace", "label": "int8", "numberOfBytes": "1"},
+            "t_uint256": {"encoding": "inplace", "label": "uint256", "numberOfBytes": "32"},
+            "t_mapping(t_address,t_uint256)": {"encoding": "mapping", "key": "t_address", "label": "mapping(address => uint256)", "numberOfBytes": "32", "value": "t_uint256"},
+            "t_array(t_struct(Point)5_storage)dyn_storage": {"base": "t_struct(Point)5_storage", "encoding": "dynamic_array", "label": "struct T.Point[]", "numberOfBytes": "32"},
+            "t_array(t_int8)3_storage": {"base": "t_int8", "encoding": "inplace", "label": "int8[3]", "numberOfBytes": "32"},
+            "t_struct(Point)5_storage": {"encoding": "inplace", "label": "struct T.Point", "numberOfBytes": "64", "members": [
+                {"astId": 6, "contract": "T.sol:T", "label": "x", "offset": 0, "slot": "0", "type": "t_uint256"},
+                {"astId": 7, "contract": "T.sol:T", "label": "y", "offset": 0, "slot": "1", "type": "t_uint256"}
+            ]}
+        }
+    }"#;
+
+    const HOLDER: Address = address!("cafe00000000000000000000000000000000face");
+
+    fn labels(layout: &StorageLayout, slot: U256, preimages: &HashMap<B256, Bytes>) -> Vec<String> {
+        layout
+            .label_slot(slot, U256::ZERO, U256::ZERO, preimages)
+            .into_iter()
+            .map(|variable| variable.label)
+            .collect()
+    }
+
+    #[test]
+    fn test_declared_slots() {
+        let layout = StorageLayout::from_solc_json(LAYOUT).unwrap();
+        let none = HashMap::new();
+
+        assert_eq!(labels(&layout, U256::ZERO, &none), ["owner", "paused"]);
+        assert_eq!(labels(&layout, U256::from(2), &none), ["points.length"]);
+        assert_eq!(
+            labels(&layout, U256::from(3), &none),
+            ["small[0]", "small[1]", "small[2]"]
+        );
+        assert!(labels(&layout, U256::from(4), &none).is_empty());
+
+        // Packed values are decoded from their own bytes
+        let word = (U256::from(1) << 160) | U256::from(0xff);
+        let variables = layout.label_slot(U256::from(3), U256::ZERO, word, &none);
+        assert_eq!(variables[0].new_value, "-1");
+        let variables = layout.label_slot(U256::ZERO, U256::ZERO, word, &none);
+        assert_eq!(
+            variables[0].new_value,
+            Address::with_last_byte(0xff).to_string()
+        );
+        assert_eq!(variables[1].new_value, "true");
+    }
+
+    #[test]
+    fn test_derived_slots() {
+        let layout = StorageLayout::from_solc_json(LAYOUT).unwrap();
+        let mapping_input = [
+            HOLDER.into_word().as_slice(),
+            &U256::from(1).to_be_bytes::<32>()[..],
+        ]
+        .concat();
+        let array_input = U256::from(2).to_be_bytes::<32>().to_vec();
+        let entry = keccak256(&mapping_input);
+        let elements = keccak256(&array_input);
+        let preimages = HashMap::from([
+            (entry, Bytes::from(mapping_input)),
+            (elements, Bytes::from(array_input)),
+        ]);
+
+        assert_eq!(
+            labels(&layout, U256::from_be_bytes(entry.0), &preimages),
+            [format!("balances[{HOLDER}]")]
+        );
+        // Element 1 of `points` takes slots 2 and 3 after the hash
+        assert_eq!(
+            labels(
+                &layout,
+                U256::from_be_bytes(elements.0) + U256::from(3),
+                &preimages
+            ),
+            ["points[1].y"]
+        );
+        // Without the preimage the slot stays unresolved
+        assert!(labels(&layout, U256::from_be_bytes(entry.0), &HashMap::new()).is_empty());
+    }
+
+    #[test]
+    fn test_labeled_diff_display() {
+        let layout = StorageLayout::from_solc_json(LAYOUT).unwrap();
+        let diff = StorageDiff::from([(
+            HOLDER,
+            vec![
+                SlotAccess {
+                    address: HOLDER,
+                    slot: U256::ZERO,
+                    old_value: U256::from(1) << 160,
+                    new_value: (U256::from(1) << 160) | U256::from(0xabc),
+                    is_write: true,
+                    is_cold: false,
+                },
+                SlotAccess {
+                    address: HOLDER,
+                    slot: U256::from(9),
+                    old_value: U256::from(1),
+                    new_value: U256::from(2),
+                    is_write: true,
+                    is_cold: false,
+                },
+            ],
+        )]);
+
+        let labeled = diff.label_with(&HashMap::from([(HOLDER, layout)]));
+        let lines: Vec<String> = labeled[&HOLDER].iter().map(ToString::to_string).collect();
+        let new_owner = Address::left_padding_from(&[0x0a, 0xbc]);
+        assert_eq!(
+            lines,
+            [
+                format!("owner (slot 0): {} → {new_owner}", Address::ZERO),
+                "slot 9: 0x1 → 0x2".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_layouts() {
+        assert!(matches!(
+            StorageLayout::from_solc_json("{"),
+            Err(LayoutError::Parse(_))
+        ));
+        assert!(matches!(
+            StorageLayout::from_solc_json(
+                r#"{"storage": [{"label": "x", "offset": 0, "slot": "0", "type": "t_missing"}], "types": null}"#
+            ),
+            Err(LayoutError::UnknownType { type_id, .. }) if type_id == "t_missing"
+        ));
+        let empty = StorageLayout::from_solc_json(r#"{"storage": [], "types": null}"#).unwrap();
+        assert!(empty.storage.is_empty());
+    }
+}
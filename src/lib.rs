@@ -35,6 +35,21 @@
 //! - `types`: Core data structures and type definitions
 //! - `traits`: Trait definitions for extensibility
 //! - `errors`: Error types and handling
+//! - `export`: Conversion of call traces into parity flat traces and observability spans (spans: feature = "otel")
+//! - `differential`: One batch run under two scenarios, with the outcomes diffed
+//! - `fuzz`: Seeded input sweeps over a single contract function
+//! - `harness`: Per-transaction expectations checked into pass/fail reports
+//! - `multichain`: Scenarios spanning several forks, with values carried between steps
+//! - `report`: Self-contained per-transaction simulation reports
+//! - `analysis`: Heuristics and analyses over simulation reports
+//! - `api`: Canonical JSON request/response schema for simulation services (feature = "serde-api")
+//! - `verify`: Offline replay of traced transactions from their captured prestate
+//! - `chains`: Per-chain presets (wrapped native token, fee currencies) for transfer accounting
+//! - `signatures`: Selector/event signature database for ABI-less traces (feature = "signatures")
+//! - `layout`: Storage layouts labeling raw slots with variable names (feature = "storage-layout")
+//! - `labels`: Known-contract labels and code fingerprints for report addresses (feature = "labels")
+//! - `explain`: Plain-language summaries of simulation reports for wallets (feature = "labels")
+//! - `state_import`: Loading Anvil state dumps and genesis allocs, and exporting the simulated state (feature = "state-import")
 //! - `utils`: Helper functions and utilities
 //!
 //! ## Installation
@@ -48,25 +63,71 @@
 //! # Alternative: Pure Rust TLS for system-dependency-free builds
 //! # revm-trace = { version = "4.2.0", default-features = false, features = ["rustls-tls"] }
 //! ```
+//!
+//! ## Diagnostics
+//!
+//! The `tracing-logs` feature instruments the pipeline with [`tracing`](https://docs.rs/tracing)
+//! spans: `build` (rpc scheme, block number, chain ID), `trace_transactions` (batch
+//! size, stateful flag), `tx` per transaction (caller, kind, gas used, status) and
+//! `db_fetch` per upstream lookup (kind, address, duration). Fallback paths and failed
+//! lookups log warnings. Without the feature, or without a subscriber, nothing is
+//! emitted.
 
+pub mod analysis;
+#[cfg(feature = "serde-api")]
+pub mod api;
+pub mod chains;
+pub mod differential;
 pub mod errors;
 pub mod evm;
+#[cfg(feature = "labels")]
+pub mod explain;
+pub mod export;
+pub mod fuzz;
+pub mod harness;
 pub mod inspectors;
+#[cfg(feature = "labels")]
+pub mod labels;
+#[cfg(feature = "storage-layout")]
+pub mod layout;
+pub mod multichain;
+pub mod report;
+#[cfg(feature = "signatures")]
+pub mod signatures;
+#[cfg(feature = "state-import")]
+pub mod state_import;
 pub mod traits;
 pub mod types;
 pub mod utils;
+pub mod verify;
 mod wrap_db;
 
 // Re-export core types for easier access
+pub use evm::access_list::{AccessListEntryUse, AccessListEvaluation};
+#[cfg(feature = "sim-cache")]
+pub use evm::cache::SimulationCache;
+pub use evm::config::{BackendKind, EvmConfigView};
+pub use evm::metrics::{BatchMetrics, TxMetrics};
+pub use evm::session::BatchSession;
+pub use evm::timeline::{BatchTimeline, TimelineEvent};
 pub use evm::TraceEvm;
 
 #[cfg(any(feature = "default", feature = "rustls-tls"))]
-pub use evm::builder::{create_evm, create_evm_with_tracer, EvmBuilder};
+pub use evm::builder::{
+    create_evm, create_evm_with_tracer, custom_db::CustomDbBuilder, factory::EvmFactory,
+    EvmBuilder, Fallback,
+};
 
 pub use inspectors::tx_inspector::TxInspector;
 pub use traits::*;
-pub use types::{BlockEnv, SimulationBatch, SimulationTx};
-pub use wrap_db::MyWrapDatabaseAsync;
+pub use types::{
+    BlockContext, BlockEnv, CancellationToken, EmptyCodeCallPolicy, FailurePolicy, SimulationBatch,
+    SimulationTx, TargetRef, TraceLevel, TransferRole,
+};
+pub use wrap_db::{
+    dependencies_digest, is_missing_state_error, DbStats, MyWrapDatabaseAsync, RemoteFetch,
+    RemoteFetchKind, RpcRateLimiter, WarmCache,
+};
 
 // Re-export core libraries for convenience
 pub use alloy;
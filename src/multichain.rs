@@ -0,0 +1,367 @@
+//! Scenarios spanning several forks
+//!
+//! Cross-chain flows (a message sent on one chain and delivered on another) need one
+//! EVM per chain and values carried from one simulation into the next.
+//! [`ScenarioRunner`] owns a named [`TraceEvm`] per chain and executes a script of
+//! [`Step`]s in order:
+//!
+//! - Each step runs a [`SimulationBatch`] on one chain
+//! - An [`Extractor`] pulls a value out of the step's trace (a log payload, a topic,
+//!   return data) under a name
+//! - Later steps reference the value with a placeholder (see [`Step::placeholder`]) in
+//!   their calldata, replaced by the raw extracted bytes before execution
+//!
+//! There is no cross-chain logic beyond that: no relaying, proofs or finality. Every
+//! step starts from its chain's fork state, as `trace_transactions` does for every
+//! batch.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! use alloy::primitives::{address, b256, TxKind};
+//! use revm_trace::{
+//!     multichain::{ExtractSource, Extractor, ScenarioRunner, Step},
+//!     SimulationBatch, SimulationTx,
+//! };
+//!
+//! let mut runner = ScenarioRunner::new();
+//! runner.add_fork("ethereum", "https://eth.llamarpc.com", None).await?;
+//! runner.add_fork("base", "https://mainnet.base.org", None).await?;
+//!
+//! let send = SimulationTx {
+//!     transact_to: TxKind::Call(address!("1000000000000000000000000000000000000001")),
+//!     ..Default::default()
+//! };
+//! let deliver = SimulationTx {
+//!     transact_to: TxKind::Call(address!("2000000000000000000000000000000000000002")),
+//!     data: [&[0xde, 0xad, 0xbe, 0xef][..], &Step::placeholder("payload")]
+//!         .concat()
+//!         .into(),
+//!     ..Default::default()
+//! };
+//! let batch = |tx| SimulationBatch {
+//!     transactions: vec![tx],
+//!     is_stateful: false,
+//!     ..Default::default()
+//! };
+//! let report = runner.run(vec![
+//!     Step {
+//!         chain: "ethereum".to_string(),
+//!         batch: batch(send),
+//!         extract: Some(Extractor {
+//!             name: "payload".to_string(),
+//!             tx_index: 0,
+//!             source: ExtractSource::LogData {
+//!                 address: None,
+//!                 topic0: Some(b256!("0000000000000000000000000000000000000000000000000000000000000001")),
+//!             },
+//!         }),
+//!     },
+//!     Step {
+//!         chain: "base".to_string(),
+//!         batch: batch(deliver),
+//!         extract: None,
+//!     },
+//! ])?;
+//! println!("payload: {}", report.values["payload"]);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+
+use crate::{
+    errors::ScenarioError,
+    evm::TraceEvm,
+    inspectors::tx_inspector::{TxInspector, TxTraceOutput},
+    report::SimulationReport,
+    traits::TransactionTrace,
+    types::{SimulationBatch, SimulationTx},
+};
+use alloy::primitives::{Address, Bytes, B256};
+use revm::database::{CacheDB, DatabaseRef};
+use serde::Serialize;
+
+/// Where an [`Extractor`] takes its value from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtractSource {
+    /// Data of the first log matching the emitter and first topic, when given
+    LogData {
+        address: Option<Address>,
+        topic0: Option<B256>,
+    },
+    /// Topic `index` of the first log matching the emitter and first topic
+    LogTopic {
+        address: Option<Address>,
+        topic0: Option<B256>,
+        index: usize,
+    },
+    /// Return data of the transaction's top-level call
+    ReturnData,
+}
+
+/// Named value pulled out of one transaction of a step
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extractor {
+    /// Name later steps refer to in placeholders
+    pub name: String,
+    /// Transaction of the step's batch to extract from
+    pub tx_index: usize,
+    /// What to extract
+    pub source: ExtractSource,
+}
+
+/// One batch of a scenario
+#[derive(Debug, Clone)]
+pub struct Step {
+    /// Name of the chain to run on, as registered with the runner
+    pub chain: String,
+    /// Transactions to run; calldata may contain placeholders of earlier extractions
+    pub batch: SimulationBatch,
+    /// Value to extract once the batch ran
+    pub extract: Option<Extractor>,
+}
+
+impl Step {
+    /// Returns the placeholder for the value extracted as `name`: the bytes of
+    /// `{{name}}`, to embed in calldata where the value goes
+    pub fn placeholder(name: &str) -> Vec<u8> {
+        format!("{{{{{name}}}}}").into_bytes()
+    }
+}
+
+/// Outcome of one step
+#[derive(Debug, Clone, Serialize)]
+pub struct StepReport {
+    /// Chain the step ran on
+    pub chain: String,
+    /// One report per transaction, with placeholders resolved in `tx`
+    pub reports: Vec<SimulationReport>,
+}
+
+/// Outcome of a whole scenario
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScenarioReport {
+    /// One entry per step, in order
+    pub steps: Vec<StepReport>,
+    /// Extracted values by name
+    pub values: BTreeMap<String, Bytes>,
+}
+
+impl ScenarioReport {
+    /// Returns the reports of the steps that ran on `chain`, in order
+    pub fn chain_steps<'a>(&'a self, chain: &'a str) -> impl Iterator<Item = &'a StepReport> {
+        self.steps.iter().filter(move |step| step.chain == chain)
+    }
+}
+
+/// Runs [`Step`]s across several named EVMs
+pub struct ScenarioRunner<DB> {
+    chains: BTreeMap<String, TraceEvm<CacheDB<DB>, TxInspector>>,
+}
+
+impl<DB> Default for ScenarioRunner<DB> {
+    fn default() -> Self {
+        Self {
+            chains: BTreeMap::new(),
+        }
+    }
+}
+
+impl<DB> ScenarioRunner<DB>
+where
+    DB: DatabaseRef,
+{
+    /// Creates a runner without chains
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `evm` under `name`, replacing any EVM registered under it
+    pub fn with_chain(
+        mut self,
+        name: impl Into<String>,
+        evm: TraceEvm<CacheDB<DB>, TxInspector>,
+    ) -> Self {
+        self.chains.insert(name.into(), evm);
+        self
+    }
+
+    /// Returns the EVM registered under `name`
+    pub fn chain_mut(&mut self, name: &str) -> Option<&mut TraceEvm<CacheDB<DB>, TxInspector>> {
+        self.chains.get_mut(name)
+    }
+
+    /// Runs `steps` in order, stopping at the first failing one
+    ///
+    /// Transactions that execute but revert do not fail the step. Extracting logs from
+    /// them does, as reverted calls leave none.
+    pub fn run(&mut self, steps: Vec<Step>) -> Result<ScenarioReport, ScenarioError> {
+        let mut report = ScenarioReport::default();
+        for (index, step) in steps.into_iter().enumerate() {
+            let Step {
+                chain,
+                mut batch,
+                extract,
+            } = step;
+            let evm = self
+                .chains
+                .get_mut(&chain)
+                .ok_or_else(|| ScenarioError::UnknownChain {
+                    step: index,
+                    chain: chain.clone(),
+                })?;
+            for tx in &mut batch.transactions {
+                tx.data = substitute(index, &tx.data, &report.values)?;
+            }
+
+            let transactions: Vec<SimulationTx> = batch.transactions.clone();
+            let mut reports = Vec::with_capacity(transactions.len());
+            for (tx_index, (tx, result)) in transactions
+                .into_iter()
+                .zip(evm.trace_transactions(batch))
+                .enumerate()
+            {
+                let (result, storage_diff, trace) =
+                    result.map_err(|source| ScenarioError::Execution {
+                        step: index,
+                        tx_index,
+                        source,
+                    })?;
+                reports.push(SimulationReport::new(tx, &result, storage_diff, trace));
+            }
+
+            if let Some(extractor) = extract {
+                let value = extract_value(&extractor, &reports).map_err(|reason| {
+                    ScenarioError::Extraction {
+                        step: index,
+                        name: extractor.name.clone(),
+                        reason: reason.to_string(),
+                    }
+                })?;
+                report.values.insert(extractor.name, value);
+            }
+            report.steps.push(StepReport { chain, reports });
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(any(feature = "default", feature = "rustls-tls"))]
+impl ScenarioRunner<crate::types::AllDBType> {
+    /// Forks `rpc_url` at `block_number` (latest if `None`) and registers the EVM
+    /// under `name`
+    pub async fn add_fork(
+        &mut self,
+        name: impl Into<String>,
+        rpc_url: &str,
+        block_number: Option<u64>,
+    ) -> Result<(), crate::errors::EvmError> {
+        let mut builder = crate::EvmBuilder::new_alloy(rpc_url).with_tracer(TxInspector::new());
+        if let Some(block_number) = block_number {
+            builder = builder.with_block_number(block_number);
+        }
+        self.chains.insert(name.into(), builder.build().await?);
+        Ok(())
+    }
+}
+
+/// Replaces every `{{name}}` placeholder in `data` by the value extracted as `name`
+fn substitute(
+    step: usize,
+    data: &Bytes,
+    values: &BTreeMap<String, Bytes>,
+) -> Result<Bytes, ScenarioError> {
+    let mut output = Vec::with_capacity(data.len());
+    let mut rest = data.as_ref();
+    while let Some(start) = find(rest, b"{{") {
+        let Some(len) = find(&rest[start + 2..], b"}}") else {
+            break;
+        };
+        let name = String::from_utf8_lossy(&rest[start + 2..start + 2 + len]);
+        let value =
+            values
+                .get(name.as_ref())
+                .ok_or_else(|| ScenarioError::UnresolvedPlaceholder {
+                    step,
+                    name: name.to_string(),
+                })?;
+        output.extend_from_slice(&rest[..start]);
+        output.extend_from_slice(value);
+        rest = &rest[start + len + 4..];
+    }
+    output.extend_from_slice(rest);
+    Ok(output.into())
+}
+
+/// Position of the first occurrence of `needle` in `haystack`
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn extract_value(
+    extractor: &Extractor,
+    reports: &[SimulationReport],
+) -> Result<Bytes, &'static str> {
+    let report = reports
+        .get(extractor.tx_index)
+        .ok_or("no such transaction in the step")?;
+    let trace: &TxTraceOutput = &report.trace;
+    let matching_log = |address: &Option<Address>, topic0: &Option<B256>| {
+        trace.logs.iter().find(|log| {
+            address.is_none_or(|address| log.address == address)
+                && topic0.is_none_or(|topic0| log.topics().first() == Some(&topic0))
+        })
+    };
+    match &extractor.source {
+        ExtractSource::LogData { address, topic0 } => matching_log(address, topic0)
+            .map(|log| log.data.data.clone())
+            .ok_or("no matching log"),
+        ExtractSource::LogTopic {
+            address,
+            topic0,
+            index,
+        } => matching_log(address, topic0)
+            .ok_or("no matching log")?
+            .topics()
+            .get(*index)
+            .map(|topic| Bytes::copy_from_slice(topic.as_slice()))
+            .ok_or("log has no such topic"),
+        ExtractSource::ReturnData => Ok(report.summary.output.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_substitute_placeholders() {
+        let values = BTreeMap::from([
+            ("a".to_string(), Bytes::from_static(&[0xaa, 0xaa])),
+            ("b".to_string(), Bytes::from_static(&[0xbb])),
+        ]);
+        let data = [
+            &[0x01][..],
+            &Step::placeholder("a"),
+            &[0x02],
+            &Step::placeholder("b"),
+            &Step::placeholder("a"),
+        ]
+        .concat();
+        assert_eq!(
+            substitute(0, &data.into(), &values).unwrap().as_ref(),
+            &[0x01, 0xaa, 0xaa, 0x02, 0xbb, 0xaa, 0xaa]
+        );
+
+        // Calldata without placeholders is kept as is
+        let plain = Bytes::from_static(&[0x7b, 0x7b, 0x00]);
+        assert_eq!(substitute(0, &plain, &values).unwrap(), plain);
+
+        assert!(matches!(
+            substitute(3, &Step::placeholder("c").into(), &values),
+            Err(ScenarioError::UnresolvedPlaceholder { step: 3, name }) if name == "c"
+        ));
+    }
+}
@@ -0,0 +1,475 @@
+//! Self-contained simulation reports
+//!
+//! A [`SimulationReport`] bundles everything known about one simulated transaction:
+//! the input, an execution summary, the storage diff and the full `TxTraceOutput`.
+//! Optional facts (balances before and after, account info) can be attached by the
+//! caller to enable richer analysis; consumers must treat them as possibly incomplete.
+//!
+//! With the `ndjson` feature, reports can be streamed to and from newline-delimited
+//! JSON, see [`NdjsonWriter`] and [`TraceEvm::trace_to_writer`].
+//!
+//! [`TraceEvm::trace_to_writer`]: crate::TraceEvm::trace_to_writer
+
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    analysis::payer::{payer_analysis_with_config, PayerAnalysis, PayerConfig},
+    inspectors::tx_inspector::TxTraceOutput,
+    types::{sorted_map, ExecutedTxEnv, SimulationTx, StorageDiff, TokenInfo},
+    utils::error_utils::parse_custom_error,
+    wrap_db::{dependencies_digest, RemoteFetch},
+};
+use alloy::{
+    dyn_abi::{DynSolValue, JsonAbiExt},
+    json_abi::Function,
+    primitives::{hex, Address, Bytes, B256, U256},
+};
+use revm::context_interface::result::{ExecutionResult, Output};
+use serde::{ser::SerializeSeq, Deserialize, Serialize, Serializer};
+
+#[cfg(feature = "ndjson")]
+mod ndjson;
+#[cfg(feature = "ndjson")]
+pub use ndjson::{NdjsonReader, NdjsonWriter, ReportOptions};
+mod request;
+pub use request::FeeStrategy;
+
+/// Divisor of the gas spent giving the maximum refund since London (EIP-3529)
+const MAX_REFUND_QUOTIENT: u64 = 5;
+
+/// Condensed outcome of a transaction execution
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExecutionSummary {
+    /// Whether the transaction succeeded
+    pub success: bool,
+    /// Total gas used (after refunds)
+    pub gas_used: u64,
+    /// Gas refunded at the end of execution
+    pub gas_refunded: u64,
+    /// Gas consumed before the refund was applied (`gas_used + gas_refunded`)
+    #[serde(default)]
+    pub gas_spent: u64,
+    /// Whether the refund reached the EIP-3529 cap of a fifth of `gas_spent`
+    ///
+    /// The uncapped refund counter is the sum of the `refund_counter_delta` of the
+    /// call frames whose effects persisted.
+    #[serde(default)]
+    pub refund_capped: bool,
+    /// Return data (or revert data)
+    pub output: Bytes,
+    /// Decoded revert or halt reason, if the transaction failed
+    pub error: Option<String>,
+    /// Return data decoded with `SimulationTx::return_abi`, one value per output
+    ///
+    /// Serialized as JSON values (numbers as decimal strings, bytes as hex), and not
+    /// restored by deserialization since the types are not serialized.
+    #[serde(
+        default,
+        skip_deserializing,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_values"
+    )]
+    pub decoded_return: Option<Vec<DynSolValue>>,
+    /// Why the return data did not match `SimulationTx::return_abi`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decode_error: Option<String>,
+}
+
+impl ExecutionSummary {
+    /// Builds a summary from a revm execution result
+    pub fn from_result(result: &ExecutionResult) -> Self {
+        match result {
+            ExecutionResult::Success {
+                gas_used,
+                gas_refunded,
+                output,
+                ..
+            } => Self {
+                success: true,
+                gas_used: *gas_used,
+                gas_refunded: *gas_refunded,
+                gas_spent: gas_used + gas_refunded,
+                refund_capped: *gas_refunded > 0
+                    && *gas_refunded == (gas_used + gas_refunded) / MAX_REFUND_QUOTIENT,
+                output: match output {
+                    Output::Call(data) => data.clone(),
+                    Output::Create(data, _) => data.clone(),
+                },
+                error: None,
+                decoded_return: None,
+                decode_error: None,
+            },
+            ExecutionResult::Revert { gas_used, output } => Self {
+                success: false,
+                gas_used: *gas_used,
+                gas_refunded: 0,
+                gas_spent: *gas_used,
+                refund_capped: false,
+                output: output.clone(),
+                error: Some(
+                    parse_custom_error(output)
+                        .unwrap_or_else(|| format!("0x{}", hex::encode(output))),
+                ),
+                decoded_return: None,
+                decode_error: None,
+            },
+            ExecutionResult::Halt { reason, gas_used } => Self {
+                success: false,
+                gas_used: *gas_used,
+                gas_refunded: 0,
+                gas_spent: *gas_used,
+                refund_capped: false,
+                output: Bytes::new(),
+                error: Some(format!("{reason:?}")),
+                decoded_return: None,
+                decode_error: None,
+            },
+        }
+    }
+
+    /// Decodes the return data with the outputs of `function`
+    ///
+    /// Decoding is strict: the data must be the exact ABI encoding of the outputs.
+    /// On a mismatch `decode_error` is set and `output` is kept as is. Failed
+    /// transactions are left untouched, their output is revert data.
+    pub fn decode_return(&mut self, function: &Function) {
+        if !self.success {
+            return;
+        }
+        let decoded = function
+            .abi_decode_output(&self.output)
+            .map_err(|e| e.to_string())
+            .and_then(|values| {
+                // Reject padding, trailing bytes and non-standard offsets
+                let encoded = DynSolValue::Tuple(values.clone()).abi_encode_params();
+                match encoded == self.output[..] {
+                    true => Ok(values),
+                    false => {
+                        Err("return data is not the standard encoding of the outputs".to_string())
+                    }
+                }
+            });
+        match decoded {
+            Ok(values) => {
+                self.decoded_return = Some(values);
+                self.decode_error = None;
+            }
+            Err(e) => {
+                self.decoded_return = None;
+                self.decode_error = Some(format!("{}: {e}", function.signature()));
+            }
+        }
+    }
+}
+
+/// One line summary, e.g. `success, gas used 43512, returned (18000000, [0x01])`
+///
+/// Decoded return values are shown with tuples (structs) in parentheses and arrays
+/// in brackets; without them the raw output is shown in hex.
+impl fmt::Display for ExecutionSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.success {
+            let reason = self.error.as_deref().unwrap_or("unknown reason");
+            return write!(f, "failed, gas used {}: {reason}", self.gas_used);
+        }
+        write!(f, "success, gas used {}", self.gas_used)?;
+        match &self.decoded_return {
+            Some(values) => write!(f, ", returned ({})", format_values(values)),
+            None if self.output.is_empty() => Ok(()),
+            None => write!(f, ", returned {}", self.output),
+        }
+    }
+}
+
+fn format_values(values: &[DynSolValue]) -> String {
+    values
+        .iter()
+        .map(format_value)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn format_value(value: &DynSolValue) -> String {
+    if let Some(fields) = value.as_tuple() {
+        return format!("({})", format_values(fields));
+    }
+    if let Some(items) = value.as_array().or_else(|| value.as_fixed_array()) {
+        return format!("[{}]", format_values(items));
+    }
+    match value {
+        DynSolValue::Bool(b) => b.to_string(),
+        DynSolValue::Int(v, _) => v.to_string(),
+        DynSolValue::Uint(v, _) => v.to_string(),
+        DynSolValue::Address(address) => address.to_string(),
+        DynSolValue::String(text) => format!("{text:?}"),
+        DynSolValue::Bytes(bytes) => hex::encode_prefixed(bytes),
+        DynSolValue::FixedBytes(word, size) => hex::encode_prefixed(&word[..*size]),
+        other => format!("{other:?}"),
+    }
+}
+
+/// Serializes decoded values as plain JSON values
+fn serialize_values<S: Serializer>(
+    values: &Option<Vec<DynSolValue>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match values {
+        Some(values) => JsonValues(values).serialize(serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+struct JsonValues<'a>(&'a [DynSolValue]);
+
+impl Serialize for JsonValues<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        for value in self.0 {
+            seq.serialize_element(&JsonValue(value))?;
+        }
+        seq.end()
+    }
+}
+
+struct JsonValue<'a>(&'a DynSolValue);
+
+impl Serialize for JsonValue<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let value = self.0;
+        if let Some(items) = value
+            .as_tuple()
+            .or_else(|| value.as_array())
+            .or_else(|| value.as_fixed_array())
+        {
+            return JsonValues(items).serialize(serializer);
+        }
+        match value {
+            DynSolValue::Bool(b) => serializer.serialize_bool(*b),
+            DynSolValue::String(text) => serializer.serialize_str(text),
+            other => serializer.serialize_str(&format_value(other)),
+        }
+    }
+}
+
+/// Balance of one holder for one token, captured before or after execution
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceSnapshot {
+    /// Token address (`NATIVE_TOKEN_ADDRESS` for the native token)
+    pub token: Address,
+    /// Balance holder
+    pub holder: Address,
+    /// Balance at the time of capture
+    pub balance: U256,
+}
+
+/// Pre-execution facts about an account
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountFacts {
+    /// Whether the account had deployed code
+    pub has_code: bool,
+    /// Account nonce
+    pub nonce: u64,
+}
+
+/// Known-contract label and code fingerprint of an address
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddressHint {
+    /// Name of a well-known contract, e.g. "Uniswap V2: Router"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Kind of the labeled contract, e.g. "router" or "token"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// keccak256 of the deployed code, `None` for accounts without code
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code_fingerprint: Option<B256>,
+}
+
+/// Contract whose code ran during a transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ContractDependency {
+    /// Account whose code was executed
+    pub address: Address,
+    /// Label from the report's labels or address hints, if any
+    pub label: Option<String>,
+}
+
+/// Complete report of a single simulated transaction
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SimulationReport {
+    /// Simulated transaction
+    pub tx: SimulationTx,
+    /// Execution outcome
+    pub summary: ExecutionSummary,
+    /// Storage slots changed by the transaction
+    pub storage_diff: StorageDiff,
+    /// Full trace collected by `TxInspector`
+    pub trace: TxTraceOutput,
+    /// Optional pre-execution balances
+    pub balances_before: Vec<BalanceSnapshot>,
+    /// Optional post-execution balances
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub balances_after: Vec<BalanceSnapshot>,
+    /// Optional pre-execution account facts
+    #[serde(serialize_with = "sorted_map::serialize")]
+    pub accounts: HashMap<Address, AccountFacts>,
+    /// Optional human-readable names of involved addresses (e.g. ENS names)
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "sorted_map::serialize"
+    )]
+    pub labels: HashMap<Address, String>,
+    /// Optional labels and code fingerprints of involved addresses
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "sorted_map::serialize"
+    )]
+    pub address_hints: HashMap<Address, AddressHint>,
+    /// Optional transaction environment the transaction was executed with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub executed_tx: Option<ExecutedTxEnv>,
+    /// Optional analysis of who bears the cost of the transaction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payer_analysis: Option<PayerAnalysis>,
+    /// Optional metadata of involved tokens, for formatting amounts
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "sorted_map::serialize"
+    )]
+    pub token_infos: HashMap<Address, TokenInfo>,
+    /// Optional state fetched from the remote node during execution, in order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_dependencies: Option<Vec<RemoteFetch>>,
+}
+
+impl SimulationReport {
+    /// Builds a report from the pieces returned by `trace_transactions`
+    ///
+    /// When the transaction carries a `return_abi`, its output is decoded, see
+    /// [`ExecutionSummary::decode_return`].
+    pub fn new(
+        tx: SimulationTx,
+        result: &ExecutionResult,
+        storage_diff: StorageDiff,
+        trace: TxTraceOutput,
+    ) -> Self {
+        let mut summary = ExecutionSummary::from_result(result);
+        if let Some(function) = &tx.return_abi {
+            summary.decode_return(function);
+        }
+        Self {
+            tx,
+            summary,
+            storage_diff,
+            trace,
+            ..Default::default()
+        }
+    }
+
+    /// Attaches pre-execution balance snapshots
+    pub fn with_balances(mut self, balances: Vec<BalanceSnapshot>) -> Self {
+        self.balances_before = balances;
+        self
+    }
+
+    /// Attaches post-execution balance snapshots
+    pub fn with_balances_after(mut self, balances: Vec<BalanceSnapshot>) -> Self {
+        self.balances_after = balances;
+        self
+    }
+
+    /// Attaches pre-execution account facts
+    pub fn with_accounts(mut self, accounts: HashMap<Address, AccountFacts>) -> Self {
+        self.accounts = accounts;
+        self
+    }
+
+    /// Attaches names of involved addresses
+    pub fn with_labels(mut self, labels: HashMap<Address, String>) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// Attaches labels and code fingerprints of involved addresses
+    pub fn with_address_hints(mut self, hints: HashMap<Address, AddressHint>) -> Self {
+        self.address_hints = hints;
+        self
+    }
+
+    /// Attaches metadata of involved tokens, see `erc20_utils::get_token_infos`
+    pub fn with_token_infos(mut self, token_infos: HashMap<Address, TokenInfo>) -> Self {
+        self.token_infos = token_infos;
+        self
+    }
+
+    /// Attaches the transaction environment, see `TraceEvm::take_last_executed_txs`
+    pub fn with_executed_tx(mut self, executed_tx: ExecutedTxEnv) -> Self {
+        self.executed_tx = Some(executed_tx);
+        self
+    }
+
+    /// Attaches the state fetched from the remote node, see
+    /// `TraceEvm::take_last_remote_dependencies`
+    pub fn with_remote_dependencies(mut self, fetches: Vec<RemoteFetch>) -> Self {
+        self.remote_dependencies = Some(fetches);
+        self
+    }
+
+    /// Hashes the attached remote dependencies into a single value, see
+    /// [`dependencies_digest`]
+    ///
+    /// Returns `None` when no remote dependencies are attached.
+    pub fn dependencies_digest(&self) -> Option<B256> {
+        self.remote_dependencies.as_deref().map(dependencies_digest)
+    }
+
+    /// Runs the payer heuristics of `config` on the report and attaches the result
+    ///
+    /// Attach the executed transaction environment first, so that reimbursements can
+    /// be capped at the fee actually paid.
+    pub fn with_payer_analysis(mut self, config: &PayerConfig) -> Self {
+        self.payer_analysis = Some(payer_analysis_with_config(&self, config));
+        self
+    }
+
+    /// Returns the recorded pre-execution balance of `holder` for `token`, if any
+    pub fn balance_before(&self, token: Address, holder: Address) -> Option<U256> {
+        self.balances_before
+            .iter()
+            .find(|snapshot| snapshot.token == token && snapshot.holder == holder)
+            .map(|snapshot| snapshot.balance)
+    }
+
+    /// Returns the recorded post-execution balance of `holder` for `token`, if any
+    pub fn balance_after(&self, token: Address, holder: Address) -> Option<U256> {
+        self.balances_after
+            .iter()
+            .find(|snapshot| snapshot.token == token && snapshot.holder == holder)
+            .map(|snapshot| snapshot.balance)
+    }
+
+    /// Lists the contracts whose code ran, sorted by address
+    ///
+    /// This is the set of code the outcome depends on: re-verify it before trusting a
+    /// simulation made at another block. Built from
+    /// [`TxTraceOutput::executed_code_addresses`], so it is empty below
+    /// `TraceLevel::Full`.
+    pub fn contract_dependency_list(&self) -> Vec<ContractDependency> {
+        let mut addresses: Vec<_> = self.trace.executed_code_addresses.iter().copied().collect();
+        addresses.sort_unstable();
+        addresses
+            .into_iter()
+            .map(|address| ContractDependency {
+                address,
+                label: self.labels.get(&address).cloned().or_else(|| {
+                    self.address_hints
+                        .get(&address)
+                        .and_then(|hint| hint.label.clone())
+                }),
+            })
+            .collect()
+    }
+}
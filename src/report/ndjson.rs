@@ -0,0 +1,188 @@
+//! Newline-delimited JSON streams of reports
+//!
+//! Large scanning jobs write each [`SimulationReport`] as soon as its transaction has
+//! been traced instead of collecting them: [`NdjsonWriter`] emits one compact JSON
+//! object per line and [`TraceEvm::trace_to_writer`] feeds it from a batch.
+//! [`NdjsonReader`] iterates the reports of such a stream back.
+//!
+//! Map-valued fields are serialized with sorted keys, so the same report always
+//! produces the same line.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Lines, Write},
+};
+
+use super::SimulationReport;
+use crate::{
+    analysis::PayerConfig, errors::EvmError, traits::ResetDB, types::SimulationBatch, TraceEvm,
+    TxInspector,
+};
+use alloy::primitives::Address;
+use revm::database::{Database, DatabaseCommit};
+
+/// Number of reports [`TraceEvm::trace_to_writer`] writes between flushes
+const REPORTS_PER_FLUSH: usize = 16;
+
+/// Writes reports as newline-delimited JSON
+///
+/// Every report becomes one line of compact JSON. Nothing is buffered by the writer
+/// itself; wrap files and sockets in a `BufWriter`.
+#[derive(Debug)]
+pub struct NdjsonWriter<W: Write> {
+    writer: W,
+    written: usize,
+}
+
+impl<W: Write> NdjsonWriter<W> {
+    /// Creates a writer emitting to `writer`
+    pub fn new(writer: W) -> Self {
+        Self { writer, written: 0 }
+    }
+
+    /// Writes `report` as one line
+    pub fn write(&mut self, report: &SimulationReport) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, report)?;
+        self.writer.write_all(b"\n")?;
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Flushes the underlying writer
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Returns the number of reports written so far
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Returns the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads reports back from newline-delimited JSON
+///
+/// Yields one report per non-empty line; lines that fail to parse yield an
+/// `io::ErrorKind::InvalidData` error and reading can continue past them.
+#[derive(Debug)]
+pub struct NdjsonReader<R> {
+    lines: Lines<R>,
+}
+
+impl<R: BufRead> NdjsonReader<R> {
+    /// Creates a reader over `reader`
+    pub fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for NdjsonReader<R> {
+    type Item = io::Result<SimulationReport>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e)),
+            };
+            if !line.trim().is_empty() {
+                return Some(serde_json::from_str(&line).map_err(io::Error::from));
+            }
+        }
+    }
+}
+
+/// Enrichments applied to the reports built by [`TraceEvm::trace_to_writer`]
+#[derive(Debug, Clone, Default)]
+pub struct ReportOptions {
+    /// Attach the environment each transaction was executed with
+    pub executed_tx: bool,
+    /// Run the payer heuristics with this configuration
+    pub payer_analysis: Option<PayerConfig>,
+    /// Names of involved addresses, attached to every report
+    pub labels: HashMap<Address, String>,
+}
+
+impl<DB> TraceEvm<DB, TxInspector>
+where
+    DB: Database + DatabaseCommit + ResetDB,
+{
+    /// Traces `batch` and writes a report of every transaction to `writer` as NDJSON
+    ///
+    /// Reports are written in batch order as soon as their transaction finishes, and
+    /// the writer is flushed every few reports and at the end, so the output can be
+    /// consumed while the batch runs. Transactions that could not be traced have no
+    /// report; they are returned with their batch index and error.
+    ///
+    /// # Errors
+    /// The first write error. Tracing still completes, but no further reports are
+    /// written.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use revm_trace::{
+    ///     create_evm_with_tracer, report::ReportOptions, types::SimulationBatch, TxInspector,
+    /// };
+    /// use std::{fs::File, io::BufWriter};
+    ///
+    /// let mut evm = create_evm_with_tracer("https://eth.llamarpc.com", TxInspector::new()).await?;
+    /// let batch = SimulationBatch {
+    ///     transactions: vec![/* transactions */],
+    ///     is_stateful: false,
+    ///     ..Default::default()
+    /// };
+    /// let file = BufWriter::new(File::create("reports.ndjson")?);
+    /// let failed = evm.trace_to_writer(batch, file, &ReportOptions::default())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn trace_to_writer<W: Write>(
+        &mut self,
+        batch: SimulationBatch,
+        writer: W,
+        options: &ReportOptions,
+    ) -> io::Result<Vec<(usize, EvmError)>> {
+        let mut writer = NdjsonWriter::new(writer);
+        let mut failed = Vec::new();
+        let mut write_error = None;
+        self.trace_transactions_with(batch, |index, tx, executed_tx, result| {
+            let (result, storage_diff, trace) = match result {
+                Ok(traced) => traced,
+                Err(e) => {
+                    failed.push((index, e));
+                    return;
+                }
+            };
+            if write_error.is_some() {
+                return;
+            }
+            let mut report = SimulationReport::new(tx, &result, storage_diff, trace)
+                .with_labels(options.labels.clone());
+            if let Some(executed_tx) = executed_tx.filter(|_| options.executed_tx) {
+                report = report.with_executed_tx(executed_tx.clone());
+            }
+            if let Some(config) = &options.payer_analysis {
+                report = report.with_payer_analysis(config);
+            }
+            let written = writer.write(&report).and_then(|()| {
+                if writer.written() % REPORTS_PER_FLUSH == 0 {
+                    writer.flush()
+                } else {
+                    Ok(())
+                }
+            });
+            write_error = written.err();
+        });
+        match write_error {
+            Some(e) => Err(e),
+            None => writer.flush().map(|()| failed),
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Turning a simulated transaction into one ready to sign and submit
+//!
+//! After a successful simulation the same transaction is often sent for real. The
+//! request built by [`SimulationReport::to_transaction_request`] carries the exact
+//! sender, nonce, target, value and calldata the simulation executed, its access
+//! list, a gas limit derived from the simulated gas and the fees of a
+//! [`FeeStrategy`]. Nothing is signed or sent.
+
+use super::SimulationReport;
+use crate::errors::TransactionRequestError;
+use alloy::{
+    primitives::keccak256,
+    rpc::types::{TransactionInput, TransactionRequest},
+};
+use serde::{Deserialize, Serialize};
+
+/// Fees of a transaction request built from a report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FeeStrategy {
+    /// EIP-1559 fees following the simulated block: a max fee of twice its base fee
+    /// plus `tip`, leaving room for six consecutive full blocks
+    UseBlockBaseFee {
+        /// Max priority fee per gas
+        tip: u128,
+    },
+    /// EIP-1559 fees as given
+    Fixed {
+        /// Max fee per gas
+        max_fee: u128,
+        /// Max priority fee per gas
+        tip: u128,
+    },
+    /// A single gas price; the request is an EIP-2930 transaction when it has an
+    /// access list and a legacy one otherwise
+    Legacy {
+        /// Gas price
+        gas_price: u128,
+    },
+}
+
+impl SimulationReport {
+    /// Builds a transaction request replaying the simulated transaction
+    ///
+    /// Sender, target, value, nonce, chain ID and access list come from the executed
+    /// transaction environment (see [`with_executed_tx`](Self::with_executed_tx)), so
+    /// targets resolved within a batch and access lists set by a tx hook carry over;
+    /// the calldata comes from the simulated transaction. The gas limit is the gas
+    /// the simulation spent before refunds, raised by `gas_margin_pct` percent and
+    /// rounded up, since refunds are only paid out after execution.
+    ///
+    /// The result is only as good as the simulation: with the base fee disabled (the
+    /// default of builder EVMs) `FeeStrategy::UseBlockBaseFee` sees a zero base fee.
+    ///
+    /// # Errors
+    /// - [`TransactionRequestError::FailedSimulation`] if the simulation failed and
+    ///   `allow_failed` is false
+    /// - [`TransactionRequestError::MissingExecutedTx`] without an executed
+    ///   transaction environment
+    /// - [`TransactionRequestError::CalldataMismatch`] if the executed calldata
+    ///   differs from the simulated transaction's
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use revm_trace::report::SimulationReport;
+    /// # fn example(report: SimulationReport) -> Result<(), Box<dyn std::error::Error>> {
+    /// use revm_trace::report::FeeStrategy;
+    ///
+    /// let request = report.to_transaction_request(
+    ///     FeeStrategy::UseBlockBaseFee { tip: 1_000_000_000 },
+    ///     20,
+    ///     false,
+    /// )?;
+    /// // ... sign and send `request` ...
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_transaction_request(
+        &self,
+        fee_strategy: FeeStrategy,
+        gas_margin_pct: u8,
+        allow_failed: bool,
+    ) -> Result<TransactionRequest, TransactionRequestError> {
+        if !self.summary.success && !allow_failed {
+            return Err(TransactionRequestError::FailedSimulation {
+                reason: self.summary.error.clone(),
+            });
+        }
+        let executed = self
+            .executed_tx
+            .as_ref()
+            .ok_or(TransactionRequestError::MissingExecutedTx)?;
+        if keccak256(&self.tx.data) != executed.data_hash {
+            return Err(TransactionRequestError::CalldataMismatch);
+        }
+
+        // Reports written before `gas_spent` existed only have the refunded amount
+        let gas_spent = self.summary.gas_spent.max(self.summary.gas_used);
+        let gas_limit = (u128::from(gas_spent) * (100 + u128::from(gas_margin_pct))).div_ceil(100);
+        let access_list =
+            (!executed.access_list.0.is_empty()).then(|| executed.access_list.clone());
+        let mut request = TransactionRequest {
+            from: Some(executed.caller),
+            to: Some(executed.kind),
+            value: Some(executed.value),
+            input: TransactionInput::new(self.tx.data.clone()),
+            nonce: Some(executed.nonce),
+            chain_id: executed.chain_id,
+            gas: Some(u64::try_from(gas_limit).unwrap_or(u64::MAX)),
+            ..Default::default()
+        };
+        match fee_strategy {
+            FeeStrategy::UseBlockBaseFee { tip } => {
+                request.max_fee_per_gas = Some(2 * u128::from(executed.block_base_fee) + tip);
+                request.max_priority_fee_per_gas = Some(tip);
+                request.transaction_type = Some(2);
+            }
+            FeeStrategy::Fixed { max_fee, tip } => {
+                request.max_fee_per_gas = Some(max_fee);
+                request.max_priority_fee_per_gas = Some(tip);
+                request.transaction_type = Some(2);
+            }
+            FeeStrategy::Legacy { gas_price } => {
+                request.gas_price = Some(gas_price);
+                request.transaction_type = Some(if access_list.is_some() { 1 } else { 0 });
+            }
+        }
+        request.access_list = access_list;
+        Ok(request)
+    }
+}
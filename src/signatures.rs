@@ -0,0 +1,268 @@
+//! Selector and event signature database (feature = "signatures")
+//!
+//! Resolves 4-byte function selectors and event `topic0` hashes to text signatures
+//! without needing contract ABIs. The database is a plain file maintained by the user
+//! or exported from 4byte.directory, in one of two formats:
+//!
+//! - **CSV**: one `key,signature` pair per line; empty lines and `#` comments are ignored
+//! - **JSON**: an object mapping keys to signatures
+//!
+//! Keys of 4 bytes are function selectors, keys of 32 bytes are event topics.
+//!
+//! Lookups are plain hash map accesses. A loaded database is immutable, so one instance
+//! can be wrapped in an `Arc` and shared by any number of simulations and threads.
+//!
+//! ```no_run
+//! # fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! use std::sync::Arc;
+//! use revm_trace::signatures::SignatureDb;
+//!
+//! let db = Arc::new(SignatureDb::from_file("signatures.csv")?);
+//! assert_eq!(
+//!     db.describe_input(&[0xa9, 0x05, 0x9c, 0xbb]),
+//!     "transfer(address,uint256)"
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{collections::HashMap, fmt, path::Path};
+
+use crate::{
+    errors::SignatureError,
+    inspectors::tx_inspector::TxTraceOutput,
+    types::{CallScheme, CallStatus, CallTrace, CreateScheme, TargetKind},
+};
+use alloy::primitives::{hex, keccak256, FixedBytes, Log, B256};
+
+/// In-memory selector and event signature database
+#[derive(Debug, Clone, Default)]
+pub struct SignatureDb {
+    /// Function selector → text signature
+    functions: HashMap<FixedBytes<4>, String>,
+    /// Event topic0 → text signature
+    events: HashMap<B256, String>,
+}
+
+impl SignatureDb {
+    /// Creates an empty database
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Loads a database from a file
+    ///
+    /// Files ending in `.json` are parsed as JSON, everything else as CSV.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, SignatureError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| SignatureError::Io {
+            path: path.display().to_string(),
+            reason: e.to_string(),
+        })?;
+        if path.extension().is_some_and(|ext| ext == "json") {
+            Self::from_json(&content)
+        } else {
+            Self::from_csv(&content)
+        }
+    }
+
+    /// Parses a CSV database of `key,signature` lines
+    pub fn from_csv(content: &str) -> Result<Self, SignatureError> {
+        let mut db = Self::new();
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            // Signatures contain commas themselves, so only split at the first one
+            let (key, signature) = line.split_once(',').ok_or_else(|| SignatureError::Parse {
+                entry: line.to_string(),
+                reason: "expected `key,signature`".to_string(),
+            })?;
+            db.insert(key.trim(), signature.trim())?;
+        }
+        Ok(db)
+    }
+
+    /// Parses a JSON object mapping keys to signatures
+    pub fn from_json(content: &str) -> Result<Self, SignatureError> {
+        let entries: HashMap<String, String> =
+            serde_json::from_str(content).map_err(|e| SignatureError::Parse {
+                entry: "<json>".to_string(),
+                reason: e.to_string(),
+            })?;
+        let mut db = Self::new();
+        for (key, signature) in &entries {
+            db.insert(key, signature)?;
+        }
+        Ok(db)
+    }
+
+    /// Inserts an entry keyed by a hex selector (4 bytes) or topic (32 bytes)
+    pub fn insert(&mut self, key: &str, signature: &str) -> Result<(), SignatureError> {
+        let invalid = |reason: String| SignatureError::Parse {
+            entry: format!("{key},{signature}"),
+            reason,
+        };
+        let bytes = hex::decode(key).map_err(|e| invalid(e.to_string()))?;
+        match bytes.len() {
+            4 => {
+                self.functions
+                    .insert(FixedBytes::from_slice(&bytes), signature.to_string());
+            }
+            32 => {
+                self.events
+                    .insert(B256::from_slice(&bytes), signature.to_string());
+            }
+            len => return Err(invalid(format!("key must be 4 or 32 bytes, got {len}"))),
+        }
+        Ok(())
+    }
+
+    /// Inserts a function signature, deriving its selector
+    pub fn insert_function(&mut self, signature: &str) {
+        let selector = FixedBytes::from_slice(&keccak256(signature)[..4]);
+        self.functions.insert(selector, signature.to_string());
+    }
+
+    /// Inserts an event signature, deriving its topic
+    pub fn insert_event(&mut self, signature: &str) {
+        self.events
+            .insert(keccak256(signature), signature.to_string());
+    }
+
+    /// Number of known function and event signatures
+    pub fn len(&self) -> usize {
+        self.functions.len() + self.events.len()
+    }
+
+    /// Whether the database is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Looks up the signature of the function called with `input`
+    pub fn function(&self, input: &[u8]) -> Option<&str> {
+        let selector = input.get(..4)?;
+        self.functions
+            .get(&FixedBytes::from_slice(selector))
+            .map(String::as_str)
+    }
+
+    /// Looks up the signature of an event topic
+    pub fn event(&self, topic0: &B256) -> Option<&str> {
+        self.events.get(topic0).map(String::as_str)
+    }
+
+    /// Describes call input: the text signature if known, otherwise the hex selector
+    ///
+    /// Empty input is described as `fallback`.
+    pub fn describe_input(&self, input: &[u8]) -> String {
+        match self.function(input) {
+            Some(signature) => signature.to_string(),
+            None if input.len() >= 4 => format!("0x{}", hex::encode(&input[..4])),
+            None if input.is_empty() => "fallback".to_string(),
+            None => format!("0x{}", hex::encode(input)),
+        }
+    }
+
+    /// Describes an event: `Name(<raw args hex>)` if known, otherwise the hex topic
+    ///
+    /// Raw args are the indexed topics followed by the data, without ABI decoding.
+    pub fn describe_log(&self, log: &Log) -> String {
+        let topics = log.topics();
+        let Some(topic0) = topics.first() else {
+            return format!("anonymous(0x{})", hex::encode(&log.data.data));
+        };
+        let mut args = Vec::new();
+        for topic in &topics[1..] {
+            args.extend_from_slice(topic.as_slice());
+        }
+        args.extend_from_slice(&log.data.data);
+        match self.event(topic0) {
+            Some(signature) => {
+                let name = signature.split('(').next().unwrap_or(signature);
+                format!("{name}(0x{})", hex::encode(args))
+            }
+            None => format!("{topic0}(0x{})", hex::encode(args)),
+        }
+    }
+
+    /// Renders a transaction trace as an indented call tree with resolved names
+    pub fn render<'a>(&'a self, output: &'a TxTraceOutput) -> TraceDisplay<'a> {
+        TraceDisplay { db: self, output }
+    }
+}
+
+/// Human-readable call tree, created by [`SignatureDb::render`]
+///
+/// Each frame is printed on its own line as `<kind> <target> <function> [<status>]`,
+/// followed by the events it emitted. Precompile frames show the precompile name as
+/// function, system contracts carry their name after the address.
+pub struct TraceDisplay<'a> {
+    db: &'a SignatureDb,
+    output: &'a TxTraceOutput,
+}
+
+impl fmt::Display for TraceDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(root) = &self.output.call_trace else {
+            return writeln!(f, "<no call trace>");
+        };
+        // Explicit stack instead of recursion, so deep call trees render safely
+        let mut stack = vec![(root, 0)];
+        while let Some((trace, depth)) = stack.pop() {
+            self.frame(f, trace, depth)?;
+            stack.extend(trace.subtraces.iter().rev().map(|sub| (sub, depth + 1)));
+        }
+        Ok(())
+    }
+}
+
+impl TraceDisplay<'_> {
+    /// Writes one frame and its events, without subtraces
+    fn frame(&self, f: &mut fmt::Formatter<'_>, trace: &CallTrace, depth: usize) -> fmt::Result {
+        let indent = "  ".repeat(depth);
+        let (kind, function) = match (trace.call_scheme, trace.create_scheme) {
+            (_, Some(CreateScheme::Create2 { .. })) => ("CREATE2", "constructor".to_string()),
+            (_, Some(_)) => ("CREATE", "constructor".to_string()),
+            (scheme, None) => (
+                match scheme {
+                    Some(CallScheme::CallCode) => "CALLCODE",
+                    Some(CallScheme::DelegateCall) => "DELEGATECALL",
+                    Some(CallScheme::StaticCall) => "STATICCALL",
+                    _ => "CALL",
+                },
+                match trace.target_kind {
+                    // Precompile input is raw data, not a function call
+                    TargetKind::Precompile(precompile) => precompile.name().to_string(),
+                    _ => self.db.describe_input(&trace.input),
+                },
+            ),
+        };
+        let target = match trace.target_kind {
+            TargetKind::SystemContract(name) => format!("{} ({name})", trace.to),
+            _ => trace.to.to_string(),
+        };
+        let status = match &trace.status {
+            CallStatus::Success => "ok".to_string(),
+            CallStatus::Revert { .. } => {
+                format!("revert: {}", trace.status.message().unwrap_or_default())
+            }
+            CallStatus::Halt { raw, .. } => format!("halt: {raw}"),
+            CallStatus::FatalError => "fatal error".to_string(),
+            CallStatus::InProgress => "in progress".to_string(),
+        };
+        writeln!(f, "{indent}{kind} {target} {function} [{status}]")?;
+        for log in trace
+            .log_indices
+            .iter()
+            .filter_map(|&index| self.output.logs.get(index))
+        {
+            writeln!(f, "{indent}  emit {}", self.db.describe_log(log))?;
+        }
+        if trace.truncated {
+            writeln!(f, "{indent}  ... deeper calls truncated")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,294 @@
+//! Importing state from other tools and exporting the simulated state (feature = "state-import")
+//!
+//! Teams share reproducible environments as Anvil state dumps or genesis allocs. The
+//! loaders parse the accounts of such a document (balance, nonce, code and storage)
+//! and write them into the EVM's `CacheDB` overlay; [`export_cache_state`] writes the
+//! overlay back out in Anvil's format, so a simulated state can be handed to Anvil or
+//! to another simulation.
+//!
+//! Two formats are read:
+//! - Anvil state dumps, as written by `anvil --dump-state` (`anvil_dumpState` returns
+//!   the same JSON gzip-compressed): `{ "accounts": { "0x…": { "nonce": 0, "balance":
+//!   "0x…", "code": "0x…", "storage": { "0x0": "0x1" } } } }`. Blocks and transactions
+//!   of the dump are ignored.
+//! - Genesis allocs, either a full genesis file with an `alloc` object or the bare
+//!   alloc object mapping addresses to accounts.
+//!
+//! Imported accounts replace whatever the overlay held for them, storage included:
+//! slots missing from the document read as zero rather than from upstream. Entries
+//! that cannot be read (bad address, bad account) are skipped and listed in the
+//! [`ImportSummary`] instead of failing the whole import.
+//!
+//! `trace_transactions` clears the overlay at the start of every batch. To simulate
+//! batches against an imported state without RPC access, load it into the in-memory
+//! backend beneath the overlay with the `_into` variants:
+//!
+//! ```no_run
+//! # fn example(dump: &str) -> Result<(), Box<dyn std::error::Error>> {
+//! use revm::{
+//!     database::{CacheDB, EmptyDB},
+//!     Context, MainBuilder, MainContext,
+//! };
+//! use revm_trace::{state_import::load_anvil_state_into, TraceEvm, TxInspector};
+//!
+//! let mut backend = CacheDB::new(EmptyDB::default());
+//! let summary = load_anvil_state_into(&mut backend, dump)?;
+//! println!("{} accounts, {} skipped", summary.accounts, summary.skipped.len());
+//! let mut ctx = Context::mainnet().with_db(CacheDB::new(backend));
+//! ctx.cfg.disable_base_fee = true;
+//! let evm = TraceEvm::new(ctx.build_mainnet_with_inspector(TxInspector::new()));
+//! // ... evm.trace_transactions(batch) ...
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    str::FromStr,
+};
+
+use crate::{errors::StateImportError, evm::TraceEvm};
+use alloy::{
+    genesis::GenesisAccount,
+    primitives::{Address, Bytes, U256},
+};
+use revm::{
+    database::{in_memory_db::AccountState, CacheDB, DatabaseRef},
+    state::{AccountInfo, Bytecode},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Accounts of an Anvil state dump
+///
+/// Serializes to a document `anvil --load-state` accepts.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnvilStateJson {
+    pub accounts: BTreeMap<Address, AnvilAccountRecord>,
+}
+
+/// One account of an [`AnvilStateJson`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnvilAccountRecord {
+    #[serde(default)]
+    pub nonce: u64,
+    /// Native balance in wei
+    #[serde(default)]
+    pub balance: U256,
+    /// Runtime code, empty for accounts without code
+    #[serde(default)]
+    pub code: Bytes,
+    #[serde(default)]
+    pub storage: BTreeMap<U256, U256>,
+}
+
+/// Outcome of an import
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ImportSummary {
+    /// Number of accounts written
+    pub accounts: usize,
+    /// Number of written accounts with code
+    pub contracts: usize,
+    /// Number of storage slots written
+    pub storage_slots: usize,
+    /// Entries that were not imported
+    pub skipped: Vec<SkippedEntry>,
+}
+
+/// Entry of an imported document that was not imported
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SkippedEntry {
+    /// Key of the entry as written in the document
+    pub key: String,
+    /// Why the entry was skipped
+    pub reason: String,
+}
+
+/// Loads the accounts of an Anvil state dump into the EVM's `CacheDB` overlay
+///
+/// See the [module documentation](self) for the format and the overlay lifetime.
+///
+/// # Errors
+/// - [`StateImportError::Parse`] if `json` is not an object with an `accounts` object
+/// - [`StateImportError::Insert`] if an account could not be written
+pub fn load_anvil_state<DB, INSP>(
+    evm: &mut TraceEvm<CacheDB<DB>, INSP>,
+    json: &str,
+) -> Result<ImportSummary, StateImportError>
+where
+    DB: DatabaseRef,
+{
+    load_anvil_state_into(evm.db(), json)
+}
+
+/// Loads the accounts of an Anvil state dump into `db`
+///
+/// Same as [`load_anvil_state`], for databases not yet wrapped in an EVM.
+pub fn load_anvil_state_into<DB: DatabaseRef>(
+    db: &mut CacheDB<DB>,
+    json: &str,
+) -> Result<ImportSummary, StateImportError> {
+    #[derive(Deserialize)]
+    struct Document {
+        accounts: Map<String, Value>,
+    }
+    let document: Document =
+        serde_json::from_str(json).map_err(|e| StateImportError::Parse(e.to_string()))?;
+    import_accounts(db, document.accounts, serde_json::from_value)
+}
+
+/// Loads the accounts of a genesis alloc into the EVM's `CacheDB` overlay
+///
+/// `json` is either a genesis file with an `alloc` object or the alloc object
+/// itself. Private keys of alloc entries are ignored. See the
+/// [module documentation](self) for the overlay lifetime.
+///
+/// # Errors
+/// - [`StateImportError::Parse`] if `json` is not a JSON object, or its `alloc` is
+///   not an object
+/// - [`StateImportError::Insert`] if an account could not be written
+pub fn load_genesis_alloc<DB, INSP>(
+    evm: &mut TraceEvm<CacheDB<DB>, INSP>,
+    json: &str,
+) -> Result<ImportSummary, StateImportError>
+where
+    DB: DatabaseRef,
+{
+    load_genesis_alloc_into(evm.db(), json)
+}
+
+/// Loads the accounts of a genesis alloc into `db`
+///
+/// Same as [`load_genesis_alloc`], for databases not yet wrapped in an EVM.
+pub fn load_genesis_alloc_into<DB: DatabaseRef>(
+    db: &mut CacheDB<DB>,
+    json: &str,
+) -> Result<ImportSummary, StateImportError> {
+    let mut document: Map<String, Value> =
+        serde_json::from_str(json).map_err(|e| StateImportError::Parse(e.to_string()))?;
+    let alloc = match document.remove("alloc") {
+        Some(Value::Object(alloc)) => alloc,
+        Some(_) => return Err(StateImportError::Parse("alloc is not an object".into())),
+        None => document,
+    };
+    import_accounts(db, alloc, |value| {
+        let account: GenesisAccount = serde_json::from_value(value)?;
+        Ok(AnvilAccountRecord {
+            nonce: account.nonce.unwrap_or_default(),
+            balance: account.balance,
+            code: account.code.unwrap_or_default(),
+            storage: account
+                .storage
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(slot, value)| (U256::from_be_bytes(slot.0), U256::from_be_bytes(value.0)))
+                .collect(),
+        })
+    })
+}
+
+/// Captures the accounts of the EVM's `CacheDB` overlay as an Anvil state dump
+///
+/// Covers everything the overlay holds: accounts and storage loaded from upstream,
+/// inserted, or committed by stateful batches. Storage is limited to the slots in the
+/// overlay, so the export of an RPC-backed EVM is only complete for the slots that
+/// were read or written. Accounts cached as missing upstream are left out.
+///
+/// Importing the export reproduces the overlay's accounts and slots.
+pub fn export_cache_state<DB, INSP>(evm: &mut TraceEvm<CacheDB<DB>, INSP>) -> AnvilStateJson
+where
+    DB: DatabaseRef,
+{
+    let cache = &evm.db().cache;
+    let accounts = cache
+        .accounts
+        .iter()
+        .filter(|(_, account)| account.account_state != AccountState::NotExisting)
+        .map(|(address, account)| {
+            let code = account
+                .info
+                .code
+                .as_ref()
+                .or_else(|| cache.contracts.get(&account.info.code_hash))
+                .map(Bytecode::original_bytes)
+                .unwrap_or_default();
+            let record = AnvilAccountRecord {
+                nonce: account.info.nonce,
+                balance: account.info.balance,
+                code,
+                storage: account.storage.iter().map(|(k, v)| (*k, *v)).collect(),
+            };
+            (*address, record)
+        })
+        .collect();
+    AnvilStateJson { accounts }
+}
+
+/// Parses every entry of `accounts` with `parse` and writes it into `db`, skipping
+/// entries that cannot be read
+fn import_accounts<DB: DatabaseRef>(
+    db: &mut CacheDB<DB>,
+    accounts: Map<String, Value>,
+    parse: impl Fn(Value) -> Result<AnvilAccountRecord, serde_json::Error>,
+) -> Result<ImportSummary, StateImportError> {
+    let mut summary = ImportSummary::default();
+    let mut seen = HashSet::new();
+    for (key, value) in accounts {
+        let address = match Address::from_str(&key) {
+            Ok(address) => address,
+            Err(e) => {
+                summary.skip(key, format!("invalid address: {e}"));
+                continue;
+            }
+        };
+        // Keys differing only in case name the same account
+        if !seen.insert(address) {
+            summary.skip(key, "duplicate of an earlier entry".into());
+            continue;
+        }
+        let record = match parse(value) {
+            Ok(record) => record,
+            Err(e) => {
+                summary.skip(key, format!("invalid account: {e}"));
+                continue;
+            }
+        };
+        insert_account(db, address, record, &mut summary)?;
+    }
+    Ok(summary)
+}
+
+/// Replaces the overlay entry of `address` with `record`
+fn insert_account<DB: DatabaseRef>(
+    db: &mut CacheDB<DB>,
+    address: Address,
+    record: AnvilAccountRecord,
+    summary: &mut ImportSummary,
+) -> Result<(), StateImportError> {
+    let mut info = AccountInfo {
+        balance: record.balance,
+        nonce: record.nonce,
+        ..Default::default()
+    };
+    if !record.code.is_empty() {
+        let bytecode = Bytecode::new_raw(record.code);
+        info.code_hash = bytecode.hash_slow();
+        info.code = Some(bytecode);
+        summary.contracts += 1;
+    }
+    db.insert_account_info(address, info);
+    summary.storage_slots += record.storage.len();
+    db.replace_account_storage(address, record.storage.into_iter().collect())
+        .map_err(|e| StateImportError::Insert {
+            address,
+            reason: e.to_string(),
+        })?;
+    summary.accounts += 1;
+    Ok(())
+}
+
+impl ImportSummary {
+    fn skip(&mut self, key: String, reason: String) {
+        self.skipped.push(SkippedEntry { key, reason });
+    }
+}
@@ -1,7 +1,12 @@
 use crate::errors::EvmError;
-use crate::types::{SimulationBatch, StorageDiff};
+use crate::evm::timeline::TimelineEvent;
+use crate::types::{
+    CancellationToken, SimulationBatch, SimulationWarning, StorageDiff, TraceLevel,
+};
+use alloy::primitives::{B256, U256};
 use revm::context_interface::result::ExecutionResult;
 use revm::inspector::{Inspector, NoOpInspector};
+use revm::state::EvmState;
 
 /// Defines how an inspector converts its state to a specific output type
 ///
@@ -36,6 +41,76 @@ pub trait TraceOutput {
     /// This method should collect all relevant information from the
     /// inspector and return it in the specified output format.
     fn get_output(&self) -> Self::Output;
+
+    /// Moves the current transaction's output out of the inspector
+    ///
+    /// Called once per transaction, right before the inspector is reset for the
+    /// next one, so implementations may leave their collections empty instead of
+    /// cloning them. Defaults to [`get_output`](Self::get_output).
+    fn take_output(&mut self) -> Self::Output {
+        self.get_output()
+    }
+
+    /// Receives the post-execution state before the output is collected
+    ///
+    /// Called once per transaction with the final account states, so inspectors can
+    /// reconcile hook-level observations with what actually persisted (e.g. reverted
+    /// creations). The default implementation does nothing.
+    fn apply_post_state(&mut self, _state: &EvmState) {}
+
+    /// Selects how much data to collect for the next transaction
+    ///
+    /// Called after `reset` for every transaction. `TraceLevel::Off` never reaches the
+    /// inspector hooks. The default implementation ignores the level.
+    fn set_trace_level(&mut self, _level: TraceLevel) {}
+
+    /// Receives a diagnosis of the next transaction, found before it executes
+    ///
+    /// Called after `set_trace_level`, at every trace level. The default
+    /// implementation drops the warning.
+    fn record_warning(&mut self, _warning: SimulationWarning) {}
+
+    /// Selects whether the mutations of the following transactions are recorded for a
+    /// batch timeline
+    ///
+    /// Called with `true` before a batch with `build_timeline` set, and with `false`
+    /// once it finished. The setting outlives `reset`. The default implementation
+    /// ignores it.
+    fn set_timeline(&mut self, _enabled: bool) {}
+
+    /// Moves the mutations recorded for the last transaction out of the inspector
+    ///
+    /// Called after every transaction of a batch with `build_timeline` set. Events are
+    /// returned in execution order; `trace_transactions` assigns their `tx_index` and
+    /// batch-wide `seq`. The default implementation records nothing.
+    fn take_timeline(&mut self) -> Vec<TimelineEvent> {
+        Vec::new()
+    }
+
+    /// Hands over the cancellation token of the following transactions
+    ///
+    /// Called with the token before a batch with one set, and with `None` once it
+    /// finished. Inspectors checking the token while executing can halt a cancelled
+    /// transaction early; `trace_transactions` checks it between transactions and
+    /// discards the transaction that was running either way. The default
+    /// implementation ignores the token.
+    fn set_cancellation(&mut self, _token: Option<CancellationToken>) {}
+
+    /// Estimates the memory footprint of an output in bytes
+    ///
+    /// Reported as `trace_size_bytes` in batch metrics. The default implementation
+    /// returns the inline size of the output type, ignoring heap allocations.
+    fn output_size(output: &Self::Output) -> usize {
+        std::mem::size_of_val(output)
+    }
+
+    /// Total value sent to the block coinbase by calls, as recorded in an output
+    ///
+    /// Used to reconcile the coinbase balance in fee accounting. The default
+    /// implementation returns `None`: the inspector does not track such payments.
+    fn coinbase_payments(_output: &Self::Output) -> Option<U256> {
+        None
+    }
 }
 
 /// Defines how an inspector can reset its internal state
@@ -43,6 +118,11 @@ pub trait TraceOutput {
 /// This trait is crucial for inspectors that maintain state between transactions
 /// and need to clear that state before processing a new transaction.
 ///
+/// `trace_transactions` calls `reset_batch` once when a batch starts and `reset_tx`
+/// before every transaction and once more after the last one. Both default to
+/// `reset`; inspectors accumulating data across a batch override `reset_tx` to keep
+/// it, so it can still be read once the batch returns.
+///
 /// # Example
 /// ```
 /// use revm_trace::traits::Reset;
@@ -66,6 +146,21 @@ pub trait Reset {
     /// preparing the inspector for a new transaction.
     fn reset(&mut self);
 
+    /// Resets the per-transaction state before the next transaction of a batch
+    ///
+    /// The default implementation calls `reset`.
+    fn reset_tx(&mut self) {
+        self.reset();
+    }
+
+    /// Resets all state, including data accumulated across transactions, before a
+    /// batch starts
+    ///
+    /// The default implementation calls `reset`.
+    fn reset_batch(&mut self) {
+        self.reset();
+    }
+
     /// Resets the slot cache used by the inspector
     fn reset_slot_cache(&mut self);
 }
@@ -262,12 +357,16 @@ pub trait TransactionTrace<CTX> {
 
 /// Defines the ability to reset database state
 ///
-/// This trait is implemented by EVM instances that support resetting
-/// their underlying database to a clean state, typically used for:
+/// This trait is implemented by databases that support resetting to a clean
+/// state, and by every `TraceEvm` over such a database. It is typically used for:
 ///
 /// - Batch processing where each transaction should start from the same state
 /// - Testing scenarios requiring clean state isolation
 /// - Simulation environments needing state rollback capabilities
+///
+/// `CacheDB` implements it by clearing its overlay. Implement it for a custom
+/// database to run batches on it with `TransactionTrace`; `trace_transactions`
+/// resets the database at the start of every batch.
 pub trait ResetDB {
     /// Resets the database to its initial state
     ///
@@ -325,6 +424,19 @@ pub trait ResetBlock {
     ///
     /// Returns an error if the block reset operation fails.
     fn reset_block(&mut self, block_number: u64) -> Result<(), Self::Error>;
+
+    /// Resets to the block with the given hash, so reorgs cannot change the state read
+    ///
+    /// Defaults to [`reset_block`](Self::reset_block) for databases that can only be
+    /// pinned by number.
+    ///
+    /// # Arguments
+    /// * `block_number` - Number of the block
+    /// * `block_hash` - Hash of the block
+    fn reset_block_hash(&mut self, block_number: u64, block_hash: B256) -> Result<(), Self::Error> {
+        let _ = block_hash;
+        self.reset_block(block_number)
+    }
 }
 
 // Note: NoOpInspector automatically implements TraceInspector<CTX> through the blanket implementation:
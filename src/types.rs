@@ -1,4 +1,25 @@
-use std::collections::HashMap;
+//! Data types shared by the EVM, the inspectors and the reports
+//!
+//! # Ordering Guarantees
+//!
+//! Serialized output only depends on what was simulated, so tracing the same batch
+//! twice yields byte-identical JSON:
+//! - [`StorageDiff`] is ordered by contract address, the accesses of each contract by slot
+//! - Fields held in hash maps, such as `keccak_preimages` or the accounts and labels of a
+//!   report, serialize with their keys in ascending order
+//! - [`FrameDiffs`] serialize in trace address order
+//! - Transfers, logs and call frames keep execution order and carry stable ids:
+//!   [`TokenTransfer::transfer_index`], the `log_index` of every serialized log, and
+//!   [`CallTrace::frame_id`]
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
 /// Override state for contract storage during simulation
 #[derive(Debug, Clone, Default)]
@@ -7,7 +28,24 @@ pub struct StateOverride {
     pub balances: HashMap<Address, U256>,              // address-balance
 }
 
-pub type StorageDiff = HashMap<Address, Vec<SlotAccess>>;
+/// Temporary changes to one account, applied by `TraceEvm::with_ephemeral_state`
+///
+/// Fields left at `None` (or empty) keep the account's current value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountOverride {
+    pub address: Address,
+    /// Native balance in wei
+    pub balance: Option<U256>,
+    pub nonce: Option<u64>,
+    /// Runtime code; empty code turns the account into an EOA
+    pub code: Option<Bytes>,
+    /// Storage slots to write, as `(slot, value)`
+    pub storage: Vec<(U256, U256)>,
+}
+
+/// Storage slots changed by a transaction, by contract address, each contract's
+/// accesses ordered by slot
+pub type StorageDiff = BTreeMap<Address, Vec<SlotAccess>>;
 
 /// SlotAccessType , used to filter slot access types
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -18,43 +56,112 @@ pub enum SlotAccessType {
 }
 
 impl CallTrace {
-    /// Internal recursive function to collect all slot accesses with filter
-    fn collect_slot_accesses<'a>(&'a self, filter: SlotAccessType, out: &mut Vec<&'a SlotAccess>) {
-        for access in &self.slot_accesses {
-            match filter {
-                SlotAccessType::All => out.push(access),
-                SlotAccessType::Read if !access.is_write => out.push(access),
-                SlotAccessType::Write if access.is_write => out.push(access),
-                _ => {}
-            }
-        }
-        for sub in &self.subtraces {
-            sub.collect_slot_accesses(filter, out);
+    /// Returns all slot_accesses references (filtered by type: Read, Write, or All)
+    pub fn all_slot_accesses(&self, filter: SlotAccessType) -> Vec<&SlotAccess> {
+        self.frames()
+            .flat_map(|frame| &frame.slot_accesses)
+            .filter(|access| match filter {
+                SlotAccessType::All => true,
+                SlotAccessType::Read => !access.is_write,
+                SlotAccessType::Write => access.is_write,
+            })
+            .collect()
+    }
+
+    /// Whether the frame transfers native value
+    ///
+    /// Delegatecalls report the value of their caller but transfer nothing.
+    pub fn transfers_value(&self) -> bool {
+        self.value > U256::ZERO
+            && !matches!(
+                self.call_scheme,
+                Some(CallScheme::DelegateCall | CallScheme::StaticCall)
+            )
+    }
+
+    /// Whether the frame ran code, as opposed to a value transfer or a call to an
+    /// account without code
+    ///
+    /// Exact when opcodes were counted. Otherwise falls back to the kind of the
+    /// target: contracts (including system contracts and contracts created in the
+    /// transaction) count as executed, precompiles and accounts without code do not.
+    pub fn executed_code(&self) -> bool {
+        match self.opcodes_executed {
+            Some(count) => count > 0,
+            None => matches!(
+                self.target_kind,
+                TargetKind::Contract | TargetKind::SystemContract(_) | TargetKind::Created
+            ),
         }
     }
 
-    /// Returns all slot_accesses references (filtered by type: Read, Write, or All)
-    pub fn all_slot_accesses(&self, filter: SlotAccessType) -> Vec<&SlotAccess> {
-        let mut result = Vec::new();
-        self.collect_slot_accesses(filter, &mut result);
-        result
+    /// Iterates over this frame and all nested frames in execution order (pre-order)
+    ///
+    /// Uses an explicit stack, so arbitrarily deep call trees can be walked without
+    /// exhausting the thread stack.
+    pub fn frames(&self) -> CallTraceFrames<'_> {
+        CallTraceFrames { stack: vec![self] }
     }
+
+    /// Builds the [`frame_id`](Self::frame_id) of the frame at `trace_address`
+    pub fn frame_id_for(trace_address: &[usize]) -> String {
+        trace_address
+            .iter()
+            .map(usize::to_string)
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    /// Returns the nested frame at `trace_address`, relative to this frame
+    ///
+    /// Each index selects a subcall in execution order; an empty address is this
+    /// frame itself.
+    pub fn frame_at(&self, trace_address: &[usize]) -> Option<&CallTrace> {
+        trace_address
+            .iter()
+            .try_fold(self, |frame, &index| frame.subtraces.get(index))
+    }
+}
+
+/// Pre-order iterator over a call tree, created by [`CallTrace::frames`]
+pub struct CallTraceFrames<'a> {
+    stack: Vec<&'a CallTrace>,
 }
 
-use crate::MyWrapDatabaseAsync;
+impl<'a> Iterator for CallTraceFrames<'a> {
+    type Item = &'a CallTrace;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.stack.pop()?;
+        self.stack.extend(frame.subtraces.iter().rev());
+        Some(frame)
+    }
+}
+
+use crate::{errors::ConversionError, MyWrapDatabaseAsync};
 use alloy::{
+    consensus::{transaction::SignerRecoverable, Transaction, TxEnvelope},
+    eips::{eip2718::Decodable2718, eip2930::AccessList, BlockNumberOrTag, Typed2718},
+    json_abi::Function,
     network::AnyNetwork,
-    primitives::{fixed_bytes, Address, Bytes, FixedBytes, Log, TxKind, U256},
+    primitives::{
+        fixed_bytes, keccak256, Address, Bytes, FixedBytes, Log, TxKind, B256, I256, U256,
+    },
     providers::{
         fillers::{BlobGasFiller, ChainIdFiller, FillProvider, GasFiller, JoinFill, NonceFiller},
         Identity, RootProvider,
     },
+    rpc::types::TransactionRequest,
 };
 pub use revm::{
     context::BlockEnv,
     database::AlloyDB,
     interpreter::{CallScheme, CreateScheme},
 };
+use revm::{
+    context::TxEnv,
+    context_interface::result::{ExecutionResult, HaltReason},
+};
 use serde::{Deserialize, Serialize};
 
 pub const ERC20_TRANSFER_EVENT_SIGNATURE: FixedBytes<32> =
@@ -111,7 +218,33 @@ pub const NATIVE_TOKEN_ADDRESS: Address = Address::ZERO;
 
 pub type AllDBType = MyWrapDatabaseAsync<AlloyDB<AnyNetwork, AnyNetworkProvider>>;
 
-#[derive(Debug, Clone, Serialize)]
+/// Block a `TraceEvm` was built against
+///
+/// Records exactly which chain state a simulation ran on, so results can be
+/// reproduced or checked against reorgs later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct BlockContext {
+    /// Block number
+    pub number: u64,
+    /// Block hash (`B256::ZERO` if unknown, e.g. after `set_db_block`)
+    pub hash: B256,
+    /// Block timestamp
+    pub timestamp: u64,
+    /// Chain ID of the network
+    pub chain_id: u64,
+    /// Tag the block was resolved from, see `EvmBuilder::with_block_tag`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<BlockTag>,
+}
+
+impl BlockContext {
+    /// Returns the built-in accounting preset of this chain, if any
+    pub fn chain_preset(&self) -> Option<crate::chains::ChainPreset> {
+        crate::chains::ChainPreset::from_chain_id(self.chain_id)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenInfo {
     pub name: String,
     /// Token symbol (e.g., "ETH", "USDC")
@@ -122,7 +255,59 @@ pub struct TokenInfo {
     pub total_supply: U256,
 }
 
-#[derive(Debug, Clone)]
+/// Amount of tracing performed for a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TraceLevel {
+    /// Execute without inspector hooks; output collections stay empty
+    Off,
+    /// Collect asset transfers only
+    TransfersOnly,
+    /// Collect everything (call tree, logs, slot accesses, transfers)
+    #[default]
+    Full,
+}
+
+/// Named block an EVM can be built at, resolved by the node
+///
+/// `Safe` and `Finalized` need a chain with a consensus layer reporting finality;
+/// pre-merge chains and many L2 nodes do not support them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockTag {
+    /// Most recent block, may still be reorganized
+    Latest,
+    /// Most recent block attested by a majority of validators
+    Safe,
+    /// Most recent finalized block, cannot be reorganized
+    Finalized,
+    /// Genesis block
+    Earliest,
+}
+
+impl fmt::Display for BlockTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tag = match self {
+            Self::Latest => "latest",
+            Self::Safe => "safe",
+            Self::Finalized => "finalized",
+            Self::Earliest => "earliest",
+        };
+        f.write_str(tag)
+    }
+}
+
+impl From<BlockTag> for BlockNumberOrTag {
+    fn from(tag: BlockTag) -> Self {
+        match tag {
+            BlockTag::Latest => Self::Latest,
+            BlockTag::Safe => Self::Safe,
+            BlockTag::Finalized => Self::Finalized,
+            BlockTag::Earliest => Self::Earliest,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SimulationTx {
     /// Address initiating the transaction
     pub caller: Address,
@@ -132,13 +317,112 @@ pub struct SimulationTx {
     pub data: Bytes,
     /// Transaction target (address for calls, None for creation)
     pub transact_to: TxKind,
+    /// Tracing level for this transaction (`None` means `TraceLevel::Full`)
+    pub trace_config: Option<TraceLevel>,
+    /// Function whose outputs the return data is decoded against, see
+    /// [`SimulationTx::with_return_abi`]
+    ///
+    /// Only used when building reports; it does not affect execution.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub return_abi: Option<Function>,
+}
+
+/// Target of a batch transaction that is only known once an earlier transaction of the
+/// batch has run, see [`SimulationBatch::with_target`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TargetRef {
+    /// Call to the contract created by the batch transaction at `tx_index`
+    ///
+    /// The referenced transaction must come earlier in the batch and be a successful
+    /// creation; otherwise the transaction fails with
+    /// [`RuntimeError::UnresolvedTarget`](crate::errors::RuntimeError::UnresolvedTarget)
+    /// without executing. In a stateless batch the created contract is discarded, so
+    /// the call reaches an account without code.
+    CreatedBy { tx_index: usize },
+}
+
+/// Transaction environment a [`SimulationTx`] was executed with
+///
+/// Captured after the nonce lookup and the tx hook, right before execution, so it
+/// shows the fields the crate filled in. Calldata is summarized by length and hash.
+/// Converting a `TxEnv` leaves `block_base_fee` at zero; batches fill it in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutedTxEnv {
+    /// Sender paying for the transaction
+    pub caller: Address,
+    /// Call target or creation
+    pub kind: TxKind,
+    /// Native token value
+    pub value: U256,
+    /// Calldata length in bytes
+    pub data_len: usize,
+    /// Keccak-256 hash of the calldata
+    pub data_hash: B256,
+    /// Sender nonce, as looked up in the database unless the tx hook changed it
+    pub nonce: u64,
+    /// Chain ID the transaction was signed for
+    pub chain_id: Option<u64>,
+    /// Gas limit
+    pub gas_limit: u64,
+    /// EIP-2718 transaction type
+    pub tx_type: u8,
+    /// Gas price, or max fee per gas for EIP-1559 transactions
+    pub gas_price: u128,
+    /// Max priority fee per gas for EIP-1559 transactions
+    pub gas_priority_fee: Option<u128>,
+    /// EIP-2930 access list, e.g. set by the tx hook
+    #[serde(default, skip_serializing_if = "is_empty_access_list")]
+    pub access_list: AccessList,
+    /// Base fee of the block the transaction executed in
+    ///
+    /// Zero on EVMs with the base fee disabled unless the block environment was taken
+    /// from the header (strict validation).
+    #[serde(default)]
+    pub block_base_fee: u64,
+}
+
+fn is_empty_access_list(list: &AccessList) -> bool {
+    list.0.is_empty()
+}
+
+impl From<&TxEnv> for ExecutedTxEnv {
+    fn from(tx: &TxEnv) -> Self {
+        Self {
+            caller: tx.caller,
+            kind: tx.kind,
+            value: tx.value,
+            data_len: tx.data.len(),
+            data_hash: keccak256(&tx.data),
+            nonce: tx.nonce,
+            chain_id: tx.chain_id,
+            gas_limit: tx.gas_limit,
+            tx_type: tx.tx_type,
+            gas_price: tx.gas_price,
+            gas_priority_fee: tx.gas_priority_fee,
+            access_list: tx.access_list.clone(),
+            block_base_fee: 0,
+        }
+    }
 }
 
 /// Batch transaction simulation parameters
 ///
 /// Allows execution of multiple transactions in sequence with
 /// configurable state handling between transactions.
-#[derive(Debug, Clone)]
+///
+/// New fields are added as the simulator grows; build batches with
+/// `..Default::default()` so they keep compiling:
+///
+/// ```
+/// use revm_trace::types::SimulationBatch;
+///
+/// let batch = SimulationBatch {
+///     is_stateful: true,
+///     ..Default::default()
+/// };
+/// assert!(batch.transactions.is_empty());
+/// ```
+#[derive(Debug, Clone, Default)]
 pub struct SimulationBatch {
     /// Sequence of transactions to execute
     pub transactions: Vec<SimulationTx>,
@@ -155,10 +439,266 @@ pub struct SimulationBatch {
     pub is_stateful: bool,
     /// Optional state overrides for the simulation
     pub overrides: Option<StateOverride>,
+    /// Optional block coinbase for this batch
+    ///
+    /// Overrides the coinbase configured on the EVM (see `EvmBuilder::with_coinbase`)
+    /// while the batch executes; the previous value is restored afterwards.
+    pub coinbase: Option<Address>,
+    /// How the batch proceeds after a transaction fails
+    pub failure_policy: FailurePolicy,
+    /// How calls with calldata to accounts without code are handled
+    pub empty_code_call_policy: EmptyCodeCallPolicy,
+    /// Whether to record the batch's mutations in one ordered timeline
+    ///
+    /// Read it with `TraceEvm::take_last_timeline` once the batch returns (see
+    /// [`evm::timeline`](crate::evm::timeline)).
+    pub build_timeline: bool,
+    /// Token stopping the batch when cancelled from another thread
+    ///
+    /// See [`SimulationBatch::with_cancellation`].
+    pub cancellation: Option<CancellationToken>,
+    /// Targets resolved while the batch runs, by index of the transaction they apply to
+    ///
+    /// See [`SimulationBatch::with_target`].
+    pub targets: BTreeMap<usize, TargetRef>,
+}
+
+/// Flag to stop an in-flight batch from another thread, shared by cloning
+///
+/// Clones share the same flag, so a server can keep one clone per request and
+/// cancel it when the client disconnects.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Cancels every batch holding a clone of the token
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the token was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// How a batch proceeds after one of its transactions fails
+///
+/// A transaction fails when it reverts, halts or cannot be executed at all. The
+/// policies only differ for stateful batches, except for `Abort`, which also stops
+/// stateless ones. Sender nonces are taken from the committed state, so they follow
+/// whatever was committed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FailurePolicy {
+    /// Keep executing, committing the state of failed transactions like any other
+    ///
+    /// A reverted or halted transaction still increments the sender nonce and charges
+    /// its gas, so later transactions see both.
+    #[default]
+    ContinueCommitting,
+    /// Keep executing, discarding the state of failed transactions
+    ///
+    /// Later transactions run as if the failed ones never happened, so the sender
+    /// nonce is not incremented and no gas is charged.
+    ContinueSkippingCommit,
+    /// Stop at the first failure
+    ///
+    /// The failed transaction is committed like with `ContinueCommitting`; every later
+    /// one reports `EvmError::BatchAborted` without executing.
+    Abort,
+}
+
+impl FailurePolicy {
+    /// Whether the state of a transaction with `result` is committed in a stateful batch
+    pub fn commits(self, result: &ExecutionResult) -> bool {
+        self != Self::ContinueSkippingCommit || result.is_success()
+    }
+}
+
+/// How a batch handles a call with calldata to an account without code
+///
+/// The EVM runs such a call as a successful plain value transfer, so calldata sent to
+/// the wrong address or on the wrong chain goes unnoticed. Calls without calldata,
+/// precompiles and system contracts (see
+/// [`system_contract_name`](crate::chains::system_contract_name)) are never flagged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum EmptyCodeCallPolicy {
+    /// Execute the call as usual
+    #[default]
+    Allow,
+    /// Execute the call and attach `SimulationWarning::CallToEmptyAccount`
+    Warn,
+    /// Fail the transaction with `RuntimeError::CallToEmptyAccount` without executing it
+    Error,
+}
+
+/// EIP-2718 types a `SimulationTx` cannot represent: blob transactions (no blob
+/// hashes), EIP-7702 transactions (no authorization list) and OP Stack deposits
+const UNSUPPORTED_TX_TYPES: [u8; 3] = [0x03, 0x04, crate::chains::DEPOSIT_TX_TYPE];
+
+impl TryFrom<TransactionRequest> for SimulationTx {
+    type Error = ConversionError;
+
+    /// Converts an `eth_call` / `eth_sendTransaction` style request
+    ///
+    /// Only sender, target, value and calldata are carried over; a missing `to` means
+    /// contract creation. Nonce, gas settings and access lists are ignored, as the
+    /// simulation picks the sender's current nonce and does not charge gas (use
+    /// `TraceEvm::set_tx_hook` to set them on the executed transaction).
+    fn try_from(request: TransactionRequest) -> Result<Self, Self::Error> {
+        if let Some(ty) = request
+            .transaction_type
+            .filter(|ty| UNSUPPORTED_TX_TYPES.contains(ty))
+        {
+            return Err(ConversionError::UnsupportedType(ty));
+        }
+        if request.blob_versioned_hashes.is_some() || request.sidecar.is_some() {
+            return Err(ConversionError::UnsupportedType(0x03));
+        }
+        if request.authorization_list.is_some() {
+            return Err(ConversionError::UnsupportedType(0x04));
+        }
+        Ok(Self {
+            caller: request.from.ok_or(ConversionError::MissingSender)?,
+            value: request.value.unwrap_or_default(),
+            data: request.input.input().cloned().unwrap_or_default(),
+            transact_to: request.to.unwrap_or(TxKind::Create),
+            trace_config: None,
+            return_abi: None,
+        })
+    }
+}
+
+impl SimulationTx {
+    /// Decodes the return data of the transaction with the outputs of `function`
+    ///
+    /// `SimulationReport::new` decodes the output of successful transactions into
+    /// `ExecutionSummary::decoded_return`. A mismatch is reported in
+    /// `ExecutionSummary::decode_error` and does not fail the simulation.
+    pub fn with_return_abi(mut self, function: Function) -> Self {
+        self.return_abi = Some(function);
+        self
+    }
+
+    /// Converts a signed transaction, recovering its sender from the signature
+    ///
+    /// As with `TransactionRequest`s, only sender, target, value and calldata are
+    /// carried over.
+    ///
+    /// # Returns
+    /// - `Ok(SimulationTx)`: Legacy, EIP-2930 and EIP-1559 transactions
+    /// - `Err(ConversionError::UnsupportedType(_))`: Blob and EIP-7702 transactions
+    /// - `Err(ConversionError::InvalidSignature(_))`: The signer cannot be recovered
+    pub fn from_envelope(envelope: &TxEnvelope) -> Result<Self, ConversionError> {
+        if UNSUPPORTED_TX_TYPES.contains(&envelope.ty()) {
+            return Err(ConversionError::UnsupportedType(envelope.ty()));
+        }
+        let caller = envelope
+            .recover_signer()
+            .map_err(|e| ConversionError::InvalidSignature(e.to_string()))?;
+        Ok(Self {
+            caller,
+            value: envelope.value(),
+            data: envelope.input().clone(),
+            transact_to: envelope.kind(),
+            trace_config: None,
+            return_abi: None,
+        })
+    }
+
+    /// Decodes an EIP-2718 encoded signed transaction (as sent to
+    /// `eth_sendRawTransaction`) and converts it with [`SimulationTx::from_envelope`]
+    pub fn from_raw(raw: &[u8]) -> Result<Self, ConversionError> {
+        let envelope = TxEnvelope::decode_2718(&mut &raw[..])
+            .map_err(|e| ConversionError::Decode(e.to_string()))?;
+        Self::from_envelope(&envelope)
+    }
+}
+
+impl SimulationBatch {
+    /// Builds a batch from transaction requests, without overrides or coinbase, using
+    /// the default failure policy
+    ///
+    /// Fails on the first request that cannot be converted.
+    pub fn from_requests(
+        requests: Vec<TransactionRequest>,
+        is_stateful: bool,
+    ) -> Result<Self, ConversionError> {
+        Ok(Self {
+            transactions: requests
+                .into_iter()
+                .map(SimulationTx::try_from)
+                .collect::<Result<_, _>>()?,
+            is_stateful,
+            ..Default::default()
+        })
+    }
+
+    /// Stops the batch once `token` is cancelled
+    ///
+    /// The transaction running at that moment is halted at the inspector's next
+    /// cancellation check (see `TxInspector::with_cancellation_interval`); inspectors
+    /// not implementing `TraceOutput::set_cancellation` let it finish. Either way its
+    /// state is discarded and it fails with `RuntimeError::Cancelled`, as do all
+    /// remaining transactions without executing. The EVM can be reused afterwards, as
+    /// after any failed transaction.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # fn example(batch: revm_trace::SimulationBatch) {
+    /// use revm_trace::types::CancellationToken;
+    ///
+    /// let token = CancellationToken::new();
+    /// let batch = batch.with_cancellation(token.clone());
+    /// // ... hand `batch` to a worker, then on client disconnect:
+    /// token.cancel();
+    /// # }
+    /// ```
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Makes the transaction at `tx_index` call `target` instead of its `transact_to`
+    ///
+    /// The target is resolved right before the transaction runs, from what the
+    /// referenced transaction actually did, so no nonce has to be predicted for
+    /// contracts created earlier in the batch. The resolved call replaces
+    /// `transact_to`, so `on_tx`, the tx hook and reports see it.
+    ///
+    /// # Example
+    /// ```
+    /// use alloy::primitives::{Bytes, TxKind};
+    /// use revm_trace::{SimulationBatch, SimulationTx, TargetRef};
+    ///
+    /// let deploy = SimulationTx {
+    ///     transact_to: TxKind::Create,
+    ///     data: Bytes::from_static(&[0x00]),
+    ///     ..Default::default()
+    /// };
+    /// // The placeholder target is replaced by the deployed contract
+    /// let call = SimulationTx::default();
+    /// let batch = SimulationBatch {
+    ///     transactions: vec![deploy, call],
+    ///     is_stateful: true,
+    ///     ..Default::default()
+    /// }
+    /// .with_target(1, TargetRef::CreatedBy { tx_index: 0 });
+    /// assert_eq!(batch.targets.len(), 1);
+    /// ```
+    pub fn with_target(mut self, tx_index: usize, target: TargetRef) -> Self {
+        self.targets.insert(tx_index, target);
+        self
+    }
 }
 
 /// Type of token transfer (supports future extensibility)
-#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum TokenType {
     Native,
@@ -168,6 +708,24 @@ pub enum TokenType {
     // More token types can be added in future
 }
 
+/// Accounting role of a token transfer
+///
+/// Assigned in post-processing by `TxTraceOutput::classify_transfers` from a
+/// [`ChainPreset`](crate::chains::ChainPreset); the inspector records every transfer
+/// as `Normal`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TransferRole {
+    /// Regular value movement
+    #[default]
+    Normal,
+    /// Gas fee debit or credit in a fee currency
+    FeePayment,
+    /// Native token converted into its wrapped representation
+    Wrap,
+    /// Wrapped native token converted back into the native token
+    Unwrap,
+}
+
 /// Record of a token transfer event
 ///
 /// Captures all relevant information about a token transfer,
@@ -176,7 +734,7 @@ pub enum TokenType {
 /// - For ERC721: `value` is the tokenId, `id` is Some(tokenId).
 /// - For ERC1155: `value` is the transfer amount, `id` is Some(tokenId).
 /// - For native token: `value` is the amount, `id` is None.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenTransfer {
     /// Token address (NATIVE_TOKEN_ADDRESS for ETH)
     pub token: Address,
@@ -190,6 +748,72 @@ pub struct TokenTransfer {
     pub token_type: TokenType,
     /// ERC721/1155 id (Some for ERC721/ERC1155, None for ERC20/Native)
     pub id: Option<U256>,
+    /// Accounting role, `Normal` unless classified against a chain preset
+    #[serde(default)]
+    pub role: TransferRole,
+    /// Position in the transaction's transfer list, in execution order
+    #[serde(default)]
+    pub transfer_index: usize,
+    /// Whether the contract creation this value was sent with failed, so the value
+    /// stayed with the sender; `to` is then `None`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub failed: bool,
+}
+
+/// Native token payment to the block coinbase
+///
+/// Recorded for every value-bearing call whose target is `block.coinbase`,
+/// e.g. the `block.coinbase.call{value: x}("")` pattern of MEV bundles.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoinbasePayment {
+    /// Address paying the coinbase
+    pub from: Address,
+    /// Amount paid
+    pub value: U256,
+    /// Position of the paying call in the call tree
+    pub trace_address: Vec<usize>,
+}
+
+/// Native value sent with a contract creation
+///
+/// Recorded for every value-bearing CREATE and CREATE2, the creation transaction
+/// included, in the order the creations started.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CreationFunding {
+    /// Created contract (None if the creation failed)
+    pub created: Option<Address>,
+    /// Account the value was sent from
+    pub funder: Address,
+    /// Amount sent
+    pub value: U256,
+    /// Whether the creation succeeded; a failing ancestor frame still rolls it back
+    pub success: bool,
+}
+
+/// Net movement of native tokens between two addresses
+///
+/// Produced by `TxTraceOutput::net_native_flows`, which collapses pass-through hops.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NativeFlow {
+    /// Address with a net outflow
+    pub from: Address,
+    /// Address with a net inflow
+    pub to: Address,
+    /// Netted amount
+    pub value: U256,
+}
+
+/// Net balance change of one holder in one fungible token
+///
+/// Produced by `TxTraceOutput::net_token_deltas`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenDelta {
+    /// Token address (`NATIVE_TOKEN_ADDRESS` for the native token)
+    pub token: Address,
+    /// Balance holder
+    pub holder: Address,
+    /// Net change implied by the transfers
+    pub delta: I256,
 }
 
 impl TokenTransfer {
@@ -209,15 +833,30 @@ pub enum CallType {
 }
 
 /// Status of a contract call
-#[derive(Debug, Clone, Serialize, Default)]
+///
+/// Serializes like the earlier string-carrying variants, with the structured data as
+/// extra keys: `"Success"`, `{"Revert": "<message>", "raw": "0x..", "decoded": ..}`,
+/// `{"Halt": "OutOfGas(Basic)", "kind": "OutOfGas"}`. Output of earlier versions
+/// without the extra keys still deserializes.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub enum CallStatus {
     /// Call completed successfully
     #[default]
     Success,
-    /// Call reverted with reason
-    Revert(String),
+    /// Call reverted
+    Revert {
+        /// `Error(string)` or `Panic(uint256)` payload, if the data is one
+        reason: Option<DecodedRevert>,
+        /// Revert data as stored in the frame's output, cut like it
+        raw: Bytes,
+    },
     /// Call halted due to error
-    Halt(String),
+    Halt {
+        /// Stable classification of the halt reason
+        reason: HaltReasonKind,
+        /// revm's halt reason as formatted by `Debug`, e.g. `OutOfGas(Basic)`
+        raw: String,
+    },
     /// Fatal error occurred
     FatalError,
     /// Call is still in progress
@@ -225,10 +864,268 @@ pub enum CallStatus {
 }
 
 impl CallStatus {
+    /// Builds the status of a halted call from revm's halt reason
+    pub fn halt(reason: &HaltReason) -> Self {
+        let raw = format!("{reason:?}");
+        CallStatus::Halt {
+            reason: HaltReasonKind::from_name(&raw),
+            raw,
+        }
+    }
+
     /// Check if the call was successful
     pub fn is_success(&self) -> bool {
         matches!(self, CallStatus::Success)
     }
+
+    /// Whether the call reverted
+    pub fn is_revert(&self) -> bool {
+        matches!(self, CallStatus::Revert { .. })
+    }
+
+    /// Whether the call halted by running out of gas, for any cause
+    pub fn is_out_of_gas(&self) -> bool {
+        matches!(
+            self,
+            CallStatus::Halt {
+                reason: HaltReasonKind::OutOfGas,
+                ..
+            }
+        )
+    }
+
+    /// Returns the revert data of a reverted call
+    pub fn revert_bytes(&self) -> Option<&Bytes> {
+        match self {
+            CallStatus::Revert { raw, .. } => Some(raw),
+            _ => None,
+        }
+    }
+
+    /// Returns the halt classification of a halted call
+    pub fn halt_reason(&self) -> Option<HaltReasonKind> {
+        match self {
+            CallStatus::Halt { reason, .. } => Some(*reason),
+            _ => None,
+        }
+    }
+
+    /// Describes why a reverted or halted call failed
+    ///
+    /// The decoded revert reason, the revert data in hex if it could not be decoded,
+    /// or the raw halt reason. `None` for other statuses.
+    pub fn message(&self) -> Option<String> {
+        match self {
+            CallStatus::Revert {
+                reason: Some(reason),
+                ..
+            } => Some(reason.to_string()),
+            CallStatus::Revert { reason: None, raw } => Some(raw.to_string()),
+            CallStatus::Halt { raw, .. } => Some(raw.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for CallStatus {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        match self {
+            CallStatus::Success => serializer.serialize_str("Success"),
+            CallStatus::FatalError => serializer.serialize_str("FatalError"),
+            CallStatus::InProgress => serializer.serialize_str("InProgress"),
+            CallStatus::Revert { reason, raw } => {
+                let mut map = serializer.serialize_map(Some(3))?;
+                map.serialize_entry("Revert", &self.message().unwrap_or_default())?;
+                map.serialize_entry("raw", raw)?;
+                map.serialize_entry("decoded", reason)?;
+                map.end()
+            }
+            CallStatus::Halt { reason, raw } => {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry("Halt", raw)?;
+                map.serialize_entry("kind", reason)?;
+                map.end()
+            }
+        }
+    }
+}
+
+/// Deserialization form of [`CallStatus`], also accepting the string-only output of
+/// earlier versions
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CallStatusRepr {
+    Unit(CallStatusUnit),
+    Revert {
+        #[serde(rename = "Revert")]
+        message: String,
+        #[serde(default)]
+        raw: Option<Bytes>,
+        #[serde(default)]
+        decoded: Option<DecodedRevert>,
+    },
+    Halt {
+        #[serde(rename = "Halt")]
+        raw: String,
+        #[serde(default)]
+        kind: Option<HaltReasonKind>,
+    },
+}
+
+#[derive(Deserialize)]
+enum CallStatusUnit {
+    Success,
+    FatalError,
+    InProgress,
+}
+
+impl<'de> Deserialize<'de> for CallStatus {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        CallStatusRepr::deserialize(deserializer).map(Into::into)
+    }
+}
+
+// Earlier versions only kept the message: hex is taken as undecoded revert data and
+// any other text as an `Error(string)` reason
+impl From<CallStatusRepr> for CallStatus {
+    fn from(repr: CallStatusRepr) -> Self {
+        match repr {
+            CallStatusRepr::Unit(CallStatusUnit::Success) => Self::Success,
+            CallStatusRepr::Unit(CallStatusUnit::FatalError) => Self::FatalError,
+            CallStatusRepr::Unit(CallStatusUnit::InProgress) => Self::InProgress,
+            CallStatusRepr::Revert {
+                message,
+                raw,
+                decoded,
+            } => match raw {
+                Some(raw) => Self::Revert {
+                    reason: decoded,
+                    raw,
+                },
+                None => match message.parse::<Bytes>() {
+                    Ok(raw) if message.starts_with("0x") => Self::Revert { reason: None, raw },
+                    _ => Self::Revert {
+                        reason: Some(DecodedRevert::Error(message)),
+                        raw: Bytes::new(),
+                    },
+                },
+            },
+            CallStatusRepr::Halt { raw, kind } => Self::Halt {
+                reason: kind.unwrap_or_else(|| HaltReasonKind::from_name(&raw)),
+                raw,
+            },
+        }
+    }
+}
+
+/// Standard Solidity revert payload
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DecodedRevert {
+    /// `Error(string)` with its reason
+    Error(String),
+    /// `Panic(uint256)` with its code
+    Panic(U256),
+}
+
+impl DecodedRevert {
+    /// Decodes `Error(string)` or `Panic(uint256)` revert data, see
+    /// [`decode_revert`](crate::utils::error_utils::decode_revert)
+    pub fn decode(output: &[u8]) -> Option<Self> {
+        crate::utils::error_utils::decode_revert(output)
+    }
+}
+
+/// Prints the reason of `Error(string)` and a description of panic codes
+impl fmt::Display for DecodedRevert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodedRevert::Error(reason) => f.write_str(reason),
+            DecodedRevert::Panic(code) => {
+                f.write_str(&crate::utils::error_utils::panic_reason(*code))
+            }
+        }
+    }
+}
+
+/// Why a call halted, independent of the revm version
+///
+/// Mapped from revm's `HaltReason`; reasons without a counterpart here are `Other`,
+/// with the original kept in the `raw` field of [`CallStatus::Halt`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HaltReasonKind {
+    /// Out of gas, for any cause (execution, memory expansion, precompile)
+    OutOfGas,
+    /// Undefined, `INVALID` (`0xfe`) or not yet activated opcode
+    InvalidOpcode,
+    /// Jump to an invalid destination
+    InvalidJump,
+    /// Not enough stack items for an opcode
+    StackUnderflow,
+    /// Stack grew beyond 1024 items
+    StackOverflow,
+    /// Memory or return data access out of bounds
+    OutOfOffset,
+    /// Contract creation at an address that is already in use
+    CreateCollision,
+    /// A precompile failed
+    PrecompileError,
+    /// The nonce of the creating account would overflow
+    NonceOverflow,
+    /// Created code exceeds the contract size limit
+    CreateContractSizeLimit,
+    /// Created code starts with the `0xEF` byte
+    CreateContractStartingWithEF,
+    /// Initcode exceeds the initcode size limit
+    CreateInitCodeSizeLimit,
+    /// Payment amount overflow
+    OverflowPayment,
+    /// State modification in a static call
+    StateChangeDuringStaticCall,
+    /// Value-transferring call in a static call
+    CallNotAllowedInsideStatic,
+    /// Not enough balance for the value transferred
+    OutOfFunds,
+    /// Call depth exceeded 1024
+    CallTooDeep,
+    /// Any other reason
+    Other,
+}
+
+impl HaltReasonKind {
+    /// Classifies a halt reason by the name of its revm variant, e.g. `OutOfGas` or
+    /// `OutOfGas(Basic)`
+    pub fn from_name(name: &str) -> Self {
+        let variant = name.split('(').next().unwrap_or_default().trim();
+        match variant {
+            "OutOfGas" => Self::OutOfGas,
+            "OpcodeNotFound" | "InvalidFEOpcode" | "NotActivated" => Self::InvalidOpcode,
+            "InvalidJump" => Self::InvalidJump,
+            "StackUnderflow" => Self::StackUnderflow,
+            "StackOverflow" => Self::StackOverflow,
+            "OutOfOffset" => Self::OutOfOffset,
+            "CreateCollision" => Self::CreateCollision,
+            "PrecompileError" => Self::PrecompileError,
+            "NonceOverflow" => Self::NonceOverflow,
+            "CreateContractSizeLimit" => Self::CreateContractSizeLimit,
+            "CreateContractStartingWithEF" => Self::CreateContractStartingWithEF,
+            "CreateInitCodeSizeLimit" => Self::CreateInitCodeSizeLimit,
+            "OverflowPayment" => Self::OverflowPayment,
+            "StateChangeDuringStaticCall" => Self::StateChangeDuringStaticCall,
+            "CallNotAllowedInsideStatic" => Self::CallNotAllowedInsideStatic,
+            "OutOfFunds" => Self::OutOfFunds,
+            "CallTooDeep" => Self::CallTooDeep,
+            _ => Self::Other,
+        }
+    }
+}
+
+// Matched by variant name, so that variants added or removed across revm versions
+// map to `Other` instead of breaking the build
+impl From<&HaltReason> for HaltReasonKind {
+    fn from(reason: &HaltReason) -> Self {
+        Self::from_name(&format!("{reason:?}"))
+    }
 }
 
 /// Storage slot change during a contract call
@@ -239,10 +1136,134 @@ pub struct SlotAccess {
     pub old_value: U256,
     pub new_value: U256,
     pub is_write: bool, // true=write, false=read
+    /// Whether this was the transaction's first access to the slot, charged as cold
+    /// under EIP-2929
+    ///
+    /// Only set on accesses recorded by the inspector (see `TxTraceOutput`); storage
+    /// diffs leave it false.
+    #[serde(default)]
+    pub is_cold: bool,
+}
+
+/// Net change of one storage slot by the writes of a single call frame
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct SlotChange {
+    pub address: Address,
+    pub slot: U256,
+    /// Value before the frame's first write to the slot
+    pub old_value: U256,
+    /// Value after the frame's last write to the slot
+    pub new_value: U256,
+}
+
+/// Selection of the event logs an inspector stores
+///
+/// A log is kept when it matches every configured criterion; `None` accepts anything.
+/// Logs without topics never match a `topics0` filter.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogFilter {
+    /// Emitting contracts to keep
+    pub addresses: Option<HashSet<Address>>,
+    /// Event signatures (first topic) to keep
+    pub topics0: Option<HashSet<B256>>,
+}
+
+impl LogFilter {
+    /// Whether `log` passes the filter
+    pub fn matches(&self, log: &Log) -> bool {
+        let address_ok = self
+            .addresses
+            .as_ref()
+            .is_none_or(|addresses| addresses.contains(&log.address));
+        let topic_ok = self.topics0.as_ref().is_none_or(|topics| {
+            log.topics()
+                .first()
+                .is_some_and(|topic0| topics.contains(topic0))
+        });
+        address_ok && topic_ok
+    }
+}
+
+/// Storage writes of each call frame, keyed by trace address
+pub type FrameDiffs = HashMap<Vec<usize>, Vec<SlotChange>>;
+
+/// Serializes [`FrameDiffs`] as `[trace_address, changes]` pairs in trace address
+/// order, since JSON object keys must be strings
+pub(crate) mod frame_diffs_serde {
+    use super::{FrameDiffs, SlotChange};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(diffs: &FrameDiffs, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut entries: Vec<_> = diffs.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        entries.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<FrameDiffs, D::Error> {
+        let entries = Vec::<(Vec<usize>, Vec<SlotChange>)>::deserialize(deserializer)?;
+        Ok(entries.into_iter().collect())
+    }
+}
+
+/// Serializes a `HashSet` in ascending order
+pub(crate) mod sorted_set {
+    use serde::{Serialize, Serializer};
+    use std::collections::{BTreeSet, HashSet};
+
+    pub fn serialize<T, S>(set: &HashSet<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Ord + Serialize,
+        S: Serializer,
+    {
+        set.iter().collect::<BTreeSet<_>>().serialize(serializer)
+    }
+}
+
+/// Serializes a `HashMap` with its keys in ascending order
+pub(crate) mod sorted_map {
+    use serde::{Serialize, Serializer};
+    use std::collections::{BTreeMap, HashMap};
+
+    pub fn serialize<K, V, S>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Ord + Serialize,
+        V: Serialize,
+        S: Serializer,
+    {
+        map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+    }
+}
+
+/// Serializes event logs in emission order, each with its `log_index` within the
+/// transaction
+///
+/// The index is ignored when deserializing, logs are read back in order.
+pub(crate) mod indexed_logs {
+    use alloy::primitives::Log;
+    use serde::{Serialize, Serializer};
+
+    #[derive(Serialize)]
+    struct IndexedLog<'a> {
+        log_index: usize,
+        #[serde(flatten)]
+        log: &'a Log,
+    }
+
+    pub fn serialize<S: Serializer>(logs: &[Log], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            logs.iter()
+                .enumerate()
+                .map(|(log_index, log)| IndexedLog { log_index, log }),
+        )
+    }
 }
 
 /// Detailed trace of a contract call
-#[derive(Debug, Clone, Serialize, Default)]
+///
+/// Missing fields default when deserializing, so traces stored by older
+/// versions remain readable.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
 pub struct CallTrace {
     /// Caller address
     pub from: Address,
@@ -250,116 +1271,876 @@ pub struct CallTrace {
     pub to: Address,
     /// Native token value
     pub value: U256,
-    /// Call input data
+    /// Call input data, or init code for creations
     pub input: Bytes,
+    /// Set when `input` was cut to the inspector's `max_input_capture`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_truncated: Option<TruncatedBytes>,
     /// Call scheme if regular call
     pub call_scheme: Option<CallScheme>,
     /// Create scheme if contract creation
     pub create_scheme: Option<CreateScheme>,
+    /// Gas made available to this call
+    pub gas_limit: U256,
     /// Gas used by this call
     pub gas_used: U256,
     /// Call output data
     pub output: Bytes,
+    /// Set when `output` was cut to the inspector's `max_output_capture`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_truncated: Option<TruncatedBytes>,
     /// Call execution status
     pub status: CallStatus,
+    /// Cause of the failure, when the execution result identifies one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure_kind: Option<FrameFailureKind>,
     /// Whether this call is the source of an error
     pub error_origin: bool,
     /// Nested calls made by this call
     pub subtraces: Vec<CallTrace>,
     /// Position in the call tree
     pub trace_address: Vec<usize>,
+    /// Stable id of the frame: its trace address joined with `.`, e.g. `0.2`, and
+    /// empty for the top-level call (see [`CallTrace::frame_id_for`])
+    pub frame_id: String,
     /// Access to contract storage slots during this call
     pub slot_accesses: Vec<SlotAccess>,
+    /// Net change of the gas refund counter by the SSTOREs executed in this call
+    ///
+    /// Excludes nested calls. Negative when a write undoes an earlier refund. revm
+    /// discards the refunds of frames that do not succeed.
+    pub refund_counter_delta: i64,
+    /// Indices into the transaction's log list of events emitted directly by this call
+    pub log_indices: Vec<usize>,
+    /// Nesting depth in the call tree (0 for the top-level call)
+    pub depth: usize,
+    /// Whether nested calls beyond the inspector's `max_trace_depth` were collapsed
+    /// into this frame
+    ///
+    /// Storage accesses and logs of collapsed calls are attributed to this frame.
+    pub truncated: bool,
+    /// Kind of account this call targets
+    pub target_kind: TargetKind,
+    /// Whether this call read `tx.origin` (ORIGIN), nested calls excluded
+    pub used_tx_origin: bool,
+    /// Number of ORIGIN instructions executed by this call, nested calls excluded
+    pub tx_origin_reads: usize,
+    /// Facet handling this call on behalf of a diamond, when annotated with
+    /// `utils::diamond_utils::annotate_diamond_calls`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub facet_route: Option<FacetRoute>,
+    /// Account whose code the frame ran, when it differs from `to` (CALLCODE)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_address: Option<Address>,
+    /// Number of opcodes executed by this call, nested calls excluded
+    ///
+    /// `None` when opcodes were not counted (inspectors without step hooks, traces
+    /// stored by older versions).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opcodes_executed: Option<u64>,
+    /// Peak memory, peak stack depth and memory expansion gas of this call, nested
+    /// calls excluded
+    ///
+    /// `None` when opcodes were not stepped (trace levels below `Full`, traces stored
+    /// by older versions).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_stats: Option<FrameResources>,
+}
+
+/// Interpreter resources used by a call frame, see [`CallTrace::resource_stats`]
+///
+/// Each frame has its own memory and stack, so the values of a frame do not include
+/// those of its nested calls. Nested calls collapsed beyond the inspector's
+/// `max_trace_depth` are not measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct FrameResources {
+    /// Largest memory size reached, in bytes (always a multiple of 32)
+    pub peak_memory_bytes: u64,
+    /// Largest number of items on the stack
+    pub peak_stack_depth: u64,
+    /// Gas charged for expanding memory to its peak size
+    pub mem_expansion_gas: u64,
+}
+
+/// Cause of a failed call frame, see [`CallTrace::failure_kind`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FrameFailureKind {
+    /// A value transfer ran out of gas with no more than the 2300 gas stipend, as
+    /// sent by Solidity's `transfer()` and `send()`
+    StipendOutOfGas,
+    /// The call would exceed the maximum call depth of 1024
+    CallDepthExceeded,
+    /// The caller could not cover the transferred value
+    InsufficientBalanceForTransfer,
+    /// State was modified, or value sent, inside a static call
+    StaticCallViolation,
+}
+
+/// Call frame transferring native value that failed, so the value was not moved
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailedValueTransfer {
+    /// Position of the frame in the call tree
+    pub trace_address: Vec<usize>,
+    pub from: Address,
+    pub to: Address,
+    pub value: U256,
+    /// Cause of the failure, if identified
+    pub failure_kind: Option<FrameFailureKind>,
+}
+
+/// Facet a diamond (EIP-2535) routes a call to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FacetRoute {
+    /// Diamond whose storage the call runs against
+    pub diamond: Address,
+    /// Facet whose code handles the function
+    pub facet: Address,
+    /// Function selector the diamond looked up
+    pub selector: FixedBytes<4>,
+}
+
+/// Kind of account targeted by a call frame
+///
+/// Determined when the frame starts. Accounts with code are `Contract`, unless they
+/// are precompiles, registered system contracts (see
+/// [`system_contract_name`](crate::chains::system_contract_name)) or were created
+/// earlier in the same transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub enum TargetKind {
+    /// Precompiled contract
+    Precompile(KnownPrecompile),
+    /// Protocol-level contract, by name
+    SystemContract(&'static str),
+    /// Account with code that existed before the transaction
+    #[default]
+    Contract,
+    /// Account without code
+    Eoa,
+    /// Contract created earlier in the same transaction, including frames creating it
+    Created,
+}
+
+/// Deserialization form of [`TargetKind`], owning the system contract name
+#[derive(Deserialize)]
+enum TargetKindRepr {
+    Precompile(KnownPrecompile),
+    SystemContract(String),
+    Contract,
+    Eoa,
+    Created,
+}
+
+// System contract names are resolved against the built-in registry; names unknown to
+// this version deserialize as `TargetKind::Contract`
+impl<'de> Deserialize<'de> for TargetKind {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        TargetKindRepr::deserialize(deserializer).map(Into::into)
+    }
+}
+
+impl From<TargetKindRepr> for TargetKind {
+    fn from(repr: TargetKindRepr) -> Self {
+        match repr {
+            TargetKindRepr::Precompile(precompile) => Self::Precompile(precompile),
+            TargetKindRepr::SystemContract(name) => {
+                crate::chains::known_system_contract_name(&name)
+                    .map_or(Self::Contract, Self::SystemContract)
+            }
+            TargetKindRepr::Contract => Self::Contract,
+            TargetKindRepr::Eoa => Self::Eoa,
+            TargetKindRepr::Created => Self::Created,
+        }
+    }
+}
+
+/// Precompiled contracts at the low end of the address space
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum KnownPrecompile {
+    /// `0x01`
+    EcRecover,
+    /// `0x02`
+    Sha256,
+    /// `0x03`
+    Ripemd160,
+    /// `0x04`
+    Identity,
+    /// `0x05`
+    ModExp,
+    /// `0x06`
+    Bn254Add,
+    /// `0x07`
+    Bn254Mul,
+    /// `0x08`
+    Bn254Pairing,
+    /// `0x09`
+    Blake2F,
+    /// `0x0a` (EIP-4844)
+    PointEvaluation,
+    /// `0x0b` (EIP-2537)
+    Bls12G1Add,
+    /// `0x0c` (EIP-2537)
+    Bls12G1Msm,
+    /// `0x0d` (EIP-2537)
+    Bls12G2Add,
+    /// `0x0e` (EIP-2537)
+    Bls12G2Msm,
+    /// `0x0f` (EIP-2537)
+    Bls12PairingCheck,
+    /// `0x10` (EIP-2537)
+    Bls12MapFpToG1,
+    /// `0x11` (EIP-2537)
+    Bls12MapFp2ToG2,
+    /// `0x100` (RIP-7212 / EIP-7951)
+    P256Verify,
+}
+
+impl KnownPrecompile {
+    /// Returns the precompile at `address`, if any
+    pub fn from_address(address: Address) -> Option<Self> {
+        let bytes = address.as_slice();
+        if bytes[..18].iter().any(|&b| b != 0) {
+            return None;
+        }
+        let precompile = match u16::from_be_bytes([bytes[18], bytes[19]]) {
+            0x01 => Self::EcRecover,
+            0x02 => Self::Sha256,
+            0x03 => Self::Ripemd160,
+            0x04 => Self::Identity,
+            0x05 => Self::ModExp,
+            0x06 => Self::Bn254Add,
+            0x07 => Self::Bn254Mul,
+            0x08 => Self::Bn254Pairing,
+            0x09 => Self::Blake2F,
+            0x0a => Self::PointEvaluation,
+            0x0b => Self::Bls12G1Add,
+            0x0c => Self::Bls12G1Msm,
+            0x0d => Self::Bls12G2Add,
+            0x0e => Self::Bls12G2Msm,
+            0x0f => Self::Bls12PairingCheck,
+            0x10 => Self::Bls12MapFpToG1,
+            0x11 => Self::Bls12MapFp2ToG2,
+            0x100 => Self::P256Verify,
+            _ => return None,
+        };
+        Some(precompile)
+    }
+
+    /// Conventional name of the precompile
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::EcRecover => "ecrecover",
+            Self::Sha256 => "sha256",
+            Self::Ripemd160 => "ripemd160",
+            Self::Identity => "identity",
+            Self::ModExp => "modexp",
+            Self::Bn254Add => "ecadd",
+            Self::Bn254Mul => "ecmul",
+            Self::Bn254Pairing => "ecpairing",
+            Self::Blake2F => "blake2f",
+            Self::PointEvaluation => "point_evaluation",
+            Self::Bls12G1Add => "bls12_g1add",
+            Self::Bls12G1Msm => "bls12_g1msm",
+            Self::Bls12G2Add => "bls12_g2add",
+            Self::Bls12G2Msm => "bls12_g2msm",
+            Self::Bls12PairingCheck => "bls12_pairing_check",
+            Self::Bls12MapFpToG1 => "bls12_map_fp_to_g1",
+            Self::Bls12MapFp2ToG2 => "bls12_map_fp2_to_g2",
+            Self::P256Verify => "p256verify",
+        }
+    }
+}
+
+/// Contract lifecycle event observed during a transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContractChange {
+    /// A contract executed SELFDESTRUCT
+    SelfDestructed {
+        /// Contract that self-destructed
+        contract: Address,
+        /// Recipient of the remaining balance
+        beneficiary: Address,
+        /// Native balance moved to the beneficiary
+        balance_moved: U256,
+        /// Whether the contract was created earlier in the same transaction
+        ///
+        /// Since Cancun, only such contracts are actually removed from state.
+        same_tx_creation: bool,
+        /// Position of the frame that executed SELFDESTRUCT
+        trace_address: Vec<usize>,
+    },
+    /// A contract was successfully deployed
+    Created {
+        /// Address of the new contract
+        address: Address,
+        /// Length of the deployed runtime code
+        code_len: usize,
+    },
+    /// A contract's code was removed from state by the end of the transaction
+    CodeCleared {
+        /// Address whose code was cleared
+        address: Address,
+    },
+}
+
+/// Diagnosis attached to a transaction before it executes
+///
+/// Warnings never change execution: the EVM's own rules still decide the outcome.
+/// They explain outcomes that would otherwise surface as a bare halt.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SimulationWarning {
+    /// The address a deployment targets already has code or a nonce, so the
+    /// creation will fail with a collision
+    AddressCollision {
+        /// Predicted address of the new contract
+        address: Address,
+    },
+    /// The nonce of a deployment differs from the sender's account nonce, typically
+    /// after a tx hook set it explicitly
+    NonceMismatchForCreate {
+        /// Nonce of the sender's account, which determines the new address
+        expected: u64,
+        /// Nonce carried by the transaction
+        actual: u64,
+    },
+    /// The transaction sends calldata to an account without code, so it executes as a
+    /// plain value transfer
+    CallToEmptyAccount {
+        /// Target of the call
+        address: Address,
+    },
+}
+
+/// Account and storage keys to fetch a state proof for
+///
+/// Built by [`TxTraceOutput::proof_requests`] and answered by
+/// [`fetch_proofs`](crate::verify::fetch_proofs).
+///
+/// [`TxTraceOutput::proof_requests`]: crate::inspectors::tx_inspector::TxTraceOutput::proof_requests
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofRequest {
+    pub address: Address,
+    /// Slots to prove, sorted; empty for accounts only read for balance, nonce or code
+    pub storage_keys: Vec<B256>,
+}
+
+/// Event log the inspector recognized but could not decode
+///
+/// Reported instead of a guess, so a nonstandard event never turns into a wrong
+/// transfer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ParseWarning {
+    /// A Transfer, TransferSingle or TransferBatch event whose topics and data fit
+    /// none of the known layouts; no transfer was recorded for it
+    MalformedTransferLog {
+        /// Contract that emitted the event
+        token: Address,
+        /// What did not fit
+        reason: String,
+    },
+}
+
+/// Limits applied by `TxTraceOutput::pruned` to keep traces small enough to serve
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PruneBudget {
+    /// Maximum bytes kept of each call input, call output and log data
+    pub max_bytes_per_field: usize,
+    /// Deepest call frame kept (0 keeps only the top-level call)
+    pub max_depth: usize,
+    /// Maximum direct subcalls kept per frame
+    pub max_children_per_frame: usize,
+    /// Whether STATICCALL frames (and their subcalls) are left out
+    pub drop_static_calls: bool,
+}
+
+impl Default for PruneBudget {
+    fn default() -> Self {
+        Self {
+            max_bytes_per_field: 1024,
+            max_depth: 64,
+            max_children_per_frame: 256,
+            drop_static_calls: false,
+        }
+    }
+}
+
+/// What was cut from a byte field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TruncatedBytes {
+    /// Length of the full data
+    pub len: usize,
+    /// Keccak-256 hash of the full data
+    pub keccak256: B256,
+}
+
+/// Call frames left out of a pruned call tree
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct OmittedFrames {
+    /// Direct subcalls left out
+    pub subtraces: usize,
+    /// Frames left out, nested ones included
+    pub frames: usize,
+    /// Left out frames that did not succeed
+    pub failed: usize,
+}
+
+/// Call frame of a pruned trace
+///
+/// Mirrors [`CallTrace`]; storage accesses are reduced to their count.
+#[derive(Debug, Clone, Serialize)]
+pub struct PrunedCallTrace {
+    pub from: Address,
+    pub to: Address,
+    pub value: U256,
+    /// Call input, cut to `max_bytes_per_field`
+    pub input: Bytes,
+    /// Set when `input` was cut
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_truncated: Option<TruncatedBytes>,
+    pub call_scheme: Option<CallScheme>,
+    pub create_scheme: Option<CreateScheme>,
+    pub gas_limit: U256,
+    pub gas_used: U256,
+    /// Call output, cut to `max_bytes_per_field`
+    pub output: Bytes,
+    /// Set when `output` was cut
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_truncated: Option<TruncatedBytes>,
+    pub status: CallStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure_kind: Option<FrameFailureKind>,
+    pub error_origin: bool,
+    /// Nested calls that were kept
+    pub subtraces: Vec<PrunedCallTrace>,
+    /// Set when nested calls were left out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub omitted_subtraces: Option<OmittedFrames>,
+    pub trace_address: Vec<usize>,
+    pub frame_id: String,
+    /// Number of storage accesses of the frame
+    pub slot_access_count: usize,
+    pub refund_counter_delta: i64,
+    pub log_indices: Vec<usize>,
+    pub depth: usize,
+    /// Whether the inspector collapsed nested calls into this frame while tracing
+    pub truncated: bool,
+    pub target_kind: TargetKind,
+    pub used_tx_origin: bool,
+    pub tx_origin_reads: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub facet_route: Option<FacetRoute>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_address: Option<Address>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opcodes_executed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_stats: Option<FrameResources>,
+}
+
+/// Event log of a pruned trace
+#[derive(Debug, Clone, Serialize)]
+pub struct PrunedLog {
+    /// Position in the transaction's log list
+    pub log_index: usize,
+    pub address: Address,
+    pub topics: Vec<B256>,
+    /// Log data, cut to `max_bytes_per_field`
+    pub data: Bytes,
+    /// Set when `data` was cut
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_truncated: Option<TruncatedBytes>,
+}
+
+/// Call of the logical call tree built by `TxTraceOutput::logical_view`
+///
+/// A call forwarded by a proxy appears as a single node: the frame calling the proxy
+/// merged with the delegatecall into the implementation.
+#[derive(Debug, Clone, Serialize)]
+pub struct LogicalCall {
+    pub from: Address,
+    /// Called address; the proxy for forwarded calls
+    pub to: Address,
+    /// Outermost proxy, if the call was forwarded
+    pub proxy: Option<Address>,
+    /// Contract whose code handled the call: the innermost implementation for
+    /// forwarded calls, `to` otherwise
+    pub implementation: Address,
+    /// Implementation found in the proxy's storage slots, when annotated with
+    /// `utils::proxy_utils::annotate_implementations`
+    pub active_implementation: Option<Address>,
+    /// Account whose storage the call reads and writes
+    pub storage_address: Address,
+    /// First four bytes of the input, if it has that many
+    pub function_selector: Option<FixedBytes<4>>,
+    pub value: U256,
+    pub input: Bytes,
+    pub output: Bytes,
+    pub call_scheme: Option<CallScheme>,
+    pub create_scheme: Option<CreateScheme>,
+    pub gas_used: U256,
+    pub status: CallStatus,
+    /// Trace address of the outermost merged frame
+    pub trace_address: Vec<usize>,
+    /// Trace addresses of the delegatecall frames merged into this node
+    pub merged_frames: Vec<Vec<usize>>,
+    /// Nested logical calls
+    pub subtraces: Vec<LogicalCall>,
 }
 
 impl TokenTransfer {
     /// Parses a token transfer log and returns a vector of TokenTransfer objects
+    ///
+    /// Logs that look like transfers but fit none of the known layouts yield no
+    /// transfers; [`Self::try_get_token_transfers`] reports them instead.
     pub fn get_token_transfers(log: &Log) -> Vec<TokenTransfer> {
-        let mut results = vec![];
+        Self::try_get_token_transfers(log).unwrap_or_default()
+    }
+
+    /// Parses a token transfer log, rejecting transfer events it cannot decode
+    /// unambiguously
+    ///
+    /// `Transfer(address,address,uint256)` is accepted with any split of its three
+    /// words between topics and data:
+    /// - 3 topics, 32 data bytes: ERC20 with indexed `from` and `to`
+    /// - 4 topics, no data: ERC721 with indexed token ID
+    /// - 2 topics, 64 data bytes and 1 topic, 96 data bytes: legacy tokens indexing
+    ///   fewer parameters, decoded as ERC20
+    ///
+    /// Addresses must be ABI-encoded, with zero upper bytes. ERC1155 `TransferSingle`
+    /// and `TransferBatch` events must match their standard layout.
+    ///
+    /// `transfer_index` is left at 0, the collector numbers the transfers it keeps
+    ///
+    /// # Returns
+    /// - `Ok(transfers)`: The transfers of the log, none for other events and zero
+    ///   ERC20 amounts
+    /// - `Err(ParseWarning::MalformedTransferLog)`: If the log has a transfer event
+    ///   signature but an unrecognized layout
+    pub fn try_get_token_transfers(log: &Log) -> Result<Vec<TokenTransfer>, ParseWarning> {
+        let topics = log.topics();
+        let data = &log.data.data[..];
+        let malformed = |reason: String| ParseWarning::MalformedTransferLog {
+            token: log.address,
+            reason,
+        };
+        let Some(signature) = topics.first() else {
+            return Ok(vec![]);
+        };
+        let transfer = |from, to, value, token_type, id| TokenTransfer {
+            token: log.address,
+            from,
+            to: Some(to),
+            value,
+            token_type,
+            id,
+            role: TransferRole::Normal,
+            transfer_index: 0,
+            failed: false,
+        };
+
         // erc20/erc721 transfer
-        if log.topics()[0] == ERC20_TRANSFER_EVENT_SIGNATURE {
-            if log.topics().len() == 3 {
-                let from = Address::from_slice(&log.topics()[1].as_slice()[12..]);
-                let to = Address::from_slice(&log.topics()[2].as_slice()[12..]);
-                let data = &log.data.data;
-                let amount = U256::from_be_slice(data);
-                if !amount.is_zero() {
-                    results.push(TokenTransfer {
-                        token: log.address,
-                        from,
-                        to: Some(to),
-                        value: amount,
-                        token_type: TokenType::ERC20,
-                        id: None,
-                    });
-                }
-            } else if log.topics().len() == 4 {
-                let from = Address::from_slice(&log.topics()[1].as_slice()[12..]);
-                let to = Address::from_slice(&log.topics()[2].as_slice()[12..]);
-                let id = U256::from_be_slice(log.topics()[3].as_slice());
-                let amount = U256::from(1);
-                results.push(TokenTransfer {
-                    token: log.address,
+        if *signature == ERC20_TRANSFER_EVENT_SIGNATURE {
+            let expected = 4usize.saturating_sub(topics.len()) * 32;
+            if topics.len() > 4 || data.len() != expected {
+                return Err(malformed(format!(
+                    "Transfer with {} topics has {} data bytes, expected {expected}",
+                    topics.len(),
+                    data.len()
+                )));
+            }
+            // from, to and value, the indexed ones first
+            let words: Vec<&[u8]> = topics[1..]
+                .iter()
+                .map(|topic| topic.as_slice())
+                .chain(data.chunks(32))
+                .collect();
+            let from = word_to_address(words[0])
+                .ok_or_else(|| malformed("Transfer `from` is not an address".to_string()))?;
+            let to = word_to_address(words[1])
+                .ok_or_else(|| malformed("Transfer `to` is not an address".to_string()))?;
+            let value = U256::from_be_slice(words[2]);
+            if topics.len() == 4 {
+                return Ok(vec![transfer(
                     from,
-                    to: Some(to),
-                    value: amount,
-                    token_type: TokenType::ERC721,
-                    id: Some(id),
-                });
+                    to,
+                    U256::from(1),
+                    TokenType::ERC721,
+                    Some(value),
+                )]);
             }
-        } else if log.topics()[0] == ERC1155_TRANSFER_BATCH_EVENT_SIGNATURE
-            && log.topics().len() == 4
-        {
-            let data = &log.data.data;
-            if data.len() >= 96 {
-                let from = Address::from_slice(&log.topics()[2].as_slice()[12..]);
-                let to = Address::from_slice(&log.topics()[3].as_slice()[12..]);
-                let ids_len = U256::from_be_slice(&data[64..96]).to::<usize>();
-                let mut ids = Vec::with_capacity(ids_len);
-                let mut offset = 96;
-                for _ in 0..ids_len {
-                    ids.push(U256::from_be_slice(&data[offset..offset + 32]));
-                    offset += 32;
-                }
-                // 解析 values
-                let values_len = U256::from_be_slice(&data[offset..offset + 32]).to::<usize>();
-                offset += 32;
-                let mut values = Vec::with_capacity(values_len);
-                for _ in 0..values_len {
-                    values.push(U256::from_be_slice(&data[offset..offset + 32]));
-                    offset += 32;
-                }
-                // 匹配 ids 和 values
-                for (id, value) in ids.into_iter().zip(values.into_iter()) {
-                    results.push(TokenTransfer {
-                        token: log.address,
-                        from,
-                        to: Some(to),
-                        value,
-                        token_type: TokenType::ERC1155,
-                        id: Some(id),
-                    });
-                }
+            if value.is_zero() {
+                return Ok(vec![]);
             }
-        } else if log.topics()[0] == ERC1155_TRANSFER_SINGLE_EVENT_SIGNATURE
-            && log.topics().len() == 4
-        {
-            let data = &log.data.data;
-            if data.len() >= 64 {
-                let from = Address::from_slice(&log.topics()[2].as_slice()[12..]);
-                let to = Address::from_slice(&log.topics()[3].as_slice()[12..]);
-                let id = U256::from_be_slice(&data[..32]);
-                let value = U256::from_be_slice(&data[32..64]);
-                results.push(TokenTransfer {
-                    token: log.address,
-                    from,
-                    to: Some(to),
-                    value,
-                    token_type: TokenType::ERC1155,
-                    id: Some(id),
-                });
+            return Ok(vec![transfer(from, to, value, TokenType::ERC20, None)]);
+        }
+
+        let is_single = *signature == ERC1155_TRANSFER_SINGLE_EVENT_SIGNATURE;
+        if !is_single && *signature != ERC1155_TRANSFER_BATCH_EVENT_SIGNATURE {
+            return Ok(vec![]);
+        }
+        let event = if is_single {
+            "TransferSingle"
+        } else {
+            "TransferBatch"
+        };
+        if topics.len() != 4 {
+            return Err(malformed(format!(
+                "{event} has {} topics, expected 4",
+                topics.len()
+            )));
+        }
+        let from = word_to_address(topics[2].as_slice())
+            .ok_or_else(|| malformed(format!("{event} `from` is not an address")))?;
+        let to = word_to_address(topics[3].as_slice())
+            .ok_or_else(|| malformed(format!("{event} `to` is not an address")))?;
+        if is_single {
+            if data.len() != 64 {
+                return Err(malformed(format!(
+                    "TransferSingle has {} data bytes, expected 64",
+                    data.len()
+                )));
             }
+            let id = U256::from_be_slice(&data[..32]);
+            let value = U256::from_be_slice(&data[32..]);
+            return Ok(vec![transfer(
+                from,
+                to,
+                value,
+                TokenType::ERC1155,
+                Some(id),
+            )]);
+        }
+        let (Some(ids), Some(values)) = (abi_word_array(data, 0), abi_word_array(data, 32)) else {
+            return Err(malformed(
+                "TransferBatch data is not two ABI-encoded arrays".to_string(),
+            ));
+        };
+        if ids.len() != values.len() {
+            return Err(malformed(format!(
+                "TransferBatch has {} ids but {} values",
+                ids.len(),
+                values.len()
+            )));
+        }
+        Ok(ids
+            .into_iter()
+            .zip(values)
+            .map(|(id, value)| transfer(from, to, value, TokenType::ERC1155, Some(id)))
+            .collect())
+    }
+}
+
+/// Decodes an ABI-encoded address, rejecting words with nonzero upper bytes
+fn word_to_address(word: &[u8]) -> Option<Address> {
+    (word.len() == 32 && word[..12].iter().all(|byte| *byte == 0))
+        .then(|| Address::from_slice(&word[12..]))
+}
+
+/// Decodes the dynamic `uint256[]` whose offset is stored at `head` in `data`
+fn abi_word_array(data: &[u8], head: usize) -> Option<Vec<U256>> {
+    let word = |offset: usize| -> Option<usize> {
+        usize::try_from(U256::from_be_slice(
+            data.get(offset..offset.checked_add(32)?)?,
+        ))
+        .ok()
+    };
+    let offset = word(head)?;
+    let len = word(offset)?;
+    let start = offset.checked_add(32)?;
+    let elements = data.get(start..start.checked_add(len.checked_mul(32)?)?)?;
+    Some(elements.chunks(32).map(U256::from_be_slice).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{address, LogData};
+
+    const TOKEN: Address = address!("1000000000000000000000000000000000000001");
+    const ALICE: Address = address!("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+    const BOB: Address = address!("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+
+    fn word(value: u64) -> B256 {
+        B256::from(U256::from(value))
+    }
+
+    fn transfer_log(indexed: &[B256], data: &[B256]) -> Log {
+        let topics = [&[ERC20_TRANSFER_EVENT_SIGNATURE][..], indexed].concat();
+        let data: Vec<u8> = data.iter().flat_map(|word| word.0).collect();
+        Log {
+            address: TOKEN,
+            data: LogData::new_unchecked(topics, data.into()),
         }
-        results
+    }
+
+    #[test]
+    fn test_standard_erc20_transfer() {
+        let log = transfer_log(&[ALICE.into_word(), BOB.into_word()], &[word(500)]);
+        let transfers = TokenTransfer::try_get_token_transfers(&log).unwrap();
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].token, TOKEN);
+        assert_eq!(transfers[0].from, ALICE);
+        assert_eq!(transfers[0].to, Some(BOB));
+        assert_eq!(transfers[0].value, U256::from(500));
+        assert_eq!(transfers[0].token_type, TokenType::ERC20);
+        assert_eq!(transfers[0].id, None);
+    }
+
+    #[test]
+    fn test_erc721_transfer() {
+        let log = transfer_log(&[ALICE.into_word(), BOB.into_word(), word(42)], &[]);
+        let transfers = TokenTransfer::try_get_token_transfers(&log).unwrap();
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].from, ALICE);
+        assert_eq!(transfers[0].to, Some(BOB));
+        assert_eq!(transfers[0].value, U256::from(1));
+        assert_eq!(transfers[0].token_type, TokenType::ERC721);
+        assert_eq!(transfers[0].id, Some(U256::from(42)));
+    }
+
+    #[test]
+    fn test_legacy_layouts_decode_from_data() {
+        // `to` not indexed: reading the value from the first data word would give BOB
+        let log = transfer_log(&[ALICE.into_word()], &[BOB.into_word(), word(500)]);
+        let transfers = TokenTransfer::try_get_token_transfers(&log).unwrap();
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].from, ALICE);
+        assert_eq!(transfers[0].to, Some(BOB));
+        assert_eq!(transfers[0].value, U256::from(500));
+        assert_eq!(transfers[0].token_type, TokenType::ERC20);
+
+        let log = transfer_log(&[], &[ALICE.into_word(), BOB.into_word(), word(7)]);
+        let transfers = TokenTransfer::try_get_token_transfers(&log).unwrap();
+        assert_eq!(
+            (transfers[0].from, transfers[0].to, transfers[0].value),
+            (ALICE, Some(BOB), U256::from(7))
+        );
+    }
+
+    #[test]
+    fn test_malformed_transfer_is_reported() {
+        // 3 topics with two data words
+        let log = transfer_log(&[ALICE.into_word(), BOB.into_word()], &[word(1), word(2)]);
+        assert_eq!(
+            TokenTransfer::try_get_token_transfers(&log).unwrap_err(),
+            ParseWarning::MalformedTransferLog {
+                token: TOKEN,
+                reason: "Transfer with 3 topics has 64 data bytes, expected 32".to_string(),
+            }
+        );
+        assert!(TokenTransfer::get_token_transfers(&log).is_empty());
+
+        // Dirty upper bytes in an address word
+        let log = transfer_log(&[B256::repeat_byte(0xff), BOB.into_word()], &[word(1)]);
+        assert_eq!(
+            TokenTransfer::try_get_token_transfers(&log).unwrap_err(),
+            ParseWarning::MalformedTransferLog {
+                token: TOKEN,
+                reason: "Transfer `from` is not an address".to_string(),
+            }
+        );
+
+        // Other events and anonymous logs are not transfers
+        let log = Log {
+            address: TOKEN,
+            data: LogData::new_unchecked(vec![], Bytes::from(vec![0xde, 0xad])),
+        };
+        assert!(TokenTransfer::try_get_token_transfers(&log)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_truncated_transfer_batch_is_reported() {
+        let topics = vec![
+            ERC1155_TRANSFER_BATCH_EVENT_SIGNATURE,
+            word(0),
+            ALICE.into_word(),
+            BOB.into_word(),
+        ];
+        // Offsets of both arrays, then an `ids` length pointing past the end
+        let data: Vec<u8> = [word(64), word(128), word(1_000)]
+            .iter()
+            .flat_map(|word| word.0)
+            .collect();
+        let log = Log {
+            address: TOKEN,
+            data: LogData::new_unchecked(topics, data.into()),
+        };
+        assert!(matches!(
+            TokenTransfer::try_get_token_transfers(&log),
+            Err(ParseWarning::MalformedTransferLog { .. })
+        ));
+    }
+
+    #[test]
+    fn test_halt_reason_classification() {
+        use revm::context_interface::result::OutOfGasError;
+
+        let reason = HaltReason::OutOfGas(OutOfGasError::Memory);
+        assert_eq!(HaltReasonKind::from(&reason), HaltReasonKind::OutOfGas);
+        let status = CallStatus::halt(&reason);
+        assert!(status.is_out_of_gas());
+        assert_eq!(status.halt_reason(), Some(HaltReasonKind::OutOfGas));
+        assert_eq!(status.message().as_deref(), Some("OutOfGas(Memory)"));
+
+        let status = CallStatus::halt(&HaltReason::InvalidJump);
+        assert!(!status.is_out_of_gas());
+        assert_eq!(status.halt_reason(), Some(HaltReasonKind::InvalidJump));
+        assert_eq!(
+            HaltReasonKind::from_name("NotARevmReason"),
+            HaltReasonKind::Other
+        );
+    }
+
+    #[test]
+    fn test_call_status_json_shape() {
+        let status = CallStatus::Revert {
+            reason: Some(DecodedRevert::Error("nope".to_string())),
+            raw: Bytes::from(vec![0x08, 0xc3, 0x79, 0xa0]),
+        };
+        assert_eq!(
+            serde_json::to_value(&status).unwrap(),
+            serde_json::json!({
+                "Revert": "nope",
+                "raw": "0x08c379a0",
+                "decoded": { "Error": "nope" },
+            })
+        );
+        assert_eq!(status.revert_bytes().map(|raw| raw.len()), Some(4));
+
+        let status = CallStatus::halt(&HaltReason::InvalidJump);
+        assert_eq!(
+            serde_json::to_value(&status).unwrap(),
+            serde_json::json!({ "Halt": "InvalidJump", "kind": "InvalidJump" })
+        );
+        assert_eq!(
+            serde_json::to_value(CallStatus::Success).unwrap(),
+            serde_json::json!("Success")
+        );
+    }
+
+    #[test]
+    fn test_call_status_reads_earlier_format() {
+        let read = |json: &str| serde_json::from_str::<CallStatus>(json).unwrap();
+        assert_eq!(read(r#""Success""#), CallStatus::Success);
+        assert_eq!(
+            read(r#"{"Revert":"0x1234"}"#),
+            CallStatus::Revert {
+                reason: None,
+                raw: Bytes::from(vec![0x12, 0x34]),
+            }
+        );
+        assert_eq!(
+            read(r#"{"Revert":"insufficient balance"}"#),
+            CallStatus::Revert {
+                reason: Some(DecodedRevert::Error("insufficient balance".to_string())),
+                raw: Bytes::new(),
+            }
+        );
+        assert!(read(r#"{"Halt":"OutOfGas(Basic)"}"#).is_out_of_gas());
+        assert_eq!(
+            read(r#"{"Halt":"CallTooDeep"}"#).halt_reason(),
+            Some(HaltReasonKind::CallTooDeep)
+        );
     }
 }
@@ -5,10 +5,30 @@
 //! - **Account balances**: Native token balance queries
 //! - **Error handling**: Transaction error parsing and analysis
 //! - **Proxy contracts**: Implementation resolution and detection
+//! - **Diamonds**: EIP-2535 facet resolution and trace annotation
+//! - **Interfaces**: ERC-165 and heuristic classification of counterparties
 //! - **Multicall operations**: Batch contract call execution
+//! - **Contract deployment**: Library linking and constructor argument encoding
+//! - **State cheats**: Funding accounts with native and ERC20 balances
+//! - **DeFi pools**: Batched price and liquidity snapshots of AMM pools
+//! - **Swaps**: Approve-and-swap simulations with output and price impact reporting
+//! - **Permits**: EIP-2612 and Permit2 flows through storage synthesis or test-key signatures
+//! - **ENS**: Name resolution and reverse lookups (feature = "ens")
+//! - **Local nodes**: Replaying simulated batches on Anvil and Hardhat
 
 pub mod balance_utils;
+pub mod cheat_utils;
+pub mod defi_utils;
+pub mod deploy_utils;
+pub mod diamond_utils;
+#[cfg(feature = "ens")]
+pub mod ens_utils;
 pub mod erc20_utils;
 pub mod error_utils;
+pub mod interface_utils;
+#[cfg(any(feature = "default", feature = "rustls-tls"))]
+pub mod local_node_utils;
 pub mod multicall_utils;
+pub mod permit_utils;
 pub mod proxy_utils;
+pub mod swap_utils;
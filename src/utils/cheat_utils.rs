@@ -0,0 +1,235 @@
+//! State cheat utilities for funding accounts in simulations
+//!
+//! Provides `deal` helpers that write balances directly into the EVM's `CacheDB`
+//! overlay, so simulations can start from a funded account without impersonating
+//! a whale.
+//!
+//! ERC20 balances are located by probing rather than by computing mapping slots:
+//! a traced `balanceOf` call reveals every storage slot the token reads, and the
+//! slot that controls the returned value is the one that holds the balance. This
+//! works the same for Solidity (`keccak(key . slot)`) and Vyper (`keccak(slot . key)`)
+//! mapping layouts, and for proxies, whose storage lives at the proxy address.
+//!
+//! Note that `trace_transactions` clears the cache at the start of every batch, so
+//! dealt balances only survive into a batch when passed as a `StateOverride` (see
+//! [`find_balance_slot`]).
+
+use crate::{
+    errors::{EvmError, RuntimeError},
+    evm::TraceEvm,
+    inspectors::tx_inspector::TxInspector,
+    types::{AccountOverride, SimulationTx, SlotAccessType, TraceLevel},
+    utils::erc20_utils::query_erc20_balance,
+};
+use alloy::{
+    primitives::{Address, Bytes, TxKind, U256},
+    sol,
+    sol_types::SolCall,
+};
+use revm::database::{CacheDB, DatabaseRef};
+
+sol! {
+    function balanceOf(address owner) public returns (uint256);
+}
+
+/// Distinctive value written to candidate slots while probing
+const PROBE_VALUE: u64 = 0x5eed_dea1;
+
+/// Sets the ERC20 balance of `to` to exactly `amount`
+///
+/// Locates the balance slot with [`find_balance_slot`], writes `amount` into it and
+/// verifies the result with a follow-up `balanceOf` call. Balances packed together
+/// with other fields in one slot are overwritten as a whole.
+///
+/// # Arguments
+/// - `evm`: EVM instance backed by a `CacheDB`
+/// - `token`: ERC20 token contract address
+/// - `to`: Account to fund
+/// - `amount`: Resulting token balance in the token's smallest unit
+///
+/// # Returns
+/// - `Ok(())`: The balance was written and verified
+/// - `Err(EvmError::Runtime(RuntimeError::BalanceSlotNotFound(_)))`: No storage slot
+///   controls the balance (e.g. rebasing tokens that derive balances from shares)
+/// - `Err(EvmError::Runtime(_))`: Any other variant indicates a database or RPC failure
+///
+/// # Example
+/// ```no_run
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// use revm_trace::{create_evm_with_tracer, utils::cheat_utils::deal, TxInspector};
+/// use alloy::primitives::{address, U256};
+///
+/// let mut evm = create_evm_with_tracer(
+///     "https://eth-mainnet.g.alchemy.com/v2/your-key",
+///     TxInspector::new(),
+/// )
+/// .await?;
+/// let usdc = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+/// let user = address!("1000000000000000000000000000000000000001");
+/// deal(&mut evm, usdc, user, U256::from(1_000_000_000u64))?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn deal<DB>(
+    evm: &mut TraceEvm<CacheDB<DB>, TxInspector>,
+    token: Address,
+    to: Address,
+    amount: U256,
+) -> Result<(), EvmError>
+where
+    DB: DatabaseRef,
+{
+    let (address, slot) = find_balance_slot(evm, token, to)?;
+    evm.insert_storage(address, slot, amount)?;
+
+    let balance = read_balance(evm, token, to)?;
+    if balance != amount {
+        return Err(RuntimeError::BalanceSlotNotFound(format!(
+            "balanceOf({to}) on {token} returned {balance} after writing {amount} to slot {slot}"
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// Sets the native balance of `to` to exactly `amount`
+///
+/// # Arguments
+/// - `evm`: EVM instance backed by a `CacheDB`
+/// - `to`: Account to fund
+/// - `amount`: Resulting balance in wei
+pub fn deal_native<DB, INSP>(
+    evm: &mut TraceEvm<CacheDB<DB>, INSP>,
+    to: Address,
+    amount: U256,
+) -> Result<(), EvmError>
+where
+    DB: DatabaseRef,
+{
+    evm.set_balance(to, amount)
+}
+
+/// Finds the storage slot holding the ERC20 balance of `holder`
+///
+/// Traces `balanceOf(holder)`, then overwrites each slot read during the call with a
+/// probe value until the returned balance changes to match. Every probed slot is
+/// restored afterwards.
+///
+/// # Arguments
+/// - `evm`: EVM instance backed by a `CacheDB`
+/// - `token`: ERC20 token contract address
+/// - `holder`: Account whose balance slot to locate
+///
+/// # Returns
+/// - `Ok((address, slot))`: The contract storing the balance (the proxy for proxied
+///   tokens) and the slot within it, suitable for a `StateOverride`
+/// - `Err(EvmError::Runtime(RuntimeError::BalanceSlotNotFound(_)))`: `balanceOf`
+///   reverted or no slot read during it controls the result
+/// - `Err(EvmError::Runtime(_))`: Any other variant indicates a database or RPC failure
+pub fn find_balance_slot<DB>(
+    evm: &mut TraceEvm<CacheDB<DB>, TxInspector>,
+    token: Address,
+    holder: Address,
+) -> Result<(Address, U256), EvmError>
+where
+    DB: DatabaseRef,
+{
+    let data = balanceOfCall { owner: holder }.abi_encode().into();
+    match find_view_slot(evm, token, holder, data) {
+        Ok(Some(found)) => Ok(found),
+        Ok(None) => Err(RuntimeError::BalanceSlotNotFound(format!(
+            "no storage slot controls balanceOf({holder}) on {token}"
+        ))
+        .into()),
+        Err(EvmError::Runtime(RuntimeError::Revert(_))) => Err(RuntimeError::BalanceSlotNotFound(
+            format!("balanceOf({holder}) reverted on {token}"),
+        )
+        .into()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Finds the storage slot whose value a view call returns as its first word
+///
+/// Traces the call from `caller`, then overwrites each slot read during it with a
+/// probe value until the first returned word changes to match. Slots read last are
+/// tried first, as the lookup usually follows proxy and access-control reads. Every
+/// probe lives in ephemeral state (see `TraceEvm::with_ephemeral_state`), so the
+/// overlay is left exactly as it was.
+///
+/// # Returns
+/// - `Ok(Some((address, slot)))`: The contract storing the value and the slot within it
+/// - `Ok(None)`: No slot read during the call controls the result
+/// - `Err(EvmError::Runtime(RuntimeError::Revert(_)))`: The call reverted
+/// - `Err(EvmError::Runtime(_))`: Any other variant indicates a database or RPC failure
+pub(crate) fn find_view_slot<DB>(
+    evm: &mut TraceEvm<CacheDB<DB>, TxInspector>,
+    contract: Address,
+    caller: Address,
+    data: Bytes,
+) -> Result<Option<(Address, U256)>, EvmError>
+where
+    DB: DatabaseRef,
+{
+    let tx = SimulationTx {
+        caller,
+        transact_to: TxKind::Call(contract),
+        value: U256::ZERO,
+        data: data.clone(),
+        trace_config: Some(TraceLevel::Full),
+        return_abi: None,
+    };
+    let (result, _, output) = evm.trace_internal(tx, false)?;
+    if !result.is_success() {
+        return Err(RuntimeError::Revert(format!("view call to {contract} reverted")).into());
+    }
+
+    let mut candidates: Vec<(Address, U256)> = Vec::new();
+    if let Some(root) = &output.call_trace {
+        for access in root
+            .all_slot_accesses(SlotAccessType::Read)
+            .into_iter()
+            .rev()
+        {
+            if !candidates.contains(&(access.address, access.slot)) {
+                candidates.push((access.address, access.slot));
+            }
+        }
+    }
+
+    let probe = U256::from(PROBE_VALUE);
+    for (address, slot) in candidates {
+        let probe_override = AccountOverride {
+            address,
+            storage: vec![(slot, probe)],
+            ..Default::default()
+        };
+        // A failing call means the slot is not the value (e.g. a proxy implementation slot)
+        let probed = evm.with_ephemeral_state(vec![probe_override], |evm| {
+            evm.static_call(contract, data.clone(), caller)
+                .ok()
+                .and_then(|output| output.get(..32).map(U256::from_be_slice))
+        })?;
+        if probed == Some(probe) {
+            return Ok(Some((address, slot)));
+        }
+    }
+    Ok(None)
+}
+
+/// Reads a balance after a write, reporting an unreadable balance as a lookup failure
+fn read_balance<DB>(
+    evm: &mut TraceEvm<CacheDB<DB>, TxInspector>,
+    token: Address,
+    holder: Address,
+) -> Result<U256, EvmError>
+where
+    DB: DatabaseRef,
+{
+    query_erc20_balance(evm, token, holder).map_err(|e| {
+        RuntimeError::BalanceSlotNotFound(format!(
+            "balanceOf({holder}) on {token} failed after writing the balance: {e}"
+        ))
+        .into()
+    })
+}
@@ -0,0 +1,328 @@
+//! Price and liquidity snapshots of AMM pools
+//!
+//! Reads the state of many pools with a single Multicall batch: Uniswap V2 style
+//! reserves, Uniswap V3 style `slot0`, liquidity and fee, and Curve coin balances.
+//! Queries run against the current simulated state (see
+//! [`MulticallManager::batch_call_on_current_state`]), so a snapshot taken between
+//! stateful transactions reflects their effect, e.g. the price impact of a simulated
+//! swap.
+//!
+//! Every pool is decoded independently: a pool that reverts or returns malformed data
+//! yields a [`PoolError`] for its entry without failing the rest of the snapshot.
+
+use alloy::{
+    primitives::{Address, Bytes, U256},
+    sol,
+    sol_types::SolCall,
+};
+use revm::database::{CacheDB, DatabaseRef};
+use serde::Serialize;
+
+use crate::{
+    errors::{EvmError, PoolError},
+    evm::TraceEvm,
+    utils::multicall_utils::{MulticallCall, MulticallManager, MulticallResult},
+};
+
+sol! {
+    function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast);
+    function slot0() external view returns (uint160 sqrtPriceX96, int24 tick, uint16 observationIndex, uint16 observationCardinality, uint16 observationCardinalityNext, uint8 feeProtocol, bool unlocked);
+    function liquidity() external view returns (uint128);
+    function fee() external view returns (uint24);
+    function balances(uint256 i) external view returns (uint256);
+}
+
+/// AMM protocol family of a pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum PoolProtocol {
+    UniswapV2,
+    UniswapV3,
+    Curve,
+}
+
+/// Pool to include in a snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PoolSpec {
+    /// Uniswap V2 style pair (`getReserves`)
+    UniswapV2(Address),
+    /// Uniswap V3 style pool (`slot0`, `liquidity`, `fee`)
+    UniswapV3(Address),
+    /// Curve pool with `n_coins` coins (`balances(uint256)`)
+    ///
+    /// Early Curve pools index `balances` with `int128` and are not supported.
+    Curve { pool: Address, n_coins: usize },
+}
+
+impl PoolSpec {
+    /// Pool contract address
+    pub fn address(&self) -> Address {
+        match *self {
+            Self::UniswapV2(pool) | Self::UniswapV3(pool) | Self::Curve { pool, .. } => pool,
+        }
+    }
+
+    /// Protocol family of the pool
+    pub fn protocol(&self) -> PoolProtocol {
+        match self {
+            Self::UniswapV2(_) => PoolProtocol::UniswapV2,
+            Self::UniswapV3(_) => PoolProtocol::UniswapV3,
+            Self::Curve { .. } => PoolProtocol::Curve,
+        }
+    }
+
+    /// Encoded calls reading the pool state, decoded in the same order by `decode_state`
+    fn calls(&self) -> Vec<Bytes> {
+        match *self {
+            Self::UniswapV2(_) => vec![getReservesCall {}.abi_encode().into()],
+            Self::UniswapV3(_) => vec![
+                slot0Call {}.abi_encode().into(),
+                liquidityCall {}.abi_encode().into(),
+                feeCall {}.abi_encode().into(),
+            ],
+            Self::Curve { n_coins, .. } => (0..n_coins)
+                .map(|i| balancesCall { i: U256::from(i) }.abi_encode().into())
+                .collect(),
+        }
+    }
+}
+
+/// Reserves of a Uniswap V2 style pair
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct V2Reserves {
+    /// Pair address
+    pub pool: Address,
+    /// Reserve of `token0`
+    pub reserve0: U256,
+    /// Reserve of `token1`
+    pub reserve1: U256,
+    /// Timestamp of the last reserve update (mod 2^32)
+    pub block_timestamp_last: u32,
+}
+
+/// Price and liquidity of a Uniswap V3 style pool
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct V3Slot0 {
+    /// Pool address
+    pub pool: Address,
+    /// Current sqrt(token1/token0) price as a Q64.96 value
+    pub sqrt_price_x96: U256,
+    /// Current tick
+    pub tick: i32,
+    /// In-range liquidity
+    pub liquidity: u128,
+    /// Swap fee in hundredths of a basis point
+    pub fee: u32,
+}
+
+/// Coin balances of a Curve pool
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CurveBalances {
+    /// Pool address
+    pub pool: Address,
+    /// Balance of each coin, in coin index order
+    pub balances: Vec<U256>,
+}
+
+/// State of one pool, tagged with its protocol
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "protocol")]
+pub enum PoolState {
+    UniswapV2(V2Reserves),
+    UniswapV3(V3Slot0),
+    Curve(CurveBalances),
+}
+
+impl PoolState {
+    /// Protocol family of the pool
+    pub fn protocol(&self) -> PoolProtocol {
+        match self {
+            Self::UniswapV2(_) => PoolProtocol::UniswapV2,
+            Self::UniswapV3(_) => PoolProtocol::UniswapV3,
+            Self::Curve(_) => PoolProtocol::Curve,
+        }
+    }
+
+    /// Pool contract address
+    pub fn pool(&self) -> Address {
+        match self {
+            Self::UniswapV2(state) => state.pool,
+            Self::UniswapV3(state) => state.pool,
+            Self::Curve(state) => state.pool,
+        }
+    }
+}
+
+/// Reads the reserves of Uniswap V2 style pairs
+///
+/// # Arguments
+/// - `evm`: EVM instance backed by a `CacheDB`
+/// - `pairs`: Pair contract addresses
+///
+/// # Returns
+/// - `Ok(Vec<Result<V2Reserves, PoolError>>)`: One entry per pair, in input order
+/// - `Err(EvmError)`: If the Multicall batch itself could not be executed
+pub fn get_v2_reserves<DB, INSP>(
+    evm: &mut TraceEvm<CacheDB<DB>, INSP>,
+    pairs: &[Address],
+) -> Result<Vec<Result<V2Reserves, PoolError>>, EvmError>
+where
+    DB: DatabaseRef,
+{
+    let specs: Vec<PoolSpec> = pairs.iter().copied().map(PoolSpec::UniswapV2).collect();
+    let states = snapshot_pools(evm, &specs)?;
+    Ok(states
+        .into_iter()
+        .map(|state| {
+            state.map(|state| match state {
+                PoolState::UniswapV2(reserves) => reserves,
+                _ => unreachable!("V2 spec decoded as {:?}", state.protocol()),
+            })
+        })
+        .collect())
+}
+
+/// Reads the price, tick, liquidity and fee of Uniswap V3 style pools
+///
+/// # Arguments
+/// - `evm`: EVM instance backed by a `CacheDB`
+/// - `pools`: Pool contract addresses
+///
+/// # Returns
+/// - `Ok(Vec<Result<V3Slot0, PoolError>>)`: One entry per pool, in input order
+/// - `Err(EvmError)`: If the Multicall batch itself could not be executed
+pub fn get_v3_slot0<DB, INSP>(
+    evm: &mut TraceEvm<CacheDB<DB>, INSP>,
+    pools: &[Address],
+) -> Result<Vec<Result<V3Slot0, PoolError>>, EvmError>
+where
+    DB: DatabaseRef,
+{
+    let specs: Vec<PoolSpec> = pools.iter().copied().map(PoolSpec::UniswapV3).collect();
+    let states = snapshot_pools(evm, &specs)?;
+    Ok(states
+        .into_iter()
+        .map(|state| {
+            state.map(|state| match state {
+                PoolState::UniswapV3(slot0) => slot0,
+                _ => unreachable!("V3 spec decoded as {:?}", state.protocol()),
+            })
+        })
+        .collect())
+}
+
+/// Reads the state of pools of mixed protocols in one Multicall batch
+///
+/// # Arguments
+/// - `evm`: EVM instance backed by a `CacheDB`
+/// - `specs`: Pools to read
+///
+/// # Returns
+/// - `Ok(Vec<Result<PoolState, PoolError>>)`: One entry per spec, in input order
+/// - `Err(EvmError)`: If the Multicall batch itself could not be executed
+///
+/// # Example
+/// ```no_run
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// use alloy::primitives::address;
+/// use revm_trace::{
+///     create_evm_with_tracer,
+///     utils::defi_utils::{snapshot_pools, PoolSpec},
+///     TxInspector,
+/// };
+///
+/// let mut evm = create_evm_with_tracer("https://eth.llamarpc.com", TxInspector::new()).await?;
+/// let specs = [
+///     PoolSpec::UniswapV2(address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc")),
+///     PoolSpec::UniswapV3(address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640")),
+/// ];
+/// // ... evm.trace_transactions(batch) with is_stateful: true ...
+/// for state in snapshot_pools(&mut evm, &specs)? {
+///     println!("{state:?}");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn snapshot_pools<DB, INSP>(
+    evm: &mut TraceEvm<CacheDB<DB>, INSP>,
+    specs: &[PoolSpec],
+) -> Result<Vec<Result<PoolState, PoolError>>, EvmError>
+where
+    DB: DatabaseRef,
+{
+    let calls: Vec<MulticallCall> = specs
+        .iter()
+        .flat_map(|spec| {
+            spec.calls().into_iter().map(|call_data| MulticallCall {
+                target: spec.address(),
+                callData: call_data,
+            })
+        })
+        .collect();
+    let mut results = MulticallManager::new()
+        .batch_call_on_current_state(evm, calls, false)?
+        .into_iter();
+
+    Ok(specs
+        .iter()
+        .map(|spec| {
+            let spec_results: Vec<MulticallResult> =
+                results.by_ref().take(spec.calls().len()).collect();
+            decode_state(spec, &spec_results)
+        })
+        .collect())
+}
+
+/// Decodes the results of the calls returned by `PoolSpec::calls`
+fn decode_state(spec: &PoolSpec, results: &[MulticallResult]) -> Result<PoolState, PoolError> {
+    let pool = spec.address();
+    let state = match *spec {
+        PoolSpec::UniswapV2(_) => {
+            let reserves = decode_return::<getReservesCall>(pool, &results[0])?;
+            PoolState::UniswapV2(V2Reserves {
+                pool,
+                reserve0: U256::from(reserves.reserve0),
+                reserve1: U256::from(reserves.reserve1),
+                block_timestamp_last: reserves.blockTimestampLast,
+            })
+        }
+        PoolSpec::UniswapV3(_) => {
+            let slot0 = decode_return::<slot0Call>(pool, &results[0])?;
+            let liquidity = decode_return::<liquidityCall>(pool, &results[1])?;
+            let fee = decode_return::<feeCall>(pool, &results[2])?;
+            PoolState::UniswapV3(V3Slot0 {
+                pool,
+                sqrt_price_x96: U256::from(slot0.sqrtPriceX96),
+                tick: slot0.tick.as_i32(),
+                liquidity,
+                fee: fee.to::<u32>(),
+            })
+        }
+        PoolSpec::Curve { .. } => PoolState::Curve(CurveBalances {
+            pool,
+            balances: results
+                .iter()
+                .map(|result| decode_return::<balancesCall>(pool, result))
+                .collect::<Result<_, _>>()?,
+        }),
+    };
+    Ok(state)
+}
+
+/// Decodes one call result, reporting reverts and malformed data per pool
+fn decode_return<C: SolCall>(
+    pool: Address,
+    result: &MulticallResult,
+) -> Result<C::Return, PoolError> {
+    if !result.success {
+        return Err(PoolError::CallReverted {
+            address: pool.to_string(),
+            call: C::SIGNATURE.to_string(),
+        });
+    }
+    // Calls to accounts without code succeed with empty return data
+    C::abi_decode_returns(&result.returnData).map_err(|e| PoolError::Decode {
+        address: pool.to_string(),
+        call: C::SIGNATURE.to_string(),
+        reason: e.to_string(),
+    })
+}
@@ -0,0 +1,212 @@
+//! Contract deployment utilities
+//!
+//! Helpers for simulating contract deployments straight from compiler artifacts:
+//! library linking, constructor argument encoding and extraction of the created
+//! contract address from the deployment trace.
+
+use std::collections::HashMap;
+
+use crate::{
+    errors::{EvmError, RuntimeError},
+    evm::TraceEvm,
+    inspectors::tx_inspector::{TxInspector, TxTraceOutput},
+    types::SimulationTx,
+};
+use alloy::{
+    dyn_abi::{DynSolType, DynSolValue, Specifier},
+    json_abi::Constructor,
+    primitives::{hex, keccak256, Address, Bytes, TxKind, U256},
+};
+use revm::database::{Database, DatabaseCommit};
+
+/// Length of a library placeholder (`__$` + 34 hex characters + `$__`)
+const PLACEHOLDER_LEN: usize = 40;
+
+/// Links library addresses into unlinked creation bytecode
+///
+/// Solidity emits `__$<34 hex chars>$__` placeholders for external library calls,
+/// where the hex part is the first 17 bytes of `keccak256(fully qualified name)`.
+/// Libraries can be keyed either by their fully qualified name
+/// (`contracts/Math.sol:Math`) or by the raw 34-character placeholder hash.
+///
+/// # Arguments
+/// - `bytecode`: Hex-encoded creation bytecode, with or without `0x` prefix
+/// - `libraries`: Library addresses keyed by fully qualified name or placeholder hash
+///
+/// # Returns
+/// - `Ok(Bytes)`: Fully linked bytecode
+/// - `Err(EvmError)`: If a placeholder has no matching library or the hex is invalid
+pub fn link_libraries(
+    bytecode: &str,
+    libraries: &HashMap<String, Address>,
+) -> Result<Bytes, EvmError> {
+    let by_hash: HashMap<String, Address> = libraries
+        .iter()
+        .map(|(key, address)| (placeholder_hash(key), *address))
+        .collect();
+
+    let code = bytecode.strip_prefix("0x").unwrap_or(bytecode);
+    let mut linked = String::with_capacity(code.len());
+    let mut rest = code;
+    while let Some(start) = rest.find("__$") {
+        let placeholder = rest
+            .get(start..start + PLACEHOLDER_LEN)
+            .filter(|p| p.ends_with("$__"))
+            .ok_or_else(|| {
+                RuntimeError::InvalidInput("Malformed library placeholder in bytecode".to_string())
+            })?;
+        let hash = &placeholder[3..PLACEHOLDER_LEN - 3];
+        let address = by_hash.get(hash).ok_or_else(|| {
+            RuntimeError::InvalidInput(format!("Unlinked library placeholder: {placeholder}"))
+        })?;
+        linked.push_str(&rest[..start]);
+        linked.push_str(&hex::encode(address));
+        rest = &rest[start + PLACEHOLDER_LEN..];
+    }
+    linked.push_str(rest);
+
+    hex::decode(&linked)
+        .map(Into::into)
+        .map_err(|e| RuntimeError::InvalidInput(format!("Invalid bytecode hex: {e}")).into())
+}
+
+/// Builds a contract creation transaction from artifact bytecode and constructor arguments
+///
+/// When `constructor_abi` is provided, the argument count and types are validated
+/// against it before encoding.
+///
+/// # Arguments
+/// - `artifact_bytecode`: Linked creation bytecode (see [`link_libraries`])
+/// - `constructor_args`: Constructor arguments, in declaration order
+/// - `constructor_abi`: Optional constructor definition for validation
+/// - `caller`: Deployer address
+/// - `value`: Native token sent to the constructor
+///
+/// # Returns
+/// - `Ok(SimulationTx)`: Creation transaction with `TxKind::Create`
+/// - `Err(EvmError)`: If the arguments do not match the constructor ABI
+pub fn build_deployment_tx(
+    artifact_bytecode: Bytes,
+    constructor_args: &[DynSolValue],
+    constructor_abi: Option<&Constructor>,
+    caller: Address,
+    value: U256,
+) -> Result<SimulationTx, EvmError> {
+    if let Some(constructor) = constructor_abi {
+        if constructor.inputs.len() != constructor_args.len() {
+            return Err(RuntimeError::InvalidInput(format!(
+                "Constructor expects {} arguments, got {}",
+                constructor.inputs.len(),
+                constructor_args.len()
+            ))
+            .into());
+        }
+        for (param, arg) in constructor.inputs.iter().zip(constructor_args) {
+            let ty: DynSolType = param.resolve().map_err(|e| {
+                RuntimeError::InvalidInput(format!("Invalid constructor parameter type: {e}"))
+            })?;
+            if !ty.matches(arg) {
+                return Err(RuntimeError::InvalidInput(format!(
+                    "Constructor argument `{}` does not match type {}",
+                    param.name, param.ty
+                ))
+                .into());
+            }
+        }
+    }
+
+    let mut data = artifact_bytecode.to_vec();
+    if !constructor_args.is_empty() {
+        data.extend(DynSolValue::Tuple(constructor_args.to_vec()).abi_encode_params());
+    }
+
+    Ok(SimulationTx {
+        caller,
+        value,
+        data: data.into(),
+        transact_to: TxKind::Create,
+        trace_config: None,
+        return_abi: None,
+    })
+}
+
+/// Executes a deployment transaction statefully and returns the created address
+///
+/// The deployment is committed to the EVM database, so subsequent calls can
+/// interact with the new contract.
+///
+/// # Arguments
+/// - `evm`: EVM instance with a `TxInspector`
+/// - `tx`: Creation transaction (see [`build_deployment_tx`])
+///
+/// # Returns
+/// - `Ok((Address, TxTraceOutput))`: Created contract address and deployment trace
+/// - `Err(EvmError)`: If execution fails or the deployment does not succeed
+pub fn deploy_and_get_address<DB>(
+    evm: &mut TraceEvm<DB, TxInspector>,
+    tx: SimulationTx,
+) -> Result<(Address, TxTraceOutput), EvmError>
+where
+    DB: Database + DatabaseCommit,
+{
+    if tx.transact_to != TxKind::Create {
+        return Err(RuntimeError::InvalidInput(
+            "Deployment transaction must use TxKind::Create".to_string(),
+        )
+        .into());
+    }
+
+    let (result, _, output) = evm.trace_internal(tx, true)?;
+    if !result.is_success() {
+        let reason = output
+            .call_trace
+            .as_ref()
+            .and_then(|trace| trace.status.message())
+            .unwrap_or_else(|| format!("{result:?}"));
+        return Err(RuntimeError::Revert(reason).into());
+    }
+
+    let address = output
+        .call_trace
+        .as_ref()
+        .filter(|trace| trace.create_scheme.is_some())
+        .map(|trace| trace.to)
+        .ok_or_else(|| {
+            RuntimeError::ExecutionFailed("Deployment trace has no CREATE frame".to_string())
+        })?;
+
+    Ok((address, output))
+}
+
+/// Normalizes a library key to its 34-character placeholder hash
+fn placeholder_hash(key: &str) -> String {
+    let is_hash = key.len() == PLACEHOLDER_LEN - 6 && key.chars().all(|c| c.is_ascii_hexdigit());
+    if is_hash {
+        key.to_ascii_lowercase()
+    } else {
+        hex::encode(&keccak256(key.as_bytes())[..17])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::address;
+
+    #[test]
+    fn test_link_libraries() {
+        let hash = placeholder_hash("contracts/Math.sol:Math");
+        let bytecode = format!("0x6073__${hash}$__6000");
+        let math = address!("1111111111111111111111111111111111111111");
+
+        let by_name = HashMap::from([("contracts/Math.sol:Math".to_string(), math)]);
+        let linked = link_libraries(&bytecode, &by_name).unwrap();
+        assert_eq!(linked.len(), 2 + 20 + 2);
+        assert_eq!(&linked[2..22], math.as_slice());
+
+        let by_hash = HashMap::from([(hash, math)]);
+        assert_eq!(link_libraries(&bytecode, &by_hash).unwrap(), linked);
+
+        assert!(link_libraries(&bytecode, &HashMap::new()).is_err());
+    }
+}
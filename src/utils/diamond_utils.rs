@@ -0,0 +1,374 @@
+//! Diamond (EIP-2535) facet resolution
+//!
+//! A diamond routes every call through its fallback, which looks the selector up in a
+//! selector → facet table and delegatecalls the facet. Traces therefore show the
+//! diamond as the target of every call. [`resolve_facets`] reads the table through the
+//! standard loupe functions and [`annotate_diamond_calls`] tags the frames of a trace
+//! with the facet that handled them.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    errors::{EvmError, RuntimeError},
+    evm::TraceEvm,
+    inspectors::tx_inspector::TxTraceOutput,
+    types::{CallScheme, CallTrace, FacetRoute},
+};
+use alloy::{
+    primitives::{Address, Bytes, FixedBytes, TxKind},
+    sol,
+    sol_types::SolCall,
+};
+use revm::{
+    context::TxEnv,
+    context_interface::result::{ExecutionResult, Output},
+    database::Database,
+    ExecuteEvm,
+};
+
+// Diamond loupe interface (EIP-2535)
+sol! {
+    struct LoupeFacet {
+        address facetAddress;
+        bytes4[] functionSelectors;
+    }
+
+    function facets() external view returns (LoupeFacet[] memory);
+    function facetAddress(bytes4 selector) external view returns (address);
+}
+
+/// Facet of a diamond and the functions it implements
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Facet {
+    /// Contract holding the facet's code
+    pub facet_address: Address,
+    /// Selectors the diamond routes to this facet
+    pub selectors: Vec<[u8; 4]>,
+}
+
+/// Reads the facets of `diamond` through the `facets()` loupe function
+///
+/// Facets without selectors are left out. Loupe calls run against the EVM's current
+/// state and are not committed.
+///
+/// # Returns
+/// * `Ok(facets)` - The diamond's facets, empty if `diamond` does not implement the
+///   loupe (the call reverts or returns data that does not decode)
+/// * `Err(_)` - If the call could not be executed
+///
+/// # Example
+/// ```no_run
+/// use revm_trace::{create_evm, utils::diamond_utils::resolve_facets};
+/// use alloy::primitives::address;
+///
+/// # async fn example() -> anyhow::Result<()> {
+/// let mut evm = create_evm("https://eth.llamarpc.com").await?;
+/// let diamond = address!("1231DEB6f5749EF6cE6943a275A1D3E7486F4EaE");
+/// for facet in resolve_facets(&mut evm, diamond)? {
+///     println!("{}: {} functions", facet.facet_address, facet.selectors.len());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn resolve_facets<DB, INSP>(
+    evm: &mut TraceEvm<DB, INSP>,
+    diamond: Address,
+) -> Result<Vec<Facet>, EvmError>
+where
+    DB: Database,
+{
+    let Some(output) = loupe_call(evm, diamond, facetsCall {}.abi_encode().into())? else {
+        return Ok(Vec::new());
+    };
+    let Ok(facets) = facetsCall::abi_decode_returns(&output) else {
+        return Ok(Vec::new());
+    };
+    Ok(facets
+        .into_iter()
+        .filter(|facet| !facet.functionSelectors.is_empty())
+        .map(|facet| Facet {
+            facet_address: facet.facetAddress,
+            selectors: facet.functionSelectors.into_iter().map(|s| s.0).collect(),
+        })
+        .collect())
+}
+
+/// Resolves `selectors` one by one through the `facetAddress(bytes4)` loupe function
+///
+/// For diamonds whose `facets()` is missing or too expensive to call, resolve just the
+/// selectors seen in a trace (see [`called_selectors`]). Selectors the diamond does not
+/// route, and all selectors of diamonds without the loupe, are left out.
+///
+/// # Returns
+/// * `Ok(facets)` - Facets of the routed selectors, ordered by facet address
+/// * `Err(_)` - If a call could not be executed
+pub fn resolve_selectors<DB, INSP>(
+    evm: &mut TraceEvm<DB, INSP>,
+    diamond: Address,
+    selectors: &[[u8; 4]],
+) -> Result<Vec<Facet>, EvmError>
+where
+    DB: Database,
+{
+    let mut facets: BTreeMap<Address, Vec<[u8; 4]>> = BTreeMap::new();
+    for &selector in selectors {
+        let data = facetAddressCall {
+            selector: selector.into(),
+        }
+        .abi_encode()
+        .into();
+        let Some(output) = loupe_call(evm, diamond, data)? else {
+            continue;
+        };
+        match facetAddressCall::abi_decode_returns(&output) {
+            Ok(facet) if !facet.is_zero() => facets.entry(facet).or_default().push(selector),
+            _ => {}
+        }
+    }
+    Ok(facets
+        .into_iter()
+        .map(|(facet_address, selectors)| Facet {
+            facet_address,
+            selectors,
+        })
+        .collect())
+}
+
+/// Returns the distinct selectors of the calls into `diamond` recorded in `output`
+pub fn called_selectors(output: &TxTraceOutput, diamond: Address) -> Vec<[u8; 4]> {
+    let mut selectors = Vec::new();
+    for frame in output.call_trace.iter().flat_map(CallTrace::frames) {
+        if frame.to != diamond || frame.create_scheme.is_some() {
+            continue;
+        }
+        if let Some(selector) = frame.input.get(..4) {
+            let selector: [u8; 4] = selector.try_into().unwrap();
+            if !selectors.contains(&selector) {
+                selectors.push(selector);
+            }
+        }
+    }
+    selectors
+}
+
+/// Tags the frames of `output` that run against `diamond`'s storage with their facet
+///
+/// Two kinds of frames are annotated, both only when their selector is routed by
+/// `facets`:
+/// - calls into the diamond itself
+/// - DELEGATECALLs made in the diamond's context into the facet routing their
+///   selector, i.e. the fallback's forward
+///
+/// Other delegatecalls made by facets, e.g. into libraries, keep no annotation. An
+/// empty `facets` (a diamond without the loupe) leaves the trace unchanged.
+///
+/// # Returns
+/// The number of annotated frames
+pub fn annotate_diamond_calls(
+    output: &mut TxTraceOutput,
+    diamond: Address,
+    facets: &[Facet],
+) -> usize {
+    let routes: HashMap<[u8; 4], Address> = facets
+        .iter()
+        .flat_map(|facet| {
+            facet
+                .selectors
+                .iter()
+                .map(|&selector| (selector, facet.facet_address))
+        })
+        .collect();
+    match output.call_trace.as_mut() {
+        Some(root) if !routes.is_empty() => {
+            let context = root.from;
+            annotate_frame(root, context, diamond, &routes)
+        }
+        _ => 0,
+    }
+}
+
+/// Annotates `frame` and its subcalls; `context` is the storage address of its parent
+fn annotate_frame(
+    frame: &mut CallTrace,
+    context: Address,
+    diamond: Address,
+    routes: &HashMap<[u8; 4], Address>,
+) -> usize {
+    let delegated = matches!(
+        frame.call_scheme,
+        Some(CallScheme::DelegateCall | CallScheme::CallCode)
+    );
+    let storage_address = if delegated { context } else { frame.to };
+
+    let mut annotated = 0;
+    if storage_address == diamond && frame.create_scheme.is_none() {
+        let route = frame
+            .input
+            .get(..4)
+            .map(|selector| <[u8; 4]>::try_from(selector).unwrap())
+            .and_then(|selector| Some((selector, *routes.get(&selector)?)));
+        if let Some((selector, facet)) = route {
+            if !delegated || frame.to == facet {
+                frame.facet_route = Some(FacetRoute {
+                    diamond,
+                    facet,
+                    selector: FixedBytes(selector),
+                });
+                annotated += 1;
+            }
+        }
+    }
+    for child in &mut frame.subtraces {
+        annotated += annotate_frame(child, storage_address, diamond, routes);
+    }
+    annotated
+}
+
+/// Calls a loupe function, returning its output or `None` if the call did not succeed
+fn loupe_call<DB, INSP>(
+    evm: &mut TraceEvm<DB, INSP>,
+    diamond: Address,
+    data: Bytes,
+) -> Result<Option<Bytes>, EvmError>
+where
+    DB: Database,
+{
+    // Use zero address as caller for read-only calls (no nonce needed)
+    let tx = TxEnv {
+        caller: Address::ZERO,
+        kind: TxKind::Call(diamond),
+        data,
+        chain_id: Some(evm.cfg.chain_id),
+        nonce: 0,
+        ..Default::default()
+    };
+    let result = evm.transact(tx).map_err(|e| {
+        RuntimeError::ExecutionFailed(format!("Failed to call diamond loupe of {diamond}: {e}"))
+    })?;
+    Ok(match result.result {
+        ExecutionResult::Success {
+            output: Output::Call(output),
+            ..
+        } => Some(output),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::address;
+
+    const USER: Address = address!("1000000000000000000000000000000000000001");
+    const DIAMOND: Address = address!("2000000000000000000000000000000000000002");
+    const LOUPE_FACET: Address = address!("3000000000000000000000000000000000000003");
+    const TOKEN_FACET: Address = address!("4000000000000000000000000000000000000004");
+    const LIBRARY: Address = address!("5000000000000000000000000000000000000005");
+
+    const FACETS: [u8; 4] = [0x7a, 0x0e, 0xd6, 0x27];
+    const TRANSFER: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+
+    fn frame(
+        from: Address,
+        to: Address,
+        scheme: CallScheme,
+        input: &[u8],
+        trace_address: Vec<usize>,
+        subtraces: Vec<CallTrace>,
+    ) -> CallTrace {
+        CallTrace {
+            from,
+            to,
+            input: Bytes::copy_from_slice(input),
+            call_scheme: Some(scheme),
+            trace_address,
+            subtraces,
+            ..Default::default()
+        }
+    }
+
+    fn facets() -> Vec<Facet> {
+        vec![
+            Facet {
+                facet_address: LOUPE_FACET,
+                selectors: vec![FACETS],
+            },
+            Facet {
+                facet_address: TOKEN_FACET,
+                selectors: vec![TRANSFER],
+            },
+        ]
+    }
+
+    #[test]
+    fn test_annotates_diamond_frames() {
+        let library = frame(
+            DIAMOND,
+            LIBRARY,
+            CallScheme::DelegateCall,
+            &TRANSFER,
+            vec![0, 0],
+            vec![],
+        );
+        let forward = frame(
+            USER,
+            TOKEN_FACET,
+            CallScheme::DelegateCall,
+            &TRANSFER,
+            vec![0],
+            vec![library],
+        );
+        let root = frame(
+            USER,
+            DIAMOND,
+            CallScheme::Call,
+            &TRANSFER,
+            vec![],
+            vec![forward],
+        );
+        let mut output = TxTraceOutput {
+            call_trace: Some(root),
+            ..Default::default()
+        };
+
+        assert_eq!(annotate_diamond_calls(&mut output, DIAMOND, &facets()), 2);
+        let root = output.call_trace.unwrap();
+        let expected = Some(FacetRoute {
+            diamond: DIAMOND,
+            facet: TOKEN_FACET,
+            selector: FixedBytes(TRANSFER),
+        });
+        assert_eq!(root.facet_route, expected);
+        assert_eq!(root.subtraces[0].facet_route, expected);
+        assert_eq!(root.subtraces[0].subtraces[0].facet_route, None);
+    }
+
+    #[test]
+    fn test_unrouted_and_unrelated_frames_are_untouched() {
+        let other = frame(USER, LIBRARY, CallScheme::Call, &FACETS, vec![], vec![]);
+        let unknown = frame(
+            USER,
+            DIAMOND,
+            CallScheme::Call,
+            &[1, 2, 3, 4],
+            vec![],
+            vec![],
+        );
+        for root in [other, unknown.clone()] {
+            let mut output = TxTraceOutput {
+                call_trace: Some(root),
+                ..Default::default()
+            };
+            assert_eq!(annotate_diamond_calls(&mut output, DIAMOND, &facets()), 0);
+            assert_eq!(output.call_trace.unwrap().facet_route, None);
+        }
+
+        // Without loupe data nothing is annotated
+        let mut output = TxTraceOutput {
+            call_trace: Some(unknown),
+            ..Default::default()
+        };
+        assert_eq!(annotate_diamond_calls(&mut output, DIAMOND, &[]), 0);
+        assert_eq!(called_selectors(&output, DIAMOND), vec![[1, 2, 3, 4]]);
+    }
+}
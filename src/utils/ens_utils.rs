@@ -0,0 +1,257 @@
+//! ENS name resolution through the simulated EVM (feature = "ens")
+//!
+//! Names are resolved by calling the ENS registry and resolvers inside the EVM, so
+//! the result reflects the forked block and any state changes simulated so far.
+//!
+//! - [`resolve`]: Forward resolution of a name to its address
+//! - [`reverse`]: Primary name of an address, verified by forward resolution
+//! - [`label_report`]: Attaches reverse-resolved names to the parties of a report
+//!
+//! Only Ethereum mainnet is supported; other chains fail with
+//! [`EnsError::EnsUnsupported`]. Names are normalized by ASCII lowercasing only,
+//! full UTS-46 normalization is out of scope.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{
+    errors::{EnsError, EvmError},
+    evm::TraceEvm,
+    report::SimulationReport,
+};
+use alloy::{
+    primitives::{address, hex, keccak256, Address, Bytes, TxKind, B256},
+    sol,
+    sol_types::SolCall,
+};
+use revm::{
+    context::TxEnv,
+    context_interface::result::{ExecutionResult, Output},
+    database::Database,
+    ExecuteEvm,
+};
+
+/// ENS registry, deployed at the same address on Ethereum mainnet since 2020
+pub const ENS_REGISTRY: Address = address!("00000000000C2E074eC69A0dFb2997BA6C7d2e1e");
+
+/// Chain the registry is deployed on
+const MAINNET_CHAIN_ID: u64 = 1;
+
+sol! {
+    function resolver(bytes32 node) external view returns (address);
+    function addr(bytes32 node) external view returns (address);
+    function name(bytes32 node) external view returns (string);
+}
+
+/// Computes the EIP-137 namehash of `name`
+///
+/// The empty name hashes to the zero node.
+///
+/// # Example
+/// ```
+/// use revm_trace::utils::ens_utils::namehash;
+///
+/// assert_eq!(
+///     namehash("eth").to_string(),
+///     "0x93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae"
+/// );
+/// ```
+pub fn namehash(name: &str) -> B256 {
+    let mut node = B256::ZERO;
+    if name.is_empty() {
+        return node;
+    }
+    for label in name.rsplit('.') {
+        let mut preimage = [0u8; 64];
+        preimage[..32].copy_from_slice(node.as_slice());
+        preimage[32..].copy_from_slice(keccak256(label.as_bytes()).as_slice());
+        node = keccak256(preimage);
+    }
+    node
+}
+
+/// Resolves `name` to an address
+///
+/// Looks up the name's resolver in the registry, then asks the resolver for the
+/// address.
+///
+/// # Arguments
+/// - `evm`: EVM instance forked from Ethereum mainnet
+/// - `name`: ENS name, e.g. `vitalik.eth`
+///
+/// # Returns
+/// - `Ok(Address)`: Address the name resolves to
+/// - `Err(EnsError::EnsUnsupported)`: If the EVM is not on mainnet
+/// - `Err(EnsError::NotResolved)`: If the name has no resolver or no address
+pub fn resolve<DB, INSP>(evm: &mut TraceEvm<DB, INSP>, name: &str) -> Result<Address, EvmError>
+where
+    DB: Database,
+{
+    ensure_mainnet(evm)?;
+    let name = normalize(name)?;
+    let node = namehash(&name);
+    let resolver = resolver_of(evm, node)?.ok_or_else(|| EnsError::NotResolved(name.clone()))?;
+    let resolved = call(evm, resolver, addrCall { node }.abi_encode().into())?
+        .and_then(|output| addrCall::abi_decode_returns(&output).ok())
+        .filter(|address| !address.is_zero());
+    resolved.ok_or_else(|| EnsError::NotResolved(name).into())
+}
+
+/// Returns the primary ENS name of `address`
+///
+/// The reverse record is only trusted if the name resolves back to `address`, as
+/// anyone can claim any name in their own reverse record.
+///
+/// # Returns
+/// - `Ok(Some(name))`: Verified primary name
+/// - `Ok(None)`: If there is no reverse record or it does not resolve back
+/// - `Err(EnsError::EnsUnsupported)`: If the EVM is not on mainnet
+pub fn reverse<DB, INSP>(
+    evm: &mut TraceEvm<DB, INSP>,
+    address: Address,
+) -> Result<Option<String>, EvmError>
+where
+    DB: Database,
+{
+    ensure_mainnet(evm)?;
+    let node = namehash(&format!("{}.addr.reverse", hex::encode(address)));
+    let Some(resolver) = resolver_of(evm, node)? else {
+        return Ok(None);
+    };
+    let Some(name) = call(evm, resolver, nameCall { node }.abi_encode().into())?
+        .and_then(|output| nameCall::abi_decode_returns(&output).ok())
+        .filter(|name| !name.is_empty())
+    else {
+        return Ok(None);
+    };
+    match resolve(evm, &name) {
+        Ok(resolved) if resolved == address => Ok(Some(name)),
+        Ok(_) | Err(EvmError::Ens(EnsError::NotResolved(_) | EnsError::InvalidName(_))) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Attaches verified primary names of the report's parties as labels
+///
+/// Parties are the sender, the target and both sides of every asset transfer.
+/// Addresses without a verified name are left unlabeled.
+///
+/// # Example
+/// ```no_run
+/// use revm_trace::utils::ens_utils::label_report;
+/// # use revm_trace::{report::SimulationReport, TraceEvm};
+/// # fn example<DB: revm::Database, INSP>(evm: &mut TraceEvm<DB, INSP>, report: SimulationReport) {
+/// let report = label_report(evm, report).unwrap();
+/// for (address, name) in &report.labels {
+///     println!("{address} is {name}");
+/// }
+/// # }
+/// ```
+pub fn label_report<DB, INSP>(
+    evm: &mut TraceEvm<DB, INSP>,
+    report: SimulationReport,
+) -> Result<SimulationReport, EvmError>
+where
+    DB: Database,
+{
+    let mut parties = BTreeSet::from([report.tx.caller]);
+    parties.extend(report.tx.transact_to.to().copied());
+    for transfer in &report.trace.asset_transfers {
+        parties.insert(transfer.from);
+        parties.extend(transfer.to);
+    }
+    let mut labels = HashMap::new();
+    for party in parties {
+        if let Some(name) = reverse(evm, party)? {
+            labels.insert(party, name);
+        }
+    }
+    Ok(report.with_labels(labels))
+}
+
+fn ensure_mainnet<DB, INSP>(evm: &TraceEvm<DB, INSP>) -> Result<(), EnsError>
+where
+    DB: Database,
+{
+    match evm.cfg.chain_id {
+        MAINNET_CHAIN_ID => Ok(()),
+        chain_id => Err(EnsError::EnsUnsupported { chain_id }),
+    }
+}
+
+/// Lowercases `name` and rejects empty labels
+fn normalize(name: &str) -> Result<String, EnsError> {
+    let name = name.trim().to_ascii_lowercase();
+    if name.is_empty() || name.split('.').any(str::is_empty) {
+        return Err(EnsError::InvalidName(name));
+    }
+    Ok(name)
+}
+
+/// Resolver registered for `node`, if any
+fn resolver_of<DB, INSP>(
+    evm: &mut TraceEvm<DB, INSP>,
+    node: B256,
+) -> Result<Option<Address>, EnsError>
+where
+    DB: Database,
+{
+    Ok(
+        call(evm, ENS_REGISTRY, resolverCall { node }.abi_encode().into())?
+            .and_then(|output| resolverCall::abi_decode_returns(&output).ok())
+            .filter(|resolver| !resolver.is_zero()),
+    )
+}
+
+/// Executes a read-only call, returning its output if it succeeded
+fn call<DB, INSP>(
+    evm: &mut TraceEvm<DB, INSP>,
+    to: Address,
+    data: Bytes,
+) -> Result<Option<Bytes>, EnsError>
+where
+    DB: Database,
+{
+    let tx = TxEnv {
+        caller: Address::ZERO,
+        kind: TxKind::Call(to),
+        data,
+        chain_id: Some(evm.cfg.chain_id),
+        nonce: 0,
+        ..Default::default()
+    };
+    let ref_tx = evm
+        .transact(tx)
+        .map_err(|e| EnsError::Call(format!("{to}: {e}")))?;
+    Ok(match ref_tx.result {
+        ExecutionResult::Success {
+            output: Output::Call(output),
+            ..
+        } => Some(output),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_namehash() {
+        assert_eq!(namehash(""), B256::ZERO);
+        assert_eq!(
+            namehash("foo.eth").to_string(),
+            "0xde9b09fd7c5f901e23a3f19fecc54828e9c848539801e86591bd9801b019f84f"
+        );
+        assert_eq!(
+            namehash("addr.reverse").to_string(),
+            "0x91d1777781884d03a6757a803996e38de2a42967fb37eeaca72729271025a9e2"
+        );
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(" Vitalik.ETH ").unwrap(), "vitalik.eth");
+        assert!(normalize("").is_err());
+        assert!(normalize("vitalik..eth").is_err());
+    }
+}
@@ -2,14 +2,24 @@
 //!
 //! Provides functions to interact with ERC20 tokens including balance queries,
 //! token metadata retrieval, and transfer event parsing.
+//!
+//! Token metadata is queried through the EVM with [`get_token_infos`], one call after
+//! another. [`prefetch_token_infos`] issues the same calls concurrently over RPC to warm
+//! a cache for [`get_token_infos_cached`] before or while simulating.
+
+use std::collections::{HashMap, HashSet};
 
 use crate::{
     errors::{EvmError, TokenError},
     evm::TraceEvm,
-    types::{TokenInfo, ERC20_TRANSFER_EVENT_SIGNATURE},
+    types::{AnyNetworkProvider, TokenInfo, ERC20_TRANSFER_EVENT_SIGNATURE},
 };
 use alloy::{
+    eips::BlockId,
     primitives::{Address, Bytes, FixedBytes, TxKind, U256},
+    providers::Provider,
+    rpc::types::TransactionRequest,
+    serde::WithOtherFields,
     sol,
     sol_types::SolCall,
 };
@@ -20,6 +30,7 @@ use revm::{
     database::Database,
     ExecuteEvm,
 };
+use tokio::task::JoinSet;
 
 // ERC20 interface for common token functions
 //
@@ -254,6 +265,184 @@ where
     Ok(token_infos)
 }
 
+/// Query token information, serving tokens already in `cache` from it
+///
+/// Tokens missing from the cache are queried through the EVM like in
+/// [`get_token_infos`] and added to it, so a cache warmed with
+/// [`prefetch_token_infos`] only falls back to the EVM for tokens the prefetch missed.
+///
+/// # Returns
+/// - `Ok(Vec<TokenInfo>)`: Array of token information in the same order as input
+/// - `Err(EvmError)`: If a contract call for an uncached token fails
+pub fn get_token_infos_cached<DB, INSP>(
+    evm: &mut TraceEvm<DB, INSP>,
+    tokens: &[Address],
+    cache: &mut HashMap<Address, TokenInfo>,
+) -> Result<Vec<TokenInfo>, EvmError>
+where
+    DB: Database,
+{
+    let mut token_infos = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        let token_info = match cache.get(token) {
+            Some(token_info) => token_info.clone(),
+            None => {
+                #[cfg(feature = "tracing-logs")]
+                tracing::debug!(%token, "Token info not cached, querying through the EVM");
+                let token_info = get_token_infos(evm, &[*token])?.remove(0);
+                cache.insert(*token, token_info.clone());
+                token_info
+            }
+        };
+        token_infos.push(token_info);
+    }
+    Ok(token_infos)
+}
+
+/// Fetch token information for multiple tokens concurrently over RPC
+///
+/// Issues the name(), symbol(), decimals() and totalSupply() calls of all tokens at
+/// once as `eth_call`s through the async provider instead of executing them in the
+/// EVM, so a batch takes about as long as a single token. Duplicate tokens are
+/// queried once.
+///
+/// # Arguments
+/// - `provider`: Provider to issue the calls through
+/// - `block`: Block to query, usually the one the EVM is forked at
+/// - `tokens`: Token contract addresses
+///
+/// # Returns
+/// Token information by address. Tokens whose calls fail or return undecodable data
+/// are left out, and can still be queried through the EVM with
+/// [`get_token_infos_cached`].
+pub async fn prefetch_token_infos(
+    provider: &AnyNetworkProvider,
+    block: BlockId,
+    tokens: &[Address],
+) -> HashMap<Address, TokenInfo> {
+    let mut tasks = JoinSet::new();
+    for token in tokens.iter().copied().collect::<HashSet<_>>() {
+        let provider = provider.clone();
+        tasks.spawn(async move { (token, fetch_token_info(&provider, block, token).await) });
+    }
+    let mut token_infos = HashMap::with_capacity(tasks.len());
+    while let Some(joined) = tasks.join_next().await {
+        let Ok((token, result)) = joined else {
+            continue;
+        };
+        #[cfg(feature = "tracing-logs")]
+        {
+            if let Err(error) = &result {
+                tracing::warn!(%token, %error, "Failed to prefetch token info");
+            }
+        }
+        if let Ok(token_info) = result {
+            token_infos.insert(token, token_info);
+        }
+    }
+    token_infos
+}
+
+/// Fetches the information of one token with four concurrent `eth_call`s
+async fn fetch_token_info(
+    provider: &AnyNetworkProvider,
+    block: BlockId,
+    token_address: Address,
+) -> Result<TokenInfo, TokenError> {
+    let call = |data: Vec<u8>| {
+        let request = TransactionRequest::default()
+            .to(token_address)
+            .input(data.into());
+        async move {
+            provider
+                .call(WithOtherFields::new(request))
+                .block(block)
+                .await
+                .map_err(|e| {
+                    TokenError::AnyhowError(format!("Failed to call {token_address}: {e}"))
+                })
+        }
+    };
+    let (name, symbol, decimals, total_supply) = tokio::try_join!(
+        call(nameCall {}.abi_encode()),
+        call(symbolCall {}.abi_encode()),
+        call(decimalsCall {}.abi_encode()),
+        call(totalSupplyCall {}.abi_encode()),
+    )?;
+
+    Ok(TokenInfo {
+        name: nameCall::abi_decode_returns(&name).map_err(|_| TokenError::NameDecode {
+            address: token_address.to_string(),
+            reason: "Failed to decode name".to_string(),
+        })?,
+        symbol: symbolCall::abi_decode_returns(&symbol).map_err(|_| TokenError::SymbolDecode {
+            address: token_address.to_string(),
+            reason: "Failed to decode symbol".to_string(),
+        })?,
+        decimals: decimalsCall::abi_decode_returns(&decimals).map_err(|_| {
+            TokenError::DecimalsDecode {
+                address: token_address.to_string(),
+                reason: "Failed to decode decimals".to_string(),
+            }
+        })?,
+        total_supply: totalSupplyCall::abi_decode_returns(&total_supply).map_err(|_| {
+            TokenError::TotalSupplyDecode {
+                address: token_address.to_string(),
+                reason: "Failed to decode total supply".to_string(),
+            }
+        })?,
+    })
+}
+
+/// Fetch token information for multiple tokens through a shared backend
+///
+/// The SharedBackend counterpart of [`prefetch_token_infos`]: the tokens are split
+/// across up to `workers` blocking tasks, each querying its share through its own
+/// ephemeral EVM over `backend`. Workers wait on RPC in parallel while sharing the
+/// backend's cache.
+///
+/// # Returns
+/// Token information by address, leaving out tokens whose calls fail
+#[cfg(feature = "foundry-fork")]
+pub async fn prefetch_token_infos_shared(
+    backend: &foundry_fork_db::SharedBackend,
+    tokens: &[Address],
+    workers: usize,
+) -> HashMap<Address, TokenInfo> {
+    use revm::{database::CacheDB, inspector::NoOpInspector, Context, MainBuilder, MainContext};
+
+    let tokens: Vec<Address> = tokens
+        .iter()
+        .copied()
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let chunk_size = tokens.len().div_ceil(workers.max(1)).max(1);
+    let mut tasks = JoinSet::new();
+    for chunk in tokens.chunks(chunk_size) {
+        let backend = backend.clone();
+        let chunk = chunk.to_vec();
+        tasks.spawn_blocking(move || {
+            let ctx = Context::mainnet().with_db(CacheDB::new(backend));
+            let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(NoOpInspector));
+            chunk
+                .into_iter()
+                .filter_map(|token| {
+                    let token_info = get_token_infos(&mut evm, &[token]).ok()?.remove(0);
+                    Some((token, token_info))
+                })
+                .collect::<Vec<_>>()
+        });
+    }
+    let mut token_infos = HashMap::with_capacity(tokens.len());
+    while let Some(joined) = tasks.join_next().await {
+        if let Ok(chunk) = joined {
+            token_infos.extend(chunk);
+        }
+    }
+    token_infos
+}
+
 /// Parses ERC20 Transfer event data
 ///
 /// # Arguments
@@ -266,11 +455,15 @@ where
 /// # Returns
 /// * `Some((from, to, amount))` if valid Transfer event
 /// * `None` if invalid format or zero amount
+///
+/// Only the standard layout is accepted; see
+/// [`TokenTransfer::try_get_token_transfers`](crate::types::TokenTransfer::try_get_token_transfers)
+/// for legacy layouts.
 pub fn parse_transfer_log(
     topics: &[FixedBytes<32>],
     data: &[u8],
 ) -> Option<(Address, Address, U256)> {
-    if topics.len() < 3 || topics[0] != ERC20_TRANSFER_EVENT_SIGNATURE {
+    if topics.len() != 3 || data.len() != 32 || topics[0] != ERC20_TRANSFER_EVENT_SIGNATURE {
         return None;
     }
     let amount = U256::from_be_slice(data);
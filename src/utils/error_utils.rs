@@ -11,7 +11,19 @@
 //! - Arithmetic operations
 //! - Array bounds checks
 
-use alloy::dyn_abi::{DynSolType, DynSolValue};
+use crate::types::DecodedRevert;
+use alloy::primitives::U256;
+
+/// Longest `Error(string)` reason that is decoded, in bytes
+///
+/// Revert data is controlled by the reverting contract; longer reasons are rejected
+/// instead of copied.
+pub const MAX_REVERT_REASON_LEN: usize = 1024;
+
+/// Selector of `Error(string)`
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+/// Selector of `Panic(uint256)`
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
 
 /// Parse custom error output from a failed transaction
 ///
@@ -19,12 +31,18 @@ use alloy::dyn_abi::{DynSolType, DynSolValue};
 /// 1. Error(string) - Standard revert with message (selector: 0x08c379a0)
 /// 2. Panic(uint256) - Solidity panic with error code (selector: 0x4e487b71)
 ///
+/// The output is untrusted: offsets and lengths are checked against the buffer,
+/// reasons longer than [`MAX_REVERT_REASON_LEN`] are rejected and malformed data never
+/// panics. The string offset may point anywhere in the payload, not only right after
+/// the head; invalid UTF-8 is replaced lossily.
+///
 /// # Arguments
 /// * `output` - Raw error output bytes from the failed transaction
 ///
 /// # Returns
 /// * `Some(String)` - Decoded error message or panic reason
-/// * `None` - If the error format is not recognized or cannot be decoded
+/// * `None` - If the output is empty, the error format is not recognized or cannot be
+///   decoded
 ///
 /// # Example
 /// ```no_run
@@ -35,42 +53,75 @@ use alloy::dyn_abi::{DynSolType, DynSolValue};
 /// assert_eq!(error_message, Some("Insufficient balance".to_string()));
 /// ```
 pub fn parse_custom_error(output: &[u8]) -> Option<String> {
-    if output.len() < 4 {
-        return None;
-    }
+    decode_revert(output).map(|revert| revert.to_string())
+}
 
-    let selector = &output[0..4];
-    match selector {
-        // Error(string) - 0x08c379a0
-        [0x08, 0xc3, 0x79, 0xa0] => {
-            if let Ok(DynSolValue::String(reason)) = DynSolType::String.abi_decode(&output[4..]) {
-                Some(reason)
-            } else {
-                None
-            }
-        }
-        // Panic(uint256) - 0x4e487b71
-        [0x4e, 0x48, 0x7b, 0x71] => {
-            if let Ok(DynSolValue::Uint(code, _)) = DynSolType::Uint(256).abi_decode(&output[4..]) {
-                return Some(match code.to::<u64>() {
-                    0x01 => "Panic: Assertion failed".to_string(),
-                    0x11 => "Panic: Arithmetic overflow".to_string(),
-                    0x12 => "Panic: Division by zero".to_string(),
-                    0x21 => "Panic: Invalid array access".to_string(),
-                    0x22 => "Panic: Array access out of bounds".to_string(),
-                    0x31 => "Panic: Invalid enum value".to_string(),
-                    0x32 => "Panic: Invalid storage access".to_string(),
-                    0x41 => "Panic: Zero initialization".to_string(),
-                    0x51 => "Panic: Invalid calldata access".to_string(),
-                    code => format!("Panic: Unknown error code (0x{code:x})"),
-                });
-            }
-            None
+/// Decodes `Error(string)` or `Panic(uint256)` revert data, keeping its structure
+///
+/// Accepts the same data as [`parse_custom_error`], which returns the
+/// [`Display`](std::fmt::Display) form of the result.
+///
+/// # Example
+/// ```
+/// use revm_trace::{types::DecodedRevert, utils::error_utils::decode_revert};
+/// use alloy::primitives::{hex, U256};
+///
+/// let output = hex::decode(
+///     "4e487b710000000000000000000000000000000000000000000000000000000000000011",
+/// )
+/// .unwrap();
+/// assert_eq!(decode_revert(&output), Some(DecodedRevert::Panic(U256::from(0x11))));
+/// ```
+pub fn decode_revert(output: &[u8]) -> Option<DecodedRevert> {
+    let (selector, data) = output.split_first_chunk::<4>()?;
+    match *selector {
+        ERROR_SELECTOR => decode_error_string(data).map(DecodedRevert::Error),
+        PANIC_SELECTOR => {
+            let code = U256::from_be_bytes(*data.first_chunk::<32>()?);
+            Some(DecodedRevert::Panic(code))
         }
         _ => None,
     }
 }
 
+/// Describes a Solidity panic code
+///
+/// See <https://docs.soliditylang.org/en/latest/control-structures.html#panic-via-assert-and-error-via-require>
+pub fn panic_reason(code: U256) -> String {
+    let reason = match u64::try_from(code) {
+        Ok(0x00) => "Generic compiler panic",
+        Ok(0x01) => "Assertion failed",
+        Ok(0x11) => "Arithmetic overflow",
+        Ok(0x12) => "Division by zero",
+        Ok(0x21) => "Invalid enum value",
+        Ok(0x22) => "Invalid storage byte array encoding",
+        Ok(0x31) => "Pop on empty array",
+        Ok(0x32) => "Array index out of bounds",
+        Ok(0x41) => "Out of memory",
+        Ok(0x51) => "Zero-initialized function pointer",
+        _ => return format!("Panic: Unknown error code ({code:#x})"),
+    };
+    format!("Panic: {reason}")
+}
+
+/// Decodes the ABI-encoded string argument of `Error(string)`
+fn decode_error_string(data: &[u8]) -> Option<String> {
+    let offset = read_usize(data, 0)?;
+    let len = read_usize(data, offset)?;
+    if len > MAX_REVERT_REASON_LEN {
+        return None;
+    }
+    let start = offset.checked_add(32)?;
+    let reason = data.get(start..start.checked_add(len)?)?;
+    Some(String::from_utf8_lossy(reason).into_owned())
+}
+
+/// Reads the 32-byte word at `offset` as a `usize`, if it is in bounds and fits
+fn read_usize(data: &[u8], offset: usize) -> Option<usize> {
+    let word = data.get(offset..offset.checked_add(32)?)?;
+    usize::try_from(U256::from_be_slice(word)).ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,15 +150,16 @@ mod tests {
     fn test_parse_panic() {
         // Test various panic codes
         let panic_codes = [
+            (0x00, "Panic: Generic compiler panic"),
             (0x01, "Panic: Assertion failed"),
             (0x11, "Panic: Arithmetic overflow"),
             (0x12, "Panic: Division by zero"),
-            (0x21, "Panic: Invalid array access"),
-            (0x22, "Panic: Array access out of bounds"),
-            (0x31, "Panic: Invalid enum value"),
-            (0x32, "Panic: Invalid storage access"),
-            (0x41, "Panic: Zero initialization"),
-            (0x51, "Panic: Invalid calldata access"),
+            (0x21, "Panic: Invalid enum value"),
+            (0x22, "Panic: Invalid storage byte array encoding"),
+            (0x31, "Panic: Pop on empty array"),
+            (0x32, "Panic: Array index out of bounds"),
+            (0x41, "Panic: Out of memory"),
+            (0x51, "Panic: Zero-initialized function pointer"),
             (0xFF, "Panic: Unknown error code (0xff)"),
         ];
 
@@ -144,4 +196,120 @@ mod tests {
         ];
         assert_eq!(parse_custom_error(&invalid_panic), None);
     }
+
+    /// `Error(string)` payload with the given offset word, length word and tail
+    fn error_payload(offset: U256, len: U256, tail: &[u8]) -> Vec<u8> {
+        [
+            &ERROR_SELECTOR[..],
+            &offset.to_be_bytes::<32>(),
+            &len.to_be_bytes::<32>(),
+            tail,
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_crafted_error_payloads() {
+        let reason = b"Insufficient balance";
+        let valid = error_payload(U256::from(32), U256::from(reason.len()), reason);
+        assert_eq!(
+            parse_custom_error(&valid),
+            Some("Insufficient balance".to_string())
+        );
+
+        // Huge length word
+        let huge = error_payload(U256::from(32), U256::MAX, reason);
+        assert_eq!(parse_custom_error(&huge), None);
+        // Length within usize but over the cap
+        let long = error_payload(
+            U256::from(32),
+            U256::from(MAX_REVERT_REASON_LEN + 1),
+            &[b'a'; MAX_REVERT_REASON_LEN + 1],
+        );
+        assert_eq!(parse_custom_error(&long), None);
+        // Truncated string
+        let truncated = error_payload(U256::from(32), U256::from(reason.len() + 1), reason);
+        assert_eq!(parse_custom_error(&truncated), None);
+        // Offset out of bounds, and at the edge of usize
+        for offset in [U256::from(4096), U256::from(usize::MAX), U256::MAX] {
+            let payload = error_payload(offset, U256::from(reason.len()), reason);
+            assert_eq!(parse_custom_error(&payload), None);
+        }
+
+        // Non-standard offset: the string starts after a padding word
+        let padded = error_payload(
+            U256::from(64),
+            U256::ZERO,
+            &[&U256::from(reason.len()).to_be_bytes::<32>()[..], reason].concat(),
+        );
+        assert_eq!(
+            parse_custom_error(&padded),
+            Some("Insufficient balance".to_string())
+        );
+        // Empty reason and invalid UTF-8
+        let empty = error_payload(U256::from(32), U256::ZERO, &[]);
+        assert_eq!(parse_custom_error(&empty), Some(String::new()));
+        let invalid = error_payload(U256::from(32), U256::from(2), &[0xff, b'a']);
+        assert_eq!(parse_custom_error(&invalid), Some("\u{fffd}a".to_string()));
+    }
+
+    #[test]
+    fn test_crafted_panic_payloads() {
+        let panic = |code: U256| [&PANIC_SELECTOR[..], &code.to_be_bytes::<32>()].concat();
+        assert_eq!(
+            parse_custom_error(&panic(U256::MAX)),
+            Some(format!("Panic: Unknown error code ({:#x})", U256::MAX))
+        );
+        assert_eq!(
+            parse_custom_error(&panic(U256::from(1) << 64)),
+            Some("Panic: Unknown error code (0x10000000000000000)".to_string())
+        );
+        // Trailing data is ignored, a truncated code is rejected
+        let mut trailing = panic(U256::from(0x11));
+        trailing.extend_from_slice(&[0xab; 7]);
+        assert_eq!(
+            parse_custom_error(&trailing),
+            Some("Panic: Arithmetic overflow".to_string())
+        );
+        assert_eq!(parse_custom_error(&panic(U256::from(0x11))[..35]), None);
+    }
+
+    #[test]
+    fn test_random_payloads_never_panic() {
+        // xorshift64, so failures are reproducible
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let words = [
+            U256::ZERO,
+            U256::from(32),
+            U256::from(64),
+            U256::from(u32::MAX),
+            U256::from(usize::MAX),
+            U256::MAX,
+        ];
+        for _ in 0..2_000 {
+            let selector = match next() % 3 {
+                0 => ERROR_SELECTOR,
+                1 => PANIC_SELECTOR,
+                _ => (next() as u32).to_be_bytes(),
+            };
+            let mut payload = selector.to_vec();
+            // Mix well-known words with random bytes
+            for _ in 0..next() % 5 {
+                if next() % 2 == 0 {
+                    let word = words[(next() % words.len() as u64) as usize];
+                    payload.extend_from_slice(&word.to_be_bytes::<32>());
+                } else {
+                    payload.extend((0..next() % 40).map(|_| next() as u8));
+                }
+            }
+            payload.truncate((next() % (payload.len() as u64 + 1)) as usize);
+            let _ = parse_custom_error(&payload);
+        }
+    }
 }
@@ -0,0 +1,392 @@
+//! Interface detection of counterparties
+//!
+//! Classifies the contracts appearing in traces, e.g. whether an address is an NFT, a
+//! Safe or an ERC-1271 signer. [`detect_interfaces`] runs a set of [`InterfaceProbe`]s
+//! against one address; [`detect_interfaces_batch`] runs them against many addresses in
+//! a single Multicall batch.
+//!
+//! ERC-165 probes follow the detection procedure of the standard: the contract must
+//! answer `true` for the ERC-165 interface itself and `false` for `0xffffffff` before
+//! its answer for the probed interface is trusted, which yields
+//! [`Confidence::Erc165Confirmed`]. The other probes only check that a characteristic
+//! function answers sensibly and yield [`Confidence::HeuristicOnly`].
+//!
+//! Probes are read-only calls from the zero address against the current simulated
+//! state and are never committed, so contracts deployed earlier in a stateful batch are
+//! detected as well.
+
+use alloy::{
+    primitives::{address, fixed_bytes, Address, Bytes, FixedBytes, TxKind, B256},
+    sol,
+    sol_types::SolCall,
+};
+use revm::{
+    context::TxEnv,
+    context_interface::result::{ExecutionResult, Output},
+    database::{CacheDB, Database, DatabaseRef},
+    ExecuteEvm,
+};
+use serde::Serialize;
+
+use crate::{
+    errors::{EvmError, RuntimeError},
+    evm::TraceEvm,
+    utils::multicall_utils::{MulticallCall, MulticallManager, MulticallResult},
+};
+
+sol! {
+    function supportsInterface(bytes4 interfaceId) external view returns (bool);
+    function balanceOf(address owner) external view returns (uint256);
+    function getOwners() external view returns (address[] memory);
+    function isValidSignature(bytes32 hash, bytes signature) external view returns (bytes4);
+}
+
+/// ERC-165 interface ID of ERC-165 itself
+pub const ERC165_INTERFACE_ID: FixedBytes<4> = fixed_bytes!("01ffc9a7");
+
+/// ERC-165 interface ID of ERC-721
+pub const ERC721_INTERFACE_ID: FixedBytes<4> = fixed_bytes!("80ac58cd");
+
+/// ERC-165 interface ID of ERC-1155
+pub const ERC1155_INTERFACE_ID: FixedBytes<4> = fixed_bytes!("d9b67a26");
+
+/// ERC-165 interface ID of ERC-2981 (NFT royalties)
+pub const ERC2981_INTERFACE_ID: FixedBytes<4> = fixed_bytes!("2a55205a");
+
+/// Interface ID every ERC-165 contract must reject
+const INVALID_INTERFACE_ID: FixedBytes<4> = fixed_bytes!("ffffffff");
+
+/// Owner passed to `balanceOf`; OpenZeppelin's ERC-721 reverts for the zero address
+const PROBE_OWNER: Address = address!("000000000000000000000000000000000000dEaD");
+
+/// Check run against an address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum InterfaceProbe {
+    /// `supportsInterface(id)` answers `true` on an ERC-165 compliant contract
+    Erc165(FixedBytes<4>),
+    /// `balanceOf(address)` returns a word: an ERC-20 or an ERC-721 without ERC-165
+    BalanceOf,
+    /// `getOwners()` returns a non-empty address list: a Safe
+    SafeOwners,
+    /// `isValidSignature(bytes32,bytes)` returns a word or reverts with a reason for an
+    /// unsigned hash: an ERC-1271 signer
+    Erc1271,
+}
+
+/// ERC-721, ERC-1155 and ERC-2981 through ERC-165, plus every heuristic probe
+pub const COMMON_PROBES: [InterfaceProbe; 6] = [
+    InterfaceProbe::Erc165(ERC721_INTERFACE_ID),
+    InterfaceProbe::Erc165(ERC1155_INTERFACE_ID),
+    InterfaceProbe::Erc165(ERC2981_INTERFACE_ID),
+    InterfaceProbe::BalanceOf,
+    InterfaceProbe::SafeOwners,
+    InterfaceProbe::Erc1271,
+];
+
+impl InterfaceProbe {
+    /// Calldata of the probe's call
+    fn call_data(&self) -> Bytes {
+        match *self {
+            Self::Erc165(interface_id) => supportsInterfaceCall {
+                interfaceId: interface_id,
+            }
+            .abi_encode(),
+            Self::BalanceOf => balanceOfCall { owner: PROBE_OWNER }.abi_encode(),
+            Self::SafeOwners => getOwnersCall {}.abi_encode(),
+            Self::Erc1271 => isValidSignatureCall {
+                hash: B256::ZERO,
+                signature: Bytes::new(),
+            }
+            .abi_encode(),
+        }
+        .into()
+    }
+
+    /// Confidence of a positive answer to the probe
+    pub fn confidence(&self) -> Confidence {
+        match self {
+            Self::Erc165(_) => Confidence::Erc165Confirmed,
+            _ => Confidence::HeuristicOnly,
+        }
+    }
+}
+
+/// How a detection was established
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum Confidence {
+    /// Declared by an ERC-165 compliant contract
+    Erc165Confirmed,
+    /// Inferred from the answer of a characteristic function
+    HeuristicOnly,
+}
+
+/// Probe that matched an address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct InterfaceDetection {
+    /// Matching probe
+    pub probe: InterfaceProbe,
+    /// How the match was established
+    pub confidence: Confidence,
+}
+
+/// Runs `candidates` against `address`
+///
+/// Accounts without code match no probe.
+///
+/// # Returns
+/// * `Ok(detections)` - The matching probes, in `candidates` order
+/// * `Err(_)` - If a probe could not be executed
+///
+/// # Example
+/// ```no_run
+/// use revm_trace::{
+///     create_evm,
+///     utils::interface_utils::{detect_interfaces, InterfaceProbe, COMMON_PROBES},
+/// };
+/// use alloy::primitives::address;
+///
+/// # async fn example() -> anyhow::Result<()> {
+/// let mut evm = create_evm("https://eth.llamarpc.com").await?;
+/// let bayc = address!("BC4CA0EdA7647A8aB7C2061c2E118A18a936f13D");
+/// for detection in detect_interfaces(&mut evm, bayc, &COMMON_PROBES)? {
+///     println!("{:?} ({:?})", detection.probe, detection.confidence);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn detect_interfaces<DB, INSP>(
+    evm: &mut TraceEvm<DB, INSP>,
+    address: Address,
+    candidates: &[InterfaceProbe],
+) -> Result<Vec<InterfaceDetection>, EvmError>
+where
+    DB: Database,
+{
+    let results = probe_calls(candidates)
+        .into_iter()
+        .map(|data| probe_call(evm, address, data))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(evaluate(candidates, &results))
+}
+
+/// Runs `candidates` against every address of `addresses` in one Multicall batch
+///
+/// # Returns
+/// * `Ok(detections)` - The matching probes of each address, in input order
+/// * `Err(_)` - If the Multicall batch itself could not be executed
+pub fn detect_interfaces_batch<DB, INSP>(
+    evm: &mut TraceEvm<CacheDB<DB>, INSP>,
+    addresses: &[Address],
+    candidates: &[InterfaceProbe],
+) -> Result<Vec<Vec<InterfaceDetection>>, EvmError>
+where
+    DB: DatabaseRef,
+{
+    let call_data = probe_calls(candidates);
+    let calls: Vec<MulticallCall> = addresses
+        .iter()
+        .flat_map(|&target| {
+            call_data.iter().map(move |data| MulticallCall {
+                target,
+                callData: data.clone(),
+            })
+        })
+        .collect();
+    let results = MulticallManager::new().batch_call_on_current_state(evm, calls, false)?;
+
+    Ok(results
+        .chunks(call_data.len().max(1))
+        .map(|results| evaluate(candidates, results))
+        // Without candidates no calls are made at all
+        .chain(std::iter::repeat_with(Vec::new))
+        .take(addresses.len())
+        .collect())
+}
+
+/// Calldata of the calls probing `candidates`, the ERC-165 compliance checks first
+/// when any candidate needs them
+fn probe_calls(candidates: &[InterfaceProbe]) -> Vec<Bytes> {
+    let mut calls = Vec::new();
+    if candidates.iter().any(needs_erc165) {
+        calls.push(InterfaceProbe::Erc165(ERC165_INTERFACE_ID).call_data());
+        calls.push(InterfaceProbe::Erc165(INVALID_INTERFACE_ID).call_data());
+    }
+    calls.extend(candidates.iter().map(InterfaceProbe::call_data));
+    calls
+}
+
+/// Matches `candidates` against the results of the calls of [`probe_calls`]
+fn evaluate(candidates: &[InterfaceProbe], results: &[MulticallResult]) -> Vec<InterfaceDetection> {
+    let mut results = results.iter();
+    let erc165 = candidates.iter().any(needs_erc165)
+        && returns_bool(results.next()) == Some(true)
+        && returns_bool(results.next()) == Some(false);
+
+    candidates
+        .iter()
+        .zip(results)
+        .filter(|(probe, result)| match probe {
+            InterfaceProbe::Erc165(_) => erc165 && returns_bool(Some(*result)) == Some(true),
+            InterfaceProbe::BalanceOf => {
+                result.success && balanceOfCall::abi_decode_returns(&result.returnData).is_ok()
+            }
+            InterfaceProbe::SafeOwners => {
+                result.success
+                    && getOwnersCall::abi_decode_returns(&result.returnData)
+                        .is_ok_and(|owners| !owners.is_empty())
+            }
+            // Signers reject the unsigned hash, but missing functions revert without data
+            InterfaceProbe::Erc1271 if result.success => {
+                isValidSignatureCall::abi_decode_returns(&result.returnData).is_ok()
+            }
+            InterfaceProbe::Erc1271 => !result.returnData.is_empty(),
+        })
+        .map(|(probe, _)| InterfaceDetection {
+            probe: *probe,
+            confidence: probe.confidence(),
+        })
+        .collect()
+}
+
+fn needs_erc165(probe: &InterfaceProbe) -> bool {
+    matches!(probe, InterfaceProbe::Erc165(_))
+}
+
+/// Decoded answer of a successful `supportsInterface` call
+fn returns_bool(result: Option<&MulticallResult>) -> Option<bool> {
+    let result = result.filter(|result| result.success)?;
+    supportsInterfaceCall::abi_decode_returns(&result.returnData).ok()
+}
+
+/// Executes one probe, in the shape of a Multicall result
+fn probe_call<DB, INSP>(
+    evm: &mut TraceEvm<DB, INSP>,
+    address: Address,
+    data: Bytes,
+) -> Result<MulticallResult, EvmError>
+where
+    DB: Database,
+{
+    // Use zero address as caller for read-only calls (no nonce needed)
+    let tx = TxEnv {
+        caller: Address::ZERO,
+        kind: TxKind::Call(address),
+        data,
+        chain_id: Some(evm.cfg.chain_id),
+        nonce: 0,
+        ..Default::default()
+    };
+    let result = evm.transact(tx).map_err(|e| {
+        RuntimeError::ExecutionFailed(format!("Failed to probe interfaces of {address}: {e}"))
+    })?;
+    Ok(match result.result {
+        ExecutionResult::Success {
+            output: Output::Call(output),
+            ..
+        } => MulticallResult {
+            success: true,
+            returnData: output,
+        },
+        ExecutionResult::Revert { output, .. } => MulticallResult {
+            success: false,
+            returnData: output,
+        },
+        _ => MulticallResult {
+            success: false,
+            returnData: Bytes::new(),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{primitives::U256, sol_types::SolValue};
+
+    fn ok(data: impl Into<Bytes>) -> MulticallResult {
+        MulticallResult {
+            success: true,
+            returnData: data.into(),
+        }
+    }
+
+    fn reverted(data: impl Into<Bytes>) -> MulticallResult {
+        MulticallResult {
+            success: false,
+            returnData: data.into(),
+        }
+    }
+
+    fn probes(detections: Vec<InterfaceDetection>) -> Vec<InterfaceProbe> {
+        detections.into_iter().map(|d| d.probe).collect()
+    }
+
+    #[test]
+    fn test_erc165_requires_compliance() {
+        let candidates = [InterfaceProbe::Erc165(ERC721_INTERFACE_ID)];
+        let compliant = [
+            ok(true.abi_encode()),
+            ok(false.abi_encode()),
+            ok(true.abi_encode()),
+        ];
+        let detections = evaluate(&candidates, &compliant);
+        assert_eq!(
+            detections,
+            vec![InterfaceDetection {
+                probe: candidates[0],
+                confidence: Confidence::Erc165Confirmed,
+            }]
+        );
+
+        // Answering `true` to everything is not compliant
+        let permissive = [
+            ok(true.abi_encode()),
+            ok(true.abi_encode()),
+            ok(true.abi_encode()),
+        ];
+        assert!(evaluate(&candidates, &permissive).is_empty());
+    }
+
+    #[test]
+    fn test_heuristic_probes() {
+        let candidates = [
+            InterfaceProbe::BalanceOf,
+            InterfaceProbe::SafeOwners,
+            InterfaceProbe::Erc1271,
+        ];
+        assert_eq!(probe_calls(&candidates).len(), 3);
+
+        let safe = [
+            reverted(Bytes::new()),
+            ok(vec![PROBE_OWNER].abi_encode()),
+            reverted(Bytes::from_static(b"GS026")),
+        ];
+        let detections = evaluate(&candidates, &safe);
+        assert_eq!(
+            probes(detections.clone()),
+            vec![InterfaceProbe::SafeOwners, InterfaceProbe::Erc1271]
+        );
+        assert!(detections
+            .iter()
+            .all(|d| d.confidence == Confidence::HeuristicOnly));
+
+        let token = [
+            ok(U256::from(7).abi_encode()),
+            reverted(Bytes::new()),
+            reverted(Bytes::new()),
+        ];
+        assert_eq!(
+            probes(evaluate(&candidates, &token)),
+            vec![InterfaceProbe::BalanceOf]
+        );
+    }
+
+    #[test]
+    fn test_account_without_code_matches_nothing() {
+        let results: Vec<_> = probe_calls(&COMMON_PROBES)
+            .iter()
+            .map(|_| ok(Bytes::new()))
+            .collect();
+        assert!(evaluate(&COMMON_PROBES, &results).is_empty());
+    }
+}
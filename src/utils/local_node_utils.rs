@@ -0,0 +1,347 @@
+//! Replaying simulations on local development nodes
+//!
+//! This module provides utilities for:
+//! - Detecting Anvil and Hardhat nodes from `web3_clientVersion`
+//! - Sending a simulated batch to the node with impersonated senders, so the node's
+//!   state converges with the simulated one
+//! - Comparing the mined receipts with the simulated results
+//!
+//! Use together with [`EvmBuilder::new_local`](crate::EvmBuilder::new_local) and
+//! [`TraceEvm::refresh_to_latest`](crate::TraceEvm::refresh_to_latest):
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! use revm_trace::{
+//!     evm::builder::get_provider, traits::TransactionTrace,
+//!     utils::local_node_utils::LocalNodeBridge, EvmBuilder, SimulationBatch, TxInspector,
+//! };
+//!
+//! let url = "http://127.0.0.1:8545";
+//! let mut evm = EvmBuilder::new_local(url)
+//!     .with_tracer(TxInspector::new())
+//!     .build()
+//!     .await?;
+//! let batch = SimulationBatch {
+//!     transactions: vec![Default::default()],
+//!     is_stateful: true,
+//!     ..Default::default()
+//! };
+//! let simulated = evm
+//!     .trace_transactions(batch.clone())
+//!     .into_iter()
+//!     .map(|result| result.map(|(result, _, _)| result))
+//!     .collect::<Result<Vec<_>, _>>()?;
+//!
+//! let provider = get_provider(url).await?;
+//! for applied in LocalNodeBridge::apply_to_node(&provider, &batch, &simulated).await? {
+//!     println!("{}: {:?}", applied.tx_hash, applied.mismatches);
+//! }
+//! evm.refresh_to_latest(&provider).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    errors::{EvmError, InitError, LocalNodeError, RuntimeError, UnresolvedTargetReason},
+    types::{SimulationBatch, SimulationTx, TargetRef},
+};
+use alloy::{
+    network::{AnyNetwork, ReceiptResponse},
+    primitives::{Address, TxKind, B256},
+    providers::Provider,
+    rpc::types::{TransactionInput, TransactionRequest},
+    serde::WithOtherFields,
+};
+use revm::context::result::ExecutionResult;
+use serde::Serialize;
+
+/// Supported development node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DevNode {
+    /// Foundry's Anvil (`anvil_*` methods)
+    Anvil,
+    /// Hardhat Network (`hardhat_*` methods)
+    Hardhat,
+}
+
+impl DevNode {
+    /// Identifies the node from its `web3_clientVersion`, e.g. `anvil/v1.0.0`
+    pub fn from_client_version(version: &str) -> Option<Self> {
+        let version = version.to_ascii_lowercase();
+        if version.starts_with("anvil") {
+            Some(Self::Anvil)
+        } else if version.starts_with("hardhat") {
+            Some(Self::Hardhat)
+        } else {
+            None
+        }
+    }
+
+    /// Queries `web3_clientVersion` and identifies the node
+    ///
+    /// # Returns
+    /// - `Ok(DevNode)`: The endpoint is a supported development node
+    /// - `Err(InitError::NotDevNode)`: The query failed or the client is not supported
+    pub async fn detect<P: Provider<AnyNetwork>>(provider: &P) -> Result<Self, InitError> {
+        let version = provider
+            .get_client_version()
+            .await
+            .map_err(|e| InitError::NotDevNode(format!("web3_clientVersion failed: {e}")))?;
+        Self::from_client_version(&version).ok_or(InitError::NotDevNode(version))
+    }
+
+    /// Prefix of the node's custom RPC methods
+    fn namespace(self) -> &'static str {
+        match self {
+            Self::Anvil => "anvil",
+            Self::Hardhat => "hardhat",
+        }
+    }
+}
+
+/// Difference between a simulated and a mined transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Mismatch {
+    /// The transaction succeeded in one and failed in the other
+    Status { simulated: bool, mined: bool },
+    /// The transaction used a different amount of gas
+    GasUsed { simulated: u64, mined: u64 },
+}
+
+/// Outcome of a transaction replayed on the node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AppliedTx {
+    /// Hash of the mined transaction
+    pub tx_hash: B256,
+    /// Whether the mined transaction succeeded
+    pub success: bool,
+    /// Gas used by the mined transaction
+    pub gas_used: u64,
+    /// Address of the contract created by the transaction, if any
+    pub contract_address: Option<Address>,
+    /// Differences from the simulated result, empty if the two converged
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl AppliedTx {
+    /// Whether the mined transaction matches the simulation
+    pub fn converged(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Replays simulated batches on a local development node
+pub struct LocalNodeBridge;
+
+impl LocalNodeBridge {
+    /// Sends the transactions of `batch` to the node, one after the other, and
+    /// compares each receipt with its simulated result
+    ///
+    /// Each sender (`caller`) is impersonated for the duration of its transaction, so
+    /// no keys are needed; the node fills in nonce, gas and fees, and the sender must
+    /// be able to pay for them. The node mines the transactions sequentially, so for a
+    /// non-stateful batch any transaction reading state written by an earlier one is
+    /// likely to be reported as a mismatch. The batch coinbase is not applied. Batch
+    /// [`targets`](SimulationBatch::targets) resolve to the contract address in the
+    /// referenced transaction's receipt.
+    ///
+    /// # Arguments
+    /// - `provider`: Provider connected to the node
+    /// - `batch`: Batch that was simulated
+    /// - `simulated`: Simulated result of each transaction of `batch`
+    ///
+    /// # Returns
+    /// - `Ok(Vec<AppliedTx>)`: One entry per transaction, in order
+    /// - `Err(EvmError::Init(InitError::NotDevNode))`: The node is not supported
+    /// - `Err(EvmError::Runtime(RuntimeError::UnresolvedTarget { .. }))`: A
+    ///   `CreatedBy` target refers to a transaction that created no contract on the node
+    /// - `Err(EvmError::LocalNode(_))`: The batch cannot be applied or an RPC call
+    ///   failed; transactions mined before the failure stay on the node
+    pub async fn apply_to_node<P: Provider<AnyNetwork>>(
+        provider: &P,
+        batch: &SimulationBatch,
+        simulated: &[ExecutionResult],
+    ) -> Result<Vec<AppliedTx>, EvmError> {
+        if batch.overrides.is_some() {
+            return Err(LocalNodeError::OverridesUnsupported.into());
+        }
+        if simulated.len() != batch.transactions.len() {
+            return Err(LocalNodeError::ResultCount {
+                expected: batch.transactions.len(),
+                actual: simulated.len(),
+            }
+            .into());
+        }
+        let node = DevNode::detect(provider).await?;
+
+        let mut applied: Vec<AppliedTx> = Vec::with_capacity(simulated.len());
+        for (index, (tx, simulated)) in batch.transactions.iter().zip(simulated).enumerate() {
+            let sender = tx.caller;
+            let to = match batch.targets.get(&index) {
+                Some(&target) => resolve_target(target, &applied, &batch.transactions)?,
+                None => tx.transact_to,
+            };
+            set_impersonation(provider, node, sender, true).await?;
+            let mined = send(provider, sender, to, tx).await;
+            set_impersonation(provider, node, sender, false).await?;
+            applied.push(compare(mined?, simulated));
+        }
+        Ok(applied)
+    }
+}
+
+/// Starts or stops impersonating `account`
+async fn set_impersonation<P: Provider<AnyNetwork>>(
+    provider: &P,
+    node: DevNode,
+    account: Address,
+    enabled: bool,
+) -> Result<(), LocalNodeError> {
+    let method = if enabled {
+        "impersonateAccount"
+    } else {
+        "stopImpersonatingAccount"
+    };
+    let method = format!("{}_{method}", node.namespace());
+    // Anvil answers with null, Hardhat with true
+    provider
+        .raw_request::<_, Option<bool>>(method.clone().into(), (account,))
+        .await
+        .map(|_| ())
+        .map_err(|e| LocalNodeError::Rpc {
+            method,
+            reason: e.to_string(),
+        })
+}
+
+/// Resolves a batch target against the transactions mined so far
+///
+/// `TargetRef::CreatedBy` refers to the contract the node reported for the
+/// referenced transaction's receipt.
+fn resolve_target(
+    target: TargetRef,
+    applied: &[AppliedTx],
+    transactions: &[SimulationTx],
+) -> Result<TxKind, RuntimeError> {
+    let TargetRef::CreatedBy { tx_index } = target;
+    let reason = match applied.get(tx_index) {
+        None => UnresolvedTargetReason::NotExecutedBefore,
+        Some(_) if !transactions[tx_index].transact_to.is_create() => {
+            UnresolvedTargetReason::NotACreation
+        }
+        Some(applied) => match applied.contract_address {
+            Some(address) if applied.success => return Ok(TxKind::Call(address)),
+            _ => UnresolvedTargetReason::Failed,
+        },
+    };
+    Err(RuntimeError::UnresolvedTarget { tx_index, reason })
+}
+
+/// Sends `tx` from `sender` to `to` and waits for it to be mined
+async fn send<P: Provider<AnyNetwork>>(
+    provider: &P,
+    sender: Address,
+    to: TxKind,
+    tx: &SimulationTx,
+) -> Result<AppliedTx, LocalNodeError> {
+    let request = TransactionRequest {
+        from: Some(sender),
+        to: match to {
+            TxKind::Call(to) => Some(TxKind::Call(to)),
+            TxKind::Create => None,
+        },
+        value: Some(tx.value),
+        input: TransactionInput::new(tx.data.clone()),
+        ..Default::default()
+    };
+    let rpc_error = |reason: String| LocalNodeError::Rpc {
+        method: "eth_sendTransaction".to_string(),
+        reason,
+    };
+    let receipt = provider
+        .send_transaction(WithOtherFields::new(request))
+        .await
+        .map_err(|e| rpc_error(e.to_string()))?
+        .get_receipt()
+        .await
+        .map_err(|e| rpc_error(e.to_string()))?;
+    Ok(AppliedTx {
+        tx_hash: receipt.transaction_hash(),
+        success: receipt.status(),
+        gas_used: receipt.gas_used(),
+        contract_address: receipt.contract_address(),
+        mismatches: Vec::new(),
+    })
+}
+
+/// Records the differences between the mined transaction and its simulation
+fn compare(mut applied: AppliedTx, simulated: &ExecutionResult) -> AppliedTx {
+    if applied.success != simulated.is_success() {
+        applied.mismatches.push(Mismatch::Status {
+            simulated: simulated.is_success(),
+            mined: applied.success,
+        });
+    }
+    if applied.gas_used != simulated.gas_used() {
+        applied.mismatches.push(Mismatch::GasUsed {
+            simulated: simulated.gas_used(),
+            mined: applied.gas_used,
+        });
+    }
+    applied
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use revm::context::result::{Output, SuccessReason};
+
+    #[test]
+    fn test_from_client_version() {
+        assert_eq!(
+            DevNode::from_client_version("anvil/v1.0.0"),
+            Some(DevNode::Anvil)
+        );
+        assert_eq!(
+            DevNode::from_client_version("HardhatNetwork/2.22.0/@ethereumjs/vm/5.9.3"),
+            Some(DevNode::Hardhat)
+        );
+        assert_eq!(
+            DevNode::from_client_version("Geth/v1.14.0-stable/linux-amd64/go1.22.2"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_compare() {
+        let simulated = ExecutionResult::Success {
+            reason: SuccessReason::Stop,
+            gas_used: 21_000,
+            gas_refunded: 0,
+            logs: vec![],
+            output: Output::Call(Default::default()),
+        };
+        let mined = |success, gas_used| AppliedTx {
+            tx_hash: B256::ZERO,
+            success,
+            gas_used,
+            contract_address: None,
+            mismatches: Vec::new(),
+        };
+
+        assert!(compare(mined(true, 21_000), &simulated).converged());
+        assert_eq!(
+            compare(mined(false, 25_000), &simulated).mismatches,
+            vec![
+                Mismatch::Status {
+                    simulated: true,
+                    mined: false
+                },
+                Mismatch::GasUsed {
+                    simulated: 21_000,
+                    mined: 25_000
+                },
+            ]
+        );
+    }
+}
@@ -17,15 +17,17 @@ use alloy::{
 use anyhow::Result;
 use revm::{
     context::TxEnv,
-    context_interface::result::ExecutionResult,
+    context_interface::{result::ExecutionResult, ContextTr},
     database::{CacheDB, Database, DatabaseCommit, DatabaseRef},
-    ExecuteCommitEvm, ExecuteEvm,
+    ExecuteCommitEvm, ExecuteEvm, InspectEvm,
 };
+use serde::Serialize;
 
 use crate::{
     errors::{EvmError, RuntimeError},
     evm::TraceEvm,
     traits::ResetDB,
+    TxInspector,
 };
 
 // Multicall3 interface - standard and widely supported
@@ -60,6 +62,17 @@ mod multicall3 {
 use multicall3::Multicall3::tryAggregateCall;
 pub use multicall3::{MulticallCall, MulticallResult};
 
+/// Result of a single call in a gas-attributed Multicall batch
+#[derive(Debug, Clone, Serialize)]
+pub struct MulticallResultWithGas {
+    /// Whether the call succeeded
+    pub success: bool,
+    /// Raw return data (or revert data) of the call
+    pub return_data: Bytes,
+    /// Gas consumed inside the target call frame
+    pub gas_used: u64,
+}
+
 /// Multicall manager for batch contract calls
 ///
 /// Manages the deployment and execution of Multicall contracts for batch operations.
@@ -133,7 +146,11 @@ impl MulticallManager {
     /// # Ok(())
     /// # }
     /// ```
-    fn deploy_multicall<DB, INSP>(&self, evm: &mut TraceEvm<DB, INSP>) -> Result<Address, EvmError>
+    fn deploy_multicall<DB, INSP>(
+        &self,
+        evm: &mut TraceEvm<DB, INSP>,
+        nonce: u64,
+    ) -> Result<Address, EvmError>
     where
         DB: Database + DatabaseCommit,
     {
@@ -142,6 +159,7 @@ impl MulticallManager {
             kind: TxKind::Create,
             data: self.multicall_bytecode.clone(),
             chain_id: Some(evm.cfg.chain_id),
+            nonce,
             ..Default::default()
         };
 
@@ -240,9 +258,198 @@ impl MulticallManager {
         // Reset database to ensure clean state for deployment
         evm.reset_db();
 
+        self.execute_batch(evm, 0, calls, require_success)
+    }
+
+    /// Execute batch calls against the current simulated state
+    ///
+    /// Unlike [`deploy_and_batch_call`](Self::deploy_and_batch_call), the database is
+    /// not reset first, so the calls observe every change made by earlier simulated
+    /// transactions (e.g. pool reserves after a simulated swap). The Multicall
+    /// deployment is rolled back afterwards, leaving the simulated state untouched.
+    ///
+    /// # Arguments
+    /// * `evm` - EVM instance whose current state the calls read
+    /// * `calls` - Vector of MulticallCall structs defining target contracts and call data
+    /// * `require_success` - Whether all calls must succeed (passed to tryAggregate)
+    ///
+    /// # Returns
+    /// * `Ok(Vec<MulticallResult>)` - Results for each call, including success status and return data
+    /// * `Err(EvmError)` - If deployment or batch execution fails
+    pub fn batch_call_on_current_state<DB, INSP>(
+        &self,
+        evm: &mut TraceEvm<CacheDB<DB>, INSP>,
+        calls: Vec<MulticallCall>,
+        require_success: bool,
+    ) -> Result<Vec<MulticallResult>, EvmError>
+    where
+        DB: DatabaseRef,
+    {
+        if calls.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // The simulation may already have used the deployer account
+        let nonce = evm
+            .db()
+            .basic(Address::ZERO)
+            .map_err(|e| {
+                RuntimeError::AccountAccess(format!("Failed to load Multicall deployer: {e}"))
+            })?
+            .map_or(0, |account| account.nonce);
+        let snapshot = evm.snapshot();
+        let results = self.execute_batch(evm, nonce, calls, require_success);
+        evm.revert_to(snapshot);
+        results
+    }
+
+    /// Deploy the Multicall contract with the zero address at `nonce` and run the batch
+    fn execute_batch<DB, INSP>(
+        &self,
+        evm: &mut TraceEvm<DB, INSP>,
+        nonce: u64,
+        calls: Vec<MulticallCall>,
+        require_success: bool,
+    ) -> Result<Vec<MulticallResult>, EvmError>
+    where
+        DB: Database + DatabaseCommit,
+    {
         // Deploy Multicall contract and get its address
-        let multicall_address = self.deploy_multicall(evm)?;
+        let multicall_address = self.deploy_multicall(evm, nonce)?;
 
+        // Build and execute the multicall transaction
+        let tx = self.build_batch_tx(evm, multicall_address, nonce + 1, calls, require_success);
+        let execution_result = evm.transact(tx).map_err(|e| {
+            RuntimeError::ExecutionFailed(format!("Multicall execution failed: {e}"))
+        })?;
+
+        Self::decode_batch_result(execution_result.result)
+    }
+
+    /// Deploy Multicall contract and execute batch calls, reporting gas used per call
+    ///
+    /// Works like [`deploy_and_batch_call`](Self::deploy_and_batch_call), but runs the
+    /// batch under a `TxInspector` so the gas consumed by every individual call can be
+    /// attributed. This is useful for sizing real multicall transactions.
+    ///
+    /// # Gas Attribution
+    /// `tryAggregate` performs exactly one `CALL` per entry in order, so every direct
+    /// subcall frame of the Multicall contract corresponds to the call at the same index.
+    /// This correlation also holds for reverting calls when `require_success` is false,
+    /// since failed calls still produce a frame.
+    ///
+    /// The reported `gas_used` is the gas spent inside the target call frame; it excludes
+    /// the Multicall contract's own loop and ABI-encoding overhead as well as the
+    /// intrinsic transaction cost.
+    ///
+    /// # Arguments
+    /// * `evm` - EVM instance with a `TxInspector` (its collected state is reset)
+    /// * `calls` - Vector of MulticallCall structs defining target contracts and call data
+    /// * `require_success` - Whether all calls must succeed (passed to tryAggregate)
+    ///
+    /// # Returns
+    /// * `Ok(Vec<MulticallResultWithGas>)` - Results and gas usage for each call
+    /// * `Err(EvmError)` - If deployment, execution or frame correlation fails
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use revm_trace::{create_evm_with_tracer, TxInspector, utils::multicall_utils::{MulticallManager, MulticallCall}};
+    /// use alloy::primitives::{address, bytes};
+    ///
+    /// let mut evm = create_evm_with_tracer("https://eth.llamarpc.com", TxInspector::new()).await?;
+    /// let manager = MulticallManager::new();
+    ///
+    /// let calls = vec![
+    ///     MulticallCall {
+    ///         target: address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"),
+    ///         callData: bytes!("0x70a08231000000000000000000000000d8da6bf26964af9d7eed9e03e53415d37aa96045"),
+    ///     },
+    /// ];
+    ///
+    /// let results = manager.deploy_and_batch_call_with_gas(&mut evm, calls, false)?;
+    /// for result in results {
+    ///     println!("success={}, gas_used={}", result.success, result.gas_used);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn deploy_and_batch_call_with_gas<DB>(
+        &self,
+        evm: &mut TraceEvm<CacheDB<DB>, TxInspector>,
+        calls: Vec<MulticallCall>,
+        require_success: bool,
+    ) -> Result<Vec<MulticallResultWithGas>, EvmError>
+    where
+        DB: DatabaseRef,
+    {
+        // Handle empty calls case
+        if calls.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Reset database to ensure clean state for deployment
+        evm.reset_db();
+
+        // Deploy Multicall contract and get its address
+        let multicall_address = self.deploy_multicall(evm, 0)?;
+
+        // Execute the batch with the inspector active
+        let tx = self.build_batch_tx(evm, multicall_address, 1, calls, require_success);
+        evm.reset_inspector();
+        evm.set_tx(tx);
+        let execution_result = evm.inspect_replay().map_err(|e| {
+            RuntimeError::ExecutionFailed(format!("Multicall execution failed: {e}"))
+        })?;
+        let results = Self::decode_batch_result(execution_result.result)?;
+
+        // Correlate direct subcalls of the Multicall contract with the input calls
+        let gas_used = evm
+            .get_inspector()
+            .get_traces()
+            .first()
+            .map(|root| {
+                root.subtraces
+                    .iter()
+                    .map(|frame| frame.gas_used.saturating_to::<u64>())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        evm.reset_inspector();
+        evm.set_tx(Default::default());
+
+        if gas_used.len() != results.len() {
+            return Err(RuntimeError::ExecutionFailed(format!(
+                "Failed to correlate Multicall frames: {} results but {} call frames",
+                results.len(),
+                gas_used.len()
+            ))
+            .into());
+        }
+
+        Ok(results
+            .into_iter()
+            .zip(gas_used)
+            .map(|(result, gas_used)| MulticallResultWithGas {
+                success: result.success,
+                return_data: result.returnData,
+                gas_used,
+            })
+            .collect())
+    }
+
+    /// Build the transaction calling `tryAggregate` on the deployed Multicall contract
+    fn build_batch_tx<DB, INSP>(
+        &self,
+        evm: &TraceEvm<DB, INSP>,
+        multicall_address: Address,
+        nonce: u64,
+        calls: Vec<MulticallCall>,
+        require_success: bool,
+    ) -> TxEnv
+    where
+        DB: Database,
+    {
         // Encode the batch call data using tryAggregate function
         let multicall_data = tryAggregateCall {
             requireSuccess: require_success,
@@ -251,51 +458,34 @@ impl MulticallManager {
         .abi_encode();
 
         // Create transaction to call the deployed Multicall contract
-        let tx = TxEnv {
+        TxEnv {
             kind: TxKind::Call(multicall_address),
             data: multicall_data.into(),
             chain_id: Some(evm.cfg.chain_id),
-            nonce: 1, // After deployment, nonce should start from 1
+            nonce, // The deployment used the previous nonce
             ..Default::default()
-        };
+        }
+    }
 
-        // Execute the multicall transaction
-        let execution_result = evm.transact(tx);
-
-        match execution_result {
-            Ok(execution_result) => {
-                // Process the execution result
-                match execution_result.result {
-                    ExecutionResult::Success { output, .. } => {
-                        // Decode the multicall results from the output data
-                        let results: Vec<MulticallResult> = tryAggregateCall::abi_decode_returns(
-                            &output.into_data(),
-                        )
-                        .map_err(|e| {
-                            RuntimeError::DecodeError(format!(
-                                "Failed to decode Multicall result: {e}"
-                            ))
-                        })?;
+    /// Decode the `tryAggregate` execution result into individual call results
+    fn decode_batch_result(result: ExecutionResult) -> Result<Vec<MulticallResult>, EvmError> {
+        match result {
+            ExecutionResult::Success { output, .. } => {
+                // Decode the multicall results from the output data
+                let results: Vec<MulticallResult> =
+                    tryAggregateCall::abi_decode_returns(&output.into_data()).map_err(|e| {
+                        RuntimeError::DecodeError(format!("Failed to decode Multicall result: {e}"))
+                    })?;
 
-                        Ok(results)
-                    }
-                    ExecutionResult::Revert { output, .. } => Err(RuntimeError::Revert(format!(
-                        "Multicall execution reverted: {}",
-                        String::from_utf8_lossy(&output)
-                    ))
-                    .into()),
-                    ExecutionResult::Halt { reason, .. } => Err(RuntimeError::Revert(format!(
-                        "Multicall execution halted: {reason:?}",
-                    ))
-                    .into()),
-                }
+                Ok(results)
             }
-            Err(e) => {
-                // Handle execution error
-                Err(
-                    RuntimeError::ExecutionFailed(format!("Multicall execution failed: {e}"))
-                        .into(),
-                )
+            ExecutionResult::Revert { output, .. } => Err(RuntimeError::Revert(format!(
+                "Multicall execution reverted: {}",
+                String::from_utf8_lossy(&output)
+            ))
+            .into()),
+            ExecutionResult::Halt { reason, .. } => {
+                Err(RuntimeError::Revert(format!("Multicall execution halted: {reason:?}",)).into())
             }
         }
     }
@@ -0,0 +1,555 @@
+//! Permit (EIP-2612) and Permit2 helpers for simulations
+//!
+//! Permit-based flows need a signature over the token's EIP-712 domain. Two
+//! strategies are offered:
+//!
+//! - **Storage synthesis**: [`synthesize_permit_via_storage`] and
+//!   [`synthesize_permit2_via_storage`] locate the allowance and nonce slots by probing
+//!   (see [`find_balance_slot`](crate::utils::cheat_utils::find_balance_slot)) and
+//!   return a `StateOverride` with the state a successful permit would leave behind.
+//!   No key is needed and nonstandard permits (e.g. DAI's) are covered, as only the
+//!   `allowance` and `nonces` views are used.
+//! - **Signing**: [`build_permit_tx`] and [`build_permit2_tx`] sign a real permit with
+//!   a test key through [`sign_permit`] and return the `permit` call, so the flow runs
+//!   exactly as on chain, signature verification included.
+//!
+//! Both results feed the follow-up batch: pass the override as
+//! `SimulationBatch::overrides`, or put the permit transaction first in a stateful
+//! batch.
+
+use crate::{
+    errors::{EvmError, RuntimeError},
+    evm::TraceEvm,
+    inspectors::tx_inspector::TxInspector,
+    types::{SimulationTx, StateOverride},
+    utils::cheat_utils::find_view_slot,
+};
+use alloy::{
+    primitives::{
+        address, aliases::U48, keccak256, Address, Bytes, Signature, TxKind, B256, U160, U256,
+    },
+    signers::{local::PrivateKeySigner, SignerSync},
+    sol,
+    sol_types::{Eip712Domain, SolCall, SolStruct},
+};
+use revm::database::{CacheDB, DatabaseRef};
+
+/// Canonical Permit2 deployment, at the same address on every chain
+pub const PERMIT2: Address = address!("000000000022D473030F116dDEE9F6B43aC78BA3");
+
+/// Largest value of the 48-bit fields of a Permit2 allowance
+const U48_MASK: u64 = (1 << 48) - 1;
+
+sol! {
+    /// EIP-2612 permit message
+    #[derive(Debug, PartialEq, Eq)]
+    struct Permit {
+        address owner;
+        address spender;
+        uint256 value;
+        uint256 nonce;
+        uint256 deadline;
+    }
+
+    /// Permit2 allowance granted by a `PermitSingle`
+    #[derive(Debug, PartialEq, Eq)]
+    struct PermitDetails {
+        address token;
+        uint160 amount;
+        uint48 expiration;
+        uint48 nonce;
+    }
+
+    /// Permit2 allowance transfer permit for one token
+    #[derive(Debug, PartialEq, Eq)]
+    struct PermitSingle {
+        PermitDetails details;
+        address spender;
+        uint256 sigDeadline;
+    }
+
+    interface IERC20Permit {
+        function name() external view returns (string);
+        function version() external view returns (string);
+        function DOMAIN_SEPARATOR() external view returns (bytes32);
+        function PERMIT_TYPEHASH() external view returns (bytes32);
+        function nonces(address owner) external view returns (uint256);
+        function allowance(address owner, address spender) external view returns (uint256);
+        function permit(
+            address owner,
+            address spender,
+            uint256 value,
+            uint256 deadline,
+            uint8 v,
+            bytes32 r,
+            bytes32 s
+        ) external;
+    }
+
+    interface IPermit2 {
+        function allowance(address user, address token, address spender)
+            external
+            view
+            returns (uint160 amount, uint48 expiration, uint48 nonce);
+        function permit(address owner, PermitSingle permitSingle, bytes signature) external;
+    }
+}
+
+/// Permit2 allowance of an owner for one token and spender
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Permit2Allowance {
+    /// Amount the spender may still transfer
+    pub amount: U160,
+    /// Timestamp after which the allowance is void
+    pub expiration: u64,
+    /// Nonce the next `PermitSingle` must carry
+    pub nonce: u64,
+}
+
+/// Returns the state a successful EIP-2612 `permit` would leave behind
+///
+/// Sets the allowance of `spender` over `owner`'s tokens to `value` and, for tokens
+/// exposing `nonces(owner)`, consumes the owner's current nonce. Pass the result as
+/// `SimulationBatch::overrides` so that `transferFrom` by `spender` succeeds; merge it
+/// with other overrides (e.g. a dealt balance) as needed.
+///
+/// # Returns
+/// - `Ok(StateOverride)`: Storage writes of the allowance and the nonce
+/// - `Err(EvmError::Runtime(RuntimeError::PermitFailed(_)))`: `deadline` is before the
+///   executing block, or no storage slot controls `allowance(owner, spender)`
+/// - `Err(EvmError::Runtime(_))`: Any other variant indicates a database or RPC failure
+///
+/// # Example
+/// ```no_run
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// use revm_trace::{
+///     create_evm_with_tracer, utils::permit_utils::synthesize_permit_via_storage, TxInspector,
+/// };
+/// use alloy::primitives::{address, U256};
+///
+/// let mut evm = create_evm_with_tracer("https://eth.llamarpc.com", TxInspector::new()).await?;
+/// let usdc = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+/// let owner = address!("1000000000000000000000000000000000000001");
+/// let spender = address!("2000000000000000000000000000000000000002");
+/// let overrides =
+///     synthesize_permit_via_storage(&mut evm, usdc, owner, spender, U256::from(1_000_000u64), U256::MAX)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn synthesize_permit_via_storage<DB>(
+    evm: &mut TraceEvm<CacheDB<DB>, TxInspector>,
+    token: Address,
+    owner: Address,
+    spender: Address,
+    value: U256,
+    deadline: U256,
+) -> Result<StateOverride, EvmError>
+where
+    DB: DatabaseRef,
+{
+    let timestamp = evm.block_context.timestamp;
+    if deadline < U256::from(timestamp) {
+        return Err(RuntimeError::PermitFailed(format!(
+            "deadline {deadline} is before the block timestamp {timestamp}"
+        ))
+        .into());
+    }
+
+    let mut overrides = StateOverride::default();
+    set_allowance(evm, token, owner, spender, value, &mut overrides)?;
+
+    // Tokens without `nonces` cannot be permitted by signature either, so the
+    // allowance alone is the complete effect
+    if let Ok(nonce) = permit_nonce(evm, token, owner) {
+        let data = IERC20Permit::noncesCall { owner }.abi_encode().into();
+        if let Some((address, slot)) = find_view_slot(evm, token, owner, data)? {
+            overrides
+                .storages
+                .entry(address)
+                .or_default()
+                .push((slot, nonce + U256::from(1)));
+        }
+    }
+    Ok(overrides)
+}
+
+/// Returns the state a successful Permit2 `permit` would leave behind
+///
+/// Sets `owner`'s Permit2 allowance for `spender` on `token` to `amount` until
+/// `expiration` and consumes the current Permit2 nonce. The owner's ERC20 approval of
+/// Permit2 itself is set to the maximum, as wallets do once per token, so a following
+/// `transferFrom` through Permit2 succeeds.
+///
+/// # Returns
+/// - `Ok(StateOverride)`: Storage writes of both allowances
+/// - `Err(EvmError::Runtime(RuntimeError::PermitFailed(_)))`: Permit2 is not deployed,
+///   or the token's allowance slot cannot be located
+/// - `Err(EvmError::Runtime(_))`: Any other variant indicates a database or RPC failure
+pub fn synthesize_permit2_via_storage<DB>(
+    evm: &mut TraceEvm<CacheDB<DB>, TxInspector>,
+    token: Address,
+    owner: Address,
+    spender: Address,
+    amount: U160,
+    expiration: u64,
+) -> Result<StateOverride, EvmError>
+where
+    DB: DatabaseRef,
+{
+    let mut overrides = StateOverride::default();
+    set_allowance(evm, token, owner, PERMIT2, U256::MAX, &mut overrides)?;
+
+    let current = permit2_allowance(evm, owner, token, spender)?;
+    let data = IPermit2::allowanceCall {
+        user: owner,
+        token,
+        spender,
+    }
+    .abi_encode()
+    .into();
+    let (address, slot) = find_view_slot(evm, PERMIT2, owner, data)?.ok_or_else(|| {
+        RuntimeError::PermitFailed(format!(
+            "no Permit2 storage slot controls the allowance of {owner} on {token}"
+        ))
+    })?;
+    // PackedAllowance: amount in the low 160 bits, then expiration and nonce (48 bits each)
+    let packed = U256::from(amount)
+        | U256::from(expiration & U48_MASK) << 160
+        | U256::from((current.nonce + 1) & U48_MASK) << 208;
+    overrides
+        .storages
+        .entry(address)
+        .or_default()
+        .push((slot, packed));
+    Ok(overrides)
+}
+
+/// Signs an EIP-712 permit message with `private_key`
+///
+/// Works for any `sol!` struct, in particular [`Permit`] with the token's domain (see
+/// [`permit_domain`]) and [`PermitSingle`] with [`permit2_domain`].
+///
+/// # Returns
+/// - `Ok(Signature)`: Signature over the message's EIP-712 signing hash
+/// - `Err(EvmError::Runtime(RuntimeError::PermitFailed(_)))`: `private_key` is not a
+///   valid secp256k1 key
+pub fn sign_permit<T: SolStruct>(
+    private_key: &B256,
+    domain: &Eip712Domain,
+    message: &T,
+) -> Result<Signature, EvmError> {
+    let signer = PrivateKeySigner::from_bytes(private_key)
+        .map_err(|e| RuntimeError::PermitFailed(format!("Invalid private key: {e}")))?;
+    signer
+        .sign_hash_sync(&message.eip712_signing_hash(domain))
+        .map_err(|e| RuntimeError::PermitFailed(format!("Failed to sign permit: {e}")).into())
+}
+
+/// Fetches the EIP-712 domain of a permit token
+///
+/// Builds the domain from `name()`, `version()` (`"1"` when absent), the EVM's chain
+/// id and the token address, and checks it against the token's `DOMAIN_SEPARATOR()`.
+///
+/// # Returns
+/// - `Ok(Eip712Domain)`: Domain hashing to the token's separator
+/// - `Err(EvmError::Runtime(RuntimeError::PermitFailed(_)))`: The token has no
+///   `DOMAIN_SEPARATOR()`, or it uses a domain that cannot be reconstructed
+pub fn permit_domain<DB, INSP>(
+    evm: &mut TraceEvm<CacheDB<DB>, INSP>,
+    token: Address,
+) -> Result<Eip712Domain, EvmError>
+where
+    DB: DatabaseRef,
+{
+    let separator = view(evm, token, IERC20Permit::DOMAIN_SEPARATORCall {}).map_err(|e| {
+        RuntimeError::PermitFailed(format!("{token} exposes no DOMAIN_SEPARATOR(): {e}"))
+    })?;
+    let name = view(evm, token, IERC20Permit::nameCall {})?;
+    let version =
+        view(evm, token, IERC20Permit::versionCall {}).unwrap_or_else(|_| "1".to_string());
+
+    let domain = Eip712Domain::new(
+        Some(name.into()),
+        Some(version.into()),
+        Some(U256::from(evm.cfg.chain_id)),
+        Some(token),
+        None,
+    );
+    if domain.separator() != separator {
+        return Err(RuntimeError::PermitFailed(format!(
+            "the EIP-712 domain of {token} cannot be reconstructed from name() and version()"
+        ))
+        .into());
+    }
+    Ok(domain)
+}
+
+/// Returns the EIP-712 domain of the canonical Permit2 deployment on `chain_id`
+pub fn permit2_domain(chain_id: u64) -> Eip712Domain {
+    Eip712Domain::new(
+        Some("Permit2".into()),
+        None,
+        Some(U256::from(chain_id)),
+        Some(PERMIT2),
+        None,
+    )
+}
+
+/// Reads the EIP-2612 nonce the next permit of `owner` must carry
+pub fn permit_nonce<DB, INSP>(
+    evm: &mut TraceEvm<CacheDB<DB>, INSP>,
+    token: Address,
+    owner: Address,
+) -> Result<U256, EvmError>
+where
+    DB: DatabaseRef,
+{
+    view(evm, token, IERC20Permit::noncesCall { owner })
+}
+
+/// Reads the Permit2 allowance of `owner` for `token` and `spender`
+///
+/// # Returns
+/// - `Ok(Permit2Allowance)`: Current amount, expiration and nonce
+/// - `Err(EvmError::Runtime(RuntimeError::PermitFailed(_)))`: Permit2 is not deployed
+pub fn permit2_allowance<DB, INSP>(
+    evm: &mut TraceEvm<CacheDB<DB>, INSP>,
+    owner: Address,
+    token: Address,
+    spender: Address,
+) -> Result<Permit2Allowance, EvmError>
+where
+    DB: DatabaseRef,
+{
+    let allowance = view(
+        evm,
+        PERMIT2,
+        IPermit2::allowanceCall {
+            user: owner,
+            token,
+            spender,
+        },
+    )
+    .map_err(|e| RuntimeError::PermitFailed(format!("Permit2 is not available: {e}")))?;
+    Ok(Permit2Allowance {
+        amount: allowance.amount,
+        expiration: allowance.expiration.to(),
+        nonce: allowance.nonce.to(),
+    })
+}
+
+/// Signs an EIP-2612 permit with `private_key` and returns the `permit` call
+///
+/// The owner is the key's address and sends the transaction, though anyone could.
+/// Tokens whose `PERMIT_TYPEHASH()` differs from EIP-2612's (e.g. DAI, whose permit
+/// takes a nonce, an expiry and an `allowed` flag) are rejected; use
+/// [`synthesize_permit_via_storage`] for them.
+///
+/// # Returns
+/// - `Ok(SimulationTx)`: The `permit` call, to be put before the spending transaction
+///   in a stateful batch
+/// - `Err(EvmError::Runtime(RuntimeError::PermitFailed(_)))`: The token's permit is
+///   nonstandard or its domain cannot be reconstructed, or the key is invalid
+/// - `Err(EvmError::Runtime(_))`: Any other variant indicates a database or RPC failure
+pub fn build_permit_tx<DB, INSP>(
+    evm: &mut TraceEvm<CacheDB<DB>, INSP>,
+    private_key: &B256,
+    token: Address,
+    spender: Address,
+    value: U256,
+    deadline: U256,
+) -> Result<SimulationTx, EvmError>
+where
+    DB: DatabaseRef,
+{
+    let owner = signer_address(private_key)?;
+    if let Ok(typehash) = view(evm, token, IERC20Permit::PERMIT_TYPEHASHCall {}) {
+        if typehash != permit_type_hash() {
+            return Err(RuntimeError::PermitFailed(format!(
+                "{token} implements a nonstandard permit (type hash {typehash}); \
+                 use storage synthesis instead"
+            ))
+            .into());
+        }
+    }
+    let domain = permit_domain(evm, token)?;
+    let nonce = permit_nonce(evm, token, owner)?;
+    let permit = Permit {
+        owner,
+        spender,
+        value,
+        nonce,
+        deadline,
+    };
+    let signature = sign_permit(private_key, &domain, &permit)?;
+
+    let data = IERC20Permit::permitCall {
+        owner,
+        spender,
+        value,
+        deadline,
+        v: 27 + u8::from(signature.v()),
+        r: signature.r().into(),
+        s: signature.s().into(),
+    }
+    .abi_encode()
+    .into();
+    Ok(call(owner, token, data))
+}
+
+/// Signs a Permit2 `PermitSingle` with `private_key` and returns the `permit` call
+///
+/// The permit carries the owner's current Permit2 nonce. The owner must have approved
+/// Permit2 on `token` for the spender's later `transferFrom` to succeed.
+///
+/// # Returns
+/// - `Ok(SimulationTx)`: The call to Permit2's `permit`
+/// - `Err(EvmError::Runtime(RuntimeError::PermitFailed(_)))`: Permit2 is not
+///   deployed, or the key is invalid
+pub fn build_permit2_tx<DB, INSP>(
+    evm: &mut TraceEvm<CacheDB<DB>, INSP>,
+    private_key: &B256,
+    token: Address,
+    spender: Address,
+    amount: U160,
+    expiration: u64,
+    sig_deadline: U256,
+) -> Result<SimulationTx, EvmError>
+where
+    DB: DatabaseRef,
+{
+    let owner = signer_address(private_key)?;
+    let current = permit2_allowance(evm, owner, token, spender)?;
+    let permit = PermitSingle {
+        details: PermitDetails {
+            token,
+            amount,
+            expiration: U48::saturating_from(expiration),
+            nonce: U48::saturating_from(current.nonce),
+        },
+        spender,
+        sigDeadline: sig_deadline,
+    };
+    let signature = sign_permit(private_key, &permit2_domain(evm.cfg.chain_id), &permit)?;
+
+    let data = IPermit2::permitCall {
+        owner,
+        permitSingle: permit,
+        signature: Bytes::copy_from_slice(&signature.as_bytes()),
+    }
+    .abi_encode()
+    .into();
+    Ok(call(owner, PERMIT2, data))
+}
+
+/// Adds the write setting the ERC20 allowance of `spender` over `owner`'s tokens
+fn set_allowance<DB>(
+    evm: &mut TraceEvm<CacheDB<DB>, TxInspector>,
+    token: Address,
+    owner: Address,
+    spender: Address,
+    value: U256,
+    overrides: &mut StateOverride,
+) -> Result<(), EvmError>
+where
+    DB: DatabaseRef,
+{
+    let data = IERC20Permit::allowanceCall { owner, spender }
+        .abi_encode()
+        .into();
+    let (address, slot) = find_view_slot(evm, token, owner, data)?.ok_or_else(|| {
+        RuntimeError::PermitFailed(format!(
+            "no storage slot controls allowance({owner}, {spender}) on {token}"
+        ))
+    })?;
+    overrides
+        .storages
+        .entry(address)
+        .or_default()
+        .push((slot, value));
+    Ok(())
+}
+
+/// Type hash of the EIP-2612 `Permit` struct
+fn permit_type_hash() -> B256 {
+    keccak256(Permit::eip712_encode_type().as_bytes())
+}
+
+fn signer_address(private_key: &B256) -> Result<Address, EvmError> {
+    PrivateKeySigner::from_bytes(private_key)
+        .map(|signer| signer.address())
+        .map_err(|e| RuntimeError::PermitFailed(format!("Invalid private key: {e}")).into())
+}
+
+fn call(caller: Address, to: Address, data: Bytes) -> SimulationTx {
+    SimulationTx {
+        caller,
+        transact_to: TxKind::Call(to),
+        value: U256::ZERO,
+        data,
+        trace_config: None,
+        return_abi: None,
+    }
+}
+
+/// Runs a view call under static semantics and decodes its return value
+fn view<DB, INSP, C: SolCall>(
+    evm: &mut TraceEvm<CacheDB<DB>, INSP>,
+    to: Address,
+    call: C,
+) -> Result<C::Return, EvmError>
+where
+    DB: DatabaseRef,
+{
+    let output = evm.static_call(to, call.abi_encode().into(), Address::ZERO)?;
+    C::abi_decode_returns(&output).map_err(|e| {
+        RuntimeError::DecodeError(format!("Invalid return data from {to}: {e}")).into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::b256;
+
+    const KEY: B256 = b256!("0000000000000000000000000000000000000000000000000000000000000042");
+
+    #[test]
+    fn test_permit_type_hash_matches_eip2612() {
+        assert_eq!(
+            permit_type_hash(),
+            keccak256(
+                "Permit(address owner,address spender,uint256 value,uint256 nonce,uint256 deadline)"
+            )
+        );
+    }
+
+    #[test]
+    fn test_signature_recovers_owner() {
+        let owner = signer_address(&KEY).unwrap();
+        let domain = permit2_domain(1);
+        let permit = PermitSingle {
+            details: PermitDetails {
+                token: Address::repeat_byte(0x11),
+                amount: U160::from(1_000),
+                expiration: U48::from(1_700_000_000u64),
+                nonce: U48::ZERO,
+            },
+            spender: Address::repeat_byte(0x22),
+            sigDeadline: U256::MAX,
+        };
+        let signature = sign_permit(&KEY, &domain, &permit).unwrap();
+        let recovered = signature
+            .recover_address_from_prehash(&permit.eip712_signing_hash(&domain))
+            .unwrap();
+        assert_eq!(recovered, owner);
+    }
+
+    #[test]
+    fn test_zero_key_is_rejected() {
+        assert!(matches!(
+            signer_address(&B256::ZERO),
+            Err(EvmError::Runtime(RuntimeError::PermitFailed(_)))
+        ));
+    }
+}
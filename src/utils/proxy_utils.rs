@@ -12,6 +12,7 @@
 use crate::{
     errors::{EvmError, RuntimeError},
     evm::TraceEvm,
+    types::LogicalCall,
 };
 use alloy::primitives::{Address, U256};
 use anyhow::Result;
@@ -152,3 +153,31 @@ where
 
     Ok(None)
 }
+
+/// Fills `active_implementation` on every forwarded call of a logical call tree
+///
+/// Resolves each proxy in `call` (see [`TxTraceOutput::logical_view`]) with
+/// [`get_implementation`]. Slots are read from the EVM's current state, so for a
+/// stateful simulation this is the implementation after the transaction; an upgrade
+/// made by the traced transaction itself shows up as a mismatch with `implementation`.
+///
+/// [`TxTraceOutput::logical_view`]: crate::inspectors::tx_inspector::TxTraceOutput::logical_view
+///
+/// # Returns
+/// * `Ok(())` - Every proxy node was annotated (`None` where no slot is set)
+/// * `Err(_)` - If there's an error accessing contract state
+pub fn annotate_implementations<DB, INSP>(
+    evm: &mut TraceEvm<DB, INSP>,
+    call: &mut LogicalCall,
+) -> Result<(), EvmError>
+where
+    DB: Database,
+{
+    if let Some(proxy) = call.proxy {
+        call.active_implementation = get_implementation(evm, proxy)?;
+    }
+    for child in &mut call.subtraces {
+        annotate_implementations(evm, child)?;
+    }
+    Ok(())
+}
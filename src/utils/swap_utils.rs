@@ -0,0 +1,363 @@
+//! Token swap simulation
+//!
+//! [`simulate_swap`] runs the usual three steps of a swap simulation in one stateful
+//! batch: approve the router, swap, and read the user's output balance. The amount
+//! received is the user's balance change, so fee-on-transfer tokens report what
+//! actually arrived, and the pool hops are picked out of the swap's call tree.
+
+use crate::{
+    errors::{EvmError, RuntimeError},
+    evm::TraceEvm,
+    inspectors::tx_inspector::{TxInspector, TxTraceOutput},
+    traits::TransactionTrace,
+    types::{CallTrace, SimulationBatch, SimulationTx, StateOverride},
+    utils::{cheat_utils::find_balance_slot, error_utils::parse_custom_error},
+};
+use alloy::{
+    hex,
+    primitives::{aliases::U24, Address, Bytes, TxKind, U160, U256},
+    sol,
+    sol_types::SolCall,
+};
+use revm::{
+    context_interface::result::ExecutionResult,
+    database::{CacheDB, DatabaseRef},
+};
+
+sol! {
+    function balanceOf(address owner) public returns (uint256);
+    function approve(address spender, uint256 amount) public returns (bool);
+
+    // Uniswap V2 router
+    function swapExactTokensForTokens(
+        uint256 amountIn,
+        uint256 amountOutMin,
+        address[] calldata path,
+        address to,
+        uint256 deadline
+    ) external returns (uint256[] memory amounts);
+
+    // Uniswap V3 SwapRouter
+    struct ExactInputSingleParams {
+        address tokenIn;
+        address tokenOut;
+        uint24 fee;
+        address recipient;
+        uint256 deadline;
+        uint256 amountIn;
+        uint256 amountOutMinimum;
+        uint160 sqrtPriceLimitX96;
+    }
+    function exactInputSingle(ExactInputSingleParams calldata params)
+        external
+        payable
+        returns (uint256 amountOut);
+}
+
+/// Selectors of the pool functions counted as swap hops
+///
+/// - `swap(uint256,uint256,address,bytes)`: Uniswap V2 style pairs
+/// - `swap(address,bool,int256,uint160,bytes)`: Uniswap V3 style pools
+const POOL_SWAP_SELECTORS: [[u8; 4]; 2] = [[0x02, 0x2c, 0x0d, 0x9f], [0x12, 0x8a, 0xcb, 0x08]];
+
+/// Fraction of `amount_in` swapped by the reference swap of the price impact estimate
+const REFERENCE_DIVISOR: u64 = 1_000;
+
+/// Router calldata of a swap
+#[derive(Debug, Clone)]
+pub enum SwapCalldata {
+    /// Pre-built calldata, sent to the router as is
+    ///
+    /// It must swap `amount_in` of `token_in` and pay `token_out` to the user.
+    Raw(Bytes),
+    /// `swapExactTokensForTokens` on a Uniswap V2 style router, through the direct
+    /// `token_in`/`token_out` pair
+    UniswapV2 { amount_out_min: U256 },
+    /// `exactInputSingle` on the Uniswap V3 `SwapRouter`, through the pool with fee
+    /// tier `fee` (in hundredths of a basis point)
+    UniswapV3 { fee: u32, amount_out_min: U256 },
+}
+
+impl SwapCalldata {
+    /// Encodes the router call swapping `amount_in` on behalf of `spec.user`
+    fn encode(&self, spec: &SwapSpec, amount_in: U256) -> Result<Bytes, EvmError> {
+        Ok(match self {
+            Self::Raw(data) => data.clone(),
+            Self::UniswapV2 { amount_out_min } => swapExactTokensForTokensCall {
+                amountIn: amount_in,
+                amountOutMin: *amount_out_min,
+                path: vec![spec.token_in, spec.token_out],
+                to: spec.user,
+                deadline: U256::MAX,
+            }
+            .abi_encode()
+            .into(),
+            Self::UniswapV3 {
+                fee,
+                amount_out_min,
+            } => exactInputSingleCall {
+                params: ExactInputSingleParams {
+                    tokenIn: spec.token_in,
+                    tokenOut: spec.token_out,
+                    fee: U24::checked_from(*fee).ok_or_else(|| {
+                        RuntimeError::InvalidInput(format!("Fee tier {fee} exceeds 24 bits"))
+                    })?,
+                    recipient: spec.user,
+                    deadline: U256::MAX,
+                    amountIn: amount_in,
+                    amountOutMinimum: *amount_out_min,
+                    sqrtPriceLimitX96: U160::ZERO,
+                },
+            }
+            .abi_encode()
+            .into(),
+        })
+    }
+
+    /// Calldata of the reference swap, without a minimum output; `None` for raw
+    /// calldata, which cannot be rescaled
+    fn reference(&self, spec: &SwapSpec, amount_in: U256) -> Option<Result<Bytes, EvmError>> {
+        let unbounded = match self {
+            Self::Raw(_) => return None,
+            Self::UniswapV2 { .. } => Self::UniswapV2 {
+                amount_out_min: U256::ZERO,
+            },
+            Self::UniswapV3 { fee, .. } => Self::UniswapV3 {
+                fee: *fee,
+                amount_out_min: U256::ZERO,
+            },
+        };
+        Some(unbounded.encode(spec, amount_in))
+    }
+}
+
+/// Swap to simulate with [`simulate_swap`]
+#[derive(Debug, Clone)]
+pub struct SwapSpec {
+    /// Account approving and swapping
+    pub user: Address,
+    /// Router the user approves and calls
+    pub router: Address,
+    /// Token sold
+    pub token_in: Address,
+    /// Token bought
+    pub token_out: Address,
+    /// Amount of `token_in` sold, in its smallest unit
+    pub amount_in: U256,
+    /// How the router call is built
+    pub calldata_builder: SwapCalldata,
+    /// Whether to set the user's `token_in` balance to `amount_in` first (see
+    /// [`deal`](crate::utils::cheat_utils::deal)); otherwise the user's real balance
+    /// is spent
+    pub fund_user: bool,
+}
+
+/// Outcome of [`simulate_swap`]
+#[derive(Debug, Clone)]
+pub struct SwapResult {
+    /// Increase of the user's `token_out` balance
+    pub amount_out: U256,
+    /// Relative shortfall of the swap's rate against a swap of a thousandth of
+    /// `amount_in` on the same route, e.g. `0.003` for 0.3%
+    ///
+    /// Fees apply to both swaps alike, so this isolates the impact of the trade size.
+    /// `None` for [`SwapCalldata::Raw`] and amounts too small to scale down.
+    pub price_impact_estimate: Option<f64>,
+    /// Gas used by the router call
+    pub gas_used: u64,
+    /// Trace addresses of the pool `swap` calls, in execution order
+    pub route_frames: Vec<Vec<usize>>,
+    /// Pools called at `route_frames`
+    pub pools: Vec<Address>,
+    /// Trace of the router call
+    pub trace: TxTraceOutput,
+}
+
+/// Approves the router, swaps and measures what the user received
+///
+/// Runs `balanceOf`, `approve`, the router call and `balanceOf` again as one
+/// stateful batch on the pinned state; nothing persists into later batches. With
+/// `fund_user`, the user's `token_in` balance is written through a state override.
+/// Transactions are sent without gas fees, so the user needs no native balance on an
+/// EVM built with base fees disabled.
+///
+/// For the built-in encoders, a second batch swaps a thousandth of `amount_in` on the
+/// same route to estimate the price impact.
+///
+/// # Returns
+/// - `Ok(SwapResult)`: The swap succeeded
+/// - `Err(EvmError::Runtime(RuntimeError::Revert(_)))`: The approval or the swap
+///   reverted, with the decoded reason
+/// - `Err(EvmError::Runtime(RuntimeError::BalanceSlotNotFound(_)))`: `fund_user` is
+///   set but the balance of `token_in` cannot be written
+/// - `Err(EvmError)`: Any other variant indicates a database or RPC failure
+///
+/// # Example
+/// ```no_run
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// use revm_trace::{
+///     create_evm_with_tracer,
+///     utils::swap_utils::{simulate_swap, SwapCalldata, SwapSpec},
+///     TxInspector,
+/// };
+/// use alloy::primitives::{address, U256};
+///
+/// let mut evm = create_evm_with_tracer("https://eth.llamarpc.com", TxInspector::new()).await?;
+/// let result = simulate_swap(
+///     &mut evm,
+///     SwapSpec {
+///         user: address!("1000000000000000000000000000000000000001"),
+///         router: address!("7a250d5630B4cF539739dF2C5dAcb4c659F2488D"),
+///         token_in: address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+///         token_out: address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"),
+///         amount_in: U256::from(10).pow(U256::from(18)),
+///         calldata_builder: SwapCalldata::UniswapV2 {
+///             amount_out_min: U256::ZERO,
+///         },
+///         fund_user: true,
+///     },
+/// )?;
+/// println!("received {} through {} pools", result.amount_out, result.pools.len());
+/// # Ok(())
+/// # }
+/// ```
+pub fn simulate_swap<DB>(
+    evm: &mut TraceEvm<CacheDB<DB>, TxInspector>,
+    spec: SwapSpec,
+) -> Result<SwapResult, EvmError>
+where
+    DB: DatabaseRef,
+{
+    let calldata = spec.calldata_builder.encode(&spec, spec.amount_in)?;
+    let swap = run_swap(evm, &spec, spec.amount_in, calldata)?;
+
+    let reference_amount = spec.amount_in / U256::from(REFERENCE_DIVISOR);
+    let price_impact_estimate = match spec.calldata_builder.reference(&spec, reference_amount) {
+        Some(calldata) if !reference_amount.is_zero() && !swap.amount_out.is_zero() => {
+            let reference = run_swap(evm, &spec, reference_amount, calldata?)?;
+            let rate = ratio(swap.amount_out, spec.amount_in);
+            let reference_rate = ratio(reference.amount_out, reference_amount);
+            (reference_rate > 0.0).then(|| 1.0 - rate / reference_rate)
+        }
+        _ => None,
+    };
+
+    let (route_frames, pools) = swap
+        .trace
+        .call_trace
+        .iter()
+        .flat_map(CallTrace::frames)
+        .filter(|frame| {
+            frame.call_scheme.is_some()
+                && frame
+                    .input
+                    .get(..4)
+                    .is_some_and(|selector| POOL_SWAP_SELECTORS.iter().any(|s| s == selector))
+        })
+        .map(|frame| (frame.trace_address.clone(), frame.to))
+        .unzip();
+
+    Ok(SwapResult {
+        amount_out: swap.amount_out,
+        price_impact_estimate,
+        gas_used: swap.gas_used,
+        route_frames,
+        pools,
+        trace: swap.trace,
+    })
+}
+
+/// Router call of one simulated swap
+struct SwapRun {
+    amount_out: U256,
+    gas_used: u64,
+    trace: TxTraceOutput,
+}
+
+/// Runs the balance reads, approval and swap of `amount_in` as one batch
+fn run_swap<DB>(
+    evm: &mut TraceEvm<CacheDB<DB>, TxInspector>,
+    spec: &SwapSpec,
+    amount_in: U256,
+    calldata: Bytes,
+) -> Result<SwapRun, EvmError>
+where
+    DB: DatabaseRef,
+{
+    let overrides = if spec.fund_user {
+        let (holder, slot) = find_balance_slot(evm, spec.token_in, spec.user)?;
+        let mut overrides = StateOverride::default();
+        overrides.storages.insert(holder, vec![(slot, amount_in)]);
+        Some(overrides)
+    } else {
+        None
+    };
+    let call = |to: Address, data: Bytes| SimulationTx {
+        caller: spec.user,
+        transact_to: TxKind::Call(to),
+        value: U256::ZERO,
+        data,
+        trace_config: None,
+        return_abi: None,
+    };
+    let balance_of: Bytes = balanceOfCall { owner: spec.user }.abi_encode().into();
+    let approval = approveCall {
+        spender: spec.router,
+        amount: amount_in,
+    }
+    .abi_encode()
+    .into();
+
+    let mut results = evm
+        .trace_transactions(SimulationBatch {
+            transactions: vec![
+                call(spec.token_out, balance_of.clone()),
+                call(spec.token_in, approval),
+                call(spec.router, calldata),
+                call(spec.token_out, balance_of),
+            ],
+            is_stateful: true,
+            overrides,
+            ..Default::default()
+        })
+        .into_iter();
+    let mut next = |step: &str| -> Result<_, EvmError> {
+        let (result, _, trace) = results
+            .next()
+            .ok_or_else(|| RuntimeError::ExecutionFailed(format!("No result for {step}")))??;
+        match result {
+            ExecutionResult::Success { .. } => Ok((result, trace)),
+            ExecutionResult::Revert { output, .. } => Err(RuntimeError::Revert(format!(
+                "{step} reverted: {}",
+                parse_custom_error(&output)
+                    .unwrap_or_else(|| format!("0x{}", hex::encode(&output)))
+            ))
+            .into()),
+            ExecutionResult::Halt { reason, .. } => {
+                Err(RuntimeError::ExecutionFailed(format!("{step} halted: {reason:?}")).into())
+            }
+        }
+    };
+
+    let balance_before = decode_balance(next("balanceOf")?.0)?;
+    next("approve")?;
+    let (swap, trace) = next("swap")?;
+    let balance_after = decode_balance(next("balanceOf")?.0)?;
+    Ok(SwapRun {
+        amount_out: balance_after.saturating_sub(balance_before),
+        gas_used: swap.gas_used(),
+        trace,
+    })
+}
+
+/// Decodes the result of a successful `balanceOf` call
+fn decode_balance(result: ExecutionResult) -> Result<U256, EvmError> {
+    let output = result.output().cloned().unwrap_or_default();
+    balanceOfCall::abi_decode_returns(&output)
+        .map_err(|e| RuntimeError::DecodeError(format!("Failed to decode balanceOf: {e}")).into())
+}
+
+/// `numerator / denominator` as a float
+fn ratio(numerator: U256, denominator: U256) -> f64 {
+    f64::from(numerator) / f64::from(denominator)
+}
@@ -0,0 +1,405 @@
+//! Offline re-execution of traced transactions
+//!
+//! [`TraceEvm::trace_with_prestate`] traces a transaction and records its
+//! [`Prestate`]: every account and storage slot it touched, as they were before it
+//! ran. [`replay_from_prestate`] loads that prestate into an in-memory database,
+//! executes the transaction again without any RPC access and compares the outcome
+//! with the original [`SimulationReport`].
+//!
+//! A clean replay shows the trace is reproducible from the archived data alone; a
+//! divergence points at state the simulation read without it being captured, or at a
+//! bug in the tracing pipeline. Block hashes are not part of the prestate, so
+//! transactions reading `BLOCKHASH` replay against the in-memory database's
+//! placeholder hashes.
+//!
+//! For verification against the chain itself rather than a trusted archive,
+//! [`fetch_proofs`] fetches `eth_getProof` proofs of the state a transaction accessed,
+//! as listed by [`TxTraceOutput::proof_requests`].
+//!
+//! [`TxTraceOutput::proof_requests`]: crate::inspectors::tx_inspector::TxTraceOutput::proof_requests
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! use alloy::primitives::{address, TxKind, U256};
+//! use revm_trace::{
+//!     create_evm_with_tracer, verify::replay_from_prestate, SimulationTx, TxInspector,
+//! };
+//!
+//! let mut evm = create_evm_with_tracer("https://eth.llamarpc.com", TxInspector::new()).await?;
+//! let tx = SimulationTx {
+//!     caller: address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045"),
+//!     transact_to: TxKind::Call(address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2")),
+//!     value: U256::from(10u64.pow(18)),
+//!     data: Default::default(),
+//!     trace_config: None,
+//!     return_abi: None,
+//! };
+//! let (report, prestate) = evm.trace_with_prestate(tx.clone(), false)?;
+//! replay_from_prestate(&prestate, evm.ctx.block.clone(), tx, &report)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    errors::{EvmError, RuntimeError},
+    evm::TraceEvm,
+    inspectors::tx_inspector::TxInspector,
+    report::SimulationReport,
+    types::{sorted_map, AnyNetworkProvider, ProofRequest, SimulationTx, StorageDiff},
+};
+use alloy::{
+    eips::BlockId,
+    primitives::{Address, Bytes, U256},
+    providers::Provider,
+    rpc::types::EIP1186AccountProofResponse,
+};
+use revm::{
+    context::{BlockEnv, Context},
+    database::{CacheDB, Database, DatabaseRef, EmptyDB},
+    handler::{MainBuilder, MainContext},
+    state::{AccountInfo, Bytecode, EvmState},
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// State of one account before a transaction
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrestateAccount {
+    pub balance: U256,
+    pub nonce: u64,
+    /// Deployed code (empty for accounts without code)
+    pub code: Bytes,
+    /// Values of the slots the transaction read or wrote
+    #[serde(serialize_with = "sorted_map::serialize")]
+    pub storage: HashMap<U256, U256>,
+}
+
+/// Everything a transaction read, as it was before the transaction ran
+///
+/// Accounts that did not exist are omitted; they replay as non-existent.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Prestate {
+    /// Chain id the transaction executed with
+    pub chain_id: u64,
+    /// Touched accounts
+    #[serde(serialize_with = "sorted_map::serialize")]
+    pub accounts: HashMap<Address, PrestateAccount>,
+}
+
+/// One difference between the original execution and its replay
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Error)]
+pub enum Mismatch {
+    /// The replay could not be executed at all
+    #[error("replay failed: {0}")]
+    ReplayFailed(String),
+    /// Success status differs
+    #[error("success: expected {expected}, got {actual}")]
+    Status { expected: bool, actual: bool },
+    /// Gas used differs
+    #[error("gas used: expected {expected}, got {actual}")]
+    GasUsed { expected: u64, actual: u64 },
+    /// Return or revert data differs
+    #[error("output: expected {expected}, got {actual}")]
+    Output { expected: Bytes, actual: Bytes },
+    /// Number of emitted logs differs
+    #[error("log count: expected {expected}, got {actual}")]
+    LogCount { expected: usize, actual: usize },
+    /// Log at `index` differs in emitter, topics or data
+    #[error("log {index} differs")]
+    Log { index: usize },
+    /// Final value of a slot differs (`None`: the slot was not changed)
+    #[error("slot {slot} of {address}: expected {expected:?}, got {actual:?}")]
+    Slot {
+        address: Address,
+        slot: U256,
+        expected: Option<U256>,
+        actual: Option<U256>,
+    },
+}
+
+/// Differences found by [`replay_from_prestate`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Error)]
+#[error("replay diverged from the original execution: {}", .differences.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+pub struct VerificationMismatch {
+    /// All differences, in the order they were checked
+    pub differences: Vec<Mismatch>,
+}
+
+impl<DB> TraceEvm<CacheDB<DB>, TxInspector>
+where
+    DB: DatabaseRef,
+{
+    /// Traces a transaction and captures its prestate for offline replay
+    ///
+    /// Behaves like tracing a single transaction with `trace_transactions`, except that
+    /// the cache is not reset first; with `is_stateful` the transaction is committed.
+    ///
+    /// # Returns
+    /// - `Ok((report, prestate))`: The simulation report, including the executed
+    ///   transaction environment, and the state it started from
+    /// - `Err(EvmError::Runtime(_))`: Execution or a database lookup failed
+    pub fn trace_with_prestate(
+        &mut self,
+        tx: SimulationTx,
+        is_stateful: bool,
+    ) -> Result<(SimulationReport, Prestate), EvmError> {
+        let (result, state) = self.execute_internal(tx.clone())?;
+        let prestate = self.capture_prestate(&state)?;
+        let (result, storage_diff, trace) = self.finish_internal(result, state, is_stateful);
+        let mut report = SimulationReport::new(tx, &result, storage_diff, trace);
+        report.executed_tx = self.executed_tx.take();
+        Ok((report, prestate))
+    }
+
+    /// Reads the pre-transaction values of everything in `state` from the uncommitted cache
+    fn capture_prestate(&mut self, state: &EvmState) -> Result<Prestate, RuntimeError> {
+        let mut accounts = HashMap::with_capacity(state.len());
+        for (address, account) in state {
+            let info = self.db().basic(*address).map_err(|e| {
+                RuntimeError::AccountAccess(format!("Failed to load account {address}: {e}"))
+            })?;
+            let Some(info) = info else {
+                continue;
+            };
+            let code = match info.code {
+                Some(code) => code,
+                None => self.db().code_by_hash(info.code_hash).map_err(|e| {
+                    RuntimeError::AccountAccess(format!("Failed to load code of {address}: {e}"))
+                })?,
+            };
+            accounts.insert(
+                *address,
+                PrestateAccount {
+                    balance: info.balance,
+                    nonce: info.nonce,
+                    code: code.original_bytes(),
+                    storage: account
+                        .storage
+                        .iter()
+                        .map(|(slot, value)| (*slot, value.original_value))
+                        .collect(),
+                },
+            );
+        }
+        Ok(Prestate {
+            chain_id: self.cfg.chain_id,
+            accounts,
+        })
+    }
+}
+
+/// Re-executes a transaction against its prestate alone and compares the outcome
+///
+/// The replay EVM uses the same simulation settings as the RPC-backed builder
+/// (no base fee, block gas limit, code size limit or EIP-3607 checks).
+///
+/// # Arguments
+/// - `prestate`: State captured by [`TraceEvm::trace_with_prestate`]
+/// - `block_env`: Block environment of the original execution
+/// - `tx`: The traced transaction
+/// - `expected`: Report of the original execution
+///
+/// # Returns
+/// - `Ok(())`: Status, gas, output, logs and storage changes all match
+/// - `Err(VerificationMismatch)`: Every difference found
+pub fn replay_from_prestate(
+    prestate: &Prestate,
+    block_env: BlockEnv,
+    tx: SimulationTx,
+    expected: &SimulationReport,
+) -> Result<(), VerificationMismatch> {
+    let mut db = CacheDB::new(EmptyDB::default());
+    for (address, account) in &prestate.accounts {
+        let code = Bytecode::new_raw(account.code.clone());
+        db.insert_account_info(
+            *address,
+            AccountInfo {
+                balance: account.balance,
+                nonce: account.nonce,
+                code_hash: code.hash_slow(),
+                code: Some(code),
+            },
+        );
+        for (slot, value) in &account.storage {
+            // The account was inserted just above, so this cannot fail
+            let _ = db.insert_account_storage(*address, *slot, *value);
+        }
+    }
+
+    let mut ctx = Context::mainnet().with_db(db).with_block(block_env);
+    ctx.cfg.chain_id = prestate.chain_id;
+    ctx.cfg.disable_eip3607 = true;
+    ctx.cfg.limit_contract_code_size = None;
+    ctx.cfg.disable_block_gas_limit = true;
+    ctx.cfg.disable_base_fee = true;
+    let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(TxInspector::new()));
+
+    let (result, storage_diff, trace) =
+        evm.trace_internal(tx.clone(), false)
+            .map_err(|e| VerificationMismatch {
+                differences: vec![Mismatch::ReplayFailed(e.to_string())],
+            })?;
+    let actual = SimulationReport::new(tx, &result, storage_diff, trace);
+
+    let differences = compare_reports(expected, &actual);
+    if differences.is_empty() {
+        Ok(())
+    } else {
+        Err(VerificationMismatch { differences })
+    }
+}
+
+/// Fetch account and storage proofs for the state a transaction accessed
+///
+/// Issues one `eth_getProof` per request, so the result holds exactly the witness
+/// needed to check the accessed state against the block's state root.
+///
+/// # Arguments
+/// - `provider`: Provider to fetch the proofs through
+/// - `block`: Block whose state the transaction executed on
+/// - `requests`: Accounts and storage keys to prove, usually from
+///   [`TxTraceOutput::proof_requests`]
+///
+/// # Returns
+/// - `Ok(BTreeMap)`: Proofs by account address
+/// - `Err(EvmError)`: The first request the provider failed to answer
+///
+/// [`TxTraceOutput::proof_requests`]: crate::inspectors::tx_inspector::TxTraceOutput::proof_requests
+pub async fn fetch_proofs(
+    provider: &AnyNetworkProvider,
+    block: BlockId,
+    requests: &[ProofRequest],
+) -> Result<BTreeMap<Address, EIP1186AccountProofResponse>, EvmError> {
+    let mut proofs = BTreeMap::new();
+    for request in requests {
+        let proof = provider
+            .get_proof(request.address, request.storage_keys.clone())
+            .block_id(block)
+            .await
+            .map_err(|e| {
+                RuntimeError::AccountAccess(format!(
+                    "Failed to fetch proof for {}: {e}",
+                    request.address
+                ))
+            })?;
+        proofs.insert(request.address, proof);
+    }
+    Ok(proofs)
+}
+
+/// Lists the differences between two reports of the same transaction
+fn compare_reports(expected: &SimulationReport, actual: &SimulationReport) -> Vec<Mismatch> {
+    let mut differences = Vec::new();
+    let (want, got) = (&expected.summary, &actual.summary);
+    if want.success != got.success {
+        differences.push(Mismatch::Status {
+            expected: want.success,
+            actual: got.success,
+        });
+    }
+    if want.gas_used != got.gas_used {
+        differences.push(Mismatch::GasUsed {
+            expected: want.gas_used,
+            actual: got.gas_used,
+        });
+    }
+    if want.output != got.output {
+        differences.push(Mismatch::Output {
+            expected: want.output.clone(),
+            actual: got.output.clone(),
+        });
+    }
+
+    let (want, got) = (&expected.trace.logs, &actual.trace.logs);
+    if want.len() != got.len() {
+        differences.push(Mismatch::LogCount {
+            expected: want.len(),
+            actual: got.len(),
+        });
+    }
+    differences.extend(
+        want.iter()
+            .zip(got)
+            .enumerate()
+            .filter(|(_, (want, got))| want != got)
+            .map(|(index, _)| Mismatch::Log { index }),
+    );
+
+    let want = final_values(&expected.storage_diff);
+    let got = final_values(&actual.storage_diff);
+    let mut slots: Vec<_> = want.keys().chain(got.keys()).copied().collect();
+    slots.sort();
+    slots.dedup();
+    for (address, slot) in slots {
+        let (expected, actual) = (want.get(&(address, slot)), got.get(&(address, slot)));
+        if expected != actual {
+            differences.push(Mismatch::Slot {
+                address,
+                slot,
+                expected: expected.copied(),
+                actual: actual.copied(),
+            });
+        }
+    }
+    differences
+}
+
+/// Final value of every slot changed in a storage diff
+fn final_values(diff: &StorageDiff) -> HashMap<(Address, U256), U256> {
+    diff.values()
+        .flatten()
+        .map(|access| ((access.address, access.slot), access.new_value))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::ExecutionSummary;
+    use crate::types::SlotAccess;
+
+    #[test]
+    fn test_compare_reports() {
+        let address = Address::repeat_byte(1);
+        let diff = |value: u64| {
+            StorageDiff::from([(
+                address,
+                vec![SlotAccess {
+                    address,
+                    slot: U256::from(1),
+                    new_value: U256::from(value),
+                    is_write: true,
+                    ..Default::default()
+                }],
+            )])
+        };
+        let report = |gas_used: u64, value: u64| SimulationReport {
+            summary: ExecutionSummary {
+                success: true,
+                gas_used,
+                ..Default::default()
+            },
+            storage_diff: diff(value),
+            ..Default::default()
+        };
+
+        assert!(compare_reports(&report(21_000, 5), &report(21_000, 5)).is_empty());
+        assert_eq!(
+            compare_reports(&report(21_000, 5), &report(23_000, 6)),
+            vec![
+                Mismatch::GasUsed {
+                    expected: 21_000,
+                    actual: 23_000
+                },
+                Mismatch::Slot {
+                    address,
+                    slot: U256::from(1),
+                    expected: Some(U256::from(5)),
+                    actual: Some(U256::from(6)),
+                },
+            ]
+        );
+    }
+}
@@ -21,6 +21,45 @@
 //!
 //! This extension is essential for scenarios where you need to update the underlying database state
 //! (e.g., switching block context) without reconstructing the entire wrapper.
+//!
+//! ## Usage Statistics
+//!
+//! Every lookup is counted by kind with relaxed atomics, and the time spent blocked
+//! on it is accumulated, see [`DbStats`]. In the default stack the wrapper sits below
+//! the `CacheDB` overlay, so each counted lookup is an upstream RPC request.
+//!
+//! With the `tracing-logs` feature, every lookup runs in a debug-level `db_fetch` span
+//! recording its kind, address and duration.
+//!
+//! ## Missing Historical State
+//!
+//! Non-archive nodes only keep recent state; lookups at older blocks fail with
+//! client-specific messages. [`is_missing_state_error`] recognises them so they can be
+//! reported as `RuntimeError::HistoricalStateUnavailable`.
+//!
+//! ## Warm Cache
+//!
+//! A [`WarmCache`] attached with [`MyWrapDatabaseAsync::with_warm_cache`] answers
+//! repeated account, code and storage lookups of EVMs at the same block without
+//! upstream requests.
+//!
+//! ## Remote Dependencies
+//!
+//! With [`MyWrapDatabaseAsync::record_fetches`] enabled, every lookup answered by the
+//! upstream node (not by a warm cache) is logged as a [`RemoteFetch`] with a hash of
+//! the answer. [`dependencies_digest`] hashes such a log into a single value.
+//!
+//! ## Rate Limiting
+//!
+//! An [`RpcRateLimiter`] attached with [`MyWrapDatabaseAsync::with_rate_limiter`]
+//! spaces upstream lookups to stay under an endpoint's request quota. The time spent
+//! waiting for it is reported as `rate_limit_wait` in [`DbStats`].
+//!
+//! ## Endpoint Failover
+//!
+//! When the upstream provider fails over between several RPC endpoints (see
+//! `EvmBuilder::new_alloy_multi`), [`DbStats`] also reports the endpoint in use and
+//! the number of switches.
 
 use revm::{
     database::{Database, DatabaseRef},
@@ -30,13 +69,250 @@ use revm::{
 };
 
 use core::future::Future;
+use serde::Serialize;
+use std::{
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 use tokio::runtime::{Handle, Runtime};
 
+mod rate_limit;
+mod remote_fetch;
+mod warm_cache;
+
+pub use rate_limit::RpcRateLimiter;
+use remote_fetch::FetchLog;
+pub use remote_fetch::{dependencies_digest, RemoteFetch, RemoteFetchKind};
+pub use warm_cache::WarmCache;
+use warm_cache::WarmLink;
+
+/// Database lookup statistics for cost monitoring
+///
+/// Counts are cumulative since creation or the last reset. Account and storage
+/// lookups are counted at each level they pass: `cache_hits` and `cache_misses` at
+/// the `CacheDB` overlay, `warm_cache_hits` at a [`WarmCache`] and the per-kind counts
+/// at the upstream database. The per-kind counts add up to `upstream_calls`. Compare
+/// `cache_misses` and `upstream_calls` across runs to observe the effect of caching.
+///
+/// `cache_hits` is counted by the EVM from the state each executed transaction
+/// loaded, so it is only filled in by
+/// [`TraceEvm::db_stats`](crate::TraceEvm::db_stats) and batch metrics;
+/// [`MyWrapDatabaseAsync::stats`] leaves it at 0.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct DbStats {
+    /// Account info lookups
+    pub basic_calls: u64,
+    /// Storage slot lookups
+    pub storage_calls: u64,
+    /// Bytecode lookups by hash
+    pub code_calls: u64,
+    /// Block hash lookups
+    pub block_hash_calls: u64,
+    /// Account and storage lookups of executed transactions answered by the
+    /// `CacheDB` overlay
+    pub cache_hits: u64,
+    /// Account and storage lookups the `CacheDB` overlay forwarded to the wrapper
+    pub cache_misses: u64,
+    /// Lookups answered by a [`WarmCache`] (0 without one)
+    pub warm_cache_hits: u64,
+    /// Lookups forwarded to the upstream database, the sum of the per-kind counts
+    pub upstream_calls: u64,
+    /// Total time spent waiting for upstream lookups
+    pub fetch_time: Duration,
+    /// Part of `fetch_time` spent waiting for an [`RpcRateLimiter`]
+    pub rate_limit_wait: Duration,
+    /// Switches to another RPC endpoint after transport failures
+    pub failovers: u64,
+    /// Position of the RPC endpoint in use in the configured list (`None` with a
+    /// single endpoint)
+    pub active_endpoint: Option<usize>,
+}
+
+impl DbStats {
+    /// Returns the statistics accumulated since an earlier snapshot
+    ///
+    /// `active_endpoint` is taken from `self`.
+    pub fn since(&self, earlier: &DbStats) -> DbStats {
+        DbStats {
+            basic_calls: self.basic_calls.saturating_sub(earlier.basic_calls),
+            storage_calls: self.storage_calls.saturating_sub(earlier.storage_calls),
+            code_calls: self.code_calls.saturating_sub(earlier.code_calls),
+            block_hash_calls: self
+                .block_hash_calls
+                .saturating_sub(earlier.block_hash_calls),
+            cache_hits: self.cache_hits.saturating_sub(earlier.cache_hits),
+            cache_misses: self.cache_misses.saturating_sub(earlier.cache_misses),
+            warm_cache_hits: self.warm_cache_hits.saturating_sub(earlier.warm_cache_hits),
+            upstream_calls: self.upstream_calls.saturating_sub(earlier.upstream_calls),
+            fetch_time: self.fetch_time.saturating_sub(earlier.fetch_time),
+            rate_limit_wait: self.rate_limit_wait.saturating_sub(earlier.rate_limit_wait),
+            failovers: self.failovers.saturating_sub(earlier.failovers),
+            active_endpoint: self.active_endpoint,
+        }
+    }
+}
+
+/// Error message fragments of nodes lacking the state of the requested block
+///
+/// Matched case-insensitively against the full error, which for AlloyDB is the
+/// transport error wrapping the node's JSON-RPC error response.
+const MISSING_STATE_PATTERNS: &[&str] = &[
+    // geth and forks, hash-based state scheme
+    "missing trie node",
+    // geth, path-based state scheme
+    "historical state",
+    // besu
+    "state not available",
+    // nethermind
+    "state is not available",
+    "no state available",
+    // erigon
+    "due to pruning",
+    "has been pruned",
+];
+
+/// Whether a database error reports that the node lacks the requested block's state
+///
+/// ```
+/// use revm_trace::is_missing_state_error;
+///
+/// assert!(is_missing_state_error(
+///     "server returned an error response: error code -32000: missing trie node 1b5f…"
+/// ));
+/// assert!(!is_missing_state_error("error sending request for url"));
+/// ```
+pub fn is_missing_state_error(message: &str) -> bool {
+    let message = message.to_lowercase();
+    MISSING_STATE_PATTERNS
+        .iter()
+        .any(|pattern| message.contains(pattern))
+}
+
+/// Atomic counters backing [`DbStats`]
+#[derive(Debug, Default)]
+struct DbCounters {
+    basic: AtomicU64,
+    storage: AtomicU64,
+    code: AtomicU64,
+    block_hash: AtomicU64,
+    cache_misses: AtomicU64,
+    warm_hits: AtomicU64,
+    fetch_nanos: AtomicU64,
+    rate_limit_nanos: AtomicU64,
+}
+
+impl DbCounters {
+    /// Counts a lookup and accumulates the time it took
+    #[inline]
+    fn timed<R>(
+        &self,
+        counter: &AtomicU64,
+        kind: &'static str,
+        address: Option<Address>,
+        lookup: impl FnOnce() -> R,
+    ) -> R {
+        counter.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "tracing-logs")]
+        let span = tracing::debug_span!(
+            "db_fetch",
+            kind,
+            address = address.map(tracing::field::display),
+            duration_us = tracing::field::Empty,
+        )
+        .entered();
+        #[cfg(not(feature = "tracing-logs"))]
+        let _ = (kind, address);
+        let start = Instant::now();
+        let result = lookup();
+        let elapsed = start.elapsed();
+        self.fetch_nanos
+            .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+        #[cfg(feature = "tracing-logs")]
+        span.record("duration_us", elapsed.as_micros() as u64);
+        result
+    }
+
+    /// Waits for a token of `limiter`, if any, then runs `lookup`
+    #[inline]
+    async fn throttled<F: Future>(&self, limiter: Option<&RpcRateLimiter>, lookup: F) -> F::Output {
+        if let Some(limiter) = limiter {
+            let waited = limiter.acquire().await;
+            self.rate_limit_nanos
+                .fetch_add(waited.as_nanos() as u64, Ordering::Relaxed);
+        }
+        lookup.await
+    }
+
+    /// Counts an account or storage lookup the `CacheDB` overlay forwarded
+    #[inline]
+    fn cache_miss(&self) {
+        self.cache_misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a lookup answered by a warm cache
+    fn warm_hit(&self) {
+        self.warm_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> DbStats {
+        let basic_calls = self.basic.load(Ordering::Relaxed);
+        let storage_calls = self.storage.load(Ordering::Relaxed);
+        let code_calls = self.code.load(Ordering::Relaxed);
+        let block_hash_calls = self.block_hash.load(Ordering::Relaxed);
+        DbStats {
+            basic_calls,
+            storage_calls,
+            code_calls,
+            block_hash_calls,
+            cache_hits: 0,
+            cache_misses: self.cache_misses.load(Ordering::Relaxed),
+            warm_cache_hits: self.warm_hits.load(Ordering::Relaxed),
+            upstream_calls: basic_calls + storage_calls + code_calls + block_hash_calls,
+            fetch_time: Duration::from_nanos(self.fetch_nanos.load(Ordering::Relaxed)),
+            rate_limit_wait: Duration::from_nanos(self.rate_limit_nanos.load(Ordering::Relaxed)),
+            failovers: 0,
+            active_endpoint: None,
+        }
+    }
+
+    fn reset(&self) {
+        for counter in [
+            &self.basic,
+            &self.storage,
+            &self.code,
+            &self.block_hash,
+            &self.cache_misses,
+            &self.warm_hits,
+            &self.fetch_nanos,
+            &self.rate_limit_nanos,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+/// RPC endpoint selection shared by a failover transport and the wrappers above it
+#[derive(Debug, Default)]
+pub(crate) struct EndpointState {
+    /// Position of the endpoint serving requests in the configured list
+    pub(crate) active: AtomicUsize,
+    /// Switches to another endpoint after transport failures
+    pub(crate) failovers: AtomicU64,
+}
+
 /// Wraps a [DatabaseAsync] or [DatabaseAsyncRef] to provide a [`Database`] implementation.
 #[derive(Debug)]
 pub struct MyWrapDatabaseAsync<T> {
     db: T,
     rt: HandleOrRuntime,
+    stats: DbCounters,
+    warm_cache: Option<WarmLink>,
+    endpoints: Option<Arc<EndpointState>>,
+    rate_limiter: Option<RpcRateLimiter>,
+    fetches: FetchLog,
 }
 
 impl<T> MyWrapDatabaseAsync<T> {
@@ -51,7 +327,15 @@ impl<T> MyWrapDatabaseAsync<T> {
             },
             Err(_) => return None,
         };
-        Some(Self { db, rt })
+        Some(Self {
+            db,
+            rt,
+            stats: DbCounters::default(),
+            warm_cache: None,
+            endpoints: None,
+            rate_limiter: None,
+            fetches: FetchLog::default(),
+        })
     }
 
     /// Gets a mutable reference to the inner database
@@ -70,6 +354,95 @@ impl<T> MyWrapDatabaseAsync<T> {
         &mut self.db
     }
 
+    /// Returns a snapshot of the lookup statistics
+    pub fn stats(&self) -> DbStats {
+        let mut stats = self.stats.snapshot();
+        if let Some(endpoints) = &self.endpoints {
+            stats.failovers = endpoints.failovers.load(Ordering::Relaxed);
+            stats.active_endpoint = Some(endpoints.active.load(Ordering::Relaxed));
+        }
+        stats
+    }
+
+    /// Resets all lookup statistics to zero
+    ///
+    /// The failover count is reset too; the active endpoint is kept.
+    pub fn reset_stats(&self) {
+        self.stats.reset();
+        if let Some(endpoints) = &self.endpoints {
+            endpoints.failovers.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Enables or disables the log of upstream lookups
+    ///
+    /// While enabled, each successful upstream lookup is appended to a log read with
+    /// [`take_fetches`](Self::take_fetches). Lookups answered by a warm cache are not
+    /// logged. Disabling drops the entries not taken yet.
+    pub fn record_fetches(&self, enabled: bool) {
+        self.fetches.set_recording(enabled);
+    }
+
+    /// Takes the upstream lookups logged since the last call, in the order they were
+    /// made
+    ///
+    /// Returns `None` while recording is disabled.
+    pub fn take_fetches(&self) -> Option<Vec<RemoteFetch>> {
+        self.fetches.take()
+    }
+
+    /// Attaches a shared cache of upstream lookups
+    ///
+    /// The wrapper is taken to read state at the block `cache` is pinned to; pin the
+    /// cache first (see [`WarmCache::pin`]) when that is not certain. Account, code and
+    /// storage lookups are answered from the cache when possible, and the answers of
+    /// upstream requests are recorded in it.
+    pub fn with_warm_cache(mut self, cache: WarmCache) -> Self {
+        self.attach_warm_cache(cache);
+        self
+    }
+
+    /// Attaches a shared cache of upstream lookups in place, see
+    /// [`with_warm_cache`](Self::with_warm_cache)
+    pub(crate) fn attach_warm_cache(&mut self, cache: WarmCache) {
+        let block_number = cache.block_number();
+        self.warm_cache = Some(WarmLink {
+            cache,
+            block_number,
+        });
+    }
+
+    /// Limits the rate of upstream lookups with `limiter`
+    ///
+    /// Every lookup not answered by a warm cache first takes a token from `limiter`,
+    /// waiting asynchronously when none is available. Share clones of one limiter
+    /// between all wrappers reading from the same endpoint to limit the endpoint as a
+    /// whole.
+    pub fn with_rate_limiter(mut self, limiter: RpcRateLimiter) -> Self {
+        self.attach_rate_limiter(limiter);
+        self
+    }
+
+    /// Limits the rate of upstream lookups in place, see
+    /// [`with_rate_limiter`](Self::with_rate_limiter)
+    pub(crate) fn attach_rate_limiter(&mut self, limiter: RpcRateLimiter) {
+        self.rate_limiter = Some(limiter);
+    }
+
+    /// Reports the endpoint selection of a failover transport in the statistics
+    pub(crate) fn attach_endpoints(&mut self, endpoints: Arc<EndpointState>) {
+        self.endpoints = Some(endpoints);
+    }
+
+    /// Records that the wrapped database now reads state at `block_number`
+    ///
+    /// An attached warm cache is bypassed unless it is pinned to the same block.
+    pub(crate) fn set_warm_block(&mut self, block_number: u64) {
+        if let Some(link) = self.warm_cache.as_mut() {
+            link.block_number = block_number;
+        }
+    }
+
     /// Wraps a [DatabaseAsync] or [DatabaseAsyncRef] instance, with a runtime.
     ///
     /// Refer to [tokio::runtime::Builder] on how to create a runtime if you are in synchronous world.
@@ -77,7 +450,15 @@ impl<T> MyWrapDatabaseAsync<T> {
     /// If you are already using something like [tokio::main], call [`WrapDatabaseAsync::new`] instead.
     pub fn with_runtime(db: T, runtime: Runtime) -> Self {
         let rt = HandleOrRuntime::Runtime(runtime);
-        Self { db, rt }
+        Self {
+            db,
+            rt,
+            stats: DbCounters::default(),
+            warm_cache: None,
+            endpoints: None,
+            rate_limiter: None,
+            fetches: FetchLog::default(),
+        }
     }
 
     /// Wraps a [DatabaseAsync] or [DatabaseAsyncRef] instance, with a runtime handle.
@@ -88,7 +469,15 @@ impl<T> MyWrapDatabaseAsync<T> {
     /// If you are already in asynchronous world, like [tokio::main], use [`WrapDatabaseAsync::new`] instead.
     pub fn with_handle(db: T, handle: Handle) -> Self {
         let rt = HandleOrRuntime::Handle(handle);
-        Self { db, rt }
+        Self {
+            db,
+            rt,
+            stats: DbCounters::default(),
+            warm_cache: None,
+            endpoints: None,
+            rate_limiter: None,
+            fetches: FetchLog::default(),
+        }
     }
 }
 
@@ -97,12 +486,62 @@ impl<T: DatabaseAsync> Database for MyWrapDatabaseAsync<T> {
 
     #[inline]
     fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
-        self.rt.block_on(self.db.basic_async(address))
+        let Self {
+            db,
+            rt,
+            stats,
+            warm_cache,
+            rate_limiter,
+            fetches,
+            ..
+        } = self;
+        stats.cache_miss();
+        WarmLink::through(
+            warm_cache.as_ref(),
+            || stats.warm_hit(),
+            |warm| warm.accounts.get(&address).cloned(),
+            || {
+                stats
+                    .timed(&stats.basic, "basic", Some(address), || {
+                        rt.block_on(stats.throttled(rate_limiter.as_ref(), db.basic_async(address)))
+                    })
+                    .inspect(|info| fetches.record(|| RemoteFetch::basic(address, info.as_ref())))
+            },
+            |warm, info| {
+                warm.accounts.insert(address, info);
+            },
+        )
     }
 
     #[inline]
     fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
-        self.rt.block_on(self.db.code_by_hash_async(code_hash))
+        let Self {
+            db,
+            rt,
+            stats,
+            warm_cache,
+            rate_limiter,
+            fetches,
+            ..
+        } = self;
+        WarmLink::through(
+            warm_cache.as_ref(),
+            || stats.warm_hit(),
+            |warm| warm.contracts.get(&code_hash).cloned(),
+            || {
+                stats
+                    .timed(&stats.code, "code", None, || {
+                        rt.block_on(
+                            stats
+                                .throttled(rate_limiter.as_ref(), db.code_by_hash_async(code_hash)),
+                        )
+                    })
+                    .inspect(|_| fetches.record(|| RemoteFetch::code(code_hash)))
+            },
+            |warm, code| {
+                warm.contracts.insert(code_hash, code);
+            },
+        )
     }
 
     #[inline]
@@ -111,12 +550,51 @@ impl<T: DatabaseAsync> Database for MyWrapDatabaseAsync<T> {
         address: Address,
         index: StorageKey,
     ) -> Result<StorageValue, Self::Error> {
-        self.rt.block_on(self.db.storage_async(address, index))
+        let Self {
+            db,
+            rt,
+            stats,
+            warm_cache,
+            rate_limiter,
+            fetches,
+            ..
+        } = self;
+        stats.cache_miss();
+        WarmLink::through(
+            warm_cache.as_ref(),
+            || stats.warm_hit(),
+            |warm| warm.storage.get(&(address, index)).copied(),
+            || {
+                stats
+                    .timed(&stats.storage, "storage", Some(address), || {
+                        rt.block_on(
+                            stats
+                                .throttled(rate_limiter.as_ref(), db.storage_async(address, index)),
+                        )
+                    })
+                    .inspect(|value| {
+                        fetches.record(|| RemoteFetch::storage(address, index, *value))
+                    })
+            },
+            |warm, value| {
+                warm.storage.insert((address, index), value);
+            },
+        )
     }
 
     #[inline]
     fn block_hash(&mut self, number: u64) -> Result<B256, Self::Error> {
-        self.rt.block_on(self.db.block_hash_async(number))
+        self.stats
+            .timed(&self.stats.block_hash, "block_hash", None, || {
+                self.rt.block_on(
+                    self.stats
+                        .throttled(self.rate_limiter.as_ref(), self.db.block_hash_async(number)),
+                )
+            })
+            .inspect(|hash| {
+                self.fetches
+                    .record(|| RemoteFetch::block_hash(number, *hash))
+            })
     }
 }
 
@@ -125,12 +603,50 @@ impl<T: DatabaseAsyncRef> DatabaseRef for MyWrapDatabaseAsync<T> {
 
     #[inline]
     fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
-        self.rt.block_on(self.db.basic_async_ref(address))
+        self.stats.cache_miss();
+        WarmLink::through(
+            self.warm_cache.as_ref(),
+            || self.stats.warm_hit(),
+            |warm| warm.accounts.get(&address).cloned(),
+            || {
+                self.stats
+                    .timed(&self.stats.basic, "basic", Some(address), || {
+                        self.rt.block_on(self.stats.throttled(
+                            self.rate_limiter.as_ref(),
+                            self.db.basic_async_ref(address),
+                        ))
+                    })
+                    .inspect(|info| {
+                        self.fetches
+                            .record(|| RemoteFetch::basic(address, info.as_ref()))
+                    })
+            },
+            |warm, info| {
+                warm.accounts.insert(address, info);
+            },
+        )
     }
 
     #[inline]
     fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
-        self.rt.block_on(self.db.code_by_hash_async_ref(code_hash))
+        WarmLink::through(
+            self.warm_cache.as_ref(),
+            || self.stats.warm_hit(),
+            |warm| warm.contracts.get(&code_hash).cloned(),
+            || {
+                self.stats
+                    .timed(&self.stats.code, "code", None, || {
+                        self.rt.block_on(self.stats.throttled(
+                            self.rate_limiter.as_ref(),
+                            self.db.code_by_hash_async_ref(code_hash),
+                        ))
+                    })
+                    .inspect(|_| self.fetches.record(|| RemoteFetch::code(code_hash)))
+            },
+            |warm, code| {
+                warm.contracts.insert(code_hash, code);
+            },
+        )
     }
 
     #[inline]
@@ -139,12 +655,43 @@ impl<T: DatabaseAsyncRef> DatabaseRef for MyWrapDatabaseAsync<T> {
         address: Address,
         index: StorageKey,
     ) -> Result<StorageValue, Self::Error> {
-        self.rt.block_on(self.db.storage_async_ref(address, index))
+        self.stats.cache_miss();
+        WarmLink::through(
+            self.warm_cache.as_ref(),
+            || self.stats.warm_hit(),
+            |warm| warm.storage.get(&(address, index)).copied(),
+            || {
+                self.stats
+                    .timed(&self.stats.storage, "storage", Some(address), || {
+                        self.rt.block_on(self.stats.throttled(
+                            self.rate_limiter.as_ref(),
+                            self.db.storage_async_ref(address, index),
+                        ))
+                    })
+                    .inspect(|value| {
+                        self.fetches
+                            .record(|| RemoteFetch::storage(address, index, *value))
+                    })
+            },
+            |warm, value| {
+                warm.storage.insert((address, index), value);
+            },
+        )
     }
 
     #[inline]
     fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
-        self.rt.block_on(self.db.block_hash_async_ref(number))
+        self.stats
+            .timed(&self.stats.block_hash, "block_hash", None, || {
+                self.rt.block_on(self.stats.throttled(
+                    self.rate_limiter.as_ref(),
+                    self.db.block_hash_async_ref(number),
+                ))
+            })
+            .inspect(|hash| {
+                self.fetches
+                    .record(|| RemoteFetch::block_hash(number, *hash))
+            })
     }
 }
 
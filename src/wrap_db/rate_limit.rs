@@ -0,0 +1,104 @@
+//! Client-side limit on the rate of upstream lookups
+//!
+//! Free-tier RPC endpoints enforce a requests-per-second quota and answer bursts over
+//! it with errors or server-side throttling. An [`RpcRateLimiter`] spaces the lookups
+//! of the wrappers it is attached to with a token bucket: up to `burst` lookups go out
+//! at once, then one per `1 / requests_per_second`. Waiting happens inside the future
+//! the wrapper blocks on, so the runtime's worker threads are never put to sleep.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Token bucket shared by every clone of an [`RpcRateLimiter`]
+#[derive(Debug)]
+struct Bucket {
+    /// Tokens available; negative when lookups are queued for later tokens
+    tokens: f64,
+    /// When `tokens` was last refilled
+    refilled: Instant,
+}
+
+/// Token bucket limiting the upstream lookups of one or more database wrappers
+///
+/// Clones share the bucket, so attaching clones of one limiter to every EVM reading
+/// from an endpoint (see `EvmFactory::with_rpc_rate_limit`) enforces the limit per
+/// endpoint rather than per EVM. Each lookup takes one token, whatever its kind;
+/// lookups answered by the `CacheDB` overlay or a [`WarmCache`](super::WarmCache) take
+/// none.
+///
+/// # Example
+/// ```
+/// use revm_trace::RpcRateLimiter;
+///
+/// // 10 requests per second, at most 5 at once
+/// let limiter = RpcRateLimiter::new(10, 5);
+/// assert_eq!(limiter.requests_per_second(), 10);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RpcRateLimiter {
+    requests_per_second: u32,
+    burst: u32,
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+impl RpcRateLimiter {
+    /// Creates a limiter allowing `requests_per_second` lookups on average and up to
+    /// `burst` at once
+    ///
+    /// Both values are raised to at least 1. The bucket starts full.
+    pub fn new(requests_per_second: u32, burst: u32) -> Self {
+        let burst = burst.max(1);
+        Self {
+            requests_per_second: requests_per_second.max(1),
+            burst,
+            bucket: Arc::new(Mutex::new(Bucket {
+                tokens: f64::from(burst),
+                refilled: Instant::now(),
+            })),
+        }
+    }
+
+    /// Returns the average number of lookups allowed per second
+    pub fn requests_per_second(&self) -> u32 {
+        self.requests_per_second
+    }
+
+    /// Returns the number of lookups allowed at once
+    pub fn burst(&self) -> u32 {
+        self.burst
+    }
+
+    /// Waits until a token is available and takes it, returning the time waited
+    ///
+    /// The token is reserved before waiting, so concurrent callers are served in the
+    /// order they arrived.
+    pub(super) async fn acquire(&self) -> Duration {
+        let wait = self.reserve();
+        if wait.is_zero() {
+            return wait;
+        }
+        let start = Instant::now();
+        tokio::time::sleep(wait).await;
+        start.elapsed()
+    }
+
+    /// Takes a token, possibly ahead of time, returning how long until it is due
+    fn reserve(&self) -> Duration {
+        let rate = f64::from(self.requests_per_second);
+        let mut bucket = self
+            .bucket
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(f64::from(self.burst)) - 1.0;
+        bucket.refilled = now;
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / rate)
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Log of the state fetched from the upstream node
+//!
+//! Everything a simulation reads below the `CacheDB` overlay and outside a
+//! [`WarmCache`](super::WarmCache) comes from the remote node, and could differ if the
+//! node answered wrongly or pruned the block. With recording enabled, the wrapper logs
+//! each successful upstream lookup as a [`RemoteFetch`] carrying a hash of the answer,
+//! so the outside state a result depends on can be enumerated and compared with
+//! [`dependencies_digest`].
+
+use alloy::primitives::{keccak256, Address, B256, U256};
+use revm::state::AccountInfo;
+use serde::{Deserialize, Serialize};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex, MutexGuard,
+};
+
+/// Kind of an upstream lookup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RemoteFetchKind {
+    /// Account balance, nonce and code
+    Basic,
+    /// Storage slot value
+    Storage,
+    /// Bytecode by hash
+    Code,
+    /// Hash of a past block
+    BlockHash,
+}
+
+impl RemoteFetchKind {
+    fn tag(self) -> u8 {
+        match self {
+            Self::Basic => 0,
+            Self::Storage => 1,
+            Self::Code => 2,
+            Self::BlockHash => 3,
+        }
+    }
+}
+
+/// Piece of state fetched from the upstream node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RemoteFetch {
+    /// Kind of lookup
+    pub kind: RemoteFetchKind,
+    /// Account looked up; zero for `Code` and `BlockHash`
+    pub address: Address,
+    /// Storage slot for `Storage`, block number for `BlockHash`
+    pub slot: Option<U256>,
+    /// Hash of the answer
+    ///
+    /// - `Basic`: keccak256 of balance (32 bytes), nonce (8 bytes) and code hash, all
+    ///   big-endian; zero for accounts that do not exist
+    /// - `Storage`: keccak256 of the 32-byte value
+    /// - `Code`: the code hash looked up
+    /// - `BlockHash`: the block hash returned
+    pub value_hash: B256,
+}
+
+impl RemoteFetch {
+    pub(super) fn basic(address: Address, info: Option<&AccountInfo>) -> Self {
+        let value_hash = info.map_or(B256::ZERO, |info| {
+            let mut preimage = Vec::with_capacity(72);
+            preimage.extend_from_slice(&info.balance.to_be_bytes::<32>());
+            preimage.extend_from_slice(&info.nonce.to_be_bytes());
+            preimage.extend_from_slice(info.code_hash.as_slice());
+            keccak256(preimage)
+        });
+        Self {
+            kind: RemoteFetchKind::Basic,
+            address,
+            slot: None,
+            value_hash,
+        }
+    }
+
+    pub(super) fn storage(address: Address, slot: U256, value: U256) -> Self {
+        Self {
+            kind: RemoteFetchKind::Storage,
+            address,
+            slot: Some(slot),
+            value_hash: keccak256(value.to_be_bytes::<32>()),
+        }
+    }
+
+    pub(super) fn code(code_hash: B256) -> Self {
+        Self {
+            kind: RemoteFetchKind::Code,
+            address: Address::ZERO,
+            slot: None,
+            value_hash: code_hash,
+        }
+    }
+
+    pub(super) fn block_hash(number: u64, hash: B256) -> Self {
+        Self {
+            kind: RemoteFetchKind::BlockHash,
+            address: Address::ZERO,
+            slot: Some(U256::from(number)),
+            value_hash: hash,
+        }
+    }
+}
+
+/// Hashes an ordered list of remote fetches into a single value
+///
+/// Each fetch contributes its kind, address, slot (zero when absent) and value hash,
+/// so two runs share a digest exactly when they fetched the same state in the same
+/// order and received the same answers. An empty list hashes to keccak256 of nothing.
+///
+/// ```
+/// use revm_trace::{dependencies_digest, RemoteFetch, RemoteFetchKind};
+/// use alloy::primitives::{Address, B256};
+///
+/// let fetch = RemoteFetch {
+///     kind: RemoteFetchKind::Basic,
+///     address: Address::repeat_byte(1),
+///     slot: None,
+///     value_hash: B256::ZERO,
+/// };
+/// assert_eq!(dependencies_digest(&[fetch]), dependencies_digest(&[fetch]));
+/// assert_ne!(dependencies_digest(&[fetch]), dependencies_digest(&[]));
+/// ```
+pub fn dependencies_digest(fetches: &[RemoteFetch]) -> B256 {
+    let mut preimage = Vec::with_capacity(fetches.len() * 85);
+    for fetch in fetches {
+        preimage.push(fetch.kind.tag());
+        preimage.extend_from_slice(fetch.address.as_slice());
+        preimage.extend_from_slice(&fetch.slot.unwrap_or_default().to_be_bytes::<32>());
+        preimage.extend_from_slice(fetch.value_hash.as_slice());
+    }
+    keccak256(preimage)
+}
+
+/// Fetches recorded since they were last taken, while recording is enabled
+#[derive(Debug, Default)]
+pub(super) struct FetchLog {
+    recording: AtomicBool,
+    fetches: Mutex<Vec<RemoteFetch>>,
+}
+
+impl FetchLog {
+    pub(super) fn set_recording(&self, enabled: bool) {
+        self.recording.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            self.lock().clear();
+        }
+    }
+
+    pub(super) fn is_recording(&self) -> bool {
+        self.recording.load(Ordering::Relaxed)
+    }
+
+    /// Records the fetch built by `fetch`, if recording is enabled
+    #[inline]
+    pub(super) fn record(&self, fetch: impl FnOnce() -> RemoteFetch) {
+        if self.is_recording() {
+            self.lock().push(fetch());
+        }
+    }
+
+    /// Takes the recorded fetches, `None` if recording is disabled
+    pub(super) fn take(&self) -> Option<Vec<RemoteFetch>> {
+        self.is_recording()
+            .then(|| std::mem::take(&mut *self.lock()))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<RemoteFetch>> {
+        self.fetches
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
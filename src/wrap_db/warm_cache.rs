@@ -0,0 +1,158 @@
+//! Upstream lookups shared across EVMs pinned to the same block
+//!
+//! Every EVM starts with an empty `CacheDB` overlay, so services building a fresh EVM
+//! per request refetch the same hot contracts over and over. A [`WarmCache`] attached
+//! to the wrapper keeps the answers of upstream lookups (accounts, code and storage)
+//! and serves them to every EVM sharing it. Only upstream answers are recorded;
+//! simulated writes live in the `CacheDB` overlay above the wrapper and never reach
+//! the cache.
+
+use revm::{
+    primitives::{Address, StorageKey, StorageValue, B256},
+    state::{AccountInfo, Bytecode},
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+/// Cache of upstream state lookups at one block, shared by cloning
+///
+/// Clones share the same entries, so a cache can be handed to any number of EVMs,
+/// including EVMs on other threads. Entries are only served to EVMs reading state at
+/// the block the cache is pinned to.
+///
+/// # Example
+/// ```no_run
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// use revm_trace::{EvmBuilder, TxInspector, WarmCache};
+///
+/// let cache = WarmCache::new(18_000_000);
+/// for _ in 0..2 {
+///     let evm = EvmBuilder::new_alloy("https://eth.llamarpc.com")
+///         .with_block_number(18_000_000)
+///         .with_tracer(TxInspector::new())
+///         .with_warm_cache(cache.clone())
+///         .build()
+///         .await?;
+///     // ... the second EVM reads the state fetched by the first from the cache ...
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct WarmCache {
+    state: Arc<RwLock<WarmState>>,
+}
+
+/// Entries of a [`WarmCache`] with the block they were fetched at
+#[derive(Debug, Default)]
+pub(super) struct WarmState {
+    block_number: u64,
+    pub(super) accounts: HashMap<Address, Option<AccountInfo>>,
+    pub(super) contracts: HashMap<B256, Bytecode>,
+    pub(super) storage: HashMap<(Address, StorageKey), StorageValue>,
+}
+
+impl WarmCache {
+    /// Creates an empty cache pinned to `block_number`
+    pub fn new(block_number: u64) -> Self {
+        Self {
+            state: Arc::new(RwLock::new(WarmState {
+                block_number,
+                ..Default::default()
+            })),
+        }
+    }
+
+    /// Returns the block the entries were fetched at
+    pub fn block_number(&self) -> u64 {
+        self.read().block_number
+    }
+
+    /// Pins the cache to `block_number`, dropping all entries if it was pinned to
+    /// another block
+    ///
+    /// EVMs still reading state at the previous block stop using the cache.
+    pub fn pin(&self, block_number: u64) {
+        let mut state = self.write();
+        if state.block_number != block_number {
+            *state = WarmState {
+                block_number,
+                ..Default::default()
+            };
+        }
+    }
+
+    /// Returns the number of cached accounts, contracts and storage slots
+    pub fn len(&self) -> usize {
+        let state = self.read();
+        state.accounts.len() + state.contracts.len() + state.storage.len()
+    }
+
+    /// Whether the cache holds no entries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops all entries, keeping the block
+    pub fn clear(&self) {
+        let block_number = self.block_number();
+        *self.write() = WarmState {
+            block_number,
+            ..Default::default()
+        };
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, WarmState> {
+        self.state
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, WarmState> {
+        self.state
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A [`WarmCache`] attached to a wrapper reading state at `block_number`
+#[derive(Debug)]
+pub(super) struct WarmLink {
+    pub(super) cache: WarmCache,
+    pub(super) block_number: u64,
+}
+
+impl WarmLink {
+    /// Answers a lookup from the cache, or fetches and records it
+    ///
+    /// The cache is bypassed while it is pinned to another block than the wrapper.
+    /// Failed lookups are not recorded.
+    pub(super) fn through<V: Clone, E>(
+        link: Option<&Self>,
+        on_hit: impl FnOnce(),
+        get: impl FnOnce(&WarmState) -> Option<V>,
+        fetch: impl FnOnce() -> Result<V, E>,
+        put: impl FnOnce(&mut WarmState, V),
+    ) -> Result<V, E> {
+        let Some(link) = link else {
+            return fetch();
+        };
+        {
+            let state = link.cache.read();
+            if state.block_number == link.block_number {
+                if let Some(value) = get(&state) {
+                    on_hit();
+                    return Ok(value);
+                }
+            }
+        }
+        let value = fetch()?;
+        let mut state = link.cache.write();
+        if state.block_number == link.block_number {
+            put(&mut state, value.clone());
+        }
+        Ok(value)
+    }
+}
@@ -0,0 +1,142 @@
+//! Gas savings of EIP-2930 access lists
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. `ENTRY` calls `STORE`,
+//! which reads three storage slots, so listing `STORE` and those slots pays off while
+//! listing the called contract itself or unused entries does not.
+
+use alloy::{
+    eips::eip2930::{AccessList, AccessListItem},
+    primitives::{address, Address, TxKind, B256, U256},
+};
+use revm::database::Database;
+use revm_trace::{AccessListEntryUse, SimulationTx};
+
+mod common;
+use common::{contract, ether, funded, TestEvm};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const ENTRY: Address = address!("eeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee");
+const STORE: Address = address!("5555555555555555555555555555555555555555");
+const UNUSED: Address = address!("7777777777777777777777777777777777777777");
+
+/// Calls STORE with all gas and stops
+const ENTRY_CODE: &str = "600060006000600060007355555555555555555555555555555555555555555af15000";
+
+/// Loads slots 0, 1 and 2 and stops
+const STORE_CODE: &str = "60005450600154506002545000";
+
+fn evm() -> TestEvm {
+    common::evm(common::backend([
+        (ENTRY, contract(ENTRY_CODE)),
+        (STORE, contract(STORE_CODE)),
+        (SENDER, funded(ether())),
+    ]))
+}
+
+fn tx() -> SimulationTx {
+    SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Call(ENTRY),
+        value: U256::ZERO,
+        data: Default::default(),
+        trace_config: None,
+        return_abi: None,
+    }
+}
+
+fn slots(range: std::ops::Range<u64>) -> Vec<B256> {
+    range.map(|slot| B256::from(U256::from(slot))).collect()
+}
+
+#[test]
+fn test_listing_accessed_state_saves_gas() {
+    let mut evm = evm();
+    let list = AccessList(vec![AccessListItem {
+        address: STORE,
+        storage_keys: slots(0..3),
+    }]);
+    let evaluation = evm.evaluate_access_list(tx(), list).unwrap();
+
+    // One cold call and three cold loads become warm, 100 gas saved each
+    assert_eq!(evaluation.savings, 400);
+    assert_eq!(evaluation.gas_without - evaluation.gas_with, 400);
+    assert!(evaluation.list_is_beneficial);
+    assert_eq!(evaluation.entries.len(), 4);
+    assert!(evaluation.entries.iter().all(|entry| entry.useful));
+    let estimated: i64 = evaluation
+        .entries
+        .iter()
+        .map(|entry| entry.estimated_savings)
+        .sum();
+    assert_eq!(estimated, evaluation.savings);
+}
+
+#[test]
+fn test_unused_entries_cost_gas() {
+    let mut evm = evm();
+    let list = AccessList(vec![
+        AccessListItem {
+            address: STORE,
+            storage_keys: slots(2..4),
+        },
+        // The target is warm anyway
+        AccessListItem {
+            address: ENTRY,
+            storage_keys: vec![],
+        },
+        AccessListItem {
+            address: UNUSED,
+            storage_keys: vec![],
+        },
+    ]);
+    let evaluation = evm.evaluate_access_list(tx(), list).unwrap();
+
+    // STORE +100, slot 2 +100, slot 3 -1900, ENTRY -2400, UNUSED -2400
+    assert_eq!(evaluation.savings, 100 + 100 - 1_900 - 2_400 - 2_400);
+    assert!(!evaluation.list_is_beneficial);
+    let useful: Vec<_> = evaluation
+        .entries
+        .iter()
+        .map(|entry| (entry.address, entry.storage_key, entry.useful))
+        .collect();
+    assert_eq!(
+        useful,
+        vec![
+            (STORE, None, true),
+            (STORE, Some(B256::from(U256::from(2))), true),
+            (STORE, Some(B256::from(U256::from(3))), false),
+            (ENTRY, None, false),
+            (UNUSED, None, false),
+        ]
+    );
+    assert_eq!(
+        evaluation.entries[4],
+        AccessListEntryUse {
+            address: UNUSED,
+            storage_key: None,
+            useful: false,
+            estimated_savings: -2_400,
+        }
+    );
+}
+
+#[test]
+fn test_evaluation_leaves_state_untouched() {
+    let mut evm = evm();
+    let list = AccessList(vec![AccessListItem {
+        address: STORE,
+        storage_keys: slots(0..3),
+    }]);
+    let first = evm.evaluate_access_list(tx(), list.clone()).unwrap();
+    let second = evm.evaluate_access_list(tx(), list).unwrap();
+    assert_eq!(first, second);
+    // Neither run was committed
+    assert_eq!(evm.db().basic(SENDER).unwrap().unwrap().nonce, 0);
+
+    let empty = evm
+        .evaluate_access_list(tx(), AccessList::default())
+        .unwrap();
+    assert_eq!(empty.savings, 0);
+    assert!(!empty.list_is_beneficial);
+    assert!(empty.entries.is_empty());
+}
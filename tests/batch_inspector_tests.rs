@@ -0,0 +1,128 @@
+//! Inspector state accumulated across the transactions of a batch
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use std::collections::HashMap;
+
+use alloy::primitives::{address, hex, Address, TxKind, U256};
+use revm::{
+    context::ContextTr,
+    database::{CacheDB, EmptyDB},
+    interpreter::{interpreter_types::InterpreterTypes, CallInputs, CallOutcome},
+    state::{AccountInfo, Bytecode},
+    Context, Inspector, MainBuilder, MainContext,
+};
+use revm_trace::{
+    traits::{Reset, TraceOutput, TransactionTrace},
+    types::SimulationBatch,
+    SimulationTx, TraceEvm,
+};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const BOB: Address = address!("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+const CONTRACT: Address = address!("c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0");
+
+/// Reads storage slot 0 and stops
+const READER_CODE: &str = "6000545000";
+
+/// Gas spent by call frames per callee, for the current transaction and for the batch
+#[derive(Default)]
+struct GasByCallee {
+    tx: HashMap<Address, u64>,
+    batch: HashMap<Address, u64>,
+}
+
+impl<CTX: ContextTr, INTR: InterpreterTypes> Inspector<CTX, INTR> for GasByCallee {
+    fn call_end(&mut self, _context: &mut CTX, inputs: &CallInputs, outcome: &mut CallOutcome) {
+        let spent = outcome.result.gas.spent();
+        *self.tx.entry(inputs.target_address).or_default() += spent;
+        *self.batch.entry(inputs.target_address).or_default() += spent;
+    }
+}
+
+impl Reset for GasByCallee {
+    fn reset(&mut self) {
+        self.tx.clear();
+        self.batch.clear();
+    }
+
+    /// Keeps the batch totals
+    fn reset_tx(&mut self) {
+        self.tx.clear();
+    }
+
+    fn reset_slot_cache(&mut self) {}
+}
+
+impl TraceOutput for GasByCallee {
+    type Output = HashMap<Address, u64>;
+
+    fn get_output(&self) -> Self::Output {
+        self.tx.clone()
+    }
+}
+
+fn call(to: Address) -> SimulationTx {
+    SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Call(to),
+        value: U256::from(1),
+        data: Default::default(),
+        trace_config: None,
+        return_abi: None,
+    }
+}
+
+fn batch(transactions: Vec<SimulationTx>) -> SimulationBatch {
+    SimulationBatch {
+        transactions,
+        is_stateful: false,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_batch_totals_survive_tx_resets() {
+    let mut backend = CacheDB::new(EmptyDB::default());
+    backend.insert_account_info(
+        SENDER,
+        AccountInfo {
+            balance: U256::from(10).pow(U256::from(18)),
+            ..Default::default()
+        },
+    );
+    let code = Bytecode::new_raw(hex::decode(READER_CODE).unwrap().into());
+    backend.insert_account_info(
+        CONTRACT,
+        AccountInfo {
+            code_hash: code.hash_slow(),
+            code: Some(code),
+            ..Default::default()
+        },
+    );
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(backend));
+    ctx.cfg.disable_base_fee = true;
+    let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(GasByCallee::default()));
+
+    let outputs: Vec<_> = evm
+        .trace_transactions(batch(vec![call(CONTRACT), call(BOB), call(CONTRACT)]))
+        .into_iter()
+        .map(|result| result.unwrap().2)
+        .collect();
+
+    // Per-transaction outputs only cover their own transaction
+    let per_tx = outputs[0][&CONTRACT];
+    assert!(per_tx > 0);
+    assert_eq!(outputs[1].get(&CONTRACT), None);
+    assert_eq!(outputs[2][&CONTRACT], per_tx);
+
+    // Batch totals are still readable after the batch returns
+    assert_eq!(evm.inspector.batch[&CONTRACT], 2 * per_tx);
+    assert_eq!(evm.inspector.batch[&BOB], outputs[1][&BOB]);
+    assert!(evm.inspector.tx.is_empty());
+
+    // The next batch starts over
+    evm.trace_transactions(batch(vec![call(CONTRACT)]));
+    assert_eq!(evm.inspector.batch, HashMap::from([(CONTRACT, per_tx)]));
+}
@@ -0,0 +1,138 @@
+//! Integration tests for block pinning and simulated block advances
+//!
+//! An EVM built from a block hash must run against exactly that block, and
+//! report the same context as one built from the corresponding number.
+//! Advancing the block moves only the execution context, never the pin.
+
+use alloy::primitives::{hex, Address, Bytes, TxKind, B256, U256};
+use revm::database::Database;
+use revm_trace::{
+    create_evm_with_tracer,
+    errors::{EvmError, InitError},
+    BackendKind, EvmBuilder, SimulationBatch, SimulationTx, TransactionTrace, TxInspector,
+};
+
+const ETH_RPC_URL: &str = "https://eth.llamarpc.com";
+const BLOCK_NUMBER: u64 = 18_000_000;
+const SENDER: Address = Address::repeat_byte(0x5e);
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_build_by_block_hash() -> anyhow::Result<()> {
+    let by_number = EvmBuilder::new_alloy(ETH_RPC_URL)
+        .with_block_number(BLOCK_NUMBER)
+        .build()
+        .await?;
+    let expected = by_number.block_context();
+    assert_eq!(expected.number, BLOCK_NUMBER);
+    assert_ne!(
+        expected.hash,
+        B256::ZERO,
+        "Hash must be resolved at build time"
+    );
+
+    let by_hash = EvmBuilder::new_alloy(ETH_RPC_URL)
+        .with_block_hash(expected.hash)
+        .build()
+        .await?;
+    assert_eq!(by_hash.block_context(), expected);
+    assert_eq!(by_hash.block.number, BLOCK_NUMBER);
+    assert_eq!(by_hash.block.timestamp, expected.timestamp);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_unknown_block_hash_fails() -> anyhow::Result<()> {
+    let result = EvmBuilder::new_alloy(ETH_RPC_URL)
+        .with_block_hash(B256::repeat_byte(0x42))
+        .build()
+        .await;
+    assert!(matches!(
+        result,
+        Err(EvmError::Init(InitError::BlockNotFound(_)))
+    ));
+
+    Ok(())
+}
+
+/// Deploys a contract reverting before `unlock` and calls it
+fn timelock_batch(unlock: u64) -> anyhow::Result<SimulationBatch> {
+    // Runtime: if (block.timestamp < unlock) revert();
+    let runtime = format!("67{unlock:016x}421015601357600080fd5b00");
+    let init_code = hex::decode(format!("601580600b6000396000f3{runtime}"))?;
+    let timelock = SENDER.create(0);
+    Ok(SimulationBatch {
+        is_stateful: true,
+        transactions: vec![
+            SimulationTx {
+                caller: SENDER,
+                transact_to: TxKind::Create,
+                value: U256::ZERO,
+                data: init_code.into(),
+                trace_config: None,
+                return_abi: None,
+            },
+            SimulationTx {
+                caller: SENDER,
+                transact_to: TxKind::Call(timelock),
+                value: U256::ZERO,
+                data: Bytes::new(),
+                trace_config: None,
+                return_abi: None,
+            },
+        ],
+        ..Default::default()
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_advance_block_unlocks_timelock() -> anyhow::Result<()> {
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+    assert_eq!(evm.db().basic(SENDER)?.map_or(0, |acc| acc.nonce), 0);
+    let pinned = evm.block_context();
+    let unlock = evm.block.timestamp + 3600;
+
+    let results = evm.trace_transactions(timelock_batch(unlock)?);
+    assert!(results[0].as_ref().is_ok_and(|r| r.0.is_success()));
+    assert!(
+        results[1].as_ref().is_ok_and(|r| !r.0.is_success()),
+        "Call before unlock must revert"
+    );
+
+    // One hour of 12 second blocks
+    evm.advance_block(300, 12);
+    assert_eq!(evm.block.number, pinned.number + 300);
+    assert_eq!(evm.block.timestamp, unlock);
+    assert_eq!(evm.block_context(), pinned, "The fork pin must not move");
+
+    let results = evm.trace_transactions(timelock_batch(unlock)?);
+    assert!(results[0].as_ref().is_ok_and(|r| r.0.is_success()));
+    assert!(
+        results[1].as_ref().is_ok_and(|r| r.0.is_success()),
+        "Call after unlock must succeed"
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_config_view_matches_builder_inputs() -> anyhow::Result<()> {
+    let coinbase = Address::repeat_byte(0xcb);
+    let evm = EvmBuilder::new_alloy(ETH_RPC_URL)
+        .with_block_number(BLOCK_NUMBER)
+        .with_coinbase(coinbase)
+        .build()
+        .await?;
+
+    let config = evm.config();
+    assert_eq!(config.chain_id, 1);
+    assert_eq!(config.block_number, BLOCK_NUMBER);
+    assert_eq!(config.coinbase, coinbase);
+    assert_eq!(config.backend, BackendKind::AlloyDb);
+    assert_eq!(config.pinned_block, evm.block_context());
+    // Relaxations applied by the builder outside strict validation
+    assert!(config.flags.disable_base_fee && config.flags.disable_eip3607);
+    assert!(!config.flags.disable_balance_check);
+
+    Ok(())
+}
@@ -0,0 +1,164 @@
+//! Building EVMs at block tags and re-resolving them
+//!
+//! Tag resolution is checked against a mocked provider; the remaining tests fork
+//! mainnet at its finalized block.
+
+use alloy::{
+    network::AnyNetwork,
+    primitives::{Address, Bloom, TxKind, B256, U256, U64},
+    providers::{mock::Asserter, ProviderBuilder},
+};
+use revm_trace::{
+    errors::{EvmError, InitError, RuntimeError},
+    evm::builder::{get_block_context_by_tag, get_provider},
+    types::{BlockContext, BlockTag},
+    EvmBuilder, SimulationBatch, SimulationTx, TransactionTrace, TxInspector,
+};
+use serde_json::json;
+
+const ETH_RPC_URL: &str = "https://eth.llamarpc.com";
+
+/// Minimal `eth_getBlockByNumber` response
+fn block_json(number: u64) -> serde_json::Value {
+    json!({
+        "hash": B256::repeat_byte(number as u8),
+        "parentHash": B256::ZERO,
+        "sha3Uncles": B256::ZERO,
+        "miner": Address::ZERO,
+        "stateRoot": B256::ZERO,
+        "transactionsRoot": B256::ZERO,
+        "receiptsRoot": B256::ZERO,
+        "logsBloom": Bloom::ZERO,
+        "difficulty": "0x0",
+        "number": U64::from(number),
+        "gasLimit": "0x1c9c380",
+        "gasUsed": "0x0",
+        "timestamp": U64::from(1_700_000_000 + number),
+        "extraData": "0x",
+        "mixHash": B256::ZERO,
+        "nonce": "0x0000000000000000",
+        "uncles": [],
+        "transactions": [],
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_tag_is_recorded() {
+    let asserter = Asserter::new();
+    let provider = ProviderBuilder::new()
+        .network::<AnyNetwork>()
+        .connect_mocked_client(asserter.clone());
+
+    asserter.push_success(&U64::from(1));
+    asserter.push_success(&block_json(100));
+    let block = get_block_context_by_tag(&provider, BlockTag::Finalized)
+        .await
+        .unwrap();
+    assert_eq!(
+        block,
+        BlockContext {
+            number: 100,
+            hash: B256::repeat_byte(100),
+            timestamp: 1_700_000_100,
+            chain_id: 1,
+            tag: Some(BlockTag::Finalized),
+        }
+    );
+}
+
+/// Pre-merge nodes reject `finalized` and `safe` with an RPC error
+#[tokio::test(flavor = "multi_thread")]
+async fn test_unsupported_tag() {
+    let asserter = Asserter::new();
+    let provider = ProviderBuilder::new()
+        .network::<AnyNetwork>()
+        .connect_mocked_client(asserter.clone());
+
+    asserter.push_success(&U64::from(1));
+    asserter.push_failure_msg("finalized block not found");
+    let result = get_block_context_by_tag(&provider, BlockTag::Finalized).await;
+    match result {
+        Err(InitError::UnsupportedBlockTag(message)) => {
+            assert!(message.starts_with("finalized: "), "{message}")
+        }
+        other => panic!("unexpected result: {other:?}"),
+    }
+
+    // Some nodes answer with no block instead
+    asserter.push_success(&U64::from(1));
+    asserter.push_success(&serde_json::Value::Null);
+    let result = get_block_context_by_tag(&provider, BlockTag::Safe).await;
+    assert!(matches!(result, Err(InitError::UnsupportedBlockTag(_))));
+}
+
+#[test]
+fn test_tag_serialization() {
+    assert_eq!(BlockTag::Finalized.to_string(), "finalized");
+    assert_eq!(serde_json::to_value(BlockTag::Safe).unwrap(), json!("safe"));
+
+    // Contexts not built from a tag serialize as before
+    let json = serde_json::to_value(BlockContext::default()).unwrap();
+    assert!(json.get("tag").is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_build_at_finalized_block() -> anyhow::Result<()> {
+    let mut evm = EvmBuilder::new_alloy(ETH_RPC_URL)
+        .with_block_tag(BlockTag::Finalized)
+        .with_tracer(TxInspector::new())
+        .build()
+        .await?;
+    let finalized = evm.block_context();
+    assert_eq!(finalized.tag, Some(BlockTag::Finalized));
+    assert_ne!(finalized.hash, B256::ZERO);
+
+    let latest = EvmBuilder::new_alloy(ETH_RPC_URL)
+        .with_block_tag(BlockTag::Latest)
+        .build()
+        .await?
+        .block_context();
+    assert!(finalized.number <= latest.number);
+
+    let results = evm.trace_transactions(SimulationBatch {
+        transactions: vec![SimulationTx {
+            caller: Address::repeat_byte(0x5e),
+            transact_to: TxKind::Call(Address::repeat_byte(0x22)),
+            value: U256::ZERO,
+            data: Default::default(),
+            trace_config: None,
+            return_abi: None,
+        }],
+        is_stateful: false,
+        ..Default::default()
+    });
+    let (result, _, _) = results[0].as_ref().unwrap();
+    assert!(result.is_success());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_refresh_tag() -> anyhow::Result<()> {
+    let provider = get_provider(ETH_RPC_URL).await?;
+    let mut evm = EvmBuilder::new_alloy(ETH_RPC_URL)
+        .with_block_tag(BlockTag::Finalized)
+        .build()
+        .await?;
+    let before = evm.block_context();
+
+    let after = evm.refresh_tag(&provider).await?;
+    assert_eq!(after.tag, Some(BlockTag::Finalized));
+    assert!(after.number >= before.number);
+    assert_eq!(evm.block_context(), after);
+    assert_eq!(evm.block.number, after.number);
+
+    // EVMs built at a number have no tag to refresh
+    let mut pinned = EvmBuilder::new_alloy(ETH_RPC_URL)
+        .with_block_number(18_000_000)
+        .build()
+        .await?;
+    assert!(matches!(
+        pinned.refresh_tag(&provider).await,
+        Err(EvmError::Runtime(RuntimeError::InvalidInput(_)))
+    ));
+    Ok(())
+}
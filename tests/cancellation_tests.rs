@@ -0,0 +1,117 @@
+//! Cancelling in-flight batches with a `CancellationToken`
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use std::{thread, time::Duration};
+
+use alloy::primitives::{address, Address, TxKind, U256};
+use revm::database::Database;
+use revm_trace::{
+    errors::{EvmError, RuntimeError},
+    traits::TransactionTrace,
+    types::{CancellationToken, SimulationBatch},
+    SimulationTx, TxInspector,
+};
+
+mod common;
+use common::{contract, TestEvm};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const LOOP: Address = address!("1001001001001001001001001001001001001001");
+
+/// Jumps back to its start until it runs out of gas
+const LOOP_CODE: &str = "5b600056";
+
+fn evm() -> TestEvm {
+    common::evm(common::backend([(LOOP, contract(LOOP_CODE))]))
+}
+
+fn batch(to: Address, count: usize, cancellation: Option<CancellationToken>) -> SimulationBatch {
+    let tx = SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Call(to),
+        value: U256::ZERO,
+        data: Default::default(),
+        trace_config: None,
+        return_abi: None,
+    };
+    SimulationBatch {
+        transactions: vec![tx; count],
+        is_stateful: true,
+        cancellation,
+        ..Default::default()
+    }
+}
+
+fn is_cancelled<T>(result: &Result<T, EvmError>) -> bool {
+    matches!(result, Err(EvmError::Runtime(RuntimeError::Cancelled)))
+}
+
+fn nonce(evm: &mut TestEvm) -> u64 {
+    evm.db().basic(SENDER).unwrap().map_or(0, |acc| acc.nonce)
+}
+
+#[test]
+fn test_cancelled_token_skips_every_transaction() {
+    let mut evm = evm();
+    let token = CancellationToken::new();
+    token.cancel();
+
+    let results = evm.trace_transactions(batch(LOOP, 3, Some(token)));
+    assert_eq!(results.len(), 3);
+    assert!(results.iter().all(is_cancelled));
+    assert_eq!(nonce(&mut evm), 0);
+}
+
+#[test]
+fn test_cancellation_stops_running_batch() {
+    let mut evm = evm();
+    let token = CancellationToken::new();
+    let canceller = {
+        let token = token.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            token.cancel();
+        })
+    };
+
+    // Every transaction loops until its gas runs out
+    let results = evm.trace_transactions(batch(LOOP, 1_000, Some(token)));
+    canceller.join().unwrap();
+
+    let finished = results.iter().take_while(|result| result.is_ok()).count();
+    assert!(finished < results.len());
+    assert!(results[finished..].iter().all(is_cancelled));
+    // Only the transactions that finished before the cancellation were committed
+    assert_eq!(nonce(&mut evm), finished as u64);
+}
+
+#[test]
+fn test_evm_is_reusable_after_cancellation() {
+    let mut evm = evm();
+    let token = CancellationToken::new();
+    token.cancel();
+    evm.trace_transactions(batch(LOOP, 2, Some(token)));
+
+    // A transfer to an account without code finishes immediately
+    let results = evm.trace_transactions(batch(Address::repeat_byte(0x22), 2, None));
+    assert!(results.iter().all(|result| result
+        .as_ref()
+        .is_ok_and(|(result, _, _)| result.is_success())));
+    assert_eq!(nonce(&mut evm), 2);
+}
+
+#[test]
+fn test_cancellation_interval() {
+    assert_eq!(
+        TxInspector::new().cancellation_interval(),
+        revm_trace::inspectors::tx_inspector::DEFAULT_CANCELLATION_INTERVAL
+    );
+    assert_eq!(
+        TxInspector::new()
+            .with_cancellation_interval(0)
+            .cancellation_interval(),
+        1
+    );
+}
@@ -0,0 +1,140 @@
+//! Bounded capture of call data in traces
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use alloy::primitives::{address, hex, keccak256, Address, Bytes, TxKind, U256};
+use revm::{
+    context::result::ExecutionResult,
+    database::{CacheDB, EmptyDB},
+    state::{AccountInfo, Bytecode},
+    Context, MainBuilder, MainContext,
+};
+use revm_trace::{
+    inspectors::tx_inspector::TxTraceOutput, traits::TransactionTrace, types::SimulationBatch,
+    SimulationTx, TraceEvm, TxInspector,
+};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const OUTER: Address = address!("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+const INNER: Address = address!("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+
+/// Size of the data returned by INNER
+const RETURN_SIZE: usize = 256 * 1024;
+
+/// Returns 256 KiB of zeros ending in 0x2a
+const INNER_CODE: &str = "602a6203ffe052620400006000f3";
+
+/// Calls INNER four times without copying the return data, so 1 MiB is returned in
+/// total
+const CALL_INNER: &str = "6000600060006000600073bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb5af150";
+
+/// Deploys a contract incrementing storage slot 0 on every call
+const COUNTER_BYTECODE: &str = "600a80600b6000396000f360005460010160005500";
+
+fn install(db: &mut CacheDB<EmptyDB>, address: Address, code: &str) {
+    let code = Bytecode::new_raw(hex::decode(code).unwrap().into());
+    db.insert_account_info(
+        address,
+        AccountInfo {
+            code_hash: code.hash_slow(),
+            code: Some(code),
+            ..Default::default()
+        },
+    );
+}
+
+fn run(
+    inspector: TxInspector,
+    transact_to: TxKind,
+    data: Bytes,
+) -> (ExecutionResult, TxTraceOutput) {
+    let mut backend = CacheDB::new(EmptyDB::default());
+    install(&mut backend, OUTER, &format!("{}00", CALL_INNER.repeat(4)));
+    install(&mut backend, INNER, INNER_CODE);
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(backend));
+    ctx.cfg.disable_base_fee = true;
+    let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(inspector));
+
+    let mut results = evm.trace_transactions(SimulationBatch {
+        is_stateful: false,
+        transactions: vec![SimulationTx {
+            caller: SENDER,
+            transact_to,
+            value: U256::ZERO,
+            data,
+            trace_config: None,
+            return_abi: None,
+        }],
+        ..Default::default()
+    });
+    let (result, _, output) = results.remove(0).expect("transaction executes");
+    assert!(result.is_success(), "{result:?}");
+    (result, output)
+}
+
+fn returned_data() -> Vec<u8> {
+    let mut data = vec![0; RETURN_SIZE];
+    data[RETURN_SIZE - 1] = 0x2a;
+    data
+}
+
+#[test]
+fn test_large_outputs_are_cut() {
+    let inspector = TxInspector::new().with_max_output_capture(1024);
+    let (_, output) = run(inspector, TxKind::Call(OUTER), Bytes::new());
+
+    let json = serde_json::to_vec(&output).unwrap();
+    assert!(json.len() < 64 * 1024, "trace size {}", json.len());
+
+    let root = output.call_trace.unwrap();
+    assert_eq!(root.subtraces.len(), 4);
+    let expected_hash = keccak256(returned_data());
+    for frame in &root.subtraces {
+        assert_eq!(frame.output.len(), 1024);
+        let truncated = frame.output_truncated.expect("output is cut");
+        assert_eq!(truncated.len, RETURN_SIZE);
+        assert_eq!(truncated.keccak256, expected_hash);
+    }
+    assert!(root.output_truncated.is_none());
+}
+
+#[test]
+fn test_top_level_result_is_untouched() {
+    let inspector = TxInspector::new().with_max_output_capture(1024);
+    let (result, output) = run(inspector, TxKind::Call(INNER), Bytes::new());
+
+    assert_eq!(
+        result.output().unwrap().as_ref(),
+        returned_data().as_slice()
+    );
+    let root = output.call_trace.unwrap();
+    assert_eq!(root.output.len(), 1024);
+    assert_eq!(root.output_truncated.unwrap().len, RETURN_SIZE);
+}
+
+#[test]
+fn test_init_code_is_cut() {
+    let init_code = hex::decode(COUNTER_BYTECODE).unwrap();
+    let inspector = TxInspector::new().with_max_input_capture(4);
+    let (_, output) = run(inspector, TxKind::Create, init_code.clone().into());
+
+    let root = output.call_trace.unwrap();
+    assert_eq!(root.input.as_ref(), &init_code[..4]);
+    let truncated = root.input_truncated.unwrap();
+    assert_eq!(truncated.len, init_code.len());
+    assert_eq!(truncated.keccak256, keccak256(&init_code));
+}
+
+#[test]
+fn test_default_capture_limit() {
+    let (_, output) = run(TxInspector::new(), TxKind::Call(OUTER), Bytes::new());
+
+    let root = output.call_trace.unwrap();
+    assert!(root.input_truncated.is_none());
+    // 256 KiB returns exceed the 128 KiB default
+    assert!(root
+        .subtraces
+        .iter()
+        .all(|frame| frame.output.len() == 128 * 1024 && frame.output_truncated.is_some()));
+}
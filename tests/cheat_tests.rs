@@ -0,0 +1,100 @@
+//! Integration tests for balance cheats
+//!
+//! Dealt balances must be readable through `balanceOf` and spendable by a
+//! subsequent transfer, for proxied (USDC) and non-standard (USDT) tokens alike.
+
+use alloy::{
+    primitives::{address, Address, TxKind, U256},
+    sol,
+    sol_types::SolCall,
+};
+use revm_trace::{
+    create_evm_with_tracer,
+    errors::{EvmError, RuntimeError},
+    revm::{context::TxEnv, ExecuteCommitEvm},
+    utils::{
+        balance_utils::query_balance,
+        cheat_utils::{deal, deal_native, find_balance_slot},
+        erc20_utils::query_erc20_balance,
+    },
+    TxInspector,
+};
+
+sol! {
+    function transfer(address to, uint256 amount) public;
+}
+
+const ETH_RPC_URL: &str = "https://eth.llamarpc.com";
+const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+const USDT: Address = address!("dAC17F958D2ee523a2206206994597C13D831ec7");
+const ALICE: Address = address!("1000000000000000000000000000000000000001");
+const BOB: Address = address!("2000000000000000000000000000000000000002");
+
+async fn deal_and_transfer(token: Address) -> anyhow::Result<()> {
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+    let amount = U256::from(1_000_000_000u64);
+
+    deal_native(&mut evm, ALICE, U256::from(10).pow(U256::from(18)))?;
+    deal(&mut evm, token, ALICE, amount)?;
+    assert_eq!(query_erc20_balance(&mut evm, token, ALICE)?, amount);
+
+    let sent = U256::from(250_000_000u64);
+    let tx = TxEnv::builder()
+        .caller(ALICE)
+        .kind(TxKind::Call(token))
+        .data(
+            transferCall {
+                to: BOB,
+                amount: sent,
+            }
+            .abi_encode()
+            .into(),
+        )
+        .chain_id(Some(evm.cfg.chain_id))
+        .nonce(0)
+        .build_fill();
+    let result = evm.transact_commit(tx)?;
+    assert!(
+        result.is_success(),
+        "Transfer of dealt balance failed: {result:?}"
+    );
+    assert_eq!(query_erc20_balance(&mut evm, token, ALICE)?, amount - sent);
+    assert_eq!(query_erc20_balance(&mut evm, token, BOB)?, sent);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_deal_usdc() -> anyhow::Result<()> {
+    // USDC is a proxy: the balance lives in the proxy's storage
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+    let (holder, _) = find_balance_slot(&mut evm, USDC, ALICE)?;
+    assert_eq!(holder, USDC);
+
+    deal_and_transfer(USDC).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_deal_usdt() -> anyhow::Result<()> {
+    // USDT's transfer returns nothing instead of a bool
+    deal_and_transfer(USDT).await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_deal_native() -> anyhow::Result<()> {
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+    deal_native(&mut evm, ALICE, U256::from(42))?;
+    assert_eq!(query_balance(&mut evm, ALICE)?, U256::from(42));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_deal_non_token_fails_with_slot_error() -> anyhow::Result<()> {
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+    // An account without code returns empty output, which no slot can change
+    let result = deal(&mut evm, BOB, ALICE, U256::from(1));
+    assert!(matches!(
+        result,
+        Err(EvmError::Runtime(RuntimeError::BalanceSlotNotFound(_)))
+    ));
+    Ok(())
+}
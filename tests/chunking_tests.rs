@@ -0,0 +1,124 @@
+//! Chunked batch execution
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use alloy::primitives::{address, Address, TxKind, U256};
+use revm::database::Database;
+use revm_trace::{
+    inspectors::tx_inspector::TxTraceOutput,
+    traits::{TraceResult, TransactionTrace},
+    types::SimulationBatch,
+    SimulationTx,
+};
+
+mod common;
+use common::{contract, TestEvm};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const COUNTER: Address = address!("c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0");
+
+/// Increments slot 0 and returns the new value
+const COUNTER_CODE: &str = "6000546001018060005560005260206000f3";
+
+const BATCH_SIZE: usize = 25;
+
+fn evm() -> TestEvm {
+    common::evm(common::backend([(COUNTER, contract(COUNTER_CODE))]))
+}
+
+fn batch(is_stateful: bool) -> SimulationBatch {
+    let tx = SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Call(COUNTER),
+        value: U256::ZERO,
+        data: Default::default(),
+        trace_config: None,
+        return_abi: None,
+    };
+    SimulationBatch {
+        transactions: vec![tx; BATCH_SIZE],
+        is_stateful,
+        ..Default::default()
+    }
+}
+
+/// Checks that `results` equal `expected` and returns the counter value each
+/// transaction returned
+fn assert_same_results(
+    results: &[TraceResult<TxTraceOutput>],
+    expected: &[TraceResult<TxTraceOutput>],
+) -> Vec<U256> {
+    assert_eq!(results.len(), expected.len());
+    results
+        .iter()
+        .zip(expected)
+        .map(|(result, expected)| {
+            let (result, diff, output) = result.as_ref().unwrap();
+            let (expected_result, expected_diff, expected_output) = expected.as_ref().unwrap();
+            assert_eq!(result, expected_result);
+            assert_eq!(diff, expected_diff);
+            assert_eq!(
+                serde_json::to_value(output).unwrap(),
+                serde_json::to_value(expected_output).unwrap()
+            );
+            U256::from_be_slice(result.output().unwrap())
+        })
+        .collect()
+}
+
+fn final_state(evm: &mut TestEvm) -> (U256, u64) {
+    let count = evm.db().storage(COUNTER, U256::ZERO).unwrap();
+    let nonce = evm.db().basic(SENDER).unwrap().map_or(0, |acc| acc.nonce);
+    (count, nonce)
+}
+
+#[test]
+fn test_stateful_chunks_match_unchunked_run() {
+    let mut unchunked = evm();
+    let expected = unchunked.trace_transactions(batch(true));
+
+    let mut chunked = evm().with_chunk_size(10);
+    assert_eq!(chunked.chunk_size(), Some(10));
+    let results = chunked.trace_transactions(batch(true));
+
+    let counts = assert_same_results(&results, &expected);
+    assert_eq!(
+        counts,
+        (1..=BATCH_SIZE as u64).map(U256::from).collect::<Vec<_>>()
+    );
+    assert_eq!(final_state(&mut chunked), final_state(&mut unchunked));
+    assert_eq!(
+        final_state(&mut chunked),
+        (U256::from(BATCH_SIZE), BATCH_SIZE as u64)
+    );
+}
+
+#[test]
+fn test_stateless_chunks_stay_isolated() {
+    let expected = evm().trace_transactions(batch(false));
+    let mut chunked = evm().with_chunk_size(10);
+    let results = chunked.trace_transactions(batch(false));
+
+    let counts = assert_same_results(&results, &expected);
+    assert!(counts.iter().all(|count| *count == U256::from(1)));
+    assert_eq!(final_state(&mut chunked), (U256::ZERO, 0));
+}
+
+#[test]
+fn test_results_are_handed_over_per_chunk() {
+    let mut evm = evm().with_chunk_size(10);
+    let mut chunks = Vec::new();
+    evm.trace_transactions_chunked(batch(true), |first, results| {
+        chunks.push((first, results.len()));
+    });
+    assert_eq!(chunks, vec![(0, 10), (10, 10), (20, 5)]);
+    assert_eq!(final_state(&mut evm).0, U256::from(BATCH_SIZE));
+
+    evm.set_chunk_size(None);
+    let mut chunks = Vec::new();
+    evm.trace_transactions_chunked(batch(true), |first, results| {
+        chunks.push((first, results.len()));
+    });
+    assert_eq!(chunks, vec![(0, BATCH_SIZE)]);
+}
@@ -0,0 +1,141 @@
+//! Branching of simulated state by cloning a `TraceEvm`
+//!
+//! Runs on an in-memory backend, so no RPC access is needed.
+
+use alloy::primitives::{address, hex, Address, Bytes, TxKind, U256};
+use revm::{
+    database::{CacheDB, Database, EmptyDB},
+    primitives::hardfork::SpecId,
+    Context, MainBuilder, MainContext,
+};
+use revm_trace::{
+    types::{LogFilter, SimulationBatch},
+    SimulationTx, TraceEvm, TxInspector,
+};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+
+/// Deploys a contract incrementing storage slot 0 on every call
+const COUNTER_BYTECODE: &str = "600a80600b6000396000f360005460010160005500";
+
+fn tx(transact_to: TxKind, data: Bytes) -> SimulationTx {
+    SimulationTx {
+        caller: SENDER,
+        value: U256::ZERO,
+        data,
+        transact_to,
+        trace_config: None,
+        return_abi: None,
+    }
+}
+
+/// Commits transactions one by one; batches would start from a cleared cache, losing
+/// the state of earlier runs
+fn run(evm: &mut TraceEvm<CacheDB<EmptyDB>, TxInspector>, transactions: Vec<SimulationTx>) {
+    for tx in transactions {
+        let (report, _) = evm
+            .trace_with_prestate(tx, true)
+            .expect("transaction executes");
+        assert!(report.summary.success, "{:?}", report.summary);
+    }
+}
+
+fn counter(evm: &mut TraceEvm<CacheDB<EmptyDB>, TxInspector>, contract: Address) -> U256 {
+    evm.db().storage(contract, U256::ZERO).unwrap()
+}
+
+#[test]
+fn test_clone_branches_diverge() {
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(EmptyDB::default()));
+    ctx.cfg.disable_base_fee = true;
+    let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(TxInspector::new()));
+
+    let contract = SENDER.create(0);
+    run(
+        &mut evm,
+        vec![
+            tx(
+                TxKind::Create,
+                hex::decode(COUNTER_BYTECODE).unwrap().into(),
+            ),
+            tx(TxKind::Call(contract), Bytes::new()),
+        ],
+    );
+    assert_eq!(counter(&mut evm, contract), U256::from(1));
+
+    let mut branch = evm.clone();
+    run(
+        &mut evm,
+        vec![
+            tx(TxKind::Call(contract), Bytes::new()),
+            tx(TxKind::Call(contract), Bytes::new()),
+        ],
+    );
+    run(&mut branch, vec![tx(TxKind::Call(contract), Bytes::new())]);
+
+    assert_eq!(counter(&mut evm, contract), U256::from(3));
+    assert_eq!(counter(&mut branch, contract), U256::from(2));
+    // Accounts diverge as well, and the copy keeps the environment
+    assert_eq!(branch.cfg.chain_id, evm.cfg.chain_id);
+    assert_eq!(evm.db().basic(SENDER).unwrap().unwrap().nonce, 4);
+    assert_eq!(branch.db().basic(SENDER).unwrap().unwrap().nonce, 3);
+}
+
+#[test]
+fn test_clone_keeps_configuration() {
+    let inspector = TxInspector::new()
+        .with_max_trace_depth(7)
+        .with_max_input_capture(11)
+        .with_max_output_capture(13)
+        .with_mapping_resolution(true)
+        .with_max_preimages(17)
+        .with_cancellation_interval(19)
+        .with_log_filter(LogFilter {
+            addresses: Some([SENDER].into()),
+            topics0: None,
+        });
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(EmptyDB::default()));
+    ctx.cfg.spec = SpecId::SHANGHAI;
+    ctx.cfg.disable_nonce_check = true;
+    ctx.cfg.disable_balance_check = true;
+    ctx.cfg.disable_block_gas_limit = true;
+    ctx.cfg.disable_eip3607 = true;
+    ctx.cfg.limit_contract_code_size = Some(0x8000);
+    ctx.block.number = 1_234;
+    ctx.block.gas_limit = 25_000_000;
+    let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(inspector));
+    evm.set_chain_id(8453);
+    evm.set_timestamp(1_700_000_000);
+    evm.set_basefee(7).unwrap();
+    evm.set_coinbase(address!("2000000000000000000000000000000000000002"));
+    evm.enable_metrics(true);
+    evm.set_chunk_size(Some(2));
+
+    let mut branch = evm.clone();
+
+    // Every setting reported by the configuration view, the EVM's own options and
+    // the inspector's limits carry over
+    assert_eq!(branch.config(), evm.config());
+    assert_eq!(branch.block_context(), evm.block_context());
+    assert_eq!(branch.metrics_enabled(), evm.metrics_enabled());
+    assert_eq!(branch.chunk_size(), evm.chunk_size());
+    let (original, copy) = (&evm.inspector, &branch.inspector);
+    assert_eq!(copy.max_trace_depth(), original.max_trace_depth());
+    assert_eq!(copy.max_input_capture(), original.max_input_capture());
+    assert_eq!(copy.max_output_capture(), original.max_output_capture());
+    assert_eq!(copy.mapping_resolution(), original.mapping_resolution());
+    assert_eq!(copy.max_preimages(), original.max_preimages());
+    assert_eq!(
+        copy.cancellation_interval(),
+        original.cancellation_interval()
+    );
+    assert_eq!(copy.log_filter(), original.log_filter());
+
+    // The copy acts on them: its batches record metrics
+    let results = branch.trace_transactions(SimulationBatch {
+        transactions: vec![tx(TxKind::Call(SENDER), Bytes::new())],
+        ..Default::default()
+    });
+    assert!(results.iter().all(Result::is_ok));
+    assert!(branch.take_last_batch_metrics().is_some());
+}
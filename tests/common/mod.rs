@@ -0,0 +1,72 @@
+//! Fixtures shared by the offline integration tests
+//!
+//! Each test binary includes the module with `mod common;` and uses the parts it
+//! needs, so not every helper is used by every binary.
+#![allow(dead_code)]
+
+use alloy::primitives::{hex, Address, U256};
+use revm::{
+    database::{CacheDB, DatabaseRef, EmptyDB},
+    handler::MainnetContext,
+    state::{AccountInfo, Bytecode},
+    Context, MainBuilder, MainContext,
+};
+use revm_trace::{TraceEvm, TxInspector};
+
+/// In-memory state below the `CacheDB` overlay of a test EVM
+pub type Backend = CacheDB<EmptyDB>;
+
+/// EVM with a `CacheDB` overlay on `DB`, a [`Backend`] unless a test brings its own
+pub type TestEvm<DB = Backend, INSP = TxInspector> = TraceEvm<CacheDB<DB>, INSP>;
+
+/// One ether in wei
+pub fn ether() -> U256 {
+    U256::from(10).pow(U256::from(18))
+}
+
+/// Account holding `balance` wei
+pub fn funded(balance: U256) -> AccountInfo {
+    AccountInfo {
+        balance,
+        ..Default::default()
+    }
+}
+
+/// Account running the hex-encoded runtime `code`
+pub fn contract(code: &str) -> AccountInfo {
+    let code = Bytecode::new_raw(hex::decode(code).unwrap().into());
+    AccountInfo {
+        code_hash: code.hash_slow(),
+        code: Some(code),
+        ..Default::default()
+    }
+}
+
+/// Backend holding `accounts`
+pub fn backend(accounts: impl IntoIterator<Item = (Address, AccountInfo)>) -> Backend {
+    let mut backend = CacheDB::new(EmptyDB::default());
+    for (address, info) in accounts {
+        backend.insert_account_info(address, info);
+    }
+    backend
+}
+
+/// EVM with a `TxInspector` on `db`, with the base fee check disabled
+pub fn evm<DB: DatabaseRef>(db: DB) -> TestEvm<DB> {
+    evm_with(db, TxInspector::new(), |_| {})
+}
+
+/// EVM with `inspector` on `db`
+///
+/// The base fee check is disabled before `configure` adjusts the context, so the
+/// block context of the EVM reflects the adjusted block.
+pub fn evm_with<DB: DatabaseRef, INSP>(
+    db: DB,
+    inspector: INSP,
+    configure: impl FnOnce(&mut MainnetContext<CacheDB<DB>>),
+) -> TestEvm<DB, INSP> {
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(db));
+    ctx.cfg.disable_base_fee = true;
+    configure(&mut ctx);
+    TraceEvm::new(ctx.build_mainnet_with_inspector(inspector))
+}
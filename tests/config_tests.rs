@@ -0,0 +1,102 @@
+//! Configuration introspection and setters
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use alloy::primitives::{address, Address, TxKind, U256};
+use revm::primitives::hardfork::SpecId;
+use revm_trace::{
+    errors::{EvmError, RuntimeError},
+    traits::TransactionTrace,
+    types::SimulationBatch,
+    BackendKind, SimulationTx, TxInspector,
+};
+
+mod common;
+use common::{contract, TestEvm};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const PROBE: Address = address!("c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0");
+const COINBASE: Address = address!("cbcbcbcbcbcbcbcbcbcbcbcbcbcbcbcbcbcbcbcb");
+
+/// Returns `block.coinbase`, `block.timestamp` and `block.chainid` as three words
+const PROBE_CODE: &str = "41600052426020524660405260606000f3";
+
+fn evm() -> TestEvm {
+    common::evm_with(
+        common::backend([(PROBE, contract(PROBE_CODE))]),
+        TxInspector::new(),
+        |ctx| {
+            ctx.cfg.chain_id = 10;
+            ctx.block.number = 100;
+            ctx.block.timestamp = 1_000;
+        },
+    )
+}
+
+/// Calls the probe and returns its three words
+fn probe(evm: &mut TestEvm) -> [U256; 3] {
+    let mut results = evm.trace_transactions(SimulationBatch {
+        transactions: vec![SimulationTx {
+            caller: SENDER,
+            transact_to: TxKind::Call(PROBE),
+            value: U256::ZERO,
+            data: Default::default(),
+            trace_config: None,
+            return_abi: None,
+        }],
+        is_stateful: false,
+        ..Default::default()
+    });
+    let (result, _, _) = results.remove(0).unwrap();
+    let output = result.output().unwrap();
+    [0, 1, 2].map(|word| U256::from_be_slice(&output[word * 32..(word + 1) * 32]))
+}
+
+#[test]
+fn test_view_reflects_context() {
+    let evm = evm();
+    let config = evm.config();
+    assert_eq!(config.chain_id, 10);
+    assert_eq!(config.spec, SpecId::default());
+    assert_eq!(config.block_number, 100);
+    assert_eq!(config.timestamp, 1_000);
+    assert!(config.flags.disable_base_fee);
+    assert!(!config.flags.disable_nonce_check);
+    assert_eq!(config.backend, BackendKind::Custom);
+
+    let json = serde_json::to_value(&config).unwrap();
+    assert_eq!(json["chain_id"], 10);
+    assert_eq!(json["backend"], "Custom");
+}
+
+#[test]
+fn test_setters_apply_to_execution() {
+    let mut evm = evm();
+    assert_eq!(
+        probe(&mut evm),
+        [U256::ZERO, U256::from(1_000), U256::from(10)]
+    );
+
+    evm.set_coinbase(COINBASE);
+    evm.set_timestamp(2_000);
+    evm.set_chain_id(137);
+    assert_eq!(
+        probe(&mut evm),
+        [
+            U256::from_be_slice(COINBASE.as_slice()),
+            U256::from(2_000),
+            U256::from(137),
+        ]
+    );
+    let config = evm.config();
+    assert_eq!(config.coinbase, COINBASE);
+    assert_eq!(config.pinned_block.chain_id, 137);
+
+    // Base fees are not charged here, so setting one is refused
+    assert!(matches!(
+        evm.set_basefee(7),
+        Err(EvmError::Runtime(RuntimeError::InvalidInput(_)))
+    ));
+    assert_eq!(evm.config().basefee, 0);
+}
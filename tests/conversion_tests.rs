@@ -0,0 +1,85 @@
+//! Conversion of alloy transaction requests and signed transactions into `SimulationTx`
+
+use alloy::{
+    consensus::TxEnvelope,
+    eips::eip2718::Decodable2718,
+    hex,
+    primitives::{address, bytes, Address, TxKind, U256},
+    rpc::types::TransactionRequest,
+};
+use revm_trace::{errors::ConversionError, SimulationBatch, SimulationTx};
+
+const SENDER: Address = address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+
+/// EIP-1559 `deposit()` of 0.1 ETH into WETH on mainnet, signed by the first
+/// Hardhat/Anvil development account
+const SIGNED_DEPOSIT: &str = "02f8770180843b9aca008506fc23ac0082ea6094c02aaa39b223fe8d0a0e5c4f27ead9083c756cc288016345785d8a000084d0e30db0c001a0bb50e2d89a4ed70663d080659fe0ad4b9bc3e06c17a227433966cb59ceee020da06a5dab35493a490931ac0d6f7a94f81309099b025f3e65ffcd9ac77f306ce40b";
+
+#[test]
+fn test_eip1559_request_conversion() {
+    let request = TransactionRequest::default()
+        .from(SENDER)
+        .to(WETH)
+        .value(U256::from(10u64.pow(17)))
+        .input(bytes!("d0e30db0").into())
+        .max_fee_per_gas(30_000_000_000)
+        .max_priority_fee_per_gas(1_000_000_000)
+        .gas_limit(60_000);
+
+    // Requests arrive as JSON in services; the conversion must not depend on how
+    // the request was built
+    let json = serde_json::to_string(&request).unwrap();
+    let request: TransactionRequest = serde_json::from_str(&json).unwrap();
+
+    let tx = SimulationTx::try_from(request.clone()).unwrap();
+    assert_eq!(tx.caller, SENDER);
+    assert_eq!(tx.transact_to, TxKind::Call(WETH));
+    assert_eq!(tx.value, U256::from(10u64.pow(17)));
+    assert_eq!(tx.data, bytes!("d0e30db0"));
+
+    let batch = SimulationBatch::from_requests(vec![request.clone(), request], true).unwrap();
+    assert_eq!(batch.transactions.len(), 2);
+    assert!(batch.is_stateful);
+}
+
+#[test]
+fn test_request_conversion_errors() {
+    let create = SimulationTx::try_from(TransactionRequest::default().from(SENDER)).unwrap();
+    assert_eq!(create.transact_to, TxKind::Create);
+
+    assert_eq!(
+        SimulationTx::try_from(TransactionRequest::default().to(WETH)).unwrap_err(),
+        ConversionError::MissingSender
+    );
+    let mut deposit = TransactionRequest::default().from(SENDER).to(WETH);
+    deposit.transaction_type = Some(0x7e);
+    assert_eq!(
+        SimulationTx::try_from(deposit).unwrap_err(),
+        ConversionError::UnsupportedType(0x7e)
+    );
+    let mut blob = TransactionRequest::default().from(SENDER).to(WETH);
+    blob.blob_versioned_hashes = Some(vec![Default::default()]);
+    assert_eq!(
+        SimulationBatch::from_requests(vec![blob], false).unwrap_err(),
+        ConversionError::UnsupportedType(0x03)
+    );
+}
+
+#[test]
+fn test_signed_transaction_conversion() {
+    let raw = hex::decode(SIGNED_DEPOSIT).unwrap();
+    let envelope = TxEnvelope::decode_2718(&mut raw.as_slice()).unwrap();
+
+    let tx = SimulationTx::from_envelope(&envelope).unwrap();
+    assert_eq!(tx.caller, SENDER);
+    assert_eq!(tx.transact_to, TxKind::Call(WETH));
+    assert_eq!(tx.value, U256::from(10u64.pow(17)));
+    assert_eq!(tx.data, bytes!("d0e30db0"));
+
+    assert_eq!(SimulationTx::from_raw(&raw).unwrap().caller, SENDER);
+    assert!(matches!(
+        SimulationTx::from_raw(&raw[..raw.len() - 1]),
+        Err(ConversionError::Decode(_))
+    ));
+}
@@ -0,0 +1,91 @@
+//! Pre-deployment diagnosis of top-level creations
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use alloy::primitives::{address, hex, Address, TxKind, U256};
+use revm::context::result::{ExecutionResult, HaltReason};
+use revm_trace::{
+    inspectors::tx_inspector::TxTraceOutput,
+    traits::TransactionTrace,
+    types::{ContractChange, SimulationBatch, SimulationWarning},
+    SimulationTx,
+};
+
+mod common;
+use common::{contract, evm, Backend, TestEvm};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+
+/// Deploys a contract incrementing storage slot 0 on every call
+const COUNTER_BYTECODE: &str = "600a80600b6000396000f360005460010160005500";
+
+fn deploy(evm: &mut TestEvm) -> (ExecutionResult, TxTraceOutput) {
+    let mut results = evm.trace_transactions(SimulationBatch {
+        is_stateful: true,
+        transactions: vec![SimulationTx {
+            caller: SENDER,
+            transact_to: TxKind::Create,
+            value: U256::ZERO,
+            data: hex::decode(COUNTER_BYTECODE).unwrap().into(),
+            trace_config: None,
+            return_abi: None,
+        }],
+        ..Default::default()
+    });
+    let (result, _, output) = results.remove(0).expect("transaction executes");
+    (result, output)
+}
+
+#[test]
+fn test_create_collision_is_reported() {
+    let predicted = SENDER.create(0);
+    let backend = common::backend([(predicted, contract("00"))]);
+
+    let mut evm = evm(backend);
+    let (result, output) = deploy(&mut evm);
+    assert_eq!(
+        output.warnings,
+        vec![SimulationWarning::AddressCollision { address: predicted }]
+    );
+    assert!(
+        matches!(
+            result,
+            ExecutionResult::Halt {
+                reason: HaltReason::CreateCollision,
+                ..
+            }
+        ),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn test_clean_deployment_has_no_warnings() {
+    let mut evm = evm(Backend::default());
+    let (result, output) = deploy(&mut evm);
+    assert!(result.is_success());
+    assert!(output.warnings.is_empty());
+}
+
+#[test]
+fn test_nonce_mismatch_is_reported() {
+    let mut evm = evm(Backend::default());
+    evm.ctx.cfg.disable_nonce_check = true;
+    evm.set_tx_hook(Box::new(|tx, _| tx.nonce = 7));
+
+    let (result, output) = deploy(&mut evm);
+    assert!(result.is_success(), "{result:?}");
+    assert_eq!(
+        output.warnings,
+        vec![SimulationWarning::NonceMismatchForCreate {
+            expected: 0,
+            actual: 7
+        }]
+    );
+    // The address still follows from the account nonce
+    assert!(output.contract_changes.contains(&ContractChange::Created {
+        address: SENDER.create(0),
+        code_len: 10,
+    }));
+}
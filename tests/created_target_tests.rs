@@ -0,0 +1,160 @@
+//! Calls to contracts created earlier in a batch with `TargetRef::CreatedBy` targets
+//!
+//! Runs on an in-memory backend, so no RPC access is needed.
+
+use alloy::primitives::{address, hex, Address, Bytes, TxKind, U256};
+use revm::{
+    database::{CacheDB, Database, EmptyDB},
+    Context, MainBuilder, MainContext,
+};
+use revm_trace::{
+    errors::{EvmError, RuntimeError, UnresolvedTargetReason},
+    traits::{TraceResult, TransactionTrace},
+    types::{SimulationBatch, TargetRef},
+    SimulationTx, TraceEvm, TxInspector,
+};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const BOB: Address = address!("b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0");
+
+/// Deploys a counter incrementing slot 0 on every call
+const COUNTER_BYTECODE: &str = "600a80600b6000396000f360005460010160005500";
+
+/// Init code reverting right away
+const REVERTING_INIT_CODE: &str = "60006000fd";
+
+fn tx(transact_to: TxKind, data: &str) -> SimulationTx {
+    SimulationTx {
+        caller: SENDER,
+        transact_to,
+        value: U256::ZERO,
+        data: Bytes::from(hex::decode(data).unwrap()),
+        trace_config: None,
+        return_abi: None,
+    }
+}
+
+/// Call whose target the batch replaces
+fn retargeted() -> SimulationTx {
+    tx(TxKind::Call(Address::ZERO), "")
+}
+
+/// Runs `transactions` statefully, each `(index, tx_index)` of `targets` making the
+/// transaction at `index` call the contract created by the one at `tx_index`
+fn run(
+    transactions: Vec<SimulationTx>,
+    targets: &[(usize, usize)],
+) -> (
+    TraceEvm<CacheDB<EmptyDB>, TxInspector>,
+    Vec<TraceResult<revm_trace::inspectors::tx_inspector::TxTraceOutput>>,
+) {
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(EmptyDB::default()));
+    ctx.cfg.disable_base_fee = true;
+    let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(TxInspector::new()));
+    let batch = targets.iter().fold(
+        SimulationBatch {
+            transactions,
+            is_stateful: true,
+            ..Default::default()
+        },
+        |batch, &(index, tx_index)| batch.with_target(index, TargetRef::CreatedBy { tx_index }),
+    );
+    let results = evm.trace_transactions(batch);
+    (evm, results)
+}
+
+fn unresolved<T>(result: &Result<T, EvmError>) -> Option<(usize, UnresolvedTargetReason)> {
+    match result {
+        Err(EvmError::Runtime(RuntimeError::UnresolvedTarget { tx_index, reason })) => {
+            Some((*tx_index, *reason))
+        }
+        _ => None,
+    }
+}
+
+#[test]
+fn test_created_by_follows_the_actual_nonce() {
+    // The transfer consumes nonce 0, so the counter lands at `SENDER.create(1)`
+    let (mut evm, results) = run(
+        vec![
+            tx(TxKind::Call(BOB), ""),
+            tx(TxKind::Create, COUNTER_BYTECODE),
+            retargeted(),
+            retargeted(),
+        ],
+        &[(2, 1), (3, 1)],
+    );
+    assert!(results.iter().all(|result| result
+        .as_ref()
+        .is_ok_and(|(result, _, _)| result.is_success())));
+
+    let executed = evm.take_last_executed_txs();
+    let counter = SENDER.create(1);
+    assert_eq!(executed[2].as_ref().unwrap().kind, TxKind::Call(counter));
+    assert_eq!(
+        evm.db().storage(counter, U256::ZERO).unwrap(),
+        U256::from(2)
+    );
+}
+
+#[test]
+fn test_reference_to_failed_creation() {
+    let (_, results) = run(
+        vec![tx(TxKind::Create, REVERTING_INIT_CODE), retargeted()],
+        &[(1, 0)],
+    );
+    assert!(results[0]
+        .as_ref()
+        .is_ok_and(|(result, _, _)| !result.is_success()));
+    assert_eq!(
+        unresolved(&results[1]),
+        Some((0, UnresolvedTargetReason::Failed))
+    );
+}
+
+#[test]
+fn test_reference_to_call() {
+    let (_, results) = run(
+        vec![
+            tx(TxKind::Call(BOB), ""),
+            retargeted(),
+            tx(TxKind::Create, COUNTER_BYTECODE),
+            retargeted(),
+            retargeted(),
+        ],
+        // Resolved targets are calls, not creations
+        &[(1, 0), (3, 2), (4, 3)],
+    );
+    assert_eq!(
+        unresolved(&results[1]),
+        Some((0, UnresolvedTargetReason::NotACreation))
+    );
+    assert!(results[3].is_ok());
+    assert_eq!(
+        unresolved(&results[4]),
+        Some((3, UnresolvedTargetReason::NotACreation))
+    );
+}
+
+#[test]
+fn test_reference_to_later_transaction() {
+    let (mut evm, results) = run(
+        vec![
+            retargeted(),
+            tx(TxKind::Create, COUNTER_BYTECODE),
+            retargeted(),
+        ],
+        &[(0, 1), (2, 0)],
+    );
+    assert_eq!(
+        unresolved(&results[0]),
+        Some((1, UnresolvedTargetReason::NotExecutedBefore))
+    );
+    // The unresolved transaction did not execute, so it consumed no nonce
+    assert!(results[1].is_ok());
+    assert_eq!(evm.db().basic(SENDER).unwrap().unwrap().nonce, 1);
+    assert_eq!(
+        unresolved(&results[2]),
+        Some((0, UnresolvedTargetReason::NotACreation))
+    );
+}
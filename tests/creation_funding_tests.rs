@@ -0,0 +1,195 @@
+//! Native value sent with contract creations
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use alloy::primitives::{address, hex, keccak256, Address, TxKind, B256, U256};
+use revm_trace::{
+    analysis::verify_transfer_consistency,
+    inspectors::tx_inspector::TxTraceOutput,
+    report::{BalanceSnapshot, SimulationReport},
+    traits::TransactionTrace,
+    types::{CreationFunding, SimulationBatch, NATIVE_TOKEN_ADDRESS},
+    utils::balance_utils::query_balance,
+    SimulationTx,
+};
+
+mod common;
+use common::{ether, funded, TestEvm};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+
+/// Deploys a contract without code: `PUSH1 0 PUSH1 0 RETURN`
+const EMPTY_INIT_CODE: &str = "60006000f3";
+
+/// Constructor deploying `EMPTY_INIT_CODE` twice: with CREATE and no value, then with
+/// CREATE2, salt 0 and 40 wei
+const FACTORY_INIT_CODE: &str = concat!(
+    // MSTORE the child init code, leaving it at memory[27..32]
+    "6460006000f3600052",
+    // CREATE(value 0, offset 27, size 5)
+    "6005601b6000f050",
+    // CREATE2(value 40, offset 27, size 5, salt 0)
+    "60006005601b6028f550",
+    // RETURN empty code
+    "60006000f3",
+);
+
+/// Constructor reverting without data: `PUSH1 0 PUSH1 0 REVERT`
+const REVERTING_INIT_CODE: &str = "60006000fd";
+
+fn evm() -> TestEvm {
+    common::evm(common::backend([(SENDER, funded(ether()))]))
+}
+
+/// Deploys `init_code` from `SENDER` with `value`, returning whether the transaction
+/// succeeded and its trace
+fn deploy(
+    evm: &mut TestEvm,
+    init_code: &str,
+    value: u64,
+    holders: &[Address],
+) -> (bool, TxTraceOutput) {
+    let tx = SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Create,
+        value: U256::from(value),
+        data: hex::decode(init_code).unwrap().into(),
+        trace_config: None,
+        return_abi: None,
+    };
+    let mut results = evm.trace_transactions(SimulationBatch {
+        transactions: vec![tx.clone()],
+        is_stateful: true,
+        ..Default::default()
+    });
+    let (result, diff, output) = results.remove(0).unwrap();
+
+    // Gas is free, so native balances only move with the recorded transfers
+    let balances = holders
+        .iter()
+        .map(|&holder| BalanceSnapshot {
+            token: NATIVE_TOKEN_ADDRESS,
+            holder,
+            balance: if holder == SENDER {
+                ether()
+            } else {
+                U256::ZERO
+            },
+        })
+        .collect();
+    let report = SimulationReport::new(tx, &result, diff, output.clone()).with_balances(balances);
+    assert_eq!(verify_transfer_consistency(evm, &report), vec![]);
+
+    (result.is_success(), output)
+}
+
+/// Native transfer as `(from, to, value, failed)`
+type Record = (Address, Option<Address>, U256, bool);
+
+fn native(from: Address, to: Option<Address>, value: u64) -> Record {
+    (from, to, U256::from(value), to.is_none())
+}
+
+/// Records of the transaction's transfers, checking they are native and numbered in
+/// order
+fn transfers(output: &TxTraceOutput) -> Vec<Record> {
+    output
+        .asset_transfers
+        .iter()
+        .enumerate()
+        .map(|(index, transfer)| {
+            assert!(transfer.is_native_token());
+            assert_eq!(transfer.transfer_index, index);
+            (transfer.from, transfer.to, transfer.value, transfer.failed)
+        })
+        .collect()
+}
+
+#[test]
+fn test_funded_create() {
+    let mut evm = evm();
+    let created = SENDER.create(0);
+
+    let (success, output) = deploy(&mut evm, EMPTY_INIT_CODE, 1_000, &[SENDER, created]);
+    assert!(success);
+    assert_eq!(
+        transfers(&output),
+        vec![native(SENDER, Some(created), 1_000)]
+    );
+    assert_eq!(
+        output.creation_funding,
+        vec![CreationFunding {
+            created: Some(created),
+            funder: SENDER,
+            value: U256::from(1_000),
+            success: true,
+        }]
+    );
+    assert_eq!(query_balance(&mut evm, created).unwrap(), U256::from(1_000));
+}
+
+#[test]
+fn test_factory_funds_nested_create2() {
+    let mut evm = evm();
+    let factory = SENDER.create(0);
+    let unfunded = factory.create(1);
+    let child = factory.create2(B256::ZERO, keccak256(hex::decode(EMPTY_INIT_CODE).unwrap()));
+
+    let (success, output) = deploy(
+        &mut evm,
+        FACTORY_INIT_CODE,
+        100,
+        &[SENDER, factory, unfunded, child],
+    );
+    assert!(success);
+    // The unfunded creation in between must not take the factory's endowment
+    assert_eq!(
+        transfers(&output),
+        vec![
+            native(SENDER, Some(factory), 100),
+            native(factory, Some(child), 40),
+        ]
+    );
+    assert_eq!(
+        output.creation_funding,
+        vec![
+            CreationFunding {
+                created: Some(factory),
+                funder: SENDER,
+                value: U256::from(100),
+                success: true,
+            },
+            CreationFunding {
+                created: Some(child),
+                funder: factory,
+                value: U256::from(40),
+                success: true,
+            },
+        ]
+    );
+    assert_eq!(query_balance(&mut evm, factory).unwrap(), U256::from(60));
+    assert_eq!(query_balance(&mut evm, child).unwrap(), U256::from(40));
+    assert_eq!(query_balance(&mut evm, unfunded).unwrap(), U256::ZERO);
+}
+
+#[test]
+fn test_funded_create_with_reverting_constructor() {
+    let mut evm = evm();
+    let address = SENDER.create(0);
+
+    let (success, output) = deploy(&mut evm, REVERTING_INIT_CODE, 1_000, &[SENDER, address]);
+    assert!(!success);
+    assert_eq!(transfers(&output), vec![native(SENDER, None, 1_000)]);
+    assert_eq!(
+        output.creation_funding,
+        vec![CreationFunding {
+            created: None,
+            funder: SENDER,
+            value: U256::from(1_000),
+            success: false,
+        }]
+    );
+    assert_eq!(query_balance(&mut evm, SENDER).unwrap(), ether());
+    assert_eq!(query_balance(&mut evm, address).unwrap(), U256::ZERO);
+}
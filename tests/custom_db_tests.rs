@@ -0,0 +1,239 @@
+//! Batches on a database that is not a `CacheDB`
+//!
+//! `ToyDb` is a minimal in-memory state that commits straight into itself and resets
+//! to the state it was created with, as a persistent snapshot would. No RPC access is
+//! needed.
+
+use std::{collections::HashMap, convert::Infallible};
+
+use alloy::primitives::{address, Address, TxKind, B256, U256};
+use revm::{
+    context::BlockEnv,
+    database::{Database, DatabaseCommit},
+    state::{AccountInfo, Bytecode, EvmState},
+};
+use revm_trace::{
+    traits::{ResetDB, TransactionTrace},
+    types::{SimulationBatch, StateOverride, StorageDiff, TokenType},
+    EvmBuilder, SimulationTx, TraceEvm, TxInspector,
+};
+
+mod common;
+use common::{contract, ether, funded};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const RECIPIENT: Address = address!("2000000000000000000000000000000000000002");
+const COUNTER: Address = address!("c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0");
+
+/// Increments slot 0 and returns the new value
+const COUNTER_CODE: &str = "6000546001018060005560005260206000f3";
+
+#[derive(Debug, Clone, Default)]
+struct ToyState {
+    accounts: HashMap<Address, AccountInfo>,
+    storage: HashMap<(Address, U256), U256>,
+    contracts: HashMap<B256, Bytecode>,
+}
+
+/// In-memory database keeping the state it was created with for resets
+#[derive(Debug, Default)]
+struct ToyDb {
+    baseline: ToyState,
+    state: ToyState,
+}
+
+impl ToyDb {
+    fn insert_account(&mut self, address: Address, info: AccountInfo) {
+        if let Some(code) = &info.code {
+            self.baseline.contracts.insert(info.code_hash, code.clone());
+        }
+        self.baseline.accounts.insert(address, info);
+        self.state = self.baseline.clone();
+    }
+}
+
+impl Database for ToyDb {
+    type Error = Infallible;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        Ok(self.state.accounts.get(&address).cloned())
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        Ok(self
+            .state
+            .contracts
+            .get(&code_hash)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        Ok(self
+            .state
+            .storage
+            .get(&(address, index))
+            .copied()
+            .unwrap_or_default())
+    }
+
+    fn block_hash(&mut self, _number: u64) -> Result<B256, Self::Error> {
+        Ok(B256::ZERO)
+    }
+}
+
+impl DatabaseCommit for ToyDb {
+    fn commit(&mut self, changes: EvmState) {
+        for (address, account) in changes {
+            if !account.is_touched() {
+                continue;
+            }
+            if account.is_selfdestructed() || account.is_created() {
+                self.state.storage.retain(|(owner, _), _| *owner != address);
+            }
+            if account.is_selfdestructed() {
+                self.state.accounts.remove(&address);
+                continue;
+            }
+            if let Some(code) = &account.info.code {
+                self.state
+                    .contracts
+                    .insert(account.info.code_hash, code.clone());
+            }
+            for (slot, value) in account.storage {
+                self.state
+                    .storage
+                    .insert((address, slot), value.present_value);
+            }
+            self.state.accounts.insert(address, account.info);
+        }
+    }
+}
+
+impl ResetDB for ToyDb {
+    fn reset_db(&mut self) {
+        self.state = self.baseline.clone();
+    }
+}
+
+fn evm() -> TraceEvm<ToyDb, TxInspector> {
+    let mut db = ToyDb::default();
+    db.insert_account(SENDER, funded(ether()));
+    db.insert_account(COUNTER, contract(COUNTER_CODE));
+    // Not `common::evm`, which puts a `CacheDB` in front of the database
+    let block_env = BlockEnv {
+        number: 100,
+        timestamp: 1_700_000_000,
+        ..Default::default()
+    };
+    EvmBuilder::new_custom_db(db, 1, block_env)
+        .with_tracer(TxInspector::new())
+        .build()
+}
+
+fn tx(to: Address, value: U256) -> SimulationTx {
+    SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Call(to),
+        value,
+        data: Default::default(),
+        trace_config: None,
+        return_abi: None,
+    }
+}
+
+fn batch(
+    transactions: Vec<SimulationTx>,
+    is_stateful: bool,
+    overrides: Option<StateOverride>,
+) -> SimulationBatch {
+    SimulationBatch {
+        transactions,
+        is_stateful,
+        overrides,
+        ..Default::default()
+    }
+}
+
+/// Old and new value of the counter slot in a storage diff
+fn counter_change(diff: &StorageDiff) -> (U256, U256) {
+    let access = &diff[&COUNTER][0];
+    (access.old_value, access.new_value)
+}
+
+#[test]
+fn test_build_without_provider() {
+    let evm = evm();
+    let block = evm.block_context();
+    assert_eq!(block.number, 100);
+    assert_eq!(block.timestamp, 1_700_000_000);
+    assert_eq!(block.chain_id, 1);
+    assert_eq!(block.hash, B256::ZERO);
+    assert!(evm.cfg.disable_base_fee);
+}
+
+#[test]
+fn test_stateful_batch() {
+    let mut evm = evm();
+    let results = evm.trace_transactions(batch(
+        vec![
+            tx(RECIPIENT, U256::from(1_000)),
+            tx(COUNTER, U256::ZERO),
+            tx(COUNTER, U256::ZERO),
+        ],
+        true,
+        None,
+    ));
+
+    let (result, _, output) = results[0].as_ref().unwrap();
+    assert!(result.is_success());
+    assert_eq!(output.asset_transfers.len(), 1);
+    assert_eq!(output.asset_transfers[0].token_type, TokenType::Native);
+    assert_eq!(output.asset_transfers[0].to, Some(RECIPIENT));
+
+    // The second call sees the state committed by the first
+    let (_, diff, _) = results[1].as_ref().unwrap();
+    assert_eq!(counter_change(diff), (U256::ZERO, U256::from(1)));
+    let (_, diff, output) = results[2].as_ref().unwrap();
+    assert_eq!(counter_change(diff), (U256::from(1), U256::from(2)));
+    assert!(output.executed_code_addresses.contains(&COUNTER));
+
+    // Committed into the database itself
+    assert_eq!(
+        evm.db().storage(COUNTER, U256::ZERO).unwrap(),
+        U256::from(2)
+    );
+    assert_eq!(
+        evm.db().basic(RECIPIENT).unwrap().unwrap().balance,
+        U256::from(1_000)
+    );
+}
+
+#[test]
+fn test_batches_start_from_reset_state() {
+    let mut evm = evm();
+    evm.trace_transactions(batch(vec![tx(COUNTER, U256::ZERO)], true, None));
+
+    let results = evm.trace_transactions(batch(vec![tx(COUNTER, U256::ZERO)], false, None));
+    let (_, diff, _) = results[0].as_ref().unwrap();
+    assert_eq!(counter_change(diff), (U256::ZERO, U256::from(1)));
+}
+
+#[test]
+fn test_overrides_are_committed() {
+    let mut evm = evm();
+    let overrides = StateOverride {
+        storages: [(COUNTER, vec![(U256::ZERO, U256::from(41))])]
+            .into_iter()
+            .collect(),
+        balances: [(RECIPIENT, U256::from(7))].into_iter().collect(),
+    };
+    let results =
+        evm.trace_transactions(batch(vec![tx(COUNTER, U256::ZERO)], false, Some(overrides)));
+    let (_, diff, _) = results[0].as_ref().unwrap();
+    assert_eq!(counter_change(diff), (U256::from(41), U256::from(42)));
+    assert_eq!(
+        evm.db().basic(RECIPIENT).unwrap().unwrap().balance,
+        U256::from(7)
+    );
+}
@@ -0,0 +1,82 @@
+//! Integration tests for database lookup statistics
+//!
+//! Documents the caching behavior of the `CacheDB` overlay: within a batch, state
+//! fetched by one transaction is reused by the next, so repeating a transaction is
+//! answered by the overlay and causes almost no additional upstream requests.
+
+use alloy::{
+    primitives::{address, Address, TxKind, U256},
+    sol,
+    sol_types::SolCall,
+};
+use revm_trace::{
+    create_evm_with_tracer, SimulationBatch, SimulationTx, TransactionTrace, TxInspector,
+};
+
+sol! {
+    function balanceOf(address owner) public returns (uint256);
+}
+
+const ETH_RPC_URL: &str = "https://eth.llamarpc.com";
+const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+
+fn balance_query() -> SimulationTx {
+    SimulationTx {
+        caller: address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045"),
+        transact_to: TxKind::Call(USDC),
+        value: U256::ZERO,
+        data: balanceOfCall {
+            owner: address!("28C6c06298d514Db089934071355E5743bf21d60"),
+        }
+        .abi_encode()
+        .into(),
+        trace_config: None,
+        return_abi: None,
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_repeated_transaction_hits_cache() -> anyhow::Result<()> {
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+
+    // A single transaction against a cold cache
+    evm.reset_db_stats();
+    let results = evm.trace_transactions(SimulationBatch {
+        transactions: vec![balance_query()],
+        is_stateful: false,
+        ..Default::default()
+    });
+    assert!(results.iter().all(|r| r.is_ok()));
+    let single = evm.db_stats();
+    assert!(single.upstream_calls > 0, "Cold run must fetch state");
+    assert!(single.storage_calls > 0, "balanceOf reads storage");
+    assert!(single.cache_misses > 0, "Cold run must miss the overlay");
+    assert!(single.cache_misses >= single.basic_calls + single.storage_calls);
+
+    // The same transaction twice: the second run is served by the cache
+    evm.reset_db_stats();
+    let results = evm.trace_transactions(SimulationBatch {
+        transactions: vec![balance_query(), balance_query()],
+        is_stateful: false,
+        ..Default::default()
+    });
+    assert!(results.iter().all(|r| r.is_ok()));
+    let double = evm.db_stats();
+    assert!(double.upstream_calls >= single.upstream_calls);
+    let second_run_misses = double.upstream_calls - single.upstream_calls;
+    assert!(
+        second_run_misses * 4 < single.upstream_calls,
+        "Second identical transaction should mostly hit the cache: {single:?} vs {double:?}"
+    );
+    let second_run_cache_misses = double.cache_misses - single.cache_misses;
+    assert!(
+        second_run_cache_misses * 4 < single.cache_misses,
+        "Second identical transaction should mostly be answered by the overlay: {single:?} vs {double:?}"
+    );
+    assert!(
+        double.cache_hits > single.cache_hits,
+        "The overlay must answer the second transaction's lookups: {single:?} vs {double:?}"
+    );
+
+    Ok(())
+}
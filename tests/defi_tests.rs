@@ -0,0 +1,86 @@
+//! Integration tests for pool snapshot utilities
+//!
+//! Reads well-known mainnet pools through the Multicall-backed helpers and checks
+//! that per-pool failures and simulated state changes are reported correctly.
+
+use alloy::primitives::{address, Address, U256};
+use revm_trace::{
+    create_evm_with_tracer,
+    errors::PoolError,
+    utils::defi_utils::{get_v2_reserves, get_v3_slot0, snapshot_pools, PoolProtocol, PoolSpec},
+    TxInspector,
+};
+
+const ETH_RPC_URL: &str = "https://eth.llamarpc.com";
+/// Uniswap V2 USDC/WETH pair
+const V2_USDC_WETH: Address = address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc");
+/// Uniswap V3 USDC/WETH 0.05% pool
+const V3_USDC_WETH: Address = address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640");
+/// Curve 3pool (DAI/USDC/USDT)
+const CURVE_3POOL: Address = address!("bEbc44782C7dB0a1A60Cb6fe97d0b483032FF1C7");
+const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_snapshot_known_pools() -> anyhow::Result<()> {
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+    let specs = [
+        PoolSpec::UniswapV2(V2_USDC_WETH),
+        PoolSpec::UniswapV3(V3_USDC_WETH),
+        PoolSpec::Curve {
+            pool: CURVE_3POOL,
+            n_coins: 3,
+        },
+        // A token is not a pair: getReserves reverts
+        PoolSpec::UniswapV2(USDC),
+        // An account without code returns no data
+        PoolSpec::UniswapV3(Address::repeat_byte(0xfe)),
+    ];
+    let states = snapshot_pools(&mut evm, &specs)?;
+    assert_eq!(states.len(), specs.len());
+
+    let v2 = states[0].as_ref().expect("V2 pair should decode");
+    assert_eq!(v2.protocol(), PoolProtocol::UniswapV2);
+    assert_eq!(v2.pool(), V2_USDC_WETH);
+
+    let v3 = states[1].as_ref().expect("V3 pool should decode");
+    assert_eq!(v3.protocol(), PoolProtocol::UniswapV3);
+
+    let curve = states[2].as_ref().expect("Curve pool should decode");
+    assert_eq!(curve.protocol(), PoolProtocol::Curve);
+
+    assert!(
+        matches!(&states[3], Err(PoolError::CallReverted { call, .. }) if call == "getReserves()")
+    );
+    assert!(matches!(&states[4], Err(PoolError::Decode { .. })));
+
+    let reserves = get_v2_reserves(&mut evm, &[V2_USDC_WETH])?.remove(0)?;
+    assert!(reserves.reserve0 > U256::ZERO && reserves.reserve1 > U256::ZERO);
+
+    let slot0 = get_v3_slot0(&mut evm, &[V3_USDC_WETH])?.remove(0)?;
+    assert_eq!(slot0.fee, 500);
+    assert!(slot0.sqrt_price_x96 > U256::ZERO);
+    assert!(slot0.liquidity > 0);
+    assert!((-887272..=887272).contains(&slot0.tick));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_snapshot_reads_simulated_state() -> anyhow::Result<()> {
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+
+    // Slot 8 packs reserve0 (112 bits), reserve1 (112 bits) and blockTimestampLast
+    let reserve0 = U256::from(1_000_000u64);
+    let reserve1 = U256::from(2_000_000u64);
+    let packed = (U256::from(42u64) << 224) | (reserve1 << 112) | reserve0;
+    evm.insert_storage(V2_USDC_WETH, U256::from(8), packed)?;
+
+    let reserves = get_v2_reserves(&mut evm, &[V2_USDC_WETH])?.remove(0)?;
+    assert_eq!(reserves.reserve0, reserve0);
+    assert_eq!(reserves.reserve1, reserve1);
+    assert_eq!(reserves.block_timestamp_last, 42);
+
+    // The snapshot leaves the simulated state untouched and can be repeated
+    let again = get_v2_reserves(&mut evm, &[V2_USDC_WETH])?.remove(0)?;
+    assert_eq!(again, reserves);
+    Ok(())
+}
@@ -0,0 +1,120 @@
+//! Deterministic serialization of simulation reports
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use std::collections::HashMap;
+
+use alloy::primitives::{address, hex, Address, TxKind, U256};
+use revm::{
+    database::{CacheDB, EmptyDB},
+    state::{AccountInfo, Bytecode},
+    Context, MainBuilder, MainContext,
+};
+use revm_trace::{
+    report::SimulationReport, traits::TransactionTrace, types::SimulationBatch, SimulationTx,
+    TraceEvm, TxInspector,
+};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const TOKEN: Address = address!("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+
+/// Writes `balances[i] = i + 1` for `i` in 0..6 (mapping at slot 1), then slots 1 to 4,
+/// then emits an ERC20 `Transfer(caller, 0xbb, 100)`
+const TOKEN_CODE: &str = concat!(
+    "6001602052",
+    "6000600052604060002060019055",
+    "6001600052604060002060029055",
+    "6002600052604060002060039055",
+    "6003600052604060002060049055",
+    "6004600052604060002060059055",
+    "6005600052604060002060069055",
+    "6007600155600e6002556015600355601c600455",
+    "606460005260bb337f",
+    "ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+    "60206000a300",
+);
+
+/// Traces one call to TOKEN on a fresh EVM and builds its report
+fn report() -> SimulationReport {
+    let mut backend = CacheDB::new(EmptyDB::default());
+    let code = Bytecode::new_raw(hex::decode(TOKEN_CODE).unwrap().into());
+    backend.insert_account_info(
+        TOKEN,
+        AccountInfo {
+            code_hash: code.hash_slow(),
+            code: Some(code),
+            ..Default::default()
+        },
+    );
+    backend.insert_account_info(
+        SENDER,
+        AccountInfo {
+            balance: U256::from(10).pow(U256::from(18)),
+            ..Default::default()
+        },
+    );
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(backend));
+    ctx.cfg.disable_base_fee = true;
+    let mut evm = TraceEvm::new(
+        ctx.build_mainnet_with_inspector(TxInspector::new().with_mapping_resolution(true)),
+    );
+
+    let tx = SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Call(TOKEN),
+        value: U256::from(1_000),
+        data: Default::default(),
+        trace_config: None,
+        return_abi: None,
+    };
+    let mut results = evm.trace_transactions(SimulationBatch {
+        is_stateful: false,
+        transactions: vec![tx.clone()],
+        ..Default::default()
+    });
+    let (result, diff, output) = results.remove(0).expect("transaction executes");
+    assert!(result.is_success(), "{result:?}");
+
+    let labels: HashMap<_, _> = (1..=16)
+        .map(|byte| (Address::with_last_byte(byte), format!("account {byte}")))
+        .collect();
+    SimulationReport::new(tx, &result, diff, output).with_labels(labels)
+}
+
+#[test]
+fn test_same_batch_serializes_identically() -> anyhow::Result<()> {
+    let first = serde_json::to_string(&report())?;
+    for _ in 0..4 {
+        assert_eq!(serde_json::to_string(&report())?, first);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_stable_ids() -> anyhow::Result<()> {
+    let report = report();
+
+    let slots: Vec<_> = report.storage_diff[&TOKEN]
+        .iter()
+        .map(|access| access.slot)
+        .collect();
+    assert_eq!(slots.len(), 10);
+    assert!(slots.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(report.trace.keccak_preimages.len(), 6);
+
+    // The call value, then the ERC20 transfer
+    let indices: Vec<_> = report
+        .trace
+        .asset_transfers
+        .iter()
+        .map(|transfer| transfer.transfer_index)
+        .collect();
+    assert_eq!(indices, vec![0, 1]);
+    assert_eq!(report.trace.call_trace.as_ref().unwrap().frame_id, "");
+
+    let json = serde_json::to_value(&report)?;
+    assert_eq!(json["trace"]["logs"][0]["log_index"], 0);
+    assert_eq!(json["trace"]["call_trace"]["frame_id"], "");
+    Ok(())
+}
@@ -0,0 +1,99 @@
+//! Integration tests for diamond (EIP-2535) facet resolution
+//!
+//! Runs against the LI.FI diamond on mainnet, which implements the standard loupe.
+//! Calls into the diamond must be annotated with the facet its fallback forwarded to.
+
+use alloy::{
+    primitives::{address, Address, TxKind, U256},
+    sol,
+    sol_types::SolCall,
+};
+use revm_trace::{
+    types::{CallScheme, FacetRoute},
+    utils::diamond_utils::{
+        annotate_diamond_calls, called_selectors, resolve_facets, resolve_selectors,
+    },
+    EvmBuilder, SimulationBatch, SimulationTx, TransactionTrace, TxInspector,
+};
+
+const ETH_RPC_URL: &str = "https://eth.llamarpc.com";
+const BLOCK_NUMBER: u64 = 18_000_000;
+const SENDER: Address = Address::repeat_byte(0x5e);
+const LIFI_DIAMOND: Address = address!("1231DEB6f5749EF6cE6943a275A1D3E7486F4EaE");
+const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+
+sol! {
+    function owner() external view returns (address);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_annotates_calls_with_facet() -> anyhow::Result<()> {
+    let mut evm = EvmBuilder::new_alloy(ETH_RPC_URL)
+        .with_block_number(BLOCK_NUMBER)
+        .with_tracer(TxInspector::new())
+        .build()
+        .await?;
+
+    let facets = resolve_facets(&mut evm, LIFI_DIAMOND)?;
+    assert!(!facets.is_empty(), "Diamond must expose its facets");
+    let selector = ownerCall::SELECTOR;
+    let owner_facet = facets
+        .iter()
+        .find(|facet| facet.selectors.contains(&selector))
+        .expect("owner() must be routed")
+        .facet_address;
+
+    let mut results = evm.trace_transactions(SimulationBatch {
+        transactions: vec![SimulationTx {
+            caller: SENDER,
+            transact_to: TxKind::Call(LIFI_DIAMOND),
+            value: U256::ZERO,
+            data: ownerCall {}.abi_encode().into(),
+            trace_config: None,
+            return_abi: None,
+        }],
+        is_stateful: false,
+        ..Default::default()
+    });
+    let (result, _, mut output) = results.remove(0)?;
+    assert!(result.is_success());
+
+    assert_eq!(
+        annotate_diamond_calls(&mut output, LIFI_DIAMOND, &facets),
+        2
+    );
+    let root = output.call_trace.as_ref().unwrap();
+    let expected = Some(FacetRoute {
+        diamond: LIFI_DIAMOND,
+        facet: owner_facet,
+        selector: selector.into(),
+    });
+    assert_eq!(root.facet_route, expected);
+    let forward = &root.subtraces[0];
+    assert_eq!(forward.call_scheme, Some(CallScheme::DelegateCall));
+    assert_eq!(forward.to, owner_facet);
+    assert_eq!(forward.facet_route, expected);
+
+    // Per-selector lookup agrees with the full table
+    let selectors = called_selectors(&output, LIFI_DIAMOND);
+    assert_eq!(selectors, vec![selector]);
+    let resolved = resolve_selectors(&mut evm, LIFI_DIAMOND, &selectors)?;
+    assert_eq!(resolved.len(), 1);
+    assert_eq!(resolved[0].facet_address, owner_facet);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_contract_without_loupe_resolves_nothing() -> anyhow::Result<()> {
+    let mut evm = EvmBuilder::new_alloy(ETH_RPC_URL)
+        .with_block_number(BLOCK_NUMBER)
+        .build()
+        .await?;
+
+    // WETH accepts any calldata through its deposit fallback
+    assert!(resolve_facets(&mut evm, WETH)?.is_empty());
+    assert!(resolve_selectors(&mut evm, WETH, &[ownerCall::SELECTOR])?.is_empty());
+
+    Ok(())
+}
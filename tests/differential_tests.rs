@@ -0,0 +1,117 @@
+//! Differential simulation of one batch under two scenarios
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use alloy::primitives::{address, Address, TxKind, U256};
+use revm_trace::{
+    differential::{compare_evms, IgnoreRule, Scenario, SlotDelta, TransferDelta},
+    types::{SimulationBatch, StateOverride},
+    SimulationTx, TxInspector,
+};
+
+mod common;
+use common::{contract, TestEvm};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const TOKEN: Address = address!("2222222222222222222222222222222222222222");
+const RECIPIENT: Address = address!("3333333333333333333333333333333333333333");
+
+/// Emits `Transfer(msg.sender, RECIPIENT, fee)` with the fee in slot 0, then stores
+/// the block timestamp in slot 1
+const TOKEN_CODE: &str = concat!(
+    "600054600052",
+    "733333333333333333333333333333333333333333",
+    "337f",
+    "ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+    "60206000a3",
+    "4260015500",
+);
+
+fn evm(timestamp: u64) -> TestEvm {
+    common::evm_with(
+        common::backend([(TOKEN, contract(TOKEN_CODE))]),
+        TxInspector::new(),
+        |ctx| ctx.block.timestamp = timestamp,
+    )
+}
+
+fn batch() -> SimulationBatch {
+    SimulationBatch {
+        transactions: vec![SimulationTx {
+            caller: SENDER,
+            transact_to: TxKind::Call(TOKEN),
+            value: U256::ZERO,
+            data: Default::default(),
+            trace_config: None,
+            return_abi: None,
+        }],
+        is_stateful: false,
+        ..Default::default()
+    }
+}
+
+/// Scenario setting the fee to `fee`
+fn fee(fee: u64) -> Scenario {
+    Scenario::default().with_overrides(StateOverride {
+        storages: [(TOKEN, vec![(U256::ZERO, U256::from(fee))])].into(),
+        balances: Default::default(),
+    })
+}
+
+#[test]
+fn test_fee_change_shows_as_transfer_delta() {
+    let report = compare_evms(
+        &mut evm(1_000),
+        &fee(30),
+        &mut evm(2_000),
+        &fee(50),
+        &batch(),
+        &[],
+    );
+    assert_eq!(report.txs.len(), 1);
+    let diff = &report.txs[0];
+    assert_eq!(diff.tx_index, 0);
+    assert_eq!(diff.status, None);
+    assert_eq!(diff.gas_used, None);
+    assert_eq!(
+        diff.transfers,
+        vec![TransferDelta {
+            token: TOKEN,
+            from: SENDER,
+            to: Some(RECIPIENT),
+            id: None,
+            a: Some(U256::from(30)),
+            b: Some(U256::from(50)),
+        }]
+    );
+    // Only the amount differs, so the log is the same event in both runs
+    assert!(diff.logs_added.is_empty());
+    assert!(diff.logs_removed.is_empty());
+    assert_eq!(
+        diff.storage,
+        vec![SlotDelta {
+            address: TOKEN,
+            slot: U256::from(1),
+            a: Some(U256::from(1_000)),
+            b: Some(U256::from(2_000)),
+        }]
+    );
+}
+
+#[test]
+fn test_ignored_slot_leaves_runs_identical() {
+    let ignore = [IgnoreRule::Slot {
+        address: TOKEN,
+        slot: Some(U256::from(1)),
+    }];
+    let report = compare_evms(
+        &mut evm(1_000),
+        &fee(30),
+        &mut evm(2_000),
+        &fee(30),
+        &batch(),
+        &ignore,
+    );
+    assert!(report.is_identical(), "{:?}", report.txs);
+}
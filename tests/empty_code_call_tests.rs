@@ -0,0 +1,110 @@
+//! Handling of calls with calldata to accounts without code
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use alloy::primitives::{address, Address, Bytes, TxKind, U256};
+use revm::{
+    database::{CacheDB, EmptyDB},
+    state::{AccountInfo, Bytecode},
+    Context, MainBuilder, MainContext,
+};
+use revm_trace::{
+    errors::{EvmError, RuntimeError},
+    inspectors::tx_inspector::TxTraceOutput,
+    traits::TransactionTrace,
+    types::{SimulationBatch, SimulationWarning},
+    EmptyCodeCallPolicy, SimulationTx, TraceEvm, TxInspector,
+};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const BOB: Address = address!("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+const CONTRACT: Address = address!("c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0");
+/// SHA-256 precompile
+const SHA256: Address = address!("0000000000000000000000000000000000000002");
+/// EIP-4788 beacon roots contract, absent from the in-memory backend
+const BEACON_ROOTS: Address = address!("000F3df6D732807Ef1319fB7B8bB8522d0Beac02");
+
+fn run(
+    policy: EmptyCodeCallPolicy,
+    to: Address,
+    data: &'static [u8],
+) -> Result<TxTraceOutput, EvmError> {
+    let mut backend = CacheDB::new(EmptyDB::default());
+    backend.insert_account_info(
+        SENDER,
+        AccountInfo {
+            balance: U256::from(10).pow(U256::from(18)),
+            ..Default::default()
+        },
+    );
+    let code = Bytecode::new_raw(Bytes::from_static(&[0x00]));
+    backend.insert_account_info(
+        CONTRACT,
+        AccountInfo {
+            code_hash: code.hash_slow(),
+            code: Some(code),
+            ..Default::default()
+        },
+    );
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(backend));
+    ctx.cfg.disable_base_fee = true;
+    let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(TxInspector::new()));
+
+    let mut results = evm.trace_transactions(SimulationBatch {
+        transactions: vec![SimulationTx {
+            caller: SENDER,
+            transact_to: TxKind::Call(to),
+            value: U256::from(1),
+            data: Bytes::from_static(data),
+            trace_config: None,
+            return_abi: None,
+        }],
+        is_stateful: false,
+        empty_code_call_policy: policy,
+        ..Default::default()
+    });
+    let (result, _, output) = results.remove(0)?;
+    assert!(result.is_success());
+    Ok(output)
+}
+
+#[test]
+fn test_allow_executes_silently() {
+    let output = run(EmptyCodeCallPolicy::Allow, BOB, b"transfer").unwrap();
+    assert!(output.warnings.is_empty());
+}
+
+#[test]
+fn test_warn_attaches_warning() {
+    let output = run(EmptyCodeCallPolicy::Warn, BOB, b"transfer").unwrap();
+    assert_eq!(
+        output.warnings,
+        vec![SimulationWarning::CallToEmptyAccount { address: BOB }]
+    );
+}
+
+#[test]
+fn test_error_rejects_before_execution() {
+    let result = run(EmptyCodeCallPolicy::Error, BOB, b"transfer");
+    assert!(matches!(
+        result,
+        Err(EvmError::Runtime(RuntimeError::CallToEmptyAccount {
+            address: BOB
+        }))
+    ));
+}
+
+#[test]
+fn test_exempt_calls_pass_under_error() {
+    // Plain value transfers, precompiles, system contracts and contracts with code
+    for (to, data) in [
+        (BOB, &b""[..]),
+        (SHA256, b"transfer"),
+        (BEACON_ROOTS, b"transfer"),
+        (CONTRACT, b"transfer"),
+    ] {
+        let output = run(EmptyCodeCallPolicy::Error, to, data).unwrap();
+        assert!(output.warnings.is_empty(), "{to}");
+    }
+}
@@ -0,0 +1,86 @@
+//! Tests for ENS resolution through the simulated EVM
+//!
+//! Run with `cargo test --features ens`.
+#![cfg(feature = "ens")]
+
+use alloy::primitives::{address, Address, TxKind};
+use revm::{
+    database::{CacheDB, EmptyDB},
+    Context, MainBuilder, MainContext,
+};
+use revm_trace::{
+    create_evm_with_tracer,
+    errors::{EnsError, EvmError},
+    report::SimulationReport,
+    utils::ens_utils::{label_report, resolve, reverse},
+    SimulationTx, TraceEvm, TxInspector,
+};
+
+const ETH_RPC_URL: &str = "https://eth.llamarpc.com";
+const VITALIK: Address = address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045");
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_resolve_and_reverse() -> anyhow::Result<()> {
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+
+    let address = resolve(&mut evm, "Vitalik.eth")?;
+    assert_eq!(address, VITALIK);
+    assert_eq!(reverse(&mut evm, address)?.as_deref(), Some("vitalik.eth"));
+
+    assert!(matches!(
+        resolve(&mut evm, "no-such-name-revm-trace-test.eth"),
+        Err(EvmError::Ens(EnsError::NotResolved(_)))
+    ));
+    assert_eq!(reverse(&mut evm, Address::repeat_byte(0x42))?, None);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_label_report() -> anyhow::Result<()> {
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+    let report = SimulationReport {
+        tx: SimulationTx {
+            caller: VITALIK,
+            transact_to: TxKind::Call(Address::repeat_byte(0x42)),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let report = label_report(&mut evm, report)?;
+    assert_eq!(report.labels.len(), 1);
+    assert_eq!(report.labels[&VITALIK], "vitalik.eth");
+    Ok(())
+}
+
+#[cfg(feature = "serde-api")]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_plan_resolves_names() -> anyhow::Result<()> {
+    use revm_trace::api::types::{SimulationPlan, SimulationRequest};
+
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+    let request: SimulationRequest = serde_json::from_str(
+        r#"{"transactions": [{"from": "vitalik.eth", "to": "0x2000000000000000000000000000000000000002"}]}"#,
+    )?;
+
+    let plan = SimulationPlan::resolve(request, &mut evm)?;
+    assert_eq!(plan.batch.transactions[0].caller, VITALIK);
+    Ok(())
+}
+
+#[test]
+fn test_non_mainnet_is_unsupported() {
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(EmptyDB::default()));
+    ctx.cfg.chain_id = 10;
+    let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(TxInspector::new()));
+
+    for result in [
+        resolve(&mut evm, "vitalik.eth").map(|_| ()),
+        reverse(&mut evm, VITALIK).map(|_| ()),
+    ] {
+        assert!(matches!(
+            result,
+            Err(EvmError::Ens(EnsError::EnsUnsupported { chain_id: 10 }))
+        ));
+    }
+}
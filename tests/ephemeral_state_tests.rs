@@ -0,0 +1,128 @@
+//! Temporarily overridden state with `TraceEvm::with_ephemeral_state`
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay the overrides are applied to.
+
+use std::panic::{self, AssertUnwindSafe};
+
+use alloy::primitives::{address, Address, Bytes, U256};
+use revm::{context_interface::ContextTr, database::Database};
+use revm_trace::types::AccountOverride;
+
+mod common;
+use common::{funded, TestEvm};
+
+const FUNDED: Address = address!("1000000000000000000000000000000000000001");
+const FRESH: Address = address!("2000000000000000000000000000000000000002");
+
+fn evm() -> TestEvm {
+    common::evm(common::backend([(FUNDED, funded(U256::from(1_000)))]))
+}
+
+fn balance(evm: &mut TestEvm, address: Address) -> U256 {
+    evm.db()
+        .basic(address)
+        .unwrap()
+        .map_or(U256::ZERO, |account| account.balance)
+}
+
+fn overrides() -> Vec<AccountOverride> {
+    vec![
+        AccountOverride {
+            address: FUNDED,
+            balance: Some(U256::MAX),
+            ..Default::default()
+        },
+        AccountOverride {
+            address: FRESH,
+            balance: Some(U256::from(7)),
+            nonce: Some(3),
+            code: Some(Bytes::from_static(&[0x60, 0x00, 0x60, 0x00, 0xf3])),
+            storage: vec![(U256::from(1), U256::from(2))],
+        },
+    ]
+}
+
+/// Puts `FUNDED` into the overlay with a balance differing from the backend's
+fn prepare(evm: &mut TestEvm) {
+    evm.set_balance(FUNDED, U256::from(500)).unwrap();
+    assert!(!evm.db().cache.accounts.contains_key(&FRESH));
+}
+
+fn assert_restored(evm: &mut TestEvm) {
+    let cache = &evm.db().cache;
+    assert!(!cache.accounts.contains_key(&FRESH));
+    assert_eq!(cache.contracts.len(), 2, "only the default entries remain");
+    assert_eq!(balance(evm, FUNDED), U256::from(500));
+    assert_eq!(balance(evm, FRESH), U256::ZERO);
+}
+
+#[test]
+fn test_overrides_are_undone_on_return() {
+    let mut evm = evm();
+    prepare(&mut evm);
+
+    let inside = evm
+        .with_ephemeral_state(overrides(), |evm| {
+            let code = evm.db().basic(FRESH).unwrap().unwrap();
+            (
+                balance(evm, FUNDED),
+                code.nonce,
+                code.code.map(|code| code.original_bytes()),
+                evm.db().storage(FRESH, U256::from(1)).unwrap(),
+            )
+        })
+        .unwrap();
+    assert_eq!(
+        inside,
+        (
+            U256::MAX,
+            3,
+            Some(Bytes::from_static(&[0x60, 0x00, 0x60, 0x00, 0xf3])),
+            U256::from(2)
+        )
+    );
+
+    assert_restored(&mut evm);
+}
+
+#[test]
+fn test_overrides_are_undone_on_panic() {
+    let mut evm = evm();
+    prepare(&mut evm);
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        evm.with_ephemeral_state(overrides(), |evm| {
+            assert_eq!(balance(evm, FRESH), U256::from(7));
+            panic!("closure failed");
+        })
+    }));
+    assert!(result.is_err());
+
+    assert_restored(&mut evm);
+}
+
+#[test]
+fn test_later_override_wins() {
+    let mut evm = evm();
+    let overrides = vec![
+        AccountOverride {
+            address: FUNDED,
+            balance: Some(U256::from(1)),
+            ..Default::default()
+        },
+        AccountOverride {
+            address: FUNDED,
+            balance: Some(U256::from(2)),
+            ..Default::default()
+        },
+    ];
+
+    let inside = evm
+        .with_ephemeral_state(overrides, |evm| balance(evm, FUNDED))
+        .unwrap();
+    assert_eq!(inside, U256::from(2));
+    // The account was not in the overlay before, so it is read from the backend again
+    assert!(!evm.db().cache.accounts.contains_key(&FUNDED));
+    assert_eq!(balance(&mut evm, FUNDED), U256::from(1_000));
+}
@@ -0,0 +1,135 @@
+//! Location of the error origin in failed transactions
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use alloy::primitives::{address, hex, Address, TxKind, U256};
+use revm::{
+    database::{CacheDB, EmptyDB},
+    state::{AccountInfo, Bytecode},
+    Context, MainBuilder, MainContext,
+};
+use revm_trace::{
+    inspectors::tx_inspector::TxTraceOutput,
+    traits::TransactionTrace,
+    types::{CallStatus, HaltReasonKind, SimulationBatch},
+    SimulationTx, TraceEvm, TxInspector,
+};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const A: Address = address!("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+const B: Address = address!("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+const C: Address = address!("cccccccccccccccccccccccccccccccccccccccc");
+const D: Address = address!("dddddddddddddddddddddddddddddddddddddddd");
+
+/// Reverts without data
+const REVERT: &str = "60006000fd";
+
+/// Calls B with all gas, reverting if the call fails
+const CALL_B: &str =
+    "6000600060006000600073bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb5af160295760006000fd5b00";
+
+/// Calls C with all gas, reverting if the call fails
+const CALL_C: &str =
+    "6000600060006000600073cccccccccccccccccccccccccccccccccccccccc5af160295760006000fd5b00";
+
+/// Calls D with 4096 gas, reverting if the call fails
+const CALL_D_LIMITED: &str =
+    "6000600060006000600073dddddddddddddddddddddddddddddddddddddddd611000f1602b5760006000fd5b00";
+
+/// Loops forever
+const LOOP: &str = "5b600056";
+
+fn trace(contracts: &[(Address, &str)], target: Address) -> TxTraceOutput {
+    let mut backend = CacheDB::new(EmptyDB::default());
+    for (address, code) in contracts {
+        let code = Bytecode::new_raw(hex::decode(code).unwrap().into());
+        backend.insert_account_info(
+            *address,
+            AccountInfo {
+                code_hash: code.hash_slow(),
+                code: Some(code),
+                ..Default::default()
+            },
+        );
+    }
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(backend));
+    ctx.cfg.disable_base_fee = true;
+    let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(TxInspector::new()));
+
+    let mut results = evm.trace_transactions(SimulationBatch {
+        is_stateful: false,
+        transactions: vec![SimulationTx {
+            caller: SENDER,
+            transact_to: TxKind::Call(target),
+            value: U256::ZERO,
+            data: Default::default(),
+            trace_config: None,
+            return_abi: None,
+        }],
+        ..Default::default()
+    });
+    let (result, _, output) = results.remove(0).expect("transaction executes");
+    assert!(!result.is_success(), "{result:?}");
+    output
+}
+
+/// Trace addresses of all frames flagged as error origin
+fn origins(output: &TxTraceOutput) -> Vec<Vec<usize>> {
+    output
+        .call_trace
+        .as_ref()
+        .unwrap()
+        .frames()
+        .filter(|frame| frame.error_origin)
+        .map(|frame| frame.trace_address.clone())
+        .collect()
+}
+
+#[test]
+fn test_revert_at_top_level_frame() {
+    let output = trace(&[(A, REVERT)], A);
+
+    assert_eq!(output.error_trace_address, Some(vec![]));
+    assert_eq!(origins(&output), vec![Vec::<usize>::new()]);
+    let origin = output.error_trace().unwrap();
+    assert_eq!(origin.to, A);
+    assert!(origin.status.is_revert());
+}
+
+#[test]
+fn test_revert_bubbled_from_depth_two() {
+    let output = trace(&[(A, CALL_B), (B, CALL_C), (C, REVERT)], A);
+
+    assert_eq!(output.error_trace_address, Some(vec![0, 0]));
+    assert_eq!(origins(&output), vec![vec![0, 0]]);
+    let origin = output.error_trace().unwrap();
+    assert_eq!((origin.to, origin.depth), (C, 2));
+
+    // The frames the revert bubbled through failed without being the origin
+    let root = output.call_trace.as_ref().unwrap();
+    assert!(!root.status.is_success() && !root.error_origin);
+    assert!(!root.subtraces[0].status.is_success() && !root.subtraces[0].error_origin);
+}
+
+#[test]
+fn test_out_of_gas_halt_at_depth_one() {
+    let output = trace(&[(A, CALL_D_LIMITED), (D, LOOP)], A);
+
+    assert_eq!(output.error_trace_address, Some(vec![0]));
+    assert_eq!(origins(&output), vec![vec![0]]);
+    let origin = output.error_trace().unwrap();
+    assert_eq!(origin.to, D);
+    assert!(origin.status.is_out_of_gas(), "{:?}", origin.status);
+    assert!(
+        matches!(
+            &origin.status,
+            CallStatus::Halt {
+                reason: HaltReasonKind::OutOfGas,
+                raw,
+            } if raw.starts_with("OutOfGas")
+        ),
+        "{:?}",
+        origin.status
+    );
+}
@@ -0,0 +1,127 @@
+//! Accounts whose code ran, and the contract dependency list built from them
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use std::collections::HashMap;
+
+use alloy::primitives::{address, hex, Address, TxKind, U256};
+use revm::{
+    database::{CacheDB, EmptyDB},
+    state::{AccountInfo, Bytecode},
+    Context, MainBuilder, MainContext,
+};
+use revm_trace::{
+    report::{ContractDependency, SimulationReport},
+    traits::TransactionTrace,
+    types::SimulationBatch,
+    SimulationTx, TraceEvm, TxInspector,
+};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const PROXY: Address = address!("2222222222222222222222222222222222222222");
+const IMPLEMENTATION: Address = address!("3333333333333333333333333333333333333333");
+const RECIPIENT: Address = address!("4444444444444444444444444444444444444444");
+
+/// Delegatecalls IMPLEMENTATION without data
+const PROXY_CODE: &str = "60006000600060007333333333333333333333333333333333333333335af400";
+
+/// Stores 1 in slot 0
+const IMPLEMENTATION_CODE: &str = "600160005500";
+
+fn install(backend: &mut CacheDB<EmptyDB>, address: Address, code: &str) {
+    let code = Bytecode::new_raw(hex::decode(code).unwrap().into());
+    backend.insert_account_info(
+        address,
+        AccountInfo {
+            code_hash: code.hash_slow(),
+            code: Some(code),
+            ..Default::default()
+        },
+    );
+}
+
+fn report(to: Address, value: U256) -> SimulationReport {
+    let mut backend = CacheDB::new(EmptyDB::default());
+    install(&mut backend, PROXY, PROXY_CODE);
+    install(&mut backend, IMPLEMENTATION, IMPLEMENTATION_CODE);
+    backend.insert_account_info(
+        SENDER,
+        AccountInfo {
+            balance: U256::from(10).pow(U256::from(18)),
+            ..Default::default()
+        },
+    );
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(backend));
+    ctx.cfg.disable_base_fee = true;
+    let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(TxInspector::new()));
+
+    let tx = SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Call(to),
+        value,
+        data: Default::default(),
+        trace_config: None,
+        return_abi: None,
+    };
+    let mut results = evm.trace_transactions(SimulationBatch {
+        transactions: vec![tx.clone()],
+        is_stateful: false,
+        ..Default::default()
+    });
+    let (result, diff, output) = results.remove(0).expect("transaction executes");
+    assert!(result.is_success(), "{result:?}");
+    SimulationReport::new(tx, &result, diff, output)
+}
+
+#[test]
+fn test_proxy_and_implementation_executed() {
+    let report = report(PROXY, U256::ZERO);
+    assert_eq!(
+        report.trace.executed_code_addresses,
+        [PROXY, IMPLEMENTATION].into_iter().collect()
+    );
+
+    let root = report.trace.call_trace.as_ref().unwrap();
+    assert!(root.opcodes_executed.unwrap() > 0);
+    let delegated = &root.subtraces[0];
+    // Delegatecall frames are reported at the code they run
+    assert_eq!(delegated.to, IMPLEMENTATION);
+    assert_eq!(delegated.code_address, None);
+    assert!(delegated.executed_code());
+}
+
+#[test]
+fn test_transfer_to_eoa_executes_nothing() {
+    let report = report(RECIPIENT, U256::from(1_000));
+    assert!(report.trace.executed_code_addresses.is_empty());
+
+    let root = report.trace.call_trace.as_ref().unwrap();
+    assert_eq!(root.opcodes_executed, Some(0));
+    assert!(report.contract_dependency_list().is_empty());
+    assert!(serde_json::to_value(&report.trace)
+        .unwrap()
+        .get("executed_code_addresses")
+        .is_none());
+}
+
+#[test]
+fn test_dependency_list_is_sorted_and_labeled() {
+    let report = report(PROXY, U256::ZERO).with_labels(HashMap::from([(
+        IMPLEMENTATION,
+        "Implementation".to_string(),
+    )]));
+    assert_eq!(
+        report.contract_dependency_list(),
+        vec![
+            ContractDependency {
+                address: PROXY,
+                label: None,
+            },
+            ContractDependency {
+                address: IMPLEMENTATION,
+                label: Some("Implementation".to_string()),
+            },
+        ]
+    );
+}
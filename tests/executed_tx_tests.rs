@@ -0,0 +1,94 @@
+//! Transaction environments captured for executed transactions
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use alloy::primitives::{address, keccak256, Address, Bytes, TxKind, U256};
+use revm::state::AccountInfo;
+use revm_trace::{
+    report::SimulationReport, traits::TransactionTrace, types::SimulationBatch, SimulationTx,
+};
+
+mod common;
+use common::{ether, funded, TestEvm};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const BOB: Address = address!("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+
+/// Nonce of SENDER in the backend
+const NONCE: u64 = 5;
+
+fn evm() -> TestEvm {
+    common::evm(common::backend([(
+        SENDER,
+        AccountInfo {
+            nonce: NONCE,
+            ..funded(ether())
+        },
+    )]))
+}
+
+fn transfer(data: &[u8]) -> SimulationTx {
+    SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Call(BOB),
+        value: U256::from(1_000),
+        data: Bytes::copy_from_slice(data),
+        trace_config: None,
+        return_abi: None,
+    }
+}
+
+fn batch(is_stateful: bool) -> SimulationBatch {
+    SimulationBatch {
+        transactions: vec![transfer(b""), transfer(b"hello"), transfer(b"")],
+        is_stateful,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_nonce_follows_stateful_batch() {
+    let mut evm = evm();
+    for result in evm.trace_transactions(batch(true)) {
+        assert!(result.unwrap().0.is_success());
+    }
+    let executed: Vec<_> = evm
+        .take_last_executed_txs()
+        .into_iter()
+        .map(|tx| tx.expect("transaction executed"))
+        .collect();
+
+    let nonces: Vec<_> = executed.iter().map(|tx| tx.nonce).collect();
+    assert_eq!(nonces, vec![NONCE, NONCE + 1, NONCE + 2]);
+    assert_eq!(executed[1].caller, SENDER);
+    assert_eq!(executed[1].kind, TxKind::Call(BOB));
+    assert_eq!(executed[1].value, U256::from(1_000));
+    assert_eq!(executed[1].chain_id, Some(evm.cfg.chain_id));
+    assert_eq!(executed[1].data_len, 5);
+    assert_eq!(executed[1].data_hash, keccak256(b"hello"));
+
+    // Taken once
+    assert!(evm.take_last_executed_txs().is_empty());
+}
+
+#[test]
+fn test_nonce_resets_in_stateless_batch() {
+    let mut evm = evm();
+    let results = evm.trace_transactions(batch(false));
+    let mut executed: Vec<_> = evm
+        .take_last_executed_txs()
+        .into_iter()
+        .map(|tx| tx.unwrap())
+        .collect();
+    let nonces: Vec<_> = executed.iter().map(|tx| tx.nonce).collect();
+    assert_eq!(nonces, vec![NONCE; 3]);
+
+    // The environment travels with the report
+    let (result, diff, output) = results.into_iter().next().unwrap().unwrap();
+    let report = SimulationReport::new(transfer(b""), &result, diff, output)
+        .with_executed_tx(executed.remove(0));
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["executed_tx"]["nonce"], NONCE);
+    assert_eq!(json["executed_tx"]["data_len"], 0);
+}
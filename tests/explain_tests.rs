@@ -0,0 +1,189 @@
+#![cfg(feature = "labels")]
+//! Wording of `explain::explain` over representative reports
+//!
+//! The expected sentences are kept verbatim, so any change of wording shows up here.
+
+use std::collections::HashMap;
+
+use alloy::primitives::{address, b256, Address, Bytes, Log, LogData, B256, U256};
+use revm_trace::{
+    analysis::{FindingRef, Severity},
+    explain::{explain, Explanation, Lang},
+    labels::LabelBook,
+    report::SimulationReport,
+    types::{CallScheme, CallTrace, TokenInfo, ERC20_TRANSFER_EVENT_SIGNATURE},
+};
+
+const USER: Address = address!("1000000000000000000000000000000000000001");
+const ROUTER: Address = address!("7a250d5630b4cf539739df2c5dacb4c659f2488d");
+const PAIR: Address = address!("b4e16d0168e52d35cacd2c6185b44281ec28c9dc");
+const WETH: Address = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+const USDC: Address = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+const BAYC: Address = address!("bc4ca0eda7647a8ab7c2061c2e118a18a936f13d");
+const DRAINER: Address = address!("3333333333333333333333333333333333333333");
+const ATTACKER: Address = address!("4444444444444444444444444444444444444444");
+
+const APPROVAL: B256 = b256!("8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925");
+const APPROVAL_FOR_ALL: B256 =
+    b256!("17307eab39ab6107e8899845ad3d59bd9653f200f220920489ca2b5937696c31");
+
+fn log(emitter: Address, topics: Vec<B256>, data: Vec<u8>) -> Log {
+    Log {
+        address: emitter,
+        data: LogData::new_unchecked(topics, Bytes::from(data)),
+    }
+}
+
+fn call(from: Address, to: Address, value: U256, subtraces: Vec<CallTrace>) -> CallTrace {
+    CallTrace {
+        from,
+        to,
+        value,
+        call_scheme: Some(CallScheme::Call),
+        subtraces,
+        ..Default::default()
+    }
+}
+
+fn token_info(name: &str, symbol: &str, decimals: u8) -> TokenInfo {
+    TokenInfo {
+        name: name.to_string(),
+        symbol: symbol.to_string(),
+        decimals,
+        total_supply: U256::ZERO,
+    }
+}
+
+fn successful(root: CallTrace, logs: Vec<Log>) -> SimulationReport {
+    let mut report = SimulationReport::default();
+    report.tx.caller = USER;
+    report.summary.success = true;
+    report.trace.call_trace = Some(root);
+    report.trace.logs = logs;
+    report
+}
+
+/// USER swaps 1.2 ETH for 3000 USDC through the Uniswap V2 router
+#[test]
+fn test_swap() {
+    let eth = U256::from(10).pow(U256::from(18));
+    let value = eth * U256::from(12) / U256::from(10);
+    let root = call(USER, ROUTER, value, vec![call(ROUTER, WETH, value, vec![])]);
+    let logs = vec![log(
+        USDC,
+        vec![
+            ERC20_TRANSFER_EVENT_SIGNATURE,
+            PAIR.into_word(),
+            USER.into_word(),
+        ],
+        U256::from(3_000_000_000u64).to_be_bytes_vec(),
+    )];
+    let report = successful(root, logs)
+        .with_token_infos(HashMap::from([(USDC, token_info("USD Coin", "USDC", 6))]));
+
+    assert_eq!(
+        explain(&report, Some(&LabelBook::builtin()), Lang::English),
+        vec![
+            Explanation {
+                severity: Severity::Info,
+                text: format!("Sends 1.2 ETH to {ROUTER} (Uniswap V2: Router)"),
+                refs: vec![FindingRef::TraceAddress(vec![])],
+            },
+            Explanation {
+                severity: Severity::Info,
+                text: format!("Receives 3000 USDC from {PAIR}"),
+                refs: vec![FindingRef::LogIndex(0)],
+            },
+        ]
+    );
+}
+
+/// USER calls a drainer, which grants ATTACKER an unlimited USDC allowance and
+/// control of all BAYC NFTs, and sends BAYC #123 to ATTACKER
+#[test]
+fn test_approval_phishing() {
+    let root = call(USER, DRAINER, U256::ZERO, vec![]);
+    let logs = vec![
+        log(
+            USDC,
+            vec![APPROVAL, USER.into_word(), ATTACKER.into_word()],
+            U256::MAX.to_be_bytes_vec(),
+        ),
+        log(
+            BAYC,
+            vec![APPROVAL_FOR_ALL, USER.into_word(), ATTACKER.into_word()],
+            U256::from(1).to_be_bytes_vec(),
+        ),
+        log(
+            BAYC,
+            vec![
+                ERC20_TRANSFER_EVENT_SIGNATURE,
+                USER.into_word(),
+                ATTACKER.into_word(),
+                B256::from(U256::from(123)),
+            ],
+            vec![],
+        ),
+    ];
+    let report = successful(root, logs)
+        .with_token_infos(HashMap::from([
+            (USDC, token_info("USD Coin", "USDC", 6)),
+            (BAYC, token_info("BoredApeYachtClub", "BAYC", 0)),
+        ]))
+        .with_labels(HashMap::from([(ATTACKER, "drainer.eth".to_string())]));
+
+    let attacker = format!("{ATTACKER} (drainer.eth)");
+    assert_eq!(
+        explain(&report, None, Lang::English),
+        vec![
+            Explanation {
+                severity: Severity::Info,
+                text: format!("Sends NFT #123 of BAYC to {attacker}"),
+                refs: vec![FindingRef::LogIndex(2)],
+            },
+            Explanation {
+                severity: Severity::High,
+                text: format!("Grants unlimited USDC approval to {attacker}"),
+                refs: vec![FindingRef::LogIndex(0)],
+            },
+            Explanation {
+                severity: Severity::High,
+                text: format!("Grants {attacker} control of all BAYC NFTs"),
+                refs: vec![FindingRef::LogIndex(1)],
+            },
+            Explanation {
+                severity: Severity::Low,
+                text: "Sends an NFT to an address that does not appear in the call trace"
+                    .to_string(),
+                refs: vec![FindingRef::LogIndex(2)],
+            },
+        ]
+    );
+}
+
+/// The router rejects a swap below the minimum output
+#[test]
+fn test_failed_transaction() {
+    let mut report = SimulationReport::default();
+    report.tx.caller = USER;
+    report.summary.error = Some("UniswapV2Router: INSUFFICIENT_OUTPUT_AMOUNT".to_string());
+    report.trace.error_trace_address = Some(vec![0]);
+
+    assert_eq!(
+        explain(&report, Some(&LabelBook::builtin()), Lang::English),
+        vec![Explanation {
+            severity: Severity::High,
+            text: "Transaction reverts with: UniswapV2Router: INSUFFICIENT_OUTPUT_AMOUNT"
+                .to_string(),
+            refs: vec![FindingRef::TraceAddress(vec![0])],
+        }]
+    );
+
+    // Reverts without data decode to an empty hex string
+    report.summary.error = Some("0x".to_string());
+    report.trace.error_trace_address = None;
+    assert_eq!(
+        explain(&report, None, Lang::English)[0].text,
+        "Transaction reverts without a reason"
+    );
+}
@@ -0,0 +1,75 @@
+//! Building several EVMs over one provider with `EvmFactory`
+//!
+//! Runs against a mocked provider answering requests in order, so no RPC access is
+//! needed. A request the test did not queue an answer for fails.
+
+use alloy::{
+    network::AnyNetwork,
+    primitives::{Address, Bloom, B256, U64},
+    providers::{mock::Asserter, ProviderBuilder},
+};
+use revm_trace::{types::BlockContext, EvmFactory, TxInspector};
+use serde_json::json;
+
+fn block_context(number: u64) -> BlockContext {
+    BlockContext {
+        number,
+        hash: B256::repeat_byte(number as u8),
+        timestamp: 1_700_000_000 + number,
+        chain_id: 1,
+        tag: None,
+    }
+}
+
+/// Minimal `eth_getBlockByNumber` response
+fn block_json(number: u64) -> serde_json::Value {
+    json!({
+        "hash": B256::repeat_byte(number as u8),
+        "parentHash": B256::ZERO,
+        "sha3Uncles": B256::ZERO,
+        "miner": Address::ZERO,
+        "stateRoot": B256::ZERO,
+        "transactionsRoot": B256::ZERO,
+        "receiptsRoot": B256::ZERO,
+        "logsBloom": Bloom::ZERO,
+        "difficulty": "0x0",
+        "number": U64::from(number),
+        "gasLimit": "0x1c9c380",
+        "gasUsed": "0x0",
+        "timestamp": U64::from(1_700_000_000 + number),
+        "extraData": "0x",
+        "mixHash": B256::ZERO,
+        "nonce": "0x0000000000000000",
+        "uncles": [],
+        "transactions": [],
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_factory_looks_up_chain_id_once() -> anyhow::Result<()> {
+    let asserter = Asserter::new();
+    let provider = ProviderBuilder::new()
+        .network::<AnyNetwork>()
+        .connect_mocked_client(asserter.clone());
+
+    // One chain ID, then one header per block
+    asserter.push_success(&U64::from(1));
+    for number in [100, 101, 102] {
+        asserter.push_success(&block_json(number));
+    }
+
+    let factory = EvmFactory::from_provider(provider).await?;
+    assert_eq!(factory.chain_id(), 1);
+    for number in [100, 101, 102] {
+        let evm = factory.evm_at_block(number, TxInspector::new()).await?;
+        assert_eq!(evm.block_context(), block_context(number));
+        assert_eq!(evm.block.number, number);
+        assert_eq!(evm.cfg.chain_id, 1);
+    }
+
+    // Nothing is left to answer, so a seen block must come from the cache
+    let evm = factory.evm_at_block(101, TxInspector::new()).await?;
+    assert_eq!(evm.block_context(), block_context(101));
+    assert!(factory.evm_at_block(103, TxInspector::new()).await.is_err());
+    Ok(())
+}
@@ -0,0 +1,260 @@
+//! Failing over between RPC endpoints
+//!
+//! Runs against JSON-RPC nodes served over HTTP on localhost, so no RPC access is
+//! needed. The nodes serve empty state apart from a funded sender; a node can be told
+//! to shut down after answering a number of requests.
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use alloy::primitives::{address, Address, Bloom, TxKind, B256, U256, U64};
+use revm_trace::{
+    errors::{EvmError, InitError},
+    types::SimulationBatch,
+    EvmBuilder, SimulationTx, TxInspector,
+};
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::Notify,
+};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const BLOCK_NUMBER: u64 = 100;
+
+/// JSON-RPC node listening on localhost
+struct MockNode {
+    url: String,
+    served: Arc<AtomicUsize>,
+}
+
+impl MockNode {
+    /// Starts a node of chain `chain_id` that shuts down instead of answering more
+    /// than `limit` requests
+    async fn spawn(chain_id: u64, limit: Option<usize>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let node = Connection {
+            chain_id,
+            limit,
+            served: Arc::default(),
+            shutdown: Arc::default(),
+        };
+        let served = node.served.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    accepted = listener.accept() => {
+                        let Ok((stream, _)) = accepted else { break };
+                        tokio::spawn(node.clone().serve(stream));
+                    }
+                    // Dropping the listener refuses new connections
+                    _ = node.shutdown.notified() => break,
+                }
+            }
+        });
+        Self { url, served }
+    }
+
+    /// Requests answered so far
+    fn served(&self) -> usize {
+        self.served.load(Ordering::SeqCst)
+    }
+}
+
+/// Handler of the connections of one node
+#[derive(Clone)]
+struct Connection {
+    chain_id: u64,
+    limit: Option<usize>,
+    served: Arc<AtomicUsize>,
+    shutdown: Arc<Notify>,
+}
+
+impl Connection {
+    async fn serve(self, stream: TcpStream) {
+        let mut stream = BufReader::new(stream);
+        while let Some(body) = read_request(&mut stream).await {
+            let admitted = self
+                .served
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |served| {
+                    self.limit
+                        .is_none_or(|limit| served < limit)
+                        .then_some(served + 1)
+                })
+                .is_ok();
+            if !admitted {
+                // Drops the connection without answering
+                self.shutdown.notify_one();
+                return;
+            }
+            let response = self.answer(&body).to_string();
+            let message = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{response}",
+                response.len()
+            );
+            if stream
+                .get_mut()
+                .write_all(message.as_bytes())
+                .await
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+
+    /// Answers with a balance of 1 ether for `SENDER` and empty accounts everywhere else
+    fn answer(&self, body: &[u8]) -> Value {
+        let request: Value = serde_json::from_slice(body).unwrap();
+        let params = &request["params"];
+        let account = || serde_json::from_value::<Address>(params[0].clone()).unwrap();
+        let result = match request["method"].as_str().unwrap() {
+            "eth_chainId" => json!(U64::from(self.chain_id)),
+            "eth_getBlockByNumber" => block_json(BLOCK_NUMBER),
+            "eth_getBalance" => json!(if account() == SENDER {
+                U256::from(10).pow(U256::from(18))
+            } else {
+                U256::ZERO
+            }),
+            "eth_getTransactionCount" => json!(U64::ZERO),
+            "eth_getCode" => json!("0x"),
+            "eth_getStorageAt" => json!(U256::ZERO),
+            method => panic!("unexpected method {method}"),
+        };
+        json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })
+    }
+}
+
+/// Reads the body of the next HTTP request, `None` once the connection is closed
+async fn read_request(stream: &mut BufReader<TcpStream>) -> Option<Vec<u8>> {
+    let mut length = 0;
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await.ok()? == 0 {
+            return None;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().ok()?;
+            }
+        }
+    }
+    let mut body = vec![0; length];
+    stream.read_exact(&mut body).await.ok()?;
+    Some(body)
+}
+
+/// Minimal `eth_getBlockByNumber` response
+fn block_json(number: u64) -> Value {
+    json!({
+        "hash": B256::repeat_byte(number as u8),
+        "parentHash": B256::ZERO,
+        "sha3Uncles": B256::ZERO,
+        "miner": Address::ZERO,
+        "stateRoot": B256::ZERO,
+        "transactionsRoot": B256::ZERO,
+        "receiptsRoot": B256::ZERO,
+        "logsBloom": Bloom::ZERO,
+        "difficulty": "0x0",
+        "number": U64::from(number),
+        "gasLimit": "0x1c9c380",
+        "gasUsed": "0x0",
+        "timestamp": U64::from(1_700_000_000 + number),
+        "extraData": "0x",
+        "mixHash": B256::ZERO,
+        "nonce": "0x0000000000000000",
+        "uncles": [],
+        "transactions": [],
+    })
+}
+
+/// Transfers of 1 wei from `SENDER` to `count` fresh accounts
+fn transfers(count: u8) -> SimulationBatch {
+    SimulationBatch {
+        transactions: (1..=count)
+            .map(|i| SimulationTx {
+                caller: SENDER,
+                transact_to: TxKind::Call(Address::repeat_byte(0xa0 + i)),
+                value: U256::from(1),
+                data: Default::default(),
+                trace_config: None,
+                return_abi: None,
+            })
+            .collect(),
+        is_stateful: true,
+        ..Default::default()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_batch_completes_after_primary_dies() -> anyhow::Result<()> {
+    let primary = MockNode::spawn(1, Some(4)).await;
+    let backup = MockNode::spawn(1, None).await;
+
+    let mut evm = EvmBuilder::new_alloy_multi(vec![primary.url.clone(), backup.url.clone()])
+        .with_block_number(BLOCK_NUMBER)
+        .with_tracer(TxInspector::new())
+        .build()
+        .await?;
+    assert_eq!(evm.db_stats().active_endpoint, Some(0));
+
+    for result in evm.trace_transactions(transfers(3)) {
+        assert!(result?.0.is_success());
+    }
+    let stats = evm.db_stats();
+    assert_eq!(stats.failovers, 1);
+    assert_eq!(stats.active_endpoint, Some(1));
+    assert_eq!(primary.served(), 4);
+    assert!(backup.served() > 1);
+
+    evm.reset_db_stats();
+    let stats = evm.db_stats();
+    assert_eq!((stats.failovers, stats.active_endpoint), (0, Some(1)));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_chain_id_mismatch_fails_the_build() {
+    let mainnet = MockNode::spawn(1, None).await;
+    let other = MockNode::spawn(5, None).await;
+
+    let result = EvmBuilder::new_alloy_multi(vec![mainnet.url.clone(), other.url.clone()])
+        .with_block_number(BLOCK_NUMBER)
+        .build()
+        .await;
+    match result {
+        Err(EvmError::Init(InitError::ChainId(reason))) => {
+            assert!(reason.contains("endpoint 1"), "{reason}");
+            assert!(!reason.contains(&other.url), "URLs must not be reported");
+        }
+        Err(error) => panic!("expected a chain ID mismatch, got {error:?}"),
+        Ok(_) => panic!("expected a chain ID mismatch"),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_unreachable_endpoint_is_skipped_at_build() -> anyhow::Result<()> {
+    // Nothing listens on a port once its listener is dropped
+    let closed = TcpListener::bind("127.0.0.1:0").await?;
+    let unreachable = format!("http://{}", closed.local_addr()?);
+    drop(closed);
+    let backup = MockNode::spawn(1, None).await;
+
+    let evm = EvmBuilder::new_alloy_multi(vec![unreachable, backup.url.clone()])
+        .with_block_number(BLOCK_NUMBER)
+        .build()
+        .await?;
+    let stats = evm.db_stats();
+    assert_eq!((stats.failovers, stats.active_endpoint), (0, Some(1)));
+
+    Ok(())
+}
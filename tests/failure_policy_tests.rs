@@ -0,0 +1,94 @@
+//! Handling of failed transactions in stateful batches
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use alloy::primitives::{address, Address, TxKind, U256};
+use revm::context::result::{ExecutionResult, Output};
+use revm_trace::{
+    errors::EvmError, traits::TransactionTrace, types::SimulationBatch, FailurePolicy, SimulationTx,
+};
+
+mod common;
+use common::{contract, ether, funded, TestEvm};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const BOB: Address = address!("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+const REVERTER: Address = address!("dead00000000000000000000000000000000dead");
+
+/// Reverts every call
+const REVERTER_CODE: &str = "60006000fd";
+
+fn evm() -> TestEvm {
+    common::evm(common::backend([
+        (SENDER, funded(ether())),
+        (REVERTER, contract(REVERTER_CODE)),
+    ]))
+}
+
+fn tx(transact_to: TxKind) -> SimulationTx {
+    SimulationTx {
+        caller: SENDER,
+        transact_to,
+        value: U256::ZERO,
+        data: Default::default(),
+        trace_config: None,
+        return_abi: None,
+    }
+}
+
+/// A transfer, a reverting call, then an empty deployment whose address reveals the
+/// sender nonce it ran with
+fn run(failure_policy: FailurePolicy) -> Vec<Result<ExecutionResult, EvmError>> {
+    evm()
+        .trace_transactions(SimulationBatch {
+            transactions: vec![
+                tx(TxKind::Call(BOB)),
+                tx(TxKind::Call(REVERTER)),
+                tx(TxKind::Create),
+            ],
+            is_stateful: true,
+            failure_policy,
+            ..Default::default()
+        })
+        .into_iter()
+        .map(|result| result.map(|(result, _, _)| result))
+        .collect()
+}
+
+fn deployed_address(result: &Result<ExecutionResult, EvmError>) -> Address {
+    match result {
+        Ok(ExecutionResult::Success {
+            output: Output::Create(_, Some(address)),
+            ..
+        }) => *address,
+        other => panic!("deployment failed: {other:?}"),
+    }
+}
+
+#[test]
+fn test_continue_committing_consumes_nonce() {
+    let results = run(FailurePolicy::ContinueCommitting);
+    assert!(results[0].as_ref().unwrap().is_success());
+    assert!(matches!(results[1], Ok(ExecutionResult::Revert { .. })));
+    assert_eq!(deployed_address(&results[2]), SENDER.create(2));
+}
+
+#[test]
+fn test_continue_skipping_commit_keeps_nonce() {
+    let results = run(FailurePolicy::ContinueSkippingCommit);
+    assert!(results[0].as_ref().unwrap().is_success());
+    assert!(matches!(results[1], Ok(ExecutionResult::Revert { .. })));
+    assert_eq!(deployed_address(&results[2]), SENDER.create(1));
+}
+
+#[test]
+fn test_abort_skips_remaining() {
+    let results = run(FailurePolicy::Abort);
+    assert!(results[0].as_ref().unwrap().is_success());
+    assert!(matches!(results[1], Ok(ExecutionResult::Revert { .. })));
+    assert!(matches!(
+        results[2],
+        Err(EvmError::BatchAborted { failed_index: 1 })
+    ));
+}
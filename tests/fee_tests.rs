@@ -0,0 +1,148 @@
+//! EIP-1559 fee and burn accounting of batches
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use alloy::primitives::{address, Address, TxKind, I256, U256};
+use revm_trace::{
+    evm::fees::TxFees, traits::TransactionTrace, types::SimulationBatch, SimulationTx, TxInspector,
+};
+
+mod common;
+use common::{contract, ether, funded, TestEvm};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const BOB: Address = address!("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+const CAROL: Address = address!("cccccccccccccccccccccccccccccccccccccccc");
+const PAYER: Address = address!("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+const COINBASE: Address = address!("c0ffee0000000000000000000000000000000000");
+
+/// Forwards the call value to `block.coinbase`
+const PAYER_CODE: &str = "600060006000600034415af100";
+
+const GWEI: u128 = 1_000_000_000;
+const BASE_FEE: u64 = 10 * GWEI as u64;
+
+/// EVM enforcing a base fee of 10 gwei in a block of 30M gas, where every
+/// transaction is an EIP-1559 transaction priced by its target
+fn evm(enforce_base_fee: bool) -> TestEvm {
+    let mut evm = common::evm_with(
+        common::backend([(SENDER, funded(ether())), (PAYER, contract(PAYER_CODE))]),
+        TxInspector::new(),
+        |ctx| {
+            ctx.cfg.disable_base_fee = !enforce_base_fee;
+            ctx.block.basefee = BASE_FEE;
+            ctx.block.gas_limit = 30_000_000;
+        },
+    );
+    evm.set_tx_hook(Box::new(|tx, input| {
+        // (max fee, max priority fee) per target
+        let (max_fee, priority_fee) = match input.transact_to {
+            TxKind::Call(to) if to == BOB => (30 * GWEI, 2 * GWEI),
+            TxKind::Call(to) if to == CAROL => (11 * GWEI, 5 * GWEI),
+            _ => (20 * GWEI, 3 * GWEI),
+        };
+        tx.tx_type = 2;
+        tx.gas_limit = 100_000;
+        tx.gas_price = max_fee;
+        tx.gas_priority_fee = Some(priority_fee);
+    }));
+    evm
+}
+
+fn batch(targets: &[Address]) -> SimulationBatch {
+    SimulationBatch {
+        transactions: targets
+            .iter()
+            .map(|target| SimulationTx {
+                caller: SENDER,
+                transact_to: TxKind::Call(*target),
+                value: U256::from(1_000),
+                data: Default::default(),
+                trace_config: None,
+                return_abi: None,
+            })
+            .collect(),
+        is_stateful: true,
+        coinbase: Some(COINBASE),
+        ..Default::default()
+    }
+}
+
+fn gwei(amount: u128) -> U256 {
+    U256::from(amount * GWEI)
+}
+
+#[test]
+fn test_burned_and_tipped_amounts() {
+    let mut evm = evm(true);
+    for result in evm.trace_transactions(batch(&[BOB, CAROL])) {
+        assert!(result.unwrap().0.is_success());
+    }
+    let fees = evm
+        .take_last_fee_accounting()
+        .expect("base fee is enforced");
+
+    assert_eq!(fees.base_fee, BASE_FEE);
+    assert_eq!(fees.coinbase, COINBASE);
+    assert_eq!(
+        fees.per_tx,
+        vec![
+            // min(30, 10 + 2) gwei
+            Some(TxFees {
+                gas_used: 21_000,
+                effective_gas_price: 12 * GWEI,
+                burned: gwei(210_000),
+                tip: gwei(42_000),
+                direct_payments: Some(U256::ZERO),
+                coinbase_delta: I256::from_raw(gwei(42_000)),
+            }),
+            // min(11, 10 + 5) gwei
+            Some(TxFees {
+                gas_used: 21_000,
+                effective_gas_price: 11 * GWEI,
+                burned: gwei(210_000),
+                tip: gwei(21_000),
+                direct_payments: Some(U256::ZERO),
+                coinbase_delta: I256::from_raw(gwei(21_000)),
+            }),
+        ]
+    );
+    assert_eq!(fees.gas_used, 42_000);
+    assert_eq!(fees.total_burned, gwei(420_000));
+    assert_eq!(fees.total_tips, gwei(63_000));
+    assert_eq!(fees.coinbase_delta, I256::from_raw(gwei(63_000)));
+    // 42k gas against a 15M target: 10 gwei - 10 gwei * 14_958_000 / 15M / 8
+    assert_eq!(fees.next_base_fee, 8_753_500_000);
+    assert!(fees.is_consistent());
+
+    // Taken once
+    assert!(evm.take_last_fee_accounting().is_none());
+}
+
+#[test]
+fn test_direct_payments_reconcile_with_coinbase() {
+    let mut evm = evm(true);
+    for result in evm.trace_transactions(batch(&[PAYER])) {
+        assert!(result.unwrap().0.is_success());
+    }
+    let fees = evm.take_last_fee_accounting().unwrap();
+
+    let tx = fees.per_tx[0].unwrap();
+    assert_eq!(tx.effective_gas_price, 13 * GWEI);
+    assert_eq!(tx.direct_payments, Some(U256::from(1_000)));
+    assert_eq!(
+        tx.coinbase_delta,
+        I256::from_raw(tx.tip + U256::from(1_000))
+    );
+    assert!(fees.is_consistent());
+}
+
+#[test]
+fn test_no_accounting_without_base_fee() {
+    let mut evm = evm(false);
+    for result in evm.trace_transactions(batch(&[BOB])) {
+        assert!(result.unwrap().0.is_success());
+    }
+    assert!(evm.take_last_fee_accounting().is_none());
+}
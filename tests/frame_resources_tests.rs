@@ -0,0 +1,109 @@
+//! Per-frame memory and stack peaks
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use alloy::primitives::{address, hex, Address, TxKind, U256};
+use revm::{
+    database::{CacheDB, EmptyDB},
+    state::{AccountInfo, Bytecode},
+    Context, MainBuilder, MainContext,
+};
+use revm_trace::{
+    traits::TransactionTrace,
+    types::{CallTrace, FrameResources, SimulationBatch},
+    SimulationTx, TraceEvm, TxInspector,
+};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const PARENT: Address = address!("2222222222222222222222222222222222222222");
+const ALLOCATOR: Address = address!("3333333333333333333333333333333333333333");
+
+/// Calls ALLOCATOR without data, with seven items on the stack at the CALL
+const PARENT_CODE: &str = "600060006000600060007333333333333333333333333333333333333333335af100";
+
+/// Writes a word ending at 64 KiB: `PUSH1 1 PUSH3 0xffe0 MSTORE STOP`
+const ALLOCATOR_CODE: &str = "60016200ffe05200";
+
+fn install(backend: &mut CacheDB<EmptyDB>, address: Address, code: &str) {
+    let code = Bytecode::new_raw(hex::decode(code).unwrap().into());
+    backend.insert_account_info(
+        address,
+        AccountInfo {
+            code_hash: code.hash_slow(),
+            code: Some(code),
+            ..Default::default()
+        },
+    );
+}
+
+fn trace() -> CallTrace {
+    let mut backend = CacheDB::new(EmptyDB::default());
+    install(&mut backend, PARENT, PARENT_CODE);
+    install(&mut backend, ALLOCATOR, ALLOCATOR_CODE);
+    backend.insert_account_info(
+        SENDER,
+        AccountInfo {
+            balance: U256::from(10).pow(U256::from(18)),
+            ..Default::default()
+        },
+    );
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(backend));
+    ctx.cfg.disable_base_fee = true;
+    let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(TxInspector::new()));
+
+    let mut results = evm.trace_transactions(SimulationBatch {
+        transactions: vec![SimulationTx {
+            caller: SENDER,
+            transact_to: TxKind::Call(PARENT),
+            value: U256::ZERO,
+            data: Default::default(),
+            trace_config: None,
+            return_abi: None,
+        }],
+        is_stateful: false,
+        ..Default::default()
+    });
+    let (result, _, output) = results.remove(0).expect("transaction executes");
+    assert!(result.is_success(), "{result:?}");
+    output.call_trace.expect("call trace")
+}
+
+#[test]
+fn test_peaks_are_per_frame() {
+    let root = trace();
+    // 2048 words: 3 * 2048 + 2048² / 512
+    assert_eq!(
+        root.subtraces[0].resource_stats,
+        Some(FrameResources {
+            peak_memory_bytes: 65_536,
+            peak_stack_depth: 2,
+            mem_expansion_gas: 14_336,
+        })
+    );
+    // The allocation does not count towards the caller
+    assert_eq!(
+        root.resource_stats,
+        Some(FrameResources {
+            peak_memory_bytes: 0,
+            peak_stack_depth: 7,
+            mem_expansion_gas: 0,
+        })
+    );
+}
+
+#[test]
+fn test_stored_traces_without_stats() {
+    let root = trace();
+    let mut json = serde_json::to_value(&root).unwrap();
+    assert!(json["resource_stats"].is_object());
+
+    // Traces stored before the stats were recorded read back without them
+    json.as_object_mut().unwrap().remove("resource_stats");
+    let stored: CallTrace = serde_json::from_value(json).unwrap();
+    assert_eq!(stored.resource_stats, None);
+    assert_eq!(
+        stored.subtraces[0].resource_stats,
+        root.subtraces[0].resource_stats
+    );
+}
@@ -0,0 +1,107 @@
+//! Hot frames, hot slots and cold accesses reported by `analysis::gas_profile`
+//!
+//! Runs on an in-memory backend, so no RPC access is needed.
+
+use alloy::primitives::{address, Address, TxKind, U256};
+use revm_trace::{
+    analysis::{gas_profile, gas_profile_with_config, GasProfileConfig, WarmColdSummary},
+    traits::TransactionTrace,
+    types::SimulationBatch,
+    SimulationTx,
+};
+
+mod common;
+use common::{contract, TestEvm};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const LOOP: Address = address!("1001001001001001001001001001001001001001");
+
+/// Reads slot 0 five times in a loop, then reads slot 1 once and writes slot 0
+const LOOP_CODE: &str = "60055b6000545060019003806002575060015450600160005500";
+
+fn evm() -> TestEvm {
+    common::evm(common::backend([(LOOP, contract(LOOP_CODE))]))
+}
+
+fn batch(count: usize) -> SimulationBatch {
+    let tx = SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Call(LOOP),
+        value: U256::ZERO,
+        data: Default::default(),
+        trace_config: None,
+        return_abi: None,
+    };
+    SimulationBatch {
+        transactions: vec![tx; count],
+        is_stateful: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_loop_reads_one_cold_slot() {
+    let mut evm = evm();
+    let results = evm.trace_transactions(batch(1));
+    let (result, output, _) = results[0].as_ref().unwrap();
+    assert!(result.is_success());
+
+    let profile = gas_profile(output);
+    assert_eq!(profile.slots.len(), 2);
+
+    let looped = &profile.slots[0];
+    assert_eq!((looped.address, looped.slot), (LOOP, U256::ZERO));
+    assert_eq!((looped.reads, looped.writes), (5, 1));
+    assert_eq!(looped.cold_accesses, 1);
+
+    let once = &profile.slots[1];
+    assert_eq!((once.address, once.slot), (LOOP, U256::from(1)));
+    assert_eq!((once.reads, once.writes), (1, 0));
+    assert_eq!(once.cold_accesses, 1);
+
+    assert_eq!(
+        profile.warm_cold_summary,
+        WarmColdSummary {
+            cold_reads: 2,
+            warm_reads: 4,
+            cold_writes: 0,
+            warm_writes: 1,
+        }
+    );
+
+    // A single frame burning everything itself
+    assert_eq!(profile.frames.len(), 1);
+    let frame = &profile.frames[0];
+    assert_eq!(frame.to, LOOP);
+    assert_eq!(frame.selector, None);
+    assert_eq!(frame.self_gas, frame.total_gas);
+    assert!(frame.self_gas > 0);
+}
+
+#[test]
+fn test_slots_are_cold_again_in_next_transaction() {
+    let mut evm = evm();
+    let results = evm.trace_transactions(batch(2));
+    for result in &results {
+        let (_, output, _) = result.as_ref().unwrap();
+        let summary = gas_profile(output).warm_cold_summary;
+        assert_eq!((summary.cold_reads, summary.warm_reads), (2, 4));
+    }
+}
+
+#[test]
+fn test_profile_is_truncated() {
+    let mut evm = evm();
+    let results = evm.trace_transactions(batch(1));
+    let (_, output, _) = results[0].as_ref().unwrap();
+
+    let config = GasProfileConfig {
+        max_frames: 0,
+        max_slots: 1,
+    };
+    let profile = gas_profile_with_config(output, &config);
+    assert!(profile.frames.is_empty());
+    assert_eq!(profile.slots.len(), 1);
+    assert_eq!(profile.slots[0].slot, U256::ZERO);
+    assert_eq!(profile.warm_cold_summary.warm_reads, 4);
+}
@@ -0,0 +1,77 @@
+//! Gas refund reporting for storage-clearing transactions
+//!
+//! Runs on an in-memory backend, so no RPC access is needed.
+
+use alloy::primitives::{address, hex, Address, Bytes, TxKind, U256};
+use revm::{
+    database::{CacheDB, EmptyDB},
+    Context, MainBuilder, MainContext,
+};
+use revm_trace::{
+    report::SimulationReport,
+    types::{SimulationBatch, TargetRef},
+    SimulationTx, TraceEvm, TxInspector,
+};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+
+/// Deploys a contract whose constructor sets slots 0..5 to 1 and whose runtime code
+/// clears them again
+const CLEARING_BYTECODE: &str = "60016000556001600155600160025560016003556001600455601a8060246000396000f36000600055600060015560006002556000600355600060045500";
+
+/// EIP-3529 refund for clearing a slot that was non-zero at the start of the transaction
+const SSTORE_CLEARS_SCHEDULE: i64 = 4_800;
+
+fn tx(transact_to: TxKind, data: Bytes) -> SimulationTx {
+    SimulationTx {
+        caller: SENDER,
+        value: U256::ZERO,
+        data,
+        transact_to,
+        trace_config: None,
+        return_abi: None,
+    }
+}
+
+#[test]
+fn test_refunds_of_cleared_slots() {
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(EmptyDB::default()));
+    ctx.cfg.disable_base_fee = true;
+    let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(TxInspector::new()));
+
+    let transactions = vec![
+        tx(
+            TxKind::Create,
+            hex::decode(CLEARING_BYTECODE).unwrap().into(),
+        ),
+        // Placeholder target, replaced by the contract created above
+        tx(TxKind::Call(Address::ZERO), Bytes::new()),
+    ];
+    let batch = SimulationBatch {
+        transactions,
+        is_stateful: true,
+        ..Default::default()
+    }
+    .with_target(1, TargetRef::CreatedBy { tx_index: 0 });
+    // The call is handed over with its resolved target
+    let mut report = None;
+    evm.trace_transactions_with(batch, |_, tx, _, result| {
+        let (result, diff, trace) = result.expect("transaction executes");
+        report = Some(SimulationReport::new(tx, &result, diff, trace));
+    });
+    let report = report.unwrap();
+    assert_eq!(report.tx.transact_to, TxKind::Call(SENDER.create(0)));
+
+    // Five cleared slots, all refunded by the clearing frame itself
+    let root = report.trace.call_trace.as_ref().unwrap();
+    assert_eq!(root.refund_counter_delta, 5 * SSTORE_CLEARS_SCHEDULE);
+
+    // The refund counter exceeds a fifth of the gas spent, so the cap binds
+    let summary = &report.summary;
+    assert!(summary.success);
+    assert!(summary.gas_refunded > 0);
+    assert_eq!(summary.gas_spent, summary.gas_used + summary.gas_refunded);
+    assert_eq!(summary.gas_refunded, summary.gas_spent / 5);
+    assert!((summary.gas_refunded as i64) < root.refund_counter_delta);
+    assert!(summary.refund_capped);
+}
@@ -0,0 +1,189 @@
+//! Handling of nodes that do not serve historical state
+//!
+//! Runs against a mocked provider answering with the errors of a pruned node, so no
+//! RPC access is needed.
+
+use alloy::{
+    eips::BlockId,
+    network::AnyNetwork,
+    primitives::{Address, Bloom, TxKind, B256, U256, U64},
+    providers::{mock::Asserter, ProviderBuilder},
+};
+use revm::{
+    database::{AlloyDB, CacheDB},
+    Context, MainBuilder, MainContext,
+};
+use revm_trace::{
+    errors::{EvmError, RuntimeError},
+    evm::builder::apply_historical_fallback,
+    is_missing_state_error,
+    traits::TransactionTrace,
+    types::{BlockContext, SimulationBatch},
+    Fallback, MyWrapDatabaseAsync, SimulationTx, TraceEvm, TxInspector,
+};
+use serde_json::json;
+
+/// geth's answer for state it has pruned
+const MISSING_TRIE_NODE: &str =
+    "missing trie node 5d3cd6cd36e2e8c7ee67d1e1bb29b8cde6b34c7ec9fd3b6d1f6d3d54b8dcd7c0 (path )";
+
+fn block_context(number: u64) -> BlockContext {
+    BlockContext {
+        number,
+        hash: B256::repeat_byte(number as u8),
+        timestamp: 1_700_000_000 + number,
+        chain_id: 1,
+        tag: None,
+    }
+}
+
+/// Minimal `eth_getBlockByNumber` response
+fn block_json(number: u64) -> serde_json::Value {
+    json!({
+        "hash": B256::repeat_byte(number as u8),
+        "parentHash": B256::ZERO,
+        "sha3Uncles": B256::ZERO,
+        "miner": Address::ZERO,
+        "stateRoot": B256::ZERO,
+        "transactionsRoot": B256::ZERO,
+        "receiptsRoot": B256::ZERO,
+        "logsBloom": Bloom::ZERO,
+        "difficulty": "0x0",
+        "number": U64::from(number),
+        "gasLimit": "0x1c9c380",
+        "gasUsed": "0x0",
+        "timestamp": U64::from(1_700_000_000 + number),
+        "extraData": "0x",
+        "mixHash": B256::ZERO,
+        "nonce": "0x0000000000000000",
+        "uncles": [],
+        "transactions": [],
+    })
+}
+
+#[test]
+fn test_missing_state_messages() {
+    for message in [
+        MISSING_TRIE_NODE,
+        "historical state not available in path scheme yet",
+        "Missing trie node 0x1b5f",
+        "old data not available due to pruning",
+        "No state available for block 0x12d687",
+        "World state not available for block 15000000",
+    ] {
+        assert!(is_missing_state_error(message), "{message}");
+    }
+    for message in [
+        "header not found",
+        "execution reverted",
+        "rate limit exceeded",
+    ] {
+        assert!(!is_missing_state_error(message), "{message}");
+    }
+}
+
+/// Execution against a pruned block fails with a typed error instead of a
+/// stringly execution failure
+#[tokio::test(flavor = "multi_thread")]
+async fn test_missing_state_is_typed_during_execution() {
+    let asserter = Asserter::new();
+    let provider = ProviderBuilder::new()
+        .network::<AnyNetwork>()
+        .connect_mocked_client(asserter.clone());
+    let db = MyWrapDatabaseAsync::new(AlloyDB::new(provider, BlockId::number(100))).unwrap();
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(db));
+    ctx.block.number = 100;
+    ctx.cfg.disable_base_fee = true;
+    let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(TxInspector::new()));
+
+    // Nonce, balance and code of the sender are fetched together
+    for _ in 0..3 {
+        asserter.push_failure_msg(MISSING_TRIE_NODE);
+    }
+    let results = evm.trace_transactions(SimulationBatch {
+        is_stateful: false,
+        transactions: vec![SimulationTx {
+            caller: Address::repeat_byte(1),
+            transact_to: TxKind::Call(Address::repeat_byte(2)),
+            value: U256::ZERO,
+            data: Default::default(),
+            trace_config: None,
+            return_abi: None,
+        }],
+        ..Default::default()
+    });
+    assert!(matches!(
+        results.into_iter().next().unwrap(),
+        Err(EvmError::Runtime(
+            RuntimeError::HistoricalStateUnavailable { block: 100 }
+        ))
+    ));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fallback_error() {
+    let asserter = Asserter::new();
+    let provider = ProviderBuilder::new()
+        .network::<AnyNetwork>()
+        .connect_mocked_client(asserter.clone());
+
+    asserter.push_failure_msg(MISSING_TRIE_NODE);
+    let result = apply_historical_fallback(&provider, block_context(100), Fallback::Error).await;
+    assert!(matches!(
+        result,
+        Err(EvmError::Runtime(
+            RuntimeError::HistoricalStateUnavailable { block: 100 }
+        ))
+    ));
+
+    // Available state keeps the requested block whatever the policy
+    asserter.push_success(&U256::from(1));
+    let block = apply_historical_fallback(&provider, block_context(100), Fallback::UseLatest)
+        .await
+        .unwrap();
+    assert_eq!(block, block_context(100));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fallback_use_latest() {
+    let asserter = Asserter::new();
+    let provider = ProviderBuilder::new()
+        .network::<AnyNetwork>()
+        .connect_mocked_client(asserter.clone());
+
+    // Probe, then chain id, latest number and header of the latest block
+    asserter.push_failure_msg(MISSING_TRIE_NODE);
+    asserter.push_success(&U64::from(1));
+    asserter.push_success(&U64::from(200));
+    asserter.push_success(&block_json(200));
+
+    let block = apply_historical_fallback(&provider, block_context(100), Fallback::UseLatest)
+        .await
+        .unwrap();
+    assert_eq!(block, block_context(200));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fallback_nearest_available() {
+    let asserter = Asserter::new();
+    let provider = ProviderBuilder::new()
+        .network::<AnyNetwork>()
+        .connect_mocked_client(asserter.clone());
+
+    // 100 and 150 are pruned, 200 is available, latest is 1000
+    asserter.push_failure_msg(MISSING_TRIE_NODE);
+    asserter.push_success(&U64::from(1000));
+    asserter.push_failure_msg(MISSING_TRIE_NODE);
+    asserter.push_success(&U256::from(1));
+    asserter.push_success(&U64::from(1));
+    asserter.push_success(&block_json(200));
+
+    let block = apply_historical_fallback(
+        &provider,
+        block_context(100),
+        Fallback::NearestAvailable { probe_step: 50 },
+    )
+    .await
+    .unwrap();
+    assert_eq!(block, block_context(200));
+}
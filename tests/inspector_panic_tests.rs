@@ -0,0 +1,118 @@
+//! Recovery from panics in inspector hooks
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use alloy::primitives::{address, hex, Address, TxKind, U256};
+use revm::{
+    context::ContextTr,
+    database::{CacheDB, Database, EmptyDB},
+    interpreter::{interpreter_types::InterpreterTypes, CallInputs, CallOutcome},
+    state::{AccountInfo, Bytecode},
+    Context, Inspector, MainBuilder, MainContext,
+};
+use revm_trace::{
+    errors::{EvmError, RuntimeError},
+    traits::{Reset, TraceOutput, TransactionTrace},
+    types::SimulationBatch,
+    SimulationTx, TraceEvm,
+};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const BOB: Address = address!("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+const FORWARDER: Address = address!("c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0");
+
+/// `CALL(GAS, BOB, 0, 0, 0, 0, 0)`
+const FORWARDER_CODE: &str = concat!(
+    "6000600060006000600073",
+    "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb",
+    "5af100"
+);
+
+/// Counts the call frames of a transaction and panics on the second one
+#[derive(Default)]
+struct PanicOnSecondCall {
+    calls: usize,
+}
+
+impl<CTX: ContextTr, INTR: InterpreterTypes> Inspector<CTX, INTR> for PanicOnSecondCall {
+    fn call(&mut self, _context: &mut CTX, _inputs: &mut CallInputs) -> Option<CallOutcome> {
+        self.calls += 1;
+        if self.calls == 2 {
+            panic!("second call of the transaction");
+        }
+        None
+    }
+}
+
+impl Reset for PanicOnSecondCall {
+    fn reset(&mut self) {
+        self.calls = 0;
+    }
+
+    fn reset_slot_cache(&mut self) {}
+}
+
+impl TraceOutput for PanicOnSecondCall {
+    type Output = usize;
+
+    fn get_output(&self) -> Self::Output {
+        self.calls
+    }
+}
+
+fn call(to: Address) -> SimulationTx {
+    SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Call(to),
+        value: U256::ZERO,
+        data: Default::default(),
+        trace_config: None,
+        return_abi: None,
+    }
+}
+
+#[test]
+fn test_panicking_inspector_fails_only_its_transaction() {
+    let mut backend = CacheDB::new(EmptyDB::default());
+    backend.insert_account_info(
+        SENDER,
+        AccountInfo {
+            balance: U256::from(10).pow(U256::from(18)),
+            ..Default::default()
+        },
+    );
+    let code = Bytecode::new_raw(hex::decode(FORWARDER_CODE).unwrap().into());
+    backend.insert_account_info(
+        FORWARDER,
+        AccountInfo {
+            code_hash: code.hash_slow(),
+            code: Some(code),
+            ..Default::default()
+        },
+    );
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(backend));
+    ctx.cfg.disable_base_fee = true;
+    let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(PanicOnSecondCall::default()));
+
+    let mut results = evm.trace_transactions(SimulationBatch {
+        transactions: vec![call(FORWARDER), call(BOB)],
+        is_stateful: true,
+        ..Default::default()
+    });
+
+    // The forwarder's subcall is the second call frame
+    match results.remove(0) {
+        Err(EvmError::Runtime(RuntimeError::InspectorPanic { message })) => {
+            assert_eq!(message, "second call of the transaction");
+        }
+        other => panic!("expected an inspector panic, got {other:?}"),
+    }
+    let (result, _, calls) = results.remove(0).expect("the second transaction runs");
+    assert!(result.is_success());
+    assert_eq!(calls, 1);
+
+    // Only the second transaction was committed
+    let sender = evm.db().basic(SENDER).unwrap().unwrap();
+    assert_eq!(sender.nonce, 1);
+}
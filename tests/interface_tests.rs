@@ -0,0 +1,77 @@
+//! Integration tests for interface detection of counterparties
+//!
+//! Runs against mainnet: BAYC as an ERC-165 compliant NFT, the ENS multisig as a Safe
+//! and vitalik.eth as an account without code.
+
+use alloy::primitives::{address, Address};
+use revm_trace::{
+    utils::interface_utils::{
+        detect_interfaces, detect_interfaces_batch, Confidence, InterfaceProbe, COMMON_PROBES,
+        ERC1155_INTERFACE_ID, ERC721_INTERFACE_ID,
+    },
+    EvmBuilder,
+};
+
+const ETH_RPC_URL: &str = "https://eth.llamarpc.com";
+const BLOCK_NUMBER: u64 = 18_000_000;
+const BAYC: Address = address!("BC4CA0EdA7647A8aB7C2061c2E118A18a936f13D");
+const ENS_SAFE: Address = address!("Fe89cc7aBB2C4183683ab71653C4cdc9B02D44b7");
+const EOA: Address = address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045");
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_classifies_nft_safe_and_eoa() -> anyhow::Result<()> {
+    let mut evm = EvmBuilder::new_alloy(ETH_RPC_URL)
+        .with_block_number(BLOCK_NUMBER)
+        .build()
+        .await?;
+
+    let nft = detect_interfaces(&mut evm, BAYC, &COMMON_PROBES)?;
+    let erc721 = nft
+        .iter()
+        .find(|d| d.probe == InterfaceProbe::Erc165(ERC721_INTERFACE_ID))
+        .expect("BAYC must declare ERC-721");
+    assert_eq!(erc721.confidence, Confidence::Erc165Confirmed);
+    assert!(nft
+        .iter()
+        .all(|d| d.probe != InterfaceProbe::Erc165(ERC1155_INTERFACE_ID)));
+    assert!(nft.iter().any(|d| d.probe == InterfaceProbe::BalanceOf));
+    assert!(nft.iter().all(|d| d.probe != InterfaceProbe::SafeOwners));
+
+    let safe = detect_interfaces(&mut evm, ENS_SAFE, &COMMON_PROBES)?;
+    let probes: Vec<_> = safe.iter().map(|d| d.probe).collect();
+    assert!(probes.contains(&InterfaceProbe::SafeOwners));
+    assert!(probes.contains(&InterfaceProbe::Erc1271));
+    assert!(safe
+        .iter()
+        .all(|d| d.confidence == Confidence::HeuristicOnly));
+
+    assert!(detect_interfaces(&mut evm, EOA, &COMMON_PROBES)?.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_batch_matches_single_detection() -> anyhow::Result<()> {
+    let mut evm = EvmBuilder::new_alloy(ETH_RPC_URL)
+        .with_block_number(BLOCK_NUMBER)
+        .build()
+        .await?;
+
+    let addresses = [BAYC, ENS_SAFE, EOA];
+    let batched = detect_interfaces_batch(&mut evm, &addresses, &COMMON_PROBES)?;
+    assert_eq!(batched.len(), addresses.len());
+    for (address, detections) in addresses.iter().zip(batched) {
+        assert_eq!(
+            detections,
+            detect_interfaces(&mut evm, *address, &COMMON_PROBES)?,
+            "{address}"
+        );
+    }
+
+    assert_eq!(
+        detect_interfaces_batch(&mut evm, &addresses, &[])?,
+        vec![Vec::new(); 3]
+    );
+
+    Ok(())
+}
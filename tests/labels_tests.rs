@@ -0,0 +1,112 @@
+//! Tests for known-contract labels and code fingerprints
+//!
+//! Run with `cargo test --features labels`.
+#![cfg(feature = "labels")]
+
+use std::sync::Arc;
+
+use alloy::primitives::{address, hex, keccak256, Address, TxKind, B256, U256};
+use revm::{
+    database::{CacheDB, EmptyDB},
+    state::AccountInfo,
+    Context, MainBuilder, MainContext,
+};
+use revm_trace::{
+    create_evm_with_tracer,
+    labels::{codehash_fingerprint, enrich_report, LabelBook},
+    report::SimulationReport,
+    traits::TransactionTrace,
+    types::SimulationBatch,
+    SimulationTx, TraceEvm, TxInspector,
+};
+
+const ETH_RPC_URL: &str = "https://eth.llamarpc.com";
+const UNISWAP_V2_ROUTER: Address = address!("7a250d5630B4cF539739dF2C5dAcb4c659F2488D");
+const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+
+/// Deploys `RUNTIME_CODE`
+const INIT_CODE: &str = "600a600c600039600a6000f360005460010160005500";
+/// Increments slot 0
+const RUNTIME_CODE: &str = "60005460010160005500";
+
+fn batch(tx: SimulationTx) -> SimulationBatch {
+    SimulationBatch {
+        transactions: vec![tx],
+        is_stateful: true,
+        ..Default::default()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_label_uniswap_router() -> anyhow::Result<()> {
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+    let tx = SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Call(UNISWAP_V2_ROUTER),
+        value: U256::ZERO,
+        // WETH()
+        data: hex::decode("ad5c4648")?.into(),
+        trace_config: None,
+        return_abi: None,
+    };
+    let (result, diff, output) = evm.trace_transactions(batch(tx.clone())).remove(0)?;
+    assert!(result.is_success());
+
+    let book = Arc::new(LabelBook::builtin());
+    let report = SimulationReport::new(tx, &result, diff, output);
+    let report = enrich_report(&mut evm, report, &book)?;
+
+    let router = &report.address_hints[&UNISWAP_V2_ROUTER];
+    assert_eq!(router.label.as_deref(), Some("Uniswap V2: Router"));
+    assert_eq!(router.category.as_deref(), Some("router"));
+    assert!(router.code_fingerprint.is_some());
+    // An account with neither code nor label gets no entry
+    assert!(!report.address_hints.contains_key(&SENDER));
+    assert!(book.get(1, WETH).is_some());
+    Ok(())
+}
+
+/// Deploys `INIT_CODE` on a fresh in-memory EVM and returns the fingerprint of the
+/// new contract, checking the report carries the same
+fn deploy_and_fingerprint(nonce: u64) -> Option<B256> {
+    let mut backend = CacheDB::new(EmptyDB::default());
+    backend.insert_account_info(
+        SENDER,
+        AccountInfo {
+            nonce,
+            ..Default::default()
+        },
+    );
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(backend));
+    ctx.cfg.disable_base_fee = true;
+    let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(TxInspector::new()));
+
+    let tx = SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Create,
+        value: U256::ZERO,
+        data: hex::decode(INIT_CODE).unwrap().into(),
+        trace_config: None,
+        return_abi: None,
+    };
+    let (result, diff, output) = evm.trace_transactions(batch(tx.clone())).remove(0).unwrap();
+    assert!(result.is_success());
+    let created = SENDER.create(nonce);
+
+    let report = SimulationReport::new(tx, &result, diff, output);
+    let report = enrich_report(&mut evm, report, &LabelBook::new()).unwrap();
+    let fingerprint = codehash_fingerprint(&mut evm, created).unwrap();
+    assert_eq!(report.address_hints[&created].code_fingerprint, fingerprint);
+    assert_eq!(report.address_hints[&created].label, None);
+    fingerprint
+}
+
+#[test]
+fn test_fingerprint_is_stable_across_simulations() {
+    // Different nonces deploy the same code at different addresses
+    let first = deploy_and_fingerprint(0);
+    let second = deploy_and_fingerprint(7);
+    assert_eq!(first, Some(keccak256(hex::decode(RUNTIME_CODE).unwrap())));
+    assert_eq!(first, second);
+}
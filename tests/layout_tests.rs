@@ -0,0 +1,191 @@
+//! Labeling storage diffs with storage layouts
+//!
+//! Run with `cargo test --features storage-layout`. Runs on an in-memory backend, so
+//! no RPC access is needed.
+#![cfg(feature = "storage-layout")]
+
+use std::collections::HashMap;
+
+use alloy::{
+    primitives::{address, hex, keccak256, Address, TxKind, B256, U256},
+    sol,
+    sol_types::SolCall,
+};
+use revm::{
+    database::{CacheDB, EmptyDB},
+    state::{AccountInfo, Bytecode},
+    Context, MainBuilder, MainContext,
+};
+use revm_trace::{
+    layout::{LabelStorage, StorageLayout},
+    traits::TransactionTrace,
+    types::SimulationBatch,
+    SimulationTx, TraceEvm, TxInspector,
+};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const TOKEN: Address = address!("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+const HOLDER: Address = address!("cafe00000000000000000000000000000000face");
+
+/// Sets `balances[HOLDER] = 100`, `balances` being the mapping at slot 1
+const CREDIT_HOLDER: &str =
+    "73cafe00000000000000000000000000000000face600052600160205260406000206064905500";
+
+/// `transfer(to, amount)` moving `amount` from `balances[msg.sender]` to
+/// `balances[to]` (mapping at slot 1) and emitting `Transfer(msg.sender, to, amount)`
+const TRANSFER_TOKEN: &str = concat!(
+    "336000526001602052604060002080546024359003905",
+    "56004356000526040600020805460243501905",
+    "5602435600052600435337f",
+    "ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+    "60206000a300",
+);
+
+const TOKEN_LAYOUT: &str = r#"{
+    "storage": [
+        {"astId": 1, "contract": "Token.sol:Token", "label": "owner", "offset": 0, "slot": "0", "type": "t_address"},
+        {"astId": 2, "contract": "Token.sol:Token", "label": "balances", "offset": 0, "slot": "1", "type": "t_mapping(t_address,t_uint256)"}
+    ],
+    "types": {
+        "t_address": {"encoding": "inplace", "label": "address", "numberOfBytes": "20"},
+        "t_mapping(t_address,t_uint256)": {"encoding": "mapping", "key": "t_address", "label": "mapping(address => uint256)", "numberOfBytes": "32", "value": "t_uint256"},
+        "t_uint256": {"encoding": "inplace", "label": "uint256", "numberOfBytes": "32"}
+    }
+}"#;
+
+#[test]
+fn test_mapping_entry_is_labeled_from_preimages() -> anyhow::Result<()> {
+    let mut backend = CacheDB::new(EmptyDB::default());
+    let code = Bytecode::new_raw(hex::decode(CREDIT_HOLDER)?.into());
+    backend.insert_account_info(
+        TOKEN,
+        AccountInfo {
+            code_hash: code.hash_slow(),
+            code: Some(code),
+            ..Default::default()
+        },
+    );
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(backend));
+    ctx.cfg.disable_base_fee = true;
+    let mut evm = TraceEvm::new(
+        ctx.build_mainnet_with_inspector(TxInspector::new().with_mapping_resolution(true)),
+    );
+
+    let mut results = evm.trace_transactions(SimulationBatch {
+        is_stateful: false,
+        transactions: vec![SimulationTx {
+            caller: SENDER,
+            transact_to: TxKind::Call(TOKEN),
+            value: U256::ZERO,
+            data: Default::default(),
+            trace_config: None,
+            return_abi: None,
+        }],
+        ..Default::default()
+    });
+    let (result, diff, output) = results.remove(0)?;
+    assert!(result.is_success(), "{result:?}");
+
+    let preimage = [
+        HOLDER.into_word().as_slice(),
+        B256::with_last_byte(1).as_slice(),
+    ]
+    .concat();
+    let hash = keccak256(&preimage);
+    assert_eq!(output.keccak_preimages[&hash].as_ref(), preimage.as_slice());
+    let slot = format!("{:#x}", U256::from_be_bytes(hash.0));
+
+    let layouts = HashMap::from([(TOKEN, StorageLayout::from_solc_json(TOKEN_LAYOUT)?)]);
+    let labeled = diff.label_with_preimages(&layouts, &output.keccak_preimages);
+    assert_eq!(
+        labeled[&TOKEN][0].to_string(),
+        format!("balances[{HOLDER}] (slot {slot}): 0 → 100")
+    );
+
+    // Without the preimages the slot keeps its raw form
+    let raw = diff.label_with(&layouts);
+    assert_eq!(
+        raw[&TOKEN][0].to_string(),
+        format!("slot {slot}: 0x0 → 0x64")
+    );
+    Ok(())
+}
+
+#[test]
+fn test_transfer_resolves_both_balances() -> anyhow::Result<()> {
+    sol! {
+        function transfer(address to, uint256 amount) external returns (bool);
+    }
+
+    let sender_slot = keccak256(
+        [
+            SENDER.into_word().as_slice(),
+            B256::with_last_byte(1).as_slice(),
+        ]
+        .concat(),
+    );
+    let mut backend = CacheDB::new(EmptyDB::default());
+    let code = Bytecode::new_raw(hex::decode(TRANSFER_TOKEN)?.into());
+    backend.insert_account_info(
+        TOKEN,
+        AccountInfo {
+            code_hash: code.hash_slow(),
+            code: Some(code),
+            ..Default::default()
+        },
+    );
+    backend.insert_account_storage(TOKEN, U256::from_be_bytes(sender_slot.0), U256::from(1_000))?;
+    let run = |inspector: TxInspector| {
+        let mut ctx = Context::mainnet().with_db(CacheDB::new(backend.clone()));
+        ctx.cfg.disable_base_fee = true;
+        let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(inspector));
+        evm.trace_transactions(SimulationBatch {
+            is_stateful: false,
+            transactions: vec![SimulationTx {
+                caller: SENDER,
+                transact_to: TxKind::Call(TOKEN),
+                value: U256::ZERO,
+                data: transferCall {
+                    to: HOLDER,
+                    amount: U256::from(100),
+                }
+                .abi_encode()
+                .into(),
+                trace_config: None,
+                return_abi: None,
+            }],
+            ..Default::default()
+        })
+        .remove(0)
+    };
+
+    let (result, diff, output) = run(TxInspector::new().with_mapping_resolution(true))?;
+    assert!(result.is_success(), "{result:?}");
+    assert_eq!(output.asset_transfers.len(), 1);
+
+    let layouts = HashMap::from([(TOKEN, StorageLayout::from_solc_json(TOKEN_LAYOUT)?)]);
+    let labeled = diff.label_with_preimages(&layouts, &output.keccak_preimages);
+    let mut variables: Vec<_> = labeled[&TOKEN]
+        .iter()
+        .flat_map(|slot| &slot.variables)
+        .map(|variable| {
+            (
+                variable.label.clone(),
+                variable.old_value.clone(),
+                variable.new_value.clone(),
+            )
+        })
+        .collect();
+    variables.sort();
+    let mut expected = vec![
+        (format!("balances[{SENDER}]"), "1000".into(), "900".into()),
+        (format!("balances[{HOLDER}]"), "0".into(), "100".into()),
+    ];
+    expected.sort();
+    assert_eq!(variables, expected);
+
+    // Capture is off by default, leaving the slots unresolved
+    let (_, _, output) = run(TxInspector::new())?;
+    assert!(output.keccak_preimages.is_empty());
+    Ok(())
+}
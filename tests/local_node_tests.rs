@@ -0,0 +1,153 @@
+//! Simulating against a local Anvil node and replaying on it
+//!
+//! The replay test spawns `anvil` from `PATH` and is ignored by default. Run it with
+//! `cargo test --test local_node_tests -- --ignored`.
+
+use std::{
+    process::{Child, Command, Stdio},
+    time::Duration,
+};
+
+use alloy::{
+    primitives::{address, hex, Address, Bytes, TxKind, U256},
+    providers::Provider,
+};
+use revm::context::result::{ExecutionResult, Output};
+use revm_trace::{
+    errors::{EvmError, InitError, LocalNodeError},
+    evm::builder::get_provider,
+    traits::TransactionTrace,
+    utils::local_node_utils::LocalNodeBridge,
+    EvmBuilder, SimulationBatch, SimulationTx, TxInspector,
+};
+
+/// First default Anvil account, funded with 10000 ETH
+const DEPLOYER: Address = address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+
+/// Deploys a contract incrementing storage slot 0 on every call
+const COUNTER_BYTECODE: &str = "600a80600b6000396000f360005460010160005500";
+const COUNTER_RUNTIME: &str = "60005460010160005500";
+
+/// Kills the node when the test ends
+struct Anvil {
+    child: Child,
+    url: String,
+}
+
+impl Drop for Anvil {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+    }
+}
+
+async fn spawn_anvil(port: u16) -> anyhow::Result<Anvil> {
+    let child = Command::new("anvil")
+        .args(["--port", &port.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let anvil = Anvil {
+        child,
+        url: format!("http://127.0.0.1:{port}"),
+    };
+    let provider = get_provider(&anvil.url).await?;
+    for _ in 0..50 {
+        if provider.get_block_number().await.is_ok() {
+            return Ok(anvil);
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    anyhow::bail!("anvil did not start on port {port}")
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "requires the anvil binary"]
+async fn test_simulate_and_apply_deployment() -> anyhow::Result<()> {
+    let anvil = spawn_anvil(8546).await?;
+    let mut evm = EvmBuilder::new_local(&anvil.url)
+        .with_tracer(TxInspector::new())
+        .build()
+        .await?;
+    let start = evm.block_context().number;
+
+    let batch = SimulationBatch {
+        transactions: vec![SimulationTx {
+            caller: DEPLOYER,
+            transact_to: TxKind::Create,
+            value: U256::ZERO,
+            data: hex::decode(COUNTER_BYTECODE)?.into(),
+            trace_config: None,
+            return_abi: None,
+        }],
+        is_stateful: true,
+        ..Default::default()
+    };
+    let simulated = evm
+        .trace_transactions(batch.clone())
+        .into_iter()
+        .map(|result| result.map(|(result, _, _)| result))
+        .collect::<Result<Vec<_>, _>>()?;
+    let ExecutionResult::Success {
+        output: Output::Create(_, Some(simulated_address)),
+        ..
+    } = &simulated[0]
+    else {
+        panic!("deployment failed: {:?}", simulated[0]);
+    };
+
+    let provider = get_provider(&anvil.url).await?;
+    let applied = LocalNodeBridge::apply_to_node(&provider, &batch, &simulated).await?;
+    assert_eq!(applied.len(), 1);
+    assert!(applied[0].success);
+    assert!(applied[0].converged(), "{:?}", applied[0].mismatches);
+    assert_eq!(applied[0].contract_address, Some(*simulated_address));
+
+    let code = provider.get_code_at(*simulated_address).await?;
+    assert_eq!(code, Bytes::from(hex::decode(COUNTER_RUNTIME)?));
+
+    // The EVM follows the node once refreshed
+    let block = evm.refresh_to_latest(&provider).await?;
+    assert_eq!(block.number, start + 1);
+    assert_eq!(evm.block_context().hash, block.hash);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rejects_batches_it_cannot_replay() -> anyhow::Result<()> {
+    // Validation happens before the node is contacted
+    let provider = get_provider("http://127.0.0.1:1").await?;
+    let mut batch = SimulationBatch {
+        transactions: vec![SimulationTx::default()],
+        is_stateful: true,
+        ..Default::default()
+    };
+
+    let result = LocalNodeBridge::apply_to_node(&provider, &batch, &[]).await;
+    assert!(matches!(
+        result,
+        Err(EvmError::LocalNode(LocalNodeError::ResultCount {
+            expected: 1,
+            actual: 0
+        }))
+    ));
+
+    batch.overrides = Some(Default::default());
+    let result = LocalNodeBridge::apply_to_node(&provider, &batch, &[]).await;
+    assert!(matches!(
+        result,
+        Err(EvmError::LocalNode(LocalNodeError::OverridesUnsupported))
+    ));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_public_rpc_is_not_a_dev_node() -> anyhow::Result<()> {
+    let result = EvmBuilder::new_local("https://eth.llamarpc.com")
+        .build()
+        .await;
+    assert!(matches!(
+        result,
+        Err(EvmError::Init(InitError::NotDevNode(_)))
+    ));
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! Integration tests for batch execution metrics
+
+use alloy::{
+    primitives::{address, Address, TxKind, U256},
+    sol,
+    sol_types::SolCall,
+};
+use revm_trace::{
+    create_evm_with_tracer, SimulationBatch, SimulationTx, TransactionTrace, TxInspector,
+};
+
+sol! {
+    function balanceOf(address owner) public returns (uint256);
+}
+
+const ETH_RPC_URL: &str = "https://eth.llamarpc.com";
+const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+
+fn balance_query(owner: Address) -> SimulationTx {
+    SimulationTx {
+        caller: address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045"),
+        transact_to: TxKind::Call(USDC),
+        value: U256::ZERO,
+        data: balanceOfCall { owner }.abi_encode().into(),
+        trace_config: None,
+        return_abi: None,
+    }
+}
+
+fn batch() -> SimulationBatch {
+    SimulationBatch {
+        transactions: vec![
+            balance_query(address!("28C6c06298d514Db089934071355E5743bf21d60")),
+            balance_query(address!("F977814e90dA44bFA03b6295A0616a897441aceC")),
+            balance_query(address!("28C6c06298d514Db089934071355E5743bf21d60")),
+        ],
+        is_stateful: false,
+        ..Default::default()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_batch_metrics_populated() -> anyhow::Result<()> {
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+
+    // Disabled by default
+    evm.trace_transactions(batch());
+    assert!(evm.take_last_batch_metrics().is_none());
+
+    evm.enable_metrics(true);
+    let results = evm.trace_transactions(batch());
+    assert!(results.iter().all(|r| r.is_ok()));
+    let metrics = evm
+        .take_last_batch_metrics()
+        .expect("metrics are recorded when enabled");
+    assert!(
+        evm.take_last_batch_metrics().is_none(),
+        "Metrics are taken only once"
+    );
+
+    assert_eq!(metrics.per_tx.len(), 3);
+    for (tx, (execution, _, _)) in metrics.per_tx.iter().zip(results.iter().flatten()) {
+        assert_eq!(tx.gas_used, execution.gas_used());
+        assert!(tx.trace_size_bytes > 0);
+        let fetch = tx.db_fetch_time.expect("builder EVMs are instrumented");
+        assert!(fetch <= tx.execution_time);
+    }
+    assert!(metrics.total_execution_time() <= metrics.total_wall_time);
+    assert!(metrics.total_gas_used() > 0);
+
+    let db_stats = metrics.db_stats.expect("builder EVMs are instrumented");
+    assert!(db_stats.upstream_calls > 0, "Cold batch must fetch state");
+    assert!(db_stats.fetch_time <= metrics.total_wall_time);
+    // The repeated query is served by the cache populated by the first one
+    assert!(metrics.per_tx[2].db_fetch_time <= metrics.per_tx[0].db_fetch_time);
+
+    let json = serde_json::to_value(&metrics)?;
+    assert_eq!(json["per_tx"].as_array().map(Vec::len), Some(3));
+
+    Ok(())
+}
@@ -0,0 +1,60 @@
+//! Integration tests for Multicall utilities
+//!
+//! Verifies batch execution through the dynamically deployed Multicall contract,
+//! including per-call gas attribution with mixed successful and reverting calls.
+
+use alloy::{
+    primitives::{address, Address, Bytes},
+    sol,
+    sol_types::SolCall,
+};
+use revm_trace::{
+    create_evm_with_tracer,
+    utils::multicall_utils::{MulticallCall, MulticallManager},
+    TxInspector,
+};
+
+sol! {
+    function balanceOf(address owner) public returns (uint256);
+}
+
+const ETH_RPC_URL: &str = "https://eth.llamarpc.com";
+const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_batch_call_with_gas() -> anyhow::Result<()> {
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+    let manager = MulticallManager::new();
+
+    let holders = [
+        address!("28C6c06298d514Db089934071355E5743bf21d60"),
+        address!("21a31Ee1afC51d94C2eFcCAa2092aD1028285549"),
+        address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045"),
+    ];
+    let mut calls: Vec<MulticallCall> = holders
+        .iter()
+        .map(|holder| MulticallCall {
+            target: USDC,
+            callData: balanceOfCall { owner: *holder }.abi_encode().into(),
+        })
+        .collect();
+    // Unknown selector, reverts in the USDC implementation
+    calls.push(MulticallCall {
+        target: USDC,
+        callData: Bytes::from(vec![0xde, 0xad, 0xbe, 0xef]),
+    });
+
+    let results = manager.deploy_and_batch_call_with_gas(&mut evm, calls, false)?;
+    assert_eq!(results.len(), 4);
+
+    for result in &results[..3] {
+        assert!(result.success);
+        assert_eq!(result.return_data.len(), 32);
+        assert!(result.gas_used > 0);
+        // A proxied balanceOf costs a few thousand gas, never anything near the block limit
+        assert!(result.gas_used < 100_000);
+    }
+    assert!(!results[3].success);
+
+    Ok(())
+}
@@ -0,0 +1,148 @@
+//! Scenarios across several forks with `ScenarioRunner`
+//!
+//! Runs on two in-memory backends, so no RPC access is needed. Fixture accounts live
+//! in the backends, below the overlay cleared at the start of every batch.
+
+use alloy::primitives::{address, b256, hex, Address, Bytes, TxKind, B256, U256};
+use revm_trace::{
+    errors::ScenarioError,
+    multichain::{ExtractSource, Extractor, ScenarioRunner, Step},
+    SimulationBatch, SimulationTx, TxInspector,
+};
+
+mod common;
+use common::{contract, Backend, TestEvm};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const PORTAL: Address = address!("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+const RECEIVER: Address = address!("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+
+/// Topic of the message event emitted by PORTAL
+const SENT_TOPIC: B256 = b256!("0000000000000000000000000000000000000000000000000000000000000001");
+
+/// Emits the calldata after the selector as LOG1 with topic 1, like
+/// `emit Sent(payload)` for `send(bytes payload)`
+const PORTAL_CODE: &str = "6004360360046000376001600436036000a100";
+
+/// Stores the first word of the `bytes` argument in slot 0, like
+/// `deliver(bytes payload)` keeping `payload[0:32]`
+const RECEIVER_CODE: &str = "604435600055";
+
+fn evm(chain_id: u64, address: Address, code: &str) -> TestEvm {
+    common::evm_with(
+        common::backend([(address, contract(code))]),
+        TxInspector::new(),
+        |ctx| ctx.cfg.chain_id = chain_id,
+    )
+}
+
+fn step(chain: &str, to: Address, data: Vec<u8>, extract: Option<Extractor>) -> Step {
+    Step {
+        chain: chain.to_string(),
+        batch: SimulationBatch {
+            transactions: vec![SimulationTx {
+                caller: SENDER,
+                transact_to: TxKind::Call(to),
+                value: U256::ZERO,
+                data: data.into(),
+                trace_config: None,
+                return_abi: None,
+            }],
+            is_stateful: false,
+            ..Default::default()
+        },
+        extract,
+    }
+}
+
+fn runner() -> ScenarioRunner<Backend> {
+    ScenarioRunner::new()
+        .with_chain("a", evm(1, PORTAL, PORTAL_CODE))
+        .with_chain("b", evm(10, RECEIVER, RECEIVER_CODE))
+}
+
+#[test]
+fn test_send_on_a_deliver_on_b() {
+    let payload = B256::repeat_byte(0x2a);
+    // send(bytes) with a 32-byte payload: selector, offset, length, data
+    let send = [
+        &hex!("12345678")[..],
+        &B256::with_last_byte(0x20)[..],
+        &B256::with_last_byte(0x20)[..],
+        &payload[..],
+    ]
+    .concat();
+    let deliver = [&hex!("deadbeef")[..], &Step::placeholder("message")].concat();
+
+    let report = runner()
+        .run(vec![
+            step(
+                "a",
+                PORTAL,
+                send.clone(),
+                Some(Extractor {
+                    name: "message".to_string(),
+                    tx_index: 0,
+                    source: ExtractSource::LogData {
+                        address: Some(PORTAL),
+                        topic0: Some(SENT_TOPIC),
+                    },
+                }),
+            ),
+            step("b", RECEIVER, deliver, None),
+        ])
+        .unwrap();
+
+    // The ABI-encoded event data doubles as the arguments of deliver(bytes)
+    let message = Bytes::copy_from_slice(&send[4..]);
+    assert_eq!(report.values["message"], message);
+
+    let delivery = &report.chain_steps("b").next().unwrap().reports[0];
+    assert!(delivery.summary.success);
+    assert_eq!(
+        delivery.tx.data,
+        Bytes::from([&hex!("deadbeef")[..], &message[..]].concat())
+    );
+    let writes = &delivery.storage_diff[&RECEIVER];
+    assert!(writes
+        .iter()
+        .any(|access| access.is_write && access.new_value == U256::from_be_bytes(payload.0)));
+    assert_eq!(report.chain_steps("a").count(), 1);
+}
+
+#[test]
+fn test_scenario_errors() {
+    let mut runner = runner();
+
+    let unknown = runner.run(vec![step("c", PORTAL, vec![], None)]);
+    assert!(matches!(
+        unknown,
+        Err(ScenarioError::UnknownChain { step: 0, chain }) if chain == "c"
+    ));
+
+    let unresolved = runner.run(vec![step(
+        "b",
+        RECEIVER,
+        Step::placeholder("missing"),
+        None,
+    )]);
+    assert!(matches!(
+        unresolved,
+        Err(ScenarioError::UnresolvedPlaceholder { step: 0, name }) if name == "missing"
+    ));
+
+    // RECEIVER emits no logs
+    let extractor = Extractor {
+        name: "message".to_string(),
+        tx_index: 0,
+        source: ExtractSource::LogData {
+            address: None,
+            topic0: None,
+        },
+    };
+    let nothing = runner.run(vec![step("b", RECEIVER, vec![], Some(extractor))]);
+    assert!(matches!(
+        nothing,
+        Err(ScenarioError::Extraction { step: 0, .. })
+    ));
+}
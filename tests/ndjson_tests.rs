@@ -0,0 +1,82 @@
+//! Streaming reports as newline-delimited JSON
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+#![cfg(feature = "ndjson")]
+
+use alloy::primitives::{address, Address, TxKind, U256};
+use revm_trace::{
+    report::{NdjsonReader, ReportOptions, SimulationReport},
+    traits::TransactionTrace,
+    types::SimulationBatch,
+    SimulationTx,
+};
+
+mod common;
+use common::{contract, ether, funded, TestEvm};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const COUNTER: Address = address!("c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0");
+
+/// Increments storage slot 0 and logs the new value
+const COUNTER_CODE: &str = "6000546001018060005560005260206000a000";
+
+fn evm() -> TestEvm {
+    common::evm(common::backend([
+        (SENDER, funded(ether())),
+        (COUNTER, contract(COUNTER_CODE)),
+    ]))
+}
+
+fn batch() -> SimulationBatch {
+    SimulationBatch {
+        transactions: (1..=5)
+            .map(|value| SimulationTx {
+                caller: SENDER,
+                transact_to: TxKind::Call(COUNTER),
+                value: U256::from(value),
+                data: Default::default(),
+                trace_config: None,
+                return_abi: None,
+            })
+            .collect(),
+        is_stateful: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_streamed_reports_match_in_memory_reports() {
+    let batch = batch();
+    let expected: Vec<SimulationReport> = batch
+        .transactions
+        .clone()
+        .into_iter()
+        .zip(evm().trace_transactions(batch.clone()))
+        .map(|(tx, result)| {
+            let (result, diff, output) = result.unwrap();
+            SimulationReport::new(tx, &result, diff, output)
+        })
+        .collect();
+
+    let mut buffer = Vec::new();
+    let failed = evm()
+        .trace_to_writer(batch, &mut buffer, &ReportOptions::default())
+        .unwrap();
+    assert!(failed.is_empty());
+    assert_eq!(buffer.iter().filter(|&&byte| byte == b'\n').count(), 5);
+
+    let streamed: Vec<SimulationReport> = NdjsonReader::new(buffer.as_slice())
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(streamed.len(), expected.len());
+    for (streamed, expected) in streamed.iter().zip(&expected) {
+        assert_eq!(
+            serde_json::to_value(streamed).unwrap(),
+            serde_json::to_value(expected).unwrap()
+        );
+    }
+    // Later transactions saw the counter written by earlier ones
+    assert_eq!(streamed[4].trace.logs[0].data.data[31], 5);
+}
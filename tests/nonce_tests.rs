@@ -0,0 +1,176 @@
+//! Sender nonces across stateful batches
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. The backend counts its
+//! account lookups, so the test can tell how often the sender was read.
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{Arc, Mutex},
+};
+
+use alloy::primitives::{address, hex, Address, TxKind, B256, U256};
+use revm::{
+    database::{Database, DatabaseRef},
+    state::{AccountInfo, Bytecode},
+};
+use revm_trace::{
+    traits::TransactionTrace,
+    types::{FailurePolicy, SimulationBatch},
+    SimulationTx,
+};
+
+mod common;
+use common::TestEvm;
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const OTHER: Address = address!("2000000000000000000000000000000000000002");
+
+/// Init code deploying an empty contract
+const DEPLOY: &str = "00";
+/// Init code reverting
+const REVERT: &str = "60006000fd";
+
+/// Backend counting `basic` lookups per address
+#[derive(Default)]
+struct CountingDb {
+    basic_calls: Arc<Mutex<HashMap<Address, usize>>>,
+}
+
+impl DatabaseRef for CountingDb {
+    type Error = Infallible;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        *self.basic_calls.lock().unwrap().entry(address).or_default() += 1;
+        Ok(None)
+    }
+
+    fn code_by_hash_ref(&self, _code_hash: B256) -> Result<Bytecode, Self::Error> {
+        Ok(Bytecode::default())
+    }
+
+    fn storage_ref(&self, _address: Address, _index: U256) -> Result<U256, Self::Error> {
+        Ok(U256::ZERO)
+    }
+
+    fn block_hash_ref(&self, _number: u64) -> Result<B256, Self::Error> {
+        Ok(B256::ZERO)
+    }
+}
+
+type Evm = TestEvm<CountingDb>;
+
+fn evm() -> (Evm, Arc<Mutex<HashMap<Address, usize>>>) {
+    let backend = CountingDb::default();
+    let basic_calls = backend.basic_calls.clone();
+    (common::evm(backend), basic_calls)
+}
+
+fn create(caller: Address, init_code: &str) -> SimulationTx {
+    SimulationTx {
+        caller,
+        transact_to: TxKind::Create,
+        value: U256::ZERO,
+        data: hex::decode(init_code).unwrap().into(),
+        trace_config: None,
+        return_abi: None,
+    }
+}
+
+fn batch(transactions: Vec<SimulationTx>, failure_policy: FailurePolicy) -> SimulationBatch {
+    SimulationBatch {
+        transactions,
+        is_stateful: true,
+        failure_policy,
+        ..Default::default()
+    }
+}
+
+/// Addresses of the contracts created by a batch, `None` for failed creations
+fn created(evm: &mut Evm, batch: SimulationBatch) -> Vec<Option<Address>> {
+    evm.execute_batch(batch)
+        .into_iter()
+        .map(|result| {
+            let result = result.unwrap();
+            result.output().and_then(|output| output.address()).copied()
+        })
+        .collect()
+}
+
+fn nonce(evm: &mut Evm, address: Address) -> u64 {
+    evm.db().basic(address).unwrap().map_or(0, |acc| acc.nonce)
+}
+
+#[test]
+fn test_same_sender_is_read_once() {
+    let (mut evm, basic_calls) = evm();
+    let addresses = created(
+        &mut evm,
+        batch(vec![create(SENDER, DEPLOY); 20], Default::default()),
+    );
+
+    let expected: Vec<_> = (0..20).map(|nonce| Some(SENDER.create(nonce))).collect();
+    assert_eq!(addresses, expected);
+    assert_eq!(basic_calls.lock().unwrap()[&SENDER], 1);
+    assert_eq!(nonce(&mut evm, SENDER), 20);
+}
+
+#[test]
+fn test_mixed_senders_and_failures() {
+    let (mut evm, _) = evm();
+    let transactions = vec![
+        create(SENDER, DEPLOY),
+        create(OTHER, DEPLOY),
+        // A reverted creation still consumes its nonce
+        create(SENDER, REVERT),
+        create(SENDER, DEPLOY),
+        create(OTHER, DEPLOY),
+    ];
+    let addresses = created(&mut evm, batch(transactions, Default::default()));
+    assert_eq!(
+        addresses,
+        vec![
+            Some(SENDER.create(0)),
+            Some(OTHER.create(0)),
+            None,
+            Some(SENDER.create(2)),
+            Some(OTHER.create(1)),
+        ]
+    );
+    assert_eq!(nonce(&mut evm, SENDER), 3);
+    assert_eq!(nonce(&mut evm, OTHER), 2);
+}
+
+#[test]
+fn test_skipped_failures_keep_nonce() {
+    let (mut evm, _) = evm();
+    let transactions = vec![
+        create(SENDER, REVERT),
+        create(SENDER, DEPLOY),
+        create(SENDER, DEPLOY),
+    ];
+    let addresses = created(
+        &mut evm,
+        batch(transactions, FailurePolicy::ContinueSkippingCommit),
+    );
+    assert_eq!(
+        addresses,
+        vec![None, Some(SENDER.create(0)), Some(SENDER.create(1))]
+    );
+}
+
+#[test]
+fn test_next_batch_reads_nonce_again() {
+    let (mut evm, basic_calls) = evm();
+    created(
+        &mut evm,
+        batch(vec![create(SENDER, DEPLOY); 3], Default::default()),
+    );
+    // Every batch starts from the backend state again
+    let addresses = created(
+        &mut evm,
+        batch(vec![create(SENDER, DEPLOY)], Default::default()),
+    );
+    assert_eq!(addresses, vec![Some(SENDER.create(0))]);
+    assert_eq!(basic_calls.lock().unwrap()[&SENDER], 2);
+}
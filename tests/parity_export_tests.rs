@@ -0,0 +1,149 @@
+//! Parity trace export checked against a recorded `trace_transaction` response
+//!
+//! `fixtures/parity_trace_transaction.json` pins a mainnet transaction, the first
+//! Ether transfer (block 46147), together with a node's `trace_transaction` response
+//! for it. The transaction is replayed offline with its mined envelope on a state
+//! funding its sender, and the exported flat traces are compared with the recording
+//! entry by entry and field by field. Error messages are compared by presence, as
+//! their wording differs between clients.
+//!
+//! The ignored test checks the recording against a live node; run it with
+//! `cargo test --test parity_export_tests -- --ignored` after changing the fixture.
+
+use alloy::{
+    primitives::{Address, Bytes, TxKind, B256, U128, U256, U64},
+    providers::Provider,
+};
+use revm::{
+    database::{CacheDB, EmptyDB},
+    state::AccountInfo,
+    Context, MainBuilder, MainContext,
+};
+use revm_trace::{
+    evm::builder::get_provider,
+    export::parity::{ParityTrace, TxMeta},
+    SimulationBatch, SimulationTx, TraceEvm, TransactionTrace, TxInspector,
+};
+use serde::Deserialize;
+
+const FIXTURE: &str = include_str!("fixtures/parity_trace_transaction.json");
+const ETH_RPC_URL: &str = "https://eth.llamarpc.com";
+
+/// Fields of `eth_getTransactionByHash` needed to replay the pinned transaction
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecordedTx {
+    hash: B256,
+    block_hash: B256,
+    block_number: U64,
+    transaction_index: U64,
+    from: Address,
+    to: Address,
+    value: U256,
+    gas: U64,
+    gas_price: U128,
+    nonce: U64,
+    input: Bytes,
+}
+
+#[derive(Debug, Deserialize)]
+struct Recording {
+    transaction: RecordedTx,
+    trace_transaction: Vec<ParityTrace>,
+}
+
+fn recording() -> Recording {
+    serde_json::from_str(FIXTURE).expect("fixture parses")
+}
+
+/// Replays the pinned transaction on an in-memory state funding its sender
+fn replay(tx: &RecordedTx) -> Vec<ParityTrace> {
+    let mut db = CacheDB::new(EmptyDB::default());
+    db.insert_account_info(
+        tx.from,
+        AccountInfo {
+            balance: U256::from(10).pow(U256::from(18)),
+            nonce: tx.nonce.to(),
+            ..Default::default()
+        },
+    );
+    let mut ctx = Context::mainnet().with_db(db);
+    ctx.block.number = tx.block_number.to();
+    // Pre-London block
+    ctx.cfg.disable_base_fee = true;
+    let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(TxInspector::new()));
+    let gas_limit: u64 = tx.gas.to();
+    let gas_price: u128 = tx.gas_price.to();
+    evm.set_tx_hook(Box::new(move |env, _| {
+        env.tx_type = 0;
+        env.gas_limit = gas_limit;
+        env.gas_price = gas_price;
+    }));
+
+    let mut results = evm.trace_transactions(SimulationBatch {
+        transactions: vec![SimulationTx {
+            caller: tx.from,
+            transact_to: TxKind::Call(tx.to),
+            value: tx.value,
+            data: tx.input.clone(),
+            trace_config: None,
+            return_abi: None,
+        }],
+        is_stateful: true,
+        ..Default::default()
+    });
+    let (_, _, output) = results.remove(0).expect("pinned transaction replays");
+    output.to_parity_traces(&TxMeta {
+        block_hash: Some(tx.block_hash),
+        block_number: Some(tx.block_number.to()),
+        transaction_hash: Some(tx.hash),
+        transaction_position: Some(tx.transaction_index.to()),
+    })
+}
+
+/// Clears the error message, whose wording is client specific
+fn comparable(trace: &ParityTrace) -> ParityTrace {
+    let mut trace = trace.clone();
+    trace.error = trace.error.map(|_| String::new());
+    trace
+}
+
+#[test]
+fn test_export_matches_recorded_trace_transaction() {
+    let Recording {
+        transaction,
+        trace_transaction: recorded,
+    } = recording();
+    let exported = replay(&transaction);
+
+    assert_eq!(
+        exported.len(),
+        recorded.len(),
+        "trace count of {}: {exported:#?}",
+        transaction.hash
+    );
+    for (ours, node) in exported.iter().zip(&recorded) {
+        assert_eq!(
+            comparable(ours),
+            comparable(node),
+            "trace {:?} of {}",
+            node.trace_address,
+            transaction.hash
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "queries a live node to check the recorded fixture"]
+async fn test_recording_matches_node() -> Result<(), Box<dyn std::error::Error>> {
+    let Recording {
+        transaction,
+        trace_transaction: recorded,
+    } = recording();
+    let provider = get_provider(ETH_RPC_URL).await?;
+    let live: Vec<ParityTrace> = provider
+        .raw_request("trace_transaction".into(), (transaction.hash,))
+        .await?;
+    assert_eq!(live, recorded);
+    Ok(())
+}
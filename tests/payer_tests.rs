@@ -0,0 +1,102 @@
+//! Effective payers of relayed transactions
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use alloy::primitives::{address, hex, Address, TxKind, U256};
+use revm::{
+    database::{CacheDB, EmptyDB},
+    state::{AccountInfo, Bytecode},
+    Context, MainBuilder, MainContext,
+};
+use revm_trace::{
+    analysis::{EffectivePayer, PayerConfig, PayerRule},
+    report::SimulationReport,
+    traits::TransactionTrace,
+    types::SimulationBatch,
+    SimulationTx, TraceEvm, TxInspector,
+};
+
+const RELAYER: Address = address!("1000000000000000000000000000000000000001");
+const USER: Address = address!("eeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee");
+const TARGET: Address = address!("c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0");
+const FEE_TOKEN: Address = address!("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+
+/// Stand-in token whose `transferFrom(from, to, amount)` only emits
+/// `Transfer(from, to, amount)` and returns true
+const FEE_TOKEN_CODE: &str = concat!(
+    "604435600052602435600435",
+    "7fddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+    "60206000a3",
+    "600160005260206000f3",
+);
+
+/// Relayed contract charging the relayer's fee: calls
+/// `FEE_TOKEN.transferFrom(USER, tx.origin, 100)`
+const TARGET_CODE: &str = concat!(
+    "6323b872dd60e01b600052",
+    "73eeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee600452",
+    "32602452",
+    "6064604452",
+    "6020600060646000600073aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa5af15000",
+);
+
+fn insert_code(backend: &mut CacheDB<EmptyDB>, address: Address, code: &str) {
+    let code = Bytecode::new_raw(hex::decode(code).unwrap().into());
+    backend.insert_account_info(
+        address,
+        AccountInfo {
+            code_hash: code.hash_slow(),
+            code: Some(code),
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn test_end_user_paying_the_relayer_is_the_effective_payer() {
+    let mut backend = CacheDB::new(EmptyDB::default());
+    insert_code(&mut backend, FEE_TOKEN, FEE_TOKEN_CODE);
+    insert_code(&mut backend, TARGET, TARGET_CODE);
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(backend));
+    ctx.cfg.disable_base_fee = true;
+    let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(TxInspector::new()));
+
+    let tx = SimulationTx {
+        caller: RELAYER,
+        transact_to: TxKind::Call(TARGET),
+        value: U256::ZERO,
+        data: Default::default(),
+        trace_config: None,
+        return_abi: None,
+    };
+    let mut results = evm.trace_transactions(SimulationBatch {
+        transactions: vec![tx.clone()],
+        is_stateful: false,
+        ..Default::default()
+    });
+    let (result, diff, output) = results.remove(0).unwrap();
+    assert!(result.is_success(), "{result:?}");
+
+    let report = SimulationReport::new(tx, &result, diff, output)
+        .with_payer_analysis(&PayerConfig::default());
+    let analysis = report.payer_analysis.as_ref().unwrap();
+    assert_eq!(analysis.nominal_payer, RELAYER);
+    assert_eq!(
+        analysis.effective_payers,
+        vec![EffectivePayer {
+            address: USER,
+            amount_native: U256::ZERO,
+            amount_tokens: vec![(FEE_TOKEN, U256::from(100))],
+            rules: vec![PayerRule::RelayedFeeToken],
+        }]
+    );
+
+    // The rule can be turned off on its own
+    let without_tokens = PayerConfig {
+        relayed_fee_token: false,
+        ..Default::default()
+    };
+    let report = report.with_payer_analysis(&without_tokens);
+    assert!(!report.payer_analysis.unwrap().is_sponsored());
+}
@@ -0,0 +1,195 @@
+//! Integration tests for permit simulation
+//!
+//! Runs USDC (EIP-2612) and DAI (nonstandard permit) flows at a fixed mainnet block:
+//! a permit, synthesized through storage or signed with a test key, must let the
+//! spender pull the owner's tokens with `transferFrom`.
+
+use alloy::{
+    primitives::{address, b256, Address, TxKind, B256, U256},
+    signers::local::PrivateKeySigner,
+    sol,
+    sol_types::SolCall,
+};
+use revm::database::{CacheDB, DatabaseRef};
+use revm_trace::{
+    errors::{EvmError, RuntimeError},
+    types::StateOverride,
+    utils::{
+        cheat_utils::find_balance_slot,
+        permit_utils::{build_permit_tx, synthesize_permit_via_storage},
+    },
+    EvmBuilder, SimulationBatch, SimulationTx, TraceEvm, TransactionTrace, TxInspector,
+};
+
+const ETH_RPC_URL: &str = "https://eth.llamarpc.com";
+const BLOCK_NUMBER: u64 = 18_000_000;
+const OWNER_KEY: B256 = b256!("00000000000000000000000000000000000000000000000000000000000a11ce");
+const SPENDER: Address = address!("5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e5e");
+const RECIPIENT: Address = address!("ecececececececececececececececececececec");
+const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+const DAI: Address = address!("6B175474E89094C44Da98b954EedeAC495271d0F");
+
+sol! {
+    function transferFrom(address from, address to, uint256 amount) external returns (bool);
+}
+
+fn owner() -> Address {
+    PrivateKeySigner::from_bytes(&OWNER_KEY).unwrap().address()
+}
+
+/// Writes `amount` into the owner's balance slot of `token`
+fn fund<DB: DatabaseRef>(
+    evm: &mut TraceEvm<CacheDB<DB>, TxInspector>,
+    overrides: &mut StateOverride,
+    token: Address,
+    amount: U256,
+) -> anyhow::Result<()> {
+    let (holder, slot) = find_balance_slot(evm, token, owner())?;
+    overrides
+        .storages
+        .entry(holder)
+        .or_default()
+        .push((slot, amount));
+    Ok(())
+}
+
+fn transfer_from(token: Address, amount: U256) -> SimulationTx {
+    SimulationTx {
+        caller: SPENDER,
+        transact_to: TxKind::Call(token),
+        value: U256::ZERO,
+        data: transferFromCall {
+            from: owner(),
+            to: RECIPIENT,
+            amount,
+        }
+        .abi_encode()
+        .into(),
+        trace_config: None,
+        return_abi: None,
+    }
+}
+
+/// Runs `transactions` statefully and returns whether each one succeeded
+fn run<DB: DatabaseRef>(
+    evm: &mut TraceEvm<CacheDB<DB>, TxInspector>,
+    transactions: Vec<SimulationTx>,
+    overrides: StateOverride,
+) -> anyhow::Result<Vec<bool>> {
+    evm.trace_transactions(SimulationBatch {
+        transactions,
+        is_stateful: true,
+        overrides: Some(overrides),
+        ..Default::default()
+    })
+    .into_iter()
+    .map(|result| Ok(result?.0.is_success()))
+    .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_usdc_permit_via_storage_enables_transfer_from() -> anyhow::Result<()> {
+    let mut evm = EvmBuilder::new_alloy(ETH_RPC_URL)
+        .with_block_number(BLOCK_NUMBER)
+        .with_tracer(TxInspector::new())
+        .build()
+        .await?;
+    let amount = U256::from(1_000_000u64);
+
+    // Without the permit the spender has no allowance
+    let mut overrides = StateOverride::default();
+    fund(&mut evm, &mut overrides, USDC, amount)?;
+    let unpermitted = run(
+        &mut evm,
+        vec![transfer_from(USDC, amount)],
+        overrides.clone(),
+    )?;
+    assert_eq!(unpermitted, vec![false]);
+
+    let permit =
+        synthesize_permit_via_storage(&mut evm, USDC, owner(), SPENDER, amount, U256::MAX)?;
+    for (address, slots) in permit.storages {
+        overrides.storages.entry(address).or_default().extend(slots);
+    }
+    let permitted = run(&mut evm, vec![transfer_from(USDC, amount)], overrides)?;
+    assert_eq!(permitted, vec![true]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_usdc_signed_permit_enables_transfer_from() -> anyhow::Result<()> {
+    let mut evm = EvmBuilder::new_alloy(ETH_RPC_URL)
+        .with_block_number(BLOCK_NUMBER)
+        .with_tracer(TxInspector::new())
+        .build()
+        .await?;
+    let amount = U256::from(1_000_000u64);
+
+    let permit = build_permit_tx(&mut evm, &OWNER_KEY, USDC, SPENDER, amount, U256::MAX)?;
+    assert_eq!(permit.caller, owner());
+    let mut overrides = StateOverride::default();
+    fund(&mut evm, &mut overrides, USDC, amount)?;
+    let results = run(
+        &mut evm,
+        vec![permit, transfer_from(USDC, amount)],
+        overrides,
+    )?;
+    assert_eq!(results, vec![true, true]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_expired_deadline_is_rejected() -> anyhow::Result<()> {
+    let mut evm = EvmBuilder::new_alloy(ETH_RPC_URL)
+        .with_block_number(BLOCK_NUMBER)
+        .with_tracer(TxInspector::new())
+        .build()
+        .await?;
+
+    let result =
+        synthesize_permit_via_storage(&mut evm, USDC, owner(), SPENDER, U256::from(1), U256::ZERO);
+    assert!(matches!(
+        result,
+        Err(EvmError::Runtime(RuntimeError::PermitFailed(_)))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_dai_permit_via_storage_enables_transfer_from() -> anyhow::Result<()> {
+    let mut evm = EvmBuilder::new_alloy(ETH_RPC_URL)
+        .with_block_number(BLOCK_NUMBER)
+        .with_tracer(TxInspector::new())
+        .build()
+        .await?;
+    let amount = U256::from(10).pow(U256::from(18));
+
+    let mut overrides =
+        synthesize_permit_via_storage(&mut evm, DAI, owner(), SPENDER, amount, U256::MAX)?;
+    fund(&mut evm, &mut overrides, DAI, amount)?;
+    let results = run(&mut evm, vec![transfer_from(DAI, amount)], overrides)?;
+    assert_eq!(results, vec![true]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_dai_signed_permit_is_unsupported() -> anyhow::Result<()> {
+    let mut evm = EvmBuilder::new_alloy(ETH_RPC_URL)
+        .with_block_number(BLOCK_NUMBER)
+        .with_tracer(TxInspector::new())
+        .build()
+        .await?;
+
+    match build_permit_tx(&mut evm, &OWNER_KEY, DAI, SPENDER, U256::from(1), U256::MAX) {
+        Err(EvmError::Runtime(RuntimeError::PermitFailed(reason))) => {
+            assert!(reason.contains("nonstandard permit"), "{reason}")
+        }
+        other => panic!("expected an unsupported permit, got {other:?}"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,119 @@
+//! Balance and approval requirements derived by `analysis::preconditions`
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay the overrides are applied to.
+
+use alloy::{
+    primitives::{address, Address, TxKind, U256},
+    sol,
+    sol_types::SolCall,
+};
+use revm_trace::{
+    analysis::{preconditions, ApprovalRequirement, TokenRequirement},
+    utils::erc20_utils::query_erc20_balance,
+    SimulationTx,
+};
+
+mod common;
+use common::{contract, TestEvm};
+
+sol! {
+    function deposit(uint256 amount) external;
+}
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const TOKEN: Address = address!("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+const VAULT: Address = address!("7a017a017a017a017a017a017a017a017a017a01");
+
+/// Minimal token with `balances` as the mapping at slot 0 and `allowances` at slot 1:
+/// `balanceOf`, `allowance` and a checked `transferFrom` emitting `Transfer`
+const TOKEN_CODE: &str = concat!(
+    // Dispatch on the selector
+    "60003560e01c806370a082311461002c578063dd62ed3e1461004657806323b872dd1461006e57",
+    "60006000fd",
+    // balanceOf
+    "5b600435600052600060205260406000205460005260206000f3",
+    // allowance
+    "5b6004356000526001602052604060002060205260243560005260406000205460005260206000f3",
+    // transferFrom: spend the allowance, then move the balance
+    "5b60043560005260016020526040600020602052336000526040600020805460443580821061010a",
+    "579003905560043560005260006020526040600020805460443580821061010a5790039055602435",
+    "600052604060002080546044350190556044356000526024356004357fddf252ad1be2c89b69c2b0",
+    "68fc378daa952ba7f163c4a11628f55a4df523b3ef60206000a3600160005260206000f3",
+    // Insufficient balance or allowance
+    "5b60006000fd",
+);
+
+/// `deposit(amount)` pulls `amount` of TOKEN from the caller with `transferFrom`
+const VAULT_CODE: &str = concat!(
+    "60003560e01c63b6b55f25146100155760006000fd",
+    "5b6323b872dd60e01b60005233600452306024526004356044526020600060646000600073",
+    "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+    "5af16100595760006000fd5b00",
+);
+
+fn evm() -> TestEvm {
+    common::evm(common::backend([
+        (TOKEN, contract(TOKEN_CODE)),
+        (VAULT, contract(VAULT_CODE)),
+    ]))
+}
+
+#[test]
+fn test_deposit_requires_balance_and_allowance() {
+    let mut evm = evm();
+    let amount = U256::from(1_234_567);
+    let tx = SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Call(VAULT),
+        value: U256::ZERO,
+        data: depositCall { amount }.abi_encode().into(),
+        trace_config: None,
+        return_abi: None,
+    };
+
+    let needed = preconditions(&mut evm, tx).unwrap();
+    assert!(needed.succeeded);
+    assert_eq!(needed.native_required, U256::ZERO);
+    assert_eq!(
+        needed.token_requirements,
+        vec![TokenRequirement {
+            token: TOKEN,
+            holder: SENDER,
+            min_balance: amount,
+        }]
+    );
+    assert_eq!(
+        needed.approvals_required,
+        vec![ApprovalRequirement {
+            token: TOKEN,
+            owner: SENDER,
+            spender: VAULT,
+            min_allowance: amount,
+        }]
+    );
+    // The overrides were ephemeral
+    assert_eq!(
+        query_erc20_balance(&mut evm, TOKEN, SENDER).unwrap(),
+        U256::ZERO
+    );
+}
+
+#[test]
+fn test_native_transfer_requires_value() {
+    let mut evm = evm();
+    let tx = SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Call(address!("2000000000000000000000000000000000000002")),
+        value: U256::from(5_000),
+        data: Default::default(),
+        trace_config: None,
+        return_abi: None,
+    };
+
+    let needed = preconditions(&mut evm, tx).unwrap();
+    assert!(needed.succeeded);
+    assert_eq!(needed.native_required, U256::from(5_000));
+    assert!(needed.token_requirements.is_empty());
+    assert!(needed.approvals_required.is_empty());
+}
@@ -0,0 +1,103 @@
+//! Proof requests built from the state a transaction accessed
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use alloy::primitives::{address, hex, keccak256, Address, TxKind, B256, U256};
+use revm::{
+    database::{CacheDB, EmptyDB},
+    state::{AccountInfo, Bytecode},
+    Context, MainBuilder, MainContext,
+};
+use revm_trace::{
+    traits::TransactionTrace,
+    types::{ProofRequest, SimulationBatch},
+    SimulationTx, TraceEvm, TxInspector,
+};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const BOB: Address = address!("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+const TOKEN: Address = address!("7000000000000000000000000000000000000007");
+const COINBASE: Address = address!("c0000000000000000000000000000000000000c0");
+
+/// Moves `amount` of the caller's balance to `to`, with balances in a mapping at
+/// slot 0; calldata is `to ‖ amount` without a selector
+const TOKEN_CODE: &str =
+    "33600052600060205260406000208054602035900390556000356000526040600020805460203501905500";
+
+/// Slot of `balances[holder]`
+fn balance_slot(holder: Address) -> U256 {
+    let mut preimage = [0u8; 64];
+    preimage[12..32].copy_from_slice(holder.as_slice());
+    keccak256(preimage).into()
+}
+
+#[test]
+fn test_erc20_transfer_proof_requests() {
+    let mut backend = CacheDB::new(EmptyDB::default());
+    backend.insert_account_info(
+        SENDER,
+        AccountInfo {
+            balance: U256::from(10).pow(U256::from(18)),
+            ..Default::default()
+        },
+    );
+    let code = Bytecode::new_raw(hex::decode(TOKEN_CODE).unwrap().into());
+    backend.insert_account_info(
+        TOKEN,
+        AccountInfo {
+            code_hash: code.hash_slow(),
+            code: Some(code),
+            ..Default::default()
+        },
+    );
+    backend
+        .insert_account_storage(TOKEN, balance_slot(SENDER), U256::from(1_000))
+        .unwrap();
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(backend));
+    ctx.cfg.disable_base_fee = true;
+    ctx.block.beneficiary = COINBASE;
+    let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(TxInspector::new()));
+
+    let mut data = B256::left_padding_from(BOB.as_slice()).to_vec();
+    data.extend_from_slice(&U256::from(250).to_be_bytes::<32>());
+    let results = evm.trace_transactions(SimulationBatch {
+        transactions: vec![SimulationTx {
+            caller: SENDER,
+            transact_to: TxKind::Call(TOKEN),
+            value: U256::ZERO,
+            data: data.into(),
+            trace_config: None,
+            return_abi: None,
+        }],
+        is_stateful: false,
+        ..Default::default()
+    });
+    let (result, _, output) = results.into_iter().next().unwrap().unwrap();
+    assert!(result.is_success());
+
+    let mut token_keys: Vec<B256> = [SENDER, BOB]
+        .map(|holder| balance_slot(holder).into())
+        .to_vec();
+    token_keys.sort();
+    // The sender pays for gas and the beneficiary receives the fee, so both are loaded
+    // without any storage. The recipient only appears in the token's storage; its
+    // account is never touched.
+    assert_eq!(
+        output.proof_requests(),
+        vec![
+            ProofRequest {
+                address: SENDER,
+                storage_keys: vec![],
+            },
+            ProofRequest {
+                address: TOKEN,
+                storage_keys: token_keys,
+            },
+            ProofRequest {
+                address: COINBASE,
+                storage_keys: vec![],
+            },
+        ]
+    );
+}
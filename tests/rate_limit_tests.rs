@@ -0,0 +1,194 @@
+//! Client-side rate limiting of upstream lookups
+//!
+//! Runs against an in-process transport serving a token stand-in, so no RPC access is
+//! needed. The transport records when each storage request arrives to show how the
+//! limiter spaces them.
+
+use std::{
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use alloy::{
+    network::AnyNetwork,
+    primitives::{address, bytes, Address, Bloom, Bytes, B256, U256, U64},
+    providers::ProviderBuilder,
+    rpc::{
+        client::RpcClient,
+        json_rpc::{RequestPacket, Response, ResponsePacket, ResponsePayload, SerializedRequest},
+    },
+    transports::{TransportError, TransportFut},
+};
+use revm_trace::{utils::erc20_utils::query_erc20_balance, EvmFactory, TxInspector};
+use serde_json::{json, value::RawValue};
+
+const TOKEN: Address = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+
+/// `balanceOf(owner)` returning the storage slot keyed by the owner's address:
+/// `PUSH1 4 CALLDATALOAD SLOAD PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN`
+const TOKEN_CODE: Bytes = bytes!("6004355460005260206000f3");
+
+/// Lower bound for the gap between requests at 10 per second, with some slack for
+/// timer granularity
+const MIN_GAP: Duration = Duration::from_millis(90);
+
+/// Transport serving the token at any block and recording storage request times
+#[derive(Clone, Default)]
+struct TokenNode {
+    storage_requests: Arc<Mutex<Vec<Instant>>>,
+}
+
+impl TokenNode {
+    fn storage_request_gaps(&self) -> Vec<Duration> {
+        self.storage_requests
+            .lock()
+            .unwrap()
+            .windows(2)
+            .map(|pair| pair[1] - pair[0])
+            .collect()
+    }
+
+    async fn handle(self, packet: RequestPacket) -> Result<ResponsePacket, TransportError> {
+        let RequestPacket::Single(request) = packet else {
+            panic!("unexpected batch request");
+        };
+        if request.method() == "eth_getStorageAt" {
+            self.storage_requests.lock().unwrap().push(Instant::now());
+        }
+        let result = RawValue::from_string(answer(&request).to_string()).unwrap();
+        Ok(ResponsePacket::Single(Response {
+            id: request.id().clone(),
+            payload: ResponsePayload::Success(result),
+        }))
+    }
+}
+
+impl tower::Service<RequestPacket> for TokenNode {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, packet: RequestPacket) -> Self::Future {
+        Box::pin(self.clone().handle(packet))
+    }
+}
+
+/// Answers with the token code at `TOKEN`, a balance equal to the low byte of the
+/// owner's address for every owner, and empty accounts everywhere else
+fn answer(request: &SerializedRequest) -> serde_json::Value {
+    let params: serde_json::Value = request.params().map_or(json!([]), |params| {
+        serde_json::from_str(params.get()).unwrap()
+    });
+    let account = || serde_json::from_value::<Address>(params[0].clone()).unwrap();
+    match request.method() {
+        "eth_chainId" => json!(U64::from(1)),
+        "eth_getBlockByNumber" => {
+            let number: U64 = serde_json::from_value(params[0].clone()).unwrap();
+            block_json(number.to())
+        }
+        "eth_getBalance" | "eth_getTransactionCount" => json!(U256::ZERO),
+        "eth_getCode" => json!(if account() == TOKEN {
+            TOKEN_CODE
+        } else {
+            Bytes::new()
+        }),
+        "eth_getStorageAt" => {
+            let slot: U256 = serde_json::from_value(params[1].clone()).unwrap();
+            json!(slot & U256::from(0xff))
+        }
+        method => panic!("unexpected method {method}"),
+    }
+}
+
+/// Minimal `eth_getBlockByNumber` response
+fn block_json(number: u64) -> serde_json::Value {
+    json!({
+        "hash": B256::repeat_byte(number as u8),
+        "parentHash": B256::ZERO,
+        "sha3Uncles": B256::ZERO,
+        "miner": Address::ZERO,
+        "stateRoot": B256::ZERO,
+        "transactionsRoot": B256::ZERO,
+        "receiptsRoot": B256::ZERO,
+        "logsBloom": Bloom::ZERO,
+        "difficulty": "0x0",
+        "number": U64::from(number),
+        "gasLimit": "0x1c9c380",
+        "gasUsed": "0x0",
+        "timestamp": U64::from(1_700_000_000 + number),
+        "extraData": "0x",
+        "mixHash": B256::ZERO,
+        "nonce": "0x0000000000000000",
+        "uncles": [],
+        "transactions": [],
+    })
+}
+
+async fn factory(node: &TokenNode) -> anyhow::Result<EvmFactory> {
+    let provider = ProviderBuilder::new()
+        .network::<AnyNetwork>()
+        .connect_client(RpcClient::new(node.clone(), true));
+    Ok(EvmFactory::from_provider(provider).await?)
+}
+
+fn owner(n: u8) -> Address {
+    Address::with_last_byte(n)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_requests_are_spaced() -> anyhow::Result<()> {
+    let node = TokenNode::default();
+    let factory = factory(&node).await?.with_rpc_rate_limit(10, 1);
+    let mut evm = factory.evm_at_block(100, TxInspector::new()).await?;
+
+    for n in 1..=5 {
+        // Each query after the first only misses the owner's storage slot
+        let balance = query_erc20_balance(&mut evm, TOKEN, owner(n))?;
+        assert_eq!(balance, U256::from(n));
+    }
+
+    let gaps = node.storage_request_gaps();
+    assert_eq!(gaps.len(), 4);
+    assert!(gaps.iter().all(|gap| *gap >= MIN_GAP), "{gaps:?}");
+    let stats = evm.db_stats();
+    assert!(stats.rate_limit_wait >= MIN_GAP * 4, "{stats:?}");
+    assert!(stats.fetch_time >= stats.rate_limit_wait);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_factory_evms_share_the_limit() -> anyhow::Result<()> {
+    let node = TokenNode::default();
+    let factory = factory(&node).await?.with_rpc_rate_limit(10, 1);
+    let mut first = factory.evm_at_block(100, TxInspector::new()).await?;
+    let mut second = factory.evm_at_block(100, TxInspector::new()).await?;
+    query_erc20_balance(&mut first, TOKEN, owner(1))?;
+    query_erc20_balance(&mut second, TOKEN, owner(2))?;
+
+    // Each EVM alternates with the other, so only a shared bucket spaces them
+    for n in 3..=6 {
+        let evm = if n % 2 == 1 { &mut first } else { &mut second };
+        assert_eq!(query_erc20_balance(evm, TOKEN, owner(n))?, U256::from(n));
+    }
+    let gaps = node.storage_request_gaps();
+    assert!(gaps.iter().all(|gap| *gap >= MIN_GAP), "{gaps:?}");
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_no_wait_without_limit() -> anyhow::Result<()> {
+    let node = TokenNode::default();
+    let factory = factory(&node).await?;
+    assert!(factory.rate_limiter().is_none());
+    let mut evm = factory.evm_at_block(100, TxInspector::new()).await?;
+    for n in 1..=3 {
+        query_erc20_balance(&mut evm, TOKEN, owner(n))?;
+    }
+    assert_eq!(evm.db_stats().rate_limit_wait, Duration::ZERO);
+    Ok(())
+}
@@ -24,13 +24,15 @@ async fn test_basic_usage() -> anyhow::Result<()> {
         transact_to: TxKind::Call(address!("d878229c9c3575F224784DE610911B5607a3ad15")),
         value: U256::from(120000000000000000u64), //  0.12 ETH
         data: vec![].into(),
+        trace_config: None,
+        return_abi: None,
     };
 
     // Create batch with single transaction
     let batch = SimulationBatch {
         transactions: vec![tx],
         is_stateful: false,
-        overrides: None,
+        ..Default::default()
     };
 
     // Execute transaction batch
@@ -0,0 +1,273 @@
+//! Recording the state each transaction fetched from the remote node
+//!
+//! Runs against an in-process transport serving a USDC stand-in, so no RPC access is
+//! needed.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use alloy::{
+    network::AnyNetwork,
+    primitives::{address, bytes, Address, Bloom, Bytes, TxKind, B256, U256, U64},
+    providers::ProviderBuilder,
+    rpc::{
+        client::RpcClient,
+        json_rpc::{RequestPacket, Response, ResponsePacket, ResponsePayload, SerializedRequest},
+    },
+    transports::{TransportError, TransportFut},
+};
+use revm_trace::{
+    dependencies_digest, report::SimulationReport, types::SimulationBatch, EvmFactory, RemoteFetch,
+    RemoteFetchKind, SimulationTx, TransactionTrace, TxInspector, WarmCache,
+};
+use serde_json::{json, value::RawValue};
+
+const USDC: Address = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+const HOLDER: Address = address!("1000000000000000000000000000000000000001");
+
+/// `balanceOf(owner)` returning the storage slot keyed by the owner's address:
+/// `PUSH1 4 CALLDATALOAD SLOAD PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN`
+const TOKEN_CODE: Bytes = bytes!("6004355460005260206000f3");
+
+/// Transport serving the token at any block and counting requests by method
+#[derive(Clone, Default)]
+struct TokenNode {
+    requests: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl TokenNode {
+    fn count(&self, method: &str) -> usize {
+        self.requests
+            .lock()
+            .unwrap()
+            .get(method)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    async fn handle(self, packet: RequestPacket) -> Result<ResponsePacket, TransportError> {
+        let RequestPacket::Single(request) = packet else {
+            panic!("unexpected batch request");
+        };
+        *self
+            .requests
+            .lock()
+            .unwrap()
+            .entry(request.method().to_string())
+            .or_default() += 1;
+        let result = RawValue::from_string(answer(&request).to_string()).unwrap();
+        Ok(ResponsePacket::Single(Response {
+            id: request.id().clone(),
+            payload: ResponsePayload::Success(result),
+        }))
+    }
+}
+
+impl tower::Service<RequestPacket> for TokenNode {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, packet: RequestPacket) -> Self::Future {
+        Box::pin(self.clone().handle(packet))
+    }
+}
+
+/// Answers with the token code at `USDC`, a balance of 1000 USDC for `HOLDER` and
+/// empty accounts everywhere else
+fn answer(request: &SerializedRequest) -> serde_json::Value {
+    let params: serde_json::Value = request.params().map_or(json!([]), |params| {
+        serde_json::from_str(params.get()).unwrap()
+    });
+    let account = || serde_json::from_value::<Address>(params[0].clone()).unwrap();
+    match request.method() {
+        "eth_chainId" => json!(U64::from(1)),
+        "eth_getBlockByNumber" => {
+            let number: U64 = serde_json::from_value(params[0].clone()).unwrap();
+            block_json(number.to())
+        }
+        "eth_getBalance" | "eth_getTransactionCount" => json!(U256::ZERO),
+        "eth_getCode" => json!(if account() == USDC {
+            TOKEN_CODE
+        } else {
+            Bytes::new()
+        }),
+        "eth_getStorageAt" => {
+            let slot: U256 = serde_json::from_value(params[1].clone()).unwrap();
+            let holder = U256::from_be_slice(HOLDER.as_slice());
+            json!(if account() == USDC && slot == holder {
+                U256::from(1_000_000_000u64)
+            } else {
+                U256::ZERO
+            })
+        }
+        method => panic!("unexpected method {method}"),
+    }
+}
+
+/// Minimal `eth_getBlockByNumber` response
+fn block_json(number: u64) -> serde_json::Value {
+    json!({
+        "hash": B256::repeat_byte(number as u8),
+        "parentHash": B256::ZERO,
+        "sha3Uncles": B256::ZERO,
+        "miner": Address::ZERO,
+        "stateRoot": B256::ZERO,
+        "transactionsRoot": B256::ZERO,
+        "receiptsRoot": B256::ZERO,
+        "logsBloom": Bloom::ZERO,
+        "difficulty": "0x0",
+        "number": U64::from(number),
+        "gasLimit": "0x1c9c380",
+        "gasUsed": "0x0",
+        "timestamp": U64::from(1_700_000_000 + number),
+        "extraData": "0x",
+        "mixHash": B256::ZERO,
+        "nonce": "0x0000000000000000",
+        "uncles": [],
+        "transactions": [],
+    })
+}
+
+async fn factory(node: &TokenNode) -> anyhow::Result<EvmFactory> {
+    let provider = ProviderBuilder::new()
+        .network::<AnyNetwork>()
+        .connect_client(RpcClient::new(node.clone(), true));
+    Ok(EvmFactory::from_provider(provider).await?)
+}
+
+async fn factory(node: &TokenNode) -> anyhow::Result<EvmFactory> {
+    let provider = ProviderBuilder::new()
+        .network::<AnyNetwork>()
+        .connect_client(RpcClient::new(node.clone(), true));
+    Ok(EvmFactory::from_provider(provider).await?)
+}
+
+/// `balanceOf(HOLDER)` sent by `HOLDER`
+fn balance_query() -> SimulationTx {
+    let mut data = vec![0x70, 0xa0, 0x82, 0x31];
+    data.extend_from_slice(B256::left_padding_from(HOLDER.as_slice()).as_slice());
+    SimulationTx {
+        caller: HOLDER,
+        transact_to: TxKind::Call(USDC),
+        value: U256::ZERO,
+        data: data.into(),
+        trace_config: None,
+        return_abi: None,
+    }
+}
+
+fn batch() -> SimulationBatch {
+    SimulationBatch {
+        transactions: vec![balance_query()],
+        is_stateful: false,
+        ..Default::default()
+    }
+}
+
+/// Runs the balance query at block 100 and returns the fetches of the transaction
+async fn fetches(
+    factory: &EvmFactory,
+    cache: Option<&WarmCache>,
+) -> anyhow::Result<(Vec<RemoteFetch>, Address)> {
+    let mut evm = factory.evm_at_block(100, TxInspector::new()).await?;
+    if let Some(cache) = cache {
+        evm = evm.with_warm_cache(cache.clone());
+    }
+    evm.record_remote_dependencies(true);
+    let results = evm.trace_transactions(batch());
+    let (result, _, _) = results[0].as_ref().unwrap();
+    assert!(result.is_success(), "{result:?}");
+
+    let mut dependencies = evm.take_last_remote_dependencies().unwrap();
+    assert_eq!(dependencies.len(), 1);
+    Ok((dependencies.remove(0), evm.block.beneficiary))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cold_run_lists_touched_accounts() -> anyhow::Result<()> {
+    let node = TokenNode::default();
+    let factory = factory(&node).await?;
+    let (fetches, beneficiary) = fetches(&factory, None).await?;
+
+    // The sender, the token and the beneficiary paid for the gas
+    let accounts: HashSet<_> = fetches
+        .iter()
+        .filter(|fetch| fetch.kind == RemoteFetchKind::Basic)
+        .map(|fetch| fetch.address)
+        .collect();
+    assert_eq!(accounts, HashSet::from([HOLDER, USDC, beneficiary]));
+    let slot = U256::from_be_slice(HOLDER.as_slice());
+    let storage: Vec<_> = fetches
+        .iter()
+        .filter(|fetch| fetch.kind == RemoteFetchKind::Storage)
+        .collect();
+    assert_eq!(storage.len(), 1);
+    assert_eq!((storage[0].address, storage[0].slot), (USDC, Some(slot)));
+    assert_eq!(
+        storage[0].value_hash,
+        alloy::primitives::keccak256(U256::from(1_000_000_000u64).to_be_bytes::<32>())
+    );
+    // Every fetch reached the node once
+    assert_eq!(fetches.len(), accounts.len() + storage.len());
+    assert_eq!(node.count("eth_getStorageAt"), 1);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_warm_rerun_lists_nothing() -> anyhow::Result<()> {
+    let node = TokenNode::default();
+    let factory = factory(&node).await?;
+    let cache = WarmCache::new(100);
+
+    let (cold, _) = fetches(&factory, Some(&cache)).await?;
+    assert!(!cold.is_empty());
+    let (warm, _) = fetches(&factory, Some(&cache)).await?;
+    assert!(warm.is_empty(), "{warm:?}");
+    assert_eq!(node.count("eth_getStorageAt"), 1);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_digest_is_reproducible() -> anyhow::Result<()> {
+    let node = TokenNode::default();
+    let factory = factory(&node).await?;
+    let (first, _) = fetches(&factory, None).await?;
+    let (second, _) = fetches(&factory, None).await?;
+    assert_eq!(first, second);
+    assert_eq!(dependencies_digest(&first), dependencies_digest(&second));
+    assert_ne!(
+        dependencies_digest(&first),
+        dependencies_digest(&first[1..])
+    );
+
+    let report = SimulationReport::default().with_remote_dependencies(first.clone());
+    assert_eq!(
+        report.dependencies_digest(),
+        Some(dependencies_digest(&first))
+    );
+    let json = serde_json::to_value(&report)?;
+    assert_eq!(
+        json["remote_dependencies"].as_array().map(Vec::len),
+        Some(first.len())
+    );
+    assert!(SimulationReport::default().dependencies_digest().is_none());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_not_recorded_by_default() -> anyhow::Result<()> {
+    let node = TokenNode::default();
+    let factory = factory(&node).await?;
+    let mut evm = factory.evm_at_block(100, TxInspector::new()).await?;
+    evm.trace_transactions(batch());
+    assert!(evm.take_last_remote_dependencies().is_none());
+    Ok(())
+}
@@ -0,0 +1,337 @@
+//! Replaying mined transactions by hash, with OP Stack deposits in their block
+//!
+//! The routing of deposits and the replayed envelopes are checked against a mocked
+//! provider and an in-memory backend; the last test replays a user transaction of a
+//! recent Base block.
+
+use std::convert::Infallible;
+
+use alloy::{
+    consensus::Transaction as _,
+    eips::Typed2718,
+    network::{AnyNetwork, TransactionResponse},
+    primitives::{address, Address, Bloom, Bytes, B256, U256, U64},
+    providers::{mock::Asserter, Provider, ProviderBuilder},
+};
+use revm::{
+    database::{CacheDB, DatabaseRef, EmptyDB},
+    state::{AccountInfo, Bytecode},
+};
+use revm_trace::{
+    chains::{SystemTxHandling, DEPOSIT_TX_TYPE, L1_ATTRIBUTES_DEPOSITOR, L1_BLOCK_PREDEPLOY},
+    create_evm_with_tracer,
+    errors::{EvmError, RuntimeError},
+    evm::builder::get_provider,
+    ResetBlock, TxInspector,
+};
+use serde_json::json;
+
+mod common;
+use common::{ether, funded, TestEvm};
+
+const BASE_RPC_URL: &str = "https://mainnet.base.org";
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const RECEIVER: Address = address!("2000000000000000000000000000000000000002");
+const BLOCK: u64 = 100;
+const DEPOSIT_HASH: B256 = B256::repeat_byte(0x7e);
+const TRANSFER_HASH: B256 = B256::repeat_byte(0x02);
+/// L1 base fee carried by the fixture's L1 attributes deposit
+const L1_BASE_FEE: u64 = 7_000_000_000;
+
+/// In-memory state standing in for an RPC database, which can be re-pinned
+#[derive(Debug, Default)]
+struct Backend(CacheDB<EmptyDB>);
+
+impl DatabaseRef for Backend {
+    type Error = Infallible;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        self.0.basic_ref(address)
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        self.0.code_by_hash_ref(code_hash)
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.0.storage_ref(address, index)
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        self.0.block_hash_ref(number)
+    }
+}
+
+impl ResetBlock for Backend {
+    type Error = EvmError;
+
+    /// The fixture state is the same at every block
+    fn reset_block(&mut self, _block_number: u64) -> Result<(), EvmError> {
+        Ok(())
+    }
+}
+
+fn evm() -> TestEvm<Backend> {
+    common::evm(Backend(common::backend([(SENDER, funded(ether()))])))
+}
+
+/// Fields every mined transaction of the fixture block carries
+fn mined(index: u64) -> serde_json::Value {
+    json!({
+        "blockHash": B256::repeat_byte(BLOCK as u8),
+        "blockNumber": U64::from(BLOCK),
+        "transactionIndex": U64::from(index),
+    })
+}
+
+/// Packed `setL1BlockValuesEcotone` calldata of the fixture block
+fn l1_attributes_input() -> Bytes {
+    let mut input = vec![0x44, 0x0a, 0x5e, 0x20];
+    input.extend(1_368u32.to_be_bytes()); // baseFeeScalar
+    input.extend(810_949u32.to_be_bytes()); // blobBaseFeeScalar
+    input.extend(3u64.to_be_bytes()); // sequenceNumber
+    input.extend(1_699_999_988u64.to_be_bytes()); // timestamp
+    input.extend(18_500_000u64.to_be_bytes()); // number
+    input.extend(U256::from(L1_BASE_FEE).to_be_bytes::<32>()); // basefee
+    input.extend(U256::from(1).to_be_bytes::<32>()); // blobBaseFee
+    input.extend([0x11; 32]); // hash
+    input.extend([0x22; 32]); // batcherHash
+    input.into()
+}
+
+/// L1 attributes deposit as returned by op-geth
+fn deposit_json(index: u64) -> serde_json::Value {
+    let mut tx = mined(index);
+    tx.as_object_mut().unwrap().extend(
+        json!({
+            "type": "0x7e",
+            "hash": DEPOSIT_HASH,
+            "sourceHash": B256::repeat_byte(0x01),
+            "from": L1_ATTRIBUTES_DEPOSITOR,
+            "to": L1_BLOCK_PREDEPLOY,
+            "mint": "0x0",
+            "value": "0x0",
+            "gas": "0xf4240",
+            "isSystemTx": false,
+            "input": l1_attributes_input(),
+            "nonce": "0x0",
+            "gasPrice": "0x0",
+            "v": "0x0",
+            "r": "0x0",
+            "s": "0x0",
+        })
+        .as_object()
+        .unwrap()
+        .clone(),
+    );
+    tx
+}
+
+/// EIP-1559 transfer of 1000 wei from SENDER to RECEIVER, with RECEIVER in its access list
+fn transfer_json(index: u64) -> serde_json::Value {
+    let mut tx = mined(index);
+    tx.as_object_mut().unwrap().extend(
+        json!({
+            "type": "0x2",
+            "hash": TRANSFER_HASH,
+            "chainId": "0x2105",
+            "from": SENDER,
+            "to": RECEIVER,
+            "value": "0x3e8",
+            "gas": "0x5b68",
+            "maxFeePerGas": "0x0",
+            "maxPriorityFeePerGas": "0x0",
+            "gasPrice": "0x0",
+            "input": "0x",
+            "nonce": "0x0",
+            "accessList": [{ "address": RECEIVER, "storageKeys": [] }],
+            "yParity": "0x0",
+            "v": "0x0",
+            "r": "0x1",
+            "s": "0x1",
+        })
+        .as_object()
+        .unwrap()
+        .clone(),
+    );
+    tx
+}
+
+/// `eth_getBlockByNumber` response holding a deposit and a transfer
+fn block_json() -> serde_json::Value {
+    json!({
+        "hash": B256::repeat_byte(BLOCK as u8),
+        "parentHash": B256::repeat_byte(BLOCK as u8 - 1),
+        "sha3Uncles": B256::ZERO,
+        "miner": address!("4200000000000000000000000000000000000011"),
+        "stateRoot": B256::ZERO,
+        "transactionsRoot": B256::ZERO,
+        "receiptsRoot": B256::ZERO,
+        "logsBloom": Bloom::ZERO,
+        "difficulty": "0x0",
+        "number": U64::from(BLOCK),
+        "gasLimit": "0x1c9c380",
+        "gasUsed": "0x0",
+        "timestamp": U64::from(1_700_000_000u64),
+        "extraData": "0x",
+        "mixHash": B256::repeat_byte(0x33),
+        "nonce": "0x0000000000000000",
+        "baseFeePerGas": "0x0",
+        "uncles": [],
+        "transactions": [deposit_json(0), transfer_json(1)],
+    })
+}
+
+fn mocked_provider() -> (impl Provider<AnyNetwork>, Asserter) {
+    let asserter = Asserter::new();
+    let provider = ProviderBuilder::new()
+        .network::<AnyNetwork>()
+        .connect_mocked_client(asserter.clone());
+    (provider, asserter)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_skip_replays_user_tx_after_deposit() {
+    let (provider, asserter) = mocked_provider();
+    asserter.push_success(&transfer_json(1));
+    asserter.push_success(&block_json());
+
+    let mut evm = evm();
+    evm.set_tx_hook(Box::new(|tx, _| tx.gas_limit = 1_000_000));
+    let report = evm
+        .trace_transaction_by_hash(&provider, TRANSFER_HASH, SystemTxHandling::Skip)
+        .await
+        .unwrap()
+        .expect("a user transaction is replayed");
+    assert!(report.summary.success, "{:?}", report.summary);
+    assert_eq!(report.tx.caller, SENDER);
+
+    // Only the transfer ran, with its mined envelope instead of the hook's settings
+    let executed = evm.take_last_executed_txs();
+    assert_eq!(executed.len(), 1);
+    assert_eq!(executed[0].tx_type, 2);
+    assert_eq!(executed[0].gas_limit, 23_400);
+    assert_eq!(executed[0].access_list.0[0].address, RECEIVER);
+    assert!(evm.has_tx_hook(), "the caller's hook is kept");
+    // The deposit left the L1 attributes untouched
+    assert_eq!(
+        evm.db()
+            .storage_ref(L1_BLOCK_PREDEPLOY, U256::from(1))
+            .unwrap(),
+        U256::ZERO
+    );
+
+    // In the mined block on top of its parent's state
+    assert_eq!(evm.block.number, BLOCK);
+    assert_eq!(evm.block.prevrandao, Some(B256::repeat_byte(0x33)));
+    assert_eq!(evm.block_context().number, BLOCK - 1);
+    assert_eq!(
+        evm.db().basic_ref(RECEIVER).unwrap().unwrap().balance,
+        U256::from(1_000)
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_apply_as_state_override_writes_l1_attributes() {
+    let (provider, asserter) = mocked_provider();
+    asserter.push_success(&transfer_json(1));
+    asserter.push_success(&block_json());
+
+    let mut evm = evm();
+    let report = evm
+        .trace_transaction_by_hash(
+            &provider,
+            TRANSFER_HASH,
+            SystemTxHandling::ApplyAsStateOverride,
+        )
+        .await
+        .unwrap()
+        .expect("a user transaction is replayed");
+    assert!(report.summary.success, "{:?}", report.summary);
+    assert_eq!(evm.take_last_executed_txs().len(), 1);
+
+    let db = evm.db();
+    let slot = |slot: u64| {
+        db.storage_ref(L1_BLOCK_PREDEPLOY, U256::from(slot))
+            .unwrap()
+    };
+    // basefee, and number and timestamp packed into slot 0
+    assert_eq!(slot(1), U256::from(L1_BASE_FEE));
+    assert_eq!(
+        slot(0),
+        (U256::from(1_699_999_988u64) << 64) | U256::from(18_500_000u64)
+    );
+    assert_eq!(slot(2), U256::from_be_bytes([0x11; 32]));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_error_rejects_deposit_before_user_tx() {
+    let (provider, asserter) = mocked_provider();
+    asserter.push_success(&transfer_json(1));
+    asserter.push_success(&block_json());
+
+    let result = evm()
+        .trace_transaction_by_hash(&provider, TRANSFER_HASH, SystemTxHandling::Error)
+        .await;
+    assert!(
+        matches!(
+            result,
+            Err(EvmError::Runtime(RuntimeError::UnsupportedTransaction(_)))
+        ),
+        "{result:?}"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_deposit_itself_is_routed_by_type() {
+    let (provider, asserter) = mocked_provider();
+    // The block is never fetched for a deposit
+    asserter.push_success(&deposit_json(0));
+    asserter.push_success(&deposit_json(0));
+
+    let skipped = evm()
+        .trace_transaction_by_hash(&provider, DEPOSIT_HASH, SystemTxHandling::Skip)
+        .await
+        .unwrap();
+    assert!(skipped.is_none());
+
+    let rejected = evm()
+        .trace_transaction_by_hash(&provider, DEPOSIT_HASH, SystemTxHandling::Error)
+        .await;
+    assert!(
+        matches!(
+            rejected,
+            Err(EvmError::Runtime(RuntimeError::UnsupportedTransaction(_)))
+        ),
+        "{rejected:?}"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_base_user_tx_in_block_with_deposit() -> Result<(), Box<dyn std::error::Error>> {
+    let provider = get_provider(BASE_RPC_URL).await?;
+    let number = provider.get_block_number().await? - 2;
+    let block = provider
+        .get_block_by_number(number.into())
+        .full()
+        .await?
+        .expect("recent block exists");
+    let transactions = block.transactions.as_transactions().unwrap();
+    // Every OP Stack block opens with the L1 attributes deposit
+    assert_eq!(transactions[0].ty(), DEPOSIT_TX_TYPE);
+    let user_tx = transactions
+        .iter()
+        .find(|tx| tx.ty() != DEPOSIT_TX_TYPE)
+        .expect("block holds a user transaction");
+
+    let mut evm = create_evm_with_tracer(BASE_RPC_URL, TxInspector::new()).await?;
+    let report = evm
+        .trace_transaction_by_hash(&provider, user_tx.tx_hash(), SystemTxHandling::Skip)
+        .await?
+        .expect("a user transaction is replayed");
+    assert_eq!(report.tx.caller, user_tx.from());
+    assert_eq!(report.tx.value, user_tx.value());
+    assert_eq!(evm.block.number, number);
+    Ok(())
+}
@@ -0,0 +1,165 @@
+//! Decoding of top-level return data with `SimulationTx::with_return_abi`
+//!
+//! Reports are built from hand-made execution results, so no RPC access is needed.
+
+use alloy::{
+    dyn_abi::DynSolValue,
+    hex,
+    json_abi::Function,
+    primitives::{address, Address, Bytes, TxKind, U256},
+};
+use revm::context_interface::result::{ExecutionResult, Output, SuccessReason};
+use revm_trace::{report::SimulationReport, SimulationTx};
+
+const MULTICALL: Address = address!("eefba1e63905ef1d7acba5a8513c70307c1ce441");
+
+fn aggregate() -> Function {
+    Function::parse(
+        "function aggregate((address target, bytes callData)[] calls) \
+         returns (uint256 blockNumber, bytes[] returnData)",
+    )
+    .unwrap()
+}
+
+/// Return data of `aggregate` at block 18,000,000 with two results
+fn aggregate_output() -> Bytes {
+    DynSolValue::Tuple(vec![
+        DynSolValue::Uint(U256::from(18_000_000), 256),
+        DynSolValue::Array(vec![
+            DynSolValue::Bytes(U256::from(42).to_be_bytes_vec()),
+            DynSolValue::Bytes(vec![]),
+        ]),
+    ])
+    .abi_encode_params()
+    .into()
+}
+
+fn tx() -> SimulationTx {
+    SimulationTx {
+        caller: address!("1000000000000000000000000000000000000001"),
+        transact_to: TxKind::Call(MULTICALL),
+        value: U256::ZERO,
+        data: Bytes::new(),
+        trace_config: None,
+        return_abi: None,
+    }
+}
+
+fn success(output: Bytes) -> ExecutionResult {
+    ExecutionResult::Success {
+        reason: SuccessReason::Return,
+        gas_used: 30_000,
+        gas_refunded: 0,
+        logs: vec![],
+        output: Output::Call(output),
+    }
+}
+
+fn report(tx: SimulationTx, result: &ExecutionResult) -> SimulationReport {
+    SimulationReport::new(tx, result, Default::default(), Default::default())
+}
+
+#[test]
+fn test_aggregate_return_is_decoded() {
+    let report = report(
+        tx().with_return_abi(aggregate()),
+        &success(aggregate_output()),
+    );
+    let summary = &report.summary;
+
+    assert_eq!(summary.decode_error, None);
+    let values = summary.decoded_return.as_ref().unwrap();
+    assert_eq!(values.len(), 2);
+    assert_eq!(values[0].as_uint(), Some((U256::from(18_000_000), 256)));
+    let results = values[1].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(
+        results[0].as_bytes(),
+        Some(&U256::from(42).to_be_bytes_vec()[..])
+    );
+    assert_eq!(results[1].as_bytes(), Some(&[][..]));
+
+    let word = format!("0x{}", hex::encode(U256::from(42).to_be_bytes_vec()));
+    assert_eq!(
+        summary.to_string(),
+        format!("success, gas used 30000, returned (18000000, [{word}, 0x])")
+    );
+    assert_eq!(
+        serde_json::to_value(summary).unwrap()["decoded_return"],
+        serde_json::json!(["18000000", [word, "0x"]])
+    );
+}
+
+#[test]
+fn test_structs_are_printed_as_tuples() {
+    let function = Function::parse(
+        "function balances() returns ((address token, uint256 amount)[] held, bool complete)",
+    )
+    .unwrap();
+    let token = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+    let output = DynSolValue::Tuple(vec![
+        DynSolValue::Array(vec![DynSolValue::Tuple(vec![
+            DynSolValue::Address(token),
+            DynSolValue::Uint(U256::from(5), 256),
+        ])]),
+        DynSolValue::Bool(true),
+    ])
+    .abi_encode_params();
+
+    let report = report(tx().with_return_abi(function), &success(output.into()));
+    assert_eq!(
+        report.summary.to_string(),
+        format!("success, gas used 30000, returned ([({token}, 5)], true)")
+    );
+}
+
+#[test]
+fn test_abi_mismatch_keeps_raw_output() {
+    // The first word of the output is not a valid offset for a string
+    let function = Function::parse("function aggregate() returns (string)").unwrap();
+    let report = report(tx().with_return_abi(function), &success(aggregate_output()));
+
+    assert!(report.summary.success);
+    assert_eq!(report.summary.decoded_return, None);
+    assert!(report
+        .summary
+        .decode_error
+        .as_deref()
+        .unwrap()
+        .starts_with("aggregate(): "));
+    assert_eq!(report.summary.output, aggregate_output());
+}
+
+#[test]
+fn test_decoding_is_strict() {
+    // Decodes the first three words, but leaves the rest of the data unread
+    let function =
+        Function::parse("function aggregate() returns (uint256, uint256, uint256)").unwrap();
+    let report = report(tx().with_return_abi(function), &success(aggregate_output()));
+
+    assert_eq!(report.summary.decoded_return, None);
+    assert!(report.summary.decode_error.is_some());
+}
+
+#[test]
+fn test_failed_transaction_is_not_decoded() {
+    let result = ExecutionResult::Revert {
+        gas_used: 30_000,
+        output: Bytes::new(),
+    };
+    let report = report(tx().with_return_abi(aggregate()), &result);
+
+    assert_eq!(report.summary.decoded_return, None);
+    assert_eq!(report.summary.decode_error, None);
+    assert_eq!(report.summary.to_string(), "failed, gas used 30000: 0x");
+}
+
+#[test]
+fn test_without_return_abi() {
+    let report = report(tx(), &success(Bytes::from(vec![0xab])));
+    assert_eq!(report.summary.decoded_return, None);
+    assert_eq!(
+        report.summary.to_string(),
+        "success, gas used 30000, returned 0xab"
+    );
+}
@@ -0,0 +1,342 @@
+//! Serialization round-trip tests for trace output types
+//!
+//! Stored traces must load back into the crate's types: randomly generated traces
+//! survive serialize → deserialize unchanged, and JSON written by the current
+//! version (checked in as a fixture) remains readable.
+
+use alloy::primitives::{Address, Bytes, FixedBytes, Log, LogData, B256, U256};
+use revm_trace::{
+    inspectors::tx_inspector::TxTraceOutput,
+    types::{
+        CallScheme, CallStatus, CallTrace, CoinbasePayment, ContractChange, CreateScheme,
+        CreationFunding, DecodedRevert, FacetRoute, FailedValueTransfer, FrameDiffs,
+        FrameFailureKind, FrameResources, HaltReasonKind, KnownPrecompile, ParseWarning,
+        SimulationWarning, SlotAccess, SlotChange, TargetKind, TokenTransfer, TokenType,
+        TransferRole, TruncatedBytes,
+    },
+    TraceLevel,
+};
+
+const FIXTURE: &str = include_str!("fixtures/trace_output.json");
+
+/// Small deterministic xorshift generator, enough to vary trace shapes
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn address(&mut self) -> Address {
+        Address::with_last_byte(self.below(256) as u8)
+    }
+
+    fn u256(&mut self) -> U256 {
+        match self.below(3) {
+            0 => U256::ZERO,
+            1 => U256::from(self.next()),
+            _ => U256::MAX - U256::from(self.next()),
+        }
+    }
+
+    fn bytes(&mut self) -> Bytes {
+        (0..self.below(40)).map(|_| self.next() as u8).collect()
+    }
+
+    fn truncated(&mut self) -> Option<TruncatedBytes> {
+        (self.below(4) == 0).then(|| TruncatedBytes {
+            len: self.below(1 << 20) as usize,
+            keccak256: B256::from(self.u256()),
+        })
+    }
+}
+
+fn random_status(rng: &mut Rng) -> CallStatus {
+    match rng.below(6) {
+        0 => CallStatus::Revert {
+            reason: None,
+            raw: rng.next().to_be_bytes().to_vec().into(),
+        },
+        1 => CallStatus::Halt {
+            reason: HaltReasonKind::OutOfGas,
+            raw: "OutOfGas(Basic)".to_string(),
+        },
+        2 => CallStatus::Revert {
+            reason: Some(DecodedRevert::Panic(U256::from(0x11))),
+            raw: Bytes::new(),
+        },
+        3 => CallStatus::FatalError,
+        _ => CallStatus::Success,
+    }
+}
+
+fn random_call(rng: &mut Rng, trace_address: Vec<usize>, depth: usize) -> CallTrace {
+    let (call_scheme, create_scheme) = match rng.below(5) {
+        0 => (None, Some(CreateScheme::Create)),
+        1 => (None, Some(CreateScheme::Create2 { salt: rng.u256() })),
+        2 => (Some(CallScheme::DelegateCall), None),
+        3 => (Some(CallScheme::StaticCall), None),
+        _ => (Some(CallScheme::Call), None),
+    };
+    let children = if depth < 3 { rng.below(3) as usize } else { 0 };
+    let tx_origin_reads = rng.below(3) as usize;
+    let subtraces = (0..children)
+        .map(|index| {
+            let mut child_address = trace_address.clone();
+            child_address.push(index);
+            random_call(rng, child_address, depth + 1)
+        })
+        .collect();
+    CallTrace {
+        from: rng.address(),
+        to: rng.address(),
+        value: rng.u256(),
+        input: rng.bytes(),
+        input_truncated: rng.truncated(),
+        call_scheme,
+        create_scheme,
+        gas_limit: U256::from(rng.below(30_000_000)),
+        gas_used: U256::from(rng.below(30_000_000)),
+        output: rng.bytes(),
+        output_truncated: rng.truncated(),
+        status: random_status(rng),
+        failure_kind: [
+            None,
+            Some(FrameFailureKind::StipendOutOfGas),
+            Some(FrameFailureKind::CallDepthExceeded),
+            Some(FrameFailureKind::InsufficientBalanceForTransfer),
+            Some(FrameFailureKind::StaticCallViolation),
+        ][rng.below(5) as usize],
+        error_origin: rng.below(2) == 0,
+        subtraces,
+        frame_id: CallTrace::frame_id_for(&trace_address),
+        trace_address,
+        slot_accesses: (0..rng.below(3))
+            .map(|_| SlotAccess {
+                address: rng.address(),
+                slot: rng.u256(),
+                old_value: rng.u256(),
+                new_value: rng.u256(),
+                is_write: rng.below(2) == 0,
+                is_cold: rng.below(2) == 0,
+            })
+            .collect(),
+        refund_counter_delta: rng.below(40_000) as i64 - 20_000,
+        log_indices: (0..rng.below(3) as usize).collect(),
+        depth,
+        truncated: rng.below(4) == 0,
+        target_kind: [
+            TargetKind::Contract,
+            TargetKind::Eoa,
+            TargetKind::Created,
+            TargetKind::Precompile(KnownPrecompile::EcRecover),
+            TargetKind::SystemContract("BeaconRoots"),
+        ][rng.below(5) as usize],
+        used_tx_origin: tx_origin_reads > 0,
+        tx_origin_reads,
+        facet_route: (rng.below(4) == 0).then(|| FacetRoute {
+            diamond: rng.address(),
+            facet: rng.address(),
+            selector: FixedBytes::from((rng.next() as u32).to_be_bytes()),
+        }),
+        code_address: (rng.below(4) == 0).then(|| rng.address()),
+        opcodes_executed: (rng.below(2) == 0).then(|| rng.below(1_000)),
+        resource_stats: (rng.below(2) == 0).then(|| FrameResources {
+            peak_memory_bytes: rng.below(1 << 20) * 32,
+            peak_stack_depth: rng.below(1_025),
+            mem_expansion_gas: rng.below(1_000_000),
+        }),
+    }
+}
+
+fn random_output(rng: &mut Rng) -> TxTraceOutput {
+    let token_types = [
+        TokenType::Native,
+        TokenType::ERC20,
+        TokenType::ERC721,
+        TokenType::ERC1155,
+    ];
+    TxTraceOutput {
+        asset_transfers: (0..rng.below(4))
+            .map(|transfer_index| TokenTransfer {
+                token: rng.address(),
+                from: rng.address(),
+                to: (rng.below(4) != 0).then(|| rng.address()),
+                value: rng.u256(),
+                token_type: token_types[rng.below(4) as usize].clone(),
+                id: (rng.below(2) == 0).then(|| rng.u256()),
+                role: [
+                    TransferRole::Normal,
+                    TransferRole::FeePayment,
+                    TransferRole::Wrap,
+                ][rng.below(3) as usize],
+                transfer_index: transfer_index as usize,
+                failed: rng.below(4) == 0,
+            })
+            .collect(),
+        call_trace: (rng.below(4) != 0).then(|| random_call(rng, vec![], 0)),
+        logs: (0..rng.below(3))
+            .map(|_| Log {
+                address: rng.address(),
+                data: LogData::new_unchecked(vec![B256::with_last_byte(1)], rng.bytes()),
+            })
+            .collect(),
+        logs_filtered: rng.below(2) == 0,
+        error_trace_address: (rng.below(2) == 0).then(|| vec![0, 1]),
+        contract_changes: vec![
+            ContractChange::Created {
+                address: rng.address(),
+                code_len: rng.below(100) as usize,
+            },
+            ContractChange::SelfDestructed {
+                contract: rng.address(),
+                beneficiary: rng.address(),
+                balance_moved: rng.u256(),
+                same_tx_creation: rng.below(2) == 0,
+                trace_address: vec![0],
+            },
+            ContractChange::CodeCleared {
+                address: rng.address(),
+            },
+        ],
+        coinbase_payments: (0..rng.below(2))
+            .map(|_| CoinbasePayment {
+                from: rng.address(),
+                value: rng.u256(),
+                trace_address: vec![],
+            })
+            .collect(),
+        creation_funding: (0..rng.below(2))
+            .map(|_| CreationFunding {
+                created: (rng.below(2) == 0).then(|| rng.address()),
+                funder: rng.address(),
+                value: rng.u256(),
+                success: rng.below(2) == 0,
+            })
+            .collect(),
+        frame_diffs: random_frame_diffs(rng),
+        reverted_writes: random_frame_diffs(rng),
+        trace_level: [TraceLevel::Off, TraceLevel::TransfersOnly, TraceLevel::Full]
+            [rng.below(3) as usize],
+        warnings: vec![
+            SimulationWarning::AddressCollision {
+                address: rng.address(),
+            },
+            SimulationWarning::NonceMismatchForCreate {
+                expected: rng.below(10),
+                actual: rng.below(10),
+            },
+            SimulationWarning::CallToEmptyAccount {
+                address: rng.address(),
+            },
+        ][..rng.below(4) as usize]
+            .to_vec(),
+        keccak_preimages: (0..rng.below(3))
+            .map(|_| (B256::from(rng.u256()), rng.bytes()))
+            .collect(),
+        tx_origin_usages: (0..rng.below(3))
+            .map(|_| (0..rng.below(3) as usize).collect())
+            .collect(),
+        failed_value_transfers: (0..rng.below(2))
+            .map(|_| FailedValueTransfer {
+                trace_address: (0..rng.below(3) as usize).collect(),
+                from: rng.address(),
+                to: rng.address(),
+                value: rng.u256(),
+                failure_kind: Some(FrameFailureKind::StipendOutOfGas),
+            })
+            .collect(),
+        parse_warnings: (0..rng.below(2))
+            .map(|_| ParseWarning::MalformedTransferLog {
+                token: rng.address(),
+                reason: "Transfer with 3 topics has 0 data bytes, expected 32".to_string(),
+            })
+            .collect(),
+        accessed_state: (0..rng.below(3))
+            .map(|_| {
+                let slots = (0..rng.below(3)).map(|_| rng.u256()).collect();
+                (rng.address(), slots)
+            })
+            .collect(),
+        executed_code_addresses: (0..rng.below(3)).map(|_| rng.address()).collect(),
+    }
+}
+
+fn random_frame_diffs(rng: &mut Rng) -> FrameDiffs {
+    (0..rng.below(3))
+        .map(|index| {
+            let changes = (0..=rng.below(2))
+                .map(|_| SlotChange {
+                    address: rng.address(),
+                    slot: rng.u256(),
+                    old_value: rng.u256(),
+                    new_value: rng.u256(),
+                })
+                .collect();
+            (vec![index as usize], changes)
+        })
+        .collect()
+}
+
+#[test]
+fn test_random_traces_round_trip() -> anyhow::Result<()> {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..200 {
+        let output = random_output(&mut rng);
+        let json = serde_json::to_value(&output)?;
+        let decoded: TxTraceOutput = serde_json::from_value(json.clone())?;
+        assert_eq!(serde_json::to_value(&decoded)?, json);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_fixture_is_readable() -> anyhow::Result<()> {
+    let output: TxTraceOutput = serde_json::from_str(FIXTURE)?;
+
+    assert_eq!(output.asset_transfers.len(), 1);
+    assert_eq!(output.asset_transfers[0].value, U256::from(1_000_000));
+    assert_eq!(output.asset_transfers[0].token_type, TokenType::ERC20);
+    assert_eq!(output.logs.len(), 1);
+    assert_eq!(output.trace_level, TraceLevel::Full);
+
+    let root = output
+        .call_trace
+        .as_ref()
+        .expect("fixture has a call trace");
+    assert_eq!(root.call_scheme, Some(CallScheme::Call));
+    assert!(root.status.is_success());
+    let delegate = &root.subtraces[0];
+    assert_eq!(delegate.call_scheme, Some(CallScheme::DelegateCall));
+    assert_eq!(delegate.trace_address, vec![0]);
+    assert_eq!(delegate.slot_accesses.len(), 1);
+    assert_eq!(delegate.log_indices, vec![0]);
+
+    // Re-serializing yields JSON that decodes to the same value
+    let json = serde_json::to_value(&output)?;
+    let again: TxTraceOutput = serde_json::from_value(json.clone())?;
+    assert_eq!(serde_json::to_value(&again)?, json);
+    Ok(())
+}
+
+#[test]
+fn test_missing_fields_default() -> anyhow::Result<()> {
+    // Output stored before contract changes, coinbase payments, frame diffs and trace
+    // levels existed
+    let output: TxTraceOutput = serde_json::from_str(
+        r#"{"asset_transfers":[],"call_trace":null,"logs":[],"error_trace_address":null}"#,
+    )?;
+    assert!(output.contract_changes.is_empty());
+    assert!(output.coinbase_payments.is_empty());
+    assert!(output.frame_diffs.is_empty() && output.reverted_writes.is_empty());
+    assert!(!output.logs_filtered);
+    assert_eq!(output.trace_level, TraceLevel::Full);
+    Ok(())
+}
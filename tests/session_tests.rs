@@ -0,0 +1,149 @@
+//! Re-running the tail of a stateful batch with `BatchSession`
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use alloy::primitives::{address, Address, TxKind, U256};
+use revm::{
+    interpreter::{interpreter_types::InterpreterTypes, CallInputs, CallOutcome},
+    Inspector,
+};
+use revm_trace::{
+    errors::{EvmError, RuntimeError},
+    traits::{Reset, TraceOutput},
+    types::{SimulationBatch, StorageDiff},
+    SimulationTx,
+};
+
+mod common;
+use common::{contract, ether, funded, Backend, TestEvm};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const RECIPIENT: Address = address!("2000000000000000000000000000000000000002");
+const COUNTER: Address = address!("c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0");
+
+/// Increments slot 0 and returns the new value
+const COUNTER_CODE: &str = "6000546001018060005560005260206000f3";
+
+/// Counts top-level calls of the current transaction and of the batch
+#[derive(Debug, Clone, Default)]
+struct CallCounter {
+    tx: usize,
+    batch: usize,
+}
+
+impl<CTX, INTR: InterpreterTypes> Inspector<CTX, INTR> for CallCounter {
+    fn call(&mut self, _context: &mut CTX, _inputs: &mut CallInputs) -> Option<CallOutcome> {
+        self.tx += 1;
+        self.batch += 1;
+        None
+    }
+}
+
+impl Reset for CallCounter {
+    fn reset(&mut self) {
+        self.tx = 0;
+        self.batch = 0;
+    }
+
+    /// Keeps the batch count
+    fn reset_tx(&mut self) {
+        self.tx = 0;
+    }
+
+    fn reset_slot_cache(&mut self) {}
+}
+
+impl TraceOutput for CallCounter {
+    type Output = usize;
+
+    fn get_output(&self) -> Self::Output {
+        self.tx
+    }
+}
+
+fn evm() -> TestEvm<Backend, CallCounter> {
+    common::evm_with(
+        common::backend([(SENDER, funded(ether())), (COUNTER, contract(COUNTER_CODE))]),
+        CallCounter::default(),
+        |_| {},
+    )
+}
+
+fn tx(to: Address, value: u64) -> SimulationTx {
+    SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Call(to),
+        value: U256::from(value),
+        data: Default::default(),
+        trace_config: None,
+        return_abi: None,
+    }
+}
+
+fn prefix() -> SimulationBatch {
+    SimulationBatch {
+        transactions: vec![tx(COUNTER, 0), tx(RECIPIENT, 1_000), tx(COUNTER, 0)],
+        is_stateful: true,
+        ..Default::default()
+    }
+}
+
+/// Old and new value of the counter slot in a storage diff
+fn counter_change(diff: &StorageDiff) -> (U256, U256) {
+    let access = &diff[&COUNTER][0];
+    (access.old_value, access.new_value)
+}
+
+#[test]
+fn test_tails_start_from_the_prefix() {
+    let mut evm = evm();
+    let mut session = evm.begin_session(prefix());
+    assert_eq!(session.prefix_results().len(), 3);
+    assert!(session.prefix_results().iter().all(Result::is_ok));
+    assert_eq!(session.evm().inspector.batch, 3);
+
+    for value in [0, 5] {
+        let results = session.run_tail(vec![tx(COUNTER, value)]);
+        let (result, diff, calls) = results[0].as_ref().unwrap();
+        assert!(result.is_success());
+        assert_eq!(*calls, 1);
+        // Each tail sees the counter as the prefix left it, not as the last tail did
+        assert_eq!(counter_change(diff), (U256::from(2), U256::from(3)));
+
+        let evm = session.evm();
+        // The prefix did not run again
+        assert_eq!(evm.inspector.batch, 4);
+        let executed = evm.take_last_executed_txs().remove(0).unwrap();
+        assert_eq!(executed.nonce, 3);
+    }
+}
+
+#[test]
+fn test_tail_changes_stay_in_the_tail() {
+    let mut evm = evm();
+    let mut session = evm.begin_session(prefix());
+    let results = session.run_tail(vec![tx(COUNTER, 0), tx(COUNTER, 0)]);
+    let (_, diff, _) = results[1].as_ref().unwrap();
+    assert_eq!(counter_change(diff), (U256::from(3), U256::from(4)));
+
+    let results = session.run_tail(vec![tx(COUNTER, 0)]);
+    let (_, diff, _) = results[0].as_ref().unwrap();
+    assert_eq!(counter_change(diff), (U256::from(2), U256::from(3)));
+}
+
+#[test]
+fn test_block_change_invalidates_session() {
+    let mut evm = evm();
+    let mut session = evm.begin_session(prefix());
+    assert!(session.is_valid());
+
+    session.evm().advance_block(1, 12);
+    assert!(!session.is_valid());
+    let results = session.run_tail(vec![tx(COUNTER, 0)]);
+    assert!(matches!(
+        results[0],
+        Err(EvmError::Runtime(RuntimeError::SessionInvalidated))
+    ));
+    assert_eq!(session.evm().inspector.batch, 3);
+}
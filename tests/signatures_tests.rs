@@ -0,0 +1,92 @@
+//! Tests for ABI-less trace formatting with a signature database
+//!
+//! Run with `cargo test --features signatures`.
+#![cfg(feature = "signatures")]
+
+use alloy::primitives::{address, b256, hex, Bytes, Log, LogData};
+use revm_trace::{
+    inspectors::tx_inspector::TxTraceOutput,
+    signatures::SignatureDb,
+    types::{CallScheme, CallTrace, KnownPrecompile, TargetKind},
+};
+
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/signatures.csv");
+
+fn call(input: &str, trace_address: Vec<usize>) -> CallTrace {
+    CallTrace {
+        to: address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"),
+        input: Bytes::from(hex::decode(input).unwrap()),
+        call_scheme: Some(CallScheme::Call),
+        trace_address,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_trace_formats_resolved_names() -> anyhow::Result<()> {
+    let db = SignatureDb::from_file(FIXTURE)?;
+    assert_eq!(db.len(), 3);
+
+    let mut root = call("a9059cbb0000", vec![]);
+    root.log_indices = vec![0];
+    root.subtraces = vec![call("deadbeef", vec![0])];
+    let output = TxTraceOutput {
+        call_trace: Some(root),
+        logs: vec![Log {
+            address: address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"),
+            data: LogData::new_unchecked(
+                vec![b256!(
+                    "ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+                )],
+                Bytes::from(vec![0x01]),
+            ),
+        }],
+        ..Default::default()
+    };
+
+    let rendered = db.render(&output).to_string();
+    let lines: Vec<&str> = rendered.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("CALL "));
+    assert!(lines[0].ends_with("transfer(address,uint256) [ok]"));
+    assert_eq!(lines[1], "  emit Transfer(0x01)");
+    // Unknown selectors stay hex
+    assert!(lines[2].ends_with("0xdeadbeef [ok]"));
+    Ok(())
+}
+
+#[test]
+fn test_precompile_and_system_contract_names() -> anyhow::Result<()> {
+    let db = SignatureDb::new();
+    let mut root = call("a9059cbb0000", vec![]);
+    root.target_kind = TargetKind::SystemContract("BeaconRoots");
+    let mut ecrecover = call("00", vec![0]);
+    ecrecover.call_scheme = Some(CallScheme::StaticCall);
+    ecrecover.target_kind = TargetKind::Precompile(KnownPrecompile::EcRecover);
+    root.subtraces = vec![ecrecover];
+    let output = TxTraceOutput {
+        call_trace: Some(root),
+        ..Default::default()
+    };
+
+    let rendered = db.render(&output).to_string();
+    let lines: Vec<&str> = rendered.lines().collect();
+    assert!(lines[0].contains(" (BeaconRoots) "));
+    assert!(lines[1].starts_with("  STATICCALL "));
+    assert!(lines[1].ends_with(" ecrecover [ok]"));
+    Ok(())
+}
+
+#[test]
+fn test_json_database_and_invalid_entries() -> anyhow::Result<()> {
+    let db = SignatureDb::from_json(r#"{"0x70a08231": "balanceOf(address)"}"#)?;
+    assert_eq!(
+        db.function(&hex::decode("70a08231")?),
+        Some("balanceOf(address)")
+    );
+    assert_eq!(db.describe_input(&[]), "fallback");
+
+    assert!(SignatureDb::from_csv("0x1234,foo()").is_err());
+    assert!(SignatureDb::from_csv("no separator").is_err());
+    Ok(())
+}
@@ -0,0 +1,191 @@
+//! Memoization of identical simulation batches
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+#![cfg(feature = "sim-cache")]
+
+use std::time::Duration;
+
+use alloy::primitives::{address, Address, TxKind, U256};
+use revm_trace::{
+    report::SimulationReport,
+    types::{LogFilter, SimulationBatch},
+    SimulationCache, SimulationTx, TxInspector,
+};
+
+mod common;
+use common::{contract, ether, funded, TestEvm};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const COUNTER: Address = address!("c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0");
+
+/// Increments storage slot 0 and logs the new value
+const COUNTER_CODE: &str = "6000546001018060005560005260206000a000";
+
+fn evm() -> TestEvm {
+    evm_with(TxInspector::new())
+}
+
+fn evm_with(inspector: TxInspector) -> TestEvm {
+    common::evm_with(
+        common::backend([(SENDER, funded(ether())), (COUNTER, contract(COUNTER_CODE))]),
+        inspector,
+        |_| {},
+    )
+}
+
+/// `count` stateful increments of the counter
+fn batch(count: u64) -> SimulationBatch {
+    SimulationBatch {
+        transactions: (1..=count)
+            .map(|value| SimulationTx {
+                caller: SENDER,
+                transact_to: TxKind::Call(COUNTER),
+                value: U256::from(value),
+                data: Default::default(),
+                trace_config: None,
+                return_abi: None,
+            })
+            .collect(),
+        is_stateful: true,
+        ..Default::default()
+    }
+}
+
+fn reports(
+    evm: &mut TestEvm,
+    batch: SimulationBatch,
+    cache: &SimulationCache,
+) -> Vec<SimulationReport> {
+    evm.trace_transactions_cached(batch, cache)
+        .into_iter()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn test_identical_batch_is_answered_without_execution() {
+    let cache = SimulationCache::new(8);
+    let mut evm = evm();
+    evm.enable_metrics(true);
+
+    let first = reports(&mut evm, batch(5), &cache);
+    let metrics = evm.take_last_batch_metrics().expect("First batch executes");
+    assert_eq!(metrics.per_tx.len(), 5);
+    assert_eq!((cache.hits(), cache.misses()), (0, 1));
+
+    let second = reports(&mut evm, batch(5), &cache);
+    assert!(
+        evm.take_last_batch_metrics().is_none(),
+        "Cached batch must not execute"
+    );
+    assert_eq!((cache.hits(), cache.misses()), (1, 1));
+    assert_eq!(
+        serde_json::to_vec(&first).unwrap(),
+        serde_json::to_vec(&second).unwrap()
+    );
+}
+
+#[test]
+fn test_different_batch_misses() {
+    let cache = SimulationCache::new(8);
+    let mut evm = evm();
+
+    reports(&mut evm, batch(5), &cache);
+    let shorter = reports(&mut evm, batch(4), &cache);
+    assert_eq!(shorter.len(), 4);
+    assert_eq!((cache.hits(), cache.misses()), (0, 2));
+    assert_eq!(cache.len(), 2);
+}
+
+#[test]
+fn test_invalidate_block_drops_entries() {
+    let cache = SimulationCache::new(8);
+    let mut evm = evm();
+
+    reports(&mut evm, batch(5), &cache);
+    assert_eq!(cache.invalidate_block(1), 0);
+    assert_eq!(cache.invalidate_block(0), 1);
+    assert!(cache.is_empty());
+
+    reports(&mut evm, batch(5), &cache);
+    assert_eq!((cache.hits(), cache.misses()), (0, 2));
+}
+
+#[test]
+fn test_expired_entries_are_executed_again() {
+    let cache = SimulationCache::new(8).with_ttl(Duration::ZERO);
+    let mut evm = evm();
+
+    reports(&mut evm, batch(5), &cache);
+    reports(&mut evm, batch(5), &cache);
+    assert_eq!((cache.hits(), cache.misses()), (0, 2));
+}
+
+#[test]
+fn test_least_recently_used_batch_is_evicted() {
+    let cache = SimulationCache::new(2);
+    let mut evm = evm();
+
+    reports(&mut evm, batch(1), &cache);
+    reports(&mut evm, batch(2), &cache);
+    // Touch the first batch so the second one is the oldest
+    reports(&mut evm, batch(1), &cache);
+    reports(&mut evm, batch(3), &cache);
+    assert_eq!(cache.len(), 2);
+
+    reports(&mut evm, batch(1), &cache);
+    reports(&mut evm, batch(2), &cache);
+    assert_eq!((cache.hits(), cache.misses()), (2, 4));
+}
+
+#[test]
+fn test_hit_clears_outputs_of_previous_batch() {
+    let cache = SimulationCache::new(8);
+    let mut evm = evm();
+    evm.enable_metrics(true);
+
+    reports(&mut evm, batch(3), &cache);
+    reports(&mut evm, batch(5), &cache);
+    // Outputs of the fresh run are left untaken, then replaced by the hit
+    reports(&mut evm, batch(3), &cache);
+    assert_eq!(cache.hits(), 1);
+    assert!(evm.take_last_batch_metrics().is_none());
+    assert!(evm.take_last_fee_accounting().is_none());
+    assert!(evm.take_last_timeline().is_none());
+    assert_eq!(evm.take_last_executed_txs(), vec![None; 3]);
+}
+
+#[test]
+fn test_trace_settings_are_part_of_the_key() {
+    let cache = SimulationCache::new(8);
+    reports(&mut evm(), batch(2), &cache);
+
+    // Each of these shapes the reports differently from the default inspector
+    let inspectors = [
+        TxInspector::new().with_max_trace_depth(1),
+        TxInspector::new().with_max_input_capture(0),
+        TxInspector::new().with_max_output_capture(0),
+        TxInspector::new().with_mapping_resolution(true),
+        TxInspector::new().with_max_preimages(1),
+        TxInspector::new().with_log_filter(LogFilter {
+            addresses: Some([SENDER].into()),
+            topics0: None,
+        }),
+    ];
+    let configured = inspectors.len() as u64;
+    for inspector in inspectors {
+        reports(&mut evm_with(inspector), batch(2), &cache);
+    }
+    assert_eq!((cache.hits(), cache.misses()), (0, 1 + configured));
+
+    let mut chunked = evm();
+    chunked.set_chunk_size(Some(1));
+    reports(&mut chunked, batch(2), &cache);
+    assert_eq!((cache.hits(), cache.misses()), (0, 2 + configured));
+
+    // The same settings hit again
+    reports(&mut evm(), batch(2), &cache);
+    assert_eq!(cache.hits(), 1);
+}
@@ -0,0 +1,158 @@
+//! Loading Anvil state dumps and genesis allocs, and exporting the overlay
+//!
+//! Run with `cargo test --features state-import`. Runs on an in-memory backend, so
+//! no RPC access is needed.
+#![cfg(feature = "state-import")]
+
+use alloy::primitives::{address, Address, TxKind, U256};
+use revm::{
+    database::{CacheDB, Database, DatabaseRef, EmptyDB},
+    Context, MainBuilder, MainContext,
+};
+use revm_trace::{
+    state_import::{
+        export_cache_state, load_anvil_state, load_anvil_state_into, load_genesis_alloc,
+        AnvilStateJson,
+    },
+    traits::TransactionTrace,
+    types::SimulationBatch,
+    SimulationTx, TraceEvm, TxInspector,
+};
+
+const FIXTURE: &str = include_str!("fixtures/anvil_state.json");
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+/// Increments slot 0 and returns the new value; starts at 41 in the fixture
+const COUNTER: Address = address!("c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0");
+
+type Evm<DB> = TraceEvm<CacheDB<DB>, TxInspector>;
+
+fn in_memory_evm<DB: DatabaseRef>(db: DB) -> Evm<DB> {
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(db));
+    ctx.cfg.disable_base_fee = true;
+    TraceEvm::new(ctx.build_mainnet_with_inspector(TxInspector::new()))
+}
+
+fn increment() -> SimulationTx {
+    SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Call(COUNTER),
+        value: U256::ZERO,
+        data: Default::default(),
+        trace_config: None,
+        return_abi: None,
+    }
+}
+
+#[test]
+fn test_simulate_against_dump() {
+    let mut backend = CacheDB::new(EmptyDB::default());
+    let summary = load_anvil_state_into(&mut backend, FIXTURE).unwrap();
+    assert_eq!(
+        (summary.accounts, summary.contracts, summary.storage_slots),
+        (2, 1, 1)
+    );
+    let mut skipped: Vec<_> = summary.skipped.iter().map(|s| s.key.as_str()).collect();
+    skipped.sort();
+    assert_eq!(
+        skipped,
+        [
+            "0x2000000000000000000000000000000000000002",
+            "not-an-address"
+        ]
+    );
+
+    let mut evm = in_memory_evm(backend);
+    let results = evm.trace_transactions(SimulationBatch {
+        transactions: vec![increment(), increment()],
+        is_stateful: true,
+        ..Default::default()
+    });
+    let counts: Vec<_> = results
+        .into_iter()
+        .map(|result| {
+            let (result, ..) = result.expect("transaction executes");
+            U256::from_be_slice(result.output().unwrap())
+        })
+        .collect();
+    assert_eq!(counts, [U256::from(42), U256::from(43)]);
+    let nonces: Vec<_> = evm
+        .take_last_executed_txs()
+        .into_iter()
+        .map(|tx| tx.unwrap().nonce)
+        .collect();
+    assert_eq!(nonces, [3, 4]);
+}
+
+#[test]
+fn test_export_round_trip() {
+    let mut evm = in_memory_evm(EmptyDB::default());
+    load_anvil_state(&mut evm, FIXTURE).unwrap();
+    evm.insert_storage(COUNTER, U256::from(7), U256::from(9))
+        .unwrap();
+    evm.set_balance(SENDER, U256::from(5)).unwrap();
+
+    let exported = export_cache_state(&mut evm);
+    assert_eq!(exported.accounts.len(), 2);
+    let counter = &exported.accounts[&COUNTER];
+    assert_eq!(counter.nonce, 1);
+    assert_eq!(
+        counter.storage,
+        [(U256::ZERO, U256::from(41)), (U256::from(7), U256::from(9))]
+            .into_iter()
+            .collect()
+    );
+    assert_eq!(exported.accounts[&SENDER].balance, U256::from(5));
+
+    // Import into a fresh EVM and export again
+    let json = serde_json::to_string(&exported).unwrap();
+    let mut other = in_memory_evm(EmptyDB::default());
+    let summary = load_anvil_state(&mut other, &json).unwrap();
+    assert!(summary.skipped.is_empty());
+    assert_eq!(export_cache_state(&mut other), exported);
+    let parsed: AnvilStateJson = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, exported);
+}
+
+#[test]
+fn test_genesis_alloc() {
+    let genesis = r#"{
+        "config": { "chainId": 1337 },
+        "gasLimit": "0x1c9c380",
+        "alloc": {
+            "1000000000000000000000000000000000000001": { "balance": "0x3e8", "nonce": "0x2" },
+            "0xc0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0": {
+                "balance": "0x0",
+                "code": "0x6000546001018060005560005260206000f3",
+                "storage": {
+                    "0x0000000000000000000000000000000000000000000000000000000000000000":
+                        "0x0000000000000000000000000000000000000000000000000000000000000007"
+                }
+            }
+        }
+    }"#;
+    let mut evm = in_memory_evm(EmptyDB::default());
+    let summary = load_genesis_alloc(&mut evm, genesis).unwrap();
+    assert_eq!(
+        (summary.accounts, summary.contracts, summary.storage_slots),
+        (2, 1, 1)
+    );
+    let sender = evm.db().basic(SENDER).unwrap().unwrap();
+    assert_eq!((sender.balance, sender.nonce), (U256::from(1_000), 2));
+    assert_eq!(
+        evm.db().storage(COUNTER, U256::ZERO).unwrap(),
+        U256::from(7)
+    );
+    // Slots missing from the document read as zero
+    assert_eq!(
+        evm.db().storage(COUNTER, U256::from(1)).unwrap(),
+        U256::ZERO
+    );
+
+    // A bare alloc object works the same
+    let alloc: serde_json::Value = serde_json::from_str(genesis).unwrap();
+    let mut bare = in_memory_evm(EmptyDB::default());
+    let summary = load_genesis_alloc(&mut bare, &alloc["alloc"].to_string()).unwrap();
+    assert_eq!(summary.accounts, 2);
+    assert_eq!(export_cache_state(&mut bare), export_cache_state(&mut evm));
+}
@@ -0,0 +1,108 @@
+//! View-safety checks with `TraceEvm::static_call`
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use alloy::{
+    primitives::{address, keccak256, Address, Bytes, B256, U256},
+    sol,
+    sol_types::SolCall,
+};
+use revm_trace::errors::{EvmError, RuntimeError};
+
+mod common;
+use common::{contract, TestEvm};
+
+sol! {
+    function balanceOf(address owner) external returns (uint256);
+    function transfer(address to, uint256 amount) external returns (bool);
+}
+
+const HOLDER: Address = address!("cafe00000000000000000000000000000000face");
+const BOB: Address = address!("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+const TOKEN: Address = address!("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+const ROUTER: Address = address!("c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0");
+
+/// Minimal token with `balances` as the mapping at slot 0: `balanceOf(owner)` and an
+/// unchecked `transfer(to, amount)` returning true
+const TOKEN_CODE: &str = concat!(
+    // Dispatch on the selector
+    "60003560e01c806370a0823114610020578063a9059cbb1461003a57600080fd",
+    // balanceOf
+    "5b600435600052600060205260406000205460005260206000f3",
+    // transfer
+    "5b336000526000602052604060002080546024359003905560043560005260406000208054",
+    "602435019055600160005260206000f3",
+);
+
+/// Forwards its calldata to TOKEN and returns the first word of the result
+const ROUTER_CODE: &str = concat!(
+    "366000600037602060003660006000",
+    "73aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+    "5af15060206000f3",
+);
+
+fn evm() -> TestEvm {
+    let mut backend = common::backend([
+        (TOKEN, contract(TOKEN_CODE)),
+        (ROUTER, contract(ROUTER_CODE)),
+    ]);
+    let holder_slot = keccak256([HOLDER.into_word().as_slice(), B256::ZERO.as_slice()].concat());
+    backend
+        .insert_account_storage(TOKEN, U256::from_be_bytes(holder_slot.0), U256::from(1_000))
+        .unwrap();
+    common::evm(backend)
+}
+
+#[test]
+fn test_balance_of_succeeds() {
+    let mut evm = evm();
+    let data: Bytes = balanceOfCall { owner: HOLDER }.abi_encode().into();
+    let output = evm.static_call(TOKEN, data, Address::ZERO).unwrap();
+    assert_eq!(
+        balanceOfCall::abi_decode_returns(&output).unwrap(),
+        U256::from(1_000)
+    );
+}
+
+#[test]
+fn test_transfer_fails_with_mutation_error() {
+    let mut evm = evm();
+    let data: Bytes = transferCall {
+        to: BOB,
+        amount: U256::from(10),
+    }
+    .abi_encode()
+    .into();
+
+    match evm.static_call(TOKEN, data.clone(), HOLDER) {
+        Err(EvmError::Runtime(RuntimeError::StateMutationInStaticCall {
+            opcode,
+            trace_address,
+        })) => {
+            assert_eq!(opcode, "SSTORE");
+            assert!(trace_address.is_empty());
+        }
+        other => panic!("expected a state mutation error, got {other:?}"),
+    }
+
+    // The router ignores the failed subcall, yet the attempt is still reported
+    match evm.static_call(ROUTER, data, HOLDER) {
+        Err(EvmError::Runtime(RuntimeError::StateMutationInStaticCall {
+            opcode,
+            trace_address,
+        })) => {
+            assert_eq!(opcode, "SSTORE");
+            assert_eq!(trace_address, vec![0]);
+        }
+        other => panic!("expected a state mutation error, got {other:?}"),
+    }
+
+    // Nothing was written
+    let data: Bytes = balanceOfCall { owner: HOLDER }.abi_encode().into();
+    let output = evm.static_call(TOKEN, data, Address::ZERO).unwrap();
+    assert_eq!(
+        balanceOfCall::abi_decode_returns(&output).unwrap(),
+        U256::from(1_000)
+    );
+}
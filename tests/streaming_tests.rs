@@ -0,0 +1,147 @@
+//! Event streaming with `StreamingInspector`
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use std::{cell::RefCell, rc::Rc};
+
+use alloy::primitives::{address, hex, Address, Bytes, FixedBytes, TxKind, B256, U256};
+use revm::{
+    database::{CacheDB, EmptyDB},
+    state::{AccountInfo, Bytecode},
+    Context, MainBuilder, MainContext,
+};
+use revm_trace::{
+    inspectors::streaming::{StreamingInspector, TraceEvent},
+    traits::TransactionTrace,
+    types::{SimulationBatch, NATIVE_TOKEN_ADDRESS},
+    SimulationTx, TraceEvm,
+};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const OUTER: Address = address!("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+const INNER: Address = address!("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+
+/// Emits LOG1 with topic 2
+const INNER_CODE: &str = "600260006000a100";
+
+/// Calls INNER without value or input, then emits LOG1 with topic 1
+const OUTER_CODE: &str = concat!(
+    "60006000600060006000",
+    "73bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb",
+    "5af150",
+    "600160006000a100"
+);
+
+/// Deploys a contract incrementing storage slot 0 on every call
+const COUNTER_BYTECODE: &str = "600a80600b6000396000f360005460010160005500";
+
+/// Owned summary of a streamed event
+#[derive(Debug, PartialEq)]
+enum Seen {
+    Start(usize, Address, Option<Address>, U256, Option<FixedBytes<4>>),
+    End(usize, bool),
+    Transfer(Address, Option<Address>, U256),
+    Log(Address, Option<B256>),
+    Create(Address),
+}
+
+impl From<TraceEvent<'_>> for Seen {
+    fn from(event: TraceEvent<'_>) -> Self {
+        match event {
+            TraceEvent::CallStart {
+                depth,
+                from,
+                to,
+                value,
+                selector,
+            } => Seen::Start(depth, from, to, value, selector),
+            TraceEvent::CallEnd { depth, status, .. } => Seen::End(depth, status.is_ok()),
+            TraceEvent::Transfer(transfer) => {
+                assert_eq!(transfer.token, NATIVE_TOKEN_ADDRESS);
+                Seen::Transfer(transfer.from, transfer.to, transfer.value)
+            }
+            TraceEvent::Log { address, topic0 } => Seen::Log(address, topic0),
+            TraceEvent::Create { address } => Seen::Create(address),
+        }
+    }
+}
+
+fn install(db: &mut CacheDB<EmptyDB>, address: Address, code: &str) {
+    let code = Bytecode::new_raw(hex::decode(code).unwrap().into());
+    db.insert_account_info(
+        address,
+        AccountInfo {
+            code_hash: code.hash_slow(),
+            code: Some(code),
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn test_nested_call_events_in_order() {
+    let mut backend = CacheDB::new(EmptyDB::default());
+    install(&mut backend, OUTER, OUTER_CODE);
+    install(&mut backend, INNER, INNER_CODE);
+    backend.insert_account_info(
+        SENDER,
+        AccountInfo {
+            balance: U256::from(10u64.pow(18)),
+            ..Default::default()
+        },
+    );
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(backend));
+    ctx.cfg.disable_base_fee = true;
+
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let sink = seen.clone();
+    let inspector = StreamingInspector::new(move |event| sink.borrow_mut().push(Seen::from(event)));
+    let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(inspector));
+
+    let selector = FixedBytes::new([0x12, 0x34, 0x56, 0x78]);
+    let results = evm.trace_transactions(SimulationBatch {
+        is_stateful: true,
+        transactions: vec![
+            SimulationTx {
+                caller: SENDER,
+                transact_to: TxKind::Call(OUTER),
+                value: U256::from(1),
+                data: Bytes::copy_from_slice(selector.as_slice()),
+                trace_config: None,
+                return_abi: None,
+            },
+            SimulationTx {
+                caller: SENDER,
+                transact_to: TxKind::Create,
+                value: U256::ZERO,
+                data: hex::decode(COUNTER_BYTECODE).unwrap().into(),
+                trace_config: None,
+                return_abi: None,
+            },
+        ],
+        ..Default::default()
+    });
+    for result in results {
+        let (result, _, ()) = result.expect("transaction executes");
+        assert!(result.is_success(), "{result:?}");
+    }
+
+    // The first transaction bumped the sender nonce to 1
+    let counter = SENDER.create(1);
+    assert_eq!(
+        *seen.borrow(),
+        vec![
+            Seen::Start(0, SENDER, Some(OUTER), U256::from(1), Some(selector)),
+            Seen::Transfer(SENDER, Some(OUTER), U256::from(1)),
+            Seen::Start(1, OUTER, Some(INNER), U256::ZERO, None),
+            Seen::Log(INNER, Some(B256::with_last_byte(2))),
+            Seen::End(1, true),
+            Seen::Log(OUTER, Some(B256::with_last_byte(1))),
+            Seen::End(0, true),
+            Seen::Start(0, SENDER, None, U256::ZERO, None),
+            Seen::Create(counter),
+            Seen::End(0, true),
+        ]
+    );
+}
@@ -0,0 +1,130 @@
+//! Integration tests for swap simulation
+//!
+//! Swaps on the Uniswap routers at a fixed mainnet block, funding the user through
+//! the deal cheat, and checks the received amount, the pool hops and revert reporting.
+
+use alloy::primitives::{address, Address, U256};
+use revm_trace::{
+    errors::{EvmError, RuntimeError},
+    utils::swap_utils::{simulate_swap, SwapCalldata, SwapSpec},
+    EvmBuilder, TxInspector,
+};
+
+const ETH_RPC_URL: &str = "https://eth.llamarpc.com";
+const BLOCK_NUMBER: u64 = 18_000_000;
+const USER: Address = address!("1000000000000000000000000000000000000001");
+const V2_ROUTER: Address = address!("7a250d5630B4cF539739dF2C5dAcb4c659F2488D");
+const V3_ROUTER: Address = address!("E592427A0AEce92De3Edee1F82c4A8b6C5E9583D");
+/// Uniswap V2 USDC/WETH pair
+const V2_USDC_WETH: Address = address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc");
+/// Uniswap V3 USDC/WETH 0.05% pool
+const V3_USDC_WETH: Address = address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640");
+const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+
+fn one_weth_to_usdc(router: Address, calldata_builder: SwapCalldata) -> SwapSpec {
+    SwapSpec {
+        user: USER,
+        router,
+        token_in: WETH,
+        token_out: USDC,
+        amount_in: U256::from(10).pow(U256::from(18)),
+        calldata_builder,
+        fund_user: true,
+    }
+}
+
+/// Checks a 1 WETH → USDC result against the ETH price of the block (about $1,650)
+fn assert_plausible_usdc(amount_out: U256) {
+    assert!(
+        amount_out > U256::from(1_000_000_000u64) && amount_out < U256::from(3_000_000_000u64),
+        "1 WETH bought {amount_out} USDC units"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_v2_swap_weth_to_usdc() -> anyhow::Result<()> {
+    let mut evm = EvmBuilder::new_alloy(ETH_RPC_URL)
+        .with_block_number(BLOCK_NUMBER)
+        .with_tracer(TxInspector::new())
+        .build()
+        .await?;
+
+    let result = simulate_swap(
+        &mut evm,
+        one_weth_to_usdc(
+            V2_ROUTER,
+            SwapCalldata::UniswapV2 {
+                amount_out_min: U256::ZERO,
+            },
+        ),
+    )?;
+    assert_plausible_usdc(result.amount_out);
+    assert_eq!(result.pools, vec![V2_USDC_WETH]);
+    assert_eq!(result.route_frames, vec![vec![2]]);
+    let hop = result
+        .trace
+        .call_trace
+        .as_ref()
+        .unwrap()
+        .frame_at(&result.route_frames[0])
+        .unwrap();
+    assert_eq!(hop.to, V2_USDC_WETH);
+    assert!(result.gas_used > 50_000);
+    let impact = result.price_impact_estimate.unwrap();
+    assert!((0.0..0.01).contains(&impact), "price impact {impact}");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_v3_swap_weth_to_usdc() -> anyhow::Result<()> {
+    let mut evm = EvmBuilder::new_alloy(ETH_RPC_URL)
+        .with_block_number(BLOCK_NUMBER)
+        .with_tracer(TxInspector::new())
+        .build()
+        .await?;
+
+    let result = simulate_swap(
+        &mut evm,
+        one_weth_to_usdc(
+            V3_ROUTER,
+            SwapCalldata::UniswapV3 {
+                fee: 500,
+                amount_out_min: U256::ZERO,
+            },
+        ),
+    )?;
+    assert_plausible_usdc(result.amount_out);
+    assert_eq!(result.pools, vec![V3_USDC_WETH]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_unreachable_minimum_reports_revert_reason() -> anyhow::Result<()> {
+    let mut evm = EvmBuilder::new_alloy(ETH_RPC_URL)
+        .with_block_number(BLOCK_NUMBER)
+        .with_tracer(TxInspector::new())
+        .build()
+        .await?;
+
+    let result = simulate_swap(
+        &mut evm,
+        one_weth_to_usdc(
+            V2_ROUTER,
+            SwapCalldata::UniswapV2 {
+                amount_out_min: U256::from(1_000_000_000_000u64),
+            },
+        ),
+    );
+    match result {
+        Err(EvmError::Runtime(RuntimeError::Revert(reason))) => assert!(
+            reason.contains("UniswapV2Router: INSUFFICIENT_OUTPUT_AMOUNT"),
+            "{reason}"
+        ),
+        other => panic!("expected a revert, got {other:?}"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,130 @@
+//! Moving the per-transaction output out of the inspector
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use alloy::primitives::{address, hex, Address, TxKind, U256};
+use revm::{
+    context::TxEnv,
+    database::{CacheDB, EmptyDB},
+    state::{AccountInfo, Bytecode},
+    Context, ExecuteEvm, InspectEvm, MainBuilder, MainContext,
+};
+use revm_trace::{
+    traits::{TraceOutput, TransactionTrace},
+    types::SimulationBatch,
+    SimulationTx, TraceEvm, TxInspector,
+};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const FANOUT: Address = address!("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+const EMITTER: Address = address!("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+
+/// Number of subcalls made by FANOUT
+const SUBCALLS: usize = 200;
+
+/// Logs the word 0x2a and returns it
+const EMITTER_CODE: &str = "602a60005260206000a060206000f3";
+
+/// `CALL(GAS, EMITTER, 1, 0, 0, 0, 0)`, so every subcall also moves value
+const CALL_EMITTER: &str = "6000600060006000600173bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb5af150";
+
+fn fanout_evm() -> TraceEvm<CacheDB<CacheDB<EmptyDB>>, TxInspector> {
+    let mut backend = CacheDB::new(EmptyDB::default());
+    backend.insert_account_info(
+        SENDER,
+        AccountInfo {
+            balance: U256::from(10).pow(U256::from(18)),
+            ..Default::default()
+        },
+    );
+    for (address, code) in [
+        (FANOUT, format!("{}00", CALL_EMITTER.repeat(SUBCALLS))),
+        (EMITTER, EMITTER_CODE.to_string()),
+    ] {
+        let code = Bytecode::new_raw(hex::decode(code).unwrap().into());
+        backend.insert_account_info(
+            address,
+            AccountInfo {
+                code_hash: code.hash_slow(),
+                code: Some(code),
+                ..Default::default()
+            },
+        );
+    }
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(backend));
+    ctx.cfg.disable_base_fee = true;
+    TraceEvm::new(ctx.build_mainnet_with_inspector(TxInspector::new()))
+}
+
+fn fanout_tx() -> SimulationTx {
+    SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Call(FANOUT),
+        value: U256::from(SUBCALLS),
+        data: Default::default(),
+        trace_config: None,
+        return_abi: None,
+    }
+}
+
+#[test]
+fn test_taken_output_equals_cloned_output() {
+    let mut evm = fanout_evm();
+    let tx = TxEnv::builder()
+        .caller(SENDER)
+        .kind(TxKind::Call(FANOUT))
+        .value(U256::from(SUBCALLS))
+        .gas_limit(30_000_000)
+        .build_fill();
+    evm.set_tx(tx);
+    let result = evm.inspect_replay().unwrap();
+    assert!(result.result.is_success(), "{:?}", result.result);
+
+    let cloned = evm.get_inspector_output();
+    let taken = evm.inspector.take_output();
+    assert_eq!(
+        cloned.call_trace.as_ref().unwrap().subtraces.len(),
+        SUBCALLS
+    );
+    assert_eq!(cloned.logs.len(), SUBCALLS);
+    assert_eq!(
+        serde_json::to_value(&cloned).unwrap(),
+        serde_json::to_value(&taken).unwrap()
+    );
+
+    // The collections were moved out, so a second take comes back empty
+    let empty = evm.inspector.take_output();
+    assert!(empty.call_trace.is_none());
+    assert!(empty.logs.is_empty());
+    assert!(empty.asset_transfers.is_empty());
+}
+
+#[test]
+fn test_batch_outputs_do_not_carry_over() {
+    let mut evm = fanout_evm();
+    let mut results = evm.trace_transactions(SimulationBatch {
+        transactions: vec![fanout_tx(), fanout_tx()],
+        is_stateful: true,
+        ..Default::default()
+    });
+    let (_, _, first) = results.remove(0).unwrap();
+    let (result, _, second) = results.remove(0).unwrap();
+    assert!(result.is_success(), "{result:?}");
+
+    // Each output holds only its own transaction's frames, logs and transfers
+    for output in [&first, &second] {
+        let root = output.call_trace.as_ref().unwrap();
+        assert_eq!(root.subtraces.len(), SUBCALLS);
+        assert_eq!(output.logs.len(), SUBCALLS);
+        assert_eq!(output.asset_transfers.len(), SUBCALLS + 1);
+    }
+    assert_eq!(
+        serde_json::to_value(&first.logs).unwrap(),
+        serde_json::to_value(&second.logs).unwrap()
+    );
+    assert_eq!(
+        serde_json::to_value(&first.call_trace).unwrap(),
+        serde_json::to_value(&second.call_trace).unwrap()
+    );
+}
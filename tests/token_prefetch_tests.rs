@@ -0,0 +1,172 @@
+//! Concurrent token info prefetching over RPC
+//!
+//! Runs against an in-process transport answering `eth_call`s by target and selector,
+//! so no RPC access is needed. The transport counts requests in flight to show that
+//! the calls are issued concurrently.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use alloy::{
+    eips::BlockId,
+    network::AnyNetwork,
+    primitives::{Address, U256},
+    providers::ProviderBuilder,
+    rpc::{
+        client::RpcClient,
+        json_rpc::{
+            ErrorPayload, RequestPacket, Response, ResponsePacket, ResponsePayload,
+            SerializedRequest,
+        },
+    },
+    sol_types::SolValue,
+    transports::{TransportError, TransportFut},
+};
+use revm::{
+    database::{CacheDB, EmptyDB},
+    Context as EvmContext, MainBuilder, MainContext,
+};
+use revm_trace::{
+    types::{AnyNetworkProvider, TokenInfo},
+    utils::erc20_utils::{get_token_infos_cached, prefetch_token_infos},
+    TraceEvm, TxInspector,
+};
+use serde_json::value::RawValue;
+
+const TOKENS: u8 = 10;
+
+/// Token without ERC20 metadata; every call to it reverts
+const BROKEN: Address = Address::repeat_byte(0xff);
+
+/// Transport serving ERC20 metadata of tokens `0x..01` to `0x..0a`
+#[derive(Clone, Default)]
+struct TokenNode {
+    requests: Arc<AtomicUsize>,
+    in_flight: Arc<AtomicUsize>,
+    peak_in_flight: Arc<AtomicUsize>,
+}
+
+impl TokenNode {
+    async fn handle(self, packet: RequestPacket) -> Result<ResponsePacket, TransportError> {
+        let RequestPacket::Single(request) = packet else {
+            panic!("unexpected batch request");
+        };
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+        // Stay in flight for a few polls so concurrent requests overlap
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+        Ok(ResponsePacket::Single(Response {
+            id: request.id().clone(),
+            payload: answer(&request),
+        }))
+    }
+}
+
+impl tower::Service<RequestPacket> for TokenNode {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, packet: RequestPacket) -> Self::Future {
+        Box::pin(self.clone().handle(packet))
+    }
+}
+
+/// Answers an `eth_call` to token `i` with name `Token i`, symbol `TKi`, `i`
+/// decimals and a total supply of `i * 1000`
+fn answer(request: &SerializedRequest) -> ResponsePayload {
+    assert_eq!(request.method(), "eth_call");
+    let params: serde_json::Value = serde_json::from_str(request.params().unwrap().get()).unwrap();
+    let tx = &params[0];
+    let to: Address = serde_json::from_value(tx["to"].clone()).unwrap();
+    let input = tx["input"].as_str().or(tx["data"].as_str()).unwrap();
+    if to == BROKEN {
+        return ResponsePayload::Failure(ErrorPayload::internal_error_message(
+            "execution reverted".into(),
+        ));
+    }
+
+    let i = to[19];
+    let output = match &input[..10] {
+        "0x06fdde03" => (format!("Token {i}"),).abi_encode_params(),
+        "0x95d89b41" => (format!("TK{i}"),).abi_encode_params(),
+        "0x313ce567" => (i,).abi_encode_params(),
+        "0x18160ddd" => (U256::from(i as u64 * 1000),).abi_encode_params(),
+        selector => panic!("unexpected selector {selector}"),
+    };
+    let output = serde_json::to_string(&alloy::primitives::Bytes::from(output)).unwrap();
+    ResponsePayload::Success(RawValue::from_string(output).unwrap())
+}
+
+fn provider(node: TokenNode) -> AnyNetworkProvider {
+    ProviderBuilder::new()
+        .network::<AnyNetwork>()
+        .connect_client(RpcClient::new(node, true))
+}
+
+fn token(i: u8) -> Address {
+    Address::with_last_byte(i)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_prefetch_issues_calls_concurrently() {
+    let node = TokenNode::default();
+    let mut tokens: Vec<Address> = (1..=TOKENS).map(token).collect();
+    // Duplicates are fetched once, broken tokens are left out
+    tokens.push(token(1));
+    tokens.push(BROKEN);
+
+    let infos = prefetch_token_infos(&provider(node.clone()), BlockId::latest(), &tokens).await;
+
+    assert_eq!(infos.len(), TOKENS as usize);
+    for i in 1..=TOKENS {
+        let info = &infos[&token(i)];
+        assert_eq!(info.name, format!("Token {i}"));
+        assert_eq!(info.symbol, format!("TK{i}"));
+        assert_eq!(info.decimals, i);
+        assert_eq!(info.total_supply, U256::from(i as u64 * 1000));
+    }
+    // Four calls per distinct token
+    assert_eq!(
+        node.requests.load(Ordering::SeqCst),
+        4 * (TOKENS as usize + 1)
+    );
+    assert!(node.peak_in_flight.load(Ordering::SeqCst) > 1);
+}
+
+#[test]
+fn test_cached_infos_skip_the_evm() {
+    // The EVM has no token contracts, so only cached tokens can be answered
+    let ctx = EvmContext::mainnet().with_db(CacheDB::new(EmptyDB::default()));
+    let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(TxInspector::new()));
+    let mut cache = HashMap::from([(
+        token(1),
+        TokenInfo {
+            name: "Token 1".to_string(),
+            symbol: "TK1".to_string(),
+            decimals: 1,
+            total_supply: U256::from(1000),
+        },
+    )]);
+
+    let infos = get_token_infos_cached(&mut evm, &[token(1), token(1)], &mut cache).unwrap();
+    assert_eq!(infos.len(), 2);
+    assert_eq!(infos[1].symbol, "TK1");
+    assert!(get_token_infos_cached(&mut evm, &[token(2)], &mut cache).is_err());
+    assert_eq!(cache.len(), 1);
+}
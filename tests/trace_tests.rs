@@ -23,15 +23,39 @@
 //! - Recent blocks: May succeed on regular nodes
 //! - Old blocks: Requires archive node access
 
-use revm::context::ContextTr;
+use revm::context::{ContextTr, TxEnv};
+use revm::context_interface::result::InvalidTransaction;
 use revm::database::Database;
+use revm::state::{AccountInfo, Bytecode};
+use revm::{ExecuteEvm, InspectEvm};
 use revm_trace::{
-    create_evm_with_tracer, utils::error_utils::parse_custom_error, SimulationBatch, SimulationTx,
-    TransactionTrace, TxInspector,
+    analysis::{address_activity, diff_trace_outputs, verify_transfer_consistency, FrameChange},
+    create_evm_with_tracer,
+    errors::{EvmError, RuntimeError},
+    evm::{builder::InspectorEvm, timeline::TimelineEventKind},
+    export::parity::TxMeta,
+    fuzz::{run_sweep, ArgGen, TxTemplate},
+    harness::{CheckedBatch, Expectation},
+    inspectors::tx_inspector::TxTraceOutput,
+    report::{BalanceSnapshot, SimulationReport},
+    types::{
+        CallScheme, CallStatus, CallTrace, CoinbasePayment, ContractChange, KnownPrecompile,
+        LogFilter, SlotAccessType, SlotChange, TargetKind,
+    },
+    utils::{
+        deploy_utils::{build_deployment_tx, deploy_and_get_address},
+        error_utils::parse_custom_error,
+        proxy_utils::{annotate_implementations, get_implementation},
+    },
+    EvmBuilder, SimulationBatch, SimulationTx, TraceLevel, TraceOutput, TransactionTrace,
+    TxInspector,
 };
+use std::{cell::Cell, collections::HashSet, rc::Rc};
 
 use alloy::{
-    primitives::{address, hex, Address, TxKind, U256},
+    dyn_abi::DynSolValue,
+    eips::eip2930::{AccessList, AccessListItem},
+    primitives::{address, hex, Address, Bytes, TxKind, B256, I256, U256},
     sol,
     sol_types::SolCall,
 };
@@ -116,6 +140,8 @@ async fn test_nested_revert_with_try_catch() -> anyhow::Result<()> {
         transact_to: TxKind::Create,
         value: U256::ZERO,
         data: hex::decode(REVERT_DEMO_BYTECODE).unwrap().into(),
+        trace_config: None,
+        return_abi: None,
     };
 
     // 2. deploy OwnerDemo contract
@@ -124,6 +150,8 @@ async fn test_nested_revert_with_try_catch() -> anyhow::Result<()> {
         transact_to: TxKind::Create,
         value: U256::ZERO,
         data: hex::decode(OWNER_DEMO_BYTECODE).unwrap().into(),
+        trace_config: None,
+        return_abi: None,
     };
 
     // 3. call setRevertDemo to set revert_address
@@ -136,6 +164,8 @@ async fn test_nested_revert_with_try_catch() -> anyhow::Result<()> {
         transact_to: TxKind::Call(owner_demo_address),
         value: U256::ZERO,
         data: data.into(),
+        trace_config: None,
+        return_abi: None,
     };
 
     // 4. call revert_demo_multi to trigger two calls
@@ -145,6 +175,8 @@ async fn test_nested_revert_with_try_catch() -> anyhow::Result<()> {
         transact_to: TxKind::Call(owner_demo_address),
         value: U256::ZERO,
         data: data.into(),
+        trace_config: None,
+        return_abi: None,
     };
 
     // execute all transactions
@@ -152,7 +184,7 @@ async fn test_nested_revert_with_try_catch() -> anyhow::Result<()> {
         .trace_transactions(SimulationBatch {
             is_stateful: true,
             transactions: vec![tx0, tx1, tx2, tx3],
-            overrides: None,
+            ..Default::default()
         })
         .into_iter()
         .map(|v| v.unwrap())
@@ -258,6 +290,8 @@ async fn test_nested_revert_with_multicall() -> anyhow::Result<()> {
         transact_to: TxKind::Create,
         value: U256::ZERO,
         data: hex::decode(REVERT_DEMO_BYTECODE).unwrap().into(),
+        trace_config: None,
+        return_abi: None,
     };
 
     // 2. deploy OwnerDemo contract
@@ -266,6 +300,8 @@ async fn test_nested_revert_with_multicall() -> anyhow::Result<()> {
         transact_to: TxKind::Create,
         value: U256::ZERO,
         data: hex::decode(OWNER_DEMO_BYTECODE).unwrap().into(),
+        trace_config: None,
+        return_abi: None,
     };
 
     // 3. call setRevertDemo to set revert_address
@@ -278,6 +314,8 @@ async fn test_nested_revert_with_multicall() -> anyhow::Result<()> {
         transact_to: TxKind::Call(owner_demo_address),
         value: U256::ZERO,
         data: data.into(),
+        trace_config: None,
+        return_abi: None,
     };
 
     // 4. call revert_demo to trigger nested call failure
@@ -287,6 +325,8 @@ async fn test_nested_revert_with_multicall() -> anyhow::Result<()> {
         transact_to: TxKind::Call(owner_demo_address),
         value: U256::ZERO,
         data: data.into(),
+        trace_config: None,
+        return_abi: None,
     };
 
     // execute all transactions
@@ -294,7 +334,7 @@ async fn test_nested_revert_with_multicall() -> anyhow::Result<()> {
         .trace_transactions(SimulationBatch {
             is_stateful: true,
             transactions: vec![tx0, tx1, tx2, tx3],
-            overrides: None,
+            ..Default::default()
         })
         .into_iter()
         .map(|v| v.unwrap())
@@ -389,6 +429,8 @@ async fn test_nested_revert_without_multicall() -> anyhow::Result<()> {
         transact_to: TxKind::Create,
         value: U256::ZERO,
         data: hex::decode(REVERT_DEMO_BYTECODE).unwrap().into(),
+        trace_config: None,
+        return_abi: None,
     };
 
     // 2. deploy OwnerDemo contract
@@ -397,6 +439,8 @@ async fn test_nested_revert_without_multicall() -> anyhow::Result<()> {
         transact_to: TxKind::Create,
         value: U256::ZERO,
         data: hex::decode(OWNER_DEMO_BYTECODE).unwrap().into(),
+        trace_config: None,
+        return_abi: None,
     };
 
     // 3. call setRevertDemo to set revert_address
@@ -409,6 +453,8 @@ async fn test_nested_revert_without_multicall() -> anyhow::Result<()> {
         transact_to: TxKind::Call(owner_demo_address),
         value: U256::ZERO,
         data: data.into(),
+        trace_config: None,
+        return_abi: None,
     };
 
     // 4. call revert_demo to trigger nested call failure
@@ -418,6 +464,8 @@ async fn test_nested_revert_without_multicall() -> anyhow::Result<()> {
         transact_to: TxKind::Call(owner_demo_address),
         value: U256::ZERO,
         data: data.into(),
+        trace_config: None,
+        return_abi: None,
     };
 
     // execute all transactions
@@ -425,7 +473,7 @@ async fn test_nested_revert_without_multicall() -> anyhow::Result<()> {
         .trace_transactions(SimulationBatch {
             is_stateful: true,
             transactions: vec![tx0, tx1, tx2, tx3],
-            overrides: None,
+            ..Default::default()
         })
         .into_iter()
         .map(|v| v.unwrap())
@@ -540,6 +588,8 @@ async fn test_multicall_with_error() -> anyhow::Result<()> {
         transact_to: TxKind::Create,
         value: U256::ZERO,
         data: hex::decode(OWNER_DEMO_BYTECODE).unwrap().into(),
+        trace_config: None,
+        return_abi: None,
     };
 
     // 2. non-owner attempt to set owner (will fail)
@@ -552,6 +602,8 @@ async fn test_multicall_with_error() -> anyhow::Result<()> {
         transact_to: TxKind::Call(expected_contract_address),
         value: U256::ZERO,
         data: data.clone().into(),
+        trace_config: None,
+        return_abi: None,
     };
 
     // 3. owner set new owner transaction (will succeed)
@@ -560,6 +612,8 @@ async fn test_multicall_with_error() -> anyhow::Result<()> {
         transact_to: TxKind::Call(expected_contract_address),
         value: U256::ZERO,
         data: data.clone().into(),
+        trace_config: None,
+        return_abi: None,
     };
 
     // execute batch transactions
@@ -567,7 +621,7 @@ async fn test_multicall_with_error() -> anyhow::Result<()> {
         .trace_transactions(SimulationBatch {
             is_stateful: true,
             transactions: vec![tx0, tx1, tx2],
-            overrides: None,
+            ..Default::default()
         })
         .into_iter()
         .map(|v| v.unwrap())
@@ -607,6 +661,247 @@ async fn test_multicall_with_error() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Test diffing a failed attempt against its successful retry
+///
+/// Verifies:
+/// - Matching top frames despite different callers
+/// - Status difference localized to the top frame
+#[tokio::test(flavor = "multi_thread")]
+async fn test_diff_failed_and_retried_set_owner() -> anyhow::Result<()> {
+    let inspector = TxInspector::new();
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, inspector).await?;
+    let nonce = evm.db().basic(SENDER).unwrap().unwrap().nonce;
+    let contract = SENDER.create(nonce);
+
+    let deploy = SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Create,
+        value: U256::ZERO,
+        data: hex::decode(OWNER_DEMO_BYTECODE).unwrap().into(),
+        trace_config: None,
+        return_abi: None,
+    };
+    // The same call, first from a non-owner (fails), then from the owner
+    let set_owner = |caller| SimulationTx {
+        caller,
+        transact_to: TxKind::Call(contract),
+        value: U256::ZERO,
+        data: OwnerDemo::setOwnerCall {
+            _owner: DEAD_ADDRESS,
+        }
+        .abi_encode()
+        .into(),
+        trace_config: None,
+        return_abi: None,
+    };
+    let results = evm
+        .trace_transactions(SimulationBatch {
+            is_stateful: true,
+            transactions: vec![deploy, set_owner(CAFE_ADDRESS), set_owner(SENDER)],
+            ..Default::default()
+        })
+        .into_iter()
+        .map(|v| v.unwrap())
+        .collect::<Vec<_>>();
+
+    let diff = diff_trace_outputs(&results[1].2, &results[2].2).unwrap();
+    assert!(diff.only_in_a.is_empty() && diff.only_in_b.is_empty());
+    assert!(!diff.truncated);
+    assert_eq!(diff.changed.len(), 1, "Only the top frame should differ");
+    let top = &diff.changed[0];
+    assert!(top.path_a.is_empty() && top.path_b.is_empty());
+    assert!(top.changes.iter().any(|change| matches!(
+        change,
+        FrameChange::Status {
+            a: CallStatus::Revert { .. },
+            b: CallStatus::Success
+        }
+    )));
+
+    Ok(())
+}
+
+/// Test expectation checks over the OwnerDemo fixtures
+///
+/// Verifies:
+/// - Met expectations pass
+/// - Deliberately wrong expectations fail with the actual outcome
+#[tokio::test(flavor = "multi_thread")]
+async fn test_run_checked_owner_demo() -> anyhow::Result<()> {
+    let inspector = TxInspector::new();
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, inspector).await?;
+    let nonce = evm.db().basic(SENDER).unwrap().unwrap().nonce;
+    let contract = SENDER.create(nonce);
+
+    let call = |caller, data: Vec<u8>| SimulationTx {
+        caller,
+        transact_to: TxKind::Call(contract),
+        value: U256::ZERO,
+        data: data.into(),
+        trace_config: None,
+        return_abi: None,
+    };
+    let set_owner = OwnerDemo::setOwnerCall {
+        _owner: DEAD_ADDRESS,
+    }
+    .abi_encode();
+    let owner = OwnerDemo::ownerCall {}.abi_encode();
+    let deploy = SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Create,
+        value: U256::ZERO,
+        data: hex::decode(OWNER_DEMO_BYTECODE).unwrap().into(),
+        trace_config: None,
+        return_abi: None,
+    };
+    let reason = "Only the owner can set the owner";
+
+    let report = evm.run_checked(CheckedBatch {
+        items: vec![
+            (deploy, vec![Expectation::Succeeds]),
+            (
+                call(CAFE_ADDRESS, set_owner.clone()),
+                vec![
+                    Expectation::RevertsWith(reason.to_string()),
+                    Expectation::Succeeds,
+                ],
+            ),
+            (call(SENDER, set_owner), vec![Expectation::Succeeds]),
+            (
+                call(SENDER, owner),
+                vec![
+                    Expectation::ReturnsAbi(DynSolValue::Address(DEAD_ADDRESS)),
+                    Expectation::ReturnsAbi(DynSolValue::Address(SENDER)),
+                ],
+            ),
+        ],
+        is_stateful: true,
+    });
+
+    assert_eq!(report.items.len(), 4);
+    assert!(report.items[0].passed());
+    assert!(report.items[2].passed());
+    let failures: Vec<_> = report.failures().collect();
+    assert_eq!(failures.len(), 2, "{failures:?}");
+
+    // The non-owner call reverts as expected, so expecting success fails
+    let non_owner = &report.items[1].results;
+    assert!(non_owner[0].passed);
+    assert!(!non_owner[1].passed);
+    assert_eq!(non_owner[1].message, format!("reverted with {reason:?}"));
+
+    let owner = &report.items[3].results;
+    assert!(owner[0].passed);
+    assert!(!owner[1].passed);
+    assert_eq!(
+        owner[1].message,
+        format!("returned {:?}", DynSolValue::Address(DEAD_ADDRESS))
+    );
+
+    Ok(())
+}
+
+/// Test the batch timeline over the OwnerDemo fixtures
+///
+/// Verifies:
+/// - Sequence numbers follow execution order across transactions
+/// - The owner slot's history spans the deployment and the owner's `setOwner`
+/// - The reverted `setOwner` of a non-owner leaves no write
+#[tokio::test(flavor = "multi_thread")]
+async fn test_timeline_owner_history() -> anyhow::Result<()> {
+    let inspector = TxInspector::new();
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, inspector).await?;
+    let nonce = evm.db().basic(SENDER).unwrap().unwrap().nonce;
+    let contract = SENDER.create(nonce);
+
+    let call = |caller, data: Vec<u8>| SimulationTx {
+        caller,
+        transact_to: TxKind::Call(contract),
+        value: U256::ZERO,
+        data: data.into(),
+        trace_config: None,
+        return_abi: None,
+    };
+    let set_owner = OwnerDemo::setOwnerCall {
+        _owner: DEAD_ADDRESS,
+    }
+    .abi_encode();
+    let deploy = SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Create,
+        value: U256::ZERO,
+        data: hex::decode(OWNER_DEMO_BYTECODE).unwrap().into(),
+        trace_config: None,
+        return_abi: None,
+    };
+    let results = evm.trace_transactions(SimulationBatch {
+        transactions: vec![
+            deploy,
+            call(CAFE_ADDRESS, set_owner.clone()),
+            call(SENDER, set_owner),
+            call(SENDER, OwnerDemo::ownerCall {}.abi_encode()),
+        ],
+        is_stateful: true,
+        build_timeline: true,
+        ..Default::default()
+    });
+    let succeeded: Vec<_> = results
+        .into_iter()
+        .map(|result| result.map(|(result, _, _)| result.is_success()))
+        .collect::<Result<_, _>>()?;
+    assert_eq!(succeeded, vec![true, false, true, true]);
+
+    let timeline = evm.take_last_timeline().expect("timeline was requested");
+    let events = timeline.events();
+    for (position, event) in events.iter().enumerate() {
+        assert_eq!(event.seq, position as u64);
+    }
+    assert!(events
+        .windows(2)
+        .all(|pair| pair[0].tx_index <= pair[1].tx_index));
+    assert!(events.iter().all(|event| event.tx_index != 1));
+
+    // The deployment is the contract's first event, before its constructor sets the owner
+    let first = timeline.by_address(contract).next().unwrap();
+    assert_eq!(first.tx_index, 0);
+    assert!(matches!(
+        first.kind,
+        TimelineEventKind::Creation { creator, created, .. }
+            if creator == SENDER && created == Some(contract)
+    ));
+
+    let word = |address: Address| U256::from_be_slice(address.as_slice());
+    let history: Vec<_> = timeline
+        .by_slot(contract, U256::ZERO)
+        .map(|event| match event.kind {
+            TimelineEventKind::StorageWrite {
+                old_value,
+                new_value,
+                ..
+            } => (event.tx_index, event.reverted, old_value, new_value),
+            _ => unreachable!("by_slot only yields storage writes"),
+        })
+        .collect();
+    assert_eq!(
+        history,
+        vec![
+            (0, false, U256::ZERO, word(SENDER)),
+            (2, false, word(SENDER), word(DEAD_ADDRESS)),
+        ]
+    );
+    let owner_writes: Vec<_> = timeline.by_slot(contract, U256::ZERO).collect();
+    let span = timeline.between(owner_writes[0].seq, owner_writes[1].seq);
+    assert_eq!(span.first(), Some(owner_writes[0]));
+    assert_eq!(span.last(), Some(owner_writes[1]));
+    assert!(first.seq < owner_writes[0].seq);
+
+    let json = serde_json::to_value(&timeline)?;
+    assert_eq!(json.as_array().map(Vec::len), Some(events.len()));
+    assert!(evm.take_last_timeline().is_none());
+
+    Ok(())
+}
+
 /// Test contract creation and deployment
 ///
 /// Verifies:
@@ -632,12 +927,14 @@ async fn test_create_contract() {
         transact_to: TxKind::Create,
         value: U256::ZERO,
         data: data.into(),
+        trace_config: None,
+        return_abi: None,
     };
     let results = evm
         .trace_transactions(SimulationBatch {
             is_stateful: false,
             transactions: vec![tx0],
-            overrides: None,
+            ..Default::default()
         })
         .into_iter()
         .map(|v| v.unwrap())
@@ -655,6 +952,58 @@ async fn test_create_contract() {
     );
 }
 
+/// Test contract deployment through the deployment helpers
+///
+/// Verifies:
+/// - Created address matches the sender's CREATE address
+/// - Deployment is committed, so the constructor-set owner is readable afterwards
+#[tokio::test(flavor = "multi_thread")]
+async fn test_deploy_and_get_address() -> anyhow::Result<()> {
+    let inspector = TxInspector::new();
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, inspector).await?;
+
+    let sender = address!("b20a608c624Ca5003905aA834De7156C68b2E1d0");
+    let nonce = evm
+        .db()
+        .basic(sender)
+        .unwrap()
+        .map(|acc| acc.nonce)
+        .unwrap_or_default();
+    let expected_contract_address = sender.create(nonce);
+
+    let tx = build_deployment_tx(
+        hex::decode(OWNER_DEMO_BYTECODE)?.into(),
+        &[],
+        None,
+        sender,
+        U256::ZERO,
+    )?;
+    let (contract, output) = deploy_and_get_address(&mut evm, tx)?;
+    assert_eq!(
+        contract, expected_contract_address,
+        "Contract address should match"
+    );
+    assert_eq!(output.call_trace.as_ref().unwrap().from, sender);
+
+    let owner_tx = revm::context::TxEnv::builder()
+        .caller(sender)
+        .kind(TxKind::Call(contract))
+        .data(OwnerDemo::ownerCall {}.abi_encode().into())
+        .nonce(nonce + 1)
+        .build_fill();
+    let result = evm
+        .transact(owner_tx)
+        .map_err(|e| anyhow::anyhow!("owner() call failed: {e}"))?
+        .result;
+    let owner = OwnerDemo::ownerCall::abi_decode_returns(result.output().unwrap())?;
+    assert_eq!(
+        owner, sender,
+        "Constructor should set the deployer as owner"
+    );
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_stateful_and_stateless_call_trace() {
     let inspector = TxInspector::new();
@@ -675,19 +1024,23 @@ async fn test_stateful_and_stateless_call_trace() {
         transact_to: TxKind::Create,
         value: U256::ZERO,
         data: data.clone().into(),
+        trace_config: None,
+        return_abi: None,
     };
     let tx1 = SimulationTx {
         caller: sender,
         transact_to: TxKind::Create,
         value: U256::ZERO,
         data: data.into(),
+        trace_config: None,
+        return_abi: None,
     };
 
     let results = evm
         .trace_transactions(SimulationBatch {
             is_stateful: false,
             transactions: vec![tx0.clone(), tx1.clone()],
-            overrides: None,
+            ..Default::default()
         })
         .into_iter()
         .map(|v| v.unwrap())
@@ -717,7 +1070,7 @@ async fn test_stateful_and_stateless_call_trace() {
         .trace_transactions(SimulationBatch {
             is_stateful: true,
             transactions: vec![tx0.clone(), tx1.clone()],
-            overrides: None,
+            ..Default::default()
         })
         .into_iter()
         .map(|v| v.unwrap())
@@ -775,15 +1128,19 @@ async fn test_wth_ws() -> anyhow::Result<()> {
                 transact_to: TxKind::Call(CAFE_ADDRESS),
                 value: transfer1_amount,
                 data: vec![].into(),
+                trace_config: None,
+                return_abi: None,
             },
             SimulationTx {
                 caller: CAFE_ADDRESS,
                 transact_to: TxKind::Call(DEAD_ADDRESS),
                 value: transfer2_amount,
                 data: vec![].into(),
+                trace_config: None,
+                return_abi: None,
             },
         ],
-        overrides: None,
+        ..Default::default()
     };
 
     let results = evm
@@ -832,3 +1189,1018 @@ async fn test_wth_ws() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Test lifecycle tracking for a contract created and destroyed in one transaction
+///
+/// The deployed factory CREATE2-deploys a child (runtime `CALLER SELFDESTRUCT`) from
+/// its constructor and immediately calls it, the classic metamorphic pattern.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_selfdestruct_in_same_tx_creation() -> anyhow::Result<()> {
+    let inspector = TxInspector::new();
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, inspector).await?;
+
+    // Child init code: copy the 2-byte runtime `33ff` and return it
+    let child_init_code = hex::decode("6002600c60003960026000f333ff")?;
+    // Factory init code: CREATE2(child, salt 0), then CALL the child
+    let factory_init_code = hex::decode(concat!(
+        "6d6002600c60003960026000f333ff600052",
+        "6000600e60126000f5",
+        "600060006000600060008",
+        "55af1505000"
+    ))?;
+
+    let sender = address!("b20a608c624Ca5003905aA834De7156C68b2E1d0");
+    let nonce = evm
+        .db()
+        .basic(sender)
+        .unwrap()
+        .map(|acc| acc.nonce)
+        .unwrap_or_default();
+    let factory = sender.create(nonce);
+    let child = factory.create2_from_code(B256::ZERO, &child_init_code);
+
+    let results = evm.trace_transactions(SimulationBatch {
+        is_stateful: false,
+        transactions: vec![SimulationTx {
+            caller: sender,
+            transact_to: TxKind::Create,
+            value: U256::ZERO,
+            data: factory_init_code.into(),
+            trace_config: None,
+            return_abi: None,
+        }],
+        ..Default::default()
+    });
+    let (result, _, output) = results.into_iter().next().unwrap()?;
+    assert!(result.is_success(), "Factory deployment should succeed");
+
+    let changes = &output.contract_changes;
+    assert!(changes.contains(&ContractChange::Created {
+        address: child,
+        code_len: 2
+    }));
+    assert!(changes.contains(&ContractChange::SelfDestructed {
+        contract: child,
+        beneficiary: factory,
+        balance_moved: U256::ZERO,
+        same_tx_creation: true,
+        trace_address: vec![1],
+    }));
+    assert!(changes.contains(&ContractChange::CodeCleared { address: child }));
+    assert!(
+        !changes.contains(&ContractChange::CodeCleared { address: factory }),
+        "Factory itself is not destroyed"
+    );
+
+    Ok(())
+}
+
+/// Test per-transaction trace levels within one stateful batch
+#[tokio::test(flavor = "multi_thread")]
+async fn test_per_transaction_trace_level() -> anyhow::Result<()> {
+    let inspector = TxInspector::new();
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, inspector).await?;
+
+    let sender = address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045");
+    let recipient = address!("000000000000000000000000000000000000dEaD");
+    let transfer = |level: TraceLevel| SimulationTx {
+        caller: sender,
+        transact_to: TxKind::Call(recipient),
+        value: U256::from(10_000_000_000_000_000u64),
+        data: Default::default(),
+        trace_config: Some(level),
+        return_abi: None,
+    };
+    let results = evm
+        .trace_transactions(SimulationBatch {
+            is_stateful: true,
+            transactions: vec![
+                transfer(TraceLevel::Off),
+                transfer(TraceLevel::TransfersOnly),
+                transfer(TraceLevel::Full),
+            ],
+            ..Default::default()
+        })
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(results.len(), 3);
+    assert!(results.iter().all(|(result, ..)| result.is_success()));
+
+    let off = &results[0].2;
+    assert_eq!(off.trace_level, TraceLevel::Off);
+    assert!(off.call_trace.is_none() && off.asset_transfers.is_empty());
+
+    let transfers_only = &results[1].2;
+    assert_eq!(transfers_only.trace_level, TraceLevel::TransfersOnly);
+    assert!(transfers_only.call_trace.is_none());
+    assert_eq!(transfers_only.asset_transfers.len(), 1);
+
+    let full = &results[2].2;
+    assert_eq!(full.trace_level, TraceLevel::Full);
+    assert!(full.call_trace.is_some());
+    assert_eq!(full.asset_transfers.len(), 1);
+
+    Ok(())
+}
+
+/// Test netting of native transfers for a Uniswap V2 ETH -> USDC swap
+///
+/// The router forwards the ETH to WETH, so the raw transfers contain two hops while
+/// the net view shows a single outflow from the user.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_net_native_flows_for_swap() -> anyhow::Result<()> {
+    sol! {
+        function swapExactETHForTokens(
+            uint256 amountOutMin,
+            address[] calldata path,
+            address to,
+            uint256 deadline
+        ) external payable returns (uint256[] memory amounts);
+    }
+
+    let inspector = TxInspector::new();
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, inspector).await?;
+
+    let user = address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045");
+    let router = address!("7a250d5630B4cF539739dF2C5dAcb4c659F2488D");
+    let weth = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    let usdc = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+    let amount = U256::from(100_000_000_000_000_000u64);
+
+    let data = swapExactETHForTokensCall {
+        amountOutMin: U256::ZERO,
+        path: vec![weth, usdc],
+        to: user,
+        deadline: U256::MAX,
+    }
+    .abi_encode();
+    let results = evm.trace_transactions(SimulationBatch {
+        is_stateful: false,
+        transactions: vec![SimulationTx {
+            caller: user,
+            transact_to: TxKind::Call(router),
+            value: amount,
+            data: data.into(),
+            trace_config: None,
+            return_abi: None,
+        }],
+        ..Default::default()
+    });
+    let (result, _, output) = results.into_iter().next().unwrap()?;
+    assert!(result.is_success(), "Swap should succeed");
+
+    let native_hops = output
+        .asset_transfers
+        .iter()
+        .filter(|transfer| transfer.is_native_token())
+        .count();
+    assert!(native_hops >= 2, "Raw view keeps every hop");
+
+    let flows = output.net_native_flows();
+    assert_eq!(flows.len(), 1, "Net view collapses pass-through hops");
+    assert_eq!(flows[0].from, user);
+    assert_eq!(flows[0].to, weth);
+    assert_eq!(flows[0].value, amount);
+
+    Ok(())
+}
+
+/// Filter stored logs to the pair of a Uniswap V2 ETH -> USDC swap
+///
+/// Verifies:
+/// - Only logs emitted by the pair are stored, and the output says so
+/// - Token transfers emitted by WETH and USDC are still tracked
+#[tokio::test(flavor = "multi_thread")]
+async fn test_log_filter_keeps_transfers() -> anyhow::Result<()> {
+    sol! {
+        function swapExactETHForTokens(
+            uint256 amountOutMin,
+            address[] calldata path,
+            address to,
+            uint256 deadline
+        ) external payable returns (uint256[] memory amounts);
+    }
+
+    let user = address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045");
+    let router = address!("7a250d5630B4cF539739dF2C5dAcb4c659F2488D");
+    let pair = address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc");
+    let weth = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    let usdc = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+
+    let inspector = TxInspector::new().with_log_filter(LogFilter {
+        addresses: Some(HashSet::from([pair])),
+        topics0: None,
+    });
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, inspector).await?;
+    let data = swapExactETHForTokensCall {
+        amountOutMin: U256::ZERO,
+        path: vec![weth, usdc],
+        to: user,
+        deadline: U256::MAX,
+    }
+    .abi_encode();
+    let results = evm.trace_transactions(SimulationBatch {
+        is_stateful: false,
+        transactions: vec![SimulationTx {
+            caller: user,
+            transact_to: TxKind::Call(router),
+            value: U256::from(100_000_000_000_000_000u64),
+            data: data.into(),
+            trace_config: None,
+            return_abi: None,
+        }],
+        ..Default::default()
+    });
+    let (result, _, output) = results.into_iter().next().unwrap()?;
+    assert!(result.is_success(), "Swap should succeed");
+
+    assert!(output.logs_filtered);
+    // Sync and Swap
+    assert_eq!(output.logs.len(), 2);
+    assert!(output.logs.iter().all(|log| log.address == pair));
+    for token in [weth, usdc] {
+        assert!(
+            output.asset_transfers.iter().any(|t| t.token == token),
+            "transfers of {token} are still tracked"
+        );
+    }
+
+    Ok(())
+}
+
+/// Test detection of a fee-on-transfer token whose events overstate received amounts
+///
+/// The mock token keeps balances at `slot = holder`, mints 1e24 to the deployer and
+/// burns 1% of every transfer while emitting `Transfer` for the full amount.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_transfer_consistency_fee_on_transfer() -> anyhow::Result<()> {
+    sol! {
+        function transfer(address to, uint256 amount) external returns (bool);
+    }
+
+    let inspector = TxInspector::new();
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, inspector).await?;
+
+    let token_init_code = hex::decode(concat!(
+        "69d3c21bcecceda10000003355608d8060186000396000f3",
+        "60003560e01c806370a0823114610020578063a9059cbb1461002d57600080fd5b",
+        "6004355460005260206000f35b3354602435808210610088578082033355806063",
+        "026064900460043580548201905550600052600435337fddf252ad1be2c89b69c2",
+        "b068fc378daa952ba7f163c4a11628f55a4df523b3ef60206000a3600160005260",
+        "206000f35b600080fd"
+    ))?;
+    let supply = U256::from(10).pow(U256::from(24));
+    let amount = U256::from(1_000_000);
+
+    let sender = address!("b20a608c624Ca5003905aA834De7156C68b2E1d0");
+    let recipient = address!("3000000000000000000000000000000000000003");
+    let nonce = evm
+        .db()
+        .basic(sender)
+        .unwrap()
+        .map(|acc| acc.nonce)
+        .unwrap_or_default();
+    let token = sender.create(nonce);
+
+    let transfer_tx = SimulationTx {
+        caller: sender,
+        transact_to: TxKind::Call(token),
+        value: U256::ZERO,
+        data: transferCall {
+            to: recipient,
+            amount,
+        }
+        .abi_encode()
+        .into(),
+        trace_config: None,
+        return_abi: None,
+    };
+    let results = evm.trace_transactions(SimulationBatch {
+        is_stateful: true,
+        transactions: vec![
+            SimulationTx {
+                caller: sender,
+                transact_to: TxKind::Create,
+                value: U256::ZERO,
+                data: token_init_code.into(),
+                trace_config: None,
+                return_abi: None,
+            },
+            transfer_tx.clone(),
+        ],
+        ..Default::default()
+    });
+    let (result, diff, output) = results.into_iter().nth(1).unwrap()?;
+    assert!(result.is_success(), "Transfer should succeed");
+
+    // The transfer is the last transaction, so current state is its post-state
+    let report = SimulationReport::new(transfer_tx, &result, diff, output).with_balances(vec![
+        BalanceSnapshot {
+            token,
+            holder: sender,
+            balance: supply,
+        },
+        BalanceSnapshot {
+            token,
+            holder: recipient,
+            balance: U256::ZERO,
+        },
+    ]);
+    let inconsistencies = verify_transfer_consistency(&mut evm, &report);
+
+    assert_eq!(inconsistencies.len(), 1, "Only the recipient is affected");
+    let found = &inconsistencies[0];
+    assert_eq!(found.token, token);
+    assert_eq!(found.address, recipient);
+    assert_eq!(found.event_implied_delta, I256::try_from(amount)?);
+    assert_eq!(
+        found.state_delta,
+        I256::try_from(amount * U256::from(99) / U256::from(100))?
+    );
+
+    Ok(())
+}
+
+/// Test coinbase payment tracking with a builder-configured coinbase
+///
+/// The contract forwards `msg.value` to `block.coinbase`, the usual way MEV
+/// searchers pay block builders.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_coinbase_payment() -> anyhow::Result<()> {
+    let builder = address!("95222290DD7278Aa3Ddd389Cc1E1d165CC4BAfe5");
+    let mut evm = EvmBuilder::new_alloy(ETH_RPC_URL)
+        .with_coinbase(builder)
+        .with_tracer(TxInspector::new())
+        .build()
+        .await?;
+
+    // Runtime: coinbase.call{value: msg.value}("")
+    let init_code = hex::decode("600b80600b6000396000f3600080808034415af15000")?;
+    let sender = address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045");
+    let nonce = evm
+        .db()
+        .basic(sender)
+        .unwrap()
+        .map(|acc| acc.nonce)
+        .unwrap_or_default();
+    let payer = sender.create(nonce);
+    let tip = U256::from(10_000_000_000_000_000u64); // 0.01 ETH
+
+    let results = evm.trace_transactions(SimulationBatch {
+        is_stateful: true,
+        transactions: vec![
+            SimulationTx {
+                caller: sender,
+                transact_to: TxKind::Create,
+                value: U256::ZERO,
+                data: init_code.into(),
+                trace_config: None,
+                return_abi: None,
+            },
+            SimulationTx {
+                caller: sender,
+                transact_to: TxKind::Call(payer),
+                value: tip,
+                data: Default::default(),
+                trace_config: None,
+                return_abi: None,
+            },
+        ],
+        ..Default::default()
+    });
+    let (result, _, output) = results.into_iter().nth(1).unwrap()?;
+    assert!(result.is_success(), "Payment should succeed");
+
+    assert_eq!(
+        output.coinbase_payments,
+        vec![CoinbasePayment {
+            from: payer,
+            value: tip,
+            trace_address: vec![0],
+        }]
+    );
+
+    Ok(())
+}
+
+/// Sweep `setOwner` from random callers against a deployed OwnerDemo
+///
+/// Verifies:
+/// - The owner-only revert is the dominant outcome
+/// - Only the owner succeeds, and only its iterations change the owner slot
+/// - The state is restored after every iteration
+/// - Equal seeds reproduce the same report
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fuzz_sweep_set_owner() -> anyhow::Result<()> {
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+
+    let nonce = evm.db().basic(SENDER)?.map(|acc| acc.nonce).unwrap_or(0);
+    let owner_demo_address = SENDER.create(nonce);
+    let results = evm.trace_transactions(SimulationBatch {
+        is_stateful: true,
+        transactions: vec![SimulationTx {
+            caller: SENDER,
+            transact_to: TxKind::Create,
+            value: U256::ZERO,
+            data: hex::decode(OWNER_DEMO_BYTECODE)?.into(),
+            trace_config: None,
+            return_abi: None,
+        }],
+        ..Default::default()
+    });
+    assert!(results[0].as_ref().is_ok_and(|r| r.0.is_success()));
+
+    let mut callers: Vec<Address> = (1..10u8).map(Address::repeat_byte).collect();
+    callers.push(SENDER);
+    let owner_slot = (owner_demo_address, U256::ZERO);
+    let template = TxTemplate {
+        callers,
+        to: owner_demo_address,
+        selector: OwnerDemo::setOwnerCall::SELECTOR.into(),
+        arg_generators: vec![ArgGen::AddressPool(vec![CAFE_ADDRESS, DEAD_ADDRESS])],
+        value: U256::ZERO,
+        watched_slots: vec![owner_slot],
+    };
+    let report = run_sweep(&mut evm, template.clone(), 200, 7);
+
+    assert_eq!(report.iterations, 200);
+    assert_eq!(report.execution_errors, 0);
+    assert_eq!(report.reverts.len(), 1, "Only the owner check can fail");
+    let dominant = &report.reverts[0];
+    assert_eq!(dominant.reason, "Only the owner can set the owner");
+    assert!(dominant.count > report.success_count);
+    assert_eq!(dominant.count + report.success_count, 200);
+    assert_ne!(dominant.example.caller, SENDER);
+
+    assert!(report.success_count > 0);
+    assert_eq!(
+        report.success_example.as_ref().map(|i| i.caller),
+        Some(SENDER)
+    );
+    assert_eq!(report.watched_slot_changes.len(), report.success_count);
+    assert!(report
+        .watched_slot_changes
+        .iter()
+        .all(|change| change.input.caller == SENDER
+            && change.old_value == U256::from_be_slice(SENDER.as_slice())));
+    assert!(report.gas.min <= report.gas.p50 && report.gas.p50 <= report.gas.max);
+
+    // Every iteration was reverted, so the owner is unchanged
+    let owner = evm.db().storage(owner_demo_address, U256::ZERO)?;
+    assert_eq!(owner, U256::from_be_slice(SENDER.as_slice()));
+
+    let again = run_sweep(&mut evm, template, 200, 7);
+    assert_eq!(again.success_count, report.success_count);
+    assert_eq!(again.success_example, report.success_example);
+
+    Ok(())
+}
+
+/// Runtime code calling itself `calldata[0]` more times:
+/// `if n > 0 { address(this).call(abi.encode(n - 1)) }`
+const RECURSIVE_BYTECODE: &str = "6000358015601d576001900360005260006000602060006000305af1505b00";
+const RECURSIVE_ADDRESS: Address = address!("00000000000000000000000000000000000de9e0");
+
+/// Executes a call into the recursive contract nesting `depth` frames below the root
+///
+/// The gas limit is far above the block limit, as each level only passes 63/64 of
+/// its gas on.
+async fn trace_recursion(inspector: TxInspector, depth: u64) -> anyhow::Result<TxTraceOutput> {
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, inspector).await?;
+    let code = Bytecode::new_raw(hex::decode(RECURSIVE_BYTECODE)?.into());
+    evm.db().insert_account_info(
+        RECURSIVE_ADDRESS,
+        AccountInfo {
+            code_hash: code.hash_slow(),
+            code: Some(code),
+            ..Default::default()
+        },
+    );
+
+    let nonce = evm.db().basic(SENDER)?.map(|acc| acc.nonce).unwrap_or(0);
+    let tx = TxEnv::builder()
+        .caller(SENDER)
+        .kind(TxKind::Call(RECURSIVE_ADDRESS))
+        .data(Bytes::from(U256::from(depth).to_be_bytes::<32>().to_vec()))
+        .gas_limit(50_000_000_000)
+        .nonce(nonce)
+        .build_fill();
+    evm.reset_inspector();
+    evm.set_tx(tx);
+    let result = evm
+        .inspect_replay()
+        .map_err(|e| anyhow::anyhow!("Inspector execution failed: {e}"))?;
+    assert!(result.result.is_success(), "{:?}", result.result);
+    Ok(evm.get_inspector_output())
+}
+
+/// Trace a self-recursive contract 1000 calls deep
+///
+/// Verifies:
+/// - Every frame is recorded with its depth
+/// - Analysis and export helpers walk the tree without overflowing the stack
+#[tokio::test(flavor = "multi_thread")]
+async fn test_deep_recursion_trace() -> anyhow::Result<()> {
+    let output = trace_recursion(TxInspector::new(), 1000).await?;
+    let root = output.call_trace.as_ref().expect("call trace");
+
+    let frames: Vec<&CallTrace> = root.frames().collect();
+    assert_eq!(frames.len(), 1001);
+    assert!(frames
+        .iter()
+        .enumerate()
+        .all(|(i, f)| f.depth == i && !f.truncated));
+    assert_eq!(frames[1000].trace_address, vec![0; 1000]);
+
+    assert!(root.all_slot_accesses(SlotAccessType::All).is_empty());
+    assert!(output.net_native_flows().is_empty());
+    assert_eq!(output.to_parity_traces(&TxMeta::default()).len(), 1001);
+    #[cfg(feature = "otel")]
+    {
+        use revm_trace::export::{visit_spans, TxSpanContext};
+
+        let mut spans = 0;
+        visit_spans(&output, &TxSpanContext::default(), |_| spans += 1);
+        assert_eq!(spans, 1001);
+    }
+    assert!(TxInspector::output_size(&output) > 1001 * std::mem::size_of::<CallTrace>());
+
+    // serde recurses once per nesting level, so give it a larger stack
+    let json = std::thread::Builder::new()
+        .stack_size(64 * 1024 * 1024)
+        .spawn(move || serde_json::to_string(&output))?
+        .join()
+        .expect("serialization thread")?;
+    assert!(json.contains("\"depth\":1000"));
+
+    Ok(())
+}
+
+/// Trace the same recursion with a depth limit of 100
+///
+/// Verifies:
+/// - Frames beyond the limit are collapsed into the deepest recorded one
+/// - Collapsing does not affect the execution result
+#[tokio::test(flavor = "multi_thread")]
+async fn test_deep_recursion_truncated() -> anyhow::Result<()> {
+    let output = trace_recursion(TxInspector::new().with_max_trace_depth(100), 1000).await?;
+    let root = output.call_trace.as_ref().expect("call trace");
+
+    let frames: Vec<&CallTrace> = root.frames().collect();
+    assert_eq!(frames.len(), 100);
+    let deepest = frames[99];
+    assert_eq!(deepest.depth, 99);
+    assert!(deepest.truncated && deepest.subtraces.is_empty());
+    assert!(frames[..99].iter().all(|f| !f.truncated));
+    assert!(deepest.status.is_success());
+
+    Ok(())
+}
+
+/// Contract that, called without data, writes slot 0 and calls itself twice: first
+/// with `0x01` (writes 0xaa to slot 2, then reverts), then with `0x02` (writes 0xbb
+/// and then 0xcc to slot 2)
+const FRAME_WRITES_BYTECODE: &str =
+    "0x605680600b6000396000f360003560f81c80600114603f57600214604a576001600055600160005360006000600160006000305af150600260005360006000600160006000305af150005b60aa60025560006000fd5b60bb60025560cc60025500";
+
+/// Attribute storage writes to the frames that made them
+///
+/// Verifies:
+/// - Persisted writes are keyed by trace address and collapsed to first-old/last-new
+/// - Writes of the reverted subcall land in `reverted_writes`
+/// - A later frame sees the slot value from before the reverted write
+#[tokio::test(flavor = "multi_thread")]
+async fn test_frame_diffs() -> anyhow::Result<()> {
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+
+    let nonce = evm.db().basic(SENDER)?.map(|acc| acc.nonce).unwrap_or(0);
+    let contract = SENDER.create(nonce);
+    let results = evm.trace_transactions(SimulationBatch {
+        is_stateful: true,
+        transactions: vec![
+            SimulationTx {
+                caller: SENDER,
+                transact_to: TxKind::Create,
+                value: U256::ZERO,
+                data: hex::decode(FRAME_WRITES_BYTECODE)?.into(),
+                trace_config: None,
+                return_abi: None,
+            },
+            SimulationTx {
+                caller: SENDER,
+                transact_to: TxKind::Call(contract),
+                value: U256::ZERO,
+                data: Bytes::new(),
+                trace_config: None,
+                return_abi: None,
+            },
+        ],
+        ..Default::default()
+    });
+    let results: Vec<_> = results.into_iter().collect::<Result<_, _>>()?;
+    let (result, _, output) = &results[1];
+    assert!(result.is_success());
+
+    let change = |slot: u64, old: u64, new: u64| SlotChange {
+        address: contract,
+        slot: U256::from(slot),
+        old_value: U256::from(old),
+        new_value: U256::from(new),
+    };
+    assert_eq!(output.frame_diffs.len(), 2);
+    assert_eq!(output.frame_diffs[&vec![]], vec![change(0, 0, 1)]);
+    assert_eq!(output.frame_diffs[&vec![1]], vec![change(2, 0, 0xcc)]);
+    assert_eq!(output.reverted_writes.len(), 1);
+    assert_eq!(output.reverted_writes[&vec![0]], vec![change(2, 0, 0xaa)]);
+
+    // Trace addresses are not valid JSON keys; the maps serialize as pairs
+    let json = serde_json::to_value(output)?;
+    assert_eq!(json["reverted_writes"][0][0], serde_json::json!([0]));
+    let restored: TxTraceOutput = serde_json::from_value(json)?;
+    assert_eq!(restored.frame_diffs, output.frame_diffs);
+
+    Ok(())
+}
+
+/// Contract forwarding its calldata to the ecrecover precompile and returning the result
+const ECRECOVER_FORWARDER_BYTECODE: &str =
+    "0x601780600b6000396000f33660006000376020600036600060015afa5060206000f3";
+
+/// Verify a signature through a contract and send value to an unused address
+///
+/// Verifies:
+/// - The internal ecrecover call is labeled as a precompile
+/// - The deployed contract and a fresh address are labeled by code presence
+#[tokio::test(flavor = "multi_thread")]
+async fn test_call_target_kinds() -> anyhow::Result<()> {
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+
+    let nonce = evm.db().basic(SENDER)?.map(|acc| acc.nonce).unwrap_or(0);
+    let verifier = SENDER.create(nonce);
+    // hash, v, r, s signed by 0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b
+    let signature = hex::decode(
+        "18c547e4f7b0f325ad1e56f57e26c745b09a3e503d86e00e5255ff7f715d3d1c\
+         000000000000000000000000000000000000000000000000000000000000001c\
+         73b1693892219d736caba55bdb67216e485557ea6b6af75f37096c9aa6a5a75f\
+         eeb940b1d03b21e36b0e47e79769f095fe2ab855bd91e3a38756b7d75a9c4549",
+    )?;
+    let fresh = Address::repeat_byte(0xfe);
+    let results = evm.trace_transactions(SimulationBatch {
+        is_stateful: true,
+        transactions: vec![
+            SimulationTx {
+                caller: SENDER,
+                transact_to: TxKind::Create,
+                value: U256::ZERO,
+                data: hex::decode(ECRECOVER_FORWARDER_BYTECODE)?.into(),
+                trace_config: None,
+                return_abi: None,
+            },
+            SimulationTx {
+                caller: SENDER,
+                transact_to: TxKind::Call(verifier),
+                value: U256::ZERO,
+                data: signature.into(),
+                trace_config: None,
+                return_abi: None,
+            },
+            SimulationTx {
+                caller: SENDER,
+                transact_to: TxKind::Call(fresh),
+                value: U256::from(1),
+                data: Bytes::new(),
+                trace_config: None,
+                return_abi: None,
+            },
+        ],
+        ..Default::default()
+    });
+    let results: Vec<_> = results.into_iter().collect::<Result<_, _>>()?;
+
+    let (result, _, output) = &results[1];
+    let recovered = result.output().expect("verification output");
+    assert_eq!(
+        Address::from_slice(&recovered[12..]),
+        address!("a94f5374fce5edbc8e2a8697c15331677e6ebf0b")
+    );
+    let root = output.call_trace.as_ref().expect("call trace");
+    assert_eq!(root.target_kind, TargetKind::Contract);
+    let ecrecover = &root.subtraces[0];
+    assert_eq!(ecrecover.call_scheme, Some(CallScheme::StaticCall));
+    assert_eq!(
+        ecrecover.target_kind,
+        TargetKind::Precompile(KnownPrecompile::EcRecover)
+    );
+
+    let (result, _, output) = &results[2];
+    assert!(result.is_success());
+    let root = output.call_trace.as_ref().expect("call trace");
+    assert_eq!(root.target_kind, TargetKind::Eoa);
+
+    Ok(())
+}
+
+/// Add an access list to every transaction through the tx hook
+///
+/// Verifies:
+/// - The hook runs once per simulated transaction, before execution
+/// - Execution still succeeds, and intrinsic gas grows by the access list cost
+///   (2400 per address plus 1900 per storage key)
+/// - Cleared hooks no longer run
+#[tokio::test(flavor = "multi_thread")]
+async fn test_tx_hook_access_list() -> anyhow::Result<()> {
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+    let batch = || SimulationBatch {
+        is_stateful: false,
+        transactions: vec![SimulationTx {
+            caller: SENDER,
+            transact_to: TxKind::Call(Address::repeat_byte(0x11)),
+            value: U256::ZERO,
+            data: Bytes::new(),
+            trace_config: None,
+            return_abi: None,
+        }],
+        ..Default::default()
+    };
+    let gas_used = |evm: &mut InspectorEvm<TxInspector>| -> anyhow::Result<u64> {
+        let mut results = evm.trace_transactions(batch());
+        let (result, _, _) = results.remove(0)?;
+        assert!(result.is_success());
+        Ok(result.gas_used())
+    };
+
+    let plain = gas_used(&mut evm)?;
+    assert_eq!(plain, 21_000);
+
+    let calls = Rc::new(Cell::new(0));
+    let seen = calls.clone();
+    evm.set_tx_hook(Box::new(move |tx, input| {
+        assert_eq!(tx.caller, input.caller);
+        seen.set(seen.get() + 1);
+        tx.tx_type = 1;
+        tx.access_list = AccessList(vec![AccessListItem {
+            address: Address::repeat_byte(0x22),
+            storage_keys: vec![B256::ZERO],
+        }]);
+    }));
+    assert_eq!(gas_used(&mut evm)?, plain + 2_400 + 1_900);
+    assert_eq!(calls.get(), 1);
+
+    evm.clear_tx_hook();
+    assert_eq!(gas_used(&mut evm)?, plain);
+    assert_eq!(calls.get(), 1);
+    Ok(())
+}
+
+/// Merge the proxy forward of a USDC transfer into one logical call
+///
+/// Verifies:
+/// - The raw trace has the proxy frame and its delegatecall into the implementation
+/// - The logical view has a single frame naming both, with the transfer selector
+/// - Annotating the view reports the implementation from the EIP-1967 slot
+#[tokio::test(flavor = "multi_thread")]
+async fn test_logical_view_of_proxied_transfer() -> anyhow::Result<()> {
+    sol! {
+        function transfer(address to, uint256 amount) external returns (bool);
+    }
+
+    let usdc = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+    let holder = address!("28C6c06298d514Db089934071355E5743bf21d60");
+
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+    let implementation = get_implementation(&mut evm, usdc)?.expect("USDC is a proxy");
+    let data = transferCall {
+        to: SENDER,
+        amount: U256::from(1_000_000),
+    }
+    .abi_encode();
+    let results = evm.trace_transactions(SimulationBatch {
+        is_stateful: false,
+        transactions: vec![SimulationTx {
+            caller: holder,
+            transact_to: TxKind::Call(usdc),
+            value: U256::ZERO,
+            data: data.into(),
+            trace_config: None,
+            return_abi: None,
+        }],
+        ..Default::default()
+    });
+    let (result, _, output) = results.into_iter().next().unwrap()?;
+    assert!(result.is_success(), "Transfer should succeed");
+
+    let raw = output.call_trace.as_ref().expect("call trace");
+    assert_eq!(raw.subtraces.len(), 1);
+    assert_eq!(raw.subtraces[0].call_scheme, Some(CallScheme::DelegateCall));
+
+    let mut logical = output.logical_view().expect("call trace");
+    assert_eq!(logical.proxy, Some(usdc));
+    assert_eq!(logical.implementation, implementation);
+    assert_eq!(logical.storage_address, usdc);
+    assert_eq!(
+        logical.function_selector,
+        Some(transferCall::SELECTOR.into())
+    );
+    assert_eq!(logical.merged_frames, vec![vec![0]]);
+    assert!(logical.subtraces.is_empty());
+
+    annotate_implementations(&mut evm, &mut logical)?;
+    assert_eq!(logical.active_implementation, Some(implementation));
+    Ok(())
+}
+
+/// Test labeling the owner change of `setOwner` with the OwnerDemo storage layout
+#[cfg(feature = "storage-layout")]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_label_owner_change_with_layout() -> anyhow::Result<()> {
+    use revm_trace::layout::{LabelStorage, StorageLayout};
+    use std::collections::HashMap;
+
+    // `forge inspect OwnerDemo storage-layout --json`
+    const OWNER_DEMO_LAYOUT: &str = r#"{
+        "storage": [
+            {"astId": 3, "contract": "OwnerDemo.sol:OwnerDemo", "label": "owner", "offset": 0, "slot": "0", "type": "t_address"},
+            {"astId": 5, "contract": "OwnerDemo.sol:OwnerDemo", "label": "revert_address", "offset": 0, "slot": "1", "type": "t_address"}
+        ],
+        "types": {
+            "t_address": {"encoding": "inplace", "label": "address", "numberOfBytes": "20"}
+        }
+    }"#;
+
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+    let nonce = evm.db().basic(SENDER).unwrap().unwrap().nonce;
+    let owner_demo = SENDER.create(nonce);
+
+    let deploy = SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Create,
+        value: U256::ZERO,
+        data: hex::decode(OWNER_DEMO_BYTECODE)?.into(),
+        trace_config: None,
+        return_abi: None,
+    };
+    let set_owner = SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Call(owner_demo),
+        value: U256::ZERO,
+        data: OwnerDemo::setOwnerCall {
+            _owner: DEAD_ADDRESS,
+        }
+        .abi_encode()
+        .into(),
+        trace_config: None,
+        return_abi: None,
+    };
+    let results = evm
+        .trace_transactions(SimulationBatch {
+            is_stateful: true,
+            transactions: vec![deploy, set_owner],
+            ..Default::default()
+        })
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+    assert!(results[1].0.is_success(), "setOwner should succeed");
+
+    let layouts = HashMap::from([(
+        owner_demo,
+        StorageLayout::from_solc_json(OWNER_DEMO_LAYOUT)?,
+    )]);
+    let labeled = results[1].1.label_with(&layouts);
+    let write = labeled[&owner_demo]
+        .iter()
+        .find(|slot| slot.is_write)
+        .expect("owner is written");
+    assert_eq!(
+        write.to_string(),
+        format!("owner (slot 0): {SENDER} → {DEAD_ADDRESS}")
+    );
+    Ok(())
+}
+
+/// Enforce the block gas limit under strict validation
+///
+/// Verifies:
+/// - A strict EVM takes gas limit and base fee from the header
+/// - A transaction above the block gas limit is rejected with a typed error
+/// - The same transaction runs on a default (relaxed) EVM
+#[tokio::test(flavor = "multi_thread")]
+async fn test_strict_validation_enforces_block_gas_limit() -> anyhow::Result<()> {
+    let batch = || SimulationBatch {
+        is_stateful: false,
+        transactions: vec![SimulationTx {
+            caller: SENDER,
+            transact_to: TxKind::Call(DEAD_ADDRESS),
+            value: U256::ZERO,
+            data: Bytes::new(),
+            trace_config: None,
+            return_abi: None,
+        }],
+        ..Default::default()
+    };
+
+    let mut strict = EvmBuilder::new_alloy(ETH_RPC_URL)
+        .with_tracer(TxInspector::new())
+        .with_strict_validation(true)
+        .build()
+        .await?;
+    let block_gas_limit = strict.block.gas_limit;
+    let basefee = strict.block.basefee;
+    assert!(block_gas_limit > 0);
+    assert!(basefee > 0, "the base fee comes from the header");
+
+    // Pay the base fee so the gas limit is the first check to fail
+    strict.set_tx_hook(Box::new(move |tx, _| {
+        tx.gas_limit = block_gas_limit + 1;
+        tx.gas_price = basefee as u128;
+    }));
+    let mut results = strict.trace_transactions(batch());
+    assert!(matches!(
+        results.remove(0),
+        Err(EvmError::Runtime(RuntimeError::InvalidTransaction(
+            InvalidTransaction::CallerGasLimitMoreThanBlock
+        )))
+    ));
+
+    let mut relaxed = EvmBuilder::new_alloy(ETH_RPC_URL)
+        .with_block_number(strict.block.number)
+        .with_tracer(TxInspector::new())
+        .build()
+        .await?;
+    relaxed.set_tx_hook(Box::new(move |tx, _| tx.gas_limit = block_gas_limit + 1));
+    let (result, _, _) = relaxed.trace_transactions(batch()).remove(0)?;
+    assert!(result.is_success());
+    Ok(())
+}
+
+/// Test the per-address rollup of the try-catch fixture batch
+///
+/// Verifies:
+/// - Deployments and calls count towards the deployed contract
+/// - Subcalls count towards their caller
+/// - Failed frames are attributed to the contract that ran them
+#[tokio::test(flavor = "multi_thread")]
+async fn test_address_activity_of_owner_demo() -> anyhow::Result<()> {
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+    let nonce = evm.db().basic(SENDER).unwrap().unwrap().nonce;
+    let revert_demo_address = SENDER.create(nonce);
+    let owner_demo_address = SENDER.create(nonce + 1);
+
+    let tx = |transact_to, data: Vec<u8>| SimulationTx {
+        caller: SENDER,
+        transact_to,
+        value: U256::ZERO,
+        data: data.into(),
+        trace_config: None,
+        return_abi: None,
+    };
+    let transactions = vec![
+        tx(TxKind::Create, hex::decode(REVERT_DEMO_BYTECODE)?),
+        tx(TxKind::Create, hex::decode(OWNER_DEMO_BYTECODE)?),
+        tx(
+            TxKind::Call(owner_demo_address),
+            OwnerDemo::setRevertDemoCall {
+                _revert_address: revert_demo_address,
+            }
+            .abi_encode(),
+        ),
+        tx(
+            TxKind::Call(owner_demo_address),
+            OwnerDemo::revert_demo_multiCall {}.abi_encode(),
+        ),
+    ];
+    let results = evm.trace_transactions(SimulationBatch {
+        is_stateful: true,
+        transactions: transactions.clone(),
+        ..Default::default()
+    });
+    let reports = transactions
+        .into_iter()
+        .zip(results)
+        .map(|(tx, result)| {
+            let (result, diff, output) = result?;
+            Ok(SimulationReport::new(tx, &result, diff, output))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let activity = address_activity(&reports);
+    let owner_demo = activity
+        .iter()
+        .find(|a| a.address == owner_demo_address)
+        .unwrap();
+    // Deployment, setRevertDemo and revert_demo_multi
+    assert_eq!(owner_demo.call_count, 3);
+    // The two calls into RevertDemo
+    assert_eq!(owner_demo.as_caller_count, 2);
+    // revert_demo_multi reverts
+    assert_eq!(owner_demo.failed_frames, 1);
+    assert!(owner_demo.gas_consumed > 0);
+    assert_eq!(owner_demo.native_net, I256::ZERO);
+
+    let sender = activity.iter().find(|a| a.address == SENDER).unwrap();
+    assert_eq!((sender.call_count, sender.as_caller_count), (0, 4));
+    assert!(activity
+        .windows(2)
+        .all(|pair| pair[0].gas_consumed >= pair[1].gas_consumed));
+
+    Ok(())
+}
@@ -0,0 +1,193 @@
+//! Span hierarchy emitted with the `tracing-logs` feature
+//!
+//! State is served by an in-memory async database behind `MyWrapDatabaseAsync`, so
+//! lookups go through the instrumented wrapper without RPC access.
+
+#![cfg(feature = "tracing-logs")]
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fmt,
+    future::{ready, Future},
+    sync::{Arc, Mutex},
+};
+
+use alloy::primitives::{address, Address, StorageKey, StorageValue, TxKind, B256, U256};
+use revm::{
+    database::CacheDB,
+    database_interface::async_db::DatabaseAsyncRef,
+    state::{AccountInfo, Bytecode},
+    Context, MainBuilder, MainContext,
+};
+use revm_trace::{
+    traits::TransactionTrace, types::SimulationBatch, MyWrapDatabaseAsync, SimulationTx, TraceEvm,
+    TxInspector,
+};
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Subscriber,
+};
+use tracing_subscriber::{
+    layer::{Context as LayerContext, SubscriberExt},
+    registry::LookupSpan,
+    Layer,
+};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const BOB: Address = address!("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+
+/// Async state where every account holds 1 ETH and nothing else
+struct FundedAccounts;
+
+impl DatabaseAsyncRef for FundedAccounts {
+    type Error = Infallible;
+
+    fn basic_async_ref(
+        &self,
+        _address: Address,
+    ) -> impl Future<Output = Result<Option<AccountInfo>, Self::Error>> + Send {
+        ready(Ok(Some(AccountInfo {
+            balance: U256::from(10).pow(U256::from(18)),
+            ..Default::default()
+        })))
+    }
+
+    fn code_by_hash_async_ref(
+        &self,
+        _code_hash: B256,
+    ) -> impl Future<Output = Result<Bytecode, Self::Error>> + Send {
+        ready(Ok(Bytecode::default()))
+    }
+
+    fn storage_async_ref(
+        &self,
+        _address: Address,
+        _index: StorageKey,
+    ) -> impl Future<Output = Result<StorageValue, Self::Error>> + Send {
+        ready(Ok(StorageValue::ZERO))
+    }
+
+    fn block_hash_async_ref(
+        &self,
+        _number: u64,
+    ) -> impl Future<Output = Result<B256, Self::Error>> + Send {
+        ready(Ok(B256::ZERO))
+    }
+}
+
+/// A span as seen by the subscriber
+#[derive(Debug, Default)]
+struct SpanRecord {
+    name: &'static str,
+    parent: Option<&'static str>,
+    fields: HashMap<&'static str, String>,
+}
+
+/// Layer recording every span with its parent and final field values
+#[derive(Clone, Default)]
+struct Capture {
+    spans: Arc<Mutex<Vec<SpanRecord>>>,
+    indices: Arc<Mutex<HashMap<u64, usize>>>,
+}
+
+struct FieldVisitor<'a>(&'a mut HashMap<&'static str, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name(), format!("{value:?}"));
+    }
+}
+
+impl<S> Layer<S> for Capture
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: LayerContext<'_, S>) {
+        let mut record = SpanRecord {
+            name: attrs.metadata().name(),
+            parent: ctx
+                .span(id)
+                .and_then(|span| span.parent())
+                .map(|parent| parent.name()),
+            ..Default::default()
+        };
+        attrs.record(&mut FieldVisitor(&mut record.fields));
+        let mut spans = self.spans.lock().unwrap();
+        self.indices
+            .lock()
+            .unwrap()
+            .insert(id.into_u64(), spans.len());
+        spans.push(record);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, _ctx: LayerContext<'_, S>) {
+        let index = self.indices.lock().unwrap()[&id.into_u64()];
+        values.record(&mut FieldVisitor(
+            &mut self.spans.lock().unwrap()[index].fields,
+        ));
+    }
+}
+
+#[test]
+fn test_transaction_spans() {
+    let capture = Capture::default();
+    let subscriber = tracing_subscriber::registry().with(capture.clone());
+
+    tracing::subscriber::with_default(subscriber, || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let db = MyWrapDatabaseAsync::with_runtime(FundedAccounts, runtime);
+        let mut ctx = Context::mainnet().with_db(CacheDB::new(db));
+        ctx.cfg.disable_base_fee = true;
+        let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(TxInspector::new()));
+
+        let results = evm.trace_transactions(SimulationBatch {
+            transactions: vec![SimulationTx {
+                caller: SENDER,
+                transact_to: TxKind::Call(BOB),
+                value: U256::from(1),
+                data: Default::default(),
+                trace_config: None,
+                return_abi: None,
+            }],
+            is_stateful: false,
+            ..Default::default()
+        });
+        assert!(results[0].as_ref().unwrap().0.is_success());
+    });
+
+    let spans = capture.spans.lock().unwrap();
+    let named = |name| spans.iter().filter(move |span| span.name == name);
+
+    let batch: Vec<_> = named("trace_transactions").collect();
+    assert_eq!(batch.len(), 1);
+    assert_eq!(batch[0].parent, None);
+    assert_eq!(batch[0].fields["batch_size"], "1");
+    assert_eq!(batch[0].fields["is_stateful"], "false");
+
+    let txs: Vec<_> = named("tx").collect();
+    assert_eq!(txs.len(), 1);
+    assert_eq!(txs[0].parent, Some("trace_transactions"));
+    assert_eq!(txs[0].fields["caller"], SENDER.to_string());
+    assert_eq!(txs[0].fields["kind"], "call");
+    assert_eq!(txs[0].fields["gas_used"], "21000");
+    assert_eq!(txs[0].fields["status"], "success");
+
+    // Sender and recipient are fetched from the wrapped database during execution
+    let fetches: Vec<_> = named("db_fetch").collect();
+    assert!(fetches.iter().all(|span| span.parent == Some("tx")));
+    assert!(fetches
+        .iter()
+        .all(|span| span.fields.contains_key("duration_us")));
+    for account in [SENDER, BOB] {
+        assert!(fetches.iter().any(|span| span.fields["kind"] == "basic"
+            && span.fields.get("address") == Some(&account.to_string())));
+    }
+}
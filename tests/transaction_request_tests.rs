@@ -0,0 +1,180 @@
+//! Transaction requests built from simulation reports
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use alloy::{
+    eips::eip2930::{AccessList, AccessListItem},
+    primitives::{address, hex, Address, Bytes, TxKind, B256, U256},
+};
+use revm::state::AccountInfo;
+use revm_trace::{
+    errors::TransactionRequestError,
+    report::{FeeStrategy, SimulationReport},
+    traits::TransactionTrace,
+    types::SimulationBatch,
+    SimulationTx, TxInspector,
+};
+
+mod common;
+use common::{contract, ether, funded, TestEvm};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const BOB: Address = address!("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+const REVERTER: Address = address!("dddddddddddddddddddddddddddddddddddddddd");
+
+/// Nonce of SENDER in the backend
+const NONCE: u64 = 5;
+
+/// Base fee of the simulated block
+const BASE_FEE: u64 = 7;
+
+/// Reverts without data
+const REVERT_CODE: &str = "60006000fd";
+
+fn evm() -> TestEvm {
+    common::evm_with(
+        common::backend([
+            (
+                SENDER,
+                AccountInfo {
+                    nonce: NONCE,
+                    ..funded(ether())
+                },
+            ),
+            (REVERTER, contract(REVERT_CODE)),
+        ]),
+        TxInspector::new(),
+        |ctx| ctx.block.basefee = BASE_FEE,
+    )
+}
+
+fn tx(kind: TxKind, data: &[u8]) -> SimulationTx {
+    SimulationTx {
+        caller: SENDER,
+        transact_to: kind,
+        value: U256::from(1_000),
+        data: Bytes::copy_from_slice(data),
+        trace_config: None,
+        return_abi: None,
+    }
+}
+
+/// Simulates `tx` and returns its report with the executed environment attached
+fn simulate(evm: &mut TestEvm, tx: SimulationTx) -> SimulationReport {
+    let mut results = evm.trace_transactions(SimulationBatch {
+        transactions: vec![tx.clone()],
+        is_stateful: false,
+        ..Default::default()
+    });
+    let (result, diff, output) = results.remove(0).expect("transaction executes");
+    let executed = evm.take_last_executed_txs().remove(0).unwrap();
+    SimulationReport::new(tx, &result, diff, output).with_executed_tx(executed)
+}
+
+fn access_list() -> AccessList {
+    AccessList(vec![AccessListItem {
+        address: BOB,
+        storage_keys: vec![B256::ZERO],
+    }])
+}
+
+#[test]
+fn test_call_request() {
+    let mut evm = evm();
+    evm.set_tx_hook(Box::new(|tx, _| {
+        tx.tx_type = 1;
+        tx.access_list = access_list();
+    }));
+    let report = simulate(&mut evm, tx(TxKind::Call(BOB), b"hello"));
+    assert!(report.summary.success);
+    let gas_spent = report.summary.gas_spent;
+
+    let request = report
+        .to_transaction_request(FeeStrategy::UseBlockBaseFee { tip: 2 }, 10, false)
+        .unwrap();
+    assert_eq!(request.from, Some(SENDER));
+    assert_eq!(request.to, Some(TxKind::Call(BOB)));
+    assert_eq!(request.value, Some(U256::from(1_000)));
+    assert_eq!(request.input.input(), Some(&Bytes::from_static(b"hello")));
+    assert_eq!(request.nonce, Some(NONCE));
+    assert_eq!(request.chain_id, Some(evm.cfg.chain_id));
+    assert_eq!(request.gas, Some((gas_spent * 110).div_ceil(100)));
+    assert_eq!(request.max_fee_per_gas, Some(2 * u128::from(BASE_FEE) + 2));
+    assert_eq!(request.max_priority_fee_per_gas, Some(2));
+    assert_eq!(request.gas_price, None);
+    assert_eq!(request.transaction_type, Some(2));
+    assert_eq!(request.access_list, Some(access_list()));
+
+    // Access lists turn legacy requests into EIP-2930 ones
+    let request = report
+        .to_transaction_request(FeeStrategy::Legacy { gas_price: 9 }, 0, false)
+        .unwrap();
+    assert_eq!(request.gas, Some(gas_spent));
+    assert_eq!(request.gas_price, Some(9));
+    assert_eq!(request.transaction_type, Some(1));
+}
+
+#[test]
+fn test_create_request() {
+    let mut evm = evm();
+    // Returns empty code
+    let report = simulate(&mut evm, tx(TxKind::Create, &hex!("60006000f3")));
+    assert!(report.summary.success);
+
+    let request = report
+        .to_transaction_request(FeeStrategy::Legacy { gas_price: 3 }, 25, false)
+        .unwrap();
+    assert_eq!(request.to, Some(TxKind::Create));
+    assert_eq!(request.nonce, Some(NONCE));
+    assert_eq!(request.gas_price, Some(3));
+    assert_eq!(request.max_fee_per_gas, None);
+    assert_eq!(request.transaction_type, Some(0));
+    assert_eq!(request.access_list, None);
+    assert_eq!(
+        request.gas,
+        Some((report.summary.gas_spent * 125).div_ceil(100))
+    );
+}
+
+#[test]
+fn test_gas_margin_rounds_up() {
+    let mut evm = evm();
+    let mut report = simulate(&mut evm, tx(TxKind::Call(BOB), b""));
+    report.summary.gas_used = 20_000;
+    report.summary.gas_spent = 21_001;
+    let fees = FeeStrategy::Fixed { max_fee: 5, tip: 1 };
+
+    let request = report.to_transaction_request(fees, 10, false).unwrap();
+    // 21_001 * 1.1 = 23_101.1, from the gas spent before refunds
+    assert_eq!(request.gas, Some(23_102));
+    assert_eq!(request.max_fee_per_gas, Some(5));
+    assert_eq!(request.max_priority_fee_per_gas, Some(1));
+    let request = report.to_transaction_request(fees, 255, false).unwrap();
+    assert_eq!(request.gas, Some(74_554));
+}
+
+#[test]
+fn test_refused_requests() {
+    let mut evm = evm();
+    let report = simulate(&mut evm, tx(TxKind::Call(REVERTER), b""));
+    assert!(!report.summary.success);
+    let fees = FeeStrategy::Legacy { gas_price: 1 };
+    assert!(matches!(
+        report.to_transaction_request(fees, 10, false),
+        Err(TransactionRequestError::FailedSimulation { .. })
+    ));
+    assert!(report.to_transaction_request(fees, 10, true).is_ok());
+
+    let mut report = simulate(&mut evm, tx(TxKind::Call(BOB), b"hello"));
+    report.tx.data = Bytes::from_static(b"other");
+    assert_eq!(
+        report.to_transaction_request(fees, 10, false),
+        Err(TransactionRequestError::CalldataMismatch)
+    );
+    report.executed_tx = None;
+    assert_eq!(
+        report.to_transaction_request(fees, 10, false),
+        Err(TransactionRequestError::MissingExecutedTx)
+    );
+}
@@ -0,0 +1,107 @@
+//! Attribution of `tx.origin` reads to call frames
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use alloy::primitives::{address, hex, Address, TxKind, U256};
+use revm::{
+    database::{CacheDB, EmptyDB},
+    state::{AccountInfo, Bytecode},
+    Context, MainBuilder, MainContext,
+};
+use revm_trace::{
+    analysis::{risk_assessment, FindingRef, RiskKind},
+    report::SimulationReport,
+    traits::TransactionTrace,
+    types::SimulationBatch,
+    SimulationTx, TraceEvm, TxInspector,
+};
+
+const OWNER: Address = address!("1000000000000000000000000000000000000001");
+const GUARD: Address = address!("2222222222222222222222222222222222222222");
+const PHISH: Address = address!("3333333333333333333333333333333333333333");
+
+/// `require(tx.origin == owner)` with the owner in slot 0, then emits an ERC20
+/// `Transfer(tx.origin, msg.sender, 1)`
+const GUARD_CODE: &str = concat!(
+    "32600054",
+    "14600c57",
+    "600080fd5b",
+    "6001600052",
+    "33327f",
+    "ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+    "60206000a300",
+);
+
+/// Calls GUARD without data
+const PHISH_CODE: &str = "600060006000600060007322222222222222222222222222222222222222225af100";
+
+fn install(backend: &mut CacheDB<EmptyDB>, address: Address, code: &str) {
+    let code = Bytecode::new_raw(hex::decode(code).unwrap().into());
+    backend.insert_account_info(
+        address,
+        AccountInfo {
+            code_hash: code.hash_slow(),
+            code: Some(code),
+            ..Default::default()
+        },
+    );
+}
+
+/// OWNER calls PHISH, which calls GUARD
+fn report() -> SimulationReport {
+    let mut backend = CacheDB::new(EmptyDB::default());
+    install(&mut backend, GUARD, GUARD_CODE);
+    install(&mut backend, PHISH, PHISH_CODE);
+    backend
+        .insert_account_storage(GUARD, U256::ZERO, U256::from_be_bytes(OWNER.into_word().0))
+        .unwrap();
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(backend));
+    ctx.cfg.disable_base_fee = true;
+    let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(TxInspector::new()));
+
+    let tx = SimulationTx {
+        caller: OWNER,
+        transact_to: TxKind::Call(PHISH),
+        value: U256::ZERO,
+        data: Default::default(),
+        trace_config: None,
+        return_abi: None,
+    };
+    let mut results = evm.trace_transactions(SimulationBatch {
+        transactions: vec![tx.clone()],
+        is_stateful: false,
+        ..Default::default()
+    });
+    let (result, diff, output) = results.remove(0).expect("transaction executes");
+    assert!(result.is_success(), "{result:?}");
+    SimulationReport::new(tx, &result, diff, output)
+}
+
+#[test]
+fn test_origin_reads_attributed_to_frame() {
+    let report = report();
+    let root = report.trace.call_trace.as_ref().unwrap();
+    assert!(!root.used_tx_origin);
+    assert_eq!(root.tx_origin_reads, 0);
+
+    let guard = &root.subtraces[0];
+    assert_eq!(guard.to, GUARD);
+    assert!(guard.used_tx_origin);
+    // The check, then the event topic
+    assert_eq!(guard.tx_origin_reads, 2);
+    assert_eq!(report.trace.tx_origin_usages, vec![vec![0]]);
+}
+
+#[test]
+fn test_origin_authorized_transfer_is_flagged() {
+    let findings: Vec<_> = risk_assessment(&report(), OWNER)
+        .into_iter()
+        .filter(|finding| finding.kind == RiskKind::TxOriginAuthorization)
+        .collect();
+    assert_eq!(findings.len(), 1);
+    assert_eq!(
+        findings[0].refs,
+        vec![FindingRef::TraceAddress(vec![0]), FindingRef::LogIndex(0)]
+    );
+}
@@ -0,0 +1,110 @@
+//! Native value retained by intermediate contracts
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use alloy::primitives::{address, Address, Bytes, TxKind, I256, U256};
+use revm::{
+    database::{CacheDB, EmptyDB},
+    state::{AccountInfo, Bytecode},
+    Context, MainBuilder, MainContext,
+};
+use revm_trace::{
+    analysis::{stuck_funds, value_flow},
+    report::{BalanceSnapshot, SimulationReport},
+    traits::TransactionTrace,
+    types::{SimulationBatch, NATIVE_TOKEN_ADDRESS},
+    utils::balance_utils::query_balance,
+    SimulationTx, TraceEvm, TxInspector,
+};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const ROUTER: Address = address!("c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0");
+const BOB: Address = address!("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+
+/// Forwards 90% of the received value to `BOB` and keeps the rest:
+/// `CALL(GAS, BOB, CALLVALUE * 9 / 10, 0, 0, 0, 0)`
+fn router_code() -> Bytecode {
+    let mut code = vec![
+        0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, // return and argument memory
+        0x60, 0x0a, 0x60, 0x09, 0x34, 0x02, 0x04, // CALLVALUE * 9 / 10
+        0x73, // PUSH20 BOB
+    ];
+    code.extend_from_slice(BOB.as_slice());
+    code.extend_from_slice(&[0x5a, 0xf1, 0x50, 0x00]); // GAS CALL POP STOP
+    Bytecode::new_raw(Bytes::from(code))
+}
+
+#[test]
+fn test_router_retaining_a_tenth_is_reported() {
+    let mut backend = CacheDB::new(EmptyDB::default());
+    backend.insert_account_info(
+        SENDER,
+        AccountInfo {
+            balance: U256::from(10).pow(U256::from(18)),
+            ..Default::default()
+        },
+    );
+    let code = router_code();
+    backend.insert_account_info(
+        ROUTER,
+        AccountInfo {
+            code_hash: code.hash_slow(),
+            code: Some(code),
+            ..Default::default()
+        },
+    );
+    let mut ctx = Context::mainnet().with_db(CacheDB::new(backend));
+    ctx.cfg.disable_base_fee = true;
+    let mut evm = TraceEvm::new(ctx.build_mainnet_with_inspector(TxInspector::new()));
+
+    let tx = SimulationTx {
+        caller: SENDER,
+        transact_to: TxKind::Call(ROUTER),
+        value: U256::from(1_000),
+        data: Default::default(),
+        trace_config: None,
+        return_abi: None,
+    };
+    let mut results = evm.trace_transactions(SimulationBatch {
+        transactions: vec![tx.clone()],
+        is_stateful: true,
+        ..Default::default()
+    });
+    let (result, diff, output) = results.remove(0).unwrap();
+    assert!(result.is_success());
+
+    // The batch was stateful, so current state is the transaction's post-state
+    let snapshot = |holder, balance| BalanceSnapshot {
+        token: NATIVE_TOKEN_ADDRESS,
+        holder,
+        balance,
+    };
+    let report = SimulationReport::new(tx, &result, diff, output)
+        .with_balances(vec![
+            snapshot(ROUTER, U256::ZERO),
+            snapshot(BOB, U256::ZERO),
+        ])
+        .with_balances_after(vec![
+            snapshot(ROUTER, query_balance(&mut evm, ROUTER).unwrap()),
+            snapshot(BOB, query_balance(&mut evm, BOB).unwrap()),
+        ]);
+
+    let holdings = value_flow(&report);
+    assert_eq!(holdings.len(), 2, "the sender is left out");
+    assert_eq!(holdings[0].address, BOB);
+    assert_eq!(holdings[0].net_gain, I256::try_from(900i64).unwrap());
+    assert!(!holdings[0].is_contract);
+
+    let router = &holdings[1];
+    assert_eq!(router.address, ROUTER);
+    assert_eq!(router.incoming, U256::from(1_000));
+    assert_eq!(router.outgoing, U256::from(900));
+    assert_eq!(router.net_gain, I256::try_from(100i64).unwrap());
+    assert!(router.is_contract);
+    assert!(holdings.iter().all(|holding| !holding.has_discrepancy()));
+
+    let stuck = stuck_funds(&report, U256::from(100));
+    assert_eq!(stuck, vec![router.clone()]);
+    assert!(stuck_funds(&report, U256::from(101)).is_empty());
+}
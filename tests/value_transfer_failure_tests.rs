@@ -0,0 +1,124 @@
+//! Classification of failed native value transfers
+//!
+//! Runs on an in-memory backend, so no RPC access is needed. Fixture accounts live in
+//! the backend, below the overlay cleared at the start of every batch.
+
+use alloy::primitives::{address, Address, TxKind, U256};
+use revm::state::AccountInfo;
+use revm_trace::{
+    inspectors::tx_inspector::TxTraceOutput,
+    traits::TransactionTrace,
+    types::{FailedValueTransfer, FrameFailureKind, SimulationBatch},
+    SimulationTx,
+};
+
+mod common;
+use common::{contract, ether, funded, TestEvm};
+
+const SENDER: Address = address!("1000000000000000000000000000000000000001");
+const PAYER: Address = address!("a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0");
+const BROKE_PAYER: Address = address!("b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0");
+const RECIPIENT: Address = address!("ecececececececececececececececececececec");
+
+/// Sends 1 wei to RECIPIENT, the first calldata byte selecting how:
+/// - 0: `transfer()`, stipend only and reverting on failure
+/// - 1: `send()`, stipend only and ignoring failure
+/// - 2: `call{value: 1}("")` with all remaining gas, reverting on failure
+const PAYER_CODE: &str = concat!(
+    "60003560f81c6000600060006000600173",
+    "ecececececececececececececececececececec",
+    "86600214610033576000610035565b5a5bf1906001146100475761004757600080fd5b00",
+);
+
+/// Fallback writing a storage slot, which needs more than the 2300 gas stipend
+const RECIPIENT_CODE: &str = "600160005500";
+
+fn evm() -> TestEvm {
+    common::evm(common::backend([
+        (SENDER, funded(ether())),
+        (
+            PAYER,
+            AccountInfo {
+                balance: U256::from(10),
+                ..contract(PAYER_CODE)
+            },
+        ),
+        (BROKE_PAYER, contract(PAYER_CODE)),
+        (RECIPIENT, contract(RECIPIENT_CODE)),
+    ]))
+}
+
+/// Calls `payer` with `mode`, returning whether the transaction succeeded and its trace
+fn pay(payer: Address, mode: u8) -> (bool, TxTraceOutput) {
+    let mut results = evm().trace_transactions(SimulationBatch {
+        transactions: vec![SimulationTx {
+            caller: SENDER,
+            transact_to: TxKind::Call(payer),
+            value: U256::ZERO,
+            data: vec![mode].into(),
+            trace_config: None,
+            return_abi: None,
+        }],
+        is_stateful: false,
+        ..Default::default()
+    });
+    let (result, _, output) = results.remove(0).unwrap();
+    (result.is_success(), output)
+}
+
+fn stipend_failure(payer: Address) -> FailedValueTransfer {
+    FailedValueTransfer {
+        trace_address: vec![0],
+        from: payer,
+        to: RECIPIENT,
+        value: U256::from(1),
+        failure_kind: Some(FrameFailureKind::StipendOutOfGas),
+    }
+}
+
+#[test]
+fn test_transfer_out_of_stipend_reverts_caller() {
+    let (success, output) = pay(PAYER, 0);
+    assert!(!success);
+
+    let root = output.call_trace.as_ref().unwrap();
+    assert!(root.status.is_revert());
+    assert_eq!(root.failure_kind, None, "Plain reverts stay unclassified");
+    let payment = &root.subtraces[0];
+    assert!(payment.status.is_out_of_gas(), "{:?}", payment.status);
+    assert_eq!(
+        payment.failure_kind,
+        Some(FrameFailureKind::StipendOutOfGas)
+    );
+    assert_eq!(output.failed_value_transfers, vec![stipend_failure(PAYER)]);
+}
+
+#[test]
+fn test_send_out_of_stipend_is_reported_on_success() {
+    let (success, output) = pay(PAYER, 1);
+    assert!(success, "send() ignores the failed payment");
+    assert_eq!(output.failed_value_transfers, vec![stipend_failure(PAYER)]);
+}
+
+#[test]
+fn test_call_with_gas_delivers_value() {
+    let (success, output) = pay(PAYER, 2);
+    assert!(success);
+    let payment = &output.call_trace.as_ref().unwrap().subtraces[0];
+    assert!(payment.status.is_success());
+    assert_eq!(payment.failure_kind, None);
+    assert!(output.failed_value_transfers.is_empty());
+}
+
+#[test]
+fn test_insufficient_balance_is_classified() {
+    let (success, output) = pay(BROKE_PAYER, 1);
+    assert!(success);
+    assert_eq!(
+        output.failed_value_transfers,
+        vec![FailedValueTransfer {
+            failure_kind: Some(FrameFailureKind::InsufficientBalanceForTransfer),
+            ..stipend_failure(BROKE_PAYER)
+        }]
+    );
+}
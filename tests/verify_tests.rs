@@ -0,0 +1,96 @@
+//! Offline replay tests
+//!
+//! Traces representative mainnet transactions against a fork, then replays each one
+//! from its captured prestate alone and checks the replay matches the original.
+
+use alloy::{
+    primitives::{address, Address, Bytes, TxKind, U256},
+    sol,
+    sol_types::SolCall,
+};
+use revm_trace::{
+    create_evm_with_tracer,
+    verify::{replay_from_prestate, Mismatch, Prestate},
+    SimulationTx, TxInspector,
+};
+
+sol! {
+    function transfer(address to, uint256 amount) public returns (bool);
+}
+
+const ETH_RPC_URL: &str = "https://eth.llamarpc.com";
+const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+/// Account holding both ETH and USDC
+const HOLDER: Address = address!("28C6c06298d514Db089934071355E5743bf21d60");
+
+fn tx(transact_to: Address, value: U256, data: Bytes) -> SimulationTx {
+    SimulationTx {
+        caller: HOLDER,
+        transact_to: TxKind::Call(transact_to),
+        value,
+        data,
+        trace_config: None,
+        return_abi: None,
+    }
+}
+
+/// Replays a WETH deposit and a proxied USDC transfer, archiving the prestate as JSON
+#[tokio::test(flavor = "multi_thread")]
+async fn test_replay_matches_original() -> anyhow::Result<()> {
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+    let transactions = [
+        tx(WETH, U256::from(10u64.pow(18)), Bytes::new()),
+        tx(
+            USDC,
+            U256::ZERO,
+            transferCall {
+                to: Address::repeat_byte(0x42),
+                amount: U256::from(1_000_000u64),
+            }
+            .abi_encode()
+            .into(),
+        ),
+    ];
+
+    for tx in transactions {
+        let (report, prestate) = evm.trace_with_prestate(tx.clone(), false)?;
+        assert!(report.summary.success);
+        assert!(!report.storage_diff.is_empty());
+        assert!(!report.trace.logs.is_empty());
+        assert!(prestate.accounts.contains_key(&HOLDER));
+
+        let archived: Prestate = serde_json::from_str(&serde_json::to_string(&prestate)?)?;
+        assert_eq!(archived, prestate);
+        replay_from_prestate(&archived, evm.ctx.block.clone(), tx, &report)?;
+    }
+    Ok(())
+}
+
+/// A tampered report is reported field by field
+#[tokio::test(flavor = "multi_thread")]
+async fn test_replay_reports_mismatches() -> anyhow::Result<()> {
+    let mut evm = create_evm_with_tracer(ETH_RPC_URL, TxInspector::new()).await?;
+    let deposit = tx(WETH, U256::from(10u64.pow(18)), Bytes::new());
+    let (mut report, prestate) = evm.trace_with_prestate(deposit.clone(), false)?;
+    let gas_used = report.summary.gas_used;
+    report.summary.gas_used += 1;
+    report.trace.logs.clear();
+
+    let mismatch = replay_from_prestate(&prestate, evm.ctx.block.clone(), deposit, &report)
+        .expect_err("tampered report must not verify");
+    assert_eq!(
+        mismatch.differences,
+        vec![
+            Mismatch::GasUsed {
+                expected: gas_used + 1,
+                actual: gas_used,
+            },
+            Mismatch::LogCount {
+                expected: 0,
+                actual: 1,
+            },
+        ]
+    );
+    Ok(())
+}
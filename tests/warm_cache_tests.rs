@@ -0,0 +1,238 @@
+//! Sharing upstream lookups between EVMs with a `WarmCache`
+//!
+//! Runs against an in-process transport serving a USDC stand-in, so no RPC access is
+//! needed. The transport counts requests by method to show which lookups reach the
+//! node.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use alloy::{
+    network::AnyNetwork,
+    primitives::{address, bytes, Address, Bloom, Bytes, B256, U256, U64},
+    providers::ProviderBuilder,
+    rpc::{
+        client::RpcClient,
+        json_rpc::{RequestPacket, Response, ResponsePacket, ResponsePayload, SerializedRequest},
+    },
+    transports::{TransportError, TransportFut},
+};
+use revm_trace::{
+    utils::erc20_utils::query_erc20_balance, EvmFactory, ResetDB, TxInspector, WarmCache,
+};
+use serde_json::{json, value::RawValue};
+
+const USDC: Address = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+const HOLDER: Address = address!("1000000000000000000000000000000000000001");
+
+/// `balanceOf(owner)` returning the storage slot keyed by the owner's address:
+/// `PUSH1 4 CALLDATALOAD SLOAD PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN`
+const TOKEN_CODE: Bytes = bytes!("6004355460005260206000f3");
+
+/// Transport serving the token at any block and counting requests by method
+#[derive(Clone, Default)]
+struct TokenNode {
+    requests: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl TokenNode {
+    fn count(&self, method: &str) -> usize {
+        self.requests
+            .lock()
+            .unwrap()
+            .get(method)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn total(&self) -> usize {
+        self.requests.lock().unwrap().values().sum()
+    }
+
+    async fn handle(self, packet: RequestPacket) -> Result<ResponsePacket, TransportError> {
+        let RequestPacket::Single(request) = packet else {
+            panic!("unexpected batch request");
+        };
+        *self
+            .requests
+            .lock()
+            .unwrap()
+            .entry(request.method().to_string())
+            .or_default() += 1;
+        let result = RawValue::from_string(answer(&request).to_string()).unwrap();
+        Ok(ResponsePacket::Single(Response {
+            id: request.id().clone(),
+            payload: ResponsePayload::Success(result),
+        }))
+    }
+}
+
+impl tower::Service<RequestPacket> for TokenNode {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, packet: RequestPacket) -> Self::Future {
+        Box::pin(self.clone().handle(packet))
+    }
+}
+
+/// Answers with the token code at `USDC`, a balance of 1000 USDC for `HOLDER` and
+/// empty accounts everywhere else
+fn answer(request: &SerializedRequest) -> serde_json::Value {
+    let params: serde_json::Value = request.params().map_or(json!([]), |params| {
+        serde_json::from_str(params.get()).unwrap()
+    });
+    let account = || serde_json::from_value::<Address>(params[0].clone()).unwrap();
+    match request.method() {
+        "eth_chainId" => json!(U64::from(1)),
+        "eth_getBlockByNumber" => {
+            let number: U64 = serde_json::from_value(params[0].clone()).unwrap();
+            block_json(number.to())
+        }
+        "eth_getBalance" | "eth_getTransactionCount" => json!(U256::ZERO),
+        "eth_getCode" => json!(if account() == USDC {
+            TOKEN_CODE
+        } else {
+            Bytes::new()
+        }),
+        "eth_getStorageAt" => {
+            let slot: U256 = serde_json::from_value(params[1].clone()).unwrap();
+            let holder = U256::from_be_slice(HOLDER.as_slice());
+            json!(if account() == USDC && slot == holder {
+                U256::from(1_000_000_000u64)
+            } else {
+                U256::ZERO
+            })
+        }
+        method => panic!("unexpected method {method}"),
+    }
+}
+
+/// Minimal `eth_getBlockByNumber` response
+fn block_json(number: u64) -> serde_json::Value {
+    json!({
+        "hash": B256::repeat_byte(number as u8),
+        "parentHash": B256::ZERO,
+        "sha3Uncles": B256::ZERO,
+        "miner": Address::ZERO,
+        "stateRoot": B256::ZERO,
+        "transactionsRoot": B256::ZERO,
+        "receiptsRoot": B256::ZERO,
+        "logsBloom": Bloom::ZERO,
+        "difficulty": "0x0",
+        "number": U64::from(number),
+        "gasLimit": "0x1c9c380",
+        "gasUsed": "0x0",
+        "timestamp": U64::from(1_700_000_000 + number),
+        "extraData": "0x",
+        "mixHash": B256::ZERO,
+        "nonce": "0x0000000000000000",
+        "uncles": [],
+        "transactions": [],
+    })
+}
+
+async fn factory(node: &TokenNode) -> anyhow::Result<EvmFactory> {
+    let provider = ProviderBuilder::new()
+        .network::<AnyNetwork>()
+        .connect_client(RpcClient::new(node.clone(), true));
+    Ok(EvmFactory::from_provider(provider).await?)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_second_evm_reads_from_warm_cache() -> anyhow::Result<()> {
+    let node = TokenNode::default();
+    let factory = factory(&node).await?;
+    let cache = WarmCache::new(100);
+
+    let mut first = factory
+        .evm_at_block(100, TxInspector::new())
+        .await?
+        .with_warm_cache(cache.clone());
+    let balance = query_erc20_balance(&mut first, USDC, HOLDER)?;
+    assert_eq!(balance, U256::from(1_000_000_000u64));
+    assert_eq!(node.count("eth_getStorageAt"), 1);
+    assert!(!cache.is_empty());
+
+    // The second EVM starts with an empty overlay but finds the token in the cache
+    let before = node.total();
+    let mut second = factory
+        .evm_at_block(100, TxInspector::new())
+        .await?
+        .with_warm_cache(cache.clone());
+    let requests_to_build = node.total() - before;
+    assert_eq!(requests_to_build, 0, "the header is cached by the factory");
+    assert_eq!(query_erc20_balance(&mut second, USDC, HOLDER)?, balance);
+    assert_eq!(node.count("eth_getStorageAt"), 1);
+    assert_eq!(node.total(), before);
+
+    let stats = second.db_stats();
+    assert_eq!(stats.upstream_calls, 0);
+    assert!(stats.warm_cache_hits > 0);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_simulated_writes_stay_out_of_the_cache() -> anyhow::Result<()> {
+    let node = TokenNode::default();
+    let factory = factory(&node).await?;
+    let cache = WarmCache::new(100);
+
+    let mut first = factory
+        .evm_at_block(100, TxInspector::new())
+        .await?
+        .with_warm_cache(cache.clone());
+    let slot = U256::from_be_slice(HOLDER.as_slice());
+    first.insert_storage(USDC, slot, U256::from(1))?;
+    assert_eq!(
+        query_erc20_balance(&mut first, USDC, HOLDER)?,
+        U256::from(1)
+    );
+
+    let mut second = factory
+        .evm_at_block(100, TxInspector::new())
+        .await?
+        .with_warm_cache(cache.clone());
+    assert_eq!(
+        query_erc20_balance(&mut second, USDC, HOLDER)?,
+        U256::from(1_000_000_000u64)
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cache_is_invalidated_at_another_block() -> anyhow::Result<()> {
+    let node = TokenNode::default();
+    let factory = factory(&node).await?;
+    let cache = WarmCache::new(100);
+
+    let mut first = factory
+        .evm_at_block(100, TxInspector::new())
+        .await?
+        .with_warm_cache(cache.clone());
+    query_erc20_balance(&mut first, USDC, HOLDER)?;
+    assert_eq!(node.count("eth_getStorageAt"), 1);
+
+    let mut later = factory
+        .evm_at_block(101, TxInspector::new())
+        .await?
+        .with_warm_cache(cache.clone());
+    assert_eq!(cache.block_number(), 101);
+    query_erc20_balance(&mut later, USDC, HOLDER)?;
+    assert_eq!(node.count("eth_getStorageAt"), 2);
+    assert_eq!(later.db_stats().warm_cache_hits, 0);
+
+    // The EVM at the old block bypasses the cache now pinned elsewhere
+    first.reset_db();
+    query_erc20_balance(&mut first, USDC, HOLDER)?;
+    assert_eq!(node.count("eth_getStorageAt"), 3);
+    Ok(())
+}